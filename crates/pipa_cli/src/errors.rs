@@ -0,0 +1,140 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Turns errors into the message and exit status the user sees, so scripts
+//! can tell a permission problem from a missing event or a bad input file.
+//!
+//! 将错误转换为用户看到的信息和退出码，使脚本能够区分权限问题、
+//! 不支持的事件和错误的输入文件。
+
+use pipa_collector::error::{ErrorCode, PipaCollectorError};
+
+/// Any error without a more specific status. / 没有更具体退出码的任何错误。
+pub const EXIT_FAILURE: i32 = 1;
+// 2 is left to clap for usage errors. / 2 保留给 clap 表示用法错误。
+/// The kernel refused access. / 内核拒绝访问。
+pub const EXIT_PERMISSION: i32 = 3;
+/// The requested event is not available. / 请求的事件不可用。
+pub const EXIT_EVENT_NOT_SUPPORTED: i32 = 4;
+/// Too many events or file descriptors. / 事件或文件描述符过多。
+pub const EXIT_TOO_MANY_EVENTS: i32 = 5;
+/// A file or process does not exist, or could not be read.
+/// 文件或进程不存在，或无法读取。
+pub const EXIT_IO: i32 = 6;
+/// An input file or kernel interface had unexpected content.
+/// 输入文件或内核接口的内容不符合预期。
+pub const EXIT_BAD_DATA: i32 = 7;
+/// The kernel configuration is not supported. / 不支持的内核配置。
+pub const EXIT_UNSUPPORTED: i32 = 8;
+
+/// Exit statuses listed in `pipa --help`. / `pipa --help` 中列出的退出码。
+pub const EXIT_CODES_HELP: &str = "Exit status:
+  0  success
+  1  other error
+  2  invalid command line
+  3  permission denied (see kernel.perf_event_paranoid)
+  4  event not supported by this CPU, VM or kernel
+  5  too many events or open files
+  6  file or process not found, or I/O error
+  7  unexpected content in an input file or kernel interface
+  8  unsupported kernel configuration";
+
+/// The collector error in an error chain, if any. / 错误链中的采集器错误（如果有）。
+fn collector_error(err: &anyhow::Error) -> Option<&PipaCollectorError> {
+    err.chain().find_map(|cause| cause.downcast_ref::<PipaCollectorError>())
+}
+
+/// The process exit status for an error. / 错误对应的进程退出码。
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    let Some(err) = collector_error(err) else {
+        return EXIT_FAILURE;
+    };
+    match err.code() {
+        ErrorCode::Permission => EXIT_PERMISSION,
+        ErrorCode::EventNotSupported => EXIT_EVENT_NOT_SUPPORTED,
+        ErrorCode::TooManyEvents => EXIT_TOO_MANY_EVENTS,
+        ErrorCode::NotFound | ErrorCode::Io => EXIT_IO,
+        ErrorCode::Parse | ErrorCode::Format | ErrorCode::Missing => EXIT_BAD_DATA,
+        ErrorCode::Unsupported => EXIT_UNSUPPORTED,
+    }
+}
+
+/// The message chain, ending at the collector error, whose message already
+/// includes its own source. / 错误信息链，止于采集器错误，因为其信息已包含自身的来源。
+fn message_chain(err: &anyhow::Error) -> String {
+    let mut messages = Vec::new();
+    for cause in err.chain() {
+        messages.push(cause.to_string());
+        if cause.is::<PipaCollectorError>() {
+            break;
+        }
+    }
+    messages.join(": ")
+}
+
+/// Formats an error for stderr: the message chain, the stable error code and
+/// a hint when one is known.
+///
+/// 为 stderr 格式化错误：错误信息链、稳定错误码，以及已知时的处理建议。
+pub fn render(err: &anyhow::Error) -> String {
+    let mut message = match collector_error(err) {
+        Some(cause) => format!("Error [{}]: {}", cause.code(), message_chain(err)),
+        None => format!("Error: {}", message_chain(err)),
+    };
+    if let Some(hint) = collector_error(err).and_then(PipaCollectorError::hint) {
+        message.push_str("\n  hint: ");
+        message.push_str(&hint);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use std::io;
+
+    #[test]
+    fn test_permission_error() {
+        let err = anyhow::Error::new(PipaCollectorError::PermissionDenied {
+            operation: "perf_event_open failed for event Cycles".into(),
+            paranoid: Some(4),
+        });
+        assert_eq!(exit_code(&err), EXIT_PERMISSION);
+        let text = render(&err);
+        assert!(text.starts_with("Error [E_PERMISSION]: Permission denied: perf_event_open"));
+        assert!(text.contains("hint: kernel.perf_event_paranoid is 4"), "{}", text);
+    }
+
+    #[test]
+    fn test_wrapped_and_plain_errors() {
+        let err = Err::<(), _>(
+            PipaCollectorError::from(io::Error::from(io::ErrorKind::NotFound)).at_path("/x"),
+        )
+        .context("Failed to read capture")
+        .unwrap_err();
+        assert_eq!(exit_code(&err), EXIT_IO);
+        let text = render(&err);
+        assert!(text.starts_with("Error [E_NOT_FOUND]: Failed to read capture: /x: "), "{}", text);
+        assert_eq!(text.matches("entity not found").count(), 1, "{}", text);
+
+        let err = anyhow::Error::new(PipaCollectorError::InvalidFormat("x".into()));
+        assert_eq!(exit_code(&err), EXIT_BAD_DATA);
+        assert!(!render(&err).contains("hint"));
+
+        let err = anyhow::anyhow!("No command provided");
+        assert_eq!(exit_code(&err), EXIT_FAILURE);
+        assert_eq!(render(&err), "Error: No command provided");
+    }
+}
@@ -0,0 +1,179 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa export`: streams the rates of any set of registered collectors as CSV
+//! or JSON Lines, for loading into spreadsheets or time-series databases.
+//!
+//! `pipa export`：以 CSV 或 JSON Lines 格式流式输出任意一组已注册采集器的速率，
+//! 便于导入电子表格或时序数据库。
+
+use clap::ValueEnum;
+use pipa_collector::collector::{Collector, Metric, MetricKind, Sample};
+use serde_json::{Map, Value, json};
+use std::time::Duration;
+
+/// Output format of `pipa export`. / `pipa export` 的输出格式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// One `timestamp,collector,instance,metric,unit,value` row per value.
+    /// 每个数值一行 `timestamp,collector,instance,metric,unit,value`。
+    Csv,
+    /// One JSON object per collector instance and interval.
+    /// 每个采集器实例每个区间一个 JSON 对象。
+    Jsonl,
+}
+
+/// The header line of a format, if it has one. / 格式的表头行（如果有）。
+pub fn header(format: ExportFormat) -> Option<&'static str> {
+    match format {
+        ExportFormat::Csv => Some("timestamp,collector,instance,metric,unit,value"),
+        ExportFormat::Jsonl => None,
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, quote or newline.
+/// 当 CSV 字段包含分隔符、引号或换行符时为其加引号。
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats the rates of one collector for one interval. Counter metrics are
+/// exported with a `/s` unit suffix. `timestamp` is the wall-clock end of the
+/// interval.
+///
+/// 格式化单个采集器在单个区间内的速率。计数器指标导出时单位带 `/s` 后缀。
+/// `timestamp` 为该区间结束时的挂钟时间。
+pub fn format_rates(
+    format: ExportFormat,
+    timestamp: Duration,
+    collector: &str,
+    schema: &[Metric],
+    rates: &Sample,
+) -> Vec<String> {
+    let unit = |metric: &Metric| match metric.kind {
+        MetricKind::Counter => format!("{}/s", metric.unit),
+        MetricKind::Gauge => metric.unit.clone(),
+    };
+    let time = timestamp.as_secs_f64();
+    let mut lines = Vec::new();
+    for record in &rates.records {
+        match format {
+            ExportFormat::Csv => {
+                for (metric, value) in schema.iter().zip(&record.values) {
+                    lines.push(format!(
+                        "{:.3},{},{},{},{},{}",
+                        time,
+                        csv_field(collector),
+                        csv_field(&record.instance),
+                        csv_field(&metric.name),
+                        csv_field(&unit(metric)),
+                        value
+                    ));
+                }
+            }
+            ExportFormat::Jsonl => {
+                let values: Map<String, Value> = schema
+                    .iter()
+                    .zip(&record.values)
+                    .map(|(metric, value)| (metric.name.clone(), json!(value)))
+                    .collect();
+                let object = json!({
+                    "timestamp": time,
+                    "collector": collector,
+                    "instance": record.instance,
+                    "values": values,
+                });
+                lines.push(object.to_string());
+            }
+        }
+    }
+    lines
+}
+
+/// Lists collectors and their schemas for `pipa export --list`.
+/// 为 `pipa export --list` 列出采集器及其模式。
+pub fn describe<'a, I: IntoIterator<Item = &'a dyn Collector>>(collectors: I) -> Vec<String> {
+    collectors
+        .into_iter()
+        .map(|c| {
+            let metrics: Vec<String> = c
+                .schema()
+                .iter()
+                .map(|m| match m.kind {
+                    MetricKind::Counter => format!("{} [{}, counter]", m.name, m.unit),
+                    MetricKind::Gauge => format!("{} [{}]", m.name, m.unit),
+                })
+                .collect();
+            format!("{:<12} {}", c.name(), metrics.join(", "))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::collector::{FnCollector, Record};
+
+    fn schema() -> Vec<Metric> {
+        vec![Metric::counter("rx_bytes", "bytes"), Metric::gauge("speed_mbps", "Mbit/s")]
+    }
+
+    fn rates() -> Sample {
+        Sample { records: vec![Record { instance: "eth0".into(), values: vec![1024.0, 1000.0] }] }
+    }
+
+    #[test]
+    fn test_format_csv() {
+        let lines = format_rates(
+            ExportFormat::Csv,
+            Duration::from_millis(1_700_000_000_500),
+            "net",
+            &schema(),
+            &rates(),
+        );
+        assert_eq!(
+            lines,
+            [
+                "1700000000.500,net,eth0,rx_bytes,bytes/s,1024",
+                "1700000000.500,net,eth0,speed_mbps,Mbit/s,1000",
+            ]
+        );
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn test_format_jsonl() {
+        let lines =
+            format_rates(ExportFormat::Jsonl, Duration::from_secs(10), "net", &schema(), &rates());
+        assert_eq!(lines.len(), 1);
+        let value: Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(value["collector"], "net");
+        assert_eq!(value["instance"], "eth0");
+        assert_eq!(value["values"]["rx_bytes"], 1024.0);
+        assert_eq!(value["timestamp"], 10.0);
+    }
+
+    #[test]
+    fn test_describe() {
+        let collector = FnCollector::new("net", schema(), || Ok(Sample::default()));
+        assert_eq!(
+            describe([&collector as &dyn Collector]),
+            ["net          rx_bytes [bytes, counter], speed_mbps [Mbit/s]"]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa flamegraph`: draws folded stacks as an SVG flame graph in the
+//! layout of `flamegraph.pl`, with the root at the bottom and each frame as
+//! wide as its share of the total.
+//!
+//! `pipa flamegraph`：以 `flamegraph.pl` 的布局将折叠栈绘制为 SVG 火焰图，
+//! 根位于底部，每个帧的宽度与其占总量的比例一致。
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const WIDTH: f64 = 1200.0;
+const PAD: f64 = 10.0;
+const FRAME_HEIGHT: f64 = 16.0;
+const TITLE_HEIGHT: f64 = 40.0;
+/// Average glyph width of the 12px font, to fit names into frames.
+/// 12px 字体的平均字形宽度，用于将名称放入帧中。
+const CHAR_WIDTH: f64 = 7.0;
+/// Frames narrower than this are not drawn. / 窄于该宽度的帧不绘制。
+const MIN_WIDTH: f64 = 0.1;
+
+/// The colours of a flame graph. / 火焰图的配色。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Palette {
+    /// Reds and yellows, for on-CPU time. / 红黄色系，用于 on-CPU 时间。
+    Hot,
+    /// Blues, for off-CPU time. / 蓝色系，用于 off-CPU 时间。
+    Cold,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    value: u64,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn depth(&self) -> usize {
+        self.children.values().map(|c| c.depth() + 1).max().unwrap_or(0)
+    }
+}
+
+/// Parses `frame;frame;... weight` lines into a tree. / 将折叠栈行解析为树。
+fn parse_folded(lines: &[String]) -> Node {
+    let mut root = Node::default();
+    for line in lines {
+        let Some((stack, weight)) = line.rsplit_once(' ') else { continue };
+        let Ok(weight) = weight.parse::<u64>() else { continue };
+        root.value += weight;
+        let mut node = &mut root;
+        for frame in stack.split(';') {
+            node = node.children.entry(frame.to_string()).or_default();
+            node.value += weight;
+        }
+    }
+    root
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// A stable colour for a frame name, as `flamegraph.pl` derives it.
+/// 与 `flamegraph.pl` 一样由帧名导出的稳定颜色。
+fn color(palette: Palette, name: &str) -> String {
+    // FNV-1a, split into three fractions. / FNV-1a 哈希，拆分为三个比例值。
+    let hash = name
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    let v = |shift: u32| ((hash >> shift) & 0xff) as f64 / 255.0;
+    let (r, g, b) = match palette {
+        Palette::Hot => (205.0 + 50.0 * v(0), 230.0 * v(8), 55.0 * v(16)),
+        Palette::Cold => {
+            let r = 80.0 + 60.0 * v(0);
+            (r, r, 190.0 + 55.0 * v(8))
+        }
+    };
+    format!("rgb({},{},{})", r as u8, g as u8, b as u8)
+}
+
+struct Canvas<'a> {
+    svg: String,
+    scale: f64,
+    total: u64,
+    height: f64,
+    unit: &'a str,
+    palette: Palette,
+}
+
+impl Canvas<'_> {
+    fn frame(&mut self, name: &str, node: &Node, x: f64, depth: usize) {
+        let width = node.value as f64 * self.scale;
+        if width < MIN_WIDTH {
+            return;
+        }
+        let y = self.height - PAD - (depth + 1) as f64 * FRAME_HEIGHT;
+        let share = node.value as f64 * 100.0 / self.total as f64;
+        let _ = write!(
+            self.svg,
+            "<g><title>{} ({} {}, {:.2}%)</title>\
+             <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\" rx=\"2\"/>",
+            escape(name),
+            node.value,
+            self.unit,
+            share,
+            x,
+            y,
+            width,
+            FRAME_HEIGHT - 1.0,
+            color(self.palette, name)
+        );
+        let fits = ((width - 6.0) / CHAR_WIDTH) as usize;
+        if fits >= 3 {
+            let text: String = if name.chars().count() > fits {
+                name.chars().take(fits - 2).chain("..".chars()).collect()
+            } else {
+                name.to_string()
+            };
+            let _ = write!(
+                self.svg,
+                "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                x + 3.0,
+                y + FRAME_HEIGHT - 4.5,
+                escape(&text)
+            );
+        }
+        self.svg.push_str("</g>\n");
+        let mut child_x = x;
+        for (child_name, child) in &node.children {
+            self.frame(child_name, child, child_x, depth + 1);
+            child_x += child.value as f64 * self.scale;
+        }
+    }
+}
+
+/// Renders folded stacks as an SVG flame graph. `unit` names what the
+/// weights count, such as `samples` or `ns`.
+/// 将折叠栈渲染为 SVG 火焰图。`unit` 为权重的单位，如 `samples` 或 `ns`。
+pub fn render_svg(folded: &[String], title: &str, unit: &str, palette: Palette) -> String {
+    let root = parse_folded(folded);
+    let height = TITLE_HEIGHT + (root.depth() + 1) as f64 * FRAME_HEIGHT + 2.0 * PAD;
+    let mut canvas = Canvas {
+        svg: String::new(),
+        scale: if root.value == 0 { 0.0 } else { (WIDTH - 2.0 * PAD) / root.value as f64 },
+        total: root.value.max(1),
+        height,
+        unit,
+        palette,
+    };
+    let _ = writeln!(
+        canvas.svg,
+        "<?xml version=\"1.0\" standalone=\"no\"?>\n\
+         <svg version=\"1.1\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+         xmlns=\"http://www.w3.org/2000/svg\">\n\
+         <style>text {{ font-family: Verdana, sans-serif; font-size: 12px; fill: #000; }}</style>\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"#f8f8f8\"/>\n\
+         <text x=\"{cx}\" y=\"24\" text-anchor=\"middle\" style=\"font-size: 17px\">{title}</text>",
+        w = WIDTH,
+        h = height,
+        cx = WIDTH / 2.0,
+        title = escape(title)
+    );
+    canvas.frame("all", &root, PAD, 0);
+    canvas.svg.push_str("</svg>\n");
+    canvas.svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_svg() {
+        let folded = vec![
+            "app;main;read 30".to_string(),
+            "app;main;compute<T> 70".to_string(),
+            "malformed".to_string(),
+        ];
+        let svg = render_svg(&folded, "CPU & more", "samples", Palette::Hot);
+        assert!(svg.starts_with("<?xml"), "{}", svg);
+        assert!(svg.contains(">CPU &amp; more</text>"));
+        assert_eq!(svg.matches("<rect x=").count(), 5);
+        assert!(svg.contains("<title>all (100 samples, 100.00%)</title>"));
+        assert!(svg.contains("<title>compute&lt;T&gt; (70 samples, 70.00%)</title>"));
+        // `read` sits above `main`, right of `compute<T>` as children are
+        // sorted by name. / `read` 位于 `main` 之上；子节点按名称排序，故位于
+        // `compute<T>` 右侧。
+        let read = svg.lines().find(|l| l.contains("<title>read ")).unwrap();
+        assert!(read.contains(&format!("x=\"{:.1}\"", PAD + 70.0 * 11.8)), "{}", read);
+        assert!(read.contains(&format!("y=\"{:.1}\"", 40.0 + 4.0 * 16.0 + 20.0 - 10.0 - 64.0)));
+
+        let cold = render_svg(&[], "Off-CPU", "ns", Palette::Cold);
+        assert!(!cold.contains("<rect x="));
+        assert_ne!(color(Palette::Hot, "a"), color(Palette::Cold, "a"));
+        assert_eq!(color(Palette::Cold, "a"), color(Palette::Cold, "a"));
+    }
+}
@@ -16,30 +16,96 @@
 //!
 //! PIPA-rs 的主命令行界面。
 
+mod errors;
+mod export;
+mod flamegraph;
+mod process_table;
+mod report;
+mod sar;
+mod sched;
+mod script;
+mod trace;
+
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode},
+    event::{self, Event},
     execute, queue, style,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+    terminal::{
+        self, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+    },
+};
+use pipa_collector::collector::{self, Collector, CollectorRegistry, Metric, MetricKind, Sample};
+use pipa_collector::cpu_telemetry::{TelemetrySummarizer, TelemetrySummary};
+use pipa_collector::doctor::{self, Finding, Status};
+use pipa_collector::perf_record::{
+    self, BuildIdRecord, CallGraph, OrderedEntries, RecordConfig, RecordEntry, RecordHeader,
+    RecordTarget, RecordWriter, RecordedEvent, Recorder,
+};
+use pipa_collector::process_stats::{self, ProcessSnapshot};
+use pipa_collector::raw_perf_events::{self, PendingCommand, PerfEvent};
+use pipa_collector::sampler::{self, SamplerConfig, SystemSampler};
+use pipa_collector::sysroot::SysRoot;
+use pipa_collector::system_stats::{
+    self, CpuStats, Delta, LoadStats, MemoryStats, PsiTriggerSpec, Snapshot, SnapshotDelta,
 };
-use pipa_collector::raw_perf_events::{self, PerfEvent};
-use pipa_collector::system_stats::{CpuStats, MemoryStats};
-use std::fs::File;
+use pipa_collector::topology::{self, NodeMemInfo, NumaStat};
+use pipa_collector::tracepoint::TraceFs;
+use pipa_core::aggregate::{self, AggregationLevel, GroupResult};
+use pipa_core::callgraph::{self, CallGraphOptions, CallOrder, Frame, SortKey};
+use pipa_core::offcpu;
+use pipa_core::profile::{self, SampleFilter, StackProfile, TimeRange};
+use pipa_core::sched as pipa_sched;
+use pipa_core::syscall_summary::SyscallSummarizer;
+use pipa_parser::buildid::{self, BuildIdCache};
+use pipa_parser::maps;
+use pipa_parser::symbolize::Symbolizer;
+use process_table::{ProcessTable, TableAction};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
 use std::io;
 use std::io::Read;
 use std::os::unix::io::FromRawFd;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::{
-    io::{Stdout, Write, stdout},
-    time::Duration,
+    io::{Stdout, Write, stderr, stdout},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
+/// First terminal row of the process table, below the system summary.
+/// 进程表在终端中的起始行，位于系统概览下方。
+const PROCESS_TABLE_TOP: u16 = 10;
+
+/// The collectors behind the CPU, memory and load headline of `monitor`.
+/// `monitor` 中 CPU、内存和负载概览所依据的采集器。
+const HEADLINE_COLLECTORS: [&str; 3] = ["cpu", "memory", "load"];
+
+/// How often CPU frequency/idle/thermal telemetry is sampled during `stat`.
+/// Each round walks sysfs for every CPU, so keep it slow enough not to disturb
+/// the measured workload.
+/// `stat` 期间采样 CPU 频率/空闲/温度遥测数据的间隔。每轮都会遍历所有 CPU 的
+/// sysfs，因此间隔不宜过短，以免干扰被测负载。
+const TELEMETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a PSI watcher blocks before checking whether `stat` has finished.
+/// PSI 监视线程每次阻塞等待的时长，之后检查 `stat` 是否已结束。
+const PSI_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 /// A Native Performance Analytics Toolchain for Linux, built in Rust.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_help = errors::EXIT_CODES_HELP)]
 struct Cli {
+    /// Where procfs is mounted, e.g. `/host/proc` in a sidecar container.
+    /// procfs 的挂载位置，例如在 sidecar 容器中为 `/host/proc`。
+    #[arg(long, global = true, value_name = "DIR", default_value = "/proc")]
+    procfs: PathBuf,
+    /// Where sysfs is mounted, e.g. `/host/sys` in a sidecar container.
+    /// sysfs 的挂载位置，例如在 sidecar 容器中为 `/host/sys`。
+    #[arg(long, global = true, value_name = "DIR", default_value = "/sys")]
+    sysfs: PathBuf,
     #[command(subcommand)]
     command: Commands,
 }
@@ -53,25 +119,400 @@ enum Commands {
         /// 刷新间隔（秒）。
         #[arg(short, long, default_value_t = 1)]
         interval: u64,
+        /// Also show the rates of these registered collectors (see `pipa export --list`).
+        /// 同时显示这些已注册采集器的速率（参见 `pipa export --list`）。
+        #[arg(short = 'C', long = "collector", value_name = "NAME", value_delimiter = ',')]
+        collectors: Vec<String>,
+        /// Break CPU usage down by `cpu`, `core`, `socket` or `node`; `node` also
+        /// shows each node's memory and NUMA allocation rates.
+        /// 按 `cpu`、`core`、`socket` 或 `node` 细分 CPU 使用率；`node` 还会显示
+        /// 每个节点的内存和 NUMA 分配速率。
+        #[arg(long, value_name = "LEVEL")]
+        per: Option<AggregationLevel>,
     },
     /// Execute a command and collect performance counter statistics.
     /// 执行一个命令并收集性能计数器统计信息。
     Stat {
+        /// Attach to an already running process instead of launching a command.
+        /// 附加到一个已在运行的进程，而不是启动新命令。
+        #[arg(short, long, conflicts_with = "command")]
+        pid: Option<u32>,
+        /// With `--pid`, stop counting after this many seconds instead of on
+        /// Ctrl-C or process exit.
+        /// 配合 `--pid` 使用，在指定秒数后停止计数，而不是等待 Ctrl-C 或进程退出。
+        #[arg(short, long, requires = "pid")]
+        duration: Option<u64>,
+        /// Events to count: `cycles`, `instructions`, `cpu-clock`, a tracepoint
+        /// such as `sched:sched_switch`, or a probe such as
+        /// `uprobe:/usr/bin/app:func` or `kretprobe:vfs_read`.
+        /// 要统计的事件：`cycles`、`instructions`、`cpu-clock`，
+        /// `sched:sched_switch` 这样的跟踪点，或 `uprobe:/usr/bin/app:func`、
+        /// `kretprobe:vfs_read` 这样的探针。
+        #[arg(
+            short,
+            long = "event",
+            value_name = "EVENT",
+            value_delimiter = ',',
+            default_value = "cycles,instructions"
+        )]
+        events: Vec<String>,
+        /// Alert when a PSI stall threshold is exceeded during the run, given
+        /// as `RESOURCE:some|full:STALL_MS:WINDOW_MS`, e.g. `memory:some:150:2000`.
+        /// Without `CAP_SYS_RESOURCE` the window must be a multiple of 2000ms.
+        /// 运行期间超过 PSI 停滞阈值时发出警告，格式为
+        /// `RESOURCE:some|full:STALL_MS:WINDOW_MS`，例如 `memory:some:150:2000`。
+        /// 没有 `CAP_SYS_RESOURCE` 时，窗口必须是 2000 毫秒的整数倍。
+        #[arg(long = "psi-trigger", value_name = "SPEC", value_delimiter = ',')]
+        psi_triggers: Vec<PsiTriggerSpec>,
         /// The command to execute and profile.
         /// 需要执行和分析的命令。
-        #[arg(required = true, last = true)]
+        #[arg(required_unless_present = "pid", last = true)]
+        command: Vec<String>,
+    },
+    /// Sample events into a record file, like `perf record`.
+    /// 像 `perf record` 一样将事件采样到记录文件中。
+    Record {
+        /// Events to sample, as for `stat --event`.
+        /// 要采样的事件，与 `stat --event` 相同。
+        #[arg(
+            short,
+            long = "event",
+            value_name = "EVENT",
+            value_delimiter = ',',
+            default_value = "cycles"
+        )]
+        events: Vec<String>,
+        /// The record file to write.
+        /// 要写入的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        output: PathBuf,
+        /// Sample the threads of a running process.
+        /// 采样一个正在运行的进程的线程。
+        #[arg(short, long, conflicts_with_all = ["command", "all_cpus"])]
+        pid: Option<u32>,
+        /// Sample every task on every CPU, until the command exits if one
+        /// is given.
+        /// 采样所有 CPU 上的所有任务；若给出命令，则在命令退出时停止。
+        #[arg(short, long)]
+        all_cpus: bool,
+        /// With `--pid` or `--all-cpus`, stop after this many seconds.
+        /// 配合 `--pid` 或 `--all-cpus` 使用，在指定秒数后停止。
+        #[arg(short, long)]
+        duration: Option<u64>,
+        /// Samples per second for counting events; tracepoints record every hit.
+        /// 计数事件的每秒采样数；跟踪点记录每一次触发。
+        #[arg(short = 'F', long, default_value_t = 4000)]
+        freq: u64,
+        /// Record the callchain of every sample, like `--call-graph fp`.
+        /// 记录每个采样的调用链，等同于 `--call-graph fp`。
+        #[arg(short = 'g', conflicts_with = "call_graph")]
+        callchain: bool,
+        /// How to record callchains: `fp` walks frame pointers, `lbr` reads
+        /// the Last Branch Record call stack, `dwarf` copies the user stack
+        /// and unwinds it when reporting.
+        /// 调用链的记录方式：`fp` 遍历帧指针，`lbr` 读取最后分支记录（LBR）调用栈，
+        /// `dwarf` 复制用户栈并在生成报告时回溯。
+        #[arg(long, value_name = "MODE")]
+        call_graph: Option<CallGraph>,
+        /// Record where and why threads block instead of `--event`: switch-out
+        /// callchains, context switches and the syscalls threads block in.
+        /// 记录线程阻塞的位置与原因，而不是 `--event`：切出时的调用链、
+        /// 上下文切换以及线程阻塞时所在的系统调用。
+        #[arg(long, conflicts_with = "events")]
+        off_cpu: bool,
+        /// Do not copy the binaries the recording mapped into the build-ID
+        /// cache (`~/.cache/pipa/buildid`).
+        /// 不将记录所映射的二进制文件复制到构建 ID 缓存（`~/.cache/pipa/buildid`）。
+        #[arg(short = 'N', long)]
+        no_buildid_cache: bool,
+        /// The command to execute and record.
+        /// 需要执行和记录的命令。
+        #[arg(required_unless_present_any = ["pid", "all_cpus"], last = true)]
+        command: Vec<String>,
+    },
+    /// Print the samples of a record file, like `perf script`.
+    /// 像 `perf script` 一样打印记录文件中的采样。
+    Script {
+        /// The record file to read.
+        /// 要读取的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        input: PathBuf,
+    },
+    /// Print the call graph of a record file, heaviest first, like
+    /// `perf report --stdio`. Off-CPU recordings report blocked time by reason.
+    /// 像 `perf report --stdio` 一样按权重从大到小打印记录文件的调用图。
+    /// off-CPU 记录按原因报告阻塞时间。
+    Report {
+        /// The record file to read.
+        /// 要读取的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        input: PathBuf,
+        /// What entries are grouped by: comm, dso, sym and srcline.
+        /// 条目的分组依据：comm、dso、sym 与 srcline。
+        #[arg(
+            short,
+            long,
+            value_name = "KEY",
+            value_delimiter = ',',
+            default_value = "comm,dso,sym"
+        )]
+        sort: Vec<SortKey>,
+        /// Make entries only of the innermost functions, by self weight.
+        /// 仅为最内层函数建立条目，按 self 权重排序。
+        #[arg(long)]
+        no_children: bool,
+        /// `caller` shows what each entry calls, `callee` what calls it.
+        /// Defaults to `caller`, or `callee` with `--no-children`.
+        /// `caller` 显示每个条目调用的函数，`callee` 显示调用它的函数。
+        /// 默认为 `caller`，使用 `--no-children` 时为 `callee`。
+        #[arg(short = 'g', long = "call-graph", value_name = "ORDER")]
+        order: Option<CallOrder>,
+        /// Only samples of these processes.
+        /// 仅包含这些进程的采样。
+        #[arg(short, long, value_name = "PID", value_delimiter = ',')]
+        pid: Vec<u32>,
+        /// Only samples of these threads.
+        /// 仅包含这些线程的采样。
+        #[arg(short, long, value_name = "TID", value_delimiter = ',')]
+        tid: Vec<u32>,
+        /// Only samples on these CPUs.
+        /// 仅包含这些 CPU 上的采样。
+        #[arg(short = 'C', long = "cpu", value_name = "CPU", value_delimiter = ',')]
+        cpus: Vec<u32>,
+        /// Only samples from START to END seconds after the first one;
+        /// either may be left out, as in `1.5,` or `,3`.
+        /// 仅包含首个采样后 START 至 END 秒之间的采样；两者均可省略，如 `1.5,` 或 `,3`。
+        #[arg(long, value_name = "START,END")]
+        time: Option<TimeRange>,
+    },
+    /// Draw the stacks of a record file as a flame graph.
+    /// 将记录文件中的调用栈绘制为火焰图。
+    Flamegraph {
+        /// The record file to read.
+        /// 要读取的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        input: PathBuf,
+        /// The SVG file to write.
+        /// 要写入的 SVG 文件。
+        #[arg(short, long, default_value = "flamegraph.svg")]
+        output: PathBuf,
+        /// Print folded stacks to stdout for other flame graph tools instead.
+        /// 改为向标准输出打印折叠栈，供其他火焰图工具使用。
+        #[arg(long)]
+        folded: bool,
+    },
+    /// Trace the syscalls of a command or process, like `perf trace`.
+    /// 像 `perf trace` 一样跟踪命令或进程的系统调用。
+    Trace {
+        /// Print per-syscall and per-thread counts, errors and latencies
+        /// instead of each call, like `strace -c`.
+        /// 像 `strace -c` 一样输出按系统调用和按线程的次数、错误与延迟，
+        /// 而不是逐个打印调用。
+        #[arg(short, long)]
+        summary: bool,
+        /// Trace the threads of a running process.
+        /// 跟踪一个正在运行的进程的线程。
+        #[arg(short, long, conflicts_with = "command")]
+        pid: Option<u32>,
+        /// With `--pid`, stop after this many seconds.
+        /// 配合 `--pid` 使用，在指定秒数后停止。
+        #[arg(short, long, requires = "pid")]
+        duration: Option<u64>,
+        /// The command to execute and trace.
+        /// 需要执行和跟踪的命令。
+        #[arg(required_unless_present = "pid", last = true)]
         command: Vec<String>,
     },
+    /// Record and analyze scheduler activity, like `perf sched`.
+    /// 像 `perf sched` 一样记录并分析调度器活动。
+    Sched {
+        #[command(subcommand)]
+        command: SchedCommand,
+    },
+    /// Record system statistics to a file and report them like `sar`.
+    /// 将系统统计信息记录到文件，并像 `sar` 一样生成报告。
+    Sar {
+        #[command(subcommand)]
+        command: SarCommand,
+    },
+    /// Stream collector rates as CSV or JSON Lines.
+    /// 以 CSV 或 JSON Lines 格式流式输出采集器速率。
+    Export {
+        /// Collectors to export; all of them when omitted.
+        /// 要导出的采集器；省略时导出全部。
+        #[arg(short = 'C', long = "collector", value_name = "NAME", value_delimiter = ',')]
+        collectors: Vec<String>,
+        /// The sampling interval in seconds.
+        /// 采样间隔（秒）。
+        #[arg(short, long, default_value_t = 1)]
+        interval: u64,
+        /// Stop after this many intervals.
+        /// 在指定数量的采样间隔后停止。
+        #[arg(short, long)]
+        count: Option<u64>,
+        /// The output format.
+        /// 输出格式。
+        #[arg(short, long, value_enum, default_value_t = export::ExportFormat::Csv)]
+        format: export::ExportFormat,
+        /// List the available collectors and their metrics, then exit.
+        /// 列出可用的采集器及其指标后退出。
+        #[arg(long)]
+        list: bool,
+        /// Add a `cgroup` collector for these cgroup v2 paths, e.g.
+        /// `/system.slice/app.service`.
+        /// 为这些 cgroup v2 路径（例如 `/system.slice/app.service`）添加 `cgroup` 采集器。
+        #[arg(long = "cgroup", value_name = "PATH", value_delimiter = ',')]
+        cgroups: Vec<String>,
+        /// Add a `process` collector for these PIDs.
+        /// 为这些 PID 添加 `process` 采集器。
+        #[arg(short, long = "pid", value_name = "PID", value_delimiter = ',')]
+        pids: Vec<u32>,
+    },
+    /// Check kernel settings, privileges and PMUs needed for profiling.
+    /// 检查性能分析所需的内核设置、权限和 PMU。
+    Doctor,
+}
+
+#[derive(Subcommand, Debug)]
+enum SchedCommand {
+    /// Record scheduler tracepoints on every CPU while a command runs, or
+    /// until Ctrl-C.
+    /// 在命令运行期间（或直到按下 Ctrl-C）记录所有 CPU 上的调度器跟踪点。
+    Record {
+        /// The record file to write.
+        /// 要写入的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        output: PathBuf,
+        /// Without a command, stop after this many seconds.
+        /// 未给出命令时，在指定秒数后停止。
+        #[arg(short, long, conflicts_with = "command")]
+        duration: Option<u64>,
+        /// The command to execute while recording.
+        /// 记录期间要执行的命令。
+        #[arg(last = true)]
+        command: Vec<String>,
+    },
+    /// Print per-task runtime, wait time, scheduling delay and migrations.
+    /// 打印按任务的运行时间、等待时间、调度延迟和迁移次数。
+    Latency {
+        /// The record file to read.
+        /// 要读取的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        input: PathBuf,
+    },
+    /// Print which task ran on each CPU and when.
+    /// 打印每个 CPU 上各任务的运行时段。
+    Timeline {
+        /// The record file to read.
+        /// 要读取的记录文件。
+        #[arg(short, long, default_value = "pipa.data")]
+        input: PathBuf,
+        /// Only show these CPUs.
+        /// 仅显示这些 CPU。
+        #[arg(short = 'C', long = "cpu", value_name = "CPU", value_delimiter = ',')]
+        cpus: Vec<u32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SarCommand {
+    /// Sample system statistics into a capture file until Ctrl-C.
+    /// 将系统统计信息采样到捕获文件中，直到按下 Ctrl-C。
+    Record {
+        /// The sampling interval in seconds.
+        /// 采样间隔（秒）。
+        #[arg(short, long, default_value_t = 1)]
+        interval: u64,
+        /// The capture file to append to; it is created if missing.
+        /// 要追加写入的捕获文件；若不存在则创建。
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Stop after this many intervals.
+        /// 在指定数量的采样间隔后停止。
+        #[arg(short, long)]
+        count: Option<u64>,
+        /// Also record these registered collectors (see `pipa export --list`).
+        /// 同时记录这些已注册的采集器（参见 `pipa export --list`）。
+        #[arg(short = 'C', long = "collector", value_name = "NAME", value_delimiter = ',')]
+        collectors: Vec<String>,
+    },
+    /// Print `sar`-style tables from a capture file.
+    /// 从捕获文件打印 `sar` 风格的表格。
+    Report {
+        /// The capture file to read.
+        /// 要读取的捕获文件。
+        #[arg(short, long)]
+        file: PathBuf,
+        /// CPU utilization (`sar -u`). This is the default section.
+        /// CPU 利用率（`sar -u`），为默认报告部分。
+        #[arg(long)]
+        cpu: bool,
+        /// Memory utilization (`sar -r`).
+        /// 内存利用率（`sar -r`）。
+        #[arg(long)]
+        mem: bool,
+        /// Block device activity (`sar -d`).
+        /// 块设备活动（`sar -d`）。
+        #[arg(long)]
+        disk: bool,
+        /// Network interface traffic (`sar -n DEV`).
+        /// 网络接口流量（`sar -n DEV`）。
+        #[arg(long)]
+        net: bool,
+        /// Run queue length and load averages (`sar -q`).
+        /// 运行队列长度和平均负载（`sar -q`）。
+        #[arg(long)]
+        load: bool,
+        /// Skip samples before this local time (`HH:MM[:SS]`).
+        /// 跳过早于该本地时间的样本（`HH:MM[:SS]`）。
+        #[arg(long, value_parser = sar::parse_time_of_day)]
+        start: Option<u32>,
+        /// Skip samples after this local time (`HH:MM[:SS]`).
+        /// 跳过晚于该本地时间的样本（`HH:MM[:SS]`）。
+        #[arg(long, value_parser = sar::parse_time_of_day)]
+        end: Option<u32>,
+        /// Also report these collectors recorded with `sar record --collector`.
+        /// 同时报告通过 `sar record --collector` 记录的这些采集器。
+        #[arg(short = 'C', long = "collector", value_name = "NAME", value_delimiter = ',')]
+        collectors: Vec<String>,
+    },
+}
+
+/// The terminal while in TUI mode. Dropping the guard restores the terminal,
+/// so every exit path, including an error or a panic, leaves the shell usable.
+/// 处于 TUI 模式的终端。drop 该守卫时会恢复终端，因此任何退出路径
+/// （包括出错或 panic）都会让 shell 保持可用。
+struct TerminalGuard {
+    stdout: Stdout,
+}
+
+impl Write for TerminalGuard {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // Nothing sensible can be done if restoring fails while unwinding.
+        let _ = restore_terminal(&mut self.stdout);
+    }
 }
 
 /// Helper function to set up the terminal for TUI mode.
 /// 设置终端进入 TUI 模式的辅助函数。
 #[cfg(not(tarpaulin_include))]
-fn setup_terminal() -> Result<Stdout> {
-    let mut stdout = stdout();
+fn setup_terminal() -> Result<TerminalGuard> {
+    // Take the guard first so that a half-finished setup is undone too.
+    let mut guard = TerminalGuard { stdout: stdout() };
     enable_raw_mode()?;
-    execute!(stdout, EnterAlternateScreen, cursor::Hide)?;
-    Ok(stdout)
+    execute!(guard, EnterAlternateScreen, cursor::Hide)?;
+    Ok(guard)
 }
 
 /// Helper function to restore the terminal to its original state.
@@ -86,107 +527,1275 @@ fn restore_terminal(stdout: &mut Stdout) -> Result<()> {
 /// Main application logic for the monitor subcommand.
 /// `monitor` 子命令的主应用逻辑。
 #[cfg(not(tarpaulin_include))]
-fn run_monitor(interval: u64) -> Result<()> {
+fn run_monitor(
+    root: &SysRoot,
+    interval: u64,
+    collectors: &[String],
+    per: Option<AggregationLevel>,
+) -> Result<()> {
+    let registry = CollectorRegistry::builtin(root);
+    let shown = registry.select(collectors)?;
+    // The headline is drawn from these whether or not their rates are shown.
+    let mut sampled = shown.clone();
+    for collector in registry.select(&HEADLINE_COLLECTORS)? {
+        if !sampled.iter().any(|c| c.name() == collector.name()) {
+            sampled.push(collector);
+        }
+    }
+    let mut previous: Vec<Option<Snapshot<Sample>>> = vec![None; shown.len()];
+    let mut collector_lines = Vec::new();
+    let topology = per.map(|_| topology::read_topology(root)).transpose()?;
+    let mut prev_per_cpu: Option<Vec<(u32, CpuStats)>> = None;
+    let mut prev_numa: BTreeMap<u32, Snapshot<NumaStat>> = BTreeMap::new();
     let mut f = setup_terminal()?;
     let mut prev_stats: Option<CpuStats> = None;
+    let mut table = ProcessTable::new(process_stats::clock_ticks_per_second());
     let tick_rate = Duration::from_millis(interval * 1000);
+    let mut next_sample = Instant::now();
+    let mut cpu_usage_percent = 0.0;
+    let mut mem_stats = MemoryStats::default();
+    let mut load_stats = LoadStats::default();
 
-    loop {
-        let current_stats = pipa_collector::system_stats::read_cpu_stats()?;
-        let mem_stats = pipa_collector::system_stats::read_memory_stats()?;
+    let action = loop {
+        // Key presses redraw immediately, but the statistics are only sampled
+        // once per interval so that rates are computed over a full tick.
+        if Instant::now() >= next_sample {
+            let latest: Vec<Option<Snapshot<Sample>>> =
+                sampled.iter().map(|c| c.sample().ok().map(Snapshot::now)).collect();
+            let sample_of = |name: &str| {
+                let index = sampled.iter().position(|c| c.name() == name)?;
+                latest[index].as_ref().map(|s| &s.stats)
+            };
+            // A collector that failed this round keeps its previous values.
+            if let Some(current) = sample_of("cpu").and_then(collector::cpu_stats_from_sample) {
+                cpu_usage_percent =
+                    prev_stats.map_or(0.0, |prev| calculate_cpu_usage(&prev, &current));
+                prev_stats = Some(current);
+            }
+            if let Some(current) = sample_of("memory").and_then(collector::memory_stats_from_sample)
+            {
+                mem_stats = current;
+            }
+            if let Some(current) = sample_of("load").and_then(collector::load_stats_from_sample) {
+                load_stats = current;
+            }
 
-        let cpu_usage_percent = if let Some(prev) = prev_stats {
-            calculate_cpu_usage(&prev, &current_stats)
-        } else {
-            0.0
-        };
-        prev_stats = Some(current_stats);
+            collector_lines.clear();
+            if let (Some(level), Some(topology)) = (per, &topology) {
+                let current = system_stats::read_per_cpu_stats(root)?;
+                // Memory and allocation counters of every node, for `--per node`.
+                let mut nodes = BTreeMap::new();
+                if level == AggregationLevel::Node {
+                    for node in &topology.nodes {
+                        let memory = topology::read_node_meminfo(root, node.id).ok();
+                        let numa = topology::read_numastat(root, node.id).ok().and_then(|stat| {
+                            let later = Snapshot::now(stat);
+                            let earlier = prev_numa.insert(node.id, later)?;
+                            Some(later.delta_since(&earlier))
+                        });
+                        nodes.insert(node.id, (memory, numa));
+                    }
+                }
+                if let Some(prev) = &prev_per_cpu {
+                    let deltas: Vec<(u32, CpuStats)> = current
+                        .iter()
+                        .filter_map(|(cpu, later)| {
+                            let (_, earlier) = prev.iter().find(|(c, _)| c == cpu)?;
+                            Some((*cpu, later.delta(earlier)))
+                        })
+                        .collect();
+                    for group in aggregate::aggregate_per_cpu(&deltas, topology, level) {
+                        let (memory, numa) = match group.group {
+                            aggregate::CpuGroup::Node(id) => {
+                                nodes.get(&id).copied().unwrap_or_default()
+                            }
+                            _ => (None, None),
+                        };
+                        collector_lines.push(format_cpu_group(
+                            &group,
+                            memory.as_ref(),
+                            numa.as_ref(),
+                        ));
+                    }
+                }
+                prev_per_cpu = Some(current);
+            }
+            // `sampled` starts with the shown collectors, in order.
+            for ((collector, earlier), later) in shown.iter().zip(previous.iter_mut()).zip(latest) {
+                if let (Some(earlier), Some(later)) = (earlier.as_ref(), later.as_ref()) {
+                    let delta = SnapshotDelta {
+                        elapsed: later.timestamp.saturating_sub(earlier.timestamp),
+                        delta: collector.delta(&later.stats, &earlier.stats),
+                    };
+                    collector_lines.extend(format_collector_rates(
+                        collector.name(),
+                        collector.schema(),
+                        &delta.rates(collector.schema()),
+                    ));
+                }
+                *earlier = later;
+            }
+
+            table.update(sample_processes(root)?);
+            next_sample = Instant::now() + tick_rate;
+        }
 
         // Pass stdout to the drawing function to give it drawing capabilities.
-        draw_ui(&mut f, interval, cpu_usage_percent, &mem_stats)?;
+        let online_cpus = load_stats.online_cpus as usize;
+        draw_ui(&mut f, interval, cpu_usage_percent, &mem_stats, &load_stats, online_cpus)?;
+        for (row, line) in (PROCESS_TABLE_TOP..).zip(&collector_lines) {
+            queue!(f, cursor::MoveTo(2, row), style::Print(line))?;
+        }
+        // Leave a blank row between the collectors and the process table.
+        let top = match collector_lines.len() {
+            0 => PROCESS_TABLE_TOP,
+            n => PROCESS_TABLE_TOP.saturating_add(n as u16 + 1),
+        };
+        let (_, rows) = terminal::size()?;
+        table.draw(&mut f, top, rows.saturating_sub(top))?;
 
-        if event::poll(tick_rate)? {
+        if event::poll(next_sample.saturating_duration_since(Instant::now()))? {
             if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q') {
-                    break;
+                if let Some(action) = table.handle_key(key.code) {
+                    break action;
                 }
             }
         }
+    };
+
+    drop(f);
+    match action {
+        TableAction::Quit => Ok(()),
+        TableAction::Stat(pid) => {
+            run_stat_attach(root, &["cycles".into(), "instructions".into()], &[], pid, None)
+        }
+        // The same as `pipa record -p <pid>`, until Ctrl-C.
+        TableAction::Record(pid) => {
+            let sampling = SamplingOptions {
+                freq: 4000,
+                call_graph: None,
+                context_switch: false,
+                buildid_cache: true,
+            };
+            let output = PathBuf::from("pipa.data");
+            run_record(
+                root,
+                &["cycles".into()],
+                Some(RecordTarget::Pid(pid)),
+                &output,
+                None,
+                &sampling,
+                &[],
+            )
+        }
     }
+}
 
-    restore_terminal(&mut f)?;
-    Ok(())
+/// Reads a snapshot of every process. Processes that exit while being read
+/// are skipped.
+/// 读取每个进程的快照。读取过程中退出的进程会被跳过。
+#[cfg(not(tarpaulin_include))]
+fn sample_processes(root: &SysRoot) -> Result<Vec<Snapshot<ProcessSnapshot>>> {
+    Ok(process_stats::list_pids(root)?
+        .into_iter()
+        .filter_map(|pid| process_stats::read_process_snapshot(root, pid).ok())
+        .map(Snapshot::now)
+        .collect())
 }
 
 /// Main application logic for the stat subcommand.
 /// `stat` 子命令的主应用逻辑。
 #[cfg(not(tarpaulin_include))]
-fn run_stat(command: &[String]) -> Result<()> {
+fn run_stat(
+    root: &SysRoot,
+    events: &[String],
+    psi_triggers: &[PsiTriggerSpec],
+    command: &[String],
+) -> Result<()> {
     if command.is_empty() {
         anyhow::bail!("No command provided to `stat`.");
     }
 
-    // 1. Create INDEPENDENT counters, just like `perf stat` does.
-    let cycles_counter = raw_perf_events::create_counter_for_command(PerfEvent::Cycles)?;
-    let instructions_counter =
-        raw_perf_events::create_counter_for_command(PerfEvent::Instructions)?;
-
-    let program = &command[0];
-    let args = &command[1..];
+    // 1. Fork the command and create INDEPENDENT counters on it before it
+    //    calls `execve`, just like `perf stat` does.
+    let events = resolve_events(root, events)?;
+    let pending = PendingCommand::spawn(command)?;
+    let counters = events
+        .iter()
+        .map(|(_, event)| raw_perf_events::create_counter_for_command(root, event, &pending))
+        .collect::<Result<Vec<_>, _>>()?;
 
-    // 2. Set up and run the child process.
+    // 2. Run the child process, recording CPU telemetry and watching PSI
+    //    triggers alongside.
     // NO MORE pre_exec hook! The kernel handles enabling the counters for us.
-    let status = Command::new(program)
-        .args(args)
-        .status()
-        .map_err(|e| anyhow::anyhow!("Failed to execute command `{}`: {}", program, e))?;
+    let psi = start_psi_watch(root, psi_triggers)?;
+    let telemetry = start_telemetry(root)?;
+    let status = pending.start()?.wait()?;
 
     if !status.success() {
         eprintln!("Warning: Command exited with non-zero status: {}", status);
     }
 
     // 3. Read the value from each counter's file descriptor separately.
-    let read_counter = |counter: &raw_perf_events::Counter| -> Result<u64> {
-        // This is the robust, idiomatic way to handle reading from a raw file
-        // descriptor that is owned by another structure.
-        //
-        // 1. Duplicate the file descriptor. `dup_fd` is a new, independent FD pointing
-        //    to the same underlying kernel file description.
-        let dup_fd = unsafe { libc::dup(counter.fd()) };
-        if dup_fd < 0 {
-            return Err(io::Error::last_os_error().into());
-        }
-
-        // 2. The `File` now takes ownership of the *duplicated* FD. The original
-        //    `counter.fd()` is unaffected.
-        let mut file = unsafe { File::from_raw_fd(dup_fd) };
-        let mut buf = [0u8; 8];
-
-        // 3. Read from the file. If this fails and returns early, the `file` (and
-        //    `dup_fd`) will be correctly and safely closed by its Drop impl. The
-        //    original FD in `counter` remains open.
-        file.read_exact(&mut buf)?;
-
-        // 4. No more `mem::forget`! The `file` is dropped here, closing `dup_fd`, which
-        //    is exactly what we want.
-        Ok(u64::from_le_bytes(buf))
+    let counts = counters.iter().map(read_counter).collect::<Result<Vec<_>>>()?;
+    let telemetry = stop_telemetry(telemetry)?;
+    let stalls = stop_psi_watch(psi)?;
+
+    // 4. Calculate and print the results.
+    print_counters(&format!("`{:?}`", command), &events, &counts);
+    print_telemetry(&telemetry);
+    print_psi_stalls(&stalls);
+
+    Ok(())
+}
+
+/// Set by the SIGINT handler to end an attached `stat`.
+/// 由 SIGINT 处理函数设置，用于结束附加模式下的 `stat`。
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(not(tarpaulin_include))]
+extern "C" fn request_stop(_signal: libc::c_int) {
+    STOP_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Attaches counters to a running process, like `perf stat -p`, and counts
+/// until Ctrl-C, the process exits, or `duration` seconds have passed.
+/// 像 `perf stat -p` 一样将计数器附加到正在运行的进程上，
+/// 计数直到按下 Ctrl-C、进程退出或经过 `duration` 秒。
+#[cfg(not(tarpaulin_include))]
+fn run_stat_attach(
+    root: &SysRoot,
+    events: &[String],
+    psi_triggers: &[PsiTriggerSpec],
+    pid: u32,
+    duration: Option<u64>,
+) -> Result<()> {
+    let events = resolve_events(root, events)?;
+    let counters = events
+        .iter()
+        .map(|(_, event)| raw_perf_events::create_counters_for_pid(root, event, pid))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    unsafe {
+        libc::signal(libc::SIGINT, request_stop as *const () as libc::sighandler_t);
+    }
+    let deadline = duration.map(|secs| Instant::now() + Duration::from_secs(secs));
+    eprintln!("Counting PID {}... press Ctrl-C to stop.", pid);
+    let psi = start_psi_watch(root, psi_triggers)?;
+    let telemetry = start_telemetry(root)?;
+
+    let proc_dir = root.proc(pid.to_string());
+    while !STOP_REQUESTED.load(Ordering::SeqCst)
+        && deadline.is_none_or(|d| Instant::now() < d)
+        && proc_dir.exists()
+    {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+
+    let counts = counters
+        .iter()
+        .map(|per_thread| per_thread.iter().map(read_counter).sum())
+        .collect::<Result<Vec<u64>>>()?;
+    let telemetry = stop_telemetry(telemetry)?;
+    let stalls = stop_psi_watch(psi)?;
+    print_counters(&format!("PID {}", pid), &events, &counts);
+    print_telemetry(&telemetry);
+    print_psi_stalls(&stalls);
+    Ok(())
+}
+
+/// Records system statistics to `output` every `interval` seconds, like
+/// `sar -o`, until Ctrl-C, SIGTERM, or `count` intervals have passed.
+/// 像 `sar -o` 一样每隔 `interval` 秒将系统统计信息记录到 `output`，
+/// 直到按下 Ctrl-C、收到 SIGTERM 或经过 `count` 个采样间隔。
+#[cfg(not(tarpaulin_include))]
+fn run_sar_record(
+    root: &SysRoot,
+    interval: u64,
+    output: PathBuf,
+    count: Option<u64>,
+    extra_collectors: Vec<String>,
+) -> Result<()> {
+    let interval = Duration::from_secs(interval.max(1));
+    let mut collectors: Vec<String> =
+        sar::RECORDED_COLLECTORS.iter().map(|name| name.to_string()).collect();
+    for name in extra_collectors {
+        if !collectors.contains(&name) {
+            collectors.push(name);
+        }
+    }
+    let config = SamplerConfig {
+        interval,
+        collectors,
+        // Everything goes to the capture file; keep only what `count` needs.
+        capacity: 2,
+        capture: Some(output.clone()),
+        root: root.clone(),
     };
 
-    let cycles = read_counter(&cycles_counter)?;
-    let instructions = read_counter(&instructions_counter)?;
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    unsafe {
+        libc::signal(libc::SIGINT, request_stop as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, request_stop as *const () as libc::sighandler_t);
+    }
+    let sampler = SystemSampler::start_with_registry(config, &CollectorRegistry::builtin(root))?;
+    eprintln!("Recording to {}... press Ctrl-C to stop.", output.display());
 
-    // 4. Calculate and print the results.
-    let cpi = if instructions > 0 { cycles as f64 / instructions as f64 } else { 0.0 };
+    let mut first = None;
+    while !STOP_REQUESTED.load(Ordering::SeqCst) {
+        if let (Some(count), Some(latest)) = (count, sampler.latest()) {
+            let start = *first.get_or_insert(latest.timestamp);
+            // Snapshot timestamps jitter by the time a round takes to read,
+            // so allow half an interval of slack.
+            if latest.timestamp + interval / 2 >= start + interval * count as u32 {
+                break;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+        libc::signal(libc::SIGTERM, libc::SIG_DFL);
+    }
+    sampler.stop()?;
+    Ok(())
+}
 
-    println!("\n--- Performance counters for `{:?}` ---\n", command);
-    println!("{:<20}: {}", "Cycles", cycles);
-    println!("{:<20}: {}", "Instructions", instructions);
-    println!("{:<20}: {:.2}", "CPI", cpi);
-    println!("\n------------------------------------------\n");
+/// Prints the selected `sar` sections of a capture file.
+/// 打印捕获文件中选定的 `sar` 报告部分。
+#[cfg(not(tarpaulin_include))]
+fn run_sar_report(
+    file: PathBuf,
+    mut sections: Vec<sar::Section>,
+    start: Option<u32>,
+    end: Option<u32>,
+) -> Result<()> {
+    let capture = sampler::read_capture(&file)?;
+    if sections.is_empty() {
+        sections.push(sar::Section::Cpu);
+    }
+    for line in sar::render_report(&capture, &sections, start, end, sar::local_time) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Cgroups and processes to export alongside the system-wide collectors.
+/// 与系统级采集器一同导出的 cgroup 和进程。
+struct ExportTargets {
+    cgroups: Vec<String>,
+    pids: Vec<u32>,
+}
+
+/// Samples the selected collectors every `interval` seconds and prints their
+/// rates until Ctrl-C or `count` intervals have passed.
+/// 每隔 `interval` 秒对选定的采集器采样并打印其速率，
+/// 直到按下 Ctrl-C 或经过 `count` 个采样间隔。
+#[cfg(not(tarpaulin_include))]
+fn run_export(
+    root: &SysRoot,
+    names: &[String],
+    targets: &ExportTargets,
+    interval: u64,
+    count: Option<u64>,
+    format: export::ExportFormat,
+    list: bool,
+) -> Result<()> {
+    let mut registry = CollectorRegistry::builtin(root);
+    if !targets.cgroups.is_empty() {
+        registry.register(collector::cgroup_collector(root, targets.cgroups.clone()))?;
+    }
+    if !targets.pids.is_empty() {
+        registry.register(collector::process_collector(root, targets.pids.clone()))?;
+    }
+    if list {
+        for line in export::describe(registry.iter().map(|c| c.as_ref())) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    let collectors = if names.is_empty() {
+        registry.iter().cloned().collect()
+    } else {
+        registry.select(names)?
+    };
+
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    unsafe {
+        libc::signal(libc::SIGINT, request_stop as *const () as libc::sighandler_t);
+    }
+    let result = export_loop(&collectors, interval, count, format);
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+    result
+}
+
+/// The sampling loop of `run_export`. Output goes through a locked stdout so
+/// that a closed pipe (e.g. `| head`) ends the export with an error instead of
+/// a panic.
+/// `run_export` 的采样循环。输出经由加锁的 stdout，使管道关闭（例如 `| head`）时
+/// 以错误而非 panic 结束导出。
+#[cfg(not(tarpaulin_include))]
+fn export_loop(
+    collectors: &[Arc<dyn Collector>],
+    interval: u64,
+    count: Option<u64>,
+    format: export::ExportFormat,
+) -> Result<()> {
+    let read_all = || -> Vec<Option<Snapshot<Sample>>> {
+        collectors.iter().map(|c| c.sample().ok().map(Snapshot::now)).collect()
+    };
+    // Collectors may fail individually (e.g. no cpufreq in a VM), but if none
+    // can be read at all, report why instead of printing an empty export.
+    let mut first = Vec::with_capacity(collectors.len());
+    let mut first_error = None;
+    for collector in collectors {
+        match collector.sample() {
+            Ok(sample) => first.push(Some(Snapshot::now(sample))),
+            Err(e) => {
+                first_error.get_or_insert(e);
+                first.push(None);
+            }
+        }
+    }
+    if let Some(err) = first_error.filter(|_| first.iter().all(Option::is_none)) {
+        return Err(err.into());
+    }
+
+    let mut out = stdout().lock();
+    if let Some(header) = export::header(format) {
+        writeln!(out, "{}", header)?;
+    }
+    let tick = Duration::from_secs(interval.max(1));
+    let mut previous = first;
+    let mut remaining = count;
+    while remaining != Some(0) && !STOP_REQUESTED.load(Ordering::SeqCst) {
+        let deadline = Instant::now() + tick;
+        while Instant::now() < deadline && !STOP_REQUESTED.load(Ordering::SeqCst) {
+            std::thread::sleep(
+                deadline.saturating_duration_since(Instant::now()).min(Duration::from_millis(100)),
+            );
+        }
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let current = read_all();
+        for ((collector, earlier), later) in collectors.iter().zip(&previous).zip(&current) {
+            let (Some(earlier), Some(later)) = (earlier, later) else { continue };
+            let delta = SnapshotDelta {
+                elapsed: later.timestamp.saturating_sub(earlier.timestamp),
+                delta: collector.delta(&later.stats, &earlier.stats),
+            };
+            let rates = delta.rates(collector.schema());
+            for line in
+                export::format_rates(format, now, collector.name(), collector.schema(), &rates)
+            {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        out.flush()?;
+        previous = current;
+        remaining = remaining.map(|n| n - 1);
+    }
+    Ok(())
+}
+
+/// Formats `pipa doctor` findings, one line each plus an indented hint.
+/// 格式化 `pipa doctor` 的检查结果，每项一行，另起缩进行给出建议。
+fn format_findings(findings: &[Finding]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for finding in findings {
+        lines.push(format!(
+            "[{:<4}] {:<20} {}",
+            finding.status.label(),
+            finding.check,
+            finding.detail
+        ));
+        if let Some(hint) = &finding.hint {
+            lines.push(format!("{:7}{:<20} hint: {}", "", "", hint));
+        }
+    }
+    lines
+}
+
+/// Runs the preflight checks and fails if any of them failed.
+/// 运行预检，若有任何检查失败则返回错误。
+#[cfg(not(tarpaulin_include))]
+fn run_doctor(root: &SysRoot) -> Result<()> {
+    let findings = doctor::run_checks(root);
+    for line in format_findings(&findings) {
+        println!("{}", line);
+    }
+    let failed = findings.iter().filter(|f| f.status == Status::Fail).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} checks failed", failed, findings.len());
+    }
+    Ok(())
+}
+
+/// How `pipa record` samples. / `pipa record` 的采样方式。
+#[derive(Debug, Clone, Copy)]
+struct SamplingOptions {
+    freq: u64,
+    call_graph: Option<CallGraph>,
+    context_switch: bool,
+    /// Copy the mapped binaries into the build-ID cache.
+    /// 将已映射的二进制文件复制到构建 ID 缓存。
+    buildid_cache: bool,
+}
 
+/// Samples `events` into `output` until the command exits, Ctrl-C, or
+/// `duration` seconds have passed, like `perf record`.
+/// 像 `perf record` 一样将 `events` 采样到 `output`，
+/// 直到命令退出、按下 Ctrl-C 或经过 `duration` 秒。
+#[cfg(not(tarpaulin_include))]
+fn run_record(
+    root: &SysRoot,
+    events: &[String],
+    target: Option<RecordTarget>,
+    output: &PathBuf,
+    duration: Option<u64>,
+    sampling: &SamplingOptions,
+    command: &[String],
+) -> Result<()> {
+    let events = resolve_events(root, events)?;
+    let mut recorded = Vec::new();
+    for (name, event) in &events {
+        let format = match event {
+            PerfEvent::Tracepoint(_) => Some(TraceFs::locate(root)?.event_format(&name.parse()?)?),
+            _ => None,
+        };
+        recorded.push(RecordedEvent { name: name.clone(), format });
+    }
+    // A command given with `--all-cpus` runs while the whole system is recorded.
+    let pending = if command.is_empty() { None } else { Some(PendingCommand::spawn(command)?) };
+    let target = match (target, &pending) {
+        (Some(target), _) => target,
+        (None, Some(pending)) => RecordTarget::Command(pending.pid()),
+        (None, None) => anyhow::bail!("Nothing to record: give a command, --pid or --all-cpus"),
+    };
+    let config = RecordConfig {
+        events: events.iter().map(|(_, event)| event.clone()).collect(),
+        target,
+        frequency: sampling.freq,
+        // Callchains make records several times larger, and stack copies
+        // larger still.
+        pages: match sampling.call_graph {
+            None => 64,
+            Some(CallGraph::Fp | CallGraph::Lbr) => 256,
+            Some(CallGraph::Dwarf) => 512,
+        },
+        call_graph: sampling.call_graph,
+        context_switch: sampling.context_switch,
+    };
+    let mut recorder = Recorder::open(root, &config)?;
+    let mut header = RecordHeader::new(root, command.to_vec(), recorded);
+    header.call_graph = config.call_graph;
+    header.sample_regs_user = config.regs_user();
+    let mut writer = RecordWriter::create(output, &header)
+        .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", output.display(), e))?;
+
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    unsafe {
+        libc::signal(libc::SIGINT, request_stop as *const () as libc::sighandler_t);
+    }
+    let child = match pending {
+        Some(pending) => Some(pending.start()?),
+        None => {
+            eprintln!("Recording... press Ctrl-C to stop.");
+            None
+        }
+    };
+    let deadline = duration.map(|secs| Instant::now() + Duration::from_secs(secs));
+    let proc_dir = match target {
+        RecordTarget::Pid(pid) => Some(root.proc(pid.to_string())),
+        _ => None,
+    };
+
+    // The kernel only announces mappings and thread names from now on, so
+    // those of processes that are already running are read from /proc, as
+    // perf does.
+    let running = match target {
+        RecordTarget::Pid(pid) => vec![pid],
+        RecordTarget::AllCpus => process_stats::list_pids(root)?,
+        RecordTarget::Command(_) => Vec::new(),
+    };
+    let mut mmaps = Vec::new();
+    for pid in running {
+        // Processes may exit meanwhile.
+        for comm in perf_record::synthesize_comms(root, pid).unwrap_or_default() {
+            writer.write(&RecordEntry::Comm(comm))?;
+        }
+        for mmap in maps::synthesize_mmaps(root, pid).unwrap_or_default() {
+            writer.write(&RecordEntry::Mmap(mmap.clone()))?;
+            mmaps.push(mmap);
+        }
+    }
+
+    let (mut samples, mut reported_lost, mut exit_status) = (0u64, 0u64, None);
+    let mut ordered = OrderedEntries::new();
+    loop {
+        let stop =
+            STOP_REQUESTED.load(Ordering::SeqCst) || deadline.is_some_and(|d| Instant::now() >= d);
+        let finished = match &child {
+            Some(child) => {
+                exit_status = child.try_wait()?;
+                stop || exit_status.is_some()
+            }
+            None => stop || proc_dir.as_ref().is_some_and(|dir| !dir.exists()),
+        };
+        if !finished {
+            recorder.wait(Duration::from_millis(100));
+        }
+        let start = system_stats::monotonic_now().as_nanos() as u64;
+        let mut entries = ordered.push(recorder.read()?, start);
+        if finished {
+            entries.extend(ordered.finish());
+        }
+        for entry in entries {
+            match &entry {
+                RecordEntry::Sample(_) => samples += 1,
+                RecordEntry::Mmap(mmap) => mmaps.push(mmap.clone()),
+                _ => {}
+            }
+            writer.write(&entry)?;
+        }
+        if recorder.lost() > reported_lost {
+            writer.write(&RecordEntry::Lost { count: recorder.lost() - reported_lost })?;
+            reported_lost = recorder.lost();
+        }
+        if finished {
+            break;
+        }
+    }
+    if let (Some(child), None) = (child, exit_status) {
+        child.terminate()?;
+    }
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+    // Name the exact version of every mapped file, so that reports can
+    // tell when one has changed since.
+    let build_ids = buildid::build_id_records(&mmaps);
+    for record in &build_ids {
+        writer.write(&RecordEntry::BuildId(record.clone()))?;
+    }
+    if sampling.buildid_cache {
+        cache_binaries(&build_ids);
+    }
+    writer.flush()?;
+    if let Some(status) = exit_status {
+        if !status.success() {
+            eprintln!("Warning: Command exited with non-zero status: {}", status);
+        }
+    }
+    eprintln!(
+        "[ pipa record: captured {} samples ({} lost) to {} ]",
+        samples,
+        reported_lost,
+        output.display()
+    );
     Ok(())
 }
 
+/// Copies the binaries of `build_ids` into the build-ID cache, warning
+/// about those that could not be copied.
+/// 将 `build_ids` 中的二进制文件复制到构建 ID 缓存，并对无法复制的文件发出警告。
+#[cfg(not(tarpaulin_include))]
+fn cache_binaries(build_ids: &[BuildIdRecord]) {
+    let Some(root) = BuildIdCache::default_root() else {
+        eprintln!("Warning: no home directory for the build-ID cache");
+        return;
+    };
+    let cache = BuildIdCache::new(root);
+    for record in build_ids {
+        if let Err(e) = cache.store(Path::new(&record.filename), &record.build_id) {
+            eprintln!("Warning: not caching {}: {}", record.filename, e);
+        }
+    }
+}
+
+/// Traces syscalls through `raw_syscalls` until the command exits, Ctrl-C,
+/// or `duration` seconds have passed. Output goes to stderr, as with
+/// `strace`, so it does not mix with the command's own output.
+/// 通过 `raw_syscalls` 跟踪系统调用，直到命令退出、按下 Ctrl-C 或经过
+/// `duration` 秒。与 `strace` 一样输出到 stderr，以免与命令自身的输出混在一起。
+#[cfg(not(tarpaulin_include))]
+fn run_trace(
+    root: &SysRoot,
+    summary: bool,
+    pid: Option<u32>,
+    duration: Option<u64>,
+    command: &[String],
+) -> Result<()> {
+    let tracefs = TraceFs::locate(root)?;
+    let mut events = Vec::new();
+    let mut recorded = Vec::new();
+    for name in trace::EVENTS {
+        let name = name.parse()?;
+        events.push(PerfEvent::Tracepoint(tracefs.event_id(&name)?));
+        recorded.push(RecordedEvent {
+            name: name.to_string(),
+            format: Some(tracefs.event_format(&name)?),
+        });
+    }
+    let (target, pending) = match pid {
+        Some(pid) => (RecordTarget::Pid(pid), None),
+        None => {
+            let pending = PendingCommand::spawn(command)?;
+            (RecordTarget::Command(pending.pid()), Some(pending))
+        }
+    };
+    // Every syscall is two records, so give the ring buffers more room.
+    let config = RecordConfig {
+        events,
+        target,
+        frequency: 1,
+        pages: 256,
+        call_graph: None,
+        context_switch: false,
+    };
+    let mut recorder = Recorder::open(root, &config)?;
+
+    STOP_REQUESTED.store(false, Ordering::SeqCst);
+    unsafe {
+        libc::signal(libc::SIGINT, request_stop as *const () as libc::sighandler_t);
+    }
+    let child = match pending {
+        Some(pending) => Some(pending.start()?),
+        None => {
+            eprintln!("Tracing... press Ctrl-C to stop.");
+            None
+        }
+    };
+    let deadline = duration.map(|secs| Instant::now() + Duration::from_secs(secs));
+    let proc_dir = pid.map(|pid| root.proc(pid.to_string()));
+
+    let arch = std::env::consts::ARCH;
+    let mut summarizer = SyscallSummarizer::new(arch);
+    let mut comms: HashMap<u32, String> = HashMap::new();
+    let (mut start, mut exit_status) = (None, None);
+    let mut err = stderr().lock();
+    // A syscall's enter and exit can be drained in different rounds, so
+    // events are put in time order across rounds before they are paired.
+    let mut ordered = OrderedEntries::new();
+    loop {
+        let stop =
+            STOP_REQUESTED.load(Ordering::SeqCst) || deadline.is_some_and(|d| Instant::now() >= d);
+        let finished = match &child {
+            Some(child) => {
+                exit_status = child.try_wait()?;
+                stop || exit_status.is_some()
+            }
+            None => stop || proc_dir.as_ref().is_some_and(|dir| !dir.exists()),
+        };
+        if !finished {
+            recorder.wait(Duration::from_millis(100));
+        }
+        let round = system_stats::monotonic_now().as_nanos() as u64;
+        let mut entries = ordered.push(recorder.read()?, round);
+        if finished {
+            entries.extend(ordered.finish());
+        }
+        for entry in entries {
+            let sample = match entry {
+                RecordEntry::Sample(sample) => sample,
+                RecordEntry::Comm(comm) => {
+                    summarizer.set_comm(comm.tid, &comm.comm);
+                    comms.insert(comm.tid, comm.comm);
+                    continue;
+                }
+                RecordEntry::Switch(_)
+                | RecordEntry::Mmap(_)
+                | RecordEntry::BuildId(_)
+                | RecordEntry::Lost { .. } => {
+                    continue;
+                }
+            };
+            let comm = comms.entry(sample.tid).or_insert_with(|| {
+                // Threads that existed before tracing have no COMM record.
+                let path = root.proc(format!("{}/comm", sample.tid));
+                let comm = fs::read_to_string(path).map(|c| c.trim_end().to_string());
+                let comm = comm.unwrap_or_else(|_| ":".to_string());
+                summarizer.set_comm(sample.tid, &comm);
+                comm
+            });
+            let start = *start.get_or_insert(sample.time);
+            let call = trace::feed(&mut summarizer, &recorded, &sample)?;
+            if let (false, Some(call)) = (summary, call) {
+                writeln!(err, "{}", trace::format_call(arch, start, sample.time, comm, &call))?;
+            }
+        }
+        if finished {
+            break;
+        }
+    }
+    if let (Some(child), None) = (child, exit_status) {
+        child.terminate()?;
+    }
+    unsafe {
+        libc::signal(libc::SIGINT, libc::SIG_DFL);
+    }
+    if summary {
+        writeln!(err)?;
+        for line in trace::format_summary(arch, &summarizer.finish()) {
+            writeln!(err, "{}", line)?;
+        }
+    }
+    if recorder.lost() > 0 {
+        eprintln!("Warning: {} events were lost; the counts are incomplete", recorder.lost());
+    }
+    if let Some(status) = exit_status {
+        if !status.success() {
+            eprintln!("Warning: Command exited with non-zero status: {}", status);
+        }
+    }
+    Ok(())
+}
+
+/// Prints the scheduler analysis of a record file as a task table or, with
+/// `timeline`, per CPU.
+/// 将记录文件的调度器分析结果打印为任务表，或在 `timeline` 时按 CPU 打印。
+#[cfg(not(tarpaulin_include))]
+fn run_sched_report(input: &PathBuf, timeline: Option<&[u32]>) -> Result<()> {
+    let file = perf_record::read_record(input)?;
+    if !file.header.events.iter().any(|e| e.name == pipa_sched::EVENTS[0]) {
+        anyhow::bail!(
+            "{} has no {} samples; record it with `pipa sched record`",
+            input.display(),
+            pipa_sched::EVENTS[0]
+        );
+    }
+    let analysis = pipa_sched::analyze(&file)?;
+    let lines = match timeline {
+        Some(cpus) => sched::format_timeline(&analysis, cpus),
+        None => sched::format_latency(&analysis),
+    };
+    let mut out = stdout().lock();
+    for line in lines {
+        match writeln!(out, "{}", line) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+    }
+    if file.lost() > 0 {
+        eprintln!("Warning: {} records were lost while recording", file.lost());
+    }
+    Ok(())
+}
+
+/// The stacks of a record file: blocked time by reason for an off-CPU
+/// recording, sampled events otherwise.
+/// 记录文件的调用栈：off-CPU 记录为按原因划分的阻塞时间，否则为采样事件。
+struct LoadedProfile {
+    stacks: StackProfile,
+    weight: report::Weight,
+    breakdown: Vec<(String, u64)>,
+    symbolizer: Symbolizer,
+}
+
+/// Warns about mapped files whose symbols could not be read, and about
+/// `unwound` files that DWARF unwinding found changed since the recording.
+/// 对无法读取符号的已映射文件，以及 DWARF 回溯发现记录后已变化的 `unwound` 文件发出警告。
+fn warn_unreadable(symbolizer: &Symbolizer, unwound: &[String]) {
+    for path in symbolizer.unreadable() {
+        eprintln!("Warning: no symbols for {}", path);
+    }
+    let mismatched = symbolizer.mismatched();
+    for path in &mismatched {
+        eprintln!(
+            "Warning: {} has changed since it was recorded (build-ID mismatch); no symbols for it",
+            path
+        );
+    }
+    for path in unwound.iter().filter(|path| !mismatched.contains(&path.as_str())) {
+        eprintln!(
+            "Warning: {} has changed since it was recorded (build-ID mismatch); \
+             cannot unwind through it",
+            path
+        );
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn load_profile(input: &PathBuf, filter: &SampleFilter) -> Result<LoadedProfile> {
+    let file = perf_record::read_record(input)?;
+    if file.lost() > 0 {
+        eprintln!("Warning: {} records were lost while recording", file.lost());
+    }
+    if offcpu::is_off_cpu(&file) {
+        let profile = offcpu::analyze(&file, filter)?;
+        let breakdown = profile.reasons.iter().map(|(r, ns)| (r.to_string(), *ns)).collect();
+        return Ok(LoadedProfile {
+            stacks: profile.stacks,
+            weight: report::Weight::Nanoseconds,
+            breakdown,
+            symbolizer: profile::symbolizer(&file),
+        });
+    }
+    Ok(LoadedProfile {
+        stacks: profile::on_cpu(&file, filter),
+        weight: report::Weight::Events,
+        breakdown: Vec::new(),
+        symbolizer: profile::symbolizer(&file),
+    })
+}
+
+/// Prints the call graph of the samples of a record file that `filter`
+/// matches. / 打印记录文件中 `filter` 匹配的采样的调用图。
+#[cfg(not(tarpaulin_include))]
+fn run_report(input: &PathBuf, filter: &SampleFilter, options: &CallGraphOptions) -> Result<()> {
+    let mut loaded = load_profile(input, filter)?;
+    let symbolizer = &mut loaded.symbolizer;
+    let graph = callgraph::CallGraph::build(&loaded.stacks, options, &mut |pid, addr| {
+        Frame::from_symbol(&symbolizer.symbolize(pid, addr), addr)
+    });
+    let lines = report::format_report(&graph, options, loaded.weight, &loaded.breakdown);
+    warn_unreadable(&loaded.symbolizer, &loaded.stacks.mismatched);
+    let mut out = stdout().lock();
+    for line in lines {
+        match writeln!(out, "{}", line) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes a flame graph of a record file, or its folded stacks.
+/// 写出记录文件的火焰图，或其折叠栈。
+#[cfg(not(tarpaulin_include))]
+fn run_flamegraph(input: &PathBuf, output: &PathBuf, folded: bool) -> Result<()> {
+    let mut loaded = load_profile(input, &SampleFilter::default())?;
+    let symbolizer = &mut loaded.symbolizer;
+    let lines = loaded.stacks.folded(|pid, addr| symbolizer.symbolize(pid, addr).names(addr));
+    warn_unreadable(&loaded.symbolizer, &loaded.stacks.mismatched);
+    if folded {
+        let mut out = stdout().lock();
+        for line in lines {
+            match writeln!(out, "{}", line) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                result => result?,
+            }
+        }
+        return Ok(());
+    }
+    let (title, unit, palette) = match loaded.weight {
+        report::Weight::Events => ("On-CPU Flame Graph", "samples", flamegraph::Palette::Hot),
+        report::Weight::Nanoseconds => ("Off-CPU Flame Graph", "ns", flamegraph::Palette::Cold),
+    };
+    std::fs::write(output, flamegraph::render_svg(&lines, title, unit, palette))
+        .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", output.display(), e))?;
+    eprintln!("[ pipa flamegraph: {} stacks to {} ]", loaded.stacks.stacks.len(), output.display());
+    Ok(())
+}
+
+/// Prints every sample of a record file. / 打印记录文件中的所有采样。
+#[cfg(not(tarpaulin_include))]
+fn run_script(input: &PathBuf) -> Result<()> {
+    let file = perf_record::read_record(input)?;
+    let mut symbolizer = profile::symbolizer(&file);
+    let mut out = stdout().lock();
+    let mut comms = HashMap::new();
+    for entry in &file.entries {
+        let sample = match entry {
+            RecordEntry::Sample(sample) => sample,
+            RecordEntry::Comm(comm) => {
+                comms.insert(comm.tid, comm.comm.as_str());
+                continue;
+            }
+            RecordEntry::Switch(_)
+            | RecordEntry::Mmap(_)
+            | RecordEntry::BuildId(_)
+            | RecordEntry::Lost { .. } => continue,
+        };
+        let comm = comms.get(&sample.tid).copied();
+        let symbol = match file.header.events.get(sample.event) {
+            Some(event) if event.format.is_some() => None,
+            _ => Some(symbolizer.symbolize(sample.pid, sample.ip).to_string()),
+        };
+        let line = script::format_sample(&file.header, comm, sample, symbol.as_deref());
+        match writeln!(out, "{}", line) {
+            // The reader, e.g. `head`, has seen enough.
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+    }
+    warn_unreadable(&symbolizer, &[]);
+    if file.lost() > 0 {
+        eprintln!("Warning: {} records were lost while recording", file.lost());
+    }
+    Ok(())
+}
+
+/// Reads the current value of a counter.
+/// 读取计数器的当前值。
+#[cfg(not(tarpaulin_include))]
+fn read_counter(counter: &raw_perf_events::Counter) -> Result<u64> {
+    // This is the robust, idiomatic way to handle reading from a raw file
+    // descriptor that is owned by another structure.
+    //
+    // 1. Duplicate the file descriptor. `dup_fd` is a new, independent FD pointing
+    //    to the same underlying kernel file description.
+    let dup_fd = unsafe { libc::dup(counter.fd()) };
+    if dup_fd < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    // 2. The `File` now takes ownership of the *duplicated* FD. The original
+    //    `counter.fd()` is unaffected.
+    let mut file = unsafe { File::from_raw_fd(dup_fd) };
+    let mut buf = [0u8; 8];
+
+    // 3. Read from the file. If this fails and returns early, the `file` (and
+    //    `dup_fd`) will be correctly and safely closed by its Drop impl. The
+    //    original FD in `counter` remains open.
+    file.read_exact(&mut buf)?;
+
+    // 4. No more `mem::forget`! The `file` is dropped here, closing `dup_fd`, which
+    //    is exactly what we want.
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Resolves event names given on the command line.
+/// 解析命令行中给出的事件名称。
+fn resolve_events(root: &SysRoot, names: &[String]) -> Result<Vec<(String, PerfEvent)>> {
+    names
+        .iter()
+        .map(|name| Ok((name.clone(), raw_perf_events::resolve_event(root, name)?)))
+        .collect()
+}
+
+/// Formats the counter summary shared by both `stat` modes. CPI is shown
+/// when both cycles and instructions were counted.
+/// 格式化两种 `stat` 模式共用的计数器摘要。同时统计了周期数和指令数时显示 CPI。
+fn format_counters(target: &str, events: &[(String, PerfEvent)], counts: &[u64]) -> Vec<String> {
+    let mut lines = vec![format!("--- Performance counters for {} ---", target), String::new()];
+    for ((name, _), count) in events.iter().zip(counts) {
+        lines.push(format!("{:<20}: {}", name, count));
+    }
+    let count_of = |wanted: PerfEvent| {
+        events.iter().zip(counts).find(|((_, event), _)| *event == wanted).map(|(_, c)| *c)
+    };
+    if let (Some(cycles), Some(instructions)) =
+        (count_of(PerfEvent::Cycles), count_of(PerfEvent::Instructions))
+    {
+        let cpi = if instructions > 0 { cycles as f64 / instructions as f64 } else { 0.0 };
+        lines.push(format!("{:<20}: {:.2}", "CPI", cpi));
+    }
+    lines
+}
+
+/// Prints the counter summary shared by both `stat` modes.
+/// 打印两种 `stat` 模式共用的计数器摘要。
+#[cfg(not(tarpaulin_include))]
+fn print_counters(target: &str, events: &[(String, PerfEvent)], counts: &[u64]) {
+    println!();
+    for line in format_counters(target, events, counts) {
+        println!("{}", line);
+    }
+    println!("\n------------------------------------------\n");
+}
+
+/// Starts sampling the CPU telemetry collectors in the background for the
+/// duration of `stat`. Snapshots are summarized as they are taken rather than
+/// buffered.
+/// 在 `stat` 运行期间于后台开始运行 CPU 遥测采集器。快照在采集时即被汇总，而不是缓存起来。
+#[cfg(not(tarpaulin_include))]
+fn start_telemetry(root: &SysRoot) -> Result<(SystemSampler, Arc<Mutex<TelemetrySummarizer>>)> {
+    let summarizer = Arc::new(Mutex::new(TelemetrySummarizer::new()));
+    let sink = Arc::clone(&summarizer);
+    let config = SamplerConfig {
+        interval: TELEMETRY_INTERVAL,
+        collectors: collector::CPU_TELEMETRY_COLLECTORS.map(String::from).to_vec(),
+        capacity: 1,
+        capture: None,
+        root: root.clone(),
+    };
+    let registry = CollectorRegistry::builtin(root);
+    let sampler = SystemSampler::start_observed(config, &registry, move |snapshot| {
+        // Telemetry is best effort; a round where every read failed is skipped.
+        if snapshot.stats.samples.is_empty() {
+            return;
+        }
+        let samples = &snapshot.stats;
+        let telemetry = collector::cpu_telemetry_from_samples(
+            samples.get("cpufreq"),
+            samples.get("cpuidle"),
+            samples.get("thermal"),
+        );
+        sink.lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .add(&Snapshot { timestamp: snapshot.timestamp, stats: telemetry });
+    })?;
+    Ok((sampler, summarizer))
+}
+
+/// Stops the telemetry sampler, which takes a final reading, and returns the
+/// summary.
+/// 停止遥测采样器（会采集最后一个读数）并返回汇总结果。
+#[cfg(not(tarpaulin_include))]
+fn stop_telemetry(
+    (sampler, summarizer): (SystemSampler, Arc<Mutex<TelemetrySummarizer>>),
+) -> Result<TelemetrySummary> {
+    sampler.stop()?;
+    let summary = summarizer.lock().unwrap_or_else(|e| e.into_inner()).summary();
+    Ok(summary)
+}
+
+/// Prints the CPU telemetry recorded during `stat` and flags throttling,
+/// which makes CPI comparisons between runs unreliable. Prints nothing when
+/// the platform exposes no telemetry.
+/// 打印 `stat` 期间记录的 CPU 遥测数据并标记降频情况，降频会使不同运行之间的
+/// CPI 比较失去意义。平台未提供遥测数据时不打印任何内容。
+#[cfg(not(tarpaulin_include))]
+fn print_telemetry(summary: &TelemetrySummary) {
+    let lines = format_telemetry(summary);
+    if !lines.is_empty() {
+        for line in lines {
+            println!("{}", line);
+        }
+        println!("\n------------------------------------------\n");
+    }
+}
+
+/// Formats a telemetry summary as report lines.
+/// 将遥测汇总格式化为报告行。
+fn format_telemetry(summary: &TelemetrySummary) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let (Some(mean), Some(min), Some(max)) =
+        (summary.mean_freq_khz, summary.min_freq_khz, summary.max_freq_khz)
+    {
+        lines.push(format!(
+            "{:<20}: {:.0} MHz avg ({} - {} MHz)",
+            "CPU frequency",
+            mean / 1000.0,
+            min / 1000,
+            max / 1000
+        ));
+    }
+    if let Some(temp) = summary.peak_temp_millicelsius {
+        lines.push(format!("{:<20}: {:.1} °C", "Peak temperature", temp as f64 / 1000.0));
+    }
+    for state in summary.idle.iter().filter(|s| s.time_us > 0) {
+        lines.push(format!("{:<20}: {:.1}%", format!("Idle {}", state.name), state.share * 100.0));
+    }
+    if !summary.capped_cpus.is_empty() {
+        lines.push(format!(
+            "WARNING: frequency cap lowered during the run on CPU(s) {:?}; CPI may be skewed",
+            summary.capped_cpus
+        ));
+    }
+    if !summary.hot_zones.is_empty() {
+        lines.push(format!(
+            "WARNING: thermal throttling, passive trip point reached in {}",
+            summary.hot_zones.join(", ")
+        ));
+    }
+    lines
+}
+
+/// PSI triggers armed for the duration of `stat`, each polled by its own
+/// thread until `stop` is set.
+/// `stat` 期间注册的 PSI 触发器，每个触发器由独立线程轮询，直到 `stop` 被置位。
+struct PsiWatch {
+    stop: Arc<AtomicBool>,
+    watchers: Vec<(PsiTriggerSpec, std::thread::JoinHandle<Result<Vec<Duration>>>)>,
+}
+
+/// Arms every trigger and starts watching it. All triggers are armed before
+/// any thread starts, so a rejected spec fails `stat` before the workload runs.
+/// Each firing is reported on stderr as it happens.
+/// 注册所有触发器并开始监视。所有触发器都在启动线程前注册，因此被拒绝的规格会在
+/// 负载运行前就使 `stat` 失败。每次触发都会立即在 stderr 上报告。
+#[cfg(not(tarpaulin_include))]
+fn start_psi_watch(root: &SysRoot, specs: &[PsiTriggerSpec]) -> Result<PsiWatch> {
+    let triggers = specs
+        .iter()
+        .map(|spec| spec.arm(root).map(|trigger| (*spec, trigger)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let stop = Arc::new(AtomicBool::new(false));
+    let start = Instant::now();
+    let watchers = triggers
+        .into_iter()
+        .map(|(spec, trigger)| {
+            let stop = Arc::clone(&stop);
+            let handle = std::thread::spawn(move || {
+                let mut fired = Vec::new();
+                while !stop.load(Ordering::SeqCst) {
+                    if trigger.wait(Some(PSI_POLL_INTERVAL))? {
+                        let at = start.elapsed();
+                        eprintln!(
+                            "WARNING: PSI {} stall exceeded at {:.1}s",
+                            spec,
+                            at.as_secs_f64()
+                        );
+                        fired.push(at);
+                    }
+                }
+                Ok(fired)
+            });
+            (spec, handle)
+        })
+        .collect();
+    Ok(PsiWatch { stop, watchers })
+}
+
+/// Stops the PSI watchers and returns when each trigger fired, relative to
+/// the start of the run.
+/// 停止 PSI 监视线程，并返回每个触发器的触发时刻（相对于运行开始）。
+#[cfg(not(tarpaulin_include))]
+fn stop_psi_watch(watch: PsiWatch) -> Result<Vec<(PsiTriggerSpec, Vec<Duration>)>> {
+    watch.stop.store(true, Ordering::SeqCst);
+    watch
+        .watchers
+        .into_iter()
+        .map(|(spec, handle)| {
+            let fired = handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("PSI watcher for {} panicked", spec))??;
+            Ok((spec, fired))
+        })
+        .collect()
+}
+
+/// Prints how often each PSI trigger fired during `stat`. Prints nothing
+/// when no trigger was requested.
+/// 打印 `stat` 期间每个 PSI 触发器的触发次数。未请求触发器时不打印任何内容。
+#[cfg(not(tarpaulin_include))]
+fn print_psi_stalls(stalls: &[(PsiTriggerSpec, Vec<Duration>)]) {
+    if !stalls.is_empty() {
+        for (spec, fired) in stalls {
+            println!("{}", format_psi_stalls(spec, fired));
+        }
+        println!("\n------------------------------------------\n");
+    }
+}
+
+/// Formats the report line of one PSI trigger.
+/// 格式化单个 PSI 触发器的报告行。
+fn format_psi_stalls(spec: &PsiTriggerSpec, fired: &[Duration]) -> String {
+    let threshold = format!(
+        "{} {} stall above {}ms per {}ms",
+        spec.resource.name(),
+        spec.kind.as_str(),
+        spec.stall.as_millis(),
+        spec.window.as_millis()
+    );
+    match fired.first() {
+        None => format!("{:<20}: no {}", "PSI", threshold),
+        Some(first) => format!(
+            "WARNING: {} {} time(s), first at {:.1}s",
+            threshold,
+            fired.len(),
+            first.as_secs_f64()
+        ),
+    }
+}
+
 /// Renders the UI frame to the terminal using absolute cursor positioning.
 /// 使用绝对光标定位将 UI 帧渲染到终端。
 #[cfg(not(tarpaulin_include))]
@@ -195,6 +1804,8 @@ fn draw_ui<W: Write>(
     interval: u64,
     cpu_usage: f64,
     mem_stats: &MemoryStats,
+    load_stats: &LoadStats,
+    online_cpus: usize,
 ) -> Result<()> {
     let mem_used_gib = (mem_stats.total - mem_stats.available) as f64 / 1024.0 / 1024.0;
     let mem_available_gib = mem_stats.available as f64 / 1024.0 / 1024.0;
@@ -230,8 +1841,37 @@ fn draw_ui<W: Write>(
         style::Print(format!("{:<12} {:>10.2} GiB", "Available:", mem_available_gib)),
         cursor::MoveTo(2, 8),
         style::Print(format!("{:<12} {:>10.2} GiB", "Total:", mem_total_gib)),
+        // --- Draw Load Section, next to the CPU bar ---
+        cursor::MoveTo(40, 2),
+        style::Print(format!("[ Load Average ({} CPUs) ]", online_cpus)),
+        cursor::MoveTo(40, 3),
+        style::Print(format!("{:<4}{}", "1m", load_bar(load_stats.load1, online_cpus))),
+        cursor::MoveTo(40, 4),
+        style::Print(format!("{:<4}{}", "5m", load_bar(load_stats.load5, online_cpus))),
+        cursor::MoveTo(40, 5),
+        style::Print(format!("{:<4}{}", "15m", load_bar(load_stats.load15, online_cpus))),
+        cursor::MoveTo(40, 6),
+        style::Print(format!(
+            "Tasks: {} running, {} blocked, {} total",
+            load_stats.procs_running, load_stats.procs_blocked, load_stats.total_tasks
+        )),
     )?;
 
+    // A load above the CPU count means runnable tasks are queueing.
+    // 负载超过 CPU 数量意味着可运行任务正在排队。
+    if online_cpus > 0 && load_stats.load1 > online_cpus as f64 {
+        queue!(
+            f,
+            cursor::MoveTo(40, 7),
+            style::SetForegroundColor(style::Color::Red),
+            style::Print(format!(
+                "WARNING: load {:.2} exceeds {} online CPUs",
+                load_stats.load1, online_cpus
+            )),
+            style::ResetColor,
+        )?;
+    }
+
     // This is the crucial step that draws everything queued above.
     // 这是绘制上面队列中所有内容的关键步骤。
     f.flush()?;
@@ -239,23 +1879,75 @@ fn draw_ui<W: Write>(
     Ok(())
 }
 
-fn calculate_cpu_usage(prev: &CpuStats, current: &CpuStats) -> f64 {
-    let prev_idle = prev.idle + prev.iowait;
-    let current_idle = current.idle + current.iowait;
+/// Formats the rates of one collector for the monitor, one line per instance:
+/// counters per second, gauges as read.
+/// 为监控界面格式化单个采集器的速率，每个实例一行：计数器为每秒速率，瞬时值保持读数。
+fn format_collector_rates(name: &str, schema: &[Metric], rates: &Sample) -> Vec<String> {
+    rates
+        .records
+        .iter()
+        .map(|record| {
+            let mut line = format!("[ {} ]", name);
+            if !record.instance.is_empty() {
+                line.push_str(&format!(" {}", record.instance));
+            }
+            for (metric, value) in schema.iter().zip(&record.values) {
+                let unit = match metric.kind {
+                    MetricKind::Counter => "/s",
+                    MetricKind::Gauge => "",
+                };
+                line.push_str(&format!("  {} {:.2}{}", metric.name, value, unit));
+            }
+            line
+        })
+        .collect()
+}
 
-    let prev_non_idle = prev.user + prev.nice + prev.system + prev.irq + prev.softirq + prev.steal;
-    let current_non_idle = current.user
-        + current.nice
-        + current.system
-        + current.irq
-        + current.softirq
-        + current.steal;
+/// Formats one CPU group for the monitor: its CPUs and their busy share, then
+/// the node's memory use and NUMA allocation rates when they are known.
+/// 为监控界面格式化一个 CPU 分组：其 CPU 及忙碌占比，
+/// 以及（如已知）该节点的内存使用量和 NUMA 分配速率。
+fn format_cpu_group(
+    group: &GroupResult<CpuStats>,
+    memory: Option<&NodeMemInfo>,
+    numa: Option<&SnapshotDelta<NumaStat>>,
+) -> String {
+    let mut line = format!(
+        "[ {} ] cpus {}  busy {:.2}%",
+        group.group,
+        system_stats::format_cpu_list(&group.cpus),
+        calculate_cpu_usage(&CpuStats::default(), &group.value)
+    );
+    if let Some(memory) = memory {
+        let gib = |kb: u64| kb as f64 / 1024.0 / 1024.0;
+        line.push_str(&format!("  mem {:.2}/{:.2} GiB", gib(memory.used), gib(memory.total)));
+    }
+    if let Some(numa) = numa {
+        for (name, pages) in [
+            ("local_node", numa.delta.local_node),
+            ("other_node", numa.delta.other_node),
+            ("numa_miss", numa.delta.numa_miss),
+        ] {
+            line.push_str(&format!("  {} {:.2}/s", name, numa.per_second(pages)));
+        }
+    }
+    line
+}
 
-    let prev_total = prev_idle + prev_non_idle;
-    let current_total = current_idle + current_non_idle;
+/// Renders a load average as a bar that is full when the load equals the
+/// number of online CPUs.
+/// 将平均负载渲染为条形图，负载等于在线 CPU 数量时条形图为满。
+fn load_bar(load: f64, online_cpus: usize) -> String {
+    const WIDTH: usize = 10;
+    let ratio = if online_cpus > 0 { load / online_cpus as f64 } else { 0.0 };
+    let filled = ((ratio * WIDTH as f64).round() as usize).min(WIDTH);
+    format!("[{:<width$}] {:>6.2}", "█".repeat(filled), load, width = WIDTH)
+}
 
-    let total_delta = (current_total - prev_total) as f64;
-    let idle_delta = (current_idle - prev_idle) as f64;
+fn calculate_cpu_usage(prev: &CpuStats, current: &CpuStats) -> f64 {
+    let delta = current.delta(prev);
+    let total_delta = delta.total_time() as f64;
+    let idle_delta = delta.idle_time() as f64;
 
     if total_delta == 0.0 {
         0.0
@@ -267,15 +1959,132 @@ fn calculate_cpu_usage(prev: &CpuStats, current: &CpuStats) -> f64 {
 }
 
 #[cfg(not(tarpaulin_include))]
-fn main() -> Result<()> {
+fn main() {
     let cli = Cli::parse();
+    if let Err(err) = run(cli) {
+        eprintln!("{}", errors::render(&err));
+        std::process::exit(errors::exit_code(&err));
+    }
+}
+
+/// Runs the selected subcommand. / 运行所选的子命令。
+fn run(cli: Cli) -> Result<()> {
+    let root = SysRoot::default().with_proc(cli.procfs).with_sys(cli.sysfs);
 
     match cli.command {
-        Commands::Monitor { interval } => {
-            run_monitor(interval)?;
+        Commands::Monitor { interval, collectors, per } => {
+            run_monitor(&root, interval, &collectors, per)?;
+        }
+        Commands::Stat { pid: Some(pid), duration, events, psi_triggers, .. } => {
+            run_stat_attach(&root, &events, &psi_triggers, pid, duration)?;
+        }
+        Commands::Stat { pid: None, events, psi_triggers, command, .. } => {
+            run_stat(&root, &events, &psi_triggers, &command)?;
+        }
+        Commands::Record {
+            events,
+            output,
+            pid,
+            all_cpus,
+            duration,
+            freq,
+            callchain,
+            call_graph,
+            off_cpu,
+            no_buildid_cache,
+            command,
+        } => {
+            // Without a target, `command` is forked and recorded.
+            let target = match pid {
+                Some(pid) => Some(RecordTarget::Pid(pid)),
+                None if all_cpus => Some(RecordTarget::AllCpus),
+                None => None,
+            };
+            let events = if off_cpu {
+                offcpu::EVENTS.iter().map(|e| e.to_string()).collect()
+            } else {
+                events
+            };
+            // Off-CPU stacks are taken in the kernel, where frame pointers work.
+            let call_graph = match (call_graph, callchain || off_cpu) {
+                (Some(mode), _) => Some(mode),
+                (None, true) => Some(CallGraph::Fp),
+                (None, false) => None,
+            };
+            let sampling = SamplingOptions {
+                freq,
+                call_graph,
+                context_switch: off_cpu,
+                buildid_cache: !no_buildid_cache,
+            };
+            run_record(&root, &events, target, &output, duration, &sampling, &command)?;
+        }
+        Commands::Script { input } => {
+            run_script(&input)?;
         }
-        Commands::Stat { command } => {
-            run_stat(&command)?;
+        Commands::Sched { command: SchedCommand::Record { output, duration, command } } => {
+            let events: Vec<String> = pipa_sched::EVENTS.iter().map(|e| e.to_string()).collect();
+            let target = Some(RecordTarget::AllCpus);
+            // Scheduler events carry no user addresses to symbolize.
+            let sampling = SamplingOptions {
+                freq: 4000,
+                call_graph: None,
+                context_switch: false,
+                buildid_cache: false,
+            };
+            run_record(&root, &events, target, &output, duration, &sampling, &command)?;
+        }
+        Commands::Sched { command: SchedCommand::Latency { input } } => {
+            run_sched_report(&input, None)?;
+        }
+        Commands::Sched { command: SchedCommand::Timeline { input, cpus } } => {
+            run_sched_report(&input, Some(&cpus))?;
+        }
+        Commands::Report { input, sort, no_children, order, pid, tid, cpus, time } => {
+            let filter =
+                SampleFilter { pids: pid, tids: tid, cpus, time: time.unwrap_or_default() };
+            let options = CallGraphOptions {
+                sort,
+                children: !no_children,
+                order: order.unwrap_or(if no_children {
+                    CallOrder::Callee
+                } else {
+                    CallOrder::Caller
+                }),
+            };
+            run_report(&input, &filter, &options)?;
+        }
+        Commands::Flamegraph { input, output, folded } => {
+            run_flamegraph(&input, &output, folded)?;
+        }
+        Commands::Trace { summary, pid, duration, command } => {
+            run_trace(&root, summary, pid, duration, &command)?;
+        }
+        Commands::Sar { command: SarCommand::Record { interval, output, count, collectors } } => {
+            run_sar_record(&root, interval, output, count, collectors)?;
+        }
+        Commands::Sar {
+            command: SarCommand::Report { file, cpu, mem, disk, net, load, start, end, collectors },
+        } => {
+            let sections = [
+                (cpu, sar::Section::Cpu),
+                (mem, sar::Section::Mem),
+                (disk, sar::Section::Disk),
+                (net, sar::Section::Net),
+                (load, sar::Section::Load),
+            ]
+            .into_iter()
+            .filter_map(|(selected, section)| selected.then_some(section))
+            .chain(collectors.into_iter().map(sar::Section::Registered))
+            .collect();
+            run_sar_report(file, sections, start, end)?;
+        }
+        Commands::Export { collectors, interval, count, format, list, cgroups, pids } => {
+            let targets = ExportTargets { cgroups, pids };
+            run_export(&root, &collectors, &targets, interval, count, format, list)?;
+        }
+        Commands::Doctor => {
+            run_doctor(&root)?;
         }
     }
     Ok(())
@@ -284,6 +2093,50 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_format_counters() {
+        let events = vec![
+            ("cycles".to_string(), PerfEvent::Cycles),
+            ("instructions".into(), PerfEvent::Instructions),
+        ];
+        let lines = format_counters("PID 1", &events, &[300, 200]);
+        assert_eq!(lines[0], "--- Performance counters for PID 1 ---");
+        assert_eq!(lines[2], format!("{:<20}: 300", "cycles"));
+        assert_eq!(lines.last().unwrap(), &format!("{:<20}: 1.50", "CPI"));
+
+        let events = vec![("sched:sched_switch".to_string(), PerfEvent::Tracepoint(372))];
+        let lines = format_counters("PID 1", &events, &[7]);
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].ends_with(": 7"));
+    }
+
+    #[test]
+    fn test_format_findings() {
+        let findings = [
+            Finding {
+                check: "kernel",
+                status: Status::Ok,
+                detail: "Linux 6.8.0".into(),
+                hint: None,
+            },
+            Finding {
+                check: "tracefs",
+                status: Status::Warn,
+                detail: "not mounted".into(),
+                hint: Some("mount it".into()),
+            },
+        ];
+        assert_eq!(
+            format_findings(&findings),
+            [
+                "[OK  ] kernel               Linux 6.8.0",
+                "[WARN] tracefs              not mounted",
+                "                            hint: mount it",
+            ]
+        );
+    }
+
     #[test]
     fn test_calculate_cpu_usage_basic() {
         let prev = CpuStats {
@@ -337,8 +2190,17 @@ mod tests {
             ..Default::default()
         };
 
+        let load_stats = LoadStats {
+            load1: 0.5,
+            load5: 1.0,
+            load15: 2.0,
+            procs_running: 2,
+            total_tasks: 71,
+            ..Default::default()
+        };
+
         // 3. Call our drawing function, but give it the fake terminal
-        draw_ui(&mut buffer, 1, 50.0, &mem_stats).unwrap();
+        draw_ui(&mut buffer, 1, 50.0, &mem_stats, &load_stats, 4).unwrap();
 
         // 4. Convert the raw bytes (which include ANSI codes) into a string
         let output = String::from_utf8(buffer).unwrap();
@@ -352,9 +2214,116 @@ mod tests {
         assert!(output.contains("8.00 GiB"));
         assert!(output.contains("Total:"));
         assert!(output.contains("16.00 GiB"));
+        assert!(output.contains("[ Load Average (4 CPUs) ]"));
+        assert!(output.contains("2 running, 0 blocked, 71 total"));
+        assert!(!output.contains("WARNING"));
 
         // We could even test for specific ANSI codes if we wanted to be extremely
         // precise For example, does it start with the "clear screen" code?
         assert!(output.starts_with("\x1B[2J"));
     }
+
+    #[test]
+    fn test_draw_ui_warns_when_load_exceeds_cpus() {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mem_stats = MemoryStats { total: 1024, available: 512, ..Default::default() };
+        let load_stats = LoadStats { load1: 9.0, load5: 4.0, load15: 1.0, ..Default::default() };
+
+        draw_ui(&mut buffer, 1, 10.0, &mem_stats, &load_stats, 8).unwrap();
+
+        let output = String::from_utf8(buffer).unwrap();
+        assert!(output.contains("WARNING: load 9.00 exceeds 8 online CPUs"));
+    }
+
+    #[test]
+    fn test_format_collector_rates() {
+        use pipa_collector::collector::Record;
+
+        let schema = [Metric::counter("rx_bytes", "bytes"), Metric::gauge("speed_mbps", "Mbit/s")];
+        let rates = Sample {
+            records: vec![Record { instance: "eth0".into(), values: vec![1024.0, 1000.0] }],
+        };
+        assert_eq!(
+            format_collector_rates("net", &schema, &rates),
+            ["[ net ] eth0  rx_bytes 1024.00/s  speed_mbps 1000.00"]
+        );
+        let load = Sample::single(vec![0.5]);
+        assert_eq!(
+            format_collector_rates("load", &[Metric::gauge("load1", "")], &load),
+            ["[ load ]  load1 0.50"]
+        );
+    }
+
+    #[test]
+    fn test_format_cpu_group() {
+        use pipa_core::aggregate::CpuGroup;
+
+        let socket = GroupResult {
+            group: CpuGroup::Socket(1),
+            cpus: vec![4, 5, 6, 7],
+            value: CpuStats { user: 30, system: 10, idle: 60, ..Default::default() },
+        };
+        assert_eq!(format_cpu_group(&socket, None, None), "[ S1 ] cpus 4-7  busy 40.00%");
+
+        let node = GroupResult { group: CpuGroup::Node(0), cpus: vec![0, 2], ..socket };
+        let memory =
+            NodeMemInfo { total: 4 * 1024 * 1024, used: 1024 * 1024, ..Default::default() };
+        let numa = SnapshotDelta {
+            elapsed: Duration::from_secs(2),
+            delta: NumaStat { local_node: 200, other_node: 10, ..Default::default() },
+        };
+        assert_eq!(
+            format_cpu_group(&node, Some(&memory), Some(&numa)),
+            "[ N0 ] cpus 0,2  busy 40.00%  mem 1.00/4.00 GiB  local_node 100.00/s  \
+             other_node 5.00/s  numa_miss 0.00/s"
+        );
+    }
+
+    #[test]
+    fn test_load_bar() {
+        assert_eq!(load_bar(0.0, 4), format!("[{}]   0.00", " ".repeat(10)));
+        assert_eq!(load_bar(2.0, 4), format!("[{}{}]   2.00", "█".repeat(5), " ".repeat(5)));
+        // Overload is capped at a full bar.
+        assert_eq!(load_bar(16.0, 4), format!("[{}]  16.00", "█".repeat(10)));
+        assert_eq!(load_bar(1.0, 0), format!("[{}]   1.00", " ".repeat(10)));
+    }
+
+    #[test]
+    fn test_format_telemetry() {
+        assert!(format_telemetry(&TelemetrySummary::default()).is_empty());
+
+        let summary = TelemetrySummary {
+            samples: 3,
+            mean_freq_khz: Some(2_850_000.0),
+            min_freq_khz: Some(2_000_000),
+            max_freq_khz: Some(3_600_000),
+            capped_cpus: vec![1, 3],
+            peak_temp_millicelsius: Some(91_500),
+            hot_zones: vec!["x86_pkg_temp".to_string()],
+            idle: vec![pipa_collector::cpu_telemetry::IdleResidency {
+                name: "C6".to_string(),
+                time_us: 500,
+                share: 0.25,
+            }],
+        };
+        let lines = format_telemetry(&summary);
+        assert_eq!(lines[0], format!("{:<20}: 2850 MHz avg (2000 - 3600 MHz)", "CPU frequency"));
+        assert_eq!(lines[1], format!("{:<20}: 91.5 °C", "Peak temperature"));
+        assert_eq!(lines[2], format!("{:<20}: 25.0%", "Idle C6"));
+        assert!(lines[3].contains("CPU(s) [1, 3]"));
+        assert!(lines[4].contains("x86_pkg_temp"));
+    }
+
+    #[test]
+    fn test_format_psi_stalls() {
+        let spec: PsiTriggerSpec = "memory:some:150:1000".parse().unwrap();
+        assert_eq!(
+            format_psi_stalls(&spec, &[]),
+            format!("{:<20}: no memory some stall above 150ms per 1000ms", "PSI")
+        );
+        assert_eq!(
+            format_psi_stalls(&spec, &[Duration::from_millis(1250), Duration::from_secs(3)]),
+            "WARNING: memory some stall above 150ms per 1000ms 2 time(s), first at 1.2s"
+        );
+    }
 }
@@ -0,0 +1,391 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The `top`-like process table shown by `pipa monitor`, built on the
+//! per-process `/proc` parsers of `pipa_collector::process_stats`.
+//!
+//! `pipa monitor` 中类似 `top` 的进程表，基于 `pipa_collector::process_stats`
+//! 中的单进程 `/proc` 解析器构建。
+
+use anyhow::Result;
+use crossterm::{cursor, event::KeyCode, queue, style};
+use pipa_collector::process_stats::{ProcessCounters, ProcessSnapshot};
+use pipa_collector::system_stats::Snapshot;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// One line of the process table. / 进程表中的一行。
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessRow {
+    pub pid: u32,
+    pub comm: String,
+    /// CPU usage since the previous refresh; may exceed 100% for
+    /// multi-threaded processes. / 自上次刷新以来的 CPU 使用率，多线程进程可超过 100%。
+    pub cpu_percent: f64,
+    /// Resident set size in kB. / 常驻内存大小（kB）。
+    pub rss_kb: u64,
+    pub state: char,
+    pub threads: u64,
+}
+
+/// The column the table is sorted by (descending). / 表格排序所依据的列（降序）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Cpu,
+    Memory,
+}
+
+/// What the user asked for from the table. / 用户在表格中请求的操作。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableAction {
+    /// Leave the monitor. / 退出监视器。
+    Quit,
+    /// Start a `stat` attach on the given PID. / 对指定 PID 启动 `stat` 附加。
+    Stat(u32),
+    /// Start a `record` of the given PID. / 对指定 PID 启动 `record`。
+    Record(u32),
+}
+
+/// State of the process table: the latest rows plus sorting, selection and
+/// filtering.
+///
+/// 进程表的状态：最新的行数据以及排序、选择和过滤状态。
+#[derive(Debug)]
+pub struct ProcessTable {
+    rows: Vec<ProcessRow>,
+    previous: HashMap<u32, Snapshot<ProcessCounters>>,
+    clock_ticks: u64,
+    sort: SortKey,
+    selected: usize,
+    filter: String,
+    editing_filter: bool,
+}
+
+impl ProcessTable {
+    pub fn new(clock_ticks: u64) -> Self {
+        ProcessTable {
+            rows: Vec::new(),
+            previous: HashMap::new(),
+            clock_ticks,
+            sort: SortKey::Cpu,
+            selected: 0,
+            filter: String::new(),
+            editing_filter: false,
+        }
+    }
+
+    /// Replaces the rows with a fresh set of snapshots. CPU usage is computed
+    /// against the previous snapshot of the same PID; new PIDs show 0%.
+    ///
+    /// 用一组新快照替换行数据。CPU 使用率基于同一 PID 的上一次快照计算；新出现的 PID 显示 0%。
+    pub fn update(&mut self, snapshots: Vec<Snapshot<ProcessSnapshot>>) {
+        let mut previous = HashMap::with_capacity(snapshots.len());
+        self.rows = snapshots
+            .into_iter()
+            .map(|snapshot| {
+                let counters =
+                    Snapshot { timestamp: snapshot.timestamp, stats: snapshot.stats.counters() };
+                let cpu_percent = match self.previous.get(&snapshot.stats.stat.pid) {
+                    Some(prev) => {
+                        let d = counters.delta_since(prev);
+                        d.per_second(d.delta.utime + d.delta.stime) / self.clock_ticks as f64
+                            * 100.0
+                    }
+                    None => 0.0,
+                };
+                previous.insert(snapshot.stats.stat.pid, counters);
+
+                let stats = snapshot.stats;
+                ProcessRow {
+                    pid: stats.stat.pid,
+                    comm: stats.stat.comm,
+                    cpu_percent,
+                    rss_kb: stats.status.vm_rss,
+                    state: stats.stat.state,
+                    threads: stats.stat.num_threads,
+                }
+            })
+            .collect();
+        self.previous = previous;
+        self.clamp_selection();
+    }
+
+    /// The rows that match the filter, in display order.
+    /// 匹配过滤条件的行，按显示顺序排列。
+    pub fn visible_rows(&self) -> Vec<&ProcessRow> {
+        let filter = self.filter.to_lowercase();
+        let mut rows: Vec<&ProcessRow> = self
+            .rows
+            .iter()
+            .filter(|r| {
+                filter.is_empty()
+                    || r.comm.to_lowercase().contains(&filter)
+                    || r.pid.to_string().starts_with(&filter)
+            })
+            .collect();
+        match self.sort {
+            SortKey::Cpu => rows.sort_by(|a, b| {
+                b.cpu_percent.total_cmp(&a.cpu_percent).then(b.rss_kb.cmp(&a.rss_kb))
+            }),
+            SortKey::Memory => rows.sort_by(|a, b| {
+                b.rss_kb.cmp(&a.rss_kb).then(b.cpu_percent.total_cmp(&a.cpu_percent))
+            }),
+        }
+        rows
+    }
+
+    /// The PID under the cursor, if any. / 光标所在行的 PID（如果有）。
+    pub fn selected_pid(&self) -> Option<u32> {
+        self.visible_rows().get(self.selected).map(|r| r.pid)
+    }
+
+    fn clamp_selection(&mut self) {
+        let len = self.visible_rows().len();
+        self.selected = self.selected.min(len.saturating_sub(1));
+    }
+
+    /// Handles a key press. While the filter is being edited, printable keys
+    /// are appended to it; `Enter` or `Esc` leaves filter editing.
+    ///
+    /// 处理按键。编辑过滤条件时，可打印字符会追加到过滤条件中；`Enter` 或 `Esc` 结束编辑。
+    pub fn handle_key(&mut self, key: KeyCode) -> Option<TableAction> {
+        if self.editing_filter {
+            match key {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    self.editing_filter = false;
+                    self.filter.clear();
+                }
+                _ => {}
+            }
+            self.selected = 0;
+            return None;
+        }
+
+        match key {
+            KeyCode::Char('q') => return Some(TableAction::Quit),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected += 1,
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(10),
+            KeyCode::PageDown => self.selected += 10,
+            KeyCode::Home => self.selected = 0,
+            KeyCode::Char('c') => self.sort = SortKey::Cpu,
+            KeyCode::Char('m') => self.sort = SortKey::Memory,
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::Esc => self.filter.clear(),
+            KeyCode::Char('s') => return self.selected_pid().map(TableAction::Stat),
+            KeyCode::Char('r') => return self.selected_pid().map(TableAction::Record),
+            _ => {}
+        }
+        self.clamp_selection();
+        None
+    }
+
+    /// Draws the table starting at row `top`, using at most `height` rows.
+    /// 从第 `top` 行开始绘制表格，最多占用 `height` 行。
+    pub fn draw<W: Write>(&self, f: &mut W, top: u16, height: u16) -> Result<()> {
+        let sort = match self.sort {
+            SortKey::Cpu => "CPU",
+            SortKey::Memory => "MEM",
+        };
+        let filter = if self.editing_filter {
+            format!("Filter: {}_", self.filter)
+        } else if !self.filter.is_empty() {
+            format!("Filter: {}", self.filter)
+        } else {
+            String::new()
+        };
+        queue!(
+            f,
+            cursor::MoveTo(2, top),
+            style::Print(format!(
+                "[ Processes ] sort: {} (c/m)  filter: /  stat: s  record: r  {}",
+                sort, filter
+            )),
+            cursor::MoveTo(2, top + 1),
+            style::Print(format!(
+                "{:>7} {:<16} {:>7} {:>10} {:>2} {:>7}",
+                "PID", "COMMAND", "CPU%", "RSS(MiB)", "S", "THREADS"
+            )),
+        )?;
+
+        let rows = self.visible_rows();
+        let capacity = height.saturating_sub(2) as usize;
+        // Scroll so that the selected row is always visible.
+        let first = self.selected.saturating_sub(capacity.saturating_sub(1));
+        for (i, row) in rows.iter().skip(first).take(capacity).enumerate() {
+            let line = format!(
+                "{:>7} {:<16} {:>7.1} {:>10.1} {:>2} {:>7}",
+                row.pid,
+                truncate(&row.comm, 16),
+                row.cpu_percent,
+                row.rss_kb as f64 / 1024.0,
+                row.state,
+                row.threads
+            );
+            queue!(f, cursor::MoveTo(2, top + 2 + i as u16))?;
+            if first + i == self.selected {
+                queue!(
+                    f,
+                    style::SetAttribute(style::Attribute::Reverse),
+                    style::Print(line),
+                    style::SetAttribute(style::Attribute::Reset)
+                )?;
+            } else {
+                queue!(f, style::Print(line))?;
+            }
+        }
+
+        f.flush()?;
+        Ok(())
+    }
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::process_stats::{ProcessStat, ProcessStatus};
+    use std::time::Duration;
+
+    fn snapshot(pid: u32, comm: &str, ticks: u64, rss: u64, at: u64) -> Snapshot<ProcessSnapshot> {
+        Snapshot {
+            timestamp: Duration::from_secs(at),
+            stats: ProcessSnapshot {
+                stat: ProcessStat {
+                    pid,
+                    comm: comm.to_string(),
+                    state: 'S',
+                    utime: ticks,
+                    num_threads: 1,
+                    ..Default::default()
+                },
+                status: ProcessStatus { vm_rss: rss, ..Default::default() },
+                ..Default::default()
+            },
+        }
+    }
+
+    fn table() -> ProcessTable {
+        let mut table = ProcessTable::new(100);
+        table.update(vec![
+            snapshot(1, "init", 0, 1000, 10),
+            snapshot(2, "busy worker", 0, 50, 10),
+            snapshot(3, "db", 0, 9000, 10),
+        ]);
+        table.update(vec![
+            snapshot(1, "init", 10, 1000, 12),
+            snapshot(2, "busy worker", 300, 50, 12),
+            snapshot(3, "db", 100, 9000, 12),
+            snapshot(4, "new", 999, 1, 12),
+        ]);
+        table
+    }
+
+    #[test]
+    fn test_cpu_percent_from_deltas() {
+        let table = table();
+        let rows = table.visible_rows();
+        // 300 ticks over 2s at 100 ticks/s = 150%.
+        assert_eq!(rows[0].pid, 2);
+        assert_eq!(rows[0].cpu_percent, 150.0);
+        assert_eq!(rows[1].cpu_percent, 50.0);
+        // A process seen for the first time has no baseline yet.
+        assert_eq!(rows.iter().find(|r| r.pid == 4).unwrap().cpu_percent, 0.0);
+    }
+
+    #[test]
+    fn test_sort_by_memory() {
+        let mut table = table();
+        table.handle_key(KeyCode::Char('m'));
+        let pids: Vec<u32> = table.visible_rows().iter().map(|r| r.pid).collect();
+        assert_eq!(pids, vec![3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn test_navigation_and_actions() {
+        let mut table = table();
+        assert_eq!(table.selected_pid(), Some(2));
+        table.handle_key(KeyCode::Down);
+        assert_eq!(table.selected_pid(), Some(3));
+        table.handle_key(KeyCode::PageDown);
+        // Clamped to the last row.
+        assert_eq!(table.selected_pid(), Some(4));
+        table.handle_key(KeyCode::Char('k'));
+        assert_eq!(table.handle_key(KeyCode::Char('s')), Some(TableAction::Stat(1)));
+        assert_eq!(table.handle_key(KeyCode::Char('r')), Some(TableAction::Record(1)));
+        assert_eq!(table.handle_key(KeyCode::Char('q')), Some(TableAction::Quit));
+    }
+
+    #[test]
+    fn test_filter_editing() {
+        let mut table = table();
+        table.handle_key(KeyCode::Char('/'));
+        for c in "WORK".chars() {
+            table.handle_key(KeyCode::Char(c));
+        }
+        // While editing, 'q' is part of the filter, not a quit request.
+        assert_eq!(table.handle_key(KeyCode::Char('q')), None);
+        table.handle_key(KeyCode::Backspace);
+        table.handle_key(KeyCode::Enter);
+        let rows = table.visible_rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].comm, "busy worker");
+
+        // Esc clears the filter.
+        table.handle_key(KeyCode::Esc);
+        assert_eq!(table.visible_rows().len(), 4);
+    }
+
+    #[test]
+    fn test_filter_by_pid_prefix() {
+        let mut table = table();
+        table.handle_key(KeyCode::Char('/'));
+        table.handle_key(KeyCode::Char('3'));
+        let rows = table.visible_rows();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].pid, 3);
+    }
+
+    #[test]
+    fn test_draw() {
+        let table = table();
+        let mut buffer: Vec<u8> = Vec::new();
+        table.draw(&mut buffer, 10, 4).unwrap();
+        let output = String::from_utf8(buffer).unwrap();
+
+        assert!(output.contains("[ Processes ] sort: CPU"));
+        assert!(output.contains("COMMAND"));
+        assert!(output.contains("busy worker"));
+        assert!(output.contains("150.0"));
+        // Only two rows fit below the two header lines.
+        assert!(output.contains("db"));
+        assert!(!output.contains("init"));
+    }
+
+    #[test]
+    fn test_empty_table() {
+        let mut table = ProcessTable::new(100);
+        assert_eq!(table.selected_pid(), None);
+        assert_eq!(table.handle_key(KeyCode::Char('s')), None);
+        assert_eq!(table.handle_key(KeyCode::Down), None);
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa report`: prints the call graph of an on-CPU or off-CPU profile
+//! like `perf report --stdio`: one line per entry with its children and
+//! self percentages, heaviest first, then the calls below or above it.
+//!
+//! `pipa report`：像 `perf report --stdio` 一样打印 on-CPU 或 off-CPU 剖析的调用图：
+//! 每个条目一行，带其 children 与 self 百分比，按权重从大到小排列，随后是其下方或
+//! 上方的调用。
+
+use pipa_core::callgraph::{CallGraph, CallGraphOptions, CallTree};
+
+/// Branches of a call graph below this percentage of the total are left
+/// out, as with `perf report -g graph,0.5`.
+/// 低于总量此百分比的调用图分支不显示，与 `perf report -g graph,0.5` 相同。
+pub const MIN_BRANCH_PERCENT: f64 = 0.5;
+
+/// Where call graphs start, under the entry's first column.
+/// 调用图的起始位置，位于条目第一列之下。
+const GRAPH_INDENT: &str = "            ";
+
+/// What the weights of a profile measure. / 剖析权重所度量的量。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+    /// Sampled event counts. / 采样的事件计数。
+    Events,
+    /// Blocked time in nanoseconds. / 阻塞时间（纳秒）。
+    Nanoseconds,
+}
+
+impl Weight {
+    fn format(self, value: u64) -> String {
+        match self {
+            Weight::Events => value.to_string(),
+            Weight::Nanoseconds => format!("{:.3} ms", value as f64 / 1e6),
+        }
+    }
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 { 0.0 } else { part as f64 * 100.0 / whole as f64 }
+}
+
+/// Formats a call graph built with `options`: a summary, the `breakdown`
+/// of its total if any, then every entry, with the tree of calls under it
+/// where the stacks had callchains.
+/// 格式化以 `options` 构建的调用图：摘要、总量的 `breakdown`（如有），然后是每个
+/// 条目；调用栈带有调用链时，条目下方附上调用树。
+pub fn format_report(
+    graph: &CallGraph,
+    options: &CallGraphOptions,
+    weight: Weight,
+    breakdown: &[(String, u64)],
+) -> Vec<String> {
+    let what = match weight {
+        Weight::Events => "Event count",
+        Weight::Nanoseconds => "Off-CPU time",
+    };
+    let mut out = vec![
+        format!("# {}: {} in {} entries", what, weight.format(graph.total), graph.entries.len()),
+        "#".to_string(),
+    ];
+    if !breakdown.is_empty() {
+        for (label, value) in breakdown {
+            out.push(format!(
+                "#   {:<12} {:>14} {:>7.2}%",
+                label,
+                weight.format(*value),
+                percent(*value, graph.total)
+            ));
+        }
+        out.push("#".to_string());
+    }
+
+    // Every column but the last is padded to its widest value.
+    // 除最后一列外，每列都按其最宽的取值补齐。
+    let headings: Vec<&str> = options.sort.iter().map(|key| key.heading()).collect();
+    let mut widths: Vec<usize> = headings.iter().map(|h| h.len()).collect();
+    for entry in &graph.entries {
+        for (width, value) in widths.iter_mut().zip(&entry.key) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let columns = |values: &[&str]| {
+        let last = values.len().saturating_sub(1);
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| if i == last { v.to_string() } else { format!("{:<1$}", v, widths[i]) })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    let dots: Vec<String> = widths.iter().map(|w| ".".repeat(*w)).collect();
+    let dots: Vec<&str> = dots.iter().map(String::as_str).collect();
+    if options.children {
+        out.push(format!("# {:>8} {:>8}  {}", "Children", "Self", columns(&headings)));
+        out.push(format!("# {:>8} {:>8}  {}", "........", "........", columns(&dots)));
+    } else {
+        out.push(format!("# {:>8}  {}", "Overhead", columns(&headings)));
+        out.push(format!("# {:>8}  {}", "........", columns(&dots)));
+    }
+    out.push("#".to_string());
+
+    let with_calls = graph.entries.iter().any(|entry| entry.graph.has_calls());
+    for entry in &graph.entries {
+        let key: Vec<&str> = entry.key.iter().map(String::as_str).collect();
+        let self_percent = percent(entry.self_weight, graph.total);
+        out.push(if options.children {
+            let children = percent(entry.children_weight, graph.total);
+            format!("  {:>7.2}% {:>7.2}%  {}", children, self_percent, columns(&key))
+        } else {
+            format!("  {:>7.2}%  {}", self_percent, columns(&key))
+        });
+        if with_calls {
+            format_graph(&entry.graph, graph.total, &mut out);
+        }
+    }
+    out
+}
+
+/// Draws a call tree the way `perf report` does: chains of single calls
+/// one per line, and at each fork the branches with their percentages of
+/// the total. / 以 `perf report` 的方式绘制调用树：单一调用链每行一个，
+/// 在每个分叉处列出各分支及其占总量的百分比。
+fn format_graph(tree: &CallTree, total: u64, out: &mut Vec<String>) {
+    out.push(format!("{}|", GRAPH_INDENT));
+    match tree.children.as_slice() {
+        [only] => {
+            out.push(format!("{}---{}", GRAPH_INDENT, only.name));
+            format_chain(only, total, &format!("{}   ", GRAPH_INDENT), out);
+        }
+        children => format_branches(children, total, GRAPH_INDENT, out),
+    }
+    out.push(String::new());
+}
+
+/// Follows the single calls below `node`, which is already drawn, to the
+/// next fork. / 从已绘制的 `node` 沿单一调用向下直到下一个分叉。
+fn format_chain(node: &CallTree, total: u64, prefix: &str, out: &mut Vec<String>) {
+    let mut node = node;
+    while let [only] = node.children.as_slice() {
+        out.push(format!("{}{}", prefix, only.name));
+        node = only;
+    }
+    if node.children.iter().any(|child| percent(child.weight, total) >= MIN_BRANCH_PERCENT) {
+        out.push(format!("{}|", prefix));
+        format_branches(&node.children, total, prefix, out);
+    }
+}
+
+fn format_branches(children: &[CallTree], total: u64, prefix: &str, out: &mut Vec<String>) {
+    let shown: Vec<&CallTree> = children
+        .iter()
+        .filter(|child| percent(child.weight, total) >= MIN_BRANCH_PERCENT)
+        .collect();
+    for (i, child) in shown.iter().enumerate() {
+        let last = i + 1 == shown.len();
+        let stem = if last { " " } else { "|" };
+        let branch = format!("--{:.2}%--", percent(child.weight, total));
+        out.push(format!("{}{}{}{}", prefix, stem, branch, child.name));
+        let below = format!("{}{}{}", prefix, stem, " ".repeat(branch.len()));
+        format_chain(child, total, &below, out);
+        if !last {
+            out.push(format!("{}|", prefix));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_core::callgraph::{CallOrder, Frame, SortKey};
+    use pipa_core::profile::{StackKey, StackProfile};
+
+    fn key(comm: &str, label: Option<&str>, frames: &[u64]) -> StackKey {
+        StackKey {
+            comm: comm.into(),
+            pid: 1,
+            label: label.map(String::from),
+            frames: frames.to_vec(),
+        }
+    }
+
+    fn name(_pid: u32, addr: u64) -> Vec<Frame> {
+        let symbol = match addr {
+            0x10 => "leaf",
+            0x20 => "work",
+            0x30 => "main",
+            0x40 => "lock",
+            _ => return vec![Frame::hex(addr)],
+        };
+        vec![Frame { symbol: symbol.into(), dso: Some("/bin/app".into()), ..Default::default() }]
+    }
+
+    fn report(profile: &StackProfile, options: &CallGraphOptions, weight: Weight) -> Vec<String> {
+        let graph = CallGraph::build(profile, options, &mut name);
+        format_report(&graph, options, weight, &[])
+    }
+
+    #[test]
+    fn test_format_report() {
+        let mut profile = StackProfile::default();
+        profile.add(key("app", None, &[0x10, 0x20, 0x30]), 60);
+        profile.add(key("app", None, &[0x20, 0x30]), 30);
+        profile.add(key("app", None, &[0x40, 0x30]), 10);
+        let lines = report(&profile, &CallGraphOptions::default(), Weight::Events);
+        assert_eq!(
+            lines[..5],
+            [
+                "# Event count: 100 in 4 entries",
+                "#",
+                "# Children     Self  Command  Shared Object  Symbol",
+                "# ........ ........  .......  .............  ........",
+                "#",
+            ]
+        );
+        assert_eq!(lines[5], "   100.00%    0.00%  app      app            [.] main");
+        // What `main` calls, forking after `main`. / `main` 调用的函数，在 `main` 之后分叉。
+        assert_eq!(
+            lines[6..15],
+            [
+                "            |",
+                "            ---main",
+                "               |",
+                "               |--90.00%--work",
+                "               |          leaf",
+                "               |",
+                "                --10.00%--lock",
+                "",
+                "    90.00%   30.00%  app      app            [.] work",
+            ]
+        );
+
+        // Without children: entries by self, with the callers of each.
+        // 不启用 children：按 self 排序的条目，各自附上调用者。
+        let options = CallGraphOptions {
+            sort: vec![SortKey::Symbol],
+            children: false,
+            order: CallOrder::Callee,
+        };
+        let lines = report(&profile, &options, Weight::Events);
+        assert_eq!(lines[2], "# Overhead  Symbol");
+        assert_eq!(lines[5], "    60.00%  [.] leaf");
+        assert_eq!(
+            lines[6..10],
+            ["            |", "            ---leaf", "               work", "               main"]
+        );
+        assert_eq!(
+            lines[11..16],
+            [
+                "    30.00%  [.] work",
+                "            |",
+                "            ---work",
+                "               main",
+                ""
+            ]
+        );
+        assert_eq!(lines.len(), 21);
+    }
+
+    #[test]
+    fn test_format_report_without_callchains() {
+        let mut profile = StackProfile::default();
+        profile.add(key("app", Some("[lock]"), &[0x10]), 3_000_000);
+        profile.add(key("app", Some("[sleep]"), &[0x50]), 1_000_000);
+        let options = CallGraphOptions { children: false, ..Default::default() };
+        let graph = CallGraph::build(&profile, &options, &mut name);
+        let breakdown = [("lock".to_string(), 3_000_000), ("sleep".to_string(), 1_000_000)];
+        let lines = format_report(&graph, &options, Weight::Nanoseconds, &breakdown);
+        assert_eq!(lines[0], "# Off-CPU time: 4.000 ms in 2 entries");
+        assert_eq!(
+            lines[2].split_whitespace().collect::<Vec<_>>(),
+            ["#", "lock", "3.000", "ms", "75.00%"]
+        );
+        assert_eq!(lines[8], "    75.00%  app      app            [.] leaf");
+        assert_eq!(lines[9], "            |");
+        assert_eq!(lines[10], "            ---[lock]");
+
+        // A single frame per stack draws no call graph.
+        // 每个调用栈只有一帧时不绘制调用图。
+        let mut profile = StackProfile::default();
+        profile.add(key("ls", None, &[0x50]), 7);
+        profile.add(key("ls", None, &[0x10]), 1);
+        let lines = report(&profile, &CallGraphOptions::default(), Weight::Events);
+        assert_eq!(
+            lines[5..],
+            [
+                "    87.50%   87.50%  ls       [unknown]      [.] 0x50",
+                "    12.50%   12.50%  ls       app            [.] leaf",
+            ]
+        );
+    }
+}
@@ -0,0 +1,818 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa sar`: records system statistics to a capture file with
+//! `SystemSampler` and replays them as tables with the columns of sysstat's
+//! `sar -u`, `-r`, `-d`, `-n DEV` and `-q`.
+//!
+//! `pipa sar`：使用 `SystemSampler` 将系统统计信息记录到捕获文件，
+//! 并以 sysstat 的 `sar -u`、`-r`、`-d`、`-n DEV` 和 `-q` 的列格式回放为表格。
+
+use anyhow::{Result, bail};
+use pipa_collector::collector::{Metric, MetricKind, Record, Sample};
+use pipa_collector::sampler::{Capture, SystemSnapshot};
+use pipa_collector::system_stats::{Snapshot, SnapshotDelta};
+use std::time::Duration;
+
+/// The report sections, named after the `sar` flag they replace.
+/// 报告的各个部分，以其替代的 `sar` 参数命名。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Section {
+    /// `sar -u`
+    Cpu,
+    /// `sar -r`
+    Mem,
+    /// `sar -d -p`
+    Disk,
+    /// `sar -n DEV`
+    Net,
+    /// `sar -q`
+    Load,
+    /// A collector recorded with `--collector`, shown with its schema as
+    /// columns. / 通过 `--collector` 记录的采集器，以其模式作为列显示。
+    Registered(String),
+}
+
+impl Section {
+    /// Name of the registry collector the section is built from.
+    /// 该部分所依据的注册表采集器名称。
+    pub fn collector(&self) -> &str {
+        match self {
+            Section::Cpu => "cpu",
+            Section::Mem => "memory",
+            Section::Disk => "disk",
+            Section::Net => "net",
+            Section::Load => "load",
+            Section::Registered(name) => name,
+        }
+    }
+}
+
+/// Collectors that `sar record` always records, in addition to those given
+/// with `--collector`.
+/// `sar record` 始终记录的采集器，`--collector` 指定的采集器会额外记录。
+pub const RECORDED_COLLECTORS: [&str; 6] = ["cpu", "memory", "load", "vm", "disk", "net"];
+
+/// A wall-clock time in the local time zone. / 本地时区的挂钟时间。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalTime {
+    /// Date as printed in the report header (`MM/DD/YYYY`).
+    /// 报告头中打印的日期 (`MM/DD/YYYY`)。
+    pub date: String,
+    /// Seconds since local midnight. / 自本地午夜以来的秒数。
+    pub seconds: u32,
+}
+
+impl LocalTime {
+    /// Formats the time of day as `HH:MM:SS`. / 将一天中的时刻格式化为 `HH:MM:SS`。
+    pub fn clock(&self) -> String {
+        let s = self.seconds;
+        format!("{:02}:{:02}:{:02}", s / 3600, s / 60 % 60, s % 60)
+    }
+}
+
+/// Converts seconds since the Unix epoch to local time with `localtime_r`.
+/// 使用 `localtime_r` 将自 Unix 纪元以来的秒数转换为本地时间。
+#[cfg(not(tarpaulin_include))]
+pub fn local_time(epoch: Duration) -> LocalTime {
+    let secs = epoch.as_secs() as libc::time_t;
+    // SAFETY: `tm` is plain data and `localtime_r` only writes into it.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::localtime_r(&secs, &mut tm);
+    }
+    LocalTime {
+        date: format!("{:02}/{:02}/{}", tm.tm_mon + 1, tm.tm_mday, tm.tm_year + 1900),
+        seconds: (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as u32,
+    }
+}
+
+/// Parses `HH:MM` or `HH:MM:SS` into seconds since midnight, as accepted by
+/// `sar -s` and `sar -e`.
+/// 将 `HH:MM` 或 `HH:MM:SS` 解析为自午夜以来的秒数，与 `sar -s`/`sar -e` 的格式一致。
+pub fn parse_time_of_day(value: &str) -> Result<u32> {
+    let parts: Vec<&str> = value.split(':').collect();
+    let fields = match parts.as_slice() {
+        [h, m] => [*h, *m, "0"],
+        [h, m, s] => [*h, *m, *s],
+        _ => bail!("Invalid time `{}`: expected HH:MM or HH:MM:SS", value),
+    };
+    let mut numbers = [0u32; 3];
+    for (number, field) in numbers.iter_mut().zip(fields) {
+        *number = field
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid time `{}`: expected HH:MM or HH:MM:SS", value))?;
+    }
+    let [h, m, s] = numbers;
+    if h > 23 || m > 59 || s > 59 {
+        bail!("Invalid time `{}`: out of range", value);
+    }
+    Ok(h * 3600 + m * 60 + s)
+}
+
+/// One output table: a header line followed by interval rows and averages.
+/// 一张输出表格：表头行，后跟各区间的行和平均值行。
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<(String, Vec<String>)>,
+}
+
+impl Table {
+    fn new(columns: &[&str], rows: Vec<(String, Vec<String>)>) -> Self {
+        Table { columns: columns.iter().map(|c| c.to_string()).collect(), rows }
+    }
+
+    fn render(&self, first_time: &str, out: &mut Vec<String>) {
+        let line = |time: &str, cells: &[String]| {
+            let mut line = format!("{:<11}", time);
+            for cell in cells {
+                line.push_str(&format!(" {:>9}", cell));
+            }
+            line
+        };
+        out.push(line(first_time, &self.columns));
+        for (time, cells) in &self.rows {
+            out.push(line(time, cells));
+        }
+    }
+}
+
+/// Formats a value with two decimals, like sar. / 像 sar 一样以两位小数格式化数值。
+fn f2(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+/// Percentage of `part` in `whole`, or 0 when `whole` is 0.
+/// `part` 占 `whole` 的百分比；`whole` 为 0 时返回 0。
+fn percent(part: f64, whole: f64) -> f64 {
+    if whole > 0.0 { part * 100.0 / whole } else { 0.0 }
+}
+
+/// One record of a collector, read by metric name. Metrics missing from the
+/// schema read as 0, e.g. in captures from a collector that lacks them.
+/// 按指标名称读取的采集器记录。模式中不存在的指标读为 0，例如采集器缺少这些指标时。
+#[derive(Clone, Copy)]
+struct Metrics<'a> {
+    schema: &'a [Metric],
+    record: &'a Record,
+}
+
+impl Metrics<'_> {
+    fn get(&self, name: &str) -> f64 {
+        self.record.value(self.schema, name).unwrap_or(0.0)
+    }
+}
+
+/// `sar -u` columns for one interval. Like sar, `%user` and `%nice` exclude
+/// guest time and `%system` includes hard and soft interrupt time.
+/// 单个区间的 `sar -u` 列。与 sar 一致，`%user` 和 `%nice` 不含 guest 时间，
+/// `%system` 包含硬中断和软中断时间。
+fn cpu_cells(d: Metrics) -> Vec<String> {
+    // `guest` and `guest_nice` are already included in `user` and `nice`.
+    let total: f64 = ["user", "nice", "system", "idle", "iowait", "irq", "softirq", "steal"]
+        .iter()
+        .map(|name| d.get(name))
+        .sum();
+    vec![
+        "all".to_string(),
+        f2(percent((d.get("user") - d.get("guest")).max(0.0), total)),
+        f2(percent((d.get("nice") - d.get("guest_nice")).max(0.0), total)),
+        f2(percent(d.get("system") + d.get("irq") + d.get("softirq"), total)),
+        f2(percent(d.get("iowait"), total)),
+        f2(percent(d.get("steal"), total)),
+        f2(percent(d.get("idle"), total)),
+    ]
+}
+
+/// `sar -r` gauges for one reading, as numbers so they can be averaged.
+/// 单次读数的 `sar -r` 瞬时值，以数值形式保存以便求平均。
+fn mem_values(m: Metrics) -> [f64; 11] {
+    let total = m.get("total");
+    let used =
+        (total - m.get("free") - m.get("buffers") - m.get("cached") - m.get("slab")).max(0.0);
+    [
+        m.get("free"),
+        m.get("available"),
+        used,
+        percent(used, total),
+        m.get("buffers"),
+        m.get("cached"),
+        m.get("committed_as"),
+        percent(m.get("committed_as"), total + m.get("swap_total")),
+        m.get("active"),
+        m.get("inactive"),
+        m.get("dirty"),
+    ]
+}
+
+/// Formats `sar -r` values: kB columns as integers, percentages with two
+/// decimals. / 格式化 `sar -r` 的值：kB 列为整数，百分比列保留两位小数。
+fn mem_cells(values: &[f64; 11]) -> Vec<String> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| if i == 3 || i == 7 { f2(*v) } else { format!("{:.0}", v) })
+        .collect()
+}
+
+/// `sar -d` columns for one device over one interval.
+/// 单个设备在单个区间内的 `sar -d` 列。
+fn disk_cells(elapsed: Duration, d: Metrics) -> Vec<String> {
+    let (secs, elapsed_ms) = (elapsed.as_secs_f64(), elapsed.as_secs_f64() * 1000.0);
+    let ios = d.get("reads") + d.get("writes") + d.get("discards");
+    let sectors = d.get("sectors_read") + d.get("sectors_written") + d.get("sectors_discarded");
+    let per_second = |value: f64| if secs > 0.0 { value / secs } else { 0.0 };
+    let per_io = |value: f64| if ios > 0.0 { value / ios } else { 0.0 };
+    let per_ms = |value: f64| if elapsed_ms > 0.0 { value / elapsed_ms } else { 0.0 };
+    vec![
+        d.record.instance.clone(),
+        f2(per_second(ios)),
+        f2(per_second(d.get("sectors_read")) / 2.0),
+        f2(per_second(d.get("sectors_written")) / 2.0),
+        f2(per_second(d.get("sectors_discarded")) / 2.0),
+        f2(per_io(sectors) / 2.0),
+        f2(per_ms(d.get("weighted_io_ms"))),
+        f2(per_io(d.get("read_ms") + d.get("write_ms") + d.get("discard_ms"))),
+        f2((per_ms(d.get("io_ms")) * 100.0).min(100.0)),
+    ]
+}
+
+/// `sar -n DEV` columns for one interface over one interval. `%ifutil`
+/// assumes a full-duplex link and is 0 when the speed is unknown.
+/// 单个接口在单个区间内的 `sar -n DEV` 列。`%ifutil` 假定链路为全双工，
+/// 速率未知时为 0。
+fn net_cells(elapsed: Duration, d: Metrics) -> Vec<String> {
+    let secs = elapsed.as_secs_f64();
+    let per_second = |value: f64| if secs > 0.0 { value / secs } else { 0.0 };
+    let speed = d.get("speed_mbps");
+    let ifutil = if speed > 0.0 && secs > 0.0 {
+        let bits = d.get("rx_bytes").max(d.get("tx_bytes")) * 8.0;
+        (bits / secs / (speed * 1e6) * 100.0).min(100.0)
+    } else {
+        0.0
+    };
+    vec![
+        d.record.instance.clone(),
+        f2(per_second(d.get("rx_packets"))),
+        f2(per_second(d.get("tx_packets"))),
+        f2(per_second(d.get("rx_bytes")) / 1024.0),
+        f2(per_second(d.get("tx_bytes")) / 1024.0),
+        f2(per_second(d.get("rx_compressed"))),
+        f2(per_second(d.get("tx_compressed"))),
+        f2(per_second(d.get("rx_multicast"))),
+        f2(ifutil),
+    ]
+}
+
+/// Devices that never did any I/O since boot are skipped, as `iostat` does.
+/// 与 `iostat` 一致，跳过自启动以来从未产生 I/O 的设备。
+fn disk_reported(d: Metrics) -> bool {
+    d.get("reads") + d.get("writes") + d.get("discards") > 0.0
+}
+
+/// Computes the per-device deltas between two readings of a multi-instance
+/// collector, keeping the devices of `later` for which `reported` holds.
+/// 计算多实例采集器两次读数之间各设备的增量，只保留 `later` 中满足 `reported` 的设备。
+fn device_deltas(
+    schema: &[Metric],
+    earlier: &Snapshot<&Sample>,
+    later: &Snapshot<&Sample>,
+    reported: fn(Metrics) -> bool,
+) -> (Duration, Vec<Record>) {
+    let elapsed = later.timestamp.saturating_sub(earlier.timestamp);
+    let mut delta = later.stats.delta(earlier.stats, schema);
+    delta.records.retain(|record| {
+        let raw = later.stats.get(&record.instance).unwrap_or(record);
+        reported(Metrics { schema, record: raw })
+    });
+    (elapsed, delta.records)
+}
+
+/// Builds the rows of a per-device table: one row per device and interval,
+/// then one average row per device over the whole range.
+/// 构建按设备划分的表格行：每个设备每个区间一行，最后每个设备一行整段范围的平均值。
+fn device_rows(
+    schema: &[Metric],
+    samples: &[Snapshot<&Sample>],
+    time_of: &dyn Fn(Duration) -> String,
+    reported: fn(Metrics) -> bool,
+    cells: fn(Duration, Metrics) -> Vec<String>,
+) -> Vec<(String, Vec<String>)> {
+    let mut rows = Vec::new();
+    let mut add_rows = |time: String, earlier, later| {
+        let (elapsed, records) = device_deltas(schema, earlier, later, reported);
+        for record in &records {
+            rows.push((time.clone(), cells(elapsed, Metrics { schema, record })));
+        }
+    };
+    for w in samples.windows(2) {
+        add_rows(time_of(w[1].timestamp), &w[0], &w[1]);
+    }
+    if let (Some(first), Some(last)) = (samples.first(), samples.last()) {
+        add_rows("Average:".to_string(), first, last);
+    }
+    rows
+}
+
+/// The schema a capture recorded for collector `name`.
+/// 捕获文件为采集器 `name` 记录的模式。
+fn schema<'a>(capture: &'a Capture, name: &str) -> &'a [Metric] {
+    capture.header.schemas.get(name).map_or(&[], Vec::as_slice)
+}
+
+/// Builds the table of a registry collector: counters are shown as
+/// per-second rates (suffixed `/s`), gauges as recorded. Returns `None` when
+/// the capture has no schema for `name`.
+///
+/// 构建注册表采集器的表格：计数器显示为每秒速率（列名带 `/s` 后缀），
+/// 瞬时值按记录值显示。若捕获文件中没有 `name` 的模式则返回 `None`。
+fn registered_table(
+    name: &str,
+    capture: &Capture,
+    selected: &[&Snapshot<SystemSnapshot>],
+    time_of: &dyn Fn(Duration) -> String,
+) -> Option<Table> {
+    let schema = capture.header.schemas.get(name)?;
+    let samples = series(selected, name);
+    let named = samples.iter().any(|s| s.stats.records.iter().any(|r| !r.instance.is_empty()));
+
+    let mut rows = Vec::new();
+    let mut sums: Vec<(String, Vec<f64>, usize)> = Vec::new();
+    for w in samples.windows(2) {
+        let delta = SnapshotDelta {
+            elapsed: w[1].timestamp.saturating_sub(w[0].timestamp),
+            delta: w[1].stats.delta(w[0].stats, schema),
+        };
+        for record in delta.rates(schema).records {
+            let mut cells: Vec<String> = record.values.iter().map(|v| f2(*v)).collect();
+            if named {
+                cells.insert(0, record.instance.clone());
+            }
+            rows.push((time_of(w[1].timestamp), cells));
+            match sums.iter_mut().find(|(instance, _, _)| *instance == record.instance) {
+                Some((_, sum, n)) => {
+                    sum.iter_mut().zip(&record.values).for_each(|(a, v)| *a += v);
+                    *n += 1;
+                }
+                None => sums.push((record.instance, record.values, 1)),
+            }
+        }
+    }
+    for (instance, sum, n) in sums {
+        let mut cells: Vec<String> = sum.iter().map(|v| f2(v / n as f64)).collect();
+        if named {
+            cells.insert(0, instance);
+        }
+        rows.push(("Average:".to_string(), cells));
+    }
+
+    let mut columns: Vec<String> = schema
+        .iter()
+        .map(|m| match m.kind {
+            MetricKind::Counter => format!("{}/s", m.name),
+            MetricKind::Gauge => m.name.clone(),
+        })
+        .collect();
+    if named {
+        columns.insert(0, "INSTANCE".to_string());
+    }
+    Some(Table { columns, rows })
+}
+
+/// Extracts the samples of collector `name` as borrowed snapshots.
+/// 将采集器 `name` 的读数提取为借用的快照。
+fn series<'a>(snapshots: &[&'a Snapshot<SystemSnapshot>], name: &str) -> Vec<Snapshot<&'a Sample>> {
+    snapshots
+        .iter()
+        .filter_map(|s| s.stats.get(name).map(|stats| Snapshot { timestamp: s.timestamp, stats }))
+        .collect()
+}
+
+/// The first record of a single-instance sample. / 单实例读数的第一条记录。
+fn single(sample: &Sample) -> Option<&Record> {
+    sample.records.first()
+}
+
+/// Renders the `sar` report for `sections` from the snapshots whose local
+/// time of day lies within `[start, end]`. `to_local` maps a wall-clock time
+/// to local time; it is a parameter so that tests do not depend on the time
+/// zone of the machine.
+///
+/// 根据本地时刻位于 `[start, end]` 范围内的快照渲染 `sections` 对应的 `sar` 报告。
+/// `to_local` 将挂钟时间映射为本地时间；将其作为参数是为了使测试不依赖机器的时区。
+pub fn render_report<F: Fn(Duration) -> LocalTime>(
+    capture: &Capture,
+    sections: &[Section],
+    start: Option<u32>,
+    end: Option<u32>,
+    to_local: F,
+) -> Vec<String> {
+    let header = &capture.header;
+    let clock = |s: &Snapshot<SystemSnapshot>| to_local(header.to_realtime(s.timestamp));
+    let selected: Vec<&Snapshot<SystemSnapshot>> = capture
+        .snapshots
+        .iter()
+        .filter(|s| {
+            let t = clock(s).seconds;
+            start.is_none_or(|start| t >= start) && end.is_none_or(|end| t <= end)
+        })
+        .collect();
+
+    let date = selected.first().map_or_else(|| to_local(header.realtime).date, |s| clock(s).date);
+    let mut out = vec![
+        format!(
+            "Linux {} ({}) \t{} \t_{}_\t({} CPU)",
+            header.kernel_release, header.hostname, date, header.arch, header.cpus
+        ),
+        String::new(),
+    ];
+    if selected.len() < 2 {
+        out.push("No data in the selected time range.".to_string());
+        return out;
+    }
+    let first_time = clock(selected[0]).clock();
+    let time_of = |timestamp: Duration| to_local(header.to_realtime(timestamp)).clock();
+
+    for section in sections {
+        let schema = schema(capture, section.collector());
+        let samples = series(&selected, section.collector());
+        let table = match section {
+            Section::Cpu => {
+                let cells = |later: &Sample, earlier: &Sample| {
+                    let delta = later.delta(earlier, schema);
+                    single(&delta).map(|record| cpu_cells(Metrics { schema, record }))
+                };
+                let mut rows: Vec<_> = samples
+                    .windows(2)
+                    .filter_map(|w| Some((time_of(w[1].timestamp), cells(w[1].stats, w[0].stats)?)))
+                    .collect();
+                if let Some(avg) = samples
+                    .first()
+                    .zip(samples.last())
+                    .and_then(|(first, last)| cells(last.stats, first.stats))
+                {
+                    rows.push(("Average:".to_string(), avg));
+                }
+                Table::new(
+                    &["CPU", "%user", "%nice", "%system", "%iowait", "%steal", "%idle"],
+                    rows,
+                )
+            }
+            Section::Mem => {
+                let readings: Vec<_> = samples
+                    .iter()
+                    .skip(1)
+                    .filter_map(|m| {
+                        let record = single(m.stats)?;
+                        Some((m.timestamp, mem_values(Metrics { schema, record })))
+                    })
+                    .collect();
+                let mut rows: Vec<_> =
+                    readings.iter().map(|(t, v)| (time_of(*t), mem_cells(v))).collect();
+                if !readings.is_empty() {
+                    let mut avg = [0.0; 11];
+                    for (_, v) in &readings {
+                        avg.iter_mut().zip(v).for_each(|(a, x)| *a += x / readings.len() as f64);
+                    }
+                    rows.push(("Average:".to_string(), mem_cells(&avg)));
+                }
+                Table::new(
+                    &[
+                        "kbmemfree",
+                        "kbavail",
+                        "kbmemused",
+                        "%memused",
+                        "kbbuffers",
+                        "kbcached",
+                        "kbcommit",
+                        "%commit",
+                        "kbactive",
+                        "kbinact",
+                        "kbdirty",
+                    ],
+                    rows,
+                )
+            }
+            Section::Disk => Table::new(
+                &["DEV", "tps", "rkB/s", "wkB/s", "dkB/s", "areq-sz", "aqu-sz", "await", "%util"],
+                device_rows(schema, &samples, &time_of, disk_reported, disk_cells),
+            ),
+            Section::Net => Table::new(
+                &[
+                    "IFACE", "rxpck/s", "txpck/s", "rxkB/s", "txkB/s", "rxcmp/s", "txcmp/s",
+                    "rxmcst/s", "%ifutil",
+                ],
+                device_rows(schema, &samples, &time_of, |_| true, net_cells),
+            ),
+            Section::Load => {
+                let readings: Vec<(Duration, [f64; 6])> = samples
+                    .iter()
+                    .skip(1)
+                    .filter_map(|l| {
+                        let l_metrics = Metrics { schema, record: single(l.stats)? };
+                        let values = [
+                            "runnable",
+                            "total_tasks",
+                            "load1",
+                            "load5",
+                            "load15",
+                            "procs_blocked",
+                        ]
+                        .map(|name| l_metrics.get(name));
+                        Some((l.timestamp, values))
+                    })
+                    .collect();
+                let cells = |v: &[f64; 6]| {
+                    v.iter()
+                        .enumerate()
+                        .map(
+                            |(i, x)| if (2..5).contains(&i) { f2(*x) } else { format!("{:.0}", x) },
+                        )
+                        .collect::<Vec<_>>()
+                };
+                let mut rows: Vec<_> =
+                    readings.iter().map(|(t, v)| (time_of(*t), cells(v))).collect();
+                if !readings.is_empty() {
+                    let mut avg = [0.0; 6];
+                    for (_, v) in &readings {
+                        avg.iter_mut().zip(v).for_each(|(a, x)| *a += x / readings.len() as f64);
+                    }
+                    rows.push(("Average:".to_string(), cells(&avg)));
+                }
+                Table::new(
+                    &["runq-sz", "plist-sz", "ldavg-1", "ldavg-5", "ldavg-15", "blocked"],
+                    rows,
+                )
+            }
+            Section::Registered(name) => {
+                match registered_table(name, capture, &selected, &time_of) {
+                    Some(table) => table,
+                    None => {
+                        out.push(format!("No data for collector `{}`.", name));
+                        out.push(String::new());
+                        continue;
+                    }
+                }
+            }
+        };
+        table.render(&first_time, &mut out);
+        out.push(String::new());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::collector::CollectorRegistry;
+    use pipa_collector::sampler::{CAPTURE_FORMAT_VERSION, CaptureHeader};
+    use pipa_collector::sysroot::SysRoot;
+    use std::collections::BTreeMap;
+
+    /// Maps wall-clock time to UTC so that tests are time-zone independent.
+    fn utc(epoch: Duration) -> LocalTime {
+        LocalTime { date: "10/18/2026".to_string(), seconds: (epoch.as_secs() % 86400) as u32 }
+    }
+
+    /// 10:00:00 UTC on some day.
+    const TEN_AM: u64 = 1_790_000_000 - 1_790_000_000 % 86400 + 10 * 3600;
+
+    /// Builds a record of `schema` from named values; other metrics are 0.
+    fn record(schema: &[Metric], instance: &str, values: &[(&str, f64)]) -> Record {
+        let mut record = Record { instance: instance.to_string(), values: vec![0.0; schema.len()] };
+        for (name, value) in values {
+            let index = schema.iter().position(|m| m.name == *name).unwrap();
+            record.values[index] = *value;
+        }
+        record
+    }
+
+    /// Builds a capture of the built-in collectors with one snapshot per
+    /// second starting at 10:00:00.
+    fn capture(samples: usize) -> Capture {
+        let registry = CollectorRegistry::builtin(&SysRoot::default());
+        let schemas: BTreeMap<String, Vec<Metric>> = RECORDED_COLLECTORS
+            .iter()
+            .map(|name| (name.to_string(), registry.get(name).unwrap().schema().to_vec()))
+            .collect();
+        let single = |name: &str, values: &[(&str, f64)]| {
+            (name.to_string(), Sample { records: vec![record(&schemas[name], "", values)] })
+        };
+        let snapshots = (0..samples as u64)
+            .map(|i| {
+                let n = i as f64;
+                let disks = vec![
+                    record(
+                        &schemas["disk"],
+                        "sda",
+                        &[
+                            ("reads", 100.0 * n + 1.0),
+                            ("writes", 100.0 * n),
+                            ("sectors_read", 800.0 * n),
+                            ("sectors_written", 1600.0 * n),
+                            ("read_ms", 50.0 * n),
+                            ("write_ms", 150.0 * n),
+                            ("io_ms", 250.0 * n),
+                            ("weighted_io_ms", 500.0 * n),
+                        ],
+                    ),
+                    record(&schemas["disk"], "loop0", &[]),
+                ];
+                let net = vec![record(
+                    &schemas["net"],
+                    "eth0",
+                    &[
+                        ("rx_packets", 10.0 * n),
+                        ("tx_packets", 5.0 * n),
+                        ("rx_bytes", 1_250_000.0 * n),
+                        ("tx_bytes", 2048.0 * n),
+                        ("speed_mbps", 100.0),
+                    ],
+                )];
+                let samples = BTreeMap::from([
+                    single(
+                        "cpu",
+                        &[
+                            ("user", 100.0 * n),
+                            ("system", 50.0 * n),
+                            ("idle", 800.0 * n),
+                            ("iowait", 50.0 * n),
+                        ],
+                    ),
+                    single(
+                        "memory",
+                        &[
+                            ("total", 1000.0),
+                            ("free", 100.0 + 10.0 * n),
+                            ("available", 600.0),
+                            ("buffers", 50.0),
+                            ("cached", 200.0),
+                            ("slab", 50.0),
+                            ("committed_as", 1500.0),
+                            ("swap_total", 1000.0),
+                        ],
+                    ),
+                    single("load", &[("load1", 1.5), ("runnable", 2.0), ("total_tasks", 300.0)]),
+                    ("disk".to_string(), Sample { records: disks }),
+                    ("net".to_string(), Sample { records: net }),
+                ]);
+                Snapshot {
+                    timestamp: Duration::from_secs(1000 + i),
+                    stats: SystemSnapshot { samples },
+                }
+            })
+            .collect();
+        Capture {
+            header: CaptureHeader {
+                version: CAPTURE_FORMAT_VERSION,
+                hostname: "web01".to_string(),
+                kernel_release: "6.8.0".to_string(),
+                arch: "x86_64".to_string(),
+                cpus: 8,
+                interval: Duration::from_secs(1),
+                schemas,
+                monotonic: Duration::from_secs(1000),
+                realtime: Duration::from_secs(TEN_AM),
+            },
+            snapshots,
+        }
+    }
+
+    fn columns(line: &str) -> Vec<&str> {
+        line.split_whitespace().collect()
+    }
+
+    #[test]
+    fn test_parse_time_of_day() {
+        assert_eq!(parse_time_of_day("10:00").unwrap(), 36000);
+        assert_eq!(parse_time_of_day("23:59:59").unwrap(), 86399);
+        assert!(parse_time_of_day("24:00").is_err());
+        assert!(parse_time_of_day("10").is_err());
+        assert!(parse_time_of_day("ab:cd").is_err());
+        assert!(parse_time_of_day("10:00:00:00").is_err());
+    }
+
+    #[test]
+    fn test_report_cpu() {
+        let out = render_report(&capture(3), &[Section::Cpu], None, None, utc);
+        assert_eq!(out[0], "Linux 6.8.0 (web01) \t10/18/2026 \t_x86_64_\t(8 CPU)");
+        assert_eq!(
+            columns(&out[2]),
+            ["10:00:00", "CPU", "%user", "%nice", "%system", "%iowait", "%steal", "%idle"]
+        );
+        assert_eq!(
+            columns(&out[3]),
+            ["10:00:01", "all", "10.00", "0.00", "5.00", "5.00", "0.00", "80.00"]
+        );
+        assert_eq!(columns(&out[4])[0], "10:00:02");
+        assert_eq!(
+            columns(&out[5]),
+            ["Average:", "all", "10.00", "0.00", "5.00", "5.00", "0.00", "80.00"]
+        );
+        assert_eq!(out[6], "");
+    }
+
+    #[test]
+    fn test_report_mem_and_load() {
+        let out = render_report(&capture(3), &[Section::Mem, Section::Load], None, None, utc);
+        assert_eq!(columns(&out[2])[1..4], ["kbmemfree", "kbavail", "kbmemused"]);
+        // used = 1000 - 110 - 50 - 200 - 50; %commit = 1500 / (1000 + 1000).
+        assert_eq!(
+            columns(&out[3]),
+            ["10:00:01", "110", "600", "590", "59.00", "50", "200", "1500", "75.00", "0", "0", "0"]
+        );
+        assert_eq!(columns(&out[5])[..4], ["Average:", "115", "600", "585"]);
+
+        let load = &out[7..];
+        assert_eq!(
+            columns(&load[0])[1..],
+            ["runq-sz", "plist-sz", "ldavg-1", "ldavg-5", "ldavg-15", "blocked"]
+        );
+        assert_eq!(columns(&load[1]), ["10:00:01", "2", "300", "1.50", "0.00", "0.00", "0"]);
+    }
+
+    #[test]
+    fn test_report_disk_and_net() {
+        let out = render_report(&capture(2), &[Section::Disk, Section::Net], None, None, utc);
+        // loop0 never did I/O and is skipped.
+        assert_eq!(
+            columns(&out[3]),
+            [
+                "10:00:01", "sda", "200.00", "400.00", "800.00", "0.00", "6.00", "0.50", "1.00",
+                "25.00"
+            ]
+        );
+        assert_eq!(columns(&out[4])[..2], ["Average:", "sda"]);
+
+        // 1,250,000 B/s = 10 Mbit/s on a 100 Mbit/s link.
+        assert_eq!(
+            columns(&out[7]),
+            [
+                "10:00:01", "eth0", "10.00", "5.00", "1220.70", "2.00", "0.00", "0.00", "0.00",
+                "10.00"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_registered_collector() {
+        use pipa_collector::collector::{Metric, Record, Sample};
+
+        let mut capture = capture(3);
+        let schema = vec![Metric::counter("faults", "events"), Metric::gauge("temp", "C")];
+        capture.header.schemas.insert("gpu".to_string(), schema);
+        for (i, snapshot) in capture.snapshots.iter_mut().enumerate() {
+            let record = Record {
+                instance: "gpu0".to_string(),
+                values: vec![10.0 * i as f64, 50.0 + i as f64],
+            };
+            snapshot.stats.samples.insert("gpu".to_string(), Sample { records: vec![record] });
+        }
+
+        let sections =
+            [Section::Registered("gpu".to_string()), Section::Registered("fpga".to_string())];
+        let out = render_report(&capture, &sections, None, None, utc);
+        assert_eq!(columns(&out[2]), ["10:00:00", "INSTANCE", "faults/s", "temp"]);
+        assert_eq!(columns(&out[3]), ["10:00:01", "gpu0", "10.00", "51.00"]);
+        assert_eq!(columns(&out[4]), ["10:00:02", "gpu0", "10.00", "52.00"]);
+        assert_eq!(columns(&out[5]), ["Average:", "gpu0", "10.00", "51.50"]);
+        assert_eq!(out[7], "No data for collector `fpga`.");
+    }
+
+    #[test]
+    fn test_report_time_range() {
+        let mut capture = capture(7200);
+        capture.snapshots.truncate(7200);
+        let out = render_report(
+            &capture,
+            &[Section::Cpu],
+            Some(parse_time_of_day("10:30").unwrap()),
+            Some(parse_time_of_day("10:30:03").unwrap()),
+            utc,
+        );
+        assert_eq!(columns(&out[2])[0], "10:30:00");
+        let times: Vec<&str> =
+            out[3..].iter().map(|l| columns(l).first().copied().unwrap_or("")).collect();
+        assert_eq!(times, ["10:30:01", "10:30:02", "10:30:03", "Average:", ""]);
+
+        let out = render_report(
+            &capture,
+            &[Section::Cpu],
+            Some(parse_time_of_day("12:00").unwrap()),
+            None,
+            utc,
+        );
+        assert_eq!(out[2], "No data in the selected time range.");
+    }
+}
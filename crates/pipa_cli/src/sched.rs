@@ -0,0 +1,177 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa sched`: prints the scheduler analysis of a record file as a
+//! `perf sched latency`-style task table or a per-CPU timeline.
+//!
+//! `pipa sched`：将记录文件的调度器分析结果打印为 `perf sched latency`
+//! 风格的任务表或按 CPU 的时间线。
+
+use pipa_core::sched::SchedAnalysis;
+
+fn ms(ns: u64) -> f64 {
+    ns as f64 / 1e6
+}
+
+/// Formats a timestamp as `seconds.microseconds`, as `pipa script` does.
+/// 与 `pipa script` 一样将时间戳格式化为 `秒.微秒`。
+fn seconds(ns: u64) -> String {
+    format!("{}.{:06}", ns / 1_000_000_000, ns % 1_000_000_000 / 1000)
+}
+
+/// Formats the per-task table, worst maximum delay first, with totals.
+/// 格式化按任务的表格（最大延迟最差者在前）及合计。
+pub fn format_latency(analysis: &SchedAnalysis) -> Vec<String> {
+    let rule = format!(" {}", "-".repeat(118));
+    let mut out = vec![
+        rule.clone(),
+        format!(
+            "  {:<24} | {:>13} | {:>8} | {:>11} | {:>12} | {:>12} | {:>16} | {:>10}",
+            "Task",
+            "Runtime ms",
+            "Switches",
+            "Wait ms",
+            "Avg delay ms",
+            "Max delay ms",
+            "Max delay at",
+            "Migrations"
+        ),
+        rule.clone(),
+    ];
+    let (mut runtime, mut switches) = (0, 0);
+    for task in analysis.by_max_delay() {
+        runtime += task.runtime;
+        switches += task.switches;
+        let at = match task.delays {
+            0 => "-".to_string(),
+            _ => format!("{} s", seconds(task.max_delay_at)),
+        };
+        out.push(format!(
+            "  {:<24} | {:>10.3} ms | {:>8} | {:>8.3} ms | {:>9.3} ms | {:>9.3} ms | {:>16} | {:>10}",
+            format!("{}:{}", task.comm, task.tid),
+            ms(task.runtime),
+            task.switches,
+            ms(task.wait),
+            ms(task.avg_delay().unwrap_or(0)),
+            ms(task.max_delay),
+            at,
+            task.migrations
+        ));
+    }
+    out.push(rule.clone());
+    out.push(format!("  {:<24} | {:>10.3} ms | {:>8} |", "TOTAL:", ms(runtime), switches));
+    out.push(rule);
+    out
+}
+
+/// Formats what ran on each CPU, optionally only on `cpus`, one line per
+/// stretch of time.
+/// 逐段格式化每个 CPU（可仅限 `cpus`）上运行的任务，每段一行。
+pub fn format_timeline(analysis: &SchedAnalysis, cpus: &[u32]) -> Vec<String> {
+    let mut out = Vec::new();
+    for (cpu, slices) in &analysis.timeline {
+        if !cpus.is_empty() && !cpus.contains(cpu) {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push(String::new());
+        }
+        out.push(format!("CPU {:03}", cpu));
+        out.push(format!("  {:>16} {:>16} {:>13}  {}", "start", "end", "duration", "task"));
+        for slice in slices {
+            let task = match slice.tid {
+                0 => "<idle>".to_string(),
+                tid => format!("{}:{}", slice.comm, tid),
+            };
+            out.push(format!(
+                "  {:>16} {:>16} {:>10.3} ms  {}",
+                seconds(slice.start),
+                seconds(slice.end),
+                ms(slice.end - slice.start),
+                task
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_core::sched::{SchedAnalyzer, SchedEvent, SchedEventKind};
+
+    fn analysis() -> SchedAnalysis {
+        let switch = |time, cpu, prev: (u32, &str, i64), next: (u32, &str)| SchedEvent {
+            time,
+            cpu,
+            kind: SchedEventKind::Switch {
+                prev_tid: prev.0,
+                prev_comm: prev.1.into(),
+                prev_state: prev.2,
+                next_tid: next.0,
+                next_comm: next.1.into(),
+            },
+        };
+        let mut analyzer = SchedAnalyzer::new();
+        for event in [
+            switch(1_000_000_000, 0, (0, "swapper/0", 0), (10, "app")),
+            SchedEvent {
+                time: 1_001_000_000,
+                cpu: 1,
+                kind: SchedEventKind::Wakeup { tid: 20, comm: "worker".into() },
+            },
+            switch(1_003_500_000, 0, (10, "app", 0), (20, "worker")),
+            switch(1_004_000_000, 0, (20, "worker", 1), (0, "swapper/0")),
+            switch(1_005_000_000, 1, (0, "swapper/1", 0), (10, "app")),
+        ] {
+            analyzer.event(&event);
+        }
+        analyzer.finish()
+    }
+
+    #[test]
+    fn test_format_latency() {
+        let lines = format_latency(&analysis());
+        assert!(lines[1].starts_with("  Task                     | "), "{}", lines[1]);
+        let cells = |line: &str| -> Vec<String> {
+            line.split('|').map(|cell| cell.trim().to_string()).collect()
+        };
+        assert_eq!(
+            cells(&lines[3]),
+            ["worker:20", "0.500 ms", "1", "2.500 ms", "2.500 ms", "2.500 ms", "1.003500 s", "0"]
+        );
+        assert_eq!(
+            cells(&lines[4]),
+            ["app:10", "3.500 ms", "2", "1.500 ms", "1.500 ms", "1.500 ms", "1.005000 s", "0"]
+        );
+        assert_eq!(cells(&lines[6]), ["TOTAL:", "4.000 ms", "3", ""]);
+    }
+
+    #[test]
+    fn test_format_timeline() {
+        let analysis = analysis();
+        let lines = format_timeline(&analysis, &[]);
+        assert_eq!(lines[0], "CPU 000");
+        let rows: Vec<Vec<&str>> =
+            lines[2..5].iter().map(|l| l.split_whitespace().collect()).collect();
+        assert_eq!(rows[0], ["1.000000", "1.003500", "3.500", "ms", "app:10"]);
+        assert_eq!(rows[1], ["1.003500", "1.004000", "0.500", "ms", "worker:20"]);
+        assert_eq!(rows[2], ["1.004000", "1.005000", "1.000", "ms", "<idle>"]);
+        assert_eq!(lines[6], "CPU 001");
+
+        let only = format_timeline(&analysis, &[1]);
+        assert_eq!(only[0], "CPU 001");
+        assert!(only.last().unwrap().ends_with("0.000 ms  app:10"));
+    }
+}
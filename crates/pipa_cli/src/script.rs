@@ -0,0 +1,119 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa script`: prints the samples of a `pipa record` file one per line,
+//! decoding tracepoint payloads with the formats stored in the file, like
+//! `perf script`.
+//!
+//! `pipa script`：像 `perf script` 一样逐行打印 `pipa record` 文件中的采样，
+//! 并使用文件中保存的格式解码跟踪点负载。
+
+use pipa_collector::perf_record::{RecordHeader, SampleRecord};
+
+/// Formats one sample as `comm tid [cpu] seconds: event: fields`, where
+/// `comm` is the task name last reported for the thread. Samples without a
+/// tracepoint payload show their `ip` and its `symbol`, if resolved.
+/// 将一个采样格式化为 `comm tid [cpu] seconds: event: fields`，
+/// 其中 `comm` 为该线程最近一次报告的任务名称。没有跟踪点负载的采样显示其 `ip`
+/// 以及已解析的 `symbol`。
+pub fn format_sample(
+    header: &RecordHeader,
+    comm: Option<&str>,
+    sample: &SampleRecord,
+    symbol: Option<&str>,
+) -> String {
+    let event = header.events.get(sample.event);
+    let name = event.map_or("?", |e| e.name.as_str());
+    let mut line = format!(
+        "{:>16} {:>7} [{:03}] {:>6}.{:06}: {}:",
+        comm.unwrap_or(":"),
+        sample.tid,
+        sample.cpu,
+        sample.time / 1_000_000_000,
+        sample.time % 1_000_000_000 / 1000,
+        name
+    );
+    match event.and_then(|e| e.format.as_ref()) {
+        Some(format) => match format.decode(&sample.raw) {
+            Ok(fields) => {
+                for (field, value) in fields {
+                    line.push_str(&format!(" {}={}", field, value));
+                }
+            }
+            Err(e) => line.push_str(&format!(" <{}>", e)),
+        },
+        None => {
+            line.push_str(&format!(" {:#x}", sample.ip));
+            if let Some(symbol) = symbol {
+                line.push_str(&format!(" {}", symbol));
+            }
+            line.push_str(&format!(" period={}", sample.period));
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::perf_record::RecordedEvent;
+    use pipa_collector::sysroot::SysRoot;
+    use pipa_collector::tracepoint::parse_format;
+
+    #[test]
+    fn test_format_sample() {
+        let format = parse_format(
+            "name: sched_process_exit\nID: 370\nformat:\n\
+             \tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;\n\n\
+             \tfield:char comm[4];\toffset:2;\tsize:4;\tsigned:0;\n\
+             \tfield:pid_t pid;\toffset:8;\tsize:4;\tsigned:1;\n",
+        )
+        .unwrap();
+        let header = RecordHeader::new(
+            &SysRoot::default(),
+            Vec::new(),
+            vec![
+                RecordedEvent { name: "sched:sched_process_exit".into(), format: Some(format) },
+                RecordedEvent { name: "cycles".into(), format: None },
+            ],
+        );
+        let mut raw = vec![0x72, 0x01, b'l', b's', 0, 0, 0, 0];
+        raw.extend_from_slice(&1234i32.to_ne_bytes());
+        let mut sample = SampleRecord {
+            tid: 1234,
+            cpu: 2,
+            time: 5_000_123_456,
+            ip: 0xffff,
+            period: 1,
+            raw,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_sample(&header, Some("ls"), &sample, None),
+            "              ls    1234 [002]      5.000123: sched:sched_process_exit: comm=ls pid=1234"
+        );
+
+        sample.raw.truncate(6);
+        let line = format_sample(&header, None, &sample, None);
+        assert!(line.starts_with("               :    1234"), "{}", line);
+        assert!(line.ends_with("too short for field `pid`>"), "{}", line);
+
+        sample.event = 1;
+        assert!(format_sample(&header, None, &sample, None).ends_with("cycles: 0xffff period=1"));
+        assert!(
+            format_sample(&header, None, &sample, Some("main+0x4 (/bin/ls)"))
+                .ends_with("cycles: 0xffff main+0x4 (/bin/ls) period=1")
+        );
+    }
+}
@@ -0,0 +1,255 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `pipa trace`: follows the syscalls of a workload through the
+//! `raw_syscalls` tracepoints, printing each call like `perf trace` or a
+//! per-syscall and per-thread summary like `strace -c` and `perf trace -s`.
+//!
+//! `pipa trace`：通过 `raw_syscalls` 跟踪点跟随负载的系统调用，像 `perf trace`
+//! 一样逐个打印调用，或像 `strace -c` 和 `perf trace -s` 一样输出按系统调用和
+//! 按线程的汇总。
+
+use anyhow::{Result, bail};
+use pipa_collector::perf_record::{RecordedEvent, SampleRecord};
+use pipa_collector::tracepoint::FieldValue;
+use pipa_core::syscall_summary::{
+    CompletedSyscall, SyscallStats, SyscallSummarizer, SyscallSummary, by_total_time,
+};
+use pipa_core::syscalls;
+use std::collections::BTreeMap;
+
+/// The tracepoints `pipa trace` records, enter first.
+/// `pipa trace` 记录的跟踪点，进入事件在前。
+pub const EVENTS: [&str; 2] = ["raw_syscalls:sys_enter", "raw_syscalls:sys_exit"];
+
+/// Reads an integer field of a `raw_syscalls` payload.
+/// 读取 `raw_syscalls` 负载中的整数字段。
+fn int_field(event: &RecordedEvent, name: &str, raw: &[u8]) -> Result<i64> {
+    let Some(format) = &event.format else {
+        bail!("{} has no tracepoint format", event.name);
+    };
+    let Some(field) = format.field(name) else {
+        bail!("{} has no field `{}`", event.name, name);
+    };
+    match format.decode_field(field, raw)? {
+        FieldValue::Int(v) => Ok(v),
+        FieldValue::Uint(v) => Ok(v as i64),
+        other => bail!("{}: unexpected value {} for `{}`", event.name, other, name),
+    }
+}
+
+/// Feeds one sample of [`EVENTS`] into `summarizer`, returning the call it
+/// completes, if any. `events` are the recorded events, in [`EVENTS`] order.
+/// 将一个 [`EVENTS`] 采样送入 `summarizer`，并返回其完成的调用（如有）。
+/// `events` 为按 [`EVENTS`] 顺序记录的事件。
+pub fn feed(
+    summarizer: &mut SyscallSummarizer,
+    events: &[RecordedEvent],
+    sample: &SampleRecord,
+) -> Result<Option<CompletedSyscall>> {
+    let Some(event) = events.get(sample.event) else {
+        bail!("sample of unknown event {}", sample.event);
+    };
+    let nr = int_field(event, "id", &sample.raw)?;
+    if sample.event == 0 {
+        summarizer.enter(sample.pid, sample.tid, sample.time, nr);
+        return Ok(None);
+    }
+    let ret = int_field(event, "ret", &sample.raw)?;
+    Ok(Some(summarizer.exit(sample.pid, sample.tid, sample.time, nr, ret)))
+}
+
+/// Formats one completed call as `seconds (latency ms): comm/tid name() = ret`,
+/// with `seconds` relative to `start`, like `perf trace`.
+/// 像 `perf trace` 一样将一次完成的调用格式化为
+/// `seconds (latency ms): comm/tid name() = ret`，`seconds` 相对于 `start`。
+pub fn format_call(
+    arch: &str,
+    start: u64,
+    time: u64,
+    comm: &str,
+    call: &CompletedSyscall,
+) -> String {
+    let latency = match call.latency {
+        Some(ns) => format!("{:>7.3}", ns as f64 / 1e6),
+        None => format!("{:>7}", "?"),
+    };
+    format!(
+        "{:>10.6} ({} ms): {}/{} {}() = {}",
+        time.saturating_sub(start) as f64 / 1e9,
+        latency,
+        comm,
+        call.tid,
+        syscalls::display_name(arch, call.nr),
+        call.ret
+    )
+}
+
+fn micros(ns: u64) -> String {
+    format!("{:.3}", ns as f64 / 1e3)
+}
+
+/// Renders the rows of one table, sorted by total latency.
+/// 渲染一张按总延迟排序的表格。
+fn render_table(arch: &str, syscalls: &BTreeMap<i64, SyscallStats>, out: &mut Vec<String>) {
+    let total: u64 = syscalls.values().map(|s| s.total).sum();
+    out.push(format!(
+        "{:<20} {:>9} {:>7} {:>12} {:>11} {:>11} {:>11} {:>7}",
+        "syscall", "calls", "errors", "total(ms)", "min(us)", "avg(us)", "max(us)", "%time"
+    ));
+    out.push(format!("{} {}", "-".repeat(20), "-".repeat(73)));
+    for (nr, stats) in by_total_time(syscalls) {
+        let (min, avg, max) = match stats.avg() {
+            Some(avg) => (micros(stats.min), micros(avg), micros(stats.max)),
+            None => ("-".into(), "-".into(), "-".into()),
+        };
+        let share = if total == 0 { 0.0 } else { stats.total as f64 * 100.0 / total as f64 };
+        out.push(format!(
+            "{:<20} {:>9} {:>7} {:>12.3} {:>11} {:>11} {:>11} {:>7.2}",
+            syscalls::display_name(arch, nr),
+            stats.calls,
+            stats.errors,
+            stats.total as f64 / 1e6,
+            min,
+            avg,
+            max,
+            share
+        ));
+    }
+}
+
+/// Formats a summary: the syscalls of all threads, then a table per thread
+/// with more than one thread, busiest first.
+/// 格式化汇总：先是所有线程的系统调用，当存在多个线程时再按调用数从多到少
+/// 逐线程输出表格。
+pub fn format_summary(arch: &str, summary: &SyscallSummary) -> Vec<String> {
+    let mut out = Vec::new();
+    let calls = summary.calls();
+    out.push(format!(" Summary of {} syscalls in {} threads", calls, summary.threads.len()));
+    out.push(String::new());
+    render_table(arch, &summary.syscalls(), &mut out);
+    if summary.threads.len() > 1 {
+        let mut threads: Vec<_> = summary.threads.values().collect();
+        threads.sort_by_key(|t| (std::cmp::Reverse(t.calls()), t.tid));
+        for thread in threads {
+            let comm = if thread.comm.is_empty() { ":" } else { thread.comm.as_str() };
+            out.push(String::new());
+            out.push(format!(
+                " {} ({}), {} syscalls, {:.1}%",
+                comm,
+                thread.tid,
+                thread.calls(),
+                thread.calls() as f64 * 100.0 / calls.max(1) as f64
+            ));
+            out.push(String::new());
+            render_table(arch, &thread.syscalls, &mut out);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::tracepoint::parse_format;
+
+    fn events() -> Vec<RecordedEvent> {
+        let common = "\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;\n\n";
+        let enter = parse_format(&format!(
+            "name: sys_enter\nID: 443\nformat:\n{}\
+             \tfield:long id;\toffset:8;\tsize:8;\tsigned:1;\n\
+             \tfield:unsigned long args[6];\toffset:16;\tsize:48;\tsigned:0;\n",
+            common
+        ))
+        .unwrap();
+        let exit = parse_format(&format!(
+            "name: sys_exit\nID: 442\nformat:\n{}\
+             \tfield:long id;\toffset:8;\tsize:8;\tsigned:1;\n\
+             \tfield:long ret;\toffset:16;\tsize:8;\tsigned:1;\n",
+            common
+        ))
+        .unwrap();
+        vec![
+            RecordedEvent { name: EVENTS[0].into(), format: Some(enter) },
+            RecordedEvent { name: EVENTS[1].into(), format: Some(exit) },
+        ]
+    }
+
+    fn sample(event: usize, tid: u32, time: u64, id: i64, value: i64) -> SampleRecord {
+        let mut raw = vec![0u8; 8];
+        raw.extend_from_slice(&id.to_ne_bytes());
+        raw.extend_from_slice(&value.to_ne_bytes());
+        raw.resize(64, 0);
+        SampleRecord { event, pid: 100, tid, time, raw, ..Default::default() }
+    }
+
+    #[test]
+    fn test_feed_and_format_call() {
+        let events = events();
+        let mut summarizer = SyscallSummarizer::new("x86_64");
+        assert_eq!(feed(&mut summarizer, &events, &sample(0, 100, 2_000, 257, 0)).unwrap(), None);
+        let call =
+            feed(&mut summarizer, &events, &sample(1, 100, 1_502_000, 257, -2)).unwrap().unwrap();
+        assert_eq!(call, CompletedSyscall { tid: 100, nr: 257, ret: -2, latency: Some(1_500_000) });
+        assert_eq!(
+            format_call("x86_64", 1_000, 1_502_000, "ls", &call),
+            "  0.001501 (  1.500 ms): ls/100 openat() = -2"
+        );
+        let call = CompletedSyscall { latency: None, nr: 999, ..call };
+        assert!(
+            format_call("x86_64", 0, 0, "ls", &call)
+                .ends_with("(      ? ms): ls/100 syscall_999() = -2")
+        );
+
+        let bad = SampleRecord { event: 1, raw: vec![0; 12], ..Default::default() };
+        let err = feed(&mut summarizer, &events, &bad).unwrap_err();
+        assert!(err.to_string().contains("too short for field `id`"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_summary() {
+        let events = events();
+        let mut summarizer = SyscallSummarizer::new("x86_64");
+        summarizer.set_comm(100, "app");
+        summarizer.set_comm(101, "worker");
+        for s in [
+            sample(0, 100, 0, 0, 0),
+            sample(1, 100, 3_000, 0, 10),
+            sample(0, 101, 1_000, 1, 0),
+            sample(1, 101, 2_000, 1, -9),
+            sample(0, 101, 4_000, 1, 0),
+            sample(1, 101, 5_000, 1, 1),
+        ] {
+            feed(&mut summarizer, &events, &s).unwrap();
+        }
+        let lines = format_summary("x86_64", &summarizer.finish());
+        assert_eq!(lines[0], " Summary of 3 syscalls in 2 threads");
+        let row = |name: &str, from: usize| -> Vec<String> {
+            let line = lines[from..].iter().find(|l| l.starts_with(name)).unwrap();
+            line.split_whitespace().map(String::from).collect()
+        };
+        assert_eq!(
+            row("read ", 0),
+            ["read", "1", "0", "0.003", "3.000", "3.000", "3.000", "60.00"]
+        );
+        assert_eq!(
+            row("write ", 0),
+            ["write", "2", "1", "0.002", "1.000", "1.000", "1.000", "40.00"]
+        );
+        let worker = lines.iter().position(|l| l == " worker (101), 2 syscalls, 66.7%").unwrap();
+        let app = lines.iter().position(|l| l == " app (100), 1 syscalls, 33.3%").unwrap();
+        assert!(worker < app);
+        assert_eq!(row("write ", worker)[7], "100.00");
+    }
+}
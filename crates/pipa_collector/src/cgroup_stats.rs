@@ -0,0 +1,635 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module collects resource statistics of a single cgroup by reading the
+//! cgroup v2 interface files under `/sys/fs/cgroup` directly.
+//!
+//! 本模块直接读取 `/sys/fs/cgroup` 下的 cgroup v2 接口文件，
+//! 收集单个 cgroup 的资源统计信息。
+
+use crate::error::{ResultExt, parse_file};
+use crate::sysroot::SysRoot;
+use crate::system_stats::PipaCollectorError;
+use std::io;
+use std::path::PathBuf;
+
+/// The cgroup hierarchy layout mounted on the host.
+///
+/// 主机上挂载的 cgroup 层级布局。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CgroupVersion {
+    /// Legacy per-controller hierarchies only. / 仅有传统的按控制器划分的层级。
+    V1,
+    /// Legacy hierarchies plus a unified hierarchy at `unified/`, which
+    /// usually has no controllers enabled.
+    /// 传统层级加上位于 `unified/` 的统一层级，后者通常没有启用任何控制器。
+    Hybrid,
+    /// A single unified hierarchy. / 单一的统一层级。
+    V2,
+}
+
+/// CPU statistics from `cpu.stat`. All times are in microseconds. The
+/// throttling fields are only present when the `cpu` controller is enabled.
+///
+/// 来自 `cpu.stat` 的 CPU 统计信息，所有时间单位均为微秒。
+/// 只有在启用 `cpu` 控制器时才会有限流相关字段。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CgroupCpuStat {
+    /// Total CPU time consumed. / 消耗的 CPU 总时间。
+    pub usage_usec: u64,
+    /// CPU time consumed in user mode. / 用户态消耗的 CPU 时间。
+    pub user_usec: u64,
+    /// CPU time consumed in kernel mode. / 内核态消耗的 CPU 时间。
+    pub system_usec: u64,
+    /// Enforcement periods that have elapsed. / 已经过的配额周期数。
+    pub nr_periods: u64,
+    /// Periods in which the cgroup was throttled. / 发生限流的周期数。
+    pub nr_throttled: u64,
+    /// Total time the cgroup was throttled. / 被限流的总时间。
+    pub throttled_usec: u64,
+}
+
+/// Selected fields of `memory.stat`. Sizes are in bytes; `pgfault` and
+/// `pgmajfault` are event counts.
+///
+/// `memory.stat` 中的部分字段。大小单位为字节；`pgfault` 和 `pgmajfault` 为事件计数。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CgroupMemoryStat {
+    /// Anonymous memory. / 匿名内存。
+    pub anon: u64,
+    /// Page cache. / 页缓存。
+    pub file: u64,
+    /// Kernel memory (stacks, slab, page tables, ...). / 内核内存（栈、slab、页表等）。
+    pub kernel: u64,
+    /// Kernel stacks. / 内核栈。
+    pub kernel_stack: u64,
+    /// Page tables. / 页表。
+    pub pagetables: u64,
+    /// Network socket buffers. / 网络套接字缓冲区。
+    pub sock: u64,
+    /// Shared memory and tmpfs. / 共享内存和 tmpfs。
+    pub shmem: u64,
+    /// Page cache mapped into user space. / 映射到用户空间的页缓存。
+    pub file_mapped: u64,
+    /// Dirty page cache. / 脏页缓存。
+    pub file_dirty: u64,
+    /// Page cache under writeback. / 正在回写的页缓存。
+    pub file_writeback: u64,
+    /// Reclaimable slab. / 可回收的 slab。
+    pub slab_reclaimable: u64,
+    /// Unreclaimable slab. / 不可回收的 slab。
+    pub slab_unreclaimable: u64,
+    /// Page faults. / 缺页次数。
+    pub pgfault: u64,
+    /// Major page faults. / 主要缺页次数。
+    pub pgmajfault: u64,
+}
+
+/// Counters from `memory.events`.
+///
+/// 来自 `memory.events` 的计数器。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CgroupMemoryEvents {
+    /// Reclaims while below the `memory.low` boundary. / 低于 `memory.low` 时发生的回收次数。
+    pub low: u64,
+    /// Times `memory.high` was exceeded. / 超过 `memory.high` 的次数。
+    pub high: u64,
+    /// Times `memory.max` was about to be exceeded. / 即将超过 `memory.max` 的次数。
+    pub max: u64,
+    /// Times the OOM killer was invoked. / 触发 OOM killer 的次数。
+    pub oom: u64,
+    /// Processes killed by the OOM killer. / 被 OOM killer 杀死的进程数。
+    pub oom_kill: u64,
+}
+
+/// One device line of `io.stat`. Byte and operation counts are cumulative.
+///
+/// `io.stat` 中的一行设备统计。字节数和操作次数均为累计值。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CgroupIoStat {
+    /// Device major number. / 设备主设备号。
+    pub major: u32,
+    /// Device minor number. / 设备次设备号。
+    pub minor: u32,
+    /// Bytes read. / 读取的字节数。
+    pub rbytes: u64,
+    /// Bytes written. / 写入的字节数。
+    pub wbytes: u64,
+    /// Read operations. / 读操作次数。
+    pub rios: u64,
+    /// Write operations. / 写操作次数。
+    pub wios: u64,
+    /// Bytes discarded. / 丢弃（discard）的字节数。
+    pub dbytes: u64,
+    /// Discard operations. / 丢弃操作次数。
+    pub dios: u64,
+}
+
+/// All statistics of one cgroup. Files that belong to a controller which is
+/// not enabled for the cgroup are reported as `None`.
+///
+/// 单个 cgroup 的全部统计信息。属于未对该 cgroup 启用的控制器的文件记为 `None`。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct CgroupStats {
+    /// `cpu.stat`, always present. / `cpu.stat`，始终存在。
+    pub cpu: CgroupCpuStat,
+    /// `memory.current` in bytes. / `memory.current`（字节）。
+    pub memory_current: Option<u64>,
+    /// `memory.stat`. / `memory.stat`。
+    pub memory_stat: Option<CgroupMemoryStat>,
+    /// `memory.events`. / `memory.events`。
+    pub memory_events: Option<CgroupMemoryEvents>,
+    /// `io.stat`, one entry per device. / `io.stat`，每个设备一项。
+    pub io: Option<Vec<CgroupIoStat>>,
+    /// `pids.current`. / `pids.current`。
+    pub pids_current: Option<u64>,
+}
+
+/// Parses a flat-keyed file (`key value` per line), calling `assign` for every
+/// numeric pair. Used by `cpu.stat`, `memory.stat` and `memory.events`.
+///
+/// 解析扁平键值文件（每行 `key value`），对每个数值键值对调用 `assign`。
+/// 用于 `cpu.stat`、`memory.stat` 和 `memory.events`。
+fn parse_flat_keyed<F: FnMut(&str, u64)>(content: &str, mut assign: F) {
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        if let (Some(key), Some(Ok(value))) = (parts.next(), parts.next().map(str::parse::<u64>)) {
+            assign(key, value);
+        }
+    }
+}
+
+/// Parses the content of `cpu.stat`.
+/// 解析 `cpu.stat` 的内容。
+fn parse_cpu_stat_from_content(content: &str) -> Result<CgroupCpuStat, PipaCollectorError> {
+    let mut stat = CgroupCpuStat::default();
+    let mut has_usage = false;
+    parse_flat_keyed(content, |key, value| match key {
+        "usage_usec" => {
+            stat.usage_usec = value;
+            has_usage = true;
+        }
+        "user_usec" => stat.user_usec = value,
+        "system_usec" => stat.system_usec = value,
+        "nr_periods" => stat.nr_periods = value,
+        "nr_throttled" => stat.nr_throttled = value,
+        "throttled_usec" => stat.throttled_usec = value,
+        _ => {}
+    });
+
+    if !has_usage {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find usage_usec in cpu.stat".to_string(),
+        ));
+    }
+    Ok(stat)
+}
+
+/// Parses the content of `memory.stat`.
+/// 解析 `memory.stat` 的内容。
+fn parse_memory_stat_from_content(content: &str) -> Result<CgroupMemoryStat, PipaCollectorError> {
+    let mut stat = CgroupMemoryStat::default();
+    let mut has_anon = false;
+    parse_flat_keyed(content, |key, value| match key {
+        "anon" => {
+            stat.anon = value;
+            has_anon = true;
+        }
+        "file" => stat.file = value,
+        "kernel" => stat.kernel = value,
+        "kernel_stack" => stat.kernel_stack = value,
+        "pagetables" => stat.pagetables = value,
+        "sock" => stat.sock = value,
+        "shmem" => stat.shmem = value,
+        "file_mapped" => stat.file_mapped = value,
+        "file_dirty" => stat.file_dirty = value,
+        "file_writeback" => stat.file_writeback = value,
+        "slab_reclaimable" => stat.slab_reclaimable = value,
+        "slab_unreclaimable" => stat.slab_unreclaimable = value,
+        "pgfault" => stat.pgfault = value,
+        "pgmajfault" => stat.pgmajfault = value,
+        _ => {}
+    });
+
+    if !has_anon {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find anon in memory.stat".to_string(),
+        ));
+    }
+    Ok(stat)
+}
+
+/// Parses the content of `memory.events`.
+/// 解析 `memory.events` 的内容。
+fn parse_memory_events_from_content(
+    content: &str,
+) -> Result<CgroupMemoryEvents, PipaCollectorError> {
+    let mut events = CgroupMemoryEvents::default();
+    let mut found_count = 0;
+    parse_flat_keyed(content, |key, value| {
+        match key {
+            "low" => events.low = value,
+            "high" => events.high = value,
+            "max" => events.max = value,
+            "oom" => events.oom = value,
+            "oom_kill" => events.oom_kill = value,
+            _ => return,
+        }
+        found_count += 1;
+    });
+
+    if found_count < 5 {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find all required fields in memory.events".to_string(),
+        ));
+    }
+    Ok(events)
+}
+
+/// Parses the content of `io.stat`, e.g.
+/// `8:0 rbytes=1 wbytes=2 rios=3 wios=4 dbytes=0 dios=0`.
+///
+/// 解析 `io.stat` 的内容，例如 `8:0 rbytes=1 wbytes=2 rios=3 wios=4 dbytes=0 dios=0`。
+fn parse_io_stat_from_content(content: &str) -> Result<Vec<CgroupIoStat>, PipaCollectorError> {
+    let mut devices = Vec::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let mut parts = line.split_whitespace();
+        let device = parts.next().unwrap_or_default();
+        let (major, minor) = device.split_once(':').ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(format!("Invalid device in io.stat: {}", device))
+        })?;
+        let mut stat =
+            CgroupIoStat { major: major.parse()?, minor: minor.parse()?, ..Default::default() };
+
+        for part in parts {
+            let Some((key, value)) = part.split_once('=') else {
+                continue;
+            };
+            let value = value.parse::<u64>()?;
+            match key {
+                "rbytes" => stat.rbytes = value,
+                "wbytes" => stat.wbytes = value,
+                "rios" => stat.rios = value,
+                "wios" => stat.wios = value,
+                "dbytes" => stat.dbytes = value,
+                "dios" => stat.dios = value,
+                _ => {}
+            }
+        }
+        devices.push(stat);
+    }
+    Ok(devices)
+}
+
+/// Parses a single-value file such as `memory.current` or `pids.current`.
+/// 解析 `memory.current` 或 `pids.current` 这类单值文件。
+fn parse_single_value(content: &str) -> Result<u64, PipaCollectorError> {
+    Ok(content.trim().parse::<u64>()?)
+}
+
+/// Extracts the cgroup v2 path (the `0::` line) from `/proc/<pid>/cgroup`.
+///
+/// 从 `/proc/<pid>/cgroup` 中提取 cgroup v2 路径（`0::` 行）。
+fn parse_proc_cgroup_content(content: &str) -> Result<String, PipaCollectorError> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .map(|path| path.trim().to_string())
+        .ok_or_else(|| {
+            PipaCollectorError::MissingData(
+                "No cgroup v2 entry ('0::') in /proc/<pid>/cgroup".to_string(),
+            )
+        })
+}
+
+/// Reads an optional interface file: a missing file means the owning
+/// controller is not enabled, which is reported as `None`.
+///
+/// 读取可选的接口文件：文件不存在表示对应控制器未启用，返回 `None`。
+fn read_optional<T, F>(path: PathBuf, parse: F) -> Result<Option<T>, PipaCollectorError>
+where
+    F: FnOnce(&str) -> Result<T, PipaCollectorError>,
+{
+    match std::fs::read_to_string(&path) {
+        Ok(content) => parse(&content).map(Some).at_path(path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(PipaCollectorError::from(e).at_path(path)),
+    }
+}
+
+/// A handle to a mounted cgroup filesystem. [`CgroupFs::default`] points at
+/// `/sys/fs/cgroup`; [`CgroupFs::new`] accepts any root, such as a fixture
+/// tree in tests or `/host/sys/fs/cgroup` in a container.
+///
+/// 已挂载的 cgroup 文件系统的句柄。[`CgroupFs::default`] 指向 `/sys/fs/cgroup`；
+/// [`CgroupFs::new`] 可以接受任意根目录，例如测试中的夹具目录树或容器中的
+/// `/host/sys/fs/cgroup`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CgroupFs {
+    root: PathBuf,
+}
+
+impl Default for CgroupFs {
+    fn default() -> Self {
+        CgroupFs::new("/sys/fs/cgroup")
+    }
+}
+
+impl CgroupFs {
+    /// Creates a handle rooted at `root`. / 创建一个以 `root` 为根的句柄。
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        CgroupFs { root: root.into() }
+    }
+
+    /// The cgroup filesystem under a sysfs root, i.e. `<sys>/fs/cgroup`.
+    /// sysfs 根目录下的 cgroup 文件系统，即 `<sys>/fs/cgroup`。
+    pub fn at(root: &SysRoot) -> Self {
+        CgroupFs::new(root.sys("fs/cgroup"))
+    }
+
+    /// Detects the hierarchy layout from the files present under the root.
+    ///
+    /// 根据根目录下存在的文件检测层级布局。
+    pub fn version(&self) -> Result<CgroupVersion, PipaCollectorError> {
+        if self.root.join("cgroup.controllers").is_file() {
+            Ok(CgroupVersion::V2)
+        } else if self.root.join("unified/cgroup.controllers").is_file() {
+            Ok(CgroupVersion::Hybrid)
+        } else if ["cpu", "cpuacct", "memory", "pids"].iter().any(|c| self.root.join(c).is_dir()) {
+            Ok(CgroupVersion::V1)
+        } else {
+            Err(PipaCollectorError::MissingData(format!(
+                "No cgroup hierarchy found under {}",
+                self.root.display()
+            )))
+        }
+    }
+
+    /// Resolves a cgroup path such as `/system.slice/app.service` (as found in
+    /// `/proc/<pid>/cgroup`) to its directory in the unified hierarchy.
+    /// cgroup v1 is rejected with [`PipaCollectorError::Unsupported`].
+    ///
+    /// 将 cgroup 路径（例如 `/proc/<pid>/cgroup` 中的 `/system.slice/app.service`）
+    /// 解析为其在统一层级中的目录。cgroup v1 会返回 [`PipaCollectorError::Unsupported`]。
+    pub fn cgroup_dir(&self, cgroup: &str) -> Result<PathBuf, PipaCollectorError> {
+        let base = match self.version()? {
+            CgroupVersion::V2 => self.root.clone(),
+            CgroupVersion::Hybrid => self.root.join("unified"),
+            CgroupVersion::V1 => {
+                return Err(PipaCollectorError::Unsupported(
+                    "cgroup v1 hierarchy detected; only cgroup v2 (unified) statistics are \
+                     supported. Boot with systemd.unified_cgroup_hierarchy=1 to enable v2"
+                        .to_string(),
+                ));
+            }
+        };
+        let dir = base.join(cgroup.trim_start_matches('/'));
+        if !dir.is_dir() {
+            return Err(PipaCollectorError::Io(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("cgroup not found: {}", dir.display()),
+            )));
+        }
+        Ok(dir)
+    }
+
+    /// Reads all statistics of `cgroup` (a path relative to the hierarchy root).
+    ///
+    /// 读取 `cgroup`（相对于层级根目录的路径）的全部统计信息。
+    pub fn read_stats(&self, cgroup: &str) -> Result<CgroupStats, PipaCollectorError> {
+        let dir = self.cgroup_dir(cgroup)?;
+        Ok(CgroupStats {
+            cpu: parse_file(dir.join("cpu.stat"), parse_cpu_stat_from_content)?,
+            memory_current: read_optional(dir.join("memory.current"), parse_single_value)?,
+            memory_stat: read_optional(dir.join("memory.stat"), parse_memory_stat_from_content)?,
+            memory_events: read_optional(
+                dir.join("memory.events"),
+                parse_memory_events_from_content,
+            )?,
+            io: read_optional(dir.join("io.stat"), parse_io_stat_from_content)?,
+            pids_current: read_optional(dir.join("pids.current"), parse_single_value)?,
+        })
+    }
+}
+
+/// Returns the cgroup v2 path of a process, e.g. `/system.slice/app.service`.
+/// 返回进程的 cgroup v2 路径，例如 `/system.slice/app.service`。
+pub fn cgroup_of_pid(root: &SysRoot, pid: u32) -> Result<String, PipaCollectorError> {
+    parse_file(root.proc(format!("{}/cgroup", pid)), parse_proc_cgroup_content)
+}
+
+/// Reads the statistics of `cgroup` from `<sys>/fs/cgroup`.
+/// 从 `<sys>/fs/cgroup` 读取 `cgroup` 的统计信息。
+pub fn read_cgroup_stats(root: &SysRoot, cgroup: &str) -> Result<CgroupStats, PipaCollectorError> {
+    CgroupFs::at(root).read_stats(cgroup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CPU_STAT: &str = "usage_usec 170872345\n\
+                            user_usec 143878474\n\
+                            system_usec 26993870\n\
+                            nice_usec 0\n\
+                            nr_periods 120\n\
+                            nr_throttled 7\n\
+                            throttled_usec 350000\n";
+
+    const MEMORY_STAT: &str = "anon 104857600\n\
+                               file 52428800\n\
+                               kernel 8388608\n\
+                               kernel_stack 1048576\n\
+                               pagetables 2097152\n\
+                               sock 4096\n\
+                               shmem 0\n\
+                               file_mapped 1024\n\
+                               file_dirty 512\n\
+                               file_writeback 0\n\
+                               slab_reclaimable 3000\n\
+                               slab_unreclaimable 2000\n\
+                               pgfault 9000\n\
+                               pgmajfault 12\n";
+
+    const MEMORY_EVENTS: &str = "low 0\nhigh 3\nmax 1\noom 1\noom_kill 1\noom_group_kill 0\n";
+
+    const IO_STAT: &str = "8:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n\
+                           259:1 rbytes=100 wbytes=200 rios=3 wios=4 dbytes=5 dios=6\n";
+
+    /// Test sections for the parsers
+    #[test]
+    fn test_parse_cpu_stat() {
+        let stat = parse_cpu_stat_from_content(CPU_STAT).unwrap();
+        assert_eq!(stat.usage_usec, 170872345);
+        assert_eq!(stat.user_usec, 143878474);
+        assert_eq!(stat.system_usec, 26993870);
+        assert_eq!(stat.nr_periods, 120);
+        assert_eq!(stat.nr_throttled, 7);
+        assert_eq!(stat.throttled_usec, 350000);
+    }
+
+    #[test]
+    fn test_parse_cpu_stat_without_cpu_controller() {
+        let stat =
+            parse_cpu_stat_from_content("usage_usec 10\nuser_usec 6\nsystem_usec 4\n").unwrap();
+        assert_eq!(stat.usage_usec, 10);
+        assert_eq!(stat.nr_throttled, 0);
+
+        let result = parse_cpu_stat_from_content("user_usec 6\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_parse_memory_stat() {
+        let stat = parse_memory_stat_from_content(MEMORY_STAT).unwrap();
+        assert_eq!(stat.anon, 104857600);
+        assert_eq!(stat.file, 52428800);
+        assert_eq!(stat.kernel_stack, 1048576);
+        assert_eq!(stat.slab_unreclaimable, 2000);
+        assert_eq!(stat.pgmajfault, 12);
+
+        let result = parse_memory_stat_from_content("file 1\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_parse_memory_events() {
+        let events = parse_memory_events_from_content(MEMORY_EVENTS).unwrap();
+        assert_eq!(events.high, 3);
+        assert_eq!(events.oom_kill, 1);
+
+        let result = parse_memory_events_from_content("low 0\nhigh 3\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_parse_io_stat() {
+        let devices = parse_io_stat_from_content(IO_STAT).unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(
+            devices[1],
+            CgroupIoStat {
+                major: 259,
+                minor: 1,
+                rbytes: 100,
+                wbytes: 200,
+                rios: 3,
+                wios: 4,
+                dbytes: 5,
+                dios: 6
+            }
+        );
+        assert!(parse_io_stat_from_content("").unwrap().is_empty());
+        assert!(matches!(
+            parse_io_stat_from_content("sda rbytes=1").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_io_stat_from_content("8:0 rbytes=x").unwrap_err().root_cause(),
+            PipaCollectorError::Parse(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_proc_cgroup_content() {
+        let content = "4:memory:/legacy\n0::/system.slice/app.service\n";
+        assert_eq!(parse_proc_cgroup_content(content).unwrap(), "/system.slice/app.service");
+        assert!(parse_proc_cgroup_content("4:memory:/legacy\n").is_err());
+    }
+
+    /// Test sections for the fixture-backed filesystem handle
+    fn v2_fixture() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("cgroup.controllers"), "cpu io memory pids\n").unwrap();
+        let app = root.path().join("system.slice/app.service");
+        std::fs::create_dir_all(&app).unwrap();
+        std::fs::write(app.join("cpu.stat"), CPU_STAT).unwrap();
+        std::fs::write(app.join("memory.current"), "157286400\n").unwrap();
+        std::fs::write(app.join("memory.stat"), MEMORY_STAT).unwrap();
+        std::fs::write(app.join("memory.events"), MEMORY_EVENTS).unwrap();
+        std::fs::write(app.join("io.stat"), IO_STAT).unwrap();
+        std::fs::write(app.join("pids.current"), "17\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_read_stats_v2() {
+        let root = v2_fixture();
+        let fs = CgroupFs::new(root.path());
+        assert_eq!(fs.version().unwrap(), CgroupVersion::V2);
+
+        let stats = fs.read_stats("/system.slice/app.service").unwrap();
+        assert_eq!(stats.cpu.nr_throttled, 7);
+        assert_eq!(stats.memory_current, Some(157286400));
+        assert_eq!(stats.memory_stat.unwrap().anon, 104857600);
+        assert_eq!(stats.memory_events.unwrap().oom, 1);
+        assert_eq!(stats.io.unwrap().len(), 2);
+        assert_eq!(stats.pids_current, Some(17));
+    }
+
+    #[test]
+    fn test_read_stats_with_disabled_controllers() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("cgroup.controllers"), "").unwrap();
+        std::fs::write(root.path().join("cpu.stat"), "usage_usec 5\n").unwrap();
+
+        let stats = CgroupFs::new(root.path()).read_stats("/").unwrap();
+        assert_eq!(stats.cpu.usage_usec, 5);
+        assert_eq!(stats.memory_current, None);
+        assert_eq!(stats.memory_stat, None);
+        assert_eq!(stats.io, None);
+        assert_eq!(stats.pids_current, None);
+    }
+
+    #[test]
+    fn test_read_stats_hybrid() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("memory")).unwrap();
+        let unified = root.path().join("unified");
+        std::fs::create_dir_all(&unified).unwrap();
+        std::fs::write(unified.join("cgroup.controllers"), "").unwrap();
+        std::fs::write(unified.join("cpu.stat"), "usage_usec 42\n").unwrap();
+
+        let fs = CgroupFs::new(root.path());
+        assert_eq!(fs.version().unwrap(), CgroupVersion::Hybrid);
+        assert_eq!(fs.read_stats("/").unwrap().cpu.usage_usec, 42);
+    }
+
+    #[test]
+    fn test_read_stats_v1_is_unsupported() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("cpu")).unwrap();
+        std::fs::create_dir_all(root.path().join("memory")).unwrap();
+
+        let fs = CgroupFs::new(root.path());
+        assert_eq!(fs.version().unwrap(), CgroupVersion::V1);
+        assert!(matches!(fs.read_stats("/").unwrap_err(), PipaCollectorError::Unsupported(_)));
+    }
+
+    #[test]
+    fn test_read_stats_errors() {
+        let root = v2_fixture();
+        let fs = CgroupFs::new(root.path());
+        assert!(matches!(
+            fs.read_stats("/no/such/cgroup").unwrap_err().root_cause(),
+            PipaCollectorError::Io(_)
+        ));
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            CgroupFs::new(empty.path()).version().unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+    }
+}
@@ -0,0 +1,1290 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A pluggable collector interface. A [`Collector`] describes its metrics with
+//! a schema and returns untyped [`Sample`]s, so that generic consumers such as
+//! the sampler and `pipa export` can run any set of collectors, including ones
+//! registered by other crates, without knowing their types.
+//!
+//! [`CollectorRegistry::builtin`] holds the system-wide collectors. Cgroup and
+//! process statistics need a target, so [`cgroup_collector`] and
+//! [`process_collector`] build collectors for given cgroups or PIDs that the
+//! caller registers itself.
+//!
+//! 可插拔的采集器接口。[`Collector`] 通过模式（schema）描述其指标并返回无类型的
+//! [`Sample`]，使采样器和 `pipa export` 等通用使用方无需了解具体类型，
+//! 即可运行任意一组采集器，包括其他 crate 注册的采集器。
+//!
+//! [`CollectorRegistry::builtin`] 包含系统级采集器。cgroup 和进程统计需要指定目标，
+//! 因此由 [`cgroup_collector`] 和 [`process_collector`] 为给定的 cgroup 或 PID
+//! 构造采集器，再由调用方自行注册。
+
+use crate::cgroup_stats::{self, CgroupStats};
+use crate::cpu_telemetry::{self, CpuFreq, CpuIdleState, CpuTelemetry, ThermalZone};
+use crate::interrupt_stats::{self, InterruptStats, SoftirqStats};
+use crate::process_stats::{self, ProcessSnapshot};
+use crate::sysroot::SysRoot;
+use crate::system_stats::{
+    self, CpuStats, DiskStats, LoadStats, MemoryStats, NetDevStats, PipaCollectorError,
+    PressureStats, PsiResource, SnapshotDelta, VmStats,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// How a metric changes between two samples.
+///
+/// 指标在两次采样之间的变化方式。
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum MetricKind {
+    /// A monotonically increasing total; deltas subtract and rates divide by
+    /// the elapsed time.
+    /// 单调递增的累计值；增量相减，速率除以经过时间。
+    Counter,
+    /// An instantaneous value; deltas keep the later reading.
+    /// 瞬时值；增量保留较晚的读数。
+    Gauge,
+}
+
+/// One entry of a collector's schema. / 采集器模式中的一项。
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Metric {
+    /// Metric name, unique within the collector. / 指标名称，在采集器内唯一。
+    pub name: String,
+    /// Unit of the raw value, e.g. `kB` or `jiffies`. / 原始值的单位，例如 `kB` 或 `jiffies`。
+    pub unit: String,
+    /// Counter or gauge. / 计数器或瞬时值。
+    pub kind: MetricKind,
+}
+
+impl Metric {
+    /// Builds a counter metric. / 构造一个计数器指标。
+    pub fn counter(name: &str, unit: &str) -> Self {
+        Metric { name: name.to_string(), unit: unit.to_string(), kind: MetricKind::Counter }
+    }
+
+    /// Builds a gauge metric. / 构造一个瞬时值指标。
+    pub fn gauge(name: &str, unit: &str) -> Self {
+        Metric { name: name.to_string(), unit: unit.to_string(), kind: MetricKind::Gauge }
+    }
+}
+
+/// The values of one instance (a device, an interface, an IRQ...). Values are
+/// in schema order. Single-instance collectors use an empty instance name.
+///
+/// 单个实例（设备、网络接口、中断等）的数值，按模式中的顺序排列。
+/// 单实例采集器使用空的实例名。
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Record {
+    /// Instance name. / 实例名称。
+    pub instance: String,
+    /// One value per schema metric. / 每个模式指标对应一个值。
+    pub values: Vec<f64>,
+}
+
+impl Record {
+    /// Looks up the value of the metric called `name` in `schema`.
+    /// 在 `schema` 中查找名为 `name` 的指标并返回其值。
+    pub fn value(&self, schema: &[Metric], name: &str) -> Option<f64> {
+        let index = schema.iter().position(|m| m.name == name)?;
+        self.values.get(index).copied()
+    }
+}
+
+/// One reading of a collector. / 采集器的一次读数。
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    /// Records, one per instance. / 记录，每个实例一条。
+    pub records: Vec<Record>,
+}
+
+impl Sample {
+    /// A sample with a single unnamed record. / 仅含一条无名记录的读数。
+    pub fn single(values: Vec<f64>) -> Self {
+        Sample { records: vec![Record { instance: String::new(), values }] }
+    }
+
+    /// Finds the record of `instance`. / 查找 `instance` 对应的记录。
+    pub fn get(&self, instance: &str) -> Option<&Record> {
+        self.records.iter().find(|r| r.instance == instance)
+    }
+
+    /// Computes the change from `earlier` to `self` according to `schema`.
+    /// Counters are subtracted (clamped at zero on reset) and gauges keep the
+    /// current value. Instances missing from `earlier` have no baseline and
+    /// are dropped.
+    ///
+    /// 根据 `schema` 计算从 `earlier` 到 `self` 的变化。计数器相减（计数器重置时取 0），
+    /// 瞬时值保留当前值。`earlier` 中不存在的实例没有基线，会被丢弃。
+    pub fn delta(&self, earlier: &Sample, schema: &[Metric]) -> Sample {
+        let records = self
+            .records
+            .iter()
+            .filter_map(|record| {
+                let prev = earlier.get(&record.instance)?;
+                let values = record
+                    .values
+                    .iter()
+                    .zip(&prev.values)
+                    .zip(schema)
+                    .map(|((now, before), metric)| match metric.kind {
+                        MetricKind::Counter => (now - before).max(0.0),
+                        MetricKind::Gauge => *now,
+                    })
+                    .collect();
+                Some(Record { instance: record.instance.clone(), values })
+            })
+            .collect();
+        Sample { records }
+    }
+}
+
+impl SnapshotDelta<Sample> {
+    /// Converts counters to per-second rates and leaves gauges unchanged.
+    /// 将计数器换算为每秒速率，瞬时值保持不变。
+    pub fn rates(&self, schema: &[Metric]) -> Sample {
+        let secs = self.elapsed.as_secs_f64();
+        let records = self
+            .delta
+            .records
+            .iter()
+            .map(|record| Record {
+                instance: record.instance.clone(),
+                values: record
+                    .values
+                    .iter()
+                    .zip(schema)
+                    .map(|(value, metric)| match metric.kind {
+                        MetricKind::Counter if secs > 0.0 => value / secs,
+                        MetricKind::Counter => 0.0,
+                        MetricKind::Gauge => *value,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Sample { records }
+    }
+}
+
+/// A source of system metrics. Implementations must return records whose
+/// values follow [`Collector::schema`].
+///
+/// 系统指标的来源。实现必须返回数值顺序与 [`Collector::schema`] 一致的记录。
+pub trait Collector: Send + Sync {
+    /// Unique name used to enable the collector, e.g. `cpu`.
+    /// 用于启用该采集器的唯一名称，例如 `cpu`。
+    fn name(&self) -> &str;
+
+    /// The metrics of every record. / 每条记录包含的指标。
+    fn schema(&self) -> &[Metric];
+
+    /// Reads the current values. / 读取当前数值。
+    fn sample(&self) -> Result<Sample, PipaCollectorError>;
+
+    /// Computes the change between two samples. The default applies the
+    /// counter/gauge semantics of the schema.
+    /// 计算两次读数之间的变化。默认实现按模式中的计数器/瞬时值语义处理。
+    fn delta(&self, later: &Sample, earlier: &Sample) -> Sample {
+        later.delta(earlier, self.schema())
+    }
+}
+
+/// A collector built from a name, a schema and a read function. This is the
+/// simplest way for other crates to add a collector.
+///
+/// 由名称、模式和读取函数构造的采集器。这是其他 crate 添加采集器最简单的方式。
+pub struct FnCollector<F> {
+    name: String,
+    schema: Vec<Metric>,
+    read: F,
+}
+
+impl<F> FnCollector<F>
+where
+    F: Fn() -> Result<Sample, PipaCollectorError> + Send + Sync,
+{
+    /// Creates a collector that calls `read` for every sample.
+    /// 创建一个每次采样时调用 `read` 的采集器。
+    pub fn new(name: &str, schema: Vec<Metric>, read: F) -> Self {
+        FnCollector { name: name.to_string(), schema, read }
+    }
+}
+
+impl<F> Collector for FnCollector<F>
+where
+    F: Fn() -> Result<Sample, PipaCollectorError> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn schema(&self) -> &[Metric] {
+        &self.schema
+    }
+
+    fn sample(&self) -> Result<Sample, PipaCollectorError> {
+        (self.read)()
+    }
+}
+
+/// The set of collectors available to generic consumers.
+///
+/// 通用使用方可用的采集器集合。
+#[derive(Clone, Default)]
+pub struct CollectorRegistry {
+    collectors: Vec<Arc<dyn Collector>>,
+}
+
+impl CollectorRegistry {
+    /// Creates an empty registry. / 创建一个空的注册表。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry holding the collectors of this crate, reading from
+    /// `root`.
+    /// 创建包含本 crate 所有采集器的注册表，从 `root` 读取数据。
+    pub fn builtin(root: &SysRoot) -> Self {
+        let mut registry = Self::new();
+        for collector in builtin_collectors(root) {
+            // Built-in names are distinct, so this cannot fail.
+            let _ = registry.register_arc(collector);
+        }
+        registry
+    }
+
+    /// Adds a collector. Fails if the name is already taken.
+    /// 添加一个采集器。若名称已被占用则返回错误。
+    pub fn register<C: Collector + 'static>(
+        &mut self,
+        collector: C,
+    ) -> Result<(), PipaCollectorError> {
+        self.register_arc(Arc::new(collector))
+    }
+
+    fn register_arc(&mut self, collector: Arc<dyn Collector>) -> Result<(), PipaCollectorError> {
+        if self.get(collector.name()).is_some() {
+            return Err(PipaCollectorError::InvalidFormat(format!(
+                "Collector `{}` is already registered",
+                collector.name()
+            )));
+        }
+        self.collectors.push(collector);
+        Ok(())
+    }
+
+    /// Looks up a collector by name. / 按名称查找采集器。
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Collector>> {
+        self.collectors.iter().find(|c| c.name() == name).cloned()
+    }
+
+    /// Names of all registered collectors, in registration order.
+    /// 所有已注册采集器的名称，按注册顺序排列。
+    pub fn names(&self) -> Vec<&str> {
+        self.collectors.iter().map(|c| c.name()).collect()
+    }
+
+    /// Resolves `names` to collectors, failing on the first unknown name.
+    /// 将 `names` 解析为采集器，遇到第一个未知名称时返回错误。
+    pub fn select<S: AsRef<str>>(
+        &self,
+        names: &[S],
+    ) -> Result<Vec<Arc<dyn Collector>>, PipaCollectorError> {
+        names
+            .iter()
+            .map(|name| {
+                let name = name.as_ref();
+                self.get(name).ok_or_else(|| {
+                    PipaCollectorError::MissingData(format!(
+                        "Unknown collector `{}` (available: {})",
+                        name,
+                        self.names().join(", ")
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Iterates over all registered collectors. / 遍历所有已注册的采集器。
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<dyn Collector>> {
+        self.collectors.iter()
+    }
+}
+
+/// Builds a schema where every metric has the same kind and unit.
+/// 构造所有指标类型和单位都相同的模式。
+fn metrics(kind: MetricKind, unit: &str, names: &[&str]) -> Vec<Metric> {
+    names
+        .iter()
+        .map(|name| Metric { name: name.to_string(), unit: unit.to_string(), kind })
+        .collect()
+}
+
+fn cpu_sample(s: &CpuStats) -> Sample {
+    Sample::single(
+        [
+            s.user,
+            s.nice,
+            s.system,
+            s.idle,
+            s.iowait,
+            s.irq,
+            s.softirq,
+            s.steal,
+            s.guest,
+            s.guest_nice,
+        ]
+        .map(|v| v as f64)
+        .to_vec(),
+    )
+}
+
+fn memory_sample(s: &MemoryStats) -> Sample {
+    Sample::single(
+        [
+            s.total,
+            s.free,
+            s.available,
+            s.buffers,
+            s.cached,
+            s.active,
+            s.inactive,
+            s.dirty,
+            s.slab,
+            s.committed_as,
+            s.swap_total,
+            s.swap_free,
+        ]
+        .map(|v| v as f64)
+        .to_vec(),
+    )
+}
+
+fn load_sample(s: &LoadStats) -> Sample {
+    Sample::single(vec![
+        s.load1,
+        s.load5,
+        s.load15,
+        s.runnable as f64,
+        s.total_tasks as f64,
+        s.procs_running as f64,
+        s.procs_blocked as f64,
+        s.online_cpus as f64,
+    ])
+}
+
+/// The first `N` values of a single-record sample, in schema order.
+/// 单记录读数的前 `N` 个值，按模式顺序排列。
+fn single_values<const N: usize>(sample: &Sample) -> Option<[u64; N]> {
+    let values: [f64; N] = sample.records.first()?.values.get(..N)?.try_into().ok()?;
+    Some(values.map(|v| v as u64))
+}
+
+/// Rebuilds [`CpuStats`] from a sample of the `cpu` collector.
+/// 根据 `cpu` 采集器的读数重建 [`CpuStats`]。
+pub fn cpu_stats_from_sample(sample: &Sample) -> Option<CpuStats> {
+    let [user, nice, system, idle, iowait, irq, softirq, steal, guest, guest_nice] =
+        single_values(sample)?;
+    Some(CpuStats { user, nice, system, idle, iowait, irq, softirq, steal, guest, guest_nice })
+}
+
+/// Rebuilds [`MemoryStats`] from a sample of the `memory` collector.
+/// 根据 `memory` 采集器的读数重建 [`MemoryStats`]。
+pub fn memory_stats_from_sample(sample: &Sample) -> Option<MemoryStats> {
+    let [
+        total,
+        free,
+        available,
+        buffers,
+        cached,
+        active,
+        inactive,
+        dirty,
+        slab,
+        committed_as,
+        swap_total,
+        swap_free,
+    ] = single_values(sample)?;
+    Some(MemoryStats {
+        total,
+        free,
+        available,
+        buffers,
+        cached,
+        active,
+        inactive,
+        dirty,
+        slab,
+        committed_as,
+        swap_total,
+        swap_free,
+    })
+}
+
+/// Rebuilds [`LoadStats`] from a sample of the `load` collector. `last_pid`
+/// is not sampled and reads as 0.
+/// 根据 `load` 采集器的读数重建 [`LoadStats`]。`last_pid` 不会被采样，读作 0。
+pub fn load_stats_from_sample(sample: &Sample) -> Option<LoadStats> {
+    let values: [f64; 8] = sample.records.first()?.values.get(..8)?.try_into().ok()?;
+    let [load1, load5, load15, runnable, total_tasks, procs_running, procs_blocked, online_cpus] =
+        values;
+    Some(LoadStats {
+        load1,
+        load5,
+        load15,
+        runnable: runnable as u64,
+        total_tasks: total_tasks as u64,
+        last_pid: 0,
+        procs_running: procs_running as u64,
+        procs_blocked: procs_blocked as u64,
+        online_cpus: online_cpus as u64,
+    })
+}
+
+fn vm_sample(s: &VmStats) -> Sample {
+    Sample::single(
+        [
+            s.pgpgin,
+            s.pgpgout,
+            s.pswpin,
+            s.pswpout,
+            s.pgfault,
+            s.pgmajfault,
+            s.pgfree,
+            s.pgscan_kswapd,
+            s.pgscan_direct,
+            s.pgscan_khugepaged,
+            s.pgsteal_kswapd,
+            s.pgsteal_direct,
+            s.pgsteal_khugepaged,
+            s.compact_stall,
+            s.compact_fail,
+            s.compact_success,
+            s.compact_migrate_scanned,
+            s.compact_free_scanned,
+            s.numa_hit,
+            s.numa_miss,
+            s.numa_foreign,
+            s.numa_interleave,
+            s.numa_local,
+            s.numa_other,
+            s.numa_hint_faults,
+            s.numa_pages_migrated,
+        ]
+        .map(|v| v as f64)
+        .to_vec(),
+    )
+}
+
+fn disk_sample(disks: &[DiskStats]) -> Sample {
+    let records = disks
+        .iter()
+        .map(|d| Record {
+            instance: d.name.clone(),
+            values: [
+                d.reads,
+                d.sectors_read,
+                d.read_ms,
+                d.writes,
+                d.sectors_written,
+                d.write_ms,
+                d.discards,
+                d.sectors_discarded,
+                d.discard_ms,
+                d.io_ms,
+                d.weighted_io_ms,
+                d.in_flight,
+            ]
+            .map(|v| v as f64)
+            .to_vec(),
+        })
+        .collect();
+    Sample { records }
+}
+
+fn net_sample(interfaces: &[NetDevStats]) -> Sample {
+    let records = interfaces
+        .iter()
+        .map(|n| Record {
+            instance: n.name.clone(),
+            values: [
+                n.rx_bytes,
+                n.rx_packets,
+                n.rx_errs,
+                n.rx_drop,
+                n.rx_compressed,
+                n.rx_multicast,
+                n.tx_bytes,
+                n.tx_packets,
+                n.tx_errs,
+                n.tx_drop,
+                n.tx_compressed,
+                n.speed_mbps.unwrap_or(0),
+            ]
+            .map(|v| v as f64)
+            .to_vec(),
+        })
+        .collect();
+    Sample { records }
+}
+
+fn interrupt_sample(s: &InterruptStats) -> Sample {
+    let records = s
+        .irqs
+        .iter()
+        .map(|irq| Record {
+            instance: irq.name.clone(),
+            values: vec![irq.counts.iter().sum::<u64>() as f64],
+        })
+        .collect();
+    Sample { records }
+}
+
+fn softirq_sample(s: &SoftirqStats) -> Sample {
+    let records = s
+        .softirqs
+        .iter()
+        .map(|softirq| Record {
+            instance: softirq.name.clone(),
+            values: vec![softirq.total() as f64],
+        })
+        .collect();
+    Sample { records }
+}
+
+fn psi_sample(resources: &[(PsiResource, PressureStats)]) -> Sample {
+    let records = resources
+        .iter()
+        .map(|(resource, stats)| {
+            // Without a `full` line (system-wide CPU before 5.13) report zeros.
+            let full = stats.full.unwrap_or_default();
+            let mut values = Vec::with_capacity(8);
+            for line in [&stats.some, &full] {
+                values.extend([line.avg10, line.avg60, line.avg300, line.total as f64]);
+            }
+            Record { instance: resource.name().to_string(), values }
+        })
+        .collect();
+    Sample { records }
+}
+
+/// Reads `read(item)` for every item, skipping the ones that fail. Fails with
+/// the first error only if no item could be read.
+///
+/// 对每个元素执行 `read(item)`，跳过失败的元素。仅当所有元素都无法读取时才返回第一个错误。
+fn read_each<I, T, F>(items: I, read: F) -> Result<Vec<T>, PipaCollectorError>
+where
+    I: IntoIterator,
+    F: Fn(I::Item) -> Result<T, PipaCollectorError>,
+{
+    let mut values = Vec::new();
+    let mut first_err = None;
+    for item in items {
+        match read(item) {
+            Ok(value) => values.push(value),
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    match first_err {
+        Some(e) if values.is_empty() => Err(e),
+        _ => Ok(values),
+    }
+}
+
+/// Reads every PSI resource the kernel provides. IRQ pressure needs a newer
+/// kernel, so unreadable resources are skipped unless none can be read.
+///
+/// 读取内核提供的所有 PSI 资源。IRQ 压力需要较新的内核，
+/// 因此会跳过无法读取的资源，除非所有资源都无法读取。
+fn read_psi(root: &SysRoot) -> Result<Sample, PipaCollectorError> {
+    let resources = read_each(PsiResource::ALL, |resource| {
+        Ok((resource, system_stats::read_pressure_stats(root, resource)?))
+    })?;
+    Ok(psi_sample(&resources))
+}
+
+fn cgroup_record(cgroup: &str, s: &CgroupStats) -> Record {
+    let memory_stat = s.memory_stat.unwrap_or_default();
+    let events = s.memory_events.unwrap_or_default();
+    let io = s.io.as_deref().unwrap_or_default();
+    let io_sum = |field: fn(&cgroup_stats::CgroupIoStat) -> u64| io.iter().map(field).sum::<u64>();
+    let values = [
+        s.cpu.usage_usec,
+        s.cpu.user_usec,
+        s.cpu.system_usec,
+        s.cpu.nr_periods,
+        s.cpu.nr_throttled,
+        s.cpu.throttled_usec,
+        s.memory_current.unwrap_or(0),
+        memory_stat.anon,
+        memory_stat.file,
+        memory_stat.pgfault,
+        memory_stat.pgmajfault,
+        events.oom,
+        events.oom_kill,
+        io_sum(|d| d.rbytes),
+        io_sum(|d| d.wbytes),
+        io_sum(|d| d.rios),
+        io_sum(|d| d.wios),
+        s.pids_current.unwrap_or(0),
+    ]
+    .map(|v| v as f64)
+    .to_vec();
+    Record { instance: cgroup.to_string(), values }
+}
+
+fn process_record(s: &ProcessSnapshot) -> Record {
+    let counters = s.counters();
+    let values = [
+        counters.utime,
+        counters.stime,
+        counters.minflt,
+        counters.majflt,
+        counters.voluntary_ctxt_switches,
+        counters.nonvoluntary_ctxt_switches,
+        counters.io.read_bytes,
+        counters.io.write_bytes,
+        s.status.vm_rss,
+        s.status.threads,
+    ]
+    .map(|v| v as f64)
+    .to_vec();
+    Record { instance: s.stat.pid.to_string(), values }
+}
+
+fn cpufreq_sample(freqs: &[CpuFreq]) -> Sample {
+    let records = freqs
+        .iter()
+        .map(|f| Record {
+            instance: format!("cpu{}", f.cpu),
+            values: [f.cur_khz, f.min_khz, f.max_khz, f.hw_max_khz].map(|v| v as f64).to_vec(),
+        })
+        .collect();
+    Sample { records }
+}
+
+fn cpuidle_sample(states: &[CpuIdleState]) -> Sample {
+    let records = states
+        .iter()
+        .map(|s| Record {
+            instance: format!("cpu{}/{}", s.cpu, s.name),
+            values: vec![s.usage as f64, s.time_us as f64],
+        })
+        .collect();
+    Sample { records }
+}
+
+fn thermal_sample(zones: &[ThermalZone]) -> Sample {
+    let records = zones
+        .iter()
+        .map(|z| Record {
+            instance: format!("thermal_zone{}/{}", z.zone, z.kind),
+            values: vec![
+                z.temp_millicelsius as f64,
+                z.passive_trip_millicelsius.unwrap_or(0) as f64,
+            ],
+        })
+        .collect();
+    Sample { records }
+}
+
+/// The collectors whose samples [`cpu_telemetry_from_samples`] combines.
+/// 由 [`cpu_telemetry_from_samples`] 合并其读数的采集器。
+pub const CPU_TELEMETRY_COLLECTORS: [&str; 3] = ["cpufreq", "cpuidle", "thermal"];
+
+/// Rebuilds a [`CpuTelemetry`] reading from the samples of the `cpufreq`,
+/// `cpuidle` and `thermal` collectors, e.g. to summarize a sampler's
+/// snapshots. A missing sample leaves its list empty. Governors are not
+/// sampled and read as empty strings.
+///
+/// 根据 `cpufreq`、`cpuidle` 和 `thermal` 采集器的读数重建 [`CpuTelemetry`]，
+/// 例如用于汇总采样器的快照。缺少的读数对应的列表为空。调频策略不会被采样，读作空字符串。
+pub fn cpu_telemetry_from_samples(
+    cpufreq: Option<&Sample>,
+    cpuidle: Option<&Sample>,
+    thermal: Option<&Sample>,
+) -> CpuTelemetry {
+    let records = |sample: Option<&Sample>| sample.map(|s| s.records.clone()).unwrap_or_default();
+    // `<prefix><number>/<name>`, as written by the sample builders above.
+    let split = |instance: &str, prefix: &str| -> Option<(u32, String)> {
+        let rest = instance.strip_prefix(prefix)?;
+        let (number, name) = rest.split_once('/').unwrap_or((rest, ""));
+        Some((number.parse().ok()?, name.to_string()))
+    };
+    let value = |record: &Record, i: usize| record.values.get(i).copied().unwrap_or(0.0);
+
+    let freq = records(cpufreq)
+        .iter()
+        .filter_map(|r| {
+            Some(CpuFreq {
+                cpu: split(&r.instance, "cpu")?.0,
+                cur_khz: value(r, 0) as u64,
+                min_khz: value(r, 1) as u64,
+                max_khz: value(r, 2) as u64,
+                hw_max_khz: value(r, 3) as u64,
+                governor: String::new(),
+            })
+        })
+        .collect();
+
+    let mut idle: Vec<CpuIdleState> = Vec::new();
+    for r in records(cpuidle) {
+        let Some((cpu, name)) = split(&r.instance, "cpu") else {
+            continue;
+        };
+        // States are listed in index order for each CPU.
+        let index = idle.iter().filter(|s| s.cpu == cpu).count() as u32;
+        idle.push(CpuIdleState {
+            cpu,
+            index,
+            name,
+            usage: value(&r, 0) as u64,
+            time_us: value(&r, 1) as u64,
+        });
+    }
+
+    let thermal = records(thermal)
+        .iter()
+        .filter_map(|r| {
+            let (zone, kind) = split(&r.instance, "thermal_zone")?;
+            let trip = value(r, 1) as i64;
+            Some(ThermalZone {
+                zone,
+                kind,
+                temp_millicelsius: value(r, 0) as i64,
+                passive_trip_millicelsius: (trip != 0).then_some(trip),
+            })
+        })
+        .collect();
+
+    CpuTelemetry { freq, idle, thermal }
+}
+
+/// Reads one built-in collector from a root. / 从某个根目录读取一个内置采集器。
+type BuiltinRead = Box<dyn Fn(&SysRoot) -> Result<Sample, PipaCollectorError> + Send + Sync>;
+
+/// The collectors backed by this crate's parsers. / 基于本 crate 解析器的采集器。
+fn builtin_collectors(root: &SysRoot) -> Vec<Arc<dyn Collector>> {
+    use MetricKind::{Counter, Gauge};
+
+    let load_schema = [
+        metrics(Gauge, "", &["load1", "load5", "load15"]),
+        metrics(Gauge, "tasks", &["runnable", "total_tasks", "procs_running", "procs_blocked"]),
+        metrics(Gauge, "cpus", &["online_cpus"]),
+    ]
+    .concat();
+    let vm_schema = [
+        metrics(Counter, "kB", &["pgpgin", "pgpgout"]),
+        metrics(Counter, "pages", &["pswpin", "pswpout"]),
+        metrics(Counter, "events", &["pgfault", "pgmajfault"]),
+        metrics(
+            Counter,
+            "pages",
+            &[
+                "pgfree",
+                "pgscan_kswapd",
+                "pgscan_direct",
+                "pgscan_khugepaged",
+                "pgsteal_kswapd",
+                "pgsteal_direct",
+                "pgsteal_khugepaged",
+            ],
+        ),
+        metrics(Counter, "events", &["compact_stall", "compact_fail", "compact_success"]),
+        metrics(Counter, "pages", &["compact_migrate_scanned", "compact_free_scanned"]),
+        metrics(
+            Counter,
+            "pages",
+            &[
+                "numa_hit",
+                "numa_miss",
+                "numa_foreign",
+                "numa_interleave",
+                "numa_local",
+                "numa_other",
+            ],
+        ),
+        metrics(Counter, "events", &["numa_hint_faults"]),
+        metrics(Counter, "pages", &["numa_pages_migrated"]),
+    ]
+    .concat();
+    let disk_schema = [
+        metrics(Counter, "ios", &["reads"]),
+        metrics(Counter, "sectors", &["sectors_read"]),
+        metrics(Counter, "ms", &["read_ms"]),
+        metrics(Counter, "ios", &["writes"]),
+        metrics(Counter, "sectors", &["sectors_written"]),
+        metrics(Counter, "ms", &["write_ms"]),
+        metrics(Counter, "ios", &["discards"]),
+        metrics(Counter, "sectors", &["sectors_discarded"]),
+        metrics(Counter, "ms", &["discard_ms", "io_ms", "weighted_io_ms"]),
+        metrics(Gauge, "ios", &["in_flight"]),
+    ]
+    .concat();
+    let net_schema = [
+        metrics(Counter, "bytes", &["rx_bytes"]),
+        metrics(Counter, "packets", &["rx_packets", "rx_errs", "rx_drop", "rx_compressed"]),
+        metrics(Counter, "packets", &["rx_multicast"]),
+        metrics(Counter, "bytes", &["tx_bytes"]),
+        metrics(Counter, "packets", &["tx_packets", "tx_errs", "tx_drop", "tx_compressed"]),
+        metrics(Gauge, "Mbit/s", &["speed_mbps"]),
+    ]
+    .concat();
+
+    let memory_schema = metrics(
+        Gauge,
+        "kB",
+        &[
+            "total",
+            "free",
+            "available",
+            "buffers",
+            "cached",
+            "active",
+            "inactive",
+            "dirty",
+            "slab",
+            "committed_as",
+            "swap_total",
+            "swap_free",
+        ],
+    );
+    let psi_schema = [
+        metrics(Gauge, "%", &["some_avg10", "some_avg60", "some_avg300"]),
+        metrics(Counter, "us", &["some_total"]),
+        metrics(Gauge, "%", &["full_avg10", "full_avg60", "full_avg300"]),
+        metrics(Counter, "us", &["full_total"]),
+    ]
+    .concat();
+    let cpu_names = [
+        "user",
+        "nice",
+        "system",
+        "idle",
+        "iowait",
+        "irq",
+        "softirq",
+        "steal",
+        "guest",
+        "guest_nice",
+    ];
+
+    let builtin: [(&str, Vec<Metric>, BuiltinRead); 12] = [
+        (
+            "cpu",
+            metrics(Counter, "jiffies", &cpu_names),
+            Box::new(|root| system_stats::read_cpu_stats(root).map(|s| cpu_sample(&s))),
+        ),
+        (
+            "memory",
+            memory_schema,
+            Box::new(|root| system_stats::read_memory_stats(root).map(|s| memory_sample(&s))),
+        ),
+        (
+            "load",
+            load_schema,
+            Box::new(|root| system_stats::read_load_stats(root).map(|s| load_sample(&s))),
+        ),
+        (
+            "vm",
+            vm_schema,
+            Box::new(|root| system_stats::read_vm_stats(root).map(|s| vm_sample(&s))),
+        ),
+        (
+            "disk",
+            disk_schema,
+            Box::new(|root| system_stats::read_disk_stats(root).map(|s| disk_sample(&s))),
+        ),
+        (
+            "net",
+            net_schema,
+            Box::new(|root| system_stats::read_net_dev_stats(root).map(|s| net_sample(&s))),
+        ),
+        (
+            "interrupts",
+            metrics(Counter, "interrupts", &["count"]),
+            Box::new(|root| interrupt_stats::read_interrupts(root).map(|s| interrupt_sample(&s))),
+        ),
+        (
+            "softirqs",
+            metrics(Counter, "softirqs", &["count"]),
+            Box::new(|root| interrupt_stats::read_softirqs(root).map(|s| softirq_sample(&s))),
+        ),
+        (
+            "cpufreq",
+            metrics(Gauge, "kHz", &["cur_khz", "min_khz", "max_khz", "hw_max_khz"]),
+            Box::new(|root| cpu_telemetry::read_cpu_freqs(root).map(|s| cpufreq_sample(&s))),
+        ),
+        (
+            "cpuidle",
+            [metrics(Counter, "entries", &["usage"]), metrics(Counter, "us", &["time_us"])]
+                .concat(),
+            Box::new(|root| cpu_telemetry::read_cpu_idle_states(root).map(|s| cpuidle_sample(&s))),
+        ),
+        (
+            "thermal",
+            // A zone without a passive trip point reports 0.
+            metrics(Gauge, "m°C", &["temp", "passive_trip"]),
+            Box::new(|root| cpu_telemetry::read_thermal(root).map(|s| thermal_sample(&s))),
+        ),
+        ("psi", psi_schema, Box::new(read_psi)),
+    ];
+    builtin
+        .into_iter()
+        .map(|(name, schema, read)| {
+            let root = root.clone();
+            Arc::new(FnCollector::new(name, schema, move || read(&root))) as Arc<dyn Collector>
+        })
+        .collect()
+}
+
+/// A collector named `cgroup` with one record per cgroup v2 path in
+/// `cgroups`, e.g. `/system.slice/app.service`. Memory, I/O and PID figures
+/// read as 0 when their controller is not enabled. Removed cgroups are
+/// skipped.
+///
+/// 名为 `cgroup` 的采集器，`cgroups` 中每个 cgroup v2 路径
+/// （例如 `/system.slice/app.service`）对应一条记录。对应控制器未启用时，
+/// 内存、I/O 和 PID 数据读作 0。已删除的 cgroup 会被跳过。
+pub fn cgroup_collector(root: &SysRoot, cgroups: Vec<String>) -> impl Collector {
+    use MetricKind::{Counter, Gauge};
+
+    let schema = [
+        metrics(Counter, "us", &["usage_usec", "user_usec", "system_usec"]),
+        metrics(Counter, "periods", &["nr_periods", "nr_throttled"]),
+        metrics(Counter, "us", &["throttled_usec"]),
+        metrics(Gauge, "bytes", &["memory_current", "anon", "file"]),
+        metrics(Counter, "events", &["pgfault", "pgmajfault", "oom", "oom_kill"]),
+        metrics(Counter, "bytes", &["rbytes", "wbytes"]),
+        metrics(Counter, "ios", &["rios", "wios"]),
+        metrics(Gauge, "tasks", &["pids_current"]),
+    ]
+    .concat();
+    let cgroupfs = cgroup_stats::CgroupFs::at(root);
+    FnCollector::new("cgroup", schema, move || {
+        let records =
+            read_each(&cgroups, |cgroup| Ok(cgroup_record(cgroup, &cgroupfs.read_stats(cgroup)?)))?;
+        Ok(Sample { records })
+    })
+}
+
+/// A collector named `process` with one record per PID in `pids`, keyed by
+/// the PID. Counters cover all threads of the process. Processes that have
+/// exited are skipped.
+///
+/// 名为 `process` 的采集器，`pids` 中每个 PID 对应一条以 PID 为键的记录。
+/// 计数器涵盖进程的所有线程。已退出的进程会被跳过。
+pub fn process_collector(root: &SysRoot, pids: Vec<u32>) -> impl Collector {
+    use MetricKind::{Counter, Gauge};
+
+    let schema = [
+        metrics(Counter, "ticks", &["utime", "stime"]),
+        metrics(Counter, "faults", &["minflt", "majflt"]),
+        metrics(Counter, "switches", &["voluntary_ctxt_switches", "nonvoluntary_ctxt_switches"]),
+        metrics(Counter, "bytes", &["read_bytes", "write_bytes"]),
+        metrics(Gauge, "kB", &["rss"]),
+        metrics(Gauge, "threads", &["threads"]),
+    ]
+    .concat();
+    let root = root.clone();
+    FnCollector::new("process", schema, move || {
+        let records = read_each(&pids, |&pid| {
+            Ok(process_record(&process_stats::read_aggregated_process_snapshot(&root, pid)?))
+        })?;
+        Ok(Sample { records })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupt_stats::Irq;
+    use std::time::Duration;
+
+    fn schema() -> Vec<Metric> {
+        vec![Metric::counter("packets", "packets"), Metric::gauge("queue", "packets")]
+    }
+
+    fn record(instance: &str, values: &[f64]) -> Record {
+        Record { instance: instance.to_string(), values: values.to_vec() }
+    }
+
+    #[test]
+    fn test_sample_delta_semantics() {
+        let earlier = Sample { records: vec![record("eth0", &[100.0, 7.0])] };
+        let later = Sample {
+            records: vec![
+                record("eth0", &[150.0, 3.0]),
+                // No baseline for a new interface.
+                record("eth1", &[10.0, 1.0]),
+            ],
+        };
+        assert_eq!(later.delta(&earlier, &schema()).records, vec![record("eth0", &[50.0, 3.0])]);
+
+        // Counter reset, e.g. after the interface was recreated.
+        let reset = Sample { records: vec![record("eth0", &[20.0, 0.0])] };
+        assert_eq!(reset.delta(&earlier, &schema()).records, vec![record("eth0", &[0.0, 0.0])]);
+    }
+
+    #[test]
+    fn test_rates() {
+        let delta = SnapshotDelta {
+            elapsed: Duration::from_millis(500),
+            delta: Sample { records: vec![record("eth0", &[50.0, 3.0])] },
+        };
+        assert_eq!(delta.rates(&schema()).records, vec![record("eth0", &[100.0, 3.0])]);
+
+        let zero = SnapshotDelta { elapsed: Duration::ZERO, ..delta };
+        assert_eq!(zero.rates(&schema()).records, vec![record("eth0", &[0.0, 3.0])]);
+    }
+
+    #[test]
+    fn test_registry_register_and_select() {
+        let mut registry = CollectorRegistry::new();
+        let counter = FnCollector::new("fixture", schema(), || {
+            Ok(Sample { records: vec![record("eth0", &[1.0, 2.0])] })
+        });
+        registry.register(counter).unwrap();
+        assert!(
+            registry
+                .register(FnCollector::new("fixture", vec![], || Ok(Sample::default())))
+                .is_err()
+        );
+
+        let selected = registry.select(&["fixture"]).unwrap();
+        assert_eq!(selected[0].name(), "fixture");
+        assert_eq!(selected[0].sample().unwrap().get("eth0").unwrap().values, [1.0, 2.0]);
+
+        let err = registry.select(&["fixture", "gpu"]).err().unwrap().to_string();
+        assert!(err.contains("`gpu`") && err.contains("fixture"), "{}", err);
+    }
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = CollectorRegistry::builtin(&SysRoot::default());
+        assert_eq!(
+            registry.names(),
+            [
+                "cpu",
+                "memory",
+                "load",
+                "vm",
+                "disk",
+                "net",
+                "interrupts",
+                "softirqs",
+                "cpufreq",
+                "cpuidle",
+                "thermal",
+                "psi"
+            ]
+        );
+        for collector in registry.iter() {
+            let names: Vec<_> = collector.schema().iter().map(|m| &m.name).collect();
+            let mut unique = names.clone();
+            unique.sort();
+            unique.dedup();
+            assert_eq!(unique.len(), names.len(), "duplicate metric in {}", collector.name());
+        }
+    }
+
+    #[test]
+    fn test_builtin_collectors_read_sysroot() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("proc")).unwrap();
+        std::fs::write(dir.path().join("proc/stat"), "cpu  7 0 0 0 0 0 0 0 0 0\n").unwrap();
+        let registry = CollectorRegistry::builtin(&SysRoot::new(dir.path()));
+
+        let cpu = registry.get("cpu").unwrap().sample().unwrap();
+        assert_eq!(cpu.records[0].values[0], 7.0);
+        assert!(registry.get("memory").unwrap().sample().is_err());
+    }
+
+    #[test]
+    fn test_psi_collector_skips_missing_resources() {
+        let dir = crate::test_support::fixture_tree(&[
+            ("proc/pressure/cpu", "some avg10=1.50 avg60=0.00 avg300=0.00 total=1200\n"),
+            (
+                "proc/pressure/memory",
+                "some avg10=0.00 avg60=0.00 avg300=0.00 total=10\n\
+                 full avg10=2.00 avg60=0.00 avg300=0.00 total=20\n",
+            ),
+        ]);
+        let registry = CollectorRegistry::builtin(&SysRoot::new(dir.path()));
+        let psi = registry.get("psi").unwrap();
+        let sample = psi.sample().unwrap();
+        let instances: Vec<_> = sample.records.iter().map(|r| r.instance.as_str()).collect();
+        assert_eq!(instances, ["cpu", "memory"]);
+        for record in &sample.records {
+            assert_eq!(record.values.len(), psi.schema().len());
+        }
+        assert_eq!(sample.get("cpu").unwrap().value(psi.schema(), "some_avg10"), Some(1.5));
+        assert_eq!(sample.get("cpu").unwrap().value(psi.schema(), "full_total"), Some(0.0));
+        assert_eq!(sample.get("memory").unwrap().value(psi.schema(), "full_total"), Some(20.0));
+
+        let empty = tempfile::tempdir().unwrap();
+        let registry = CollectorRegistry::builtin(&SysRoot::new(empty.path()));
+        assert!(registry.get("psi").unwrap().sample().is_err());
+    }
+
+    #[test]
+    fn test_headline_stats_round_trip() {
+        let cpu = CpuStats { user: 5, idle: 90, guest_nice: 9, ..Default::default() };
+        assert_eq!(cpu_stats_from_sample(&cpu_sample(&cpu)), Some(cpu));
+        let memory =
+            MemoryStats { total: 1024, available: 512, swap_free: 3, ..Default::default() };
+        assert_eq!(memory_stats_from_sample(&memory_sample(&memory)), Some(memory));
+        let load = LoadStats { load1: 1.5, total_tasks: 70, online_cpus: 4, ..Default::default() };
+        assert_eq!(load_stats_from_sample(&load_sample(&load)), Some(load));
+
+        assert_eq!(cpu_stats_from_sample(&Sample::default()), None);
+        assert_eq!(memory_stats_from_sample(&Sample::single(vec![1.0])), None);
+    }
+
+    #[test]
+    fn test_cpu_telemetry_round_trip() {
+        let telemetry = CpuTelemetry {
+            freq: vec![CpuFreq {
+                cpu: 2,
+                cur_khz: 3_400_000,
+                min_khz: 800_000,
+                max_khz: 3_600_000,
+                hw_max_khz: 4_000_000,
+                governor: "powersave".to_string(),
+            }],
+            idle: vec![
+                CpuIdleState { cpu: 2, index: 0, name: "POLL".into(), usage: 1, time_us: 10 },
+                CpuIdleState { cpu: 2, index: 1, name: "C1E".into(), usage: 7, time_us: 5000 },
+            ],
+            thermal: vec![
+                ThermalZone {
+                    zone: 0,
+                    kind: "x86_pkg_temp".into(),
+                    temp_millicelsius: 91_000,
+                    passive_trip_millicelsius: Some(90_000),
+                },
+                ThermalZone {
+                    zone: 1,
+                    kind: "acpitz".into(),
+                    temp_millicelsius: 40_000,
+                    passive_trip_millicelsius: None,
+                },
+            ],
+        };
+        let registry = CollectorRegistry::builtin(&SysRoot::default());
+        let (cpufreq, cpuidle, thermal) = (
+            cpufreq_sample(&telemetry.freq),
+            cpuidle_sample(&telemetry.idle),
+            thermal_sample(&telemetry.thermal),
+        );
+        for (name, sample) in [("cpufreq", &cpufreq), ("cpuidle", &cpuidle), ("thermal", &thermal)]
+        {
+            let len = registry.get(name).unwrap().schema().len();
+            assert!(sample.records.iter().all(|r| r.values.len() == len), "{}", name);
+        }
+
+        let rebuilt = cpu_telemetry_from_samples(Some(&cpufreq), Some(&cpuidle), Some(&thermal));
+        let expected = CpuTelemetry {
+            freq: vec![CpuFreq { governor: String::new(), ..telemetry.freq[0].clone() }],
+            ..telemetry
+        };
+        assert_eq!(rebuilt, expected);
+        assert_eq!(cpu_telemetry_from_samples(None, None, None), CpuTelemetry::default());
+    }
+
+    #[test]
+    fn test_cgroup_collector_skips_removed_cgroups() {
+        let dir = crate::test_support::fixture_tree(&[
+            ("sys/fs/cgroup/cgroup.controllers", "cpu memory io pids\n"),
+            (
+                "sys/fs/cgroup/app/cpu.stat",
+                "usage_usec 900\nuser_usec 600\nsystem_usec 300\n\
+                 nr_periods 10\nnr_throttled 2\nthrottled_usec 50\n",
+            ),
+            ("sys/fs/cgroup/app/memory.current", "4096\n"),
+            (
+                "sys/fs/cgroup/app/io.stat",
+                "8:0 rbytes=10 wbytes=20 rios=1 wios=2 dbytes=0 dios=0\n\
+                 8:16 rbytes=5 wbytes=0 rios=1 wios=0 dbytes=0 dios=0\n",
+            ),
+        ]);
+        let root = SysRoot::new(dir.path());
+        let collector = cgroup_collector(&root, vec!["/app".into(), "/gone".into()]);
+        let sample = collector.sample().unwrap();
+        assert_eq!(sample.records.len(), 1);
+        let app = sample.get("/app").unwrap();
+        assert_eq!(app.values.len(), collector.schema().len());
+        assert_eq!(app.value(collector.schema(), "nr_throttled"), Some(2.0));
+        assert_eq!(app.value(collector.schema(), "memory_current"), Some(4096.0));
+        assert_eq!(app.value(collector.schema(), "rbytes"), Some(15.0));
+        // No pids controller files: reads as 0.
+        assert_eq!(app.value(collector.schema(), "pids_current"), Some(0.0));
+
+        assert!(cgroup_collector(&root, vec!["/gone".into()]).sample().is_err());
+    }
+
+    #[test]
+    fn test_process_collector() {
+        let mut snapshot = ProcessSnapshot::default();
+        snapshot.stat.pid = 42;
+        snapshot.stat.utime = 7;
+        snapshot.status.vm_rss = 2048;
+        let schema = process_collector(&SysRoot::default(), vec![]).schema().to_vec();
+        let record = process_record(&snapshot);
+        assert_eq!(record.instance, "42");
+        assert_eq!(record.values.len(), schema.len());
+        assert_eq!(record.value(&schema, "utime"), Some(7.0));
+        assert_eq!(record.value(&schema, "rss"), Some(2048.0));
+
+        let empty = tempfile::tempdir().unwrap();
+        let collector = process_collector(&SysRoot::new(empty.path()), vec![42]);
+        assert!(collector.sample().is_err());
+    }
+
+    #[test]
+    fn test_builtin_samples_match_schema() {
+        let registry = CollectorRegistry::builtin(&SysRoot::default());
+        let len = |name: &str| registry.get(name).unwrap().schema().len();
+
+        let cpu = cpu_sample(&CpuStats { user: 5, guest_nice: 9, ..Default::default() });
+        assert_eq!(cpu.records[0].values.len(), len("cpu"));
+        assert_eq!(cpu.records[0].values[0], 5.0);
+        assert_eq!(memory_sample(&MemoryStats::default()).records[0].values.len(), len("memory"));
+        assert_eq!(load_sample(&LoadStats::default()).records[0].values.len(), len("load"));
+        let vm =
+            vm_sample(&VmStats { compact_fail: 3, numa_pages_migrated: 8, ..Default::default() });
+        let vm_collector = registry.get("vm").unwrap();
+        assert_eq!(vm.records[0].values.len(), len("vm"));
+        assert_eq!(vm.records[0].value(vm_collector.schema(), "compact_fail"), Some(3.0));
+        assert_eq!(vm.records[0].value(vm_collector.schema(), "numa_pages_migrated"), Some(8.0));
+
+        let disks = [DiskStats { name: "sda".into(), in_flight: 2, ..Default::default() }];
+        let disk = disk_sample(&disks);
+        assert_eq!(disk.get("sda").unwrap().values.len(), len("disk"));
+        assert_eq!(
+            disk.get("sda").unwrap().value(registry.get("disk").unwrap().schema(), "in_flight"),
+            Some(2.0)
+        );
+        assert_eq!(disk.get("sda").unwrap().value(&schema(), "in_flight"), None);
+        let net = net_sample(&[NetDevStats { name: "eth0".into(), ..Default::default() }]);
+        assert_eq!(net.get("eth0").unwrap().values.len(), len("net"));
+
+        let irqs = InterruptStats {
+            cpus: vec![0, 1],
+            irqs: vec![Irq { name: "NMI".into(), counts: vec![3, 4], description: String::new() }],
+        };
+        assert_eq!(interrupt_sample(&irqs).get("NMI").unwrap().values, [7.0]);
+    }
+}
@@ -0,0 +1,646 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module collects CPU frequency (cpufreq), idle-state (cpuidle) and
+//! thermal-zone telemetry from sysfs, and summarizes the readings taken while
+//! counters run so that throttling can be flagged in reports.
+//!
+//! 本模块从 sysfs 收集 CPU 频率 (cpufreq)、空闲状态 (cpuidle) 和温度区 (thermal)
+//! 遥测数据，并汇总计数器运行期间采集的读数，以便在报告中标记降频情况。
+
+use crate::error::{ResultExt, read_file};
+use crate::sysroot::SysRoot;
+use crate::system_stats::{PipaCollectorError, Snapshot, numbered_entries};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// The cpufreq state of one CPU. Frequencies are in kHz.
+///
+/// 单个 CPU 的 cpufreq 状态，频率单位为 kHz。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CpuFreq {
+    /// Logical CPU number. / 逻辑 CPU 编号。
+    pub cpu: u32,
+    /// `scaling_cur_freq`. / 当前频率。
+    pub cur_khz: u64,
+    /// `scaling_min_freq`, the policy lower bound. / 策略下限。
+    pub min_khz: u64,
+    /// `scaling_max_freq`, the policy upper bound. Cooling devices lower this
+    /// when the CPU is thermally throttled.
+    /// 策略上限。CPU 因温度降频时，冷却设备会降低该值。
+    pub max_khz: u64,
+    /// `cpuinfo_max_freq`, the hardware maximum. / 硬件最高频率。
+    pub hw_max_khz: u64,
+    /// `scaling_governor`, e.g. `performance`. / 调频策略，例如 `performance`。
+    pub governor: String,
+}
+
+/// Cumulative statistics of one cpuidle state of one CPU.
+///
+/// 单个 CPU 的某个 cpuidle 状态的累计统计。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CpuIdleState {
+    /// Logical CPU number. / 逻辑 CPU 编号。
+    pub cpu: u32,
+    /// State index (`stateN`). / 状态索引 (`stateN`)。
+    pub index: u32,
+    /// State name, e.g. `C1E`. / 状态名，例如 `C1E`。
+    pub name: String,
+    /// Number of times the state was entered. / 进入该状态的次数。
+    pub usage: u64,
+    /// Total residency in microseconds. / 总驻留时间（微秒）。
+    pub time_us: u64,
+}
+
+/// The temperature of one thermal zone.
+///
+/// 单个温度区的温度。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct ThermalZone {
+    /// Zone number (`thermal_zoneN`). / 温度区编号 (`thermal_zoneN`)。
+    pub zone: u32,
+    /// Zone type, e.g. `x86_pkg_temp`. / 温度区类型，例如 `x86_pkg_temp`。
+    pub kind: String,
+    /// Temperature in millidegrees Celsius. / 温度（毫摄氏度）。
+    pub temp_millicelsius: i64,
+    /// Lowest `passive` trip point, above which the kernel starts throttling.
+    /// 最低的 `passive` 触发点，超过该温度内核开始降频。
+    pub passive_trip_millicelsius: Option<i64>,
+}
+
+/// One reading of all CPU telemetry. Each list is empty when the platform
+/// does not expose the corresponding interface (e.g. inside most VMs).
+///
+/// 一次完整的 CPU 遥测读数。平台未提供相应接口时（例如大多数虚拟机中），
+/// 对应列表为空。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct CpuTelemetry {
+    /// cpufreq state per CPU. / 每个 CPU 的 cpufreq 状态。
+    pub freq: Vec<CpuFreq>,
+    /// cpuidle states of all CPUs. / 所有 CPU 的 cpuidle 状态。
+    pub idle: Vec<CpuIdleState>,
+    /// Thermal zones. / 温度区。
+    pub thermal: Vec<ThermalZone>,
+}
+
+/// Reads a sysfs attribute, trimming the trailing newline.
+/// 读取 sysfs 属性并去掉末尾换行符。
+fn read_attr(path: &Path) -> Result<String, PipaCollectorError> {
+    Ok(read_file(path)?.trim().to_string())
+}
+
+/// Reads a numeric sysfs attribute. / 读取数值型 sysfs 属性。
+fn read_num<T: std::str::FromStr<Err = std::num::ParseIntError>>(
+    path: &Path,
+) -> Result<T, PipaCollectorError> {
+    read_attr(path)?.parse::<T>().at_path(path)
+}
+
+/// Reads the cpufreq state of `cpu`, or `None` if it has no cpufreq policy or
+/// its current frequency cannot be read, as for an offline CPU.
+/// 读取 `cpu` 的 cpufreq 状态；若该 CPU 没有 cpufreq 策略或无法读取当前频率
+///（例如 CPU 已离线），则返回 `None`。
+fn read_cpu_freq(cpu_root: &Path, cpu: u32) -> Result<Option<CpuFreq>, PipaCollectorError> {
+    let dir = cpu_root.join(format!("cpu{}/cpufreq", cpu));
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let cur_path = dir.join("scaling_cur_freq");
+    let Ok(cur) = read_file(&cur_path) else {
+        return Ok(None);
+    };
+    Ok(Some(CpuFreq {
+        cpu,
+        cur_khz: cur.trim().parse().at_path(&cur_path)?,
+        min_khz: read_num(&dir.join("scaling_min_freq"))?,
+        max_khz: read_num(&dir.join("scaling_max_freq"))?,
+        hw_max_khz: read_num(&dir.join("cpuinfo_max_freq"))?,
+        governor: read_attr(&dir.join("scaling_governor"))?,
+    }))
+}
+
+/// Reads all cpuidle states of `cpu`. / 读取 `cpu` 的所有 cpuidle 状态。
+fn read_cpu_idle(cpu_root: &Path, cpu: u32) -> Result<Vec<CpuIdleState>, PipaCollectorError> {
+    let dir = cpu_root.join(format!("cpu{}/cpuidle", cpu));
+    numbered_entries(&dir, "state")?
+        .into_iter()
+        .map(|index| {
+            let state = dir.join(format!("state{}", index));
+            Ok(CpuIdleState {
+                cpu,
+                index,
+                name: read_attr(&state.join("name"))?,
+                usage: read_num(&state.join("usage"))?,
+                time_us: read_num(&state.join("time"))?,
+            })
+        })
+        .collect()
+}
+
+/// Finds the lowest `passive` trip point of a thermal zone.
+/// 查找温度区最低的 `passive` 触发点。
+fn read_passive_trip(zone_dir: &Path) -> Result<Option<i64>, PipaCollectorError> {
+    let mut lowest: Option<i64> = None;
+    for index in trip_point_indices(zone_dir)? {
+        let kind = zone_dir.join(format!("trip_point_{}_type", index));
+        if read_attr(&kind).ok().as_deref() == Some("passive") {
+            let temp = read_num::<i64>(&zone_dir.join(format!("trip_point_{}_temp", index)))?;
+            lowest = Some(lowest.map_or(temp, |l| l.min(temp)));
+        }
+    }
+    Ok(lowest)
+}
+
+/// Returns the indices N of `trip_point_N_type` files in a thermal zone.
+/// 返回温度区中 `trip_point_N_type` 文件的索引 N。
+fn trip_point_indices(zone_dir: &Path) -> Result<Vec<u32>, PipaCollectorError> {
+    let mut indices = Vec::new();
+    for entry in std::fs::read_dir(zone_dir).at_path(zone_dir)? {
+        let name = entry.at_path(zone_dir)?.file_name();
+        if let Some(index) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("trip_point_"))
+            .and_then(|n| n.strip_suffix("_type"))
+            .and_then(|n| n.parse::<u32>().ok())
+        {
+            indices.push(index);
+        }
+    }
+    indices.sort_unstable();
+    Ok(indices)
+}
+
+/// Reads all thermal zones under `thermal_root`. Zones whose sensor cannot be
+/// read (some firmware returns `EINVAL` or `ENODATA`) are skipped.
+///
+/// 读取 `thermal_root` 下的所有温度区。传感器无法读取的温度区
+///（部分固件会返回 `EINVAL` 或 `ENODATA`）会被跳过。
+fn read_thermal_zones(thermal_root: &Path) -> Result<Vec<ThermalZone>, PipaCollectorError> {
+    let mut zones = Vec::new();
+    for zone in numbered_entries(thermal_root, "thermal_zone")? {
+        let dir = thermal_root.join(format!("thermal_zone{}", zone));
+        let Ok(temp_millicelsius) = read_num::<i64>(&dir.join("temp")) else {
+            continue;
+        };
+        zones.push(ThermalZone {
+            zone,
+            kind: read_attr(&dir.join("type"))?,
+            temp_millicelsius,
+            passive_trip_millicelsius: read_passive_trip(&dir)?,
+        });
+    }
+    Ok(zones)
+}
+
+/// Reads CPU telemetry from a CPU sysfs root (normally
+/// `/sys/devices/system/cpu`) and a thermal root (normally
+/// `/sys/class/thermal`).
+///
+/// 从 CPU sysfs 根目录（通常为 `/sys/devices/system/cpu`）和温度根目录
+///（通常为 `/sys/class/thermal`）读取 CPU 遥测数据。
+pub fn read_cpu_telemetry_from_paths<P: AsRef<Path>, Q: AsRef<Path>>(
+    cpu_root: P,
+    thermal_root: Q,
+) -> Result<CpuTelemetry, PipaCollectorError> {
+    let cpu_root = cpu_root.as_ref();
+    let mut telemetry = CpuTelemetry::default();
+    for cpu in numbered_entries(cpu_root, "cpu")? {
+        if let Some(freq) = read_cpu_freq(cpu_root, cpu)? {
+            telemetry.freq.push(freq);
+        }
+        telemetry.idle.extend(read_cpu_idle(cpu_root, cpu)?);
+    }
+    telemetry.thermal = read_thermal_zones(thermal_root.as_ref())?;
+    Ok(telemetry)
+}
+
+/// Reads CPU telemetry from the live system.
+/// 从当前系统读取 CPU 遥测数据。
+pub fn read_cpu_telemetry(root: &SysRoot) -> Result<CpuTelemetry, PipaCollectorError> {
+    read_cpu_telemetry_from_paths(root.sys("devices/system/cpu"), root.sys("class/thermal"))
+}
+
+/// Reads the cpufreq state of every CPU that has a readable policy.
+/// 读取每个拥有可读 cpufreq 策略的 CPU 的状态。
+pub fn read_cpu_freqs(root: &SysRoot) -> Result<Vec<CpuFreq>, PipaCollectorError> {
+    let cpu_root = root.sys("devices/system/cpu");
+    let mut freqs = Vec::new();
+    for cpu in numbered_entries(&cpu_root, "cpu")? {
+        freqs.extend(read_cpu_freq(&cpu_root, cpu)?);
+    }
+    Ok(freqs)
+}
+
+/// Reads the cpuidle states of every CPU. / 读取每个 CPU 的 cpuidle 状态。
+pub fn read_cpu_idle_states(root: &SysRoot) -> Result<Vec<CpuIdleState>, PipaCollectorError> {
+    let cpu_root = root.sys("devices/system/cpu");
+    let mut states = Vec::new();
+    for cpu in numbered_entries(&cpu_root, "cpu")? {
+        states.extend(read_cpu_idle(&cpu_root, cpu)?);
+    }
+    Ok(states)
+}
+
+/// Reads every thermal zone with a readable sensor.
+/// 读取所有传感器可读的温度区。
+pub fn read_thermal(root: &SysRoot) -> Result<Vec<ThermalZone>, PipaCollectorError> {
+    read_thermal_zones(&root.sys("class/thermal"))
+}
+
+/// Residency of one idle state, summed over all CPUs, during a recording.
+///
+/// 记录期间某个空闲状态在所有 CPU 上的驻留时间之和。
+#[derive(Debug, PartialEq, Clone)]
+pub struct IdleResidency {
+    /// State name. / 状态名。
+    pub name: String,
+    /// Residency in microseconds summed over CPUs. / 各 CPU 驻留时间之和（微秒）。
+    pub time_us: u64,
+    /// Share of the total CPU time (elapsed time × CPUs), 0.0–1.0.
+    /// 占总 CPU 时间（经过时间 × CPU 数）的比例，0.0–1.0。
+    pub share: f64,
+}
+
+/// A summary of a telemetry series recorded during a run.
+///
+/// 对一次运行期间记录的遥测时间序列的汇总。
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct TelemetrySummary {
+    /// Number of samples. / 样本数。
+    pub samples: usize,
+    /// Mean of `scaling_cur_freq` over all CPUs and samples.
+    /// 所有 CPU 和样本的 `scaling_cur_freq` 平均值。
+    pub mean_freq_khz: Option<f64>,
+    /// Lowest observed frequency. / 观测到的最低频率。
+    pub min_freq_khz: Option<u64>,
+    /// Highest observed frequency. / 观测到的最高频率。
+    pub max_freq_khz: Option<u64>,
+    /// CPUs whose policy maximum dropped below its value at the start of the
+    /// run. Static caps, such as disabled turbo, are not counted.
+    /// 策略上限在运行期间降到低于运行开始时取值的 CPU；
+    /// 静态上限（例如关闭 turbo）不计入。
+    pub capped_cpus: Vec<u32>,
+    /// Hottest observed temperature. / 观测到的最高温度。
+    pub peak_temp_millicelsius: Option<i64>,
+    /// Types of the zones that reached their passive trip point.
+    /// 达到 passive 触发点的温度区类型。
+    pub hot_zones: Vec<String>,
+    /// Idle-state residency between the first and last sample.
+    /// 第一个与最后一个样本之间的空闲状态驻留情况。
+    pub idle: Vec<IdleResidency>,
+}
+
+impl TelemetrySummary {
+    /// Whether the run shows signs of frequency or thermal throttling.
+    /// 运行期间是否出现频率或温度降频的迹象。
+    pub fn is_throttled(&self) -> bool {
+        !self.capped_cpus.is_empty() || !self.hot_zones.is_empty()
+    }
+}
+
+/// The frequency range seen on one CPU. / 单个 CPU 上观测到的频率范围。
+#[derive(Debug, Clone, Copy)]
+struct FreqRange {
+    /// Policy maximum in the first reading. / 第一个读数中的策略上限。
+    start_max: u64,
+    min: u64,
+    max: u64,
+    sum: f64,
+    count: u64,
+}
+
+/// Builds a [`TelemetrySummary`] one sample at a time, keeping running
+/// per-CPU frequency statistics and only the first and last idle readings,
+/// so memory use does not grow with the length of the run.
+///
+/// 逐个样本地构建 [`TelemetrySummary`]，只保留每个 CPU 的频率统计以及第一个和
+/// 最后一个空闲状态读数，因此内存占用不会随运行时长增长。
+#[derive(Debug, Default, Clone)]
+pub struct TelemetrySummarizer {
+    samples: usize,
+    freq: BTreeMap<u32, FreqRange>,
+    capped: BTreeSet<u32>,
+    peak_temp_millicelsius: Option<i64>,
+    hot_zones: Vec<String>,
+    first_idle: Option<Snapshot<Vec<CpuIdleState>>>,
+    last_idle: Option<Snapshot<Vec<CpuIdleState>>>,
+}
+
+impl TelemetrySummarizer {
+    /// Creates an empty summarizer. / 创建一个空的汇总器。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one reading. / 加入一个读数。
+    pub fn add(&mut self, sample: &Snapshot<CpuTelemetry>) {
+        self.samples += 1;
+        for f in &sample.stats.freq {
+            let range = self.freq.entry(f.cpu).or_insert(FreqRange {
+                start_max: f.max_khz,
+                min: f.cur_khz,
+                max: f.cur_khz,
+                sum: 0.0,
+                count: 0,
+            });
+            range.min = range.min.min(f.cur_khz);
+            range.max = range.max.max(f.cur_khz);
+            range.sum += f.cur_khz as f64;
+            range.count += 1;
+            if f.max_khz < range.start_max {
+                self.capped.insert(f.cpu);
+            }
+        }
+
+        for zone in &sample.stats.thermal {
+            self.peak_temp_millicelsius =
+                self.peak_temp_millicelsius.max(Some(zone.temp_millicelsius));
+            let hot = zone.passive_trip_millicelsius.is_some_and(|t| zone.temp_millicelsius >= t);
+            if hot && !self.hot_zones.contains(&zone.kind) {
+                self.hot_zones.push(zone.kind.clone());
+            }
+        }
+
+        let idle = Snapshot { timestamp: sample.timestamp, stats: sample.stats.idle.clone() };
+        if self.first_idle.is_none() {
+            self.first_idle = Some(idle.clone());
+        }
+        self.last_idle = Some(idle);
+    }
+
+    /// Summarizes the readings added so far. / 汇总目前已加入的读数。
+    pub fn summary(&self) -> TelemetrySummary {
+        let mut summary = TelemetrySummary {
+            samples: self.samples,
+            capped_cpus: self.capped.iter().copied().collect(),
+            peak_temp_millicelsius: self.peak_temp_millicelsius,
+            hot_zones: self.hot_zones.clone(),
+            ..Default::default()
+        };
+
+        let count: u64 = self.freq.values().map(|r| r.count).sum();
+        if count > 0 {
+            summary.mean_freq_khz =
+                Some(self.freq.values().map(|r| r.sum).sum::<f64>() / count as f64);
+            summary.min_freq_khz = self.freq.values().map(|r| r.min).min();
+            summary.max_freq_khz = self.freq.values().map(|r| r.max).max();
+        }
+
+        if let (Some(first), Some(last)) = (&self.first_idle, &self.last_idle) {
+            let elapsed_us = last.timestamp.saturating_sub(first.timestamp).as_micros() as u64;
+            let cpus = last.stats.iter().map(|s| s.cpu).collect::<BTreeSet<_>>();
+            let capacity_us = elapsed_us * cpus.len() as u64;
+            for state in &last.stats {
+                let earlier = first
+                    .stats
+                    .iter()
+                    .find(|s| s.cpu == state.cpu && s.index == state.index)
+                    .map_or(0, |s| s.time_us);
+                let time_us = state.time_us.saturating_sub(earlier);
+                match summary.idle.iter_mut().find(|r| r.name == state.name) {
+                    Some(residency) => residency.time_us += time_us,
+                    None => summary.idle.push(IdleResidency {
+                        name: state.name.clone(),
+                        time_us,
+                        share: 0.0,
+                    }),
+                }
+            }
+            for residency in &mut summary.idle {
+                residency.share = if capacity_us > 0 {
+                    residency.time_us as f64 / capacity_us as f64
+                } else {
+                    0.0
+                };
+            }
+        }
+        summary
+    }
+}
+
+/// Summarizes a recorded series: frequency spread, throttling and idle-state
+/// residency.
+///
+/// 汇总记录的时间序列：频率范围、降频情况以及空闲状态驻留情况。
+pub fn summarize_telemetry(series: &[Snapshot<CpuTelemetry>]) -> TelemetrySummary {
+    let mut summarizer = TelemetrySummarizer::new();
+    for sample in series {
+        summarizer.add(sample);
+    }
+    summarizer.summary()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write;
+    use std::time::Duration;
+
+    /// Builds a two-CPU sysfs tree where cpu1 is capped at 2.0 GHz.
+    fn fixture() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        let cpu = root.path().join("cpu");
+        write(cpu.join("online"), "0-1\n");
+        for (n, cur, max) in [(0, "3400000", "3600000"), (1, "2000000", "2000000")] {
+            let freq = cpu.join(format!("cpu{}/cpufreq", n));
+            write(freq.join("scaling_cur_freq"), &format!("{}\n", cur));
+            write(freq.join("scaling_min_freq"), "800000\n");
+            write(freq.join("scaling_max_freq"), &format!("{}\n", max));
+            write(freq.join("cpuinfo_max_freq"), "3600000\n");
+            write(freq.join("scaling_governor"), "powersave\n");
+            for (i, name, time) in [(0, "POLL", "10"), (1, "C1E", "5000")] {
+                let state = cpu.join(format!("cpu{}/cpuidle/state{}", n, i));
+                write(state.join("name"), &format!("{}\n", name));
+                write(state.join("usage"), "7\n");
+                write(state.join("time"), &format!("{}\n", time));
+            }
+        }
+
+        let thermal = root.path().join("thermal");
+        let zone = thermal.join("thermal_zone0");
+        write(zone.join("type"), "x86_pkg_temp\n");
+        write(zone.join("temp"), "91000\n");
+        write(zone.join("trip_point_0_type"), "critical\n");
+        write(zone.join("trip_point_0_temp"), "105000\n");
+        write(zone.join("trip_point_1_type"), "passive\n");
+        write(zone.join("trip_point_1_temp"), "90000\n");
+        write(thermal.join("thermal_zone1/type"), "acpitz\n");
+        root
+    }
+
+    #[test]
+    fn test_read_cpu_telemetry_from_paths() {
+        let root = fixture();
+        let t = read_cpu_telemetry_from_paths(root.path().join("cpu"), root.path().join("thermal"))
+            .unwrap();
+
+        assert_eq!(t.freq.len(), 2);
+        assert_eq!(
+            t.freq[0],
+            CpuFreq {
+                cpu: 0,
+                cur_khz: 3400000,
+                min_khz: 800000,
+                max_khz: 3600000,
+                hw_max_khz: 3600000,
+                governor: "powersave".to_string(),
+            }
+        );
+        assert_eq!(t.idle.len(), 4);
+        assert_eq!(t.idle[1].name, "C1E");
+        assert_eq!(t.idle[1].time_us, 5000);
+
+        // thermal_zone1 has no readable temp and is skipped.
+        assert_eq!(t.thermal.len(), 1);
+        assert_eq!(t.thermal[0].kind, "x86_pkg_temp");
+        assert_eq!(t.thermal[0].temp_millicelsius, 91000);
+        assert_eq!(t.thermal[0].passive_trip_millicelsius, Some(90000));
+    }
+
+    #[test]
+    fn test_read_cpu_telemetry_without_interfaces() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("cpu/cpu0/topology")).unwrap();
+        let t = read_cpu_telemetry_from_paths(root.path().join("cpu"), root.path().join("none"))
+            .unwrap();
+        assert_eq!(t, CpuTelemetry::default());
+    }
+
+    #[test]
+    fn test_read_cpu_telemetry_skips_offline_cpu() {
+        let root = fixture();
+        std::fs::remove_file(root.path().join("cpu/cpu1/cpufreq/scaling_cur_freq")).unwrap();
+        let t = read_cpu_telemetry_from_paths(root.path().join("cpu"), root.path().join("thermal"))
+            .unwrap();
+        assert_eq!(t.freq.len(), 1);
+        assert_eq!(t.freq[0].cpu, 0);
+        assert_eq!(t.idle.len(), 4);
+    }
+
+    #[test]
+    fn test_read_cpu_telemetry_malformed() {
+        let root = fixture();
+        write(root.path().join("cpu/cpu0/cpufreq/scaling_cur_freq"), "fast\n");
+        let result =
+            read_cpu_telemetry_from_paths(root.path().join("cpu"), root.path().join("thermal"));
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::Parse(_)));
+
+        write(root.path().join("cpu/cpu0/cpufreq/scaling_cur_freq"), "2000000\n");
+        write(root.path().join("cpu/cpu0/cpufreq/scaling_min_freq"), "low\n");
+        let err =
+            read_cpu_telemetry_from_paths(root.path().join("cpu"), root.path().join("thermal"))
+                .unwrap_err();
+        assert!(matches!(err.root_cause(), PipaCollectorError::Parse(_)));
+        assert!(err.to_string().contains("cpu0/cpufreq/scaling_min_freq"), "{}", err);
+    }
+
+    fn sample(secs: u64, cur: [u64; 2], max1: u64, temp: i64, c1e: u64) -> Snapshot<CpuTelemetry> {
+        let freq = (0..2)
+            .map(|cpu| CpuFreq {
+                cpu,
+                cur_khz: cur[cpu as usize],
+                max_khz: if cpu == 1 { max1 } else { 3600000 },
+                hw_max_khz: 3600000,
+                ..Default::default()
+            })
+            .collect();
+        let idle = (0..2)
+            .map(|cpu| CpuIdleState {
+                cpu,
+                index: 1,
+                name: "C1E".to_string(),
+                usage: 0,
+                time_us: c1e,
+            })
+            .collect();
+        let thermal = vec![ThermalZone {
+            zone: 0,
+            kind: "x86_pkg_temp".to_string(),
+            temp_millicelsius: temp,
+            passive_trip_millicelsius: Some(90000),
+        }];
+        Snapshot {
+            timestamp: Duration::from_secs(secs),
+            stats: CpuTelemetry { freq, idle, thermal },
+        }
+    }
+
+    #[test]
+    fn test_summarize_telemetry_flags_throttling() {
+        let series = vec![
+            sample(10, [3600000, 3600000], 3600000, 70000, 0),
+            sample(11, [3000000, 2000000], 2000000, 95000, 500000),
+        ];
+        let summary = summarize_telemetry(&series);
+
+        assert_eq!(summary.samples, 2);
+        assert_eq!(summary.mean_freq_khz, Some(3050000.0));
+        assert_eq!(summary.min_freq_khz, Some(2000000));
+        assert_eq!(summary.max_freq_khz, Some(3600000));
+        assert_eq!(summary.capped_cpus, vec![1]);
+        assert_eq!(summary.peak_temp_millicelsius, Some(95000));
+        assert_eq!(summary.hot_zones, vec!["x86_pkg_temp".to_string()]);
+        assert!(summary.is_throttled());
+
+        // 2 CPUs x 1s = 2,000,000us of capacity, 1,000,000us spent in C1E.
+        assert_eq!(summary.idle.len(), 1);
+        assert_eq!(summary.idle[0].time_us, 1000000);
+        assert!((summary.idle[0].share - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_summarize_telemetry_steady_run() {
+        let series = vec![
+            sample(0, [3600000, 3600000], 3600000, 60000, 0),
+            sample(1, [3600000, 3600000], 3600000, 65000, 0),
+        ];
+        let summary = summarize_telemetry(&series);
+        assert!(!summary.is_throttled());
+        assert!(summarize_telemetry(&[]).mean_freq_khz.is_none());
+    }
+
+    #[test]
+    fn test_summarize_telemetry_ignores_static_cap() {
+        // Turbo is off for the whole run: cpu1 never reaches the hardware
+        // maximum, but its cap does not move.
+        let series = vec![
+            sample(0, [3600000, 2000000], 2000000, 60000, 0),
+            sample(1, [3600000, 2000000], 2000000, 60000, 0),
+        ];
+        let summary = summarize_telemetry(&series);
+        assert!(summary.capped_cpus.is_empty());
+        assert!(!summary.is_throttled());
+    }
+
+    #[test]
+    fn test_summarizer_is_incremental() {
+        let series: Vec<_> = (0..5)
+            .map(|i| sample(i, [3600000 - i * 100000, 3000000], 3600000, 60000, i * 1000))
+            .collect();
+        let mut summarizer = TelemetrySummarizer::new();
+        summarizer.add(&series[0]);
+        assert_eq!(summarizer.summary().samples, 1);
+        for sample in &series[1..] {
+            summarizer.add(sample);
+        }
+        let summary = summarizer.summary();
+        assert_eq!(summary.samples, 5);
+        assert_eq!(summary.mean_freq_khz, Some(3200000.0));
+        assert_eq!(summary.min_freq_khz, Some(3000000));
+        assert_eq!(summary.max_freq_khz, Some(3600000));
+        // Idle residency spans the first and last readings: 2 CPUs x 4000us.
+        assert_eq!(summary.idle[0].time_us, 8000);
+    }
+}
@@ -0,0 +1,569 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Preflight checks behind `pipa doctor`: the kernel settings, privileges,
+//! PMUs and environment that decide which collectors and perf events work on
+//! this machine, each with a remediation hint.
+//!
+//! `pipa doctor` 背后的预检：检查决定本机哪些采集器和 perf 事件可用的内核设置、
+//! 权限、PMU 和运行环境，并为每项给出处理建议。
+
+use crate::cgroup_stats::{CgroupFs, CgroupVersion};
+use crate::error::PipaCollectorError;
+use crate::sysroot::SysRoot;
+use std::path::Path;
+
+/// `CAP_SYS_ADMIN`, which grants perf access on every kernel.
+/// `CAP_SYS_ADMIN`，在所有内核上都授予 perf 访问权限。
+pub const CAP_SYS_ADMIN: u32 = 21;
+/// `CAP_SYSLOG`, which reveals kernel addresses under `kptr_restrict` 1.
+/// `CAP_SYSLOG`，在 `kptr_restrict` 为 1 时可查看内核地址。
+pub const CAP_SYSLOG: u32 = 34;
+/// `CAP_PERFMON`, the dedicated perf capability since Linux 5.8.
+/// `CAP_PERFMON`，自 Linux 5.8 起专用于 perf 的权限。
+pub const CAP_PERFMON: u32 = 38;
+
+/// PMUs that are implemented in software and exist on every machine.
+/// 由软件实现、在所有机器上都存在的 PMU。
+const SOFTWARE_PMUS: [&str; 5] = ["software", "tracepoint", "breakpoint", "kprobe", "uprobe"];
+
+/// The outcome of a check, ordered from best to worst.
+/// 检查结果，按从好到坏排序。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    /// The label printed by `pipa doctor`. / `pipa doctor` 打印的标签。
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Ok => "OK",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        }
+    }
+}
+
+/// The result of one check. / 单项检查的结果。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// What was checked, e.g. `perf_event_paranoid`. / 检查项，例如 `perf_event_paranoid`。
+    pub check: &'static str,
+    pub status: Status,
+    /// What was found. / 检查发现的情况。
+    pub detail: String,
+    /// How to fix a warning or failure. / 如何修复警告或失败。
+    pub hint: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, detail: String) -> Self {
+        Finding { check, status: Status::Ok, detail, hint: None }
+    }
+
+    fn warn(check: &'static str, detail: String, hint: &str) -> Self {
+        Finding { check, status: Status::Warn, detail, hint: Some(hint.to_string()) }
+    }
+
+    fn fail(check: &'static str, detail: String, hint: &str) -> Self {
+        Finding { check, status: Status::Fail, detail, hint: Some(hint.to_string()) }
+    }
+}
+
+/// The effective capability set of a process, from `CapEff` in
+/// `/proc/<pid>/status`.
+///
+/// 进程的有效权限集，来自 `/proc/<pid>/status` 中的 `CapEff`。
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(pub u64);
+
+impl Capabilities {
+    /// Whether capability number `cap` is set. / 是否拥有编号为 `cap` 的权限。
+    pub fn has(self, cap: u32) -> bool {
+        self.0 & (1 << cap) != 0
+    }
+
+    /// Whether perf is not restricted by `perf_event_paranoid`.
+    /// perf 是否不受 `perf_event_paranoid` 限制。
+    pub fn perf_privileged(self) -> bool {
+        self.has(CAP_PERFMON) || self.has(CAP_SYS_ADMIN)
+    }
+}
+
+/// Parses `CapEff` from the content of `/proc/<pid>/status`.
+/// 从 `/proc/<pid>/status` 的内容中解析 `CapEff`。
+pub fn parse_capabilities(status: &str) -> Result<Capabilities, PipaCollectorError> {
+    let value = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .ok_or_else(|| PipaCollectorError::MissingData("CapEff not found in status".into()))?;
+    u64::from_str_radix(value.trim(), 16).map(Capabilities).map_err(PipaCollectorError::from)
+}
+
+/// Parses the `major.minor` part of a kernel release such as `6.8.0-45-generic`.
+/// 解析内核版本号（例如 `6.8.0-45-generic`）中的 `major.minor` 部分。
+pub fn parse_kernel_version(release: &str) -> Option<(u32, u32)> {
+    let mut parts = release.split(['.', '-']);
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Reads a single-line integer setting. / 读取单行整数设置。
+fn read_setting(path: &Path) -> Option<i32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Reads a file for string matching, treating a missing file as empty.
+/// 读取文件用于字符串匹配，文件不存在时视为空。
+fn read_or_empty(path: &Path) -> String {
+    std::fs::read_to_string(path).unwrap_or_default()
+}
+
+/// Checks the kernel release. / 检查内核版本。
+pub fn check_kernel(release: Option<&str>) -> Finding {
+    const CHECK: &str = "kernel";
+    let Some(release) = release else {
+        return Finding::fail(
+            CHECK,
+            "cannot read sys/kernel/osrelease".into(),
+            "check that procfs is mounted, or pass --procfs",
+        );
+    };
+    match parse_kernel_version(release) {
+        Some(version) if version < (5, 8) => Finding::warn(
+            CHECK,
+            format!("Linux {}", release),
+            "kernels before 5.8 have no CAP_PERFMON; perf access needs CAP_SYS_ADMIN or a \
+             lower perf_event_paranoid",
+        ),
+        _ => Finding::ok(CHECK, format!("Linux {}", release)),
+    }
+}
+
+/// Reports the perf-related capabilities of PIPA itself.
+/// 报告 PIPA 自身与 perf 相关的权限。
+pub fn check_capabilities(caps: Option<Capabilities>) -> Finding {
+    const CHECK: &str = "capabilities";
+    let Some(caps) = caps else {
+        return Finding::warn(
+            CHECK,
+            "cannot read CapEff from self/status".into(),
+            "check that procfs is mounted, or pass --procfs",
+        );
+    };
+    let held: Vec<&str> = [(CAP_PERFMON, "CAP_PERFMON"), (CAP_SYS_ADMIN, "CAP_SYS_ADMIN")]
+        .into_iter()
+        .filter(|(cap, _)| caps.has(*cap))
+        .map(|(_, name)| name)
+        .collect();
+    if held.is_empty() {
+        Finding::warn(
+            CHECK,
+            "neither CAP_PERFMON nor CAP_SYS_ADMIN".into(),
+            "perf access is limited by perf_event_paranoid; run as root or grant the binary \
+             CAP_PERFMON with `setcap cap_perfmon+ep $(which pipa_rs)`",
+        )
+    } else {
+        Finding::ok(CHECK, held.join(", "))
+    }
+}
+
+/// Checks `kernel.perf_event_paranoid` against PIPA's privileges.
+/// 结合 PIPA 的权限检查 `kernel.perf_event_paranoid`。
+pub fn check_perf_event_paranoid(value: Option<i32>, caps: Capabilities) -> Finding {
+    const CHECK: &str = "perf_event_paranoid";
+    const HINT: &str = "lower it with `sysctl -w kernel.perf_event_paranoid=1`, or run with \
+                        CAP_PERFMON or CAP_SYS_ADMIN";
+    match value {
+        None => Finding::fail(
+            CHECK,
+            "sys/kernel/perf_event_paranoid not found".into(),
+            "the kernel was built without CONFIG_PERF_EVENTS; perf events are unavailable",
+        ),
+        Some(level) if caps.perf_privileged() => {
+            Finding::ok(CHECK, format!("{} (not enforced: privileged)", level))
+        }
+        Some(level) if level <= 1 => Finding::ok(CHECK, level.to_string()),
+        Some(2) => {
+            Finding::warn(CHECK, "2: only user-space events of your own processes".into(), HINT)
+        }
+        Some(level) => Finding::fail(
+            CHECK,
+            format!("{}: perf_event_open is disabled for unprivileged users", level),
+            HINT,
+        ),
+    }
+}
+
+/// Checks `kernel.kptr_restrict`, which hides kernel symbol addresses.
+/// 检查 `kernel.kptr_restrict`，该设置会隐藏内核符号地址。
+pub fn check_kptr_restrict(value: Option<i32>, caps: Capabilities) -> Finding {
+    const CHECK: &str = "kptr_restrict";
+    const HINT: &str = "kernel frames cannot be symbolized; lower it with \
+                        `sysctl -w kernel.kptr_restrict=0`";
+    match value {
+        None => Finding::warn(
+            CHECK,
+            "sys/kernel/kptr_restrict not found".into(),
+            "kernel symbol visibility is unknown; check that procfs is mounted",
+        ),
+        Some(0) => Finding::ok(CHECK, "0".into()),
+        Some(1) if caps.has(CAP_SYSLOG) => {
+            Finding::ok(CHECK, "1 (addresses visible: CAP_SYSLOG)".into())
+        }
+        Some(level) => {
+            Finding::warn(CHECK, format!("{}: kernel addresses read as zero", level), HINT)
+        }
+    }
+}
+
+/// Lists the PMUs registered under `bus/event_source/devices`.
+/// 列出 `bus/event_source/devices` 下注册的 PMU。
+pub fn list_pmus(root: &SysRoot) -> Vec<String> {
+    let mut pmus: Vec<String> = std::fs::read_dir(root.sys("bus/event_source/devices"))
+        .map(|entries| entries.filter_map(|e| e.ok()?.file_name().into_string().ok()).collect())
+        .unwrap_or_default();
+    pmus.sort();
+    pmus
+}
+
+/// Whether a PMU name is a CPU core PMU, e.g. `cpu`, `cpu_core` or
+/// `armv8_pmuv3_0`. / PMU 名称是否为 CPU 核心 PMU。
+fn is_core_pmu(name: &str) -> bool {
+    name == "cpu" || name.starts_with("cpu_") || name.starts_with("armv") || name == "cpum_cf"
+}
+
+/// Checks which PMUs the kernel exposes. / 检查内核提供了哪些 PMU。
+pub fn check_pmus(pmus: &[String]) -> Finding {
+    const CHECK: &str = "PMUs";
+    if pmus.is_empty() {
+        return Finding::fail(
+            CHECK,
+            "none under bus/event_source/devices".into(),
+            "the kernel has no perf support, or sysfs is not mounted (see --sysfs)",
+        );
+    }
+    let detail = pmus.join(" ");
+    if pmus.iter().any(|p| is_core_pmu(p)) {
+        Finding::ok(CHECK, detail)
+    } else if pmus.iter().all(|p| SOFTWARE_PMUS.contains(&p.as_str())) {
+        Finding::warn(
+            CHECK,
+            detail,
+            "only software PMUs; hardware events need a CPU PMU (enable the vPMU in a VM)",
+        )
+    } else {
+        Finding::warn(CHECK, detail, "no CPU core PMU; cycles and instructions will not be counted")
+    }
+}
+
+/// Turns the result of opening a hardware event, and of the software
+/// fallback, into a finding.
+///
+/// 根据打开硬件事件及其软件回退事件的结果生成检查结论。
+pub fn check_counters(
+    hardware: Result<(), PipaCollectorError>,
+    software: Result<(), PipaCollectorError>,
+) -> Finding {
+    const CHECK: &str = "hardware counters";
+    match (hardware, software) {
+        (Ok(()), _) => Finding::ok(CHECK, "cycles can be counted".into()),
+        (Err(hw), Ok(())) => Finding {
+            check: CHECK,
+            status: Status::Warn,
+            detail: format!("{}; software events (cpu-clock) work", hw.root_cause()),
+            hint: Some(hw.hint().unwrap_or_else(|| {
+                "hardware events are unavailable; software events still work".to_string()
+            })),
+        },
+        (Err(hw), Err(sw)) => Finding {
+            check: CHECK,
+            status: Status::Fail,
+            detail: format!("{}; cpu-clock also failed: {}", hw.root_cause(), sw.root_cause()),
+            hint: Some(sw.hint().unwrap_or_else(|| {
+                "perf_event_open does not work here; it may be blocked by seccomp".to_string()
+            })),
+        },
+    }
+}
+
+/// Detects a hypervisor from the CPU flags, `/sys/hypervisor` and DMI.
+/// 通过 CPU 标志、`/sys/hypervisor` 和 DMI 信息检测虚拟机管理程序。
+pub fn detect_hypervisor(root: &SysRoot) -> Option<String> {
+    let hypervisor_type = read_or_empty(&root.sys("hypervisor/type"));
+    if !hypervisor_type.trim().is_empty() {
+        return Some(hypervisor_type.trim().to_string());
+    }
+    let dmi = format!(
+        "{} {}",
+        read_or_empty(&root.sys("class/dmi/id/sys_vendor")).trim(),
+        read_or_empty(&root.sys("class/dmi/id/product_name")).trim()
+    );
+    const VENDORS: [&str; 8] =
+        ["QEMU", "KVM", "VMware", "VirtualBox", "Xen", "Microsoft", "Amazon EC2", "Google"];
+    if let Some(vendor) = VENDORS.iter().find(|v| dmi.contains(*v)) {
+        return Some(vendor.to_string());
+    }
+    let cpuinfo = read_or_empty(&root.proc("cpuinfo"));
+    let flagged = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"));
+    flagged.then(|| "unknown hypervisor".to_string())
+}
+
+/// Reports whether PIPA runs in a virtual machine. / 报告 PIPA 是否运行在虚拟机中。
+pub fn check_virtualization(hypervisor: Option<String>) -> Finding {
+    const CHECK: &str = "virtualization";
+    match hypervisor {
+        None => Finding::ok(CHECK, "bare metal".into()),
+        Some(name) => Finding::warn(
+            CHECK,
+            format!("virtual machine ({})", name),
+            "hardware counters need a virtual PMU, e.g. `-cpu host` with KVM or \
+             `vpmc.enable` on VMware",
+        ),
+    }
+}
+
+/// Detects a container runtime from marker files under `fs_root` and from
+/// the environment and cgroup of PID 1.
+///
+/// 通过 `fs_root` 下的标记文件以及 1 号进程的环境变量和 cgroup 检测容器运行时。
+pub fn detect_container(root: &SysRoot, fs_root: &Path) -> Option<String> {
+    if fs_root.join(".dockerenv").exists() {
+        return Some("docker".into());
+    }
+    if fs_root.join("run/.containerenv").exists() {
+        return Some("podman".into());
+    }
+    let environ = read_or_empty(&root.proc("1/environ"));
+    if let Some(runtime) = environ.split('\0').find_map(|var| var.strip_prefix("container=")) {
+        return Some(runtime.to_string());
+    }
+    let cgroup = read_or_empty(&root.proc("1/cgroup"));
+    ["kubepods", "docker", "containerd", "libpod", "lxc"]
+        .into_iter()
+        .find(|runtime| cgroup.contains(runtime))
+        .map(String::from)
+}
+
+/// Reports whether PIPA runs in a container. / 报告 PIPA 是否运行在容器中。
+pub fn check_container(runtime: Option<String>) -> Finding {
+    const CHECK: &str = "container";
+    match runtime {
+        None => Finding::ok(CHECK, "not detected".into()),
+        Some(runtime) => Finding::warn(
+            CHECK,
+            format!("running in a container ({})", runtime),
+            "seccomp may block perf_event_open (use --cap-add PERFMON or --privileged), and \
+             host-wide statistics need the host's /proc and /sys via --procfs/--sysfs",
+        ),
+    }
+}
+
+/// Reports the cgroup hierarchy layout. / 报告 cgroup 层级布局。
+pub fn check_cgroup(root: &SysRoot) -> Finding {
+    const CHECK: &str = "cgroup";
+    const HINT: &str = "cgroup statistics need the unified hierarchy; boot with \
+                        systemd.unified_cgroup_hierarchy=1";
+    match CgroupFs::at(root).version() {
+        Ok(CgroupVersion::V2) => Finding::ok(CHECK, "v2 (unified)".into()),
+        Ok(CgroupVersion::Hybrid) => {
+            Finding::warn(CHECK, "hybrid (v1 controllers, v2 at unified/)".into(), HINT)
+        }
+        Ok(CgroupVersion::V1) => Finding::warn(CHECK, "v1".into(), HINT),
+        Err(e) => Finding::warn(CHECK, e.to_string(), "mount cgroup2 on /sys/fs/cgroup"),
+    }
+}
+
+/// Finds where tracefs can be reached, from `/proc/self/mounts`: a tracefs
+/// mount, or the `tracing` directory of a debugfs mount.
+///
+/// 根据 `/proc/self/mounts` 找到 tracefs 的访问位置：tracefs 挂载点，
+/// 或 debugfs 挂载点下的 `tracing` 目录。
+pub fn find_tracefs(mounts: &str) -> Option<String> {
+    let entries: Vec<(&str, &str)> = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_, target, fstype) = (fields.next()?, fields.next()?, fields.next()?);
+            Some((target, fstype))
+        })
+        .collect();
+    let tracefs = entries.iter().find(|(_, fstype)| *fstype == "tracefs");
+    let debugfs = entries.iter().find(|(_, fstype)| *fstype == "debugfs");
+    tracefs
+        .map(|(target, _)| target.to_string())
+        .or_else(|| debugfs.map(|(target, _)| format!("{}/tracing", target)))
+}
+
+/// Reports whether tracefs is mounted. / 报告 tracefs 是否已挂载。
+pub fn check_tracefs(mount: Option<String>) -> Finding {
+    const CHECK: &str = "tracefs";
+    match mount {
+        Some(path) => Finding::ok(CHECK, format!("mounted at {}", path)),
+        None => Finding::warn(
+            CHECK,
+            "neither tracefs nor debugfs is mounted".into(),
+            "tracepoint events are unavailable; mount it with \
+             `mount -t tracefs nodev /sys/kernel/tracing`",
+        ),
+    }
+}
+
+/// Runs every check against the live system. The hardware counter check
+/// opens real perf events, and the capability and mount checks describe
+/// PIPA's own process, so they read the real `/proc/self`; everything else
+/// is read through `root`.
+///
+/// 对当前系统运行所有检查。硬件计数器检查会打开真实的 perf 事件，
+/// 权限与挂载检查描述的是 PIPA 自身进程，因此读取真实的 `/proc/self`；
+/// 其余检查均通过 `root` 读取。
+#[cfg(not(tarpaulin_include))]
+pub fn run_checks(root: &SysRoot) -> Vec<Finding> {
+    use crate::raw_perf_events::{PerfEvent, probe_event};
+
+    let release = std::fs::read_to_string(root.proc("sys/kernel/osrelease")).ok();
+    let caps = std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| parse_capabilities(&status).ok());
+    let privileges = caps.unwrap_or_default();
+    vec![
+        check_kernel(release.as_deref().map(str::trim)),
+        check_capabilities(caps),
+        check_perf_event_paranoid(
+            read_setting(&root.proc("sys/kernel/perf_event_paranoid")),
+            privileges,
+        ),
+        check_kptr_restrict(read_setting(&root.proc("sys/kernel/kptr_restrict")), privileges),
+        check_pmus(&list_pmus(root)),
+        check_counters(
+            probe_event(root, &PerfEvent::Cycles),
+            probe_event(root, &PerfEvent::CpuClock),
+        ),
+        check_virtualization(detect_hypervisor(root)),
+        check_container(detect_container(root, Path::new("/"))),
+        check_cgroup(root),
+        check_tracefs(find_tracefs(&read_or_empty(Path::new("/proc/self/mounts")))),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::fixture_tree;
+
+    #[test]
+    fn test_parse_capabilities_and_kernel_version() {
+        let caps = parse_capabilities("Name:\tpipa\nCapEff:\t0000004000000000\n").unwrap();
+        assert!(caps.has(CAP_PERFMON) && !caps.has(CAP_SYS_ADMIN));
+        assert!(caps.perf_privileged());
+        let root = parse_capabilities("CapEff:\t000001ffffffffff\n").unwrap();
+        assert!(root.has(CAP_SYS_ADMIN) && root.has(CAP_SYSLOG));
+        assert!(parse_capabilities("Name:\tpipa\n").is_err());
+
+        assert_eq!(parse_kernel_version("6.8.0-45-generic"), Some((6, 8)));
+        assert_eq!(parse_kernel_version("5.4.0"), Some((5, 4)));
+        assert_eq!(parse_kernel_version("garbage"), None);
+        assert_eq!(check_kernel(Some("5.4.0-1")).status, Status::Warn);
+        assert_eq!(check_kernel(Some("6.1.0")).status, Status::Ok);
+        assert_eq!(check_kernel(None).status, Status::Fail);
+    }
+
+    #[test]
+    fn test_privilege_checks() {
+        let none = Capabilities(0);
+        let admin = Capabilities(1 << CAP_SYS_ADMIN);
+        assert_eq!(check_capabilities(Some(none)).status, Status::Warn);
+        assert_eq!(check_capabilities(Some(admin)).detail, "CAP_SYS_ADMIN");
+
+        assert_eq!(check_perf_event_paranoid(Some(-1), none).status, Status::Ok);
+        assert_eq!(check_perf_event_paranoid(Some(2), none).status, Status::Warn);
+        assert_eq!(check_perf_event_paranoid(Some(3), none).status, Status::Fail);
+        assert_eq!(check_perf_event_paranoid(Some(4), admin).status, Status::Ok);
+        assert_eq!(check_perf_event_paranoid(None, admin).status, Status::Fail);
+
+        assert_eq!(check_kptr_restrict(Some(0), none).status, Status::Ok);
+        assert_eq!(check_kptr_restrict(Some(1), Capabilities(1 << CAP_SYSLOG)).status, Status::Ok);
+        let restricted = check_kptr_restrict(Some(1), none);
+        assert_eq!(restricted.status, Status::Warn);
+        assert!(restricted.hint.unwrap().contains("kptr_restrict=0"));
+    }
+
+    #[test]
+    fn test_pmu_and_counter_checks() {
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(check_pmus(&names(&["cpu", "software"])).status, Status::Ok);
+        assert_eq!(check_pmus(&names(&["armv8_pmuv3_0"])).status, Status::Ok);
+        assert_eq!(check_pmus(&names(&["software", "tracepoint"])).status, Status::Warn);
+        assert_eq!(check_pmus(&names(&["msr", "software"])).status, Status::Warn);
+        assert_eq!(check_pmus(&[]).status, Status::Fail);
+
+        let unsupported = || Err(PipaCollectorError::EventNotSupported("cycles".into()));
+        assert_eq!(check_counters(Ok(()), unsupported()).status, Status::Ok);
+        let fallback = check_counters(unsupported(), Ok(()));
+        assert_eq!(fallback.status, Status::Warn);
+        assert!(fallback.detail.contains("cpu-clock"));
+        let denied = Err(PipaCollectorError::PermissionDenied {
+            operation: "cpu-clock".into(),
+            paranoid: Some(3),
+        });
+        let failed = check_counters(unsupported(), denied);
+        assert_eq!(failed.status, Status::Fail);
+        assert!(failed.hint.unwrap().contains("perf_event_paranoid is 3"));
+    }
+
+    #[test]
+    fn test_environment_detection() {
+        let dir = fixture_tree(&[
+            ("proc/cpuinfo", "processor\t: 0\nflags\t\t: fpu sse2 hypervisor\n"),
+            ("proc/1/cgroup", "0::/kubepods/burstable/pod1/abc\n"),
+            ("sys/fs/cgroup/cgroup.controllers", "cpu memory\n"),
+            ("sys/bus/event_source/devices/software/type", "1\n"),
+            ("sys/bus/event_source/devices/cpu/type", "4\n"),
+        ]);
+        let root = SysRoot::new(dir.path());
+        assert_eq!(detect_hypervisor(&root).as_deref(), Some("unknown hypervisor"));
+        assert_eq!(detect_container(&root, dir.path()).as_deref(), Some("kubepods"));
+        assert_eq!(check_cgroup(&root).detail, "v2 (unified)");
+        assert_eq!(list_pmus(&root), ["cpu", "software"]);
+
+        std::fs::write(dir.path().join(".dockerenv"), "").unwrap();
+        assert_eq!(detect_container(&root, dir.path()).as_deref(), Some("docker"));
+
+        let bare = fixture_tree(&[("proc/cpuinfo", "flags\t\t: fpu sse2\n")]);
+        let bare_root = SysRoot::new(bare.path());
+        assert_eq!(detect_hypervisor(&bare_root), None);
+        assert_eq!(detect_container(&bare_root, bare.path()), None);
+        assert_eq!(check_cgroup(&bare_root).status, Status::Warn);
+        assert_eq!(check_virtualization(None).status, Status::Ok);
+        assert_eq!(check_container(Some("docker".into())).status, Status::Warn);
+    }
+
+    #[test]
+    fn test_find_tracefs() {
+        let mounts = "sysfs /sys sysfs rw 0 0\n\
+                      debugfs /sys/kernel/debug debugfs rw 0 0\n";
+        assert_eq!(find_tracefs(mounts).as_deref(), Some("/sys/kernel/debug/tracing"));
+        let mounts = format!("{}tracefs /sys/kernel/tracing tracefs rw 0 0\n", mounts);
+        assert_eq!(find_tracefs(&mounts).as_deref(), Some("/sys/kernel/tracing"));
+        assert_eq!(check_tracefs(None).status, Status::Warn);
+        assert_eq!(find_tracefs("proc /proc proc rw 0 0\n"), None);
+    }
+}
@@ -0,0 +1,433 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The error type shared by every collector, with the file and line that
+//! failed, perf-specific variants and stable error codes for front ends.
+//!
+//! 所有采集器共用的错误类型，包含出错的文件和行号、perf 专用的错误变体，
+//! 以及供前端使用的稳定错误码。
+
+use std::fmt;
+use std::io;
+use std::num::{ParseFloatError, ParseIntError};
+use std::path::{Path, PathBuf};
+
+/// A number that could not be parsed. / 无法解析的数字。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// An integer field. / 整数字段。
+    Int(ParseIntError),
+    /// A floating point field. / 浮点数字段。
+    Float(ParseFloatError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Int(e) => e.fmt(f),
+            ParseError::Float(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Int(e) => Some(e),
+            ParseError::Float(e) => Some(e),
+        }
+    }
+}
+
+/// A stable, machine-readable error category. Front ends and scripts can match
+/// on it; the message text may change between releases.
+///
+/// 稳定、机器可读的错误类别。前端和脚本可以据此匹配；错误信息文本可能会在版本之间变化。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// A file or process does not exist. / 文件或进程不存在。
+    NotFound,
+    /// Any other I/O error. / 其他 I/O 错误。
+    Io,
+    /// A number could not be parsed. / 数字无法解析。
+    Parse,
+    /// Content had an unexpected format. / 内容格式不符合预期。
+    Format,
+    /// Expected data was missing. / 缺少预期的数据。
+    Missing,
+    /// The kernel interface or configuration is not supported. / 不支持的内核接口或配置。
+    Unsupported,
+    /// The kernel refused access. / 内核拒绝访问。
+    Permission,
+    /// The requested perf event is not available. / 请求的 perf 事件不可用。
+    EventNotSupported,
+    /// Too many events or file descriptors. / 事件或文件描述符过多。
+    TooManyEvents,
+}
+
+impl ErrorCode {
+    /// The code as printed, e.g. `E_PERMISSION`. / 打印形式的错误码，例如 `E_PERMISSION`。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::NotFound => "E_NOT_FOUND",
+            ErrorCode::Io => "E_IO",
+            ErrorCode::Parse => "E_PARSE",
+            ErrorCode::Format => "E_FORMAT",
+            ErrorCode::Missing => "E_MISSING",
+            ErrorCode::Unsupported => "E_UNSUPPORTED",
+            ErrorCode::Permission => "E_PERMISSION",
+            ErrorCode::EventNotSupported => "E_EVENT_NOT_SUPPORTED",
+            ErrorCode::TooManyEvents => "E_TOO_MANY_EVENTS",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A unified error type for all potential failures within the `pipa_collector`.
+/// This enum allows for precise error handling.
+///
+/// `pipa_collector` 中所有潜在失败的统一错误类型。
+/// 这个枚举允许我们进行精确的错误处理。
+#[derive(Debug)]
+pub enum PipaCollectorError {
+    /// Represents an I/O error that occurred while reading a file (e.g., from
+    /// `/proc`). 代表在读取文件时发生的 I/O 错误（例如，从 `/proc`
+    /// 读取时）。
+    Io(io::Error),
+    /// Represents an error that occurred while parsing a string into a number.
+    /// 代表在将字符串解析为数字时发生的错误。
+    Parse(ParseError),
+    /// Represents a format mismatch in the parsed file content.
+    /// 代表在解析的文件内容中出现格式不匹配。
+    InvalidFormat(String),
+    /// Represents missing data where it was expected.
+    /// 代表在预期位置缺少数据。
+    MissingData(String),
+    /// Represents a kernel interface or configuration that is present but not
+    /// supported by PIPA (e.g. a cgroup v1 hierarchy).
+    /// 代表存在但 PIPA 不支持的内核接口或配置（例如 cgroup v1 层级）。
+    Unsupported(String),
+    /// The kernel refused a perf or procfs operation. `paranoid` is the value
+    /// of `/proc/sys/kernel/perf_event_paranoid` when it could be read.
+    /// 内核拒绝了 perf 或 procfs 操作。`paranoid` 为可读取时
+    /// `/proc/sys/kernel/perf_event_paranoid` 的值。
+    PermissionDenied { operation: String, paranoid: Option<i32> },
+    /// The CPU, PMU or kernel does not provide the requested event (e.g. no
+    /// hardware counters inside a VM).
+    /// CPU、PMU 或内核不提供所请求的事件（例如虚拟机中没有硬件计数器）。
+    EventNotSupported(String),
+    /// More events were requested than the PMU or the file descriptor limit
+    /// allows. / 请求的事件数超过了 PMU 或文件描述符限制所允许的数量。
+    TooManyEvents(String),
+    /// Another error, together with the file and/or line it occurred at.
+    /// 另一个错误，附带其发生的文件和/或行号。
+    Context { path: Option<PathBuf>, line: Option<usize>, source: Box<PipaCollectorError> },
+}
+
+impl PipaCollectorError {
+    /// Attaches the file that was being read. An existing path is kept, so
+    /// the innermost (most precise) path wins.
+    ///
+    /// 附加正在读取的文件。已有的路径会被保留，因此最内层（最精确）的路径优先。
+    pub fn at_path<P: AsRef<Path>>(self, path: P) -> Self {
+        match self {
+            PipaCollectorError::Context { path: None, line, source } => {
+                PipaCollectorError::Context {
+                    path: Some(path.as_ref().to_path_buf()),
+                    line,
+                    source,
+                }
+            }
+            context @ PipaCollectorError::Context { .. } => context,
+            other => PipaCollectorError::Context {
+                path: Some(path.as_ref().to_path_buf()),
+                line: None,
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Attaches a 1-based line number. / 附加从 1 开始的行号。
+    pub fn at_line(self, line: usize) -> Self {
+        match self {
+            PipaCollectorError::Context { path, line: None, source } => {
+                PipaCollectorError::Context { path, line: Some(line), source }
+            }
+            context @ PipaCollectorError::Context { .. } => context,
+            other => PipaCollectorError::Context {
+                path: None,
+                line: Some(line),
+                source: Box::new(other),
+            },
+        }
+    }
+
+    /// Classifies a failed `perf_event_open` call by its errno, the way
+    /// `perf` explains them. `what` names the event and target; `paranoid`
+    /// is the current `kernel.perf_event_paranoid`, if readable.
+    ///
+    /// 按 errno 对失败的 `perf_event_open` 调用进行分类，与 `perf` 的解释方式一致。
+    /// `what` 描述事件和目标；`paranoid` 为当前的 `kernel.perf_event_paranoid`（如可读取）。
+    pub fn from_perf_open(err: io::Error, what: &str, paranoid: Option<i32>) -> Self {
+        let message = format!("perf_event_open failed for {}: {}", what, err);
+        match err.raw_os_error() {
+            Some(libc::EACCES | libc::EPERM) => {
+                PipaCollectorError::PermissionDenied { operation: message, paranoid }
+            }
+            Some(libc::ENOENT | libc::ENODEV | libc::EOPNOTSUPP) => {
+                PipaCollectorError::EventNotSupported(message)
+            }
+            Some(libc::EMFILE | libc::ENFILE | libc::ENOSPC) => {
+                PipaCollectorError::TooManyEvents(message)
+            }
+            _ => PipaCollectorError::Io(io::Error::new(err.kind(), message)),
+        }
+    }
+
+    /// The error without any path or line context. / 去掉路径和行号上下文后的错误。
+    pub fn root_cause(&self) -> &PipaCollectorError {
+        match self {
+            PipaCollectorError::Context { source, .. } => source.root_cause(),
+            other => other,
+        }
+    }
+
+    /// The file the error occurred in, if known. / 出错的文件（如果已知）。
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            PipaCollectorError::Context { path: Some(path), .. } => Some(path),
+            PipaCollectorError::Context { source, .. } => source.path(),
+            _ => None,
+        }
+    }
+
+    /// Whether the error is a file that does not exist. / 错误是否为文件不存在。
+    pub fn is_not_found(&self) -> bool {
+        matches!(self.root_cause(), PipaCollectorError::Io(e) if e.kind() == io::ErrorKind::NotFound)
+    }
+
+    /// The stable code for the kind of error. / 表示错误类别的稳定错误码。
+    pub fn code(&self) -> ErrorCode {
+        match self.root_cause() {
+            PipaCollectorError::Io(e) if e.kind() == io::ErrorKind::NotFound => ErrorCode::NotFound,
+            PipaCollectorError::Io(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                ErrorCode::Permission
+            }
+            PipaCollectorError::Io(_) => ErrorCode::Io,
+            PipaCollectorError::Parse(_) => ErrorCode::Parse,
+            PipaCollectorError::InvalidFormat(_) => ErrorCode::Format,
+            PipaCollectorError::MissingData(_) => ErrorCode::Missing,
+            PipaCollectorError::Unsupported(_) => ErrorCode::Unsupported,
+            PipaCollectorError::PermissionDenied { .. } => ErrorCode::Permission,
+            PipaCollectorError::EventNotSupported(_) => ErrorCode::EventNotSupported,
+            PipaCollectorError::TooManyEvents(_) => ErrorCode::TooManyEvents,
+            PipaCollectorError::Context { .. } => unreachable!("root_cause strips context"),
+        }
+    }
+
+    /// What the user can do about the error, if there is a known remedy.
+    /// 用户可以如何处理该错误（如果有已知的解决办法）。
+    pub fn hint(&self) -> Option<String> {
+        match self.root_cause() {
+            PipaCollectorError::PermissionDenied { paranoid, .. } => {
+                let setting = match paranoid {
+                    Some(level) => format!("kernel.perf_event_paranoid is {}", level),
+                    None => "kernel.perf_event_paranoid could not be read".to_string(),
+                };
+                Some(format!(
+                    "{}; run as root, grant CAP_PERFMON (CAP_SYS_ADMIN before Linux 5.8), \
+                     or lower it with `sysctl -w kernel.perf_event_paranoid=1`",
+                    setting
+                ))
+            }
+            PipaCollectorError::Io(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                Some("run as root or as the owner of the target process".to_string())
+            }
+            PipaCollectorError::EventNotSupported(_) => Some(
+                "the CPU or hypervisor does not expose this event; check \
+                 /sys/bus/event_source/devices or use a software event"
+                    .to_string(),
+            ),
+            PipaCollectorError::TooManyEvents(_) => Some(
+                "count fewer events at once or raise the open file limit with `ulimit -n`"
+                    .to_string(),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Custom implementation to provide human-readable error messages.
+/// 为了提供人类可读的错误信息，我们实现了自定义的 Display。
+impl fmt::Display for PipaCollectorError {
+    #[cfg(not(tarpaulin_include))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipaCollectorError::Io(e) => write!(f, "I/O error: {}", e),
+            PipaCollectorError::Parse(e) => write!(f, "Parse error: {}", e),
+            PipaCollectorError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
+            PipaCollectorError::MissingData(msg) => write!(f, "Missing data: {}", msg),
+            PipaCollectorError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            PipaCollectorError::PermissionDenied { operation, .. } => {
+                write!(f, "Permission denied: {}", operation)
+            }
+            PipaCollectorError::EventNotSupported(msg) => write!(f, "Event not supported: {}", msg),
+            PipaCollectorError::TooManyEvents(msg) => write!(f, "Too many events: {}", msg),
+            PipaCollectorError::Context { path, line, source } => {
+                match (path, line) {
+                    (Some(path), Some(line)) => write!(f, "{}:{}: ", path.display(), line)?,
+                    (Some(path), None) => write!(f, "{}: ", path.display())?,
+                    (None, Some(line)) => write!(f, "line {}: ", line)?,
+                    (None, None) => {}
+                }
+                source.fmt(f)
+            }
+        }
+    }
+}
+
+/// Implementing the standard Error trait for interoperability with other error
+/// types. 为了与其他错误类型互操作，我们实现了标准的 Error trait。
+impl std::error::Error for PipaCollectorError {
+    #[cfg(not(tarpaulin_include))]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipaCollectorError::Io(e) => Some(e),
+            PipaCollectorError::Parse(e) => Some(e),
+            PipaCollectorError::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+// Boilerplate to allow easy conversion from standard errors using the `?`
+// operator. 使用 `?` 操作符简化从标准错误类型到自定义错误类型的转换的模板代码。
+impl From<io::Error> for PipaCollectorError {
+    fn from(err: io::Error) -> Self {
+        PipaCollectorError::Io(err)
+    }
+}
+
+impl From<ParseIntError> for PipaCollectorError {
+    fn from(err: ParseIntError) -> Self {
+        PipaCollectorError::Parse(ParseError::Int(err))
+    }
+}
+
+impl From<ParseFloatError> for PipaCollectorError {
+    fn from(err: ParseFloatError) -> Self {
+        PipaCollectorError::Parse(ParseError::Float(err))
+    }
+}
+
+/// Adds path and line context to a `Result`. / 为 `Result` 添加路径和行号上下文。
+pub trait ResultExt<T> {
+    /// See [`PipaCollectorError::at_path`]. / 参见 [`PipaCollectorError::at_path`]。
+    fn at_path<P: AsRef<Path>>(self, path: P) -> Result<T, PipaCollectorError>;
+    /// See [`PipaCollectorError::at_line`]. / 参见 [`PipaCollectorError::at_line`]。
+    fn at_line(self, line: usize) -> Result<T, PipaCollectorError>;
+}
+
+impl<T, E: Into<PipaCollectorError>> ResultExt<T> for Result<T, E> {
+    fn at_path<P: AsRef<Path>>(self, path: P) -> Result<T, PipaCollectorError> {
+        self.map_err(|e| e.into().at_path(path))
+    }
+
+    fn at_line(self, line: usize) -> Result<T, PipaCollectorError> {
+        self.map_err(|e| e.into().at_line(line))
+    }
+}
+
+/// Reads a whole file, naming it in the error. / 读取整个文件，出错时在错误中注明文件名。
+pub fn read_file<P: AsRef<Path>>(path: P) -> Result<String, PipaCollectorError> {
+    std::fs::read_to_string(path.as_ref()).at_path(path)
+}
+
+/// Reads a file and parses its content, naming the file in either error.
+/// 读取文件并解析其内容，两种错误都会注明文件名。
+pub fn parse_file<P, T, E, F>(path: P, parse: F) -> Result<T, PipaCollectorError>
+where
+    P: AsRef<Path>,
+    E: Into<PipaCollectorError>,
+    F: FnOnce(&str) -> Result<T, E>,
+{
+    parse(&read_file(path.as_ref())?).at_path(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    #[test]
+    fn test_context_display_and_code() {
+        let err = "x".parse::<u64>().at_line(3).at_path("/proc/diskstats").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "/proc/diskstats:3: Parse error: invalid digit found in string"
+        );
+        assert_eq!(err.code(), ErrorCode::Parse);
+        assert_eq!(err.path(), Some(Path::new("/proc/diskstats")));
+        assert!(matches!(err.root_cause(), PipaCollectorError::Parse(ParseError::Int(_))));
+
+        // The innermost path is kept. / 保留最内层的路径。
+        let err = err.at_path("/other");
+        assert_eq!(err.path(), Some(Path::new("/proc/diskstats")));
+
+        let err = read_file("/nonexistent/pipa").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::NotFound);
+        assert!(err.to_string().starts_with("/nonexistent/pipa: I/O error:"));
+
+        let err: PipaCollectorError = "1.x".parse::<f64>().unwrap_err().into();
+        assert!(matches!(err, PipaCollectorError::Parse(ParseError::Float(_))));
+
+        // The chain runs from the context to the underlying I/O error.
+        let err = read_file("/nonexistent/pipa").unwrap_err();
+        let inner = err.source().unwrap().downcast_ref::<PipaCollectorError>().unwrap();
+        assert!(matches!(inner, PipaCollectorError::Io(_)));
+        assert!(inner.source().unwrap().is::<io::Error>());
+        assert_eq!(ErrorCode::TooManyEvents.to_string(), "E_TOO_MANY_EVENTS");
+    }
+
+    #[test]
+    fn test_perf_error_hints() {
+        let err = PipaCollectorError::PermissionDenied {
+            operation: "perf_event_open(cycles)".into(),
+            paranoid: Some(3),
+        };
+        assert_eq!(err.code(), ErrorCode::Permission);
+        assert!(err.hint().unwrap().contains("perf_event_paranoid is 3"));
+        assert_eq!(PipaCollectorError::TooManyEvents("x".into()).code(), ErrorCode::TooManyEvents);
+        assert!(PipaCollectorError::EventNotSupported("x".into()).hint().is_some());
+        assert!(PipaCollectorError::MissingData("x".into()).hint().is_none());
+
+        let open = |errno| {
+            PipaCollectorError::from_perf_open(
+                io::Error::from_raw_os_error(errno),
+                "cycles",
+                Some(2),
+            )
+        };
+        assert_eq!(open(libc::EACCES).code(), ErrorCode::Permission);
+        assert_eq!(open(libc::ENOENT).code(), ErrorCode::EventNotSupported);
+        assert_eq!(open(libc::EMFILE).code(), ErrorCode::TooManyEvents);
+        assert_eq!(open(libc::EINVAL).code(), ErrorCode::Io);
+        assert!(open(libc::EPERM).to_string().contains("perf_event_open failed for cycles"));
+    }
+}
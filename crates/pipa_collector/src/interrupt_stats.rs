@@ -0,0 +1,477 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module parses the per-CPU interrupt counts of `/proc/interrupts` and
+//! the per-CPU softirq counts of `/proc/softirqs`.
+//!
+//! 本模块解析 `/proc/interrupts` 中按 CPU 划分的中断计数，
+//! 以及 `/proc/softirqs` 中按 CPU 划分的软中断计数。
+
+use crate::error::parse_file;
+use crate::sysroot::SysRoot;
+use crate::system_stats::{Delta, PipaCollectorError};
+use serde::{Deserialize, Serialize};
+
+/// The counts of one `/proc/interrupts` row.
+///
+/// `/proc/interrupts` 中一行的计数。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Irq {
+    /// Row label without the colon: an IRQ number (`24`) or a symbolic name
+    /// (`LOC`, `NMI`, `IPI0`, `Err`).
+    /// 去掉冒号的行标签：IRQ 编号 (`24`) 或符号名 (`LOC`、`NMI`、`IPI0`、`Err`)。
+    pub name: String,
+    /// One count per column of [`InterruptStats::cpus`]. Rows such as `ERR`
+    /// carry a single system-wide count and therefore have fewer entries.
+    /// 与 [`InterruptStats::cpus`] 的列一一对应的计数。`ERR` 等行只有一个全局计数，
+    /// 因此条目较少。
+    pub counts: Vec<u64>,
+    /// Free text after the counts: chip, hwirq, trigger type and device
+    /// names. Empty for rows without a description.
+    /// 计数之后的自由文本：中断控制器、硬件中断号、触发类型和设备名。没有描述的行为空。
+    pub description: String,
+}
+
+impl Irq {
+    /// Sum of the counts over all CPUs. / 所有 CPU 上计数之和。
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Parsed contents of `/proc/interrupts`.
+///
+/// `/proc/interrupts` 的解析结果。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct InterruptStats {
+    /// CPU numbers of the count columns. Offline CPUs are not listed.
+    /// 计数列对应的 CPU 编号，离线 CPU 不会列出。
+    pub cpus: Vec<u32>,
+    /// All rows in file order. / 按文件顺序排列的所有行。
+    pub irqs: Vec<Irq>,
+}
+
+impl InterruptStats {
+    /// Looks up a row by label, e.g. `"LOC"` or `"24"`.
+    /// 按标签查找行，例如 `"LOC"` 或 `"24"`。
+    pub fn get(&self, name: &str) -> Option<&Irq> {
+        self.irqs.iter().find(|irq| irq.name == name)
+    }
+
+    /// Total interrupts handled by each CPU, in the order of `cpus`.
+    /// 每个 CPU 处理的中断总数，顺序与 `cpus` 一致。
+    pub fn per_cpu_totals(&self) -> Vec<u64> {
+        per_cpu_totals(self.cpus.len(), self.irqs.iter().map(|irq| &irq.counts))
+    }
+}
+
+impl Delta for InterruptStats {
+    /// Rows are matched by label; a row that did not exist earlier (for
+    /// example a newly bound device) counts from zero.
+    /// 按标签匹配行；之前不存在的行（例如新绑定的设备）从零开始计数。
+    fn delta(&self, earlier: &Self) -> Self {
+        InterruptStats {
+            cpus: self.cpus.clone(),
+            irqs: self
+                .irqs
+                .iter()
+                .map(|irq| Irq {
+                    name: irq.name.clone(),
+                    counts: delta_counts(&irq.counts, earlier.get(&irq.name).map(|e| &e.counts)),
+                    description: irq.description.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The counts of one softirq type in `/proc/softirqs`.
+///
+/// `/proc/softirqs` 中某种软中断类型的计数。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Softirq {
+    /// Softirq type, e.g. `NET_RX`. / 软中断类型，例如 `NET_RX`。
+    pub name: String,
+    /// One count per column of [`SoftirqStats::cpus`].
+    /// 与 [`SoftirqStats::cpus`] 的列一一对应的计数。
+    pub counts: Vec<u64>,
+}
+
+impl Softirq {
+    /// Sum of the counts over all CPUs. / 所有 CPU 上计数之和。
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+}
+
+/// Parsed contents of `/proc/softirqs`.
+///
+/// `/proc/softirqs` 的解析结果。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct SoftirqStats {
+    /// CPU numbers of the count columns. / 计数列对应的 CPU 编号。
+    pub cpus: Vec<u32>,
+    /// One entry per softirq type. / 每种软中断类型一项。
+    pub softirqs: Vec<Softirq>,
+}
+
+impl SoftirqStats {
+    /// Looks up a softirq type by name. / 按名称查找软中断类型。
+    pub fn get(&self, name: &str) -> Option<&Softirq> {
+        self.softirqs.iter().find(|s| s.name == name)
+    }
+
+    /// Total softirqs handled by each CPU, in the order of `cpus`.
+    /// 每个 CPU 处理的软中断总数，顺序与 `cpus` 一致。
+    pub fn per_cpu_totals(&self) -> Vec<u64> {
+        per_cpu_totals(self.cpus.len(), self.softirqs.iter().map(|s| &s.counts))
+    }
+}
+
+impl Delta for SoftirqStats {
+    fn delta(&self, earlier: &Self) -> Self {
+        SoftirqStats {
+            cpus: self.cpus.clone(),
+            softirqs: self
+                .softirqs
+                .iter()
+                .map(|s| Softirq {
+                    name: s.name.clone(),
+                    counts: delta_counts(&s.counts, earlier.get(&s.name).map(|e| &e.counts)),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Column-wise saturating difference. Missing earlier columns count as zero.
+/// 按列计算饱和差值，之前缺失的列按零计算。
+fn delta_counts(current: &[u64], earlier: Option<&Vec<u64>>) -> Vec<u64> {
+    current
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| c.saturating_sub(earlier.and_then(|e| e.get(i)).copied().unwrap_or(0)))
+        .collect()
+}
+
+/// Sums rows column-wise into `cpus` per-CPU totals.
+/// 按列将各行求和，得到 `cpus` 个 CPU 各自的总数。
+fn per_cpu_totals<'a, I: Iterator<Item = &'a Vec<u64>>>(cpus: usize, rows: I) -> Vec<u64> {
+    let mut totals = vec![0; cpus];
+    for counts in rows {
+        for (total, count) in totals.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+    totals
+}
+
+/// Parses the `CPU0 CPU1 ...` header shared by both files.
+/// 解析两个文件共有的 `CPU0 CPU1 ...` 表头。
+fn parse_cpu_header(header: Option<&str>) -> Result<Vec<u32>, PipaCollectorError> {
+    let header = header
+        .ok_or_else(|| PipaCollectorError::MissingData("Missing CPU header line".to_string()))?;
+    header
+        .split_whitespace()
+        .map(|column| {
+            column.strip_prefix("CPU").and_then(|n| n.parse::<u32>().ok()).ok_or_else(|| {
+                PipaCollectorError::InvalidFormat(format!("Invalid CPU column: {}", column))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|cpus| {
+            if cpus.is_empty() {
+                Err(PipaCollectorError::MissingData("No CPU columns in header".to_string()))
+            } else {
+                Ok(cpus)
+            }
+        })
+}
+
+/// Splits a data row into its label, up to `max_counts` leading counts and the
+/// remaining description. Returns `None` for rows without a `label:` prefix.
+/// 将数据行拆分为标签、最多 `max_counts` 个前导计数以及剩余的描述。
+/// 对没有 `label:` 前缀的行返回 `None`。
+fn split_row(line: &str, max_counts: usize) -> Option<(String, Vec<u64>, String)> {
+    let (label, rest) = line.split_once(':')?;
+    let label = label.trim();
+    if label.is_empty() || label.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let mut tokens = rest.split_whitespace().peekable();
+    let mut counts = Vec::new();
+    while counts.len() < max_counts {
+        match tokens.peek().and_then(|t| t.parse::<u64>().ok()) {
+            Some(count) => {
+                counts.push(count);
+                tokens.next();
+            }
+            None => break,
+        }
+    }
+    let description = tokens.collect::<Vec<_>>().join(" ");
+    Some((label.to_string(), counts, description))
+}
+
+/// Parses `/proc/interrupts`. The format varies by architecture and driver,
+/// so rows are parsed leniently: each row yields at most one count per CPU
+/// column, everything after the counts is kept as free text, and rows that
+/// do not look like `label: counts...` are skipped.
+///
+/// 解析 `/proc/interrupts`。其格式随体系结构和驱动而变化，因此采用宽松的解析方式：
+/// 每行最多按 CPU 列数读取计数，计数之后的内容保留为自由文本，
+/// 不符合 `label: counts...` 形式的行会被跳过。
+pub fn parse_interrupts_from_content(content: &str) -> Result<InterruptStats, PipaCollectorError> {
+    let mut lines = content.lines();
+    let cpus = parse_cpu_header(lines.next())?;
+    let irqs = lines
+        .filter_map(|line| split_row(line, cpus.len()))
+        .filter(|(_, counts, _)| !counts.is_empty())
+        .map(|(name, counts, description)| Irq { name, counts, description })
+        .collect();
+    Ok(InterruptStats { cpus, irqs })
+}
+
+/// Parses `/proc/softirqs`. / 解析 `/proc/softirqs`。
+pub fn parse_softirqs_from_content(content: &str) -> Result<SoftirqStats, PipaCollectorError> {
+    let mut lines = content.lines();
+    let cpus = parse_cpu_header(lines.next())?;
+    let softirqs = lines
+        .filter_map(|line| split_row(line, cpus.len()))
+        .filter(|(_, counts, _)| !counts.is_empty())
+        .map(|(name, counts, _)| Softirq { name, counts })
+        .collect();
+    Ok(SoftirqStats { cpus, softirqs })
+}
+
+/// Reads interrupt statistics from the given path.
+/// 从指定路径读取中断统计信息。
+pub fn read_interrupts_from_path<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<InterruptStats, PipaCollectorError> {
+    parse_file(path, parse_interrupts_from_content)
+}
+
+/// Reads softirq statistics from the given path.
+/// 从指定路径读取软中断统计信息。
+pub fn read_softirqs_from_path<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<SoftirqStats, PipaCollectorError> {
+    parse_file(path, parse_softirqs_from_content)
+}
+
+/// Reads interrupt statistics from `/proc/interrupts`.
+/// 从 `/proc/interrupts` 读取中断统计信息。
+pub fn read_interrupts(root: &SysRoot) -> Result<InterruptStats, PipaCollectorError> {
+    read_interrupts_from_path(root.proc("interrupts"))
+}
+
+/// Reads softirq statistics from `/proc/softirqs`.
+/// 从 `/proc/softirqs` 读取软中断统计信息。
+pub fn read_softirqs(root: &SysRoot) -> Result<SoftirqStats, PipaCollectorError> {
+    read_softirqs_from_path(root.proc("softirqs"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_stats::Snapshot;
+    use std::time::Duration;
+
+    /// Captured from an x86_64 machine with CPU2 offline.
+    const X86_INTERRUPTS: &str = "\
+            CPU0       CPU1       CPU3
+   0:         36          0          0   IO-APIC   2-edge      timer
+   8:          0          0          1   IO-APIC   8-edge      rtc0
+   9:          0         12          0   IO-APIC   9-fasteoi   acpi
+  28:        385          0          0 PCI-MSIX-0000:00:01.0   3-edge      virtio0-stats
+ 122:          0          0          0  PCI-MSI 376832-edge      ahci[0000:00:17.0]
+ NMI:         11         12         13   Non-maskable interrupts
+ LOC:    1234567    2345678    3456789   Local timer interrupts
+ SPU:          0          0          0   Spurious interrupts
+ IWI:         17          4          9   IRQ work interrupts
+ RES:        100        200        300   Rescheduling interrupts
+ TLB:          5          6          7   TLB shootdowns
+ MCP:         40         40         40   Machine check polls
+ ERR:          0
+ MIS:          0
+ PIN:          0          0          0   Posted-interrupt notification event
+";
+
+    /// Captured from an arm64 (GICv3) machine.
+    const ARM64_INTERRUPTS: &str = "\
+           CPU0       CPU1       CPU2       CPU3
+ 11:     812345     734567     698765     701234     GICv3  27 Level     arch_timer
+ 13:          0          0          0          0     GICv3  33 Level     uart-pl011
+ 47:       2048          0          0          0   ITS-MSI 524288 Edge      nvme0q0
+IPI0:      1234       2345       3456       4567       Rescheduling interrupts
+IPI1:        10         20         30         40       Function call interrupts
+IPI2:         0          0          0          0       CPU stop interrupts
+IPI5:      9999       8888       7777       6666       IRQ work interrupts
+Err:          0
+";
+
+    const SOFTIRQS: &str = "\
+                    CPU0       CPU1
+          HI:          1          0
+       TIMER:     123456     234567
+      NET_TX:          3          4
+      NET_RX:       2048       1024
+       BLOCK:         50         60
+    IRQ_POLL:          0          0
+     TASKLET:          3          1
+       SCHED:      90000      80000
+     HRTIMER:          1          2
+         RCU:      33196      44197
+";
+
+    /// Test sections for `/proc/interrupts`
+    #[test]
+    fn test_parse_interrupts_x86() {
+        let stats = parse_interrupts_from_content(X86_INTERRUPTS).unwrap();
+        assert_eq!(stats.cpus, vec![0, 1, 3]);
+        assert_eq!(stats.irqs.len(), 15);
+
+        let timer = stats.get("0").unwrap();
+        assert_eq!(timer.counts, vec![36, 0, 0]);
+        assert_eq!(timer.description, "IO-APIC 2-edge timer");
+
+        // A description starting right after the counts without padding.
+        let virtio = stats.get("28").unwrap();
+        assert_eq!(virtio.counts, vec![385, 0, 0]);
+        assert_eq!(virtio.description, "PCI-MSIX-0000:00:01.0 3-edge virtio0-stats");
+
+        // A numeric-looking token in the description is not taken as a count.
+        let ahci = stats.get("122").unwrap();
+        assert_eq!(ahci.counts, vec![0, 0, 0]);
+        assert_eq!(ahci.description, "PCI-MSI 376832-edge ahci[0000:00:17.0]");
+
+        let loc = stats.get("LOC").unwrap();
+        assert_eq!(loc.total(), 1234567 + 2345678 + 3456789);
+        assert_eq!(loc.description, "Local timer interrupts");
+
+        // System-wide rows have a single count and no description.
+        let err = stats.get("ERR").unwrap();
+        assert_eq!(err.counts, vec![0]);
+        assert_eq!(err.description, "");
+    }
+
+    #[test]
+    fn test_parse_interrupts_arm64() {
+        let stats = parse_interrupts_from_content(ARM64_INTERRUPTS).unwrap();
+        assert_eq!(stats.cpus, vec![0, 1, 2, 3]);
+        assert_eq!(stats.irqs.len(), 8);
+
+        let timer = stats.get("11").unwrap();
+        assert_eq!(timer.counts, vec![812345, 734567, 698765, 701234]);
+        assert_eq!(timer.description, "GICv3 27 Level arch_timer");
+
+        let nvme = stats.get("47").unwrap();
+        assert_eq!(nvme.counts, vec![2048, 0, 0, 0]);
+        assert_eq!(nvme.description, "ITS-MSI 524288 Edge nvme0q0");
+
+        assert_eq!(stats.get("IPI1").unwrap().counts, vec![10, 20, 30, 40]);
+        assert_eq!(stats.get("Err").unwrap().counts, vec![0]);
+
+        let totals = stats.per_cpu_totals();
+        assert_eq!(totals[0], 812345 + 2048 + 1234 + 10 + 9999);
+        assert_eq!(totals[3], 701234 + 4567 + 40 + 6666);
+    }
+
+    #[test]
+    fn test_parse_interrupts_skips_odd_rows() {
+        let content = "           CPU0       CPU1\n\
+                       \n\
+                       garbage line without colon\n\
+                       bad label: 1 2\n\
+                       XYZ: not a number\n\
+                       40:          5          6   edge dev\n";
+        let stats = parse_interrupts_from_content(content).unwrap();
+        assert_eq!(stats.irqs.len(), 1);
+        assert_eq!(stats.irqs[0].name, "40");
+    }
+
+    #[test]
+    fn test_parse_interrupts_bad_header() {
+        assert!(matches!(
+            parse_interrupts_from_content("").unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+        assert!(matches!(
+            parse_interrupts_from_content("  0: 1 2\n").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_interrupts_from_content("   \n").unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+    }
+
+    #[test]
+    fn test_interrupts_delta_rate() {
+        let earlier = parse_interrupts_from_content(ARM64_INTERRUPTS).unwrap();
+        let mut later = earlier.clone();
+        later.irqs[0].counts = vec![812445, 734567, 698765, 701334];
+        later.irqs.push(Irq {
+            name: "48".to_string(),
+            counts: vec![7, 0, 0, 0],
+            description: "ITS-MSI 524289 Edge nvme0q1".to_string(),
+        });
+
+        let earlier = Snapshot { timestamp: Duration::from_secs(10), stats: earlier };
+        let later = Snapshot { timestamp: Duration::from_secs(12), stats: later };
+        let delta = later.delta_since(&earlier);
+
+        let timer = delta.delta.get("11").unwrap();
+        assert_eq!(timer.counts, vec![100, 0, 0, 100]);
+        assert_eq!(delta.per_second(timer.total()), 100.0);
+        assert_eq!(delta.delta.get("48").unwrap().counts, vec![7, 0, 0, 0]);
+        assert_eq!(delta.delta.get("IPI0").unwrap().total(), 0);
+    }
+
+    /// Test sections for `/proc/softirqs`
+    #[test]
+    fn test_parse_softirqs() {
+        let stats = parse_softirqs_from_content(SOFTIRQS).unwrap();
+        assert_eq!(stats.cpus, vec![0, 1]);
+        assert_eq!(stats.softirqs.len(), 10);
+        assert_eq!(stats.get("NET_RX").unwrap().counts, vec![2048, 1024]);
+        assert_eq!(stats.get("TIMER").unwrap().total(), 358023);
+        assert_eq!(stats.per_cpu_totals()[1], 234567 + 4 + 1024 + 60 + 1 + 80000 + 2 + 44197);
+    }
+
+    #[test]
+    fn test_softirqs_delta() {
+        let earlier = parse_softirqs_from_content(SOFTIRQS).unwrap();
+        let mut later = earlier.clone();
+        later.softirqs[3].counts = vec![3048, 1524];
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.get("NET_RX").unwrap().counts, vec![1000, 500]);
+        assert_eq!(delta.get("RCU").unwrap().total(), 0);
+    }
+
+    #[test]
+    fn test_read_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("interrupts"), X86_INTERRUPTS).unwrap();
+        std::fs::write(dir.path().join("softirqs"), SOFTIRQS).unwrap();
+        assert_eq!(read_interrupts_from_path(dir.path().join("interrupts")).unwrap().cpus.len(), 3);
+        assert_eq!(read_softirqs_from_path(dir.path().join("softirqs")).unwrap().cpus.len(), 2);
+        assert!(matches!(
+            read_interrupts_from_path(dir.path().join("missing")).unwrap_err().root_cause(),
+            PipaCollectorError::Io(_)
+        ));
+    }
+}
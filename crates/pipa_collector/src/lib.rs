@@ -1,2 +1,18 @@
+pub mod cgroup_stats;
+pub mod collector;
+pub mod cpu_telemetry;
+pub mod doctor;
+pub mod error;
+pub mod interrupt_stats;
+pub mod perf_record;
+pub mod probe;
+pub mod process_stats;
 pub mod raw_perf_events;
+pub mod sampler;
+pub mod sysroot;
 pub mod system_stats;
+pub mod topology;
+pub mod tracepoint;
+
+#[cfg(test)]
+pub(crate) mod test_support;
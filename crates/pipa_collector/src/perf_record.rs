@@ -0,0 +1,1440 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Event sampling for `pipa record`: opens sampling events, drains their
+//! mmap ring buffers, parses `PERF_RECORD_*` records, and reads and writes
+//! record files.
+//!
+//! A record file is JSON Lines like a sampler capture: a [`RecordHeader`]
+//! followed by one [`RecordEntry`] per line. Tracepoint formats are stored in
+//! the header, so raw payloads can be decoded on another machine.
+//!
+//! `pipa record` 的事件采样：打开采样事件，读取其 mmap 环形缓冲区，
+//! 解析 `PERF_RECORD_*` 记录，并读写记录文件。
+//!
+//! 记录文件与采样器捕获文件一样采用 JSON Lines 格式：首行为 [`RecordHeader`]，
+//! 随后每行一个 [`RecordEntry`]。跟踪点格式保存在文件头中，
+//! 因此原始负载可以在其他机器上解码。
+
+use crate::error::{ResultExt, parse_file};
+use crate::raw_perf_events::{Counter, PerfEvent, perf_event_paranoid};
+use crate::sampler::{read_kernel_setting, write_json_line};
+use crate::sysroot::SysRoot;
+use crate::system_stats::{PipaCollectorError, monotonic_now, parse_cpu_list};
+use crate::tracepoint::TracepointFormat;
+use perf_event_open_sys as sys;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the record file format. / 记录文件格式的版本号。
+pub const RECORD_FORMAT_VERSION: u32 = 1;
+
+/// The sample fields requested by [`Recorder`]. / [`Recorder`] 请求的采样字段。
+pub const SAMPLE_TYPE: u64 = (sys::bindings::PERF_SAMPLE_IP
+    | sys::bindings::PERF_SAMPLE_TID
+    | sys::bindings::PERF_SAMPLE_TIME
+    | sys::bindings::PERF_SAMPLE_CPU
+    | sys::bindings::PERF_SAMPLE_PERIOD
+    | sys::bindings::PERF_SAMPLE_RAW) as u64;
+
+/// The user registers copied for DWARF unwinding, as a `sample_regs_user`
+/// mask: on x86-64 every general purpose register plus `ip`, `flags`, `cs`
+/// and `ss`; on AArch64 `x0`-`x30`, `sp` and `pc`. Zero where PIPA cannot
+/// unwind.
+/// 为 DWARF 栈回溯复制的用户态寄存器（`sample_regs_user` 掩码）：x86-64 上为所有
+/// 通用寄存器以及 `ip`、`flags`、`cs` 和 `ss`；AArch64 上为 `x0`-`x30`、`sp` 和
+/// `pc`。在 PIPA 无法回溯的架构上为零。
+#[cfg(target_arch = "x86_64")]
+pub const USER_REGS_MASK: u64 = 0xff_0fff;
+#[cfg(target_arch = "aarch64")]
+pub const USER_REGS_MASK: u64 = (1 << 33) - 1;
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub const USER_REGS_MASK: u64 = 0;
+
+/// Bytes of user stack copied into each sample for DWARF unwinding, as
+/// `perf record --call-graph dwarf` does by default.
+/// 为 DWARF 栈回溯复制到每个采样中的用户栈字节数，与
+/// `perf record --call-graph dwarf` 的默认值相同。
+pub const DWARF_STACK_SIZE: u32 = 8192;
+
+/// How `pipa record` collects callchains. / `pipa record` 收集调用链的方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallGraph {
+    /// The kernel walks frame pointers, which code built without them lacks.
+    /// 由内核遍历帧指针；未使用帧指针编译的代码无法回溯。
+    Fp,
+    /// The CPU's Last Branch Record in call-stack mode, for user code.
+    /// CPU 的最后分支记录（LBR）的调用栈模式，用于用户态代码。
+    Lbr,
+    /// Registers and the top of the user stack are copied and unwound
+    /// offline with the binaries' call frame information.
+    /// 复制寄存器与用户栈顶部，之后借助二进制文件的调用帧信息离线回溯。
+    Dwarf,
+}
+
+impl FromStr for CallGraph {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fp" => Ok(CallGraph::Fp),
+            "lbr" => Ok(CallGraph::Lbr),
+            "dwarf" => Ok(CallGraph::Dwarf),
+            other => Err(PipaCollectorError::InvalidFormat(format!(
+                "unknown call graph mode `{}`, expected fp, lbr or dwarf",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for CallGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CallGraph::Fp => "fp",
+            CallGraph::Lbr => "lbr",
+            CallGraph::Dwarf => "dwarf",
+        };
+        f.write_str(name)
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+/// Serializes payload bytes as a hex string. / 将负载字节序列化为十六进制字符串。
+mod hex_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(serde::de::Error::custom("odd number of hex digits"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(serde::de::Error::custom))
+            .collect()
+    }
+}
+
+/// One `PERF_RECORD_SAMPLE`. / 一条 `PERF_RECORD_SAMPLE` 记录。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleRecord {
+    /// Index of the event in [`RecordHeader::events`]. / 事件在 [`RecordHeader::events`] 中的索引。
+    pub event: usize,
+    /// `CLOCK_MONOTONIC` time in nanoseconds. / `CLOCK_MONOTONIC` 时间（纳秒）。
+    pub time: u64,
+    pub cpu: u32,
+    pub pid: u32,
+    pub tid: u32,
+    /// The sampled instruction pointer. / 采样时的指令指针。
+    pub ip: u64,
+    /// Events represented by this sample. / 此采样代表的事件数。
+    pub period: u64,
+    /// Return addresses, innermost first, when callchains were requested.
+    /// 请求调用链时的返回地址，最内层在前。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub callchain: Vec<u64>,
+    /// The raw tracepoint payload. / 原始跟踪点负载。
+    #[serde(default, with = "hex_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub raw: Vec<u8>,
+    /// The LBR call stack, innermost call first, with `--call-graph lbr`.
+    /// 使用 `--call-graph lbr` 时的 LBR 调用栈，最内层调用在前。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub branch_stack: Vec<BranchEntry>,
+    /// User registers in the order of [`RecordHeader::sample_regs_user`],
+    /// with `--call-graph dwarf`; empty for samples of kernel threads.
+    /// 使用 `--call-graph dwarf` 时的用户态寄存器，顺序同
+    /// [`RecordHeader::sample_regs_user`]；内核线程的采样为空。
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regs_user: Vec<u64>,
+    /// The user stack from the sampled stack pointer up, with
+    /// `--call-graph dwarf`. / 使用 `--call-graph dwarf` 时从采样栈指针起的用户栈。
+    #[serde(default, with = "hex_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub stack_user: Vec<u8>,
+}
+
+/// One taken branch of a branch stack. / 分支栈中一条已执行的分支。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchEntry {
+    /// The branch instruction; for a call stack, the call site.
+    /// 分支指令；对调用栈而言即调用点。
+    pub from: u64,
+    /// The branch target. / 分支目标。
+    pub to: u64,
+}
+
+/// `PERF_RECORD_COMM`: a task was named, by `execve` or `prctl`.
+/// `PERF_RECORD_COMM`：任务通过 `execve` 或 `prctl` 获得了名称。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommRecord {
+    pub pid: u32,
+    pub tid: u32,
+    pub comm: String,
+    /// `CLOCK_MONOTONIC` time in nanoseconds. / `CLOCK_MONOTONIC` 时间（纳秒）。
+    pub time: u64,
+    /// Whether the name changed because of `execve`. / 名称是否因 `execve` 而改变。
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub exec: bool,
+}
+
+/// `PERF_RECORD_SWITCH`: a task was switched onto or off a CPU.
+/// `PERF_RECORD_SWITCH`：任务被切换到 CPU 上或从 CPU 上切出。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwitchRecord {
+    pub pid: u32,
+    pub tid: u32,
+    /// `CLOCK_MONOTONIC` time in nanoseconds. / `CLOCK_MONOTONIC` 时间（纳秒）。
+    pub time: u64,
+    pub cpu: u32,
+    /// Whether the task left the CPU rather than started running.
+    /// 任务是离开 CPU 而不是开始运行。
+    pub out: bool,
+    /// Whether a switch out was a preemption, leaving the task runnable.
+    /// 切出是否为抢占（任务仍可运行）。
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub preempt: bool,
+}
+
+/// `PERF_RECORD_MMAP2`: a file was mapped executable into a process.
+/// `PERF_RECORD_MMAP2`：一个文件以可执行方式映射到进程中。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MmapRecord {
+    pub pid: u32,
+    pub tid: u32,
+    /// The mapped address range. / 映射的地址范围。
+    pub start: u64,
+    pub len: u64,
+    /// Offset of the mapping in the file. / 映射在文件中的偏移。
+    pub pgoff: u64,
+    /// The file path, or a name such as `[vdso]`. / 文件路径，或 `[vdso]` 等名称。
+    pub filename: String,
+    /// The file's build-ID, from kernels that report it (5.12 and later).
+    /// 文件的构建 ID，由支持上报的内核（5.12 及以后）提供。
+    #[serde(default, with = "hex_bytes", skip_serializing_if = "Vec::is_empty")]
+    pub build_id: Vec<u8>,
+    /// `CLOCK_MONOTONIC` time in nanoseconds. / `CLOCK_MONOTONIC` 时间（纳秒）。
+    pub time: u64,
+}
+
+/// The build-ID of a file mapped during a recording, read from the file
+/// when the recording ends. / 记录期间被映射文件的构建 ID，在记录结束时从文件中读取。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildIdRecord {
+    pub filename: String,
+    #[serde(with = "hex_bytes")]
+    pub build_id: Vec<u8>,
+}
+
+/// A record read from a ring buffer. / 从环形缓冲区读取的一条记录。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PerfRecord {
+    Sample(SampleRecord),
+    Comm(CommRecord),
+    Switch(SwitchRecord),
+    Mmap(MmapRecord),
+    /// `PERF_RECORD_LOST`: the kernel dropped records because the buffer
+    /// was full. / 缓冲区已满，内核丢弃了记录。
+    Lost(u64),
+    /// A record type PIPA does not use. / PIPA 不使用的记录类型。
+    Other(u32),
+}
+
+/// Reads fixed-size native-endian fields from a record body.
+/// 从记录体中读取定长的本机字节序字段。
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PipaCollectorError> {
+        let end = self.pos.checked_add(len);
+        let bytes = end.and_then(|end| self.bytes.get(self.pos..end)).ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(format!(
+                "record of {} bytes truncated at offset {}",
+                self.bytes.len(),
+                self.pos
+            ))
+        })?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, PipaCollectorError> {
+        Ok(u32::from_ne_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn u64(&mut self) -> Result<u64, PipaCollectorError> {
+        Ok(u64::from_ne_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+}
+
+/// Parses the body of a `PERF_RECORD_SAMPLE` (after the 8-byte header),
+/// whose fields depend on `sample_type` and, for `PERF_SAMPLE_REGS_USER`,
+/// the `regs_user` mask.
+///
+/// 解析 `PERF_RECORD_SAMPLE` 的记录体（8 字节记录头之后），其字段取决于
+/// `sample_type`，对于 `PERF_SAMPLE_REGS_USER` 还取决于 `regs_user` 掩码。
+pub fn parse_sample(
+    sample_type: u64,
+    regs_user: u64,
+    body: &[u8],
+) -> Result<SampleRecord, PipaCollectorError> {
+    use sys::bindings as b;
+    let has = |bit: u32| sample_type & bit as u64 != 0;
+    if has(b::PERF_SAMPLE_READ) {
+        return Err(PipaCollectorError::Unsupported(format!(
+            "sample_type {:#x} has fields PIPA cannot parse",
+            sample_type
+        )));
+    }
+
+    let mut cursor = Cursor { bytes: body, pos: 0 };
+    let mut sample = SampleRecord::default();
+    if has(b::PERF_SAMPLE_IDENTIFIER) {
+        cursor.u64()?;
+    }
+    if has(b::PERF_SAMPLE_IP) {
+        sample.ip = cursor.u64()?;
+    }
+    if has(b::PERF_SAMPLE_TID) {
+        sample.pid = cursor.u32()?;
+        sample.tid = cursor.u32()?;
+    }
+    if has(b::PERF_SAMPLE_TIME) {
+        sample.time = cursor.u64()?;
+    }
+    for bit in [b::PERF_SAMPLE_ADDR, b::PERF_SAMPLE_ID, b::PERF_SAMPLE_STREAM_ID] {
+        if has(bit) {
+            cursor.u64()?;
+        }
+    }
+    if has(b::PERF_SAMPLE_CPU) {
+        sample.cpu = cursor.u32()?;
+        cursor.u32()?;
+    }
+    if has(b::PERF_SAMPLE_PERIOD) {
+        sample.period = cursor.u64()?;
+    }
+    if has(b::PERF_SAMPLE_CALLCHAIN) {
+        let len = cursor.u64()? as usize;
+        sample.callchain = (0..len).map(|_| cursor.u64()).collect::<Result<_, _>>()?;
+    }
+    if has(b::PERF_SAMPLE_RAW) {
+        let len = cursor.u32()? as usize;
+        sample.raw = cursor.take(len)?.to_vec();
+    }
+    if has(b::PERF_SAMPLE_BRANCH_STACK) {
+        let len = cursor.u64()? as usize;
+        for _ in 0..len {
+            let (from, to) = (cursor.u64()?, cursor.u64()?);
+            cursor.u64()?; // flags
+            sample.branch_stack.push(BranchEntry { from, to });
+        }
+    }
+    if has(b::PERF_SAMPLE_REGS_USER) && cursor.u64()? != b::PERF_SAMPLE_REGS_ABI_NONE as u64 {
+        sample.regs_user =
+            (0..regs_user.count_ones()).map(|_| cursor.u64()).collect::<Result<_, _>>()?;
+    }
+    if has(b::PERF_SAMPLE_STACK_USER) {
+        let size = cursor.u64()? as usize;
+        if size > 0 {
+            let stack = cursor.take(size)?;
+            // Only the first `dyn_size` bytes were filled in.
+            let used = (cursor.u64()? as usize).min(size);
+            sample.stack_user = stack[..used].to_vec();
+        }
+    }
+    Ok(sample)
+}
+
+/// Size of the `sample_id` trailer that `sample_id_all` appends to
+/// non-sample records. / `sample_id_all` 附加在非采样记录末尾的 `sample_id` 的大小。
+fn sample_id_size(sample_type: u64) -> usize {
+    use sys::bindings as b;
+    [
+        b::PERF_SAMPLE_TID,
+        b::PERF_SAMPLE_TIME,
+        b::PERF_SAMPLE_ID,
+        b::PERF_SAMPLE_STREAM_ID,
+        b::PERF_SAMPLE_CPU,
+        b::PERF_SAMPLE_IDENTIFIER,
+    ]
+    .iter()
+    .filter(|bit| sample_type & **bit as u64 != 0)
+    .count()
+        * 8
+}
+
+/// The fields of a `sample_id` trailer that PIPA uses.
+/// PIPA 使用的 `sample_id` 尾部字段。
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct SampleId {
+    pid: u32,
+    tid: u32,
+    time: u64,
+    cpu: u32,
+}
+
+/// Reads the `sample_id` trailer of a non-sample record.
+/// 读取非采样记录的 `sample_id` 尾部。
+fn parse_sample_id(sample_type: u64, body: &[u8]) -> Result<SampleId, PipaCollectorError> {
+    use sys::bindings as b;
+    let has = |bit: u32| sample_type & bit as u64 != 0;
+    let start = body.len().checked_sub(sample_id_size(sample_type)).ok_or_else(|| {
+        PipaCollectorError::InvalidFormat(format!(
+            "record of {} bytes has no sample_id",
+            body.len()
+        ))
+    })?;
+    let mut cursor = Cursor { bytes: body, pos: start };
+    let mut id = SampleId::default();
+    if has(b::PERF_SAMPLE_TID) {
+        id.pid = cursor.u32()?;
+        id.tid = cursor.u32()?;
+    }
+    if has(b::PERF_SAMPLE_TIME) {
+        id.time = cursor.u64()?;
+    }
+    for bit in [b::PERF_SAMPLE_ID, b::PERF_SAMPLE_STREAM_ID] {
+        if has(bit) {
+            cursor.u64()?;
+        }
+    }
+    if has(b::PERF_SAMPLE_CPU) {
+        id.cpu = cursor.u32()?;
+    }
+    Ok(id)
+}
+
+/// Parses one record given its header type, misc flags and body. Non-sample
+/// records are expected to carry a `sample_id` trailer (`sample_id_all`).
+///
+/// 根据记录头类型、misc 标志和记录体解析一条记录。
+/// 非采样记录应带有 `sample_id` 尾部（`sample_id_all`）。
+pub fn parse_record(
+    record_type: u32,
+    misc: u16,
+    sample_type: u64,
+    regs_user: u64,
+    body: &[u8],
+) -> Result<PerfRecord, PipaCollectorError> {
+    match record_type {
+        sys::bindings::PERF_RECORD_SAMPLE => {
+            parse_sample(sample_type, regs_user, body).map(PerfRecord::Sample)
+        }
+        sys::bindings::PERF_RECORD_COMM => {
+            let mut cursor = Cursor { bytes: body, pos: 0 };
+            let (pid, tid) = (cursor.u32()?, cursor.u32()?);
+            let end = body.len().saturating_sub(sample_id_size(sample_type)).max(8);
+            let name = &body[8..end];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            Ok(PerfRecord::Comm(CommRecord {
+                pid,
+                tid,
+                comm: String::from_utf8_lossy(name).into_owned(),
+                time: parse_sample_id(sample_type, body)?.time,
+                exec: misc as u32 & sys::bindings::PERF_RECORD_MISC_COMM_EXEC != 0,
+            }))
+        }
+        // Task-bound events give the task's own switches; CPU-wide events
+        // add the pid and tid of the other task, which PIPA does not need.
+        sys::bindings::PERF_RECORD_SWITCH | sys::bindings::PERF_RECORD_SWITCH_CPU_WIDE => {
+            let id = parse_sample_id(sample_type, body)?;
+            Ok(PerfRecord::Switch(SwitchRecord {
+                pid: id.pid,
+                tid: id.tid,
+                time: id.time,
+                cpu: id.cpu,
+                out: misc as u32 & sys::bindings::PERF_RECORD_MISC_SWITCH_OUT != 0,
+                preempt: misc as u32 & sys::bindings::PERF_RECORD_MISC_SWITCH_OUT_PREEMPT != 0,
+            }))
+        }
+        // pid, tid, addr, len, pgoff, the device or build-ID, prot and
+        // flags, then the file name.
+        sys::bindings::PERF_RECORD_MMAP2 => {
+            let mut cursor = Cursor { bytes: body, pos: 0 };
+            let (pid, tid) = (cursor.u32()?, cursor.u32()?);
+            let (start, len, pgoff) = (cursor.u64()?, cursor.u64()?, cursor.u64()?);
+            // A build-ID is a size byte, three reserved bytes and up to 20
+            // bytes of ID. / 构建 ID 由一个长度字节、三个保留字节和至多 20 字节的 ID 组成。
+            let device = cursor.take(24)?;
+            let build_id = if misc as u32 & sys::bindings::PERF_RECORD_MISC_MMAP_BUILD_ID != 0 {
+                device[4..4 + (device[0] as usize).min(20)].to_vec()
+            } else {
+                Vec::new()
+            };
+            cursor.take(8)?;
+            let end = body.len().saturating_sub(sample_id_size(sample_type)).max(cursor.pos);
+            let name = &body[cursor.pos..end];
+            let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+            Ok(PerfRecord::Mmap(MmapRecord {
+                pid,
+                tid,
+                start,
+                len,
+                pgoff,
+                filename: String::from_utf8_lossy(name).into_owned(),
+                build_id,
+                time: parse_sample_id(sample_type, body)?.time,
+            }))
+        }
+        sys::bindings::PERF_RECORD_LOST => {
+            let mut cursor = Cursor { bytes: body, pos: 0 };
+            cursor.u64()?;
+            Ok(PerfRecord::Lost(cursor.u64()?))
+        }
+        other => Ok(PerfRecord::Other(other)),
+    }
+}
+
+/// Copies `len` bytes starting at `pos` out of a ring whose size is a power
+/// of two, wrapping around its end.
+///
+/// 从大小为 2 的幂的环形缓冲区中复制从 `pos` 开始的 `len` 个字节，必要时绕回开头。
+fn copy_wrapped(ring: &[u8], pos: u64, len: usize) -> Vec<u8> {
+    let start = (pos % ring.len() as u64) as usize;
+    let first = len.min(ring.len() - start);
+    let mut out = Vec::with_capacity(len);
+    out.extend_from_slice(&ring[start..start + first]);
+    out.extend_from_slice(&ring[..len - first]);
+    out
+}
+
+/// Splits the bytes between `tail` and `head` of a ring into records of
+/// `(type, misc, body)`.
+/// 将环形缓冲区中 `tail` 与 `head` 之间的字节拆分为 `(type, misc, body)` 记录。
+fn split_records(
+    ring: &[u8],
+    mut tail: u64,
+    head: u64,
+) -> Result<Vec<(u32, u16, Vec<u8>)>, PipaCollectorError> {
+    let mut records = Vec::new();
+    while tail + 8 <= head {
+        let header = copy_wrapped(ring, tail, 8);
+        let record_type = u32::from_ne_bytes(header[0..4].try_into().expect("4 bytes"));
+        let misc = u16::from_ne_bytes(header[4..6].try_into().expect("2 bytes"));
+        let size = u16::from_ne_bytes(header[6..8].try_into().expect("2 bytes")) as u64;
+        if size < 8 || tail + size > head {
+            return Err(PipaCollectorError::InvalidFormat(format!(
+                "corrupt ring buffer record of {} bytes at {}",
+                size, tail
+            )));
+        }
+        records.push((record_type, misc, copy_wrapped(ring, tail + 8, size as usize - 8)));
+        tail += size;
+    }
+    Ok(records)
+}
+
+/// A counter with its mmap ring buffer. / 带有 mmap 环形缓冲区的计数器。
+#[derive(Debug)]
+struct RingBuffer {
+    counter: Counter,
+    base: *mut libc::c_void,
+    map_len: usize,
+    data_offset: usize,
+    data_size: usize,
+}
+
+// SAFETY: the mapping is owned by the buffer and only accessed through
+// `&mut self`. / 映射归缓冲区所有，且只通过 `&mut self` 访问。
+unsafe impl Send for RingBuffer {}
+
+#[cfg(not(tarpaulin_include))]
+impl RingBuffer {
+    /// Maps the metadata page plus `data_pages` (a power of two) data pages.
+    /// 映射元数据页以及 `data_pages`（2 的幂）个数据页。
+    fn map(counter: Counter, data_pages: usize) -> Result<Self, PipaCollectorError> {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let map_len = page * (1 + data_pages);
+        // SAFETY: mapping a perf fd with a valid length; failure is checked.
+        let base = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                counter.fd(),
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().into());
+        }
+        let meta = base as *const sys::bindings::perf_event_mmap_page;
+        // SAFETY: the first page of the mapping is the metadata page.
+        let (offset, size) = unsafe { ((*meta).data_offset, (*meta).data_size) };
+        let (data_offset, data_size) =
+            if size == 0 { (page, page * data_pages) } else { (offset as usize, size as usize) };
+        Ok(RingBuffer { counter, base, map_len, data_offset, data_size })
+    }
+
+    /// Removes all complete records from the buffer.
+    /// 从缓冲区中取出所有完整的记录。
+    fn drain(&mut self) -> Result<Vec<(u32, u16, Vec<u8>)>, PipaCollectorError> {
+        let meta = self.base as *mut sys::bindings::perf_event_mmap_page;
+        // SAFETY: the data area lies inside the mapping; `data_head` is read
+        // before the data and `data_tail` written after it, as documented in
+        // perf_event_open(2).
+        unsafe {
+            let head = std::ptr::read_volatile(&(*meta).data_head);
+            std::sync::atomic::fence(std::sync::atomic::Ordering::Acquire);
+            let tail = std::ptr::read_volatile(&(*meta).data_tail);
+            let ring = std::slice::from_raw_parts(
+                (self.base as *const u8).add(self.data_offset),
+                self.data_size,
+            );
+            let records = split_records(ring, tail, head)?;
+            std::sync::atomic::fence(std::sync::atomic::Ordering::SeqCst);
+            std::ptr::write_volatile(&mut (*meta).data_tail, head);
+            Ok(records)
+        }
+    }
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        // SAFETY: `base` and `map_len` describe the mapping created in `map`.
+        unsafe {
+            libc::munmap(self.base, self.map_len);
+        }
+    }
+}
+
+/// What a [`Recorder`] samples. / [`Recorder`] 的采样目标。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordTarget {
+    /// A command waiting in [`PendingCommand`](crate::raw_perf_events::PendingCommand),
+    /// by pid; events are enabled when it calls `execve`, as in `pipa stat`.
+    /// 在 [`PendingCommand`](crate::raw_perf_events::PendingCommand) 中等待的命令（以 pid 表示）；
+    /// 与 `pipa stat` 一样，在其调用 `execve` 时启用事件。
+    Command(u32),
+    /// The existing threads of a running process. / 正在运行的进程的现有线程。
+    Pid(u32),
+    /// Every task on every online CPU. / 所有在线 CPU 上的所有任务。
+    AllCpus,
+}
+
+/// Options of a [`Recorder`]. / [`Recorder`] 的选项。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordConfig {
+    pub events: Vec<PerfEvent>,
+    pub target: RecordTarget,
+    /// Samples per second for counting events such as `cycles`; tracepoints
+    /// and probes record every hit.
+    /// `cycles` 等计数事件的每秒采样数；跟踪点和探针记录每一次触发。
+    pub frequency: u64,
+    /// Data pages per ring buffer, a power of two. / 每个环形缓冲区的数据页数（2 的幂）。
+    pub pages: usize,
+    /// How to record the callchain of every sample, if at all.
+    /// 记录每个采样调用链的方式（如需记录）。
+    pub call_graph: Option<CallGraph>,
+    /// Record `PERF_RECORD_SWITCH` when the target's tasks are switched onto
+    /// or off a CPU. / 在目标任务被切换到 CPU 上或切出时记录 `PERF_RECORD_SWITCH`。
+    pub context_switch: bool,
+}
+
+impl RecordConfig {
+    /// The sample fields requested for this configuration.
+    /// 此配置所请求的采样字段。
+    pub fn sample_type(&self) -> u64 {
+        use sys::bindings as b;
+        let callchain = match self.call_graph {
+            None => 0,
+            Some(CallGraph::Fp) => b::PERF_SAMPLE_CALLCHAIN,
+            // The kernel part of the stack still comes from the callchain.
+            Some(CallGraph::Lbr) => b::PERF_SAMPLE_CALLCHAIN | b::PERF_SAMPLE_BRANCH_STACK,
+            Some(CallGraph::Dwarf) => {
+                b::PERF_SAMPLE_CALLCHAIN | b::PERF_SAMPLE_REGS_USER | b::PERF_SAMPLE_STACK_USER
+            }
+        };
+        SAMPLE_TYPE | callchain as u64
+    }
+
+    /// The `sample_regs_user` mask, non-zero only for DWARF unwinding.
+    /// `sample_regs_user` 掩码，仅在 DWARF 栈回溯时非零。
+    pub fn regs_user(&self) -> u64 {
+        if self.call_graph == Some(CallGraph::Dwarf) { USER_REGS_MASK } else { 0 }
+    }
+}
+
+/// Samples events into per-CPU or per-thread ring buffers.
+/// 将事件采样到按 CPU 或按线程划分的环形缓冲区中。
+#[derive(Debug)]
+pub struct Recorder {
+    buffers: Vec<(usize, RingBuffer)>,
+    sample_type: u64,
+    regs_user: u64,
+    lost: u64,
+}
+
+#[cfg(not(tarpaulin_include))]
+impl Recorder {
+    /// Opens every event for the target and maps its buffers.
+    /// 为目标打开所有事件并映射其缓冲区。
+    pub fn open(root: &SysRoot, config: &RecordConfig) -> Result<Self, PipaCollectorError> {
+        if config.call_graph == Some(CallGraph::Dwarf) && USER_REGS_MASK == 0 {
+            return Err(PipaCollectorError::Unsupported(format!(
+                "DWARF unwinding on {}",
+                std::env::consts::ARCH
+            )));
+        }
+        let cpus = parse_file(root.sys("devices/system/cpu/online"), parse_cpu_list)?;
+        let placements: Vec<(i32, i32)> = match config.target {
+            RecordTarget::Command(pid) => {
+                cpus.iter().map(|&cpu| (pid as i32, cpu as i32)).collect()
+            }
+            RecordTarget::AllCpus => cpus.iter().map(|&cpu| (-1, cpu as i32)).collect(),
+            RecordTarget::Pid(pid) => {
+                let task_dir = root.proc(format!("{}/task", pid));
+                let mut tids = Vec::new();
+                for entry in std::fs::read_dir(&task_dir).at_path(&task_dir)? {
+                    if let Some(tid) = entry?.file_name().to_str().and_then(|s| s.parse().ok()) {
+                        tids.push((tid, -1));
+                    }
+                }
+                tids
+            }
+        };
+
+        let mut buffers = Vec::new();
+        for (index, event) in config.events.iter().enumerate() {
+            for &(pid, cpu) in &placements {
+                // Task names, mappings and switches are reported once, through
+                // the first event.
+                let counter = match open_sampling(event, config, pid, cpu, index == 0) {
+                    Ok(counter) => counter,
+                    // A thread exited while the events were being opened.
+                    Err(e) if e.raw_os_error() == Some(libc::ESRCH) => continue,
+                    Err(e) => {
+                        let target = match config.target {
+                            RecordTarget::Command(_) => format!("the command on CPU {}", cpu),
+                            RecordTarget::AllCpus => format!("CPU {}", cpu),
+                            RecordTarget::Pid(_) => format!("tid {}", pid),
+                        };
+                        return Err(PipaCollectorError::from_perf_open(
+                            e,
+                            &format!("event {:?} on {}", event, target),
+                            perf_event_paranoid(root),
+                        ));
+                    }
+                };
+                buffers.push((index, RingBuffer::map(counter, config.pages)?));
+            }
+        }
+        if buffers.is_empty() {
+            return Err(PipaCollectorError::MissingData("No event could be opened".into()));
+        }
+        Ok(Recorder {
+            buffers,
+            sample_type: config.sample_type(),
+            regs_user: config.regs_user(),
+            lost: 0,
+        })
+    }
+
+    /// Waits up to `timeout` for a buffer to pass its wakeup watermark.
+    /// 最多等待 `timeout`，直到某个缓冲区超过其唤醒水位。
+    pub fn wait(&self, timeout: Duration) {
+        let mut fds: Vec<libc::pollfd> = self
+            .buffers
+            .iter()
+            .map(|(_, b)| libc::pollfd { fd: b.counter.fd(), events: libc::POLLIN, revents: 0 })
+            .collect();
+        // SAFETY: `fds` is a valid array of pollfd for the duration of the call.
+        unsafe {
+            libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout.as_millis() as i32);
+        }
+    }
+
+    /// Drains every buffer and returns the samples, task names, mappings and
+    /// context switches, sorted by time. Only this drain is sorted: a later
+    /// one may return older entries, which [`OrderedEntries`] puts in order.
+    /// 清空所有缓冲区，并返回按时间排序的采样、任务名称、内存映射与上下文切换。
+    /// 仅本次清空的结果有序：之后的清空可能返回更早的条目，可由 [`OrderedEntries`] 排序。
+    pub fn read(&mut self) -> Result<Vec<RecordEntry>, PipaCollectorError> {
+        let mut entries = Vec::new();
+        for (index, buffer) in &mut self.buffers {
+            for (record_type, misc, body) in buffer.drain()? {
+                match parse_record(record_type, misc, self.sample_type, self.regs_user, &body)? {
+                    PerfRecord::Sample(sample) => {
+                        entries.push(RecordEntry::Sample(SampleRecord { event: *index, ..sample }))
+                    }
+                    PerfRecord::Comm(comm) => entries.push(RecordEntry::Comm(comm)),
+                    PerfRecord::Switch(switch) => entries.push(RecordEntry::Switch(switch)),
+                    PerfRecord::Mmap(mmap) => entries.push(RecordEntry::Mmap(mmap)),
+                    PerfRecord::Lost(count) => self.lost += count,
+                    PerfRecord::Other(_) => {}
+                }
+            }
+        }
+        entries.sort_by_key(RecordEntry::time);
+        Ok(entries)
+    }
+
+    /// Records lost so far because a buffer was full. / 迄今因缓冲区已满而丢失的记录数。
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+}
+
+/// Puts the entries of successive [`Recorder::read`] drains in time order,
+/// as perf's finished rounds do. An event is written to its buffer shortly
+/// after its timestamp is taken, so once a drain has started, every entry
+/// older than the start of the one before it has been read and can be
+/// passed on; newer entries are held back until the next drain.
+/// 像 perf 的 finished round 一样，将连续多次 [`Recorder::read`] 清空得到的条目按时间排序。
+/// 事件在取得时间戳后很快就会写入缓冲区，因此某次清空开始后，所有早于上一次清空开始时间
+/// 的条目都已被读出，可以输出；较新的条目则保留到下一次清空。
+#[derive(Debug, Default)]
+pub struct OrderedEntries {
+    pending: Vec<RecordEntry>,
+    /// When the previous drain started. / 上一次清空开始的时间。
+    previous_start: u64,
+}
+
+impl OrderedEntries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the entries of a drain that started at `start`, in
+    /// `CLOCK_MONOTONIC` nanoseconds, and returns those now known to be in
+    /// order. / 添加一次始于 `start`（`CLOCK_MONOTONIC` 纳秒）的清空所得条目，
+    /// 并返回已确定有序的条目。
+    pub fn push(&mut self, entries: Vec<RecordEntry>, start: u64) -> Vec<RecordEntry> {
+        self.pending.extend(entries);
+        self.pending.sort_by_key(RecordEntry::time);
+        let ready = self.pending.partition_point(|entry| entry.time() < self.previous_start);
+        self.previous_start = start;
+        self.pending.drain(..ready).collect()
+    }
+
+    /// The entries held back, once recording has stopped and the buffers
+    /// have been drained one last time.
+    /// 记录停止且缓冲区最后一次清空后，所有被保留的条目。
+    pub fn finish(&mut self) -> Vec<RecordEntry> {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Opens one sampling event. / 打开一个采样事件。
+#[cfg(not(tarpaulin_include))]
+fn open_sampling(
+    event: &PerfEvent,
+    config: &RecordConfig,
+    pid: i32,
+    cpu: i32,
+    track_comm: bool,
+) -> Result<Counter, io::Error> {
+    let mut attrs = sys::bindings::perf_event_attr {
+        size: std::mem::size_of::<sys::bindings::perf_event_attr>() as u32,
+        ..Default::default()
+    };
+    event.configure(&mut attrs);
+    attrs.sample_type = config.sample_type();
+    match config.call_graph {
+        Some(CallGraph::Lbr) => {
+            use sys::bindings as b;
+            attrs.branch_sample_type = (b::PERF_SAMPLE_BRANCH_USER
+                | b::PERF_SAMPLE_BRANCH_CALL_STACK
+                | b::PERF_SAMPLE_BRANCH_NO_FLAGS
+                | b::PERF_SAMPLE_BRANCH_NO_CYCLES) as u64;
+        }
+        Some(CallGraph::Dwarf) => {
+            attrs.sample_regs_user = USER_REGS_MASK;
+            attrs.sample_stack_user = DWARF_STACK_SIZE;
+            // User frames come from unwinding the copied stack instead.
+            attrs.set_exclude_callchain_user(1);
+        }
+        Some(CallGraph::Fp) | None => {}
+    }
+    if matches!(event, PerfEvent::Tracepoint(_) | PerfEvent::Probe(_)) {
+        attrs.__bindgen_anon_1.sample_period = 1;
+    } else {
+        attrs.set_freq(1);
+        attrs.__bindgen_anon_1.sample_freq = config.frequency;
+    }
+    // Timestamps on the same clock as the rest of PIPA, on every record.
+    attrs.set_sample_id_all(1);
+    if track_comm {
+        attrs.set_comm(1);
+        attrs.set_comm_exec(1);
+        // Executable mappings, to find the binary behind an address, with
+        // the build-ID that tells which version of it was mapped.
+        attrs.set_mmap(1);
+        attrs.set_mmap2(1);
+        attrs.set_build_id(1);
+        if config.context_switch {
+            attrs.set_context_switch(1);
+        }
+    }
+    attrs.set_use_clockid(1);
+    attrs.clockid = libc::CLOCK_MONOTONIC;
+    // Wake up when a quarter of the buffer is used.
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u32;
+    attrs.set_watermark(1);
+    attrs.__bindgen_anon_2.wakeup_watermark = page * config.pages as u32 / 4;
+    if matches!(config.target, RecordTarget::Command(_)) {
+        attrs.set_disabled(1);
+        attrs.set_inherit(1);
+        attrs.set_enable_on_exec(1);
+    }
+
+    let mut fd = unsafe { sys::perf_event_open(&mut attrs, pid, cpu, -1, 0) };
+    // Kernels before 5.12 reject build-IDs in mappings; `pipa record` then
+    // reads them from the files. / 5.12 之前的内核不支持映射中的构建 ID，
+    // 此时由 `pipa record` 从文件中读取。
+    if fd < 0
+        && attrs.build_id() == 1
+        && io::Error::last_os_error().raw_os_error() == Some(libc::EINVAL)
+    {
+        attrs.set_build_id(0);
+        fd = unsafe { sys::perf_event_open(&mut attrs, pid, cpu, -1, 0) };
+    }
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Counter::from_fd(fd))
+}
+
+/// An event in a record file. / 记录文件中的一个事件。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// The name given on the command line. / 命令行中给出的名称。
+    pub name: String,
+    /// The tracepoint format, for tracepoint events. / 跟踪点事件的格式。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<TracepointFormat>,
+}
+
+/// The first line of a record file. / 记录文件的第一行。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordHeader {
+    /// Format version, see [`RECORD_FORMAT_VERSION`]. / 格式版本。
+    pub version: u32,
+    pub hostname: String,
+    pub kernel_release: String,
+    pub arch: String,
+    /// The recorded command, if any. / 被记录的命令（如果有）。
+    #[serde(default)]
+    pub command: Vec<String>,
+    pub events: Vec<RecordedEvent>,
+    /// How callchains were recorded, if at all. / 调用链的记录方式（如有）。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub call_graph: Option<CallGraph>,
+    /// The `sample_regs_user` mask of [`SampleRecord::regs_user`].
+    /// [`SampleRecord::regs_user`] 对应的 `sample_regs_user` 掩码。
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub sample_regs_user: u64,
+    /// `CLOCK_MONOTONIC` time at which `realtime` was taken.
+    /// 读取 `realtime` 时的 `CLOCK_MONOTONIC` 时间。
+    pub monotonic: Duration,
+    /// Wall-clock time since the Unix epoch. / 自 Unix 纪元以来的挂钟时间。
+    pub realtime: Duration,
+}
+
+impl RecordHeader {
+    /// A header for a recording on this machine starting now.
+    /// 为本机从现在开始的记录创建文件头。
+    pub fn new(root: &SysRoot, command: Vec<String>, events: Vec<RecordedEvent>) -> Self {
+        RecordHeader {
+            version: RECORD_FORMAT_VERSION,
+            hostname: read_kernel_setting(&root.proc("sys/kernel/hostname")),
+            kernel_release: read_kernel_setting(&root.proc("sys/kernel/osrelease")),
+            arch: std::env::consts::ARCH.to_string(),
+            command,
+            events,
+            call_graph: None,
+            sample_regs_user: 0,
+            monotonic: monotonic_now(),
+            realtime: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+        }
+    }
+}
+
+/// One line after the header of a record file. / 记录文件头之后的一行。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecordEntry {
+    Sample(SampleRecord),
+    Comm(CommRecord),
+    Switch(SwitchRecord),
+    Mmap(MmapRecord),
+    BuildId(BuildIdRecord),
+    /// Records dropped by the kernel since the previous entry of this kind.
+    /// 自上一条同类条目以来被内核丢弃的记录数。
+    Lost {
+        count: u64,
+    },
+}
+
+impl RecordEntry {
+    /// The entry's timestamp, or 0 for entries without one.
+    /// 条目的时间戳；没有时间戳的条目为 0。
+    pub fn time(&self) -> u64 {
+        match self {
+            RecordEntry::Sample(sample) => sample.time,
+            RecordEntry::Comm(comm) => comm.time,
+            RecordEntry::Switch(switch) => switch.time,
+            RecordEntry::Mmap(mmap) => mmap.time,
+            RecordEntry::Lost { .. } | RecordEntry::BuildId(_) => 0,
+        }
+    }
+}
+
+/// A record file read back into memory. / 读回内存的记录文件。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordFile {
+    pub header: RecordHeader,
+    pub entries: Vec<RecordEntry>,
+}
+
+impl RecordFile {
+    /// The samples in the file. / 文件中的采样。
+    pub fn samples(&self) -> impl Iterator<Item = &SampleRecord> {
+        self.entries.iter().filter_map(|entry| match entry {
+            RecordEntry::Sample(sample) => Some(sample),
+            RecordEntry::Comm(_)
+            | RecordEntry::Switch(_)
+            | RecordEntry::Mmap(_)
+            | RecordEntry::BuildId(_)
+            | RecordEntry::Lost { .. } => None,
+        })
+    }
+
+    /// The total number of lost records. / 丢失记录的总数。
+    pub fn lost(&self) -> u64 {
+        self.entries
+            .iter()
+            .map(|entry| match entry {
+                RecordEntry::Lost { count } => *count,
+                RecordEntry::Sample(_)
+                | RecordEntry::Comm(_)
+                | RecordEntry::Switch(_)
+                | RecordEntry::Mmap(_)
+                | RecordEntry::BuildId(_) => 0,
+            })
+            .sum()
+    }
+}
+
+/// Writes a record file. / 写入记录文件。
+#[derive(Debug)]
+pub struct RecordWriter {
+    out: BufWriter<File>,
+}
+
+impl RecordWriter {
+    /// Creates `path` and writes the header. / 创建 `path` 并写入文件头。
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        header: &RecordHeader,
+    ) -> Result<Self, PipaCollectorError> {
+        let path = path.as_ref();
+        let mut out = BufWriter::new(File::create(path).at_path(path)?);
+        write_json_line(&mut out, header)?;
+        Ok(RecordWriter { out })
+    }
+
+    /// Appends one entry. / 追加一个条目。
+    pub fn write(&mut self, entry: &RecordEntry) -> Result<(), PipaCollectorError> {
+        write_json_line(&mut self.out, entry)
+    }
+
+    /// Flushes buffered entries to disk. / 将缓冲的条目写入磁盘。
+    pub fn flush(&mut self) -> Result<(), PipaCollectorError> {
+        Ok(self.out.flush()?)
+    }
+}
+
+/// The names of the threads of running process `pid` as `PERF_RECORD_COMM`
+/// records, which the kernel only sends for names set after recording
+/// starts. / 以 `PERF_RECORD_COMM` 记录表示的运行中进程 `pid` 各线程的名称；
+/// 内核只会为记录开始后设置的名称发送这些记录。
+#[cfg(not(tarpaulin_include))]
+pub fn synthesize_comms(root: &SysRoot, pid: u32) -> Result<Vec<CommRecord>, PipaCollectorError> {
+    let task = root.proc(format!("{}/task", pid));
+    let mut comms = Vec::new();
+    for entry in std::fs::read_dir(&task).at_path(&task)? {
+        let name = entry.at_path(&task)?.file_name();
+        let Some(tid) = name.to_str().and_then(|name| name.parse().ok()) else { continue };
+        // Threads may exit meanwhile. / 线程可能在此期间退出。
+        if let Ok(comm) = std::fs::read_to_string(task.join(format!("{}/comm", tid))) {
+            let comm = comm.trim_end().to_string();
+            comms.push(CommRecord { pid, tid, comm, ..Default::default() });
+        }
+    }
+    comms.sort_by_key(|comm| comm.tid);
+    Ok(comms)
+}
+
+/// Reads a record file. Like [`crate::sampler::read_capture`], a truncated
+/// last line is ignored.
+///
+/// 读取记录文件。与 [`crate::sampler::read_capture`] 一样，忽略不完整的最后一行。
+pub fn read_record<P: AsRef<Path>>(path: P) -> Result<RecordFile, PipaCollectorError> {
+    let path = path.as_ref();
+    read_record_lines(File::open(path).map(BufReader::new).at_path(path)?).at_path(path)
+}
+
+/// Parses the lines of a record file. / 解析记录文件的各行。
+fn read_record_lines<R: BufRead>(reader: R) -> Result<RecordFile, PipaCollectorError> {
+    let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+    let header_line =
+        lines.first().ok_or_else(|| PipaCollectorError::MissingData("Empty record file".into()))?;
+    let header: RecordHeader = serde_json::from_str(header_line)
+        .map_err(|e| PipaCollectorError::InvalidFormat(format!("not a pipa record file: {}", e)))
+        .at_line(1)?;
+    if header.version != RECORD_FORMAT_VERSION {
+        return Err(PipaCollectorError::Unsupported(format!(
+            "record format version {} (expected {})",
+            header.version, RECORD_FORMAT_VERSION
+        )));
+    }
+    let mut entries = Vec::with_capacity(lines.len() - 1);
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) if e.is_eof() && i + 1 == lines.len() => break,
+            Err(e) => return Err(PipaCollectorError::InvalidFormat(e.to_string()).at_line(i + 1)),
+        }
+    }
+    Ok(RecordFile { header, entries })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    /// Builds a sample body in `SAMPLE_TYPE` layout. / 按 `SAMPLE_TYPE` 布局构造采样记录体。
+    fn sample_body(raw: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        body.extend_from_slice(&0xffff_ffff_8100_0000u64.to_ne_bytes()); // ip
+        body.extend_from_slice(&42u32.to_ne_bytes()); // pid
+        body.extend_from_slice(&43u32.to_ne_bytes()); // tid
+        body.extend_from_slice(&1_000_000u64.to_ne_bytes()); // time
+        body.extend_from_slice(&3u32.to_ne_bytes()); // cpu
+        body.extend_from_slice(&0u32.to_ne_bytes()); // res
+        body.extend_from_slice(&1u64.to_ne_bytes()); // period
+        body.extend_from_slice(&(raw.len() as u32).to_ne_bytes());
+        body.extend_from_slice(raw);
+        body
+    }
+
+    /// Frames a body with a `perf_event_header`. / 为记录体加上 `perf_event_header`。
+    fn record(record_type: u32, misc: u16, body: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&record_type.to_ne_bytes());
+        bytes.extend_from_slice(&misc.to_ne_bytes());
+        bytes.extend_from_slice(&((body.len() + 8) as u16).to_ne_bytes());
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_sample() {
+        let sample = parse_sample(SAMPLE_TYPE, 0, &sample_body(&[1, 2, 3, 4])).unwrap();
+        assert_eq!((sample.pid, sample.tid, sample.cpu), (42, 43, 3));
+        assert_eq!((sample.time, sample.period), (1_000_000, 1));
+        assert_eq!(sample.raw, [1, 2, 3, 4]);
+        assert!(sample.callchain.is_empty());
+
+        let callchain = SAMPLE_TYPE | sys::bindings::PERF_SAMPLE_CALLCHAIN as u64;
+        let mut body = sample_body(&[]);
+        let raw_size = body.split_off(body.len() - 4);
+        body.extend_from_slice(&2u64.to_ne_bytes());
+        body.extend_from_slice(&0x1000u64.to_ne_bytes());
+        body.extend_from_slice(&0x2000u64.to_ne_bytes());
+        body.extend_from_slice(&raw_size);
+        assert_eq!(parse_sample(callchain, 0, &body).unwrap().callchain, [0x1000, 0x2000]);
+
+        let err = parse_sample(SAMPLE_TYPE, 0, &sample_body(&[1, 2])[..20]).unwrap_err();
+        assert!(err.to_string().contains("truncated at offset 16"), "{}", err);
+        let read = sys::bindings::PERF_SAMPLE_READ as u64;
+        assert!(matches!(parse_sample(read, 0, &[]), Err(PipaCollectorError::Unsupported(_))));
+    }
+
+    #[test]
+    fn test_parse_call_graph_fields() {
+        let config = |call_graph| RecordConfig {
+            events: Vec::new(),
+            target: RecordTarget::AllCpus,
+            frequency: 1,
+            pages: 1,
+            call_graph: Some(call_graph),
+            context_switch: false,
+        };
+        let u64s =
+            |values: &[u64]| -> Vec<u8> { values.iter().flat_map(|v| v.to_ne_bytes()).collect() };
+
+        // An empty callchain and raw payload, then two LBR entries of
+        // (from, to, flags). / 空的调用链与原始负载，然后是两条 LBR 条目。
+        let lbr = config(CallGraph::Lbr);
+        let mut body = sample_body(&[]);
+        body.splice(body.len() - 4..body.len() - 4, u64s(&[0]));
+        body.extend(u64s(&[2, 0x1010, 0x2000, 0, 0x3010, 0x1000, 0]));
+        let sample = parse_sample(lbr.sample_type(), lbr.regs_user(), &body).unwrap();
+        assert_eq!(
+            sample.branch_stack,
+            [BranchEntry { from: 0x1010, to: 0x2000 }, BranchEntry { from: 0x3010, to: 0x1000 }]
+        );
+
+        // Two registers of a 64-bit ABI, then 16 bytes of stack of which 12
+        // were filled in. / 64 位 ABI 的两个寄存器，然后是 16 字节栈（已填充 12 字节）。
+        let dwarf = config(CallGraph::Dwarf);
+        let mut body = sample_body(&[]);
+        body.splice(body.len() - 4..body.len() - 4, u64s(&[0]));
+        body.extend(u64s(&[sys::bindings::PERF_SAMPLE_REGS_ABI_64 as u64, 0x7ff0, 0x4000]));
+        body.extend(u64s(&[16]));
+        body.extend(0u8..16);
+        body.extend(u64s(&[12]));
+        let sample = parse_sample(dwarf.sample_type(), 0b101, &body).unwrap();
+        assert_eq!(sample.regs_user, [0x7ff0, 0x4000]);
+        assert_eq!(sample.stack_user, (0u8..12).collect::<Vec<_>>());
+
+        // A kernel thread has no user registers and an empty stack.
+        // 内核线程没有用户态寄存器，栈为空。
+        let mut body = sample_body(&[]);
+        body.splice(body.len() - 4..body.len() - 4, u64s(&[0]));
+        body.extend(u64s(&[0, 0]));
+        let sample = parse_sample(dwarf.sample_type(), 0b101, &body).unwrap();
+        assert!(sample.regs_user.is_empty() && sample.stack_user.is_empty());
+        // A corrupt stack size is an error rather than an overflow.
+        // 损坏的栈大小会返回错误，而不是溢出。
+        let last = body.len() - 8;
+        body[last..].copy_from_slice(&u64::MAX.to_ne_bytes());
+        assert!(parse_sample(dwarf.sample_type(), 0b101, &body).is_err());
+        assert_eq!(dwarf.regs_user(), USER_REGS_MASK);
+        assert_eq!("dwarf".parse::<CallGraph>().unwrap(), CallGraph::Dwarf);
+        assert!("frame".parse::<CallGraph>().is_err());
+    }
+
+    #[test]
+    fn test_ordered_entries() {
+        let sample = |time| RecordEntry::Sample(SampleRecord { time, ..Default::default() });
+        let times = |entries: Vec<RecordEntry>| -> Vec<u64> {
+            entries.iter().map(RecordEntry::time).collect()
+        };
+        let mut ordered = OrderedEntries::new();
+        // The first drain starts at 100; nothing is known to be complete yet.
+        // 第一次清空始于 100，此时尚无确定完整的条目。
+        assert!(ordered.push(vec![sample(90), sample(40)], 100).is_empty());
+        // One CPU's 95 only shows up in the second drain, from 200.
+        // 某个 CPU 的 95 直到始于 200 的第二次清空才出现。
+        assert_eq!(times(ordered.push(vec![sample(150), sample(95)], 200)), [40, 90, 95]);
+        assert_eq!(times(ordered.push(vec![sample(250)], 300)), [150]);
+        assert_eq!(times(ordered.finish()), [250]);
+        assert!(ordered.finish().is_empty());
+    }
+
+    #[test]
+    fn test_parse_mmap2_record() {
+        // pid, tid, addr, len, pgoff, the device fields, prot, flags, the
+        // name padded to 8 bytes, then the sample_id trailer.
+        // pid、tid、addr、len、pgoff、设备字段、prot、flags、补齐到 8 字节的名称，
+        // 然后是 sample_id 尾部。
+        let mut body = Vec::new();
+        for value in [42u32, 42] {
+            body.extend_from_slice(&value.to_ne_bytes());
+        }
+        for value in [0x5555_0000_1000u64, 0x2000, 0x1000] {
+            body.extend_from_slice(&value.to_ne_bytes());
+        }
+        body.extend_from_slice(&[0; 24]);
+        body.extend_from_slice(&[5, 0, 0, 0, 2, 0, 0, 0]);
+        body.extend_from_slice(b"/bin/ls\0");
+        for value in [42u32, 42] {
+            body.extend_from_slice(&value.to_ne_bytes());
+        }
+        body.extend_from_slice(&8_000u64.to_ne_bytes());
+        body.extend_from_slice(&[0; 8]);
+        let mut mmap = MmapRecord {
+            pid: 42,
+            tid: 42,
+            start: 0x5555_0000_1000,
+            len: 0x2000,
+            pgoff: 0x1000,
+            filename: "/bin/ls".into(),
+            build_id: Vec::new(),
+            time: 8_000,
+        };
+        assert_eq!(
+            parse_record(sys::bindings::PERF_RECORD_MMAP2, 0, SAMPLE_TYPE, 0, &body).unwrap(),
+            PerfRecord::Mmap(mmap.clone())
+        );
+
+        // With PERF_RECORD_MISC_MMAP_BUILD_ID the device fields hold a
+        // build-ID instead. / 带有 PERF_RECORD_MISC_MMAP_BUILD_ID 时设备字段改为构建 ID。
+        body[32..36].copy_from_slice(&[4, 0, 0, 0]);
+        body[36..40].copy_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        let misc = sys::bindings::PERF_RECORD_MISC_MMAP_BUILD_ID as u16;
+        mmap.build_id = vec![0xde, 0xad, 0xbe, 0xef];
+        assert_eq!(
+            parse_record(sys::bindings::PERF_RECORD_MMAP2, misc, SAMPLE_TYPE, 0, &body).unwrap(),
+            PerfRecord::Mmap(mmap)
+        );
+    }
+
+    #[test]
+    fn test_split_records_wraps_around() {
+        let sample = record(sys::bindings::PERF_RECORD_SAMPLE, 0, &sample_body(&[9; 4]));
+        let mut lost_body = 7u64.to_ne_bytes().to_vec();
+        lost_body.extend_from_slice(&5u64.to_ne_bytes());
+        let lost = record(sys::bindings::PERF_RECORD_LOST, 0, &lost_body);
+
+        // Place the stream so that the sample wraps past the end of the ring.
+        // 放置数据流，使采样记录跨越环形缓冲区末尾。
+        let stream: Vec<u8> = [lost.clone(), sample.clone()].concat();
+        let mut ring = vec![0u8; 128];
+        let start = 1000u64;
+        for (i, byte) in stream.iter().enumerate() {
+            ring[((start + i as u64) % 128) as usize] = *byte;
+        }
+        let head = start + stream.len() as u64;
+        let records = split_records(&ring, start, head).unwrap();
+        assert_eq!(records.len(), 2);
+        let parsed: Vec<PerfRecord> = records
+            .iter()
+            .map(|(t, misc, body)| parse_record(*t, *misc, SAMPLE_TYPE, 0, body).unwrap())
+            .collect();
+        assert_eq!(parsed[0], PerfRecord::Lost(5));
+        let PerfRecord::Sample(sample) = &parsed[1] else { panic!("expected a sample") };
+        assert_eq!(sample.raw, [9; 4]);
+
+        // A partial record at the head is left for the next drain.
+        // 头部不完整的记录留待下次读取。
+        assert_eq!(split_records(&ring, start, start + 4).unwrap().len(), 0);
+        assert!(split_records(&ring, start, start + 16).is_err());
+        assert_eq!(parse_record(5, 0, SAMPLE_TYPE, 0, &[]).unwrap(), PerfRecord::Other(5));
+    }
+
+    #[test]
+    fn test_parse_comm_record() {
+        // pid, tid, the name padded to 8 bytes, then the sample_id trailer
+        // (pid, tid, time, cpu, res).
+        // pid、tid、补齐到 8 字节的名称，然后是 sample_id 尾部（pid、tid、time、cpu、res）。
+        let mut body = Vec::new();
+        for value in [42u32, 43] {
+            body.extend_from_slice(&value.to_ne_bytes());
+        }
+        body.extend_from_slice(b"ls\0\0\0\0\0\0");
+        for value in [42u32, 43] {
+            body.extend_from_slice(&value.to_ne_bytes());
+        }
+        body.extend_from_slice(&7_000u64.to_ne_bytes());
+        body.extend_from_slice(&[0; 8]);
+        let exec = sys::bindings::PERF_RECORD_MISC_COMM_EXEC as u16;
+        let comm = parse_record(sys::bindings::PERF_RECORD_COMM, exec, SAMPLE_TYPE, 0, &body);
+        assert_eq!(
+            comm.unwrap(),
+            PerfRecord::Comm(CommRecord {
+                pid: 42,
+                tid: 43,
+                comm: "ls".into(),
+                time: 7_000,
+                exec: true
+            })
+        );
+        assert!(parse_sample_id(SAMPLE_TYPE, &body[..16]).is_err());
+    }
+
+    #[test]
+    fn test_parse_switch_record() {
+        // A CPU-wide switch out: the next task's pid and tid, then the
+        // sample_id trailer (pid, tid, time, cpu, res).
+        // CPU 范围的切出记录：下一个任务的 pid 和 tid，然后是 sample_id 尾部。
+        let mut body = Vec::new();
+        for value in [0u32, 0, 42, 43] {
+            body.extend_from_slice(&value.to_ne_bytes());
+        }
+        body.extend_from_slice(&9_000u64.to_ne_bytes());
+        body.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 0]);
+        let misc = (sys::bindings::PERF_RECORD_MISC_SWITCH_OUT
+            | sys::bindings::PERF_RECORD_MISC_SWITCH_OUT_PREEMPT) as u16;
+        let cpu_wide = sys::bindings::PERF_RECORD_SWITCH_CPU_WIDE;
+        let expected =
+            SwitchRecord { pid: 42, tid: 43, time: 9_000, cpu: 2, out: true, preempt: true };
+        assert_eq!(
+            parse_record(cpu_wide, misc, SAMPLE_TYPE, 0, &body).unwrap(),
+            PerfRecord::Switch(expected.clone())
+        );
+        // A task-bound switch in has only the trailer.
+        // 绑定任务的切入记录只有尾部。
+        assert_eq!(
+            parse_record(sys::bindings::PERF_RECORD_SWITCH, 0, SAMPLE_TYPE, 0, &body[8..]).unwrap(),
+            PerfRecord::Switch(SwitchRecord { out: false, preempt: false, ..expected })
+        );
+        let config = RecordConfig {
+            events: Vec::new(),
+            target: RecordTarget::AllCpus,
+            frequency: 1,
+            pages: 1,
+            call_graph: Some(CallGraph::Fp),
+            context_switch: true,
+        };
+        assert_ne!(config.sample_type() & sys::bindings::PERF_SAMPLE_CALLCHAIN as u64, 0);
+        assert_eq!(config.regs_user(), 0);
+    }
+
+    #[test]
+    fn test_record_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pipa.data");
+        let header = RecordHeader::new(
+            &SysRoot::default(),
+            vec!["true".into()],
+            vec![RecordedEvent { name: "sched:sched_switch".into(), format: None }],
+        );
+        let sample = SampleRecord { tid: 7, raw: vec![0xab, 0x01], ..Default::default() };
+        let mut writer = RecordWriter::create(&path, &header).unwrap();
+        writer.write(&RecordEntry::Sample(sample.clone())).unwrap();
+        writer.write(&RecordEntry::Lost { count: 3 }).unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"raw\":\"ab01\""), "{}", content);
+        let file = read_record(&path).unwrap();
+        assert_eq!(file.header, header);
+        assert_eq!(file.samples().collect::<Vec<_>>(), [&sample]);
+        assert_eq!(file.lost(), 3);
+
+        // A truncated last line is ignored; a bad line elsewhere is an error.
+        // 忽略不完整的最后一行；其他位置的错误行会报错。
+        std::fs::write(&path, format!("{}{{\"type\":\"sam", content)).unwrap();
+        assert_eq!(read_record(&path).unwrap().entries.len(), 2);
+        std::fs::write(&path, format!("{}{{}}\n{{}}\n", content)).unwrap();
+        let err = read_record(&path).unwrap_err();
+        assert!(err.to_string().contains(":4: "), "{}", err);
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(read_record(&path).unwrap_err().code(), ErrorCode::Missing);
+    }
+}
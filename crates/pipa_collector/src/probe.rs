@@ -0,0 +1,399 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dynamic kprobes and uprobes created through the `kprobe` and `uprobe` PMUs
+//! in `/sys/bus/event_source/devices`. The probe target is passed to
+//! `perf_event_open` in `config1`/`config2`, so nothing is written to tracefs
+//! and the probe disappears with its file descriptor.
+//!
+//! 通过 `/sys/bus/event_source/devices` 中的 `kprobe` 和 `uprobe` PMU 创建的动态
+//! kprobe 与 uprobe。探测目标通过 `config1`/`config2` 传给 `perf_event_open`，
+//! 因此无需写入 tracefs，探针随其文件描述符一起消失。
+
+use crate::error::{ResultExt, read_file};
+use crate::sysroot::SysRoot;
+use crate::system_stats::PipaCollectorError;
+use object::{Object, ObjectSegment, ObjectSymbol, SymbolKind};
+use std::ffi::CString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Where a probe is placed. / 探针所在的位置。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProbeTarget {
+    /// A kernel function, optionally at an offset into it.
+    /// 内核函数，可带有函数内偏移。
+    Kernel { function: String, offset: u64 },
+    /// A raw kernel address. / 原始内核地址。
+    KernelAddress(u64),
+    /// A symbol in an ELF file, optionally at an offset into it.
+    /// ELF 文件中的符号，可带有符号内偏移。
+    UserSymbol { path: PathBuf, symbol: String, offset: u64 },
+    /// A raw file offset in an ELF file. / ELF 文件中的原始文件偏移。
+    UserOffset { path: PathBuf, offset: u64 },
+}
+
+/// A probe as written after `-e`: `kprobe:FUNC[+OFF]`, `kretprobe:FUNC`,
+/// `uprobe:PATH:SYMBOL[+OFF]`, `uprobe:PATH:0xOFFSET` or
+/// `uretprobe:PATH:SYMBOL`.
+///
+/// `-e` 之后书写的探针：`kprobe:FUNC[+OFF]`、`kretprobe:FUNC`、
+/// `uprobe:PATH:SYMBOL[+OFF]`、`uprobe:PATH:0xOFFSET` 或 `uretprobe:PATH:SYMBOL`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeSpec {
+    pub target: ProbeTarget,
+    /// Fire when the function returns instead of when it is entered.
+    /// 在函数返回而非进入时触发。
+    pub retprobe: bool,
+}
+
+impl ProbeSpec {
+    /// Whether `name` is a probe rather than a tracepoint.
+    /// `name` 是否为探针而非跟踪点。
+    pub fn is_probe(name: &str) -> bool {
+        ["kprobe:", "kretprobe:", "uprobe:", "uretprobe:"].iter().any(|p| name.starts_with(p))
+    }
+
+    /// The PMU that implements this probe. / 实现此探针的 PMU。
+    fn pmu_name(&self) -> &'static str {
+        match self.target {
+            ProbeTarget::Kernel { .. } | ProbeTarget::KernelAddress(_) => "kprobe",
+            ProbeTarget::UserSymbol { .. } | ProbeTarget::UserOffset { .. } => "uprobe",
+        }
+    }
+
+    /// Looks up the PMU and, for uprobes, the symbol's file offset.
+    /// 查找 PMU，对于 uprobe 还会查找符号的文件偏移。
+    pub fn resolve(&self, root: &SysRoot) -> Result<ProbeEvent, PipaCollectorError> {
+        let pmu = DynamicPmu::read(root, self.pmu_name())?;
+        let config = if self.retprobe { 1 << pmu.retprobe_bit } else { 0 };
+        let c_string = |s: &str| {
+            CString::new(s).map_err(|_| {
+                PipaCollectorError::InvalidFormat(format!("probe target `{}` contains NUL", s))
+            })
+        };
+        let (target, offset) = match &self.target {
+            ProbeTarget::Kernel { function, offset } => (Some(c_string(function)?), *offset),
+            ProbeTarget::KernelAddress(address) => (None, *address),
+            ProbeTarget::UserSymbol { path, symbol, offset } => {
+                let file_offset = elf_symbol_offset(path, symbol)?;
+                (Some(c_string(&path.to_string_lossy())?), file_offset + offset)
+            }
+            ProbeTarget::UserOffset { path, offset } => {
+                (Some(c_string(&path.to_string_lossy())?), *offset)
+            }
+        };
+        Ok(ProbeEvent { pmu_type: pmu.type_, config, target, offset })
+    }
+}
+
+/// Parses a decimal or `0x` hexadecimal number. / 解析十进制或 `0x` 十六进制数。
+fn parse_number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Splits `NAME[+OFF]`. / 拆分 `NAME[+OFF]`。
+fn split_offset(s: &str) -> Result<(&str, u64), PipaCollectorError> {
+    match s.rsplit_once('+') {
+        Some((name, offset)) => {
+            parse_number(offset).map(|offset| (name, offset)).ok_or_else(|| {
+                PipaCollectorError::InvalidFormat(format!("bad probe offset `{}`", offset))
+            })
+        }
+        None => Ok((s, 0)),
+    }
+}
+
+impl FromStr for ProbeSpec {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = |why: &str| PipaCollectorError::InvalidFormat(format!("probe `{}`: {}", s, why));
+        let (kind, rest) = s.split_once(':').ok_or_else(|| bad("expected kind:target"))?;
+        let retprobe = matches!(kind, "kretprobe" | "uretprobe");
+        let target = match kind {
+            "kprobe" | "kretprobe" => match parse_number(rest) {
+                Some(address) if rest.starts_with("0x") => ProbeTarget::KernelAddress(address),
+                _ => {
+                    let (function, offset) = split_offset(rest)?;
+                    if function.is_empty() {
+                        return Err(bad("missing kernel function"));
+                    }
+                    ProbeTarget::Kernel { function: function.to_string(), offset }
+                }
+            },
+            "uprobe" | "uretprobe" => {
+                let (path, location) =
+                    rest.rsplit_once(':').ok_or_else(|| bad("expected PATH:SYMBOL"))?;
+                if path.is_empty() || location.is_empty() {
+                    return Err(bad("expected PATH:SYMBOL"));
+                }
+                let path = PathBuf::from(path);
+                match parse_number(location) {
+                    Some(offset) if location.starts_with("0x") => {
+                        ProbeTarget::UserOffset { path, offset }
+                    }
+                    _ => {
+                        let (symbol, offset) = split_offset(location)?;
+                        ProbeTarget::UserSymbol { path, symbol: symbol.to_string(), offset }
+                    }
+                }
+            }
+            _ => return Err(bad("expected kprobe, kretprobe, uprobe or uretprobe")),
+        };
+        let inside_function = matches!(
+            target,
+            ProbeTarget::Kernel { offset: 1.., .. } | ProbeTarget::UserSymbol { offset: 1.., .. }
+        );
+        if retprobe && inside_function {
+            return Err(bad("return probes must be placed at a function entry"));
+        }
+        Ok(ProbeSpec { target, retprobe })
+    }
+}
+
+impl fmt::Display for ProbeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match (self.pmu_name(), self.retprobe) {
+            ("kprobe", false) => "kprobe",
+            ("kprobe", true) => "kretprobe",
+            (_, false) => "uprobe",
+            (_, true) => "uretprobe",
+        };
+        let offset =
+            |offset: u64| if offset > 0 { format!("+{:#x}", offset) } else { String::new() };
+        match &self.target {
+            ProbeTarget::Kernel { function, offset: o } => {
+                write!(f, "{}:{}{}", prefix, function, offset(*o))
+            }
+            ProbeTarget::KernelAddress(address) => write!(f, "{}:{:#x}", prefix, address),
+            ProbeTarget::UserSymbol { path, symbol, offset: o } => {
+                write!(f, "{}:{}:{}{}", prefix, path.display(), symbol, offset(*o))
+            }
+            ProbeTarget::UserOffset { path, offset } => {
+                write!(f, "{}:{}:{:#x}", prefix, path.display(), offset)
+            }
+        }
+    }
+}
+
+/// The `kprobe` or `uprobe` PMU. / `kprobe` 或 `uprobe` PMU。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicPmu {
+    /// The `perf_event_attr.type` to use. / 要使用的 `perf_event_attr.type`。
+    pub type_: u32,
+    /// The `config` bit that turns the probe into a return probe.
+    /// 将探针变为返回探针的 `config` 位。
+    pub retprobe_bit: u32,
+}
+
+impl DynamicPmu {
+    /// Reads `devices/<name>/type` and `format/retprobe`.
+    /// 读取 `devices/<name>/type` 与 `format/retprobe`。
+    pub fn read(root: &SysRoot, name: &str) -> Result<Self, PipaCollectorError> {
+        let dir = root.sys(format!("bus/event_source/devices/{}", name));
+        let type_path = dir.join("type");
+        let type_ = match read_file(&type_path) {
+            Ok(content) => {
+                content.trim().parse().map_err(PipaCollectorError::from).at_path(&type_path)?
+            }
+            Err(e) if e.is_not_found() => {
+                return Err(PipaCollectorError::EventNotSupported(format!(
+                    "the kernel has no `{}` PMU (needs CONFIG_{}_EVENTS)",
+                    name,
+                    name.to_uppercase()
+                )));
+            }
+            Err(e) => return Err(e),
+        };
+        // Kernels before the format file was added use bit 0.
+        // 在添加 format 文件之前的内核使用第 0 位。
+        let retprobe_path = dir.join("format/retprobe");
+        let retprobe_bit = match read_file(&retprobe_path) {
+            Ok(content) => parse_config_bit(&content).at_path(&retprobe_path)?,
+            Err(e) if e.is_not_found() => 0,
+            Err(e) => return Err(e),
+        };
+        Ok(DynamicPmu { type_, retprobe_bit })
+    }
+}
+
+/// Parses a single-bit PMU format such as `config:0`.
+/// 解析 `config:0` 这样的单比特 PMU 格式。
+fn parse_config_bit(content: &str) -> Result<u32, PipaCollectorError> {
+    let content = content.trim();
+    content
+        .strip_prefix("config:")
+        .and_then(|bit| bit.parse().ok())
+        .filter(|bit| *bit < 64)
+        .ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(format!("expected config:N, got `{}`", content))
+        })
+}
+
+/// A resolved probe, ready for `perf_event_open`.
+/// 已解析的探针，可直接用于 `perf_event_open`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeEvent {
+    /// The PMU type. / PMU 类型。
+    pub pmu_type: u32,
+    /// `config`, with the return-probe bit set if requested.
+    /// `config`，按需设置返回探针位。
+    pub config: u64,
+    /// The function name (`config1` of a kprobe) or file path (`config1` of
+    /// a uprobe); `None` for a raw kernel address.
+    /// 函数名（kprobe 的 `config1`）或文件路径（uprobe 的 `config1`）；原始内核地址时为 `None`。
+    pub target: Option<CString>,
+    /// `config2`: the offset into the function, the kernel address, or the
+    /// file offset. / `config2`：函数内偏移、内核地址或文件偏移。
+    pub offset: u64,
+}
+
+/// Finds the file offset of a function symbol in an ELF file, which is what
+/// the `uprobe` PMU expects.
+///
+/// 查找 ELF 文件中函数符号的文件偏移，这正是 `uprobe` PMU 所需要的。
+pub fn elf_symbol_offset(path: &Path, symbol: &str) -> Result<u64, PipaCollectorError> {
+    let data = std::fs::read(path).at_path(path)?;
+    let file = object::File::parse(&*data)
+        .map_err(|e| PipaCollectorError::InvalidFormat(format!("not an ELF file: {}", e)))
+        .at_path(path)?;
+    let address = file
+        .symbols()
+        .chain(file.dynamic_symbols())
+        .find(|s| s.is_definition() && s.kind() == SymbolKind::Text && s.name() == Ok(symbol))
+        .map(|s| s.address())
+        .ok_or_else(|| {
+            PipaCollectorError::MissingData(format!(
+                "no function `{}` in the symbol tables",
+                symbol
+            ))
+        })
+        .at_path(path)?;
+    let segments: Vec<(u64, u64, u64)> =
+        file.segments().map(|s| (s.address(), s.size(), s.file_range().0)).collect();
+    vaddr_to_file_offset(&segments, address)
+        .ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(format!(
+                "`{}` at {:#x} is not in a loadable segment",
+                symbol, address
+            ))
+        })
+        .at_path(path)
+}
+
+/// Maps a virtual address to a file offset through the loadable segments,
+/// given as `(address, size, file offset)`.
+///
+/// 通过可加载段（以 `(地址, 大小, 文件偏移)` 表示）将虚拟地址映射到文件偏移。
+fn vaddr_to_file_offset(segments: &[(u64, u64, u64)], address: u64) -> Option<u64> {
+    segments
+        .iter()
+        .find(|(start, size, _)| (*start..start + size).contains(&address))
+        .map(|(start, _, offset)| address - start + offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use std::fs;
+
+    #[test]
+    fn test_parse_probe_spec() {
+        let spec: ProbeSpec = "uprobe:/usr/bin/app:func+0x10".parse().unwrap();
+        assert_eq!(
+            spec.target,
+            ProbeTarget::UserSymbol {
+                path: "/usr/bin/app".into(),
+                symbol: "func".into(),
+                offset: 16
+            }
+        );
+        assert!(!spec.retprobe);
+        assert_eq!(spec.to_string(), "uprobe:/usr/bin/app:func+0x10");
+
+        let spec: ProbeSpec = "uretprobe:/lib/libc.so.6:malloc".parse().unwrap();
+        assert!(spec.retprobe);
+        let spec: ProbeSpec = "uprobe:/bin/sh:0x1a20".parse().unwrap();
+        assert_eq!(spec.target, ProbeTarget::UserOffset { path: "/bin/sh".into(), offset: 0x1a20 });
+        let spec: ProbeSpec = "kretprobe:do_sys_openat2".parse().unwrap();
+        assert_eq!(spec.to_string(), "kretprobe:do_sys_openat2");
+        let spec: ProbeSpec = "kprobe:0xffffffff81000000".parse().unwrap();
+        assert_eq!(spec.target, ProbeTarget::KernelAddress(0xffff_ffff_8100_0000));
+
+        for bad in [
+            "kretprobe:vfs_read+4",
+            "uprobe:/bin/sh",
+            "uprobe::main",
+            "kprobe:",
+            "kprobe:f+x",
+            "xprobe:f",
+        ] {
+            assert!(bad.parse::<ProbeSpec>().is_err(), "{}", bad);
+        }
+        assert!(ProbeSpec::is_probe("uprobe:/bin/sh:main"));
+        assert!(!ProbeSpec::is_probe("sched:sched_switch"));
+    }
+
+    #[test]
+    fn test_dynamic_pmu_and_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let pmu = dir.path().join("bus/event_source/devices/uprobe");
+        fs::create_dir_all(pmu.join("format")).unwrap();
+        fs::write(pmu.join("type"), "8\n").unwrap();
+        fs::write(pmu.join("format/retprobe"), "config:0\n").unwrap();
+        let root = SysRoot::default().with_sys(dir.path());
+        assert_eq!(
+            DynamicPmu::read(&root, "uprobe").unwrap(),
+            DynamicPmu { type_: 8, retprobe_bit: 0 }
+        );
+
+        let spec: ProbeSpec = "uretprobe:/bin/app:0x40".parse().unwrap();
+        let event = spec.resolve(&root).unwrap();
+        assert_eq!((event.pmu_type, event.config, event.offset), (8, 1, 0x40));
+        assert_eq!(event.target.unwrap().to_str().unwrap(), "/bin/app");
+
+        let err = "kprobe:vfs_read".parse::<ProbeSpec>().unwrap().resolve(&root).unwrap_err();
+        assert_eq!(err.code(), ErrorCode::EventNotSupported);
+        assert!(err.to_string().contains("CONFIG_KPROBE_EVENTS"), "{}", err);
+
+        assert_eq!(parse_config_bit("config:3\n").unwrap(), 3);
+        assert!(parse_config_bit("config:32-63").is_err());
+    }
+
+    #[test]
+    fn test_elf_symbol_offset() {
+        let segments = [(0x0, 0x1000, 0x0), (0x401000, 0x2000, 0x1000)];
+        assert_eq!(vaddr_to_file_offset(&segments, 0x401234), Some(0x1234));
+        assert_eq!(vaddr_to_file_offset(&segments, 0x403000), None);
+
+        // The test binary itself is an ELF executable with a `main` function.
+        // 测试二进制文件本身就是带有 `main` 函数的 ELF 可执行文件。
+        let exe = std::env::current_exe().unwrap();
+        let offset = elf_symbol_offset(&exe, "main").unwrap();
+        assert!(offset > 0 && offset < fs::metadata(&exe).unwrap().len());
+
+        let err = elf_symbol_offset(&exe, "no_such_function_here").unwrap_err();
+        assert_eq!(err.code(), ErrorCode::Missing);
+        let dir = tempfile::tempdir().unwrap();
+        let text = dir.path().join("text");
+        fs::write(&text, "not elf").unwrap();
+        assert_eq!(elf_symbol_offset(&text, "main").unwrap_err().code(), ErrorCode::Format);
+    }
+}
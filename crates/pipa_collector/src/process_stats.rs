@@ -0,0 +1,779 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module collects per-process resource usage by parsing
+//! `/proc/<pid>/{stat,status,io,statm}`, and can aggregate the per-thread
+//! views found under `/proc/<pid>/task`.
+//!
+//! 本模块通过解析 `/proc/<pid>/{stat,status,io,statm}` 收集单个进程的资源使用情况，
+//! 并且可以聚合 `/proc/<pid>/task` 下每个线程的数据。
+
+use crate::error::{ResultExt, parse_file};
+use crate::sysroot::SysRoot;
+use crate::system_stats::{Delta, PipaCollectorError};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Fields of interest from `/proc/<pid>/stat`. CPU times are in clock ticks
+/// (see [`clock_ticks_per_second`]).
+///
+/// 来自 `/proc/<pid>/stat` 的关键字段。CPU 时间的单位是时钟滴答
+/// （参见 [`clock_ticks_per_second`]）。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ProcessStat {
+    /// Process (or thread) id. / 进程（或线程）ID。
+    pub pid: u32,
+    /// Command name, without the surrounding parentheses. May contain spaces
+    /// and parentheses. / 命令名（不含外层括号），可能包含空格和括号。
+    pub comm: String,
+    /// Scheduler state, e.g. `R`, `S`, `D`, `Z`.
+    /// 调度状态，例如 `R`、`S`、`D`、`Z`。
+    pub state: char,
+    /// Parent process id. / 父进程 ID。
+    pub ppid: u32,
+    /// Minor faults (no disk I/O). / 次要缺页次数（无需磁盘 I/O）。
+    pub minflt: u64,
+    /// Major faults (required disk I/O). / 主要缺页次数（需要磁盘 I/O）。
+    pub majflt: u64,
+    /// Time scheduled in user mode. / 在用户态被调度的时间。
+    pub utime: u64,
+    /// Time scheduled in kernel mode. / 在内核态被调度的时间。
+    pub stime: u64,
+    /// Kernel scheduling priority. / 内核调度优先级。
+    pub priority: i64,
+    /// Nice value. / nice 值。
+    pub nice: i64,
+    /// Number of threads in the thread group. / 线程组中的线程数。
+    pub num_threads: u64,
+    /// Start time after boot, in clock ticks. / 启动后的开始时间（时钟滴答）。
+    pub starttime: u64,
+    /// Virtual memory size in bytes. / 虚拟内存大小（字节）。
+    pub vsize: u64,
+    /// Resident set size in pages. / 常驻内存大小（页）。
+    pub rss: u64,
+    /// CPU the task last ran on. / 任务最后运行的 CPU。
+    pub processor: u32,
+}
+
+/// Parses the content of `/proc/<pid>/stat`. The command name is delimited by
+/// the first `(` and the *last* `)`, so names such as `my (weird) proc` parse
+/// correctly.
+///
+/// 解析 `/proc/<pid>/stat` 的内容。命令名以第一个 `(` 和*最后一个* `)` 为界，
+/// 因此像 `my (weird) proc` 这样的名字也能被正确解析。
+fn parse_process_stat_from_content(content: &str) -> Result<ProcessStat, PipaCollectorError> {
+    let open = content.find('(').ok_or_else(|| {
+        PipaCollectorError::InvalidFormat("Missing '(' before comm in stat".to_string())
+    })?;
+    let close = content.rfind(')').filter(|&c| c > open).ok_or_else(|| {
+        PipaCollectorError::InvalidFormat("Missing ')' after comm in stat".to_string())
+    })?;
+
+    let pid = content[..open].trim().parse::<u32>()?;
+    let comm = content[open + 1..close].to_string();
+    // Field 3 (`state`) onwards; indices below are `field number - 3`.
+    let fields: Vec<&str> = content[close + 1..].split_whitespace().collect();
+
+    let field = |index: usize, name: &str| {
+        fields.get(index).copied().ok_or_else(|| {
+            PipaCollectorError::MissingData(format!("Missing value for {} in stat", name))
+        })
+    };
+
+    let state = field(0, "state")?.chars().next().unwrap_or('?');
+
+    Ok(ProcessStat {
+        pid,
+        comm,
+        state,
+        ppid: field(1, "ppid")?.parse()?,
+        minflt: field(7, "minflt")?.parse()?,
+        majflt: field(9, "majflt")?.parse()?,
+        utime: field(11, "utime")?.parse()?,
+        stime: field(12, "stime")?.parse()?,
+        priority: field(15, "priority")?.parse()?,
+        nice: field(16, "nice")?.parse()?,
+        num_threads: field(17, "num_threads")?.parse()?,
+        starttime: field(19, "starttime")?.parse()?,
+        vsize: field(20, "vsize")?.parse()?,
+        rss: field(21, "rss")?.parse()?,
+        processor: field(36, "processor")?.parse()?,
+    })
+}
+
+/// Fields of interest from `/proc/<pid>/status`. Memory values are in kB and
+/// are zero for kernel threads, which have no address space.
+///
+/// 来自 `/proc/<pid>/status` 的关键字段。内存值单位为 kB；
+/// 内核线程没有地址空间，这些值为 0。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ProcessStatus {
+    /// Command name as escaped by the kernel. / 经内核转义后的命令名。
+    pub name: String,
+    /// Scheduler state letter. / 调度状态字母。
+    pub state: char,
+    /// Thread group id. / 线程组 ID。
+    pub tgid: u32,
+    /// Number of threads. / 线程数。
+    pub threads: u64,
+    /// Peak virtual memory size. / 虚拟内存峰值。
+    pub vm_peak: u64,
+    /// Current virtual memory size. / 当前虚拟内存大小。
+    pub vm_size: u64,
+    /// Peak resident set size. / 常驻内存峰值。
+    pub vm_hwm: u64,
+    /// Current resident set size. / 当前常驻内存大小。
+    pub vm_rss: u64,
+    /// Swapped-out anonymous memory. / 被换出的匿名内存。
+    pub vm_swap: u64,
+    /// Voluntary context switches (the task blocked).
+    /// 自愿上下文切换次数（任务主动阻塞）。
+    pub voluntary_ctxt_switches: u64,
+    /// Involuntary context switches (the task was preempted).
+    /// 非自愿上下文切换次数（任务被抢占）。
+    pub nonvoluntary_ctxt_switches: u64,
+}
+
+/// Parses the content of `/proc/<pid>/status`.
+///
+/// 解析 `/proc/<pid>/status` 的内容。
+fn parse_process_status_from_content(content: &str) -> Result<ProcessStatus, PipaCollectorError> {
+    let mut status = ProcessStatus::default();
+    let mut has_name = false;
+    let mut has_state = false;
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        // Memory lines carry a " kB" suffix; the rest are bare numbers.
+        let number = || value.split_whitespace().next().unwrap_or("").parse::<u64>();
+
+        match key {
+            "Name" => {
+                status.name = value.to_string();
+                has_name = true;
+            }
+            "State" => {
+                status.state = value.chars().next().unwrap_or('?');
+                has_state = true;
+            }
+            "Tgid" => status.tgid = value.parse()?,
+            "Threads" => status.threads = number()?,
+            "VmPeak" => status.vm_peak = number()?,
+            "VmSize" => status.vm_size = number()?,
+            "VmHWM" => status.vm_hwm = number()?,
+            "VmRSS" => status.vm_rss = number()?,
+            "VmSwap" => status.vm_swap = number()?,
+            "voluntary_ctxt_switches" => status.voluntary_ctxt_switches = number()?,
+            "nonvoluntary_ctxt_switches" => status.nonvoluntary_ctxt_switches = number()?,
+            _ => { /* We don't care about other keys */ }
+        }
+    }
+
+    if !has_name || !has_state {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find Name and State in status".to_string(),
+        ));
+    }
+
+    Ok(status)
+}
+
+/// I/O accounting from `/proc/<pid>/io`. All values are in bytes except the
+/// syscall counts.
+///
+/// 来自 `/proc/<pid>/io` 的 I/O 统计。除系统调用次数外，其余单位均为字节。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ProcessIo {
+    /// Bytes passed to `read()`-like syscalls.
+    /// 传给 `read()` 类系统调用的字节数。
+    pub rchar: u64,
+    /// Bytes passed to `write()`-like syscalls.
+    /// 传给 `write()` 类系统调用的字节数。
+    pub wchar: u64,
+    /// Number of read syscalls. / 读系统调用次数。
+    pub syscr: u64,
+    /// Number of write syscalls. / 写系统调用次数。
+    pub syscw: u64,
+    /// Bytes actually fetched from storage. / 实际从存储读取的字节数。
+    pub read_bytes: u64,
+    /// Bytes sent to the storage layer. / 发送到存储层的字节数。
+    pub write_bytes: u64,
+    /// Written bytes that were later truncated away. / 写入后又被截断的字节数。
+    pub cancelled_write_bytes: u64,
+}
+
+impl Delta for ProcessIo {
+    fn delta(&self, earlier: &Self) -> Self {
+        ProcessIo {
+            rchar: self.rchar.saturating_sub(earlier.rchar),
+            wchar: self.wchar.saturating_sub(earlier.wchar),
+            syscr: self.syscr.saturating_sub(earlier.syscr),
+            syscw: self.syscw.saturating_sub(earlier.syscw),
+            read_bytes: self.read_bytes.saturating_sub(earlier.read_bytes),
+            write_bytes: self.write_bytes.saturating_sub(earlier.write_bytes),
+            cancelled_write_bytes: self
+                .cancelled_write_bytes
+                .saturating_sub(earlier.cancelled_write_bytes),
+        }
+    }
+}
+
+/// Parses the content of `/proc/<pid>/io`.
+///
+/// 解析 `/proc/<pid>/io` 的内容。
+fn parse_process_io_from_content(content: &str) -> Result<ProcessIo, PipaCollectorError> {
+    let mut stats = ProcessIo::default();
+    let mut found_count = 0;
+    const TOTAL_FIELDS: u8 = 7;
+
+    for line in content.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim().parse::<u64>()?;
+        match key {
+            "rchar" => stats.rchar = value,
+            "wchar" => stats.wchar = value,
+            "syscr" => stats.syscr = value,
+            "syscw" => stats.syscw = value,
+            "read_bytes" => stats.read_bytes = value,
+            "write_bytes" => stats.write_bytes = value,
+            "cancelled_write_bytes" => stats.cancelled_write_bytes = value,
+            _ => continue,
+        }
+        found_count += 1;
+    }
+
+    if found_count < TOTAL_FIELDS {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find all required fields in io".to_string(),
+        ));
+    }
+
+    Ok(stats)
+}
+
+/// Memory usage from `/proc/<pid>/statm`. All values are in pages.
+///
+/// 来自 `/proc/<pid>/statm` 的内存使用情况。所有值的单位均为页。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ProcessStatm {
+    /// Total program size. / 程序总大小。
+    pub size: u64,
+    /// Resident set size. / 常驻内存大小。
+    pub resident: u64,
+    /// Resident pages backed by a file. / 由文件支持的常驻页。
+    pub shared: u64,
+    /// Text (code) size. / 代码段大小。
+    pub text: u64,
+    /// Data plus stack size. / 数据段加栈的大小。
+    pub data: u64,
+}
+
+/// Parses the content of `/proc/<pid>/statm`.
+///
+/// 解析 `/proc/<pid>/statm` 的内容。
+fn parse_process_statm_from_content(content: &str) -> Result<ProcessStatm, PipaCollectorError> {
+    let mut values = content.split_whitespace();
+    let mut next = |name: &str| -> Result<u64, PipaCollectorError> {
+        Ok(values
+            .next()
+            .ok_or_else(|| {
+                PipaCollectorError::MissingData(format!("Missing value for {} in statm", name))
+            })?
+            .parse::<u64>()?)
+    };
+
+    let size = next("size")?;
+    let resident = next("resident")?;
+    let shared = next("shared")?;
+    let text = next("text")?;
+    let _lib = next("lib")?;
+    let data = next("data")?;
+
+    Ok(ProcessStatm { size, resident, shared, text, data })
+}
+
+/// A complete reading of one process or thread.
+///
+/// 对单个进程或线程的一次完整读数。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ProcessSnapshot {
+    /// Parsed `stat`. / 解析后的 `stat`。
+    pub stat: ProcessStat,
+    /// Parsed `status`. / 解析后的 `status`。
+    pub status: ProcessStatus,
+    /// Parsed `statm`. / 解析后的 `statm`。
+    pub statm: ProcessStatm,
+    /// Parsed `io`, or `None` if it is not readable (it requires ptrace
+    /// access to the target).
+    /// 解析后的 `io`；若不可读（需要对目标有 ptrace 权限）则为 `None`。
+    pub io: Option<ProcessIo>,
+}
+
+impl ProcessSnapshot {
+    /// Extracts the monotonically increasing counters of this snapshot.
+    /// 提取该快照中单调递增的计数器。
+    pub fn counters(&self) -> ProcessCounters {
+        ProcessCounters {
+            utime: self.stat.utime,
+            stime: self.stat.stime,
+            minflt: self.stat.minflt,
+            majflt: self.stat.majflt,
+            voluntary_ctxt_switches: self.status.voluntary_ctxt_switches,
+            nonvoluntary_ctxt_switches: self.status.nonvoluntary_ctxt_switches,
+            io: self.io.unwrap_or_default(),
+        }
+    }
+}
+
+/// The counter part of a [`ProcessSnapshot`], used to compute rates between
+/// two readings with [`Delta`].
+///
+/// [`ProcessSnapshot`] 中的计数器部分，
+/// 用于通过 [`Delta`] 计算两次读数之间的速率。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ProcessCounters {
+    /// User time in clock ticks. / 用户态时间（时钟滴答）。
+    pub utime: u64,
+    /// System time in clock ticks. / 内核态时间（时钟滴答）。
+    pub stime: u64,
+    /// Minor faults. / 次要缺页次数。
+    pub minflt: u64,
+    /// Major faults. / 主要缺页次数。
+    pub majflt: u64,
+    /// Voluntary context switches. / 自愿上下文切换次数。
+    pub voluntary_ctxt_switches: u64,
+    /// Involuntary context switches. / 非自愿上下文切换次数。
+    pub nonvoluntary_ctxt_switches: u64,
+    /// I/O accounting (zero if unavailable). / I/O 统计（不可用时为 0）。
+    pub io: ProcessIo,
+}
+
+impl Delta for ProcessCounters {
+    fn delta(&self, earlier: &Self) -> Self {
+        ProcessCounters {
+            utime: self.utime.saturating_sub(earlier.utime),
+            stime: self.stime.saturating_sub(earlier.stime),
+            minflt: self.minflt.saturating_sub(earlier.minflt),
+            majflt: self.majflt.saturating_sub(earlier.majflt),
+            voluntary_ctxt_switches: self
+                .voluntary_ctxt_switches
+                .saturating_sub(earlier.voluntary_ctxt_switches),
+            nonvoluntary_ctxt_switches: self
+                .nonvoluntary_ctxt_switches
+                .saturating_sub(earlier.nonvoluntary_ctxt_switches),
+            io: self.io.delta(&earlier.io),
+        }
+    }
+}
+
+/// Combines a process-level snapshot with the snapshots of its threads.
+///
+/// The kernel already folds all threads into the CPU times and fault counts of
+/// `/proc/<pid>/stat`, and `/proc/<pid>/io` covers the whole thread group,
+/// including threads that have exited. Only the `status` context switches of
+/// the leader describe the leader thread alone, so those are summed over
+/// `threads`; everything else is taken from `process` unchanged.
+///
+/// 将进程级快照与其所有线程的快照合并。
+///
+/// 内核已经把所有线程的 CPU 时间和缺页次数汇总进 `/proc/<pid>/stat`，
+/// `/proc/<pid>/io` 也覆盖整个线程组（包括已退出的线程）。只有主线程 `status`
+/// 中的上下文切换次数仅描述主线程本身，因此只对其按 `threads` 求和；
+/// 其余数据直接取自 `process`。
+pub fn aggregate_thread_snapshots(
+    process: &ProcessSnapshot,
+    threads: &[ProcessSnapshot],
+) -> ProcessSnapshot {
+    if threads.is_empty() {
+        return process.clone();
+    }
+
+    let mut result = process.clone();
+    result.status.voluntary_ctxt_switches =
+        threads.iter().map(|t| t.status.voluntary_ctxt_switches).sum();
+    result.status.nonvoluntary_ctxt_switches =
+        threads.iter().map(|t| t.status.nonvoluntary_ctxt_switches).sum();
+    result.stat.num_threads = threads.len() as u64;
+    result.status.threads = threads.len() as u64;
+    result
+}
+
+/// Reads a snapshot from a `/proc/<pid>`-like directory (or a
+/// `/proc/<pid>/task/<tid>` directory). A missing or unreadable `io` file is
+/// reported as `None` rather than an error.
+///
+/// 从类似 `/proc/<pid>`（或 `/proc/<pid>/task/<tid>`）的目录读取快照。
+/// `io` 文件缺失或不可读时记为 `None`，而不是返回错误。
+fn read_process_snapshot_from_dir<P: AsRef<Path>>(
+    dir: P,
+) -> Result<ProcessSnapshot, PipaCollectorError> {
+    let dir = dir.as_ref();
+    let stat = parse_file(dir.join("stat"), parse_process_stat_from_content)?;
+    let status = parse_file(dir.join("status"), parse_process_status_from_content)?;
+    let statm = parse_file(dir.join("statm"), parse_process_statm_from_content)?;
+    let io = match std::fs::read_to_string(dir.join("io")) {
+        Ok(content) => Some(parse_process_io_from_content(&content).at_path(dir.join("io"))?),
+        Err(e) if matches!(e.kind(), io::ErrorKind::PermissionDenied | io::ErrorKind::NotFound) => {
+            None
+        }
+        Err(e) => return Err(PipaCollectorError::from(e).at_path(dir.join("io"))),
+    };
+
+    Ok(ProcessSnapshot { stat, status, statm, io })
+}
+
+/// Whether reading a task failed because it exited: its files are gone
+/// (`ENOENT`) or the kernel no longer finds it (`ESRCH`).
+/// 读取任务失败是否因为它已退出：其文件已消失（`ENOENT`），或内核已找不到它（`ESRCH`）。
+fn task_exited(err: &PipaCollectorError) -> bool {
+    err.is_not_found()
+        || matches!(err.root_cause(), PipaCollectorError::Io(e) if e.raw_os_error() == Some(libc::ESRCH))
+}
+
+/// Reads the snapshots of every thread under `<dir>/task`. Threads that exit
+/// while being read are skipped.
+///
+/// 读取 `<dir>/task` 下每个线程的快照。读取过程中退出的线程会被跳过。
+fn read_thread_snapshots_from_dir<P: AsRef<Path>>(
+    dir: P,
+) -> Result<Vec<ProcessSnapshot>, PipaCollectorError> {
+    let mut threads = Vec::new();
+    for entry in std::fs::read_dir(dir.as_ref().join("task"))? {
+        let path: PathBuf = entry?.path();
+        match read_process_snapshot_from_dir(&path) {
+            Ok(snapshot) => threads.push(snapshot),
+            Err(e) if task_exited(&e) => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    threads.sort_by_key(|t| t.stat.pid);
+    Ok(threads)
+}
+
+/// Reads a process snapshot from `/proc/<pid>`.
+/// 从 `/proc/<pid>` 读取进程快照。
+pub fn read_process_snapshot(
+    root: &SysRoot,
+    pid: u32,
+) -> Result<ProcessSnapshot, PipaCollectorError> {
+    read_process_snapshot_from_dir(root.proc(pid.to_string()))
+}
+
+/// Reads the snapshot of each thread of `pid` from `/proc/<pid>/task`.
+/// 从 `/proc/<pid>/task` 读取 `pid` 每个线程的快照。
+pub fn read_thread_snapshots(
+    root: &SysRoot,
+    pid: u32,
+) -> Result<Vec<ProcessSnapshot>, PipaCollectorError> {
+    read_thread_snapshots_from_dir(root.proc(pid.to_string()))
+}
+
+/// Reads a process snapshot whose counters are aggregated over all threads.
+/// 读取一个进程快照，其计数器已在所有线程上聚合。
+pub fn read_aggregated_process_snapshot(
+    root: &SysRoot,
+    pid: u32,
+) -> Result<ProcessSnapshot, PipaCollectorError> {
+    let dir = root.proc(pid.to_string());
+    let process = read_process_snapshot_from_dir(&dir)?;
+    let threads = read_thread_snapshots_from_dir(&dir)?;
+    Ok(aggregate_thread_snapshots(&process, &threads))
+}
+
+/// Lists the numeric entries of a `/proc`-like directory, i.e. the PIDs of
+/// all processes. Thread ids are not listed by procfs at the top level.
+///
+/// 列出类似 `/proc` 目录中的数字条目，即所有进程的 PID。
+/// procfs 顶层不会列出线程 ID。
+fn list_pids_from_dir<P: AsRef<Path>>(dir: P) -> Result<Vec<u32>, PipaCollectorError> {
+    let mut pids: Vec<u32> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .collect();
+    pids.sort_unstable();
+    Ok(pids)
+}
+
+/// Lists the PIDs of all processes in `/proc`.
+/// 列出 `/proc` 中所有进程的 PID。
+pub fn list_pids(root: &SysRoot) -> Result<Vec<u32>, PipaCollectorError> {
+    list_pids_from_dir(root.proc_dir())
+}
+
+/// Returns the number of clock ticks per second (`USER_HZ`), the unit of the
+/// CPU times in [`ProcessStat`].
+///
+/// 返回每秒的时钟滴答数（`USER_HZ`），即 [`ProcessStat`] 中 CPU 时间的单位。
+pub fn clock_ticks_per_second() -> u64 {
+    // SAFETY: sysconf has no memory-safety preconditions.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 { ticks as u64 } else { 100 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STAT_LINE: &str = "6080 (cat) R 6066 6080 6066 0 -1 4194304 82 0 3 0 15 7 0 0 20 0 1 0 \
+                             131252 2703360 327 18446744073709551615 94109579378688 \
+                             94109579398569 140723799891200 0 0 0 0 0 0 0 0 0 17 2 0 0 0 0 0";
+
+    const STATUS: &str = "Name:\tcat\n\
+                          Umask:\t0022\n\
+                          State:\tR (running)\n\
+                          Tgid:\t6076\n\
+                          Pid:\t6076\n\
+                          VmPeak:\t    2640 kB\n\
+                          VmSize:\t    2640 kB\n\
+                          VmHWM:\t    1304 kB\n\
+                          VmRSS:\t    1304 kB\n\
+                          VmSwap:\t      16 kB\n\
+                          Threads:\t1\n\
+                          voluntary_ctxt_switches:\t10\n\
+                          nonvoluntary_ctxt_switches:\t2\n";
+
+    const IO: &str = "rchar: 3980\n\
+                      wchar: 100\n\
+                      syscr: 9\n\
+                      syscw: 1\n\
+                      read_bytes: 4096\n\
+                      write_bytes: 8192\n\
+                      cancelled_write_bytes: 0\n";
+
+    /// Test sections for /proc/<pid>/stat
+    #[test]
+    fn test_parse_process_stat_happy_path() {
+        let stat = parse_process_stat_from_content(STAT_LINE).unwrap();
+        assert_eq!(stat.pid, 6080);
+        assert_eq!(stat.comm, "cat");
+        assert_eq!(stat.state, 'R');
+        assert_eq!(stat.ppid, 6066);
+        assert_eq!(stat.minflt, 82);
+        assert_eq!(stat.majflt, 3);
+        assert_eq!(stat.utime, 15);
+        assert_eq!(stat.stime, 7);
+        assert_eq!(stat.priority, 20);
+        assert_eq!(stat.nice, 0);
+        assert_eq!(stat.num_threads, 1);
+        assert_eq!(stat.starttime, 131252);
+        assert_eq!(stat.vsize, 2703360);
+        assert_eq!(stat.rss, 327);
+        assert_eq!(stat.processor, 2);
+    }
+
+    #[test]
+    fn test_parse_process_stat_comm_with_spaces_and_parens() {
+        let line = STAT_LINE.replace("(cat)", "(my (x) proc)");
+        let stat = parse_process_stat_from_content(&line).unwrap();
+        assert_eq!(stat.comm, "my (x) proc");
+        assert_eq!(stat.state, 'R');
+
+        // A name crafted to look like the end of the comm field.
+        let line = STAT_LINE.replace("(cat)", "(x) R (y)");
+        let stat = parse_process_stat_from_content(&line).unwrap();
+        assert_eq!(stat.comm, "x) R (y");
+        assert_eq!(stat.ppid, 6066);
+    }
+
+    #[test]
+    fn test_parse_process_stat_errors() {
+        assert!(matches!(
+            parse_process_stat_from_content("6080 cat R 1").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_process_stat_from_content("6080 (cat) R 1 2 3").unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+        assert!(matches!(
+            parse_process_stat_from_content(&STAT_LINE.replace("6066 6080", "x 6080"))
+                .unwrap_err()
+                .root_cause(),
+            PipaCollectorError::Parse(_)
+        ));
+    }
+
+    /// Test sections for /proc/<pid>/status
+    #[test]
+    fn test_parse_process_status_happy_path() {
+        let status = parse_process_status_from_content(STATUS).unwrap();
+        assert_eq!(status.name, "cat");
+        assert_eq!(status.state, 'R');
+        assert_eq!(status.tgid, 6076);
+        assert_eq!(status.threads, 1);
+        assert_eq!(status.vm_peak, 2640);
+        assert_eq!(status.vm_rss, 1304);
+        assert_eq!(status.vm_swap, 16);
+        assert_eq!(status.voluntary_ctxt_switches, 10);
+        assert_eq!(status.nonvoluntary_ctxt_switches, 2);
+    }
+
+    #[test]
+    fn test_parse_process_status_kernel_thread() {
+        // Kernel threads have no Vm* lines.
+        let content = "Name:\tkworker/0:1\nState:\tI (idle)\nTgid:\t12\nThreads:\t1\n";
+        let status = parse_process_status_from_content(content).unwrap();
+        assert_eq!(status.name, "kworker/0:1");
+        assert_eq!(status.state, 'I');
+        assert_eq!(status.vm_rss, 0);
+    }
+
+    #[test]
+    fn test_parse_process_status_missing_name() {
+        let result = parse_process_status_from_content("Threads:\t1\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    /// Test sections for /proc/<pid>/io and statm
+    #[test]
+    fn test_parse_process_io() {
+        let io = parse_process_io_from_content(IO).unwrap();
+        assert_eq!(io.rchar, 3980);
+        assert_eq!(io.read_bytes, 4096);
+        assert_eq!(io.write_bytes, 8192);
+
+        let result = parse_process_io_from_content("rchar: 1\nwchar: 2\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_parse_process_statm() {
+        let statm = parse_process_statm_from_content("660 325 300 5 0 123 0\n").unwrap();
+        assert_eq!(
+            statm,
+            ProcessStatm { size: 660, resident: 325, shared: 300, text: 5, data: 123 }
+        );
+
+        let result = parse_process_statm_from_content("660 325");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_process_counters_delta() {
+        let earlier = ProcessCounters {
+            utime: 10,
+            io: ProcessIo { read_bytes: 100, ..Default::default() },
+            ..Default::default()
+        };
+        let later = ProcessCounters {
+            utime: 25,
+            io: ProcessIo { read_bytes: 400, ..Default::default() },
+            ..Default::default()
+        };
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.utime, 15);
+        assert_eq!(delta.io.read_bytes, 300);
+    }
+
+    /// Test sections for thread aggregation
+    fn thread(tid: u32, vol: u64, nonvol: u64, read_bytes: Option<u64>) -> ProcessSnapshot {
+        ProcessSnapshot {
+            stat: ProcessStat { pid: tid, ..Default::default() },
+            status: ProcessStatus {
+                voluntary_ctxt_switches: vol,
+                nonvoluntary_ctxt_switches: nonvol,
+                ..Default::default()
+            },
+            statm: ProcessStatm::default(),
+            io: read_bytes.map(|b| ProcessIo { read_bytes: b, ..Default::default() }),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_thread_snapshots() {
+        let mut process = thread(100, 1, 1, Some(10));
+        process.status.vm_rss = 5000;
+        let threads = vec![thread(100, 1, 1, Some(10)), thread(101, 4, 2, Some(30))];
+
+        let agg = aggregate_thread_snapshots(&process, &threads);
+        assert_eq!(agg.status.voluntary_ctxt_switches, 5);
+        assert_eq!(agg.status.nonvoluntary_ctxt_switches, 3);
+        // `io` already covers the thread group. / `io` 已覆盖整个线程组。
+        assert_eq!(agg.io.unwrap().read_bytes, 10);
+        assert_eq!(agg.stat.num_threads, 2);
+        // Memory is shared by the thread group and is not summed.
+        assert_eq!(agg.status.vm_rss, 5000);
+    }
+
+    #[test]
+    fn test_aggregate_thread_snapshots_partial_io() {
+        let process = thread(100, 1, 1, Some(10));
+        let threads = vec![thread(100, 1, 1, Some(10)), thread(101, 1, 1, None)];
+        let agg = aggregate_thread_snapshots(&process, &threads);
+        // Unreadable thread `io` files do not matter. / 线程的 `io` 文件不可读不影响结果。
+        assert_eq!(agg.io.unwrap().read_bytes, 10);
+
+        let agg = aggregate_thread_snapshots(&process, &[]);
+        assert_eq!(agg, process);
+    }
+
+    /// Test sections for I/O functions, using a fixture `/proc/<pid>` tree.
+    fn write_fixture(dir: &Path, pid: u32, with_io: bool) {
+        std::fs::create_dir_all(dir).unwrap();
+        let stat = STAT_LINE.replacen("6080", &pid.to_string(), 1);
+        std::fs::write(dir.join("stat"), stat).unwrap();
+        std::fs::write(dir.join("status"), STATUS).unwrap();
+        std::fs::write(dir.join("statm"), "660 325 300 5 0 123 0\n").unwrap();
+        if with_io {
+            std::fs::write(dir.join("io"), IO).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_process_snapshot_from_dir() {
+        let root = tempfile::tempdir().unwrap();
+        let proc_dir = root.path().join("6080");
+        write_fixture(&proc_dir, 6080, true);
+        write_fixture(&proc_dir.join("task/6080"), 6080, true);
+        write_fixture(&proc_dir.join("task/6081"), 6081, false);
+        // A thread that exited after the directory was listed.
+        // 列出目录后已退出的线程。
+        std::fs::create_dir(proc_dir.join("task/6082")).unwrap();
+
+        let snapshot = read_process_snapshot_from_dir(&proc_dir).unwrap();
+        assert_eq!(snapshot.stat.comm, "cat");
+        assert_eq!(snapshot.statm.resident, 325);
+        assert_eq!(snapshot.io.unwrap().rchar, 3980);
+
+        let threads = read_thread_snapshots_from_dir(&proc_dir).unwrap();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].stat.pid, 6080);
+        assert_eq!(threads[1].stat.pid, 6081);
+        assert!(threads[1].io.is_none());
+    }
+
+    #[test]
+    fn test_read_process_snapshot_io_error() {
+        let result = read_process_snapshot_from_dir("/a/non/existent/proc/1");
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::Io(_)));
+    }
+
+    #[test]
+    fn test_list_pids_from_dir() {
+        let root = tempfile::tempdir().unwrap();
+        for name in ["1", "42", "7", "self", "sys"] {
+            std::fs::create_dir(root.path().join(name)).unwrap();
+        }
+        assert_eq!(list_pids_from_dir(root.path()).unwrap(), vec![1, 7, 42]);
+    }
+
+    #[test]
+    fn test_clock_ticks_per_second() {
+        assert!(clock_ticks_per_second() > 0);
+    }
+}
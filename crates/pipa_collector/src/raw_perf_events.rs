@@ -15,21 +15,35 @@
 //! This module provides a low-level interface to the `perf_event_open` syscall,
 //! precisely mimicking the behavior of the `perf stat` command.
 #![cfg(not(tarpaulin_include))]
+use crate::probe::{ProbeEvent, ProbeSpec};
+use crate::sysroot::SysRoot;
 use crate::system_stats::PipaCollectorError;
+use crate::tracepoint::{TraceFs, TracepointName};
 use perf_event_open_sys as sys;
 use std::io;
 use std::os::unix::io::RawFd;
 
 /// Represents a specific hardware performance event that can be monitored.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PerfEvent {
     Cycles,
     Instructions,
+    /// The software CPU clock, available even without a hardware PMU.
+    /// 软件 CPU 时钟，即使没有硬件 PMU 也可用。
+    CpuClock,
+    /// A kernel tracepoint, by its tracefs id. / 内核跟踪点，以其 tracefs id 表示。
+    Tracepoint(u64),
+    /// A dynamic kprobe or uprobe. / 动态 kprobe 或 uprobe。
+    Probe(ProbeEvent),
 }
 
 impl PerfEvent {
-    fn to_config(self) -> (u32, u64) {
-        match self {
+    /// Sets the type and config fields of `attrs`. For probes, `config1`
+    /// points into `self`, which must outlive the `perf_event_open` call.
+    /// 设置 `attrs` 的 type 与 config 字段。对于探针，`config1` 指向 `self`，
+    /// 因此 `self` 的生命周期必须长于 `perf_event_open` 调用。
+    pub(crate) fn configure(&self, attrs: &mut sys::bindings::perf_event_attr) {
+        let (type_, config) = match self {
             Self::Cycles => {
                 (sys::bindings::PERF_TYPE_HARDWARE, sys::bindings::PERF_COUNT_HW_CPU_CYCLES as u64)
             }
@@ -37,10 +51,55 @@ impl PerfEvent {
                 sys::bindings::PERF_TYPE_HARDWARE,
                 sys::bindings::PERF_COUNT_HW_INSTRUCTIONS as u64,
             ),
+            Self::CpuClock => {
+                (sys::bindings::PERF_TYPE_SOFTWARE, sys::bindings::PERF_COUNT_SW_CPU_CLOCK as u64)
+            }
+            Self::Tracepoint(id) => (sys::bindings::PERF_TYPE_TRACEPOINT, *id),
+            Self::Probe(probe) => {
+                attrs.__bindgen_anon_3.config1 =
+                    probe.target.as_ref().map_or(0, |target| target.as_ptr() as u64);
+                attrs.__bindgen_anon_4.config2 = probe.offset;
+                (probe.pmu_type, probe.config)
+            }
+        };
+        attrs.type_ = type_;
+        attrs.config = config;
+    }
+}
+
+/// Resolves an event name as accepted by `-e`: `cycles`, `instructions`,
+/// `cpu-clock`, a probe such as `uprobe:/usr/bin/app:func` (see
+/// [`ProbeSpec`]), or a tracepoint such as `sched:sched_switch`.
+///
+/// 解析 `-e` 接受的事件名称：`cycles`、`instructions`、`cpu-clock`，
+/// `uprobe:/usr/bin/app:func` 这样的探针（参见 [`ProbeSpec`]），
+/// 或 `sched:sched_switch` 这样的跟踪点。
+pub fn resolve_event(root: &SysRoot, name: &str) -> Result<PerfEvent, PipaCollectorError> {
+    match name {
+        "cycles" | "cpu-cycles" => Ok(PerfEvent::Cycles),
+        "instructions" => Ok(PerfEvent::Instructions),
+        "cpu-clock" => Ok(PerfEvent::CpuClock),
+        _ if ProbeSpec::is_probe(name) => {
+            Ok(PerfEvent::Probe(name.parse::<ProbeSpec>()?.resolve(root)?))
         }
+        _ if name.contains(':') => {
+            let tracepoint: TracepointName = name.parse()?;
+            Ok(PerfEvent::Tracepoint(TraceFs::locate(root)?.event_id(&tracepoint)?))
+        }
+        _ => Err(PipaCollectorError::EventNotSupported(format!(
+            "unknown event `{}` (expected cycles, instructions, cpu-clock or subsystem:event)",
+            name
+        ))),
     }
 }
 
+/// Reads `sys/kernel/perf_event_paranoid` under `root`'s procfs, which
+/// decides what an unprivileged user may count.
+/// 读取 `root` 下 procfs 中的 `sys/kernel/perf_event_paranoid`，该设置决定非特权用户可以统计哪些事件。
+pub fn perf_event_paranoid(root: &SysRoot) -> Option<i32> {
+    std::fs::read_to_string(root.proc("sys/kernel/perf_event_paranoid")).ok()?.trim().parse().ok()
+}
+
 /// A handle to a single performance counter, ensuring it is closed on drop.
 #[derive(Debug)]
 pub struct Counter {
@@ -48,6 +107,11 @@ pub struct Counter {
 }
 
 impl Counter {
+    /// Takes ownership of an open perf event fd. / 接管一个已打开的 perf 事件 fd。
+    pub(crate) fn from_fd(fd: RawFd) -> Self {
+        Counter { fd }
+    }
+
     pub fn fd(&self) -> RawFd {
         self.fd
     }
@@ -61,36 +125,277 @@ impl Drop for Counter {
     }
 }
 
+/// A forked command held before `execve`, so counters can be opened on its
+/// pid first, as `perf stat` does with its workload. Counters opened on the
+/// profiler itself and inherited by the child miss uprobes, which filter on
+/// the task the event was opened for.
+///
+/// 在 `execve` 之前挂起的已 fork 命令，以便像 `perf stat` 处理其工作负载那样，
+/// 先在其 pid 上打开计数器。在分析器自身上打开并由子进程继承的计数器会漏掉
+/// uprobe，因为 uprobe 按事件打开时所针对的任务进行过滤。
+#[derive(Debug)]
+pub struct PendingCommand {
+    pid: libc::pid_t,
+    /// Write end of the pipe the child waits on. / 子进程等待的管道写端。
+    go: RawFd,
+    /// Read end of the pipe the child reports an `execve` error on.
+    /// 子进程报告 `execve` 错误的管道读端。
+    exec_error: RawFd,
+    program: String,
+}
+
+/// Creates a pipe, optionally close-on-exec. / 创建管道，可选择设置 close-on-exec。
+fn pipe(flags: libc::c_int) -> io::Result<[RawFd; 2]> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), flags) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fds)
+}
+
+impl PendingCommand {
+    /// Forks a child that runs `command` once [`PendingCommand::start`] is
+    /// called. / fork 一个子进程，在调用 [`PendingCommand::start`] 后运行 `command`。
+    pub fn spawn(command: &[String]) -> Result<Self, PipaCollectorError> {
+        let program = command
+            .first()
+            .ok_or_else(|| PipaCollectorError::MissingData("No command provided".into()))?;
+        let args = command
+            .iter()
+            .map(|arg| {
+                std::ffi::CString::new(arg.as_str()).map_err(|_| {
+                    PipaCollectorError::InvalidFormat(format!("argument `{}` contains NUL", arg))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut argv: Vec<*const libc::c_char> = args.iter().map(|a| a.as_ptr()).collect();
+        argv.push(std::ptr::null());
+
+        let [go_read, go_write] = pipe(0)?;
+        let [error_read, error_write] = pipe(libc::O_CLOEXEC)?;
+        // SAFETY: the child only calls async-signal-safe functions before
+        // `execvp` or `_exit`. / 子进程在 `execvp` 或 `_exit` 之前只调用异步信号安全函数。
+        let pid = unsafe { libc::fork() };
+        if pid < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        if pid == 0 {
+            unsafe {
+                libc::close(go_write);
+                libc::close(error_read);
+                let mut byte = 0u8;
+                // EOF means the profiler gave up before starting the command.
+                if libc::read(go_read, &mut byte as *mut u8 as *mut libc::c_void, 1) != 1 {
+                    libc::_exit(127);
+                }
+                libc::close(go_read);
+                libc::execvp(argv[0], argv.as_ptr());
+                let errno = *libc::__errno_location();
+                libc::write(error_write, &errno as *const i32 as *const libc::c_void, 4);
+                libc::_exit(127);
+            }
+        }
+        unsafe {
+            libc::close(go_read);
+            libc::close(error_write);
+        }
+        Ok(PendingCommand { pid, go: go_write, exec_error: error_read, program: program.clone() })
+    }
+
+    /// The pid of the waiting child. / 等待中的子进程的 pid。
+    pub fn pid(&self) -> u32 {
+        self.pid as u32
+    }
+
+    /// Lets the child call `execve`, failing if it could not.
+    /// 允许子进程调用 `execve`，若执行失败则返回错误。
+    pub fn start(mut self) -> Result<RunningCommand, PipaCollectorError> {
+        let mut errno = [0u8; 4];
+        let read = unsafe {
+            libc::write(self.go, [1u8].as_ptr() as *const libc::c_void, 1);
+            libc::close(self.go);
+            self.go = -1;
+            libc::read(self.exec_error, errno.as_mut_ptr() as *mut libc::c_void, 4)
+        };
+        let running = RunningCommand { pid: self.pid };
+        if read == 4 {
+            running.wait()?;
+            let err = io::Error::from_raw_os_error(i32::from_ne_bytes(errno));
+            let message = format!("Failed to execute command `{}`: {}", self.program, err);
+            return Err(io::Error::new(err.kind(), message).into());
+        }
+        Ok(running)
+    }
+}
+
+impl Drop for PendingCommand {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.exec_error);
+            if self.go >= 0 {
+                // The child sees EOF and exits; reap it.
+                libc::close(self.go);
+                libc::waitpid(self.pid, std::ptr::null_mut(), 0);
+            }
+        }
+    }
+}
+
+/// A command started by [`PendingCommand::start`]. / 由 [`PendingCommand::start`] 启动的命令。
+#[derive(Debug)]
+pub struct RunningCommand {
+    pid: libc::pid_t,
+}
+
+impl RunningCommand {
+    /// The exit status, if the command has exited. / 命令已退出时返回其退出状态。
+    pub fn try_wait(&self) -> Result<Option<std::process::ExitStatus>, PipaCollectorError> {
+        self.waitpid(libc::WNOHANG)
+    }
+
+    /// Waits for the command to exit. / 等待命令退出。
+    pub fn wait(self) -> Result<std::process::ExitStatus, PipaCollectorError> {
+        Ok(self.waitpid(0)?.expect("waitpid without WNOHANG returns a status"))
+    }
+
+    /// Asks the command to exit with `SIGTERM` and waits for it, as perf
+    /// does with a workload that outlives the recording.
+    /// 以 `SIGTERM` 请求命令退出并等待其结束，与 perf 对待比记录活得更久的负载一样。
+    pub fn terminate(self) -> Result<std::process::ExitStatus, PipaCollectorError> {
+        // SAFETY: `kill` has no memory-safety preconditions; the child has
+        // not been reaped, so the pid is still ours.
+        unsafe {
+            libc::kill(self.pid, libc::SIGTERM);
+        }
+        self.wait()
+    }
+
+    fn waitpid(
+        &self,
+        options: libc::c_int,
+    ) -> Result<Option<std::process::ExitStatus>, PipaCollectorError> {
+        use std::os::unix::process::ExitStatusExt;
+        let mut status = 0;
+        loop {
+            match unsafe { libc::waitpid(self.pid, &mut status, options) } {
+                0 => return Ok(None),
+                pid if pid > 0 => return Ok(Some(std::process::ExitStatus::from_raw(status))),
+                _ => {
+                    let err = io::Error::last_os_error();
+                    if err.kind() != io::ErrorKind::Interrupted {
+                        return Err(err.into());
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// Creates a single, inheritable performance counter for a command to be
 /// executed. This function precisely replicates the parameters used by `perf
 /// stat`.
-pub fn create_counter_for_command(event: PerfEvent) -> Result<Counter, PipaCollectorError> {
+pub fn create_counter_for_command(
+    root: &SysRoot,
+    event: &PerfEvent,
+    command: &PendingCommand,
+) -> Result<Counter, PipaCollectorError> {
     let mut attrs = sys::bindings::perf_event_attr {
         size: std::mem::size_of::<sys::bindings::perf_event_attr>() as u32,
         ..Default::default()
     };
-    let (type_, config) = event.to_config();
-    attrs.type_ = type_;
-    attrs.config = config;
+    event.configure(&mut attrs);
 
     // --- Settings copied exactly from `perf stat` strace ---
     attrs.set_disabled(1); // Start disabled.
     attrs.set_inherit(1); // Inherit to child processes.
     attrs.set_enable_on_exec(1); // Kernel will auto-enable on `execve`.
 
-    // pid = 0: Monitor the current process. With inherit=1, this targets children.
+    // pid: The waiting child. With inherit=1, this also covers its children.
     // cpu = -1: Monitor on any CPU the process runs on.
     // group_fd = -1: This is a standalone counter, not part of a group.
     // flags = 0: No special flags needed for this basic case.
-    let fd = unsafe { sys::perf_event_open(&mut attrs, 0, -1, -1, 0) };
+    let fd = unsafe { sys::perf_event_open(&mut attrs, command.pid, -1, -1, 0) };
 
     if fd < 0 {
-        let last_error = io::Error::last_os_error();
-        return Err(PipaCollectorError::Io(io::Error::new(
-            last_error.kind(),
-            format!("perf_event_open failed for event {:?}: {}", event, last_error),
-        )));
+        return Err(PipaCollectorError::from_perf_open(
+            io::Error::last_os_error(),
+            &format!("event {:?}", event),
+            perf_event_paranoid(root),
+        ));
     }
 
     Ok(Counter { fd })
 }
+
+/// Checks whether `event` can be counted by opening it on the calling thread
+/// and closing it again. Only user space is counted, so the check passes at
+/// `perf_event_paranoid` 2 as `perf stat` on one's own process would.
+///
+/// 通过在调用线程上打开并立即关闭 `event` 来检查该事件能否计数。
+/// 仅统计用户态，因此与对自身进程执行 `perf stat` 一样，
+/// 在 `perf_event_paranoid` 为 2 时也能通过检查。
+pub fn probe_event(root: &SysRoot, event: &PerfEvent) -> Result<(), PipaCollectorError> {
+    let mut attrs = sys::bindings::perf_event_attr {
+        size: std::mem::size_of::<sys::bindings::perf_event_attr>() as u32,
+        ..Default::default()
+    };
+    event.configure(&mut attrs);
+    attrs.set_disabled(1);
+    attrs.set_exclude_kernel(1);
+    attrs.set_exclude_hv(1);
+
+    let fd = unsafe { sys::perf_event_open(&mut attrs, 0, -1, -1, 0) };
+    if fd < 0 {
+        return Err(PipaCollectorError::from_perf_open(
+            io::Error::last_os_error(),
+            &format!("event {:?}", event),
+            perf_event_paranoid(root),
+        ));
+    }
+    drop(Counter { fd });
+    Ok(())
+}
+
+/// Creates counters that attach to an already running process, one per
+/// thread found in `<procfs>/<pid>/task`, like `perf stat -p`. Threads created
+/// later by the counted threads are covered through `inherit`.
+pub fn create_counters_for_pid(
+    root: &SysRoot,
+    event: &PerfEvent,
+    pid: u32,
+) -> Result<Vec<Counter>, PipaCollectorError> {
+    let mut counters = Vec::new();
+    for entry in std::fs::read_dir(root.proc(format!("{}/task", pid)))? {
+        let Some(tid) = entry?.file_name().to_str().and_then(|s| s.parse::<i32>().ok()) else {
+            continue;
+        };
+
+        let mut attrs = sys::bindings::perf_event_attr {
+            size: std::mem::size_of::<sys::bindings::perf_event_attr>() as u32,
+            ..Default::default()
+        };
+        event.configure(&mut attrs);
+        // The target is already running, so the counter starts enabled.
+        attrs.set_inherit(1);
+
+        let fd = unsafe { sys::perf_event_open(&mut attrs, tid, -1, -1, 0) };
+        if fd < 0 {
+            let last_error = io::Error::last_os_error();
+            // The thread exited between listing and opening it.
+            if last_error.raw_os_error() == Some(libc::ESRCH) {
+                continue;
+            }
+            return Err(PipaCollectorError::from_perf_open(
+                last_error,
+                &format!("event {:?} on tid {}", event, tid),
+                perf_event_paranoid(root),
+            ));
+        }
+        counters.push(Counter { fd });
+    }
+
+    if counters.is_empty() {
+        return Err(PipaCollectorError::MissingData(format!("No threads found for pid {}", pid)));
+    }
+    Ok(counters)
+}
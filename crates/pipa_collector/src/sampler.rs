@@ -0,0 +1,737 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A background sampler that runs a configurable set of system collectors at
+//! a fixed interval, like `sar`. Snapshots are timestamped with
+//! `CLOCK_MONOTONIC`, kept in a bounded ring buffer and optionally appended
+//! to a capture file (JSON Lines).
+//!
+//! 一个类似 `sar` 的后台采样器，以固定间隔运行一组可配置的系统采集器。
+//! 快照使用 `CLOCK_MONOTONIC` 打时间戳，保存在有界环形缓冲区中，
+//! 并可选择以 JSON Lines 格式追加写入捕获文件。
+
+use crate::collector::{CollectorRegistry, Metric, Sample};
+use crate::error::ResultExt;
+use crate::sysroot::SysRoot;
+use crate::system_stats::{self, PipaCollectorError, Snapshot, monotonic_now};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Version of the capture file format written by [`SystemSampler`].
+/// [`SystemSampler`] 写入的捕获文件格式版本。
+pub const CAPTURE_FORMAT_VERSION: u32 = 2;
+
+/// One sampling round: the sample of every configured collector, by name. A
+/// collector that failed to read in this round is absent.
+///
+/// 一轮采样的结果：每个已配置采集器的读数，按名称索引。本轮读取失败的采集器不会出现。
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct SystemSnapshot {
+    /// Samples by collector name. / 按采集器名称索引的读数。
+    pub samples: BTreeMap<String, Sample>,
+}
+
+impl SystemSnapshot {
+    /// Returns the sample of the collector called `name`.
+    /// 返回名为 `name` 的采集器的读数。
+    pub fn get(&self, name: &str) -> Option<&Sample> {
+        self.samples.get(name)
+    }
+}
+
+/// Configuration of a [`SystemSampler`].
+///
+/// [`SystemSampler`] 的配置。
+#[derive(Debug, PartialEq, Clone)]
+pub struct SamplerConfig {
+    /// Time between sampling rounds. / 两轮采样之间的时间间隔。
+    pub interval: Duration,
+    /// Names of the [`CollectorRegistry`] collectors to run each round.
+    /// 每轮运行的 [`CollectorRegistry`] 采集器名称。
+    pub collectors: Vec<String>,
+    /// Maximum snapshots kept in memory; the oldest are dropped first.
+    /// 内存中最多保留的快照数，超出时先丢弃最旧的快照。
+    pub capacity: usize,
+    /// Optional capture file every snapshot is appended to.
+    /// 可选的捕获文件，每个快照都会追加写入其中。
+    pub capture: Option<PathBuf>,
+    /// Where procfs and sysfs are read from. / 读取 procfs 和 sysfs 的位置。
+    pub root: SysRoot,
+}
+
+impl Default for SamplerConfig {
+    /// One-second sampling of every built-in collector, keeping the last hour.
+    /// 每秒采样所有内置采集器，保留最近一小时的数据。
+    fn default() -> Self {
+        let root = SysRoot::default();
+        SamplerConfig {
+            interval: Duration::from_secs(1),
+            collectors: CollectorRegistry::builtin(&root)
+                .names()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            capacity: 3600,
+            capture: None,
+            root,
+        }
+    }
+}
+
+/// The line that starts each recording in a capture file. It records the
+/// wall-clock time that corresponds to a monotonic timestamp so that readers
+/// can map snapshot times back to time of day.
+///
+/// 捕获文件中每次记录开头的一行。它记录了某个单调时间戳对应的挂钟时间，
+/// 以便读取方将快照时间换算回一天中的时刻。
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CaptureHeader {
+    /// Format version, see [`CAPTURE_FORMAT_VERSION`]. / 格式版本。
+    pub version: u32,
+    /// Host name of the recording machine. / 记录机器的主机名。
+    pub hostname: String,
+    /// Kernel release, e.g. `6.8.0-45-generic`. / 内核版本，例如 `6.8.0-45-generic`。
+    #[serde(default)]
+    pub kernel_release: String,
+    /// Machine architecture, e.g. `x86_64`. / 机器架构，例如 `x86_64`。
+    #[serde(default)]
+    pub arch: String,
+    /// Online CPUs when recording started. / 开始记录时的在线 CPU 数。
+    #[serde(default)]
+    pub cpus: usize,
+    /// Sampling interval. / 采样间隔。
+    pub interval: Duration,
+    /// Schemas of the configured collectors, by name.
+    /// 已配置采集器的模式，按名称索引。
+    pub schemas: BTreeMap<String, Vec<Metric>>,
+    /// `CLOCK_MONOTONIC` time at which `realtime` was taken.
+    /// 读取 `realtime` 时的 `CLOCK_MONOTONIC` 时间。
+    pub monotonic: Duration,
+    /// Wall-clock time since the Unix epoch. / 自 Unix 纪元以来的挂钟时间。
+    pub realtime: Duration,
+}
+
+impl CaptureHeader {
+    /// Converts a snapshot's monotonic timestamp to wall-clock time since the
+    /// Unix epoch.
+    /// 将快照的单调时间戳转换为自 Unix 纪元以来的挂钟时间。
+    pub fn to_realtime(&self, monotonic: Duration) -> Duration {
+        if monotonic >= self.monotonic {
+            self.realtime + (monotonic - self.monotonic)
+        } else {
+            self.realtime.saturating_sub(self.monotonic - monotonic)
+        }
+    }
+
+    /// Converts wall-clock time since the Unix epoch to this header's
+    /// monotonic clock; the inverse of [`CaptureHeader::to_realtime`].
+    /// 将自 Unix 纪元以来的挂钟时间转换为该文件头的单调时钟时间，
+    /// 即 [`CaptureHeader::to_realtime`] 的逆运算。
+    pub fn to_monotonic(&self, realtime: Duration) -> Duration {
+        if realtime >= self.realtime {
+            self.monotonic + (realtime - self.realtime)
+        } else {
+            self.monotonic.saturating_sub(self.realtime - realtime)
+        }
+    }
+}
+
+/// A capture file read back into memory. A file that several recordings
+/// were appended to is read as one: later snapshots are moved onto the first
+/// header's clock through wall-clock time, and their schemas are merged in.
+///
+/// 读回内存的捕获文件。追加了多次记录的文件会作为一个整体读取：
+/// 之后记录的快照通过挂钟时间换算到第一个文件头的时钟上，其模式也会合并进来。
+#[derive(Debug, PartialEq, Clone)]
+pub struct Capture {
+    /// The header of the first recording. / 第一次记录的文件头。
+    pub header: CaptureHeader,
+    /// All snapshots in recording order. / 按记录顺序排列的所有快照。
+    pub snapshots: Vec<Snapshot<SystemSnapshot>>,
+}
+
+/// Reads a single-line kernel setting such as `/proc/sys/kernel/hostname`.
+/// 读取单行内核设置，例如 `/proc/sys/kernel/hostname`。
+pub(crate) fn read_kernel_setting(path: &Path) -> String {
+    std::fs::read_to_string(path).map(|h| h.trim().to_string()).unwrap_or_default()
+}
+
+/// Writes one JSON line. / 写入一行 JSON。
+pub(crate) fn write_json_line<W: Write, T: Serialize>(
+    out: &mut W,
+    value: &T,
+) -> Result<(), PipaCollectorError> {
+    serde_json::to_writer(&mut *out, value).map_err(|e| {
+        PipaCollectorError::InvalidFormat(format!("Failed to encode capture: {}", e))
+    })?;
+    out.write_all(b"\n")?;
+    // Flush every record so an interrupted recording remains readable.
+    out.flush()?;
+    Ok(())
+}
+
+/// Parses a header line, rejecting format versions this build cannot read.
+/// 解析文件头行，拒绝当前版本无法读取的格式版本。
+fn parse_header(line: &str) -> Result<CaptureHeader, PipaCollectorError> {
+    let header: CaptureHeader = serde_json::from_str(line).map_err(|e| {
+        PipaCollectorError::InvalidFormat(format!("not a pipa capture file: {}", e))
+    })?;
+    if header.version != CAPTURE_FORMAT_VERSION {
+        return Err(PipaCollectorError::Unsupported(format!(
+            "capture format version {} (expected {})",
+            header.version, CAPTURE_FORMAT_VERSION
+        )));
+    }
+    Ok(header)
+}
+
+/// Opens `path` for appending, like sysstat's daily files. An existing file
+/// must be a capture of this format version; if a recorder was killed
+/// mid-line, the line is terminated so the next header starts on its own.
+///
+/// 以追加方式打开 `path`，与 sysstat 的每日文件一样。已有文件必须是当前格式版本的捕获文件；
+/// 若之前的记录进程在写入一行时被终止，会先补上换行，使下一个文件头独占一行。
+fn open_capture(path: &Path) -> Result<File, PipaCollectorError> {
+    let mut file = OpenOptions::new().read(true).create(true).append(true).open(path)?;
+    let len = file.metadata()?.len();
+    if len > 0 {
+        let mut first = String::new();
+        BufReader::new(&file).read_line(&mut first)?;
+        parse_header(&first).at_line(1).at_path(path)?;
+
+        let mut last = [0u8];
+        file.seek(SeekFrom::Start(len - 1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+        }
+    }
+    Ok(file)
+}
+
+/// Reads a capture file. A truncated line at the end of a recording, as left
+/// by a recorder that was killed mid-write, is ignored.
+///
+/// 读取捕获文件。若某次记录末尾的一行不完整（记录进程在写入过程中被终止），则忽略该行。
+pub fn read_capture<P: AsRef<Path>>(path: P) -> Result<Capture, PipaCollectorError> {
+    let path = path.as_ref();
+    read_capture_lines(File::open(path).map(BufReader::new).at_path(path)?).at_path(path)
+}
+
+/// Parses the lines of a capture file. / 解析捕获文件的各行。
+fn read_capture_lines<R: BufRead>(reader: R) -> Result<Capture, PipaCollectorError> {
+    let mut lines = reader.lines();
+    let header_line = lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| PipaCollectorError::MissingData("Empty capture file".to_string()))?;
+    let header = parse_header(&header_line).at_line(1)?;
+
+    let lines: Vec<String> = lines.collect::<Result<_, _>>()?;
+    let mut capture = Capture { header, snapshots: Vec::with_capacity(lines.len()) };
+    // The header of the recording being read, once past the first one.
+    let mut appended: Option<CaptureHeader> = None;
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str::<Snapshot<SystemSnapshot>>(line) {
+            Ok(mut snapshot) => {
+                if let Some(header) = &appended {
+                    snapshot.timestamp =
+                        capture.header.to_monotonic(header.to_realtime(snapshot.timestamp));
+                }
+                capture.snapshots.push(snapshot);
+            }
+            Err(e)
+                if e.is_eof()
+                    && lines
+                        .get(i + 1)
+                        .is_none_or(|next| serde_json::from_str::<CaptureHeader>(next).is_ok()) =>
+            {
+                continue;
+            }
+            Err(e) => {
+                // Not a snapshot, so it must be the header of an appended
+                // recording; otherwise report why the snapshot did not parse.
+                let header = parse_header(line)
+                    .map_err(|header_error| match header_error {
+                        PipaCollectorError::InvalidFormat(_) => {
+                            PipaCollectorError::InvalidFormat(e.to_string())
+                        }
+                        other => other,
+                    })
+                    .at_line(i + 2)?;
+                for (name, schema) in &header.schemas {
+                    capture.header.schemas.entry(name.clone()).or_insert_with(|| schema.clone());
+                }
+                appended = Some(header);
+            }
+        }
+    }
+    Ok(capture)
+}
+
+/// Runs collectors on a background thread at a fixed rate. Rounds are
+/// scheduled against absolute deadlines, so the interval does not drift with
+/// the time spent reading.
+///
+/// 在后台线程中以固定频率运行采集器。每轮采样按绝对截止时间调度，
+/// 因此采样间隔不会因读取耗时而漂移。
+pub struct SystemSampler {
+    stop: Arc<AtomicBool>,
+    ring: Arc<Mutex<VecDeque<Snapshot<SystemSnapshot>>>>,
+    handle: JoinHandle<Result<(), PipaCollectorError>>,
+}
+
+impl SystemSampler {
+    /// Starts sampling `config.root` with the built-in collectors.
+    /// 使用内置采集器开始对 `config.root` 进行采样。
+    #[cfg(not(tarpaulin_include))]
+    pub fn start(config: SamplerConfig) -> Result<Self, PipaCollectorError> {
+        let registry = CollectorRegistry::builtin(&config.root);
+        Self::start_with_registry(config, &registry)
+    }
+
+    /// Starts sampling, resolving `config.collectors` in `registry`. Fails if
+    /// a name is not registered.
+    /// 开始采样，并在 `registry` 中解析 `config.collectors`。若某个名称未注册则返回错误。
+    pub fn start_with_registry(
+        config: SamplerConfig,
+        registry: &CollectorRegistry,
+    ) -> Result<Self, PipaCollectorError> {
+        Self::start_observed(config, registry, |_| {})
+    }
+
+    /// Like [`SystemSampler::start_with_registry`], but also hands every
+    /// snapshot to `observe` on the sampling thread as it is taken. This lets
+    /// callers fold a long run into a summary without keeping the snapshots.
+    ///
+    /// 与 [`SystemSampler::start_with_registry`] 相同，但还会在采样线程上把每个
+    /// 刚采集的快照交给 `observe`。调用方因此可以把长时间运行的数据汇总起来，
+    /// 而无需保留所有快照。
+    pub fn start_observed<O>(
+        config: SamplerConfig,
+        registry: &CollectorRegistry,
+        observe: O,
+    ) -> Result<Self, PipaCollectorError>
+    where
+        O: FnMut(&Snapshot<SystemSnapshot>) + Send + 'static,
+    {
+        let collectors = registry.select(&config.collectors)?;
+        let schemas =
+            collectors.iter().map(|c| (c.name().to_string(), c.schema().to_vec())).collect();
+        let read = move || {
+            // A failed collector is left out of the round so that one
+            // unavailable interface does not stop the others.
+            let samples = collectors
+                .iter()
+                .filter_map(|c| c.sample().ok().map(|sample| (c.name().to_string(), sample)))
+                .collect();
+            SystemSnapshot { samples }
+        };
+        Self::spawn(config, schemas, read, observe)
+    }
+
+    /// Starts sampling with a custom reader, e.g. one backed by fixtures. The
+    /// capture file, if any, is opened for appending before this returns so
+    /// that path errors are reported to the caller.
+    ///
+    /// 使用自定义读取函数开始采样，例如基于夹具的读取函数。
+    /// 若配置了捕获文件，会在返回前以追加方式打开该文件，以便将路径错误报告给调用方。
+    pub fn start_with<F>(config: SamplerConfig, read: F) -> Result<Self, PipaCollectorError>
+    where
+        F: Fn() -> SystemSnapshot + Send + 'static,
+    {
+        Self::spawn(config, BTreeMap::new(), read, |_| {})
+    }
+
+    fn spawn<F, O>(
+        config: SamplerConfig,
+        schemas: BTreeMap<String, Vec<Metric>>,
+        read: F,
+        mut observe: O,
+    ) -> Result<Self, PipaCollectorError>
+    where
+        F: Fn() -> SystemSnapshot + Send + 'static,
+        O: FnMut(&Snapshot<SystemSnapshot>) + Send + 'static,
+    {
+        if config.interval.is_zero() || config.capacity == 0 {
+            return Err(PipaCollectorError::InvalidFormat(
+                "Sampler interval and capacity must be non-zero".to_string(),
+            ));
+        }
+
+        let mut capture = match &config.capture {
+            Some(path) => {
+                let mut out = BufWriter::new(open_capture(path)?);
+                let header = CaptureHeader {
+                    version: CAPTURE_FORMAT_VERSION,
+                    hostname: read_kernel_setting(&config.root.proc("sys/kernel/hostname")),
+                    kernel_release: read_kernel_setting(&config.root.proc("sys/kernel/osrelease")),
+                    arch: std::env::consts::ARCH.to_string(),
+                    cpus: system_stats::read_online_cpu_count(&config.root).unwrap_or(0),
+                    interval: config.interval,
+                    schemas,
+                    monotonic: monotonic_now(),
+                    realtime: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+                };
+                write_json_line(&mut out, &header)?;
+                Some(out)
+            }
+            None => None,
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let ring = Arc::new(Mutex::new(VecDeque::with_capacity(config.capacity.min(1024))));
+        let (thread_stop, thread_ring) = (Arc::clone(&stop), Arc::clone(&ring));
+        let handle = std::thread::spawn(move || {
+            let mut deadline = monotonic_now();
+            loop {
+                let snapshot = Snapshot::now(read());
+                if let Some(out) = capture.as_mut() {
+                    write_json_line(out, &snapshot)?;
+                }
+                observe(&snapshot);
+                {
+                    let mut ring = thread_ring.lock().unwrap_or_else(|e| e.into_inner());
+                    if ring.len() == config.capacity {
+                        ring.pop_front();
+                    }
+                    ring.push_back(snapshot);
+                }
+
+                if thread_stop.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+
+                // Wake up for the next round, or early for a final one on stop.
+                deadline += config.interval;
+                loop {
+                    let now = monotonic_now();
+                    if thread_stop.load(Ordering::SeqCst) || now >= deadline {
+                        break;
+                    }
+                    std::thread::park_timeout(deadline - now);
+                }
+                // Skip rounds that were missed entirely, e.g. after a suspend.
+                let now = monotonic_now();
+                while deadline + config.interval <= now {
+                    deadline += config.interval;
+                }
+            }
+        });
+        Ok(SystemSampler { stop, ring, handle })
+    }
+
+    /// Returns the most recent snapshot. / 返回最近一次的快照。
+    pub fn latest(&self) -> Option<Snapshot<SystemSnapshot>> {
+        self.ring.lock().unwrap_or_else(|e| e.into_inner()).back().cloned()
+    }
+
+    /// Returns a copy of the snapshots currently held in the ring buffer.
+    /// 返回当前环形缓冲区中快照的副本。
+    pub fn snapshots(&self) -> Vec<Snapshot<SystemSnapshot>> {
+        self.ring.lock().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect()
+    }
+
+    /// Takes a final sample, stops the thread and returns the buffered
+    /// snapshots, oldest first. Fails if writing the capture file failed.
+    ///
+    /// 采集最后一个样本，停止线程并返回缓冲区中的快照（从旧到新）。
+    /// 若写入捕获文件失败则返回错误。
+    pub fn stop(self) -> Result<Vec<Snapshot<SystemSnapshot>>, PipaCollectorError> {
+        self.stop.store(true, Ordering::SeqCst);
+        self.handle.thread().unpark();
+        self.handle.join().map_err(|_| {
+            PipaCollectorError::MissingData("Sampler thread panicked".to_string())
+        })??;
+        let mut ring = self.ring.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(ring.drain(..).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    /// A reader whose `calls` collector counts the rounds.
+    fn counting_reader() -> impl Fn() -> SystemSnapshot + Send + 'static {
+        let calls = AtomicU64::new(0);
+        move || {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            let samples = BTreeMap::from([("calls".to_string(), Sample::single(vec![n as f64]))]);
+            SystemSnapshot { samples }
+        }
+    }
+
+    /// The round number recorded by [`counting_reader`].
+    fn round(snapshot: &Snapshot<SystemSnapshot>) -> f64 {
+        snapshot.stats.get("calls").unwrap().records[0].values[0]
+    }
+
+    fn config(capacity: usize, capture: Option<PathBuf>) -> SamplerConfig {
+        SamplerConfig {
+            interval: Duration::from_millis(2),
+            collectors: vec!["calls".to_string()],
+            capacity,
+            capture,
+            root: SysRoot::default(),
+        }
+    }
+
+    #[test]
+    fn test_sampler_ring_is_bounded() {
+        let sampler = SystemSampler::start_with(config(4, None), counting_reader()).unwrap();
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(sampler.latest().is_some());
+        let snapshots = sampler.stop().unwrap();
+
+        assert_eq!(snapshots.len(), 4);
+        // The oldest rounds were dropped and the rest are in order.
+        assert!(round(&snapshots[0]) > 0.0);
+        assert!(
+            snapshots.windows(2).all(|w| {
+                w[0].timestamp <= w[1].timestamp && round(&w[0]) + 1.0 == round(&w[1])
+            })
+        );
+    }
+
+    #[test]
+    fn test_sampler_stops_promptly() {
+        let mut config = config(8, None);
+        config.interval = Duration::from_secs(3600);
+        let sampler = SystemSampler::start_with(config, counting_reader()).unwrap();
+        let started = std::time::Instant::now();
+        std::thread::sleep(Duration::from_millis(5));
+        // The first round and the final one taken on stop.
+        let snapshots = sampler.stop().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(round(&snapshots[1]), 1.0);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sampler_rejects_bad_config() {
+        let result = SystemSampler::start_with(config(0, None), counting_reader());
+        assert!(matches!(result.err().unwrap(), PipaCollectorError::InvalidFormat(_)));
+
+        let missing = PathBuf::from("/nonexistent/dir/capture.pipa");
+        let result = SystemSampler::start_with(config(4, Some(missing)), counting_reader());
+        assert!(matches!(result.err().unwrap(), PipaCollectorError::Io(_)));
+    }
+
+    #[test]
+    fn test_capture_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("day.pipa");
+        let sampler =
+            SystemSampler::start_with(config(2, Some(path.clone())), counting_reader()).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        let buffered = sampler.stop().unwrap();
+
+        let capture = read_capture(&path).unwrap();
+        assert_eq!(capture.header.version, CAPTURE_FORMAT_VERSION);
+        // The file keeps every round even though the ring only kept two.
+        assert!(capture.snapshots.len() > buffered.len());
+        assert_eq!(capture.snapshots.last(), buffered.last());
+        assert_eq!(round(&capture.snapshots[0]), 0.0);
+        assert!(capture.snapshots[0].timestamp >= capture.header.monotonic);
+    }
+
+    #[test]
+    fn test_capture_file_appends_recordings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("day.pipa");
+        let record = || {
+            let sampler =
+                SystemSampler::start_with(config(2, Some(path.clone())), counting_reader())
+                    .unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+            sampler.stop().unwrap();
+        };
+        record();
+        let first = read_capture(&path).unwrap();
+
+        // A recorder killed mid-line leaves a partial snapshot behind.
+        let mut content = std::fs::read_to_string(&path).unwrap();
+        content.push_str("{\"timestamp\":{\"secs\":1");
+        std::fs::write(&path, &content).unwrap();
+        record();
+
+        let both = read_capture(&path).unwrap();
+        assert_eq!(both.header, first.header);
+        assert_eq!(&both.snapshots[..first.snapshots.len()], &first.snapshots[..]);
+        assert!(both.snapshots.len() > first.snapshots.len());
+        // The second recording restarts the rounds and stays in time order.
+        assert_eq!(round(&both.snapshots[first.snapshots.len()]), 0.0);
+        assert!(both.snapshots.windows(2).all(|w| w[0].timestamp <= w[1].timestamp));
+
+        // Anything but a capture file is left alone.
+        let other = dir.path().join("notes.txt");
+        std::fs::write(&other, "hello\n").unwrap();
+        let result = SystemSampler::start_with(config(2, Some(other.clone())), counting_reader());
+        assert!(matches!(result.err().unwrap().root_cause(), PipaCollectorError::InvalidFormat(_)));
+        assert_eq!(std::fs::read_to_string(&other).unwrap(), "hello\n");
+    }
+
+    #[test]
+    fn test_sampler_runs_registered_collectors() {
+        use crate::collector::FnCollector;
+
+        let mut registry = CollectorRegistry::new();
+        let schema = vec![Metric::counter("events", "events")];
+        registry
+            .register(FnCollector::new("fixture", schema.clone(), || Ok(Sample::single(vec![1.0]))))
+            .unwrap();
+        registry
+            .register(FnCollector::new("broken", schema.clone(), || {
+                Err(PipaCollectorError::MissingData("gone".to_string()))
+            }))
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plugins.pipa");
+        let mut config = config(2, Some(path.clone()));
+        config.collectors = vec!["fixture".to_string(), "broken".to_string()];
+        let sampler = SystemSampler::start_with_registry(config.clone(), &registry).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        sampler.stop().unwrap();
+
+        let capture = read_capture(&path).unwrap();
+        assert_eq!(capture.header.schemas["fixture"], schema);
+        let first = &capture.snapshots[0].stats;
+        assert_eq!(first.get("fixture"), Some(&Sample::single(vec![1.0])));
+        assert!(first.get("broken").is_none());
+
+        config.collectors.push("gpu".to_string());
+        assert!(SystemSampler::start_with_registry(config, &registry).is_err());
+    }
+
+    #[test]
+    fn test_sampler_observes_every_snapshot() {
+        use crate::collector::FnCollector;
+
+        let mut registry = CollectorRegistry::new();
+        let schema = vec![Metric::gauge("value", "")];
+        registry
+            .register(FnCollector::new("fixture", schema, || Ok(Sample::single(vec![1.0]))))
+            .unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let mut config = config(1, None);
+        config.collectors = vec!["fixture".to_string()];
+        let sampler = SystemSampler::start_observed(config, &registry, move |snapshot| {
+            sink.lock().unwrap().push(snapshot.clone());
+        })
+        .unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        let ring = sampler.stop().unwrap();
+
+        let seen = seen.lock().unwrap();
+        // The ring holds only the newest snapshot, but every one was observed.
+        assert!(seen.len() >= 2, "{}", seen.len());
+        assert_eq!(seen.last(), ring.last());
+        assert!(seen.iter().all(|s| s.stats.get("fixture").is_some()));
+    }
+
+    #[test]
+    fn test_read_capture_tolerates_truncated_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cut.pipa");
+        let sampler =
+            SystemSampler::start_with(config(2, Some(path.clone())), counting_reader()).unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        sampler.stop().unwrap();
+
+        let full = read_capture(&path).unwrap();
+        let mut content = std::fs::read_to_string(&path).unwrap();
+        content.push_str("{\"timestamp\":{\"secs\":1");
+        std::fs::write(&path, &content).unwrap();
+        assert_eq!(read_capture(&path).unwrap(), full);
+
+        // Corruption anywhere else is an error that names the line.
+        content.insert_str(content.find('\n').unwrap() + 1, "garbage\n");
+        std::fs::write(&path, &content).unwrap();
+        let err = read_capture(&path).unwrap_err();
+        assert!(err.to_string().starts_with(&format!("{}:2: ", path.display())), "{}", err);
+        assert!(matches!(err.root_cause(), PipaCollectorError::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn test_read_capture_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("other");
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(
+            read_capture(&path).unwrap_err().root_cause(),
+            PipaCollectorError::MissingData(_)
+        ));
+        std::fs::write(&path, "hello\n").unwrap();
+        assert!(matches!(
+            read_capture(&path).unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+
+        let header = CaptureHeader {
+            version: 99,
+            hostname: String::new(),
+            kernel_release: String::new(),
+            arch: String::new(),
+            cpus: 0,
+            interval: Duration::from_secs(1),
+            schemas: BTreeMap::new(),
+            monotonic: Duration::ZERO,
+            realtime: Duration::ZERO,
+        };
+        std::fs::write(&path, serde_json::to_string(&header).unwrap()).unwrap();
+        assert!(matches!(
+            read_capture(&path).unwrap_err().root_cause(),
+            PipaCollectorError::Unsupported(_)
+        ));
+    }
+
+    #[test]
+    fn test_capture_header_to_realtime() {
+        let header = CaptureHeader {
+            version: CAPTURE_FORMAT_VERSION,
+            hostname: "host".to_string(),
+            kernel_release: "6.8.0".to_string(),
+            arch: "x86_64".to_string(),
+            cpus: 8,
+            interval: Duration::from_secs(1),
+            schemas: BTreeMap::new(),
+            monotonic: Duration::from_secs(100),
+            realtime: Duration::from_secs(1_700_000_000),
+        };
+        assert_eq!(
+            header.to_realtime(Duration::from_secs(160)),
+            Duration::from_secs(1_700_000_060)
+        );
+        assert_eq!(header.to_realtime(Duration::from_secs(90)), Duration::from_secs(1_699_999_990));
+        assert_eq!(
+            header.to_monotonic(Duration::from_secs(1_700_000_060)),
+            Duration::from_secs(160)
+        );
+        assert_eq!(
+            header.to_monotonic(Duration::from_secs(1_699_999_990)),
+            Duration::from_secs(90)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The location of procfs and sysfs. Every reader in this crate takes a
+//! [`SysRoot`], so the same code can read the host's `/proc` from a sidecar
+//! container (`/host/proc`) or a fixture tree in tests.
+//!
+//! procfs 和 sysfs 的位置。本 crate 的所有读取函数都接受一个 [`SysRoot`]，
+//! 因此同一份代码既可以在 sidecar 容器中读取主机的 `/proc`（`/host/proc`），
+//! 也可以在测试中读取夹具目录树。
+
+use std::path::{Path, PathBuf};
+
+/// Where procfs and sysfs are mounted. [`SysRoot::default`] is the live
+/// system (`/proc` and `/sys`).
+///
+/// procfs 和 sysfs 的挂载位置。[`SysRoot::default`] 表示当前系统（`/proc` 和 `/sys`）。
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SysRoot {
+    proc: PathBuf,
+    sys: PathBuf,
+}
+
+impl Default for SysRoot {
+    fn default() -> Self {
+        SysRoot { proc: PathBuf::from("/proc"), sys: PathBuf::from("/sys") }
+    }
+}
+
+impl SysRoot {
+    /// Uses `<root>/proc` and `<root>/sys`, e.g. `/host` in a container that
+    /// bind-mounts the host filesystems, or a fixture directory.
+    ///
+    /// 使用 `<root>/proc` 和 `<root>/sys`，例如在绑定挂载了主机文件系统的容器中
+    /// 使用 `/host`，或使用夹具目录。
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        let root = root.as_ref();
+        SysRoot { proc: root.join("proc"), sys: root.join("sys") }
+    }
+
+    /// Replaces the procfs mount point. / 替换 procfs 的挂载点。
+    pub fn with_proc<P: Into<PathBuf>>(mut self, proc: P) -> Self {
+        self.proc = proc.into();
+        self
+    }
+
+    /// Replaces the sysfs mount point. / 替换 sysfs 的挂载点。
+    pub fn with_sys<P: Into<PathBuf>>(mut self, sys: P) -> Self {
+        self.sys = sys.into();
+        self
+    }
+
+    /// The procfs mount point. / procfs 的挂载点。
+    pub fn proc_dir(&self) -> &Path {
+        &self.proc
+    }
+
+    /// The sysfs mount point. / sysfs 的挂载点。
+    pub fn sys_dir(&self) -> &Path {
+        &self.sys
+    }
+
+    /// Resolves a path relative to procfs, e.g. `proc("stat")`.
+    /// 解析相对于 procfs 的路径，例如 `proc("stat")`。
+    pub fn proc<P: AsRef<Path>>(&self, relative: P) -> PathBuf {
+        self.proc.join(relative)
+    }
+
+    /// Resolves a path relative to sysfs, e.g. `sys("class/net")`.
+    /// 解析相对于 sysfs 的路径，例如 `sys("class/net")`。
+    pub fn sys<P: AsRef<Path>>(&self, relative: P) -> PathBuf {
+        self.sys.join(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sysroot_paths() {
+        let live = SysRoot::default();
+        assert_eq!(live.proc("stat"), Path::new("/proc/stat"));
+        assert_eq!(live.sys("class/net"), Path::new("/sys/class/net"));
+
+        let host = SysRoot::new("/host");
+        assert_eq!(host.proc("1/cgroup"), Path::new("/host/proc/1/cgroup"));
+        assert_eq!(host.sys_dir(), Path::new("/host/sys"));
+
+        let split = SysRoot::default().with_proc("/host/proc");
+        assert_eq!(split.proc_dir(), Path::new("/host/proc"));
+        assert_eq!(split.sys_dir(), Path::new("/sys"));
+    }
+}
@@ -18,70 +18,93 @@
 //! 本模块负责通过解析 `/proc` 文件系统来收集系统级统计信息。
 //! 它的功能是替代像 `sar` 这样的工具。
 
-use std::fmt;
+pub use crate::error::PipaCollectorError;
+use crate::error::{ResultExt, parse_file};
+use crate::sysroot::SysRoot;
+use serde::{Deserialize, Serialize};
 use std::io;
-use std::num::ParseIntError;
+use std::ops::AddAssign;
 
-/// A unified error type for all potential failures within the `pipa_collector`.
-/// This enum allows for precise error handling.
+/// Implemented by statistics made of monotonically increasing kernel
+/// counters. `delta` returns the counter increase between an `earlier`
+/// reading and `self`; counters that went backwards (e.g. after a wrap or a
+/// CPU hot-unplug) saturate to zero instead of underflowing.
 ///
-/// `pipa_collector` 中所有潜在失败的统一错误类型。
-/// 这个枚举允许我们进行精确的错误处理。
-#[derive(Debug)]
-pub enum PipaCollectorError {
-    /// Represents an I/O error that occurred while reading a file (e.g., from
-    /// `/proc`). 代表在读取文件时发生的 I/O 错误（例如，从 `/proc`
-    /// 读取时）。
-    Io(io::Error),
-    /// Represents an error that occurred while parsing a string into a number.
-    /// 代表在将字符串解析为数字时发生的错误。
-    Parse(ParseIntError),
-    /// Represents a format mismatch in the parsed file content.
-    /// 代表在解析的文件内容中出现格式不匹配。
-    InvalidFormat(String),
-    /// Represents missing data where it was expected.
-    /// 代表在预期位置缺少数据。
-    MissingData(String),
-}
-
-/// Custom implementation to provide human-readable error messages.
-/// 为了提供人类可读的错误信息，我们实现了自定义的 Display。
-impl fmt::Display for PipaCollectorError {
-    #[cfg(not(tarpaulin_include))]
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            PipaCollectorError::Io(e) => write!(f, "I/O error: {}", e),
-            PipaCollectorError::Parse(e) => write!(f, "Parse error: {}", e),
-            PipaCollectorError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
-            PipaCollectorError::MissingData(msg) => write!(f, "Missing data: {}", msg),
-        }
+/// 由单调递增的内核计数器组成的统计数据实现此 trait。
+/// `delta` 返回从 `earlier` 到 `self` 之间计数器的增量；
+/// 若计数器出现回退（例如回绕或 CPU 热拔出），结果饱和为 0 而不会下溢。
+pub trait Delta {
+    /// Returns the per-field increase from `earlier` to `self`.
+    /// 返回从 `earlier` 到 `self` 每个字段的增量。
+    fn delta(&self, earlier: &Self) -> Self;
+}
+
+/// Reads the `CLOCK_MONOTONIC` clock, the same clock used by perf sample
+/// timestamps when `use_clockid` is set.
+///
+/// 读取 `CLOCK_MONOTONIC` 时钟。
+/// 当设置 `use_clockid` 时，perf 采样时间戳使用的也是该时钟。
+pub fn monotonic_now() -> std::time::Duration {
+    let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `ts` is a valid, writable timespec and CLOCK_MONOTONIC is always
+    // available on Linux.
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
     }
+    std::time::Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
 }
 
-/// Implementing the standard Error trait for interoperability with other error
-/// types. 为了与其他错误类型互操作，我们实现了标准的 Error trait。
-impl std::error::Error for PipaCollectorError {
-    #[cfg(not(tarpaulin_include))]
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            PipaCollectorError::Io(e) => Some(e),
-            PipaCollectorError::Parse(e) => Some(e),
-            _ => None,
-        }
+/// A reading of some statistics together with the monotonic time at which it
+/// was taken.
+///
+/// 一次统计数据读数及其采集时的单调时间戳。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Snapshot<T> {
+    /// `CLOCK_MONOTONIC` time of the reading.
+    /// 读数时的 `CLOCK_MONOTONIC` 时间。
+    pub timestamp: std::time::Duration,
+    /// The statistics themselves. / 统计数据本身。
+    pub stats: T,
+}
+
+impl<T> Snapshot<T> {
+    /// Wraps `stats` with the current monotonic time.
+    /// 用当前的单调时间包装 `stats`。
+    pub fn now(stats: T) -> Self {
+        Snapshot { timestamp: monotonic_now(), stats }
     }
 }
 
-// Boilerplate to allow easy conversion from standard errors using the `?`
-// operator. 使用 `?` 操作符简化从标准错误类型到自定义错误类型的转换的模板代码。
-impl From<io::Error> for PipaCollectorError {
-    fn from(err: io::Error) -> Self {
-        PipaCollectorError::Io(err)
+impl<T: Delta> Snapshot<T> {
+    /// Computes the counter increase and elapsed time since `earlier`.
+    /// 计算自 `earlier` 以来的计数器增量和经过的时间。
+    pub fn delta_since(&self, earlier: &Snapshot<T>) -> SnapshotDelta<T> {
+        SnapshotDelta {
+            elapsed: self.timestamp.saturating_sub(earlier.timestamp),
+            delta: self.stats.delta(&earlier.stats),
+        }
     }
 }
 
-impl From<ParseIntError> for PipaCollectorError {
-    fn from(err: ParseIntError) -> Self {
-        PipaCollectorError::Parse(err)
+/// The difference between two [`Snapshot`]s.
+///
+/// 两个 [`Snapshot`] 之间的差值。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct SnapshotDelta<T> {
+    /// Time between the two snapshots. / 两次快照之间的时间间隔。
+    pub elapsed: std::time::Duration,
+    /// Counter increase between the two snapshots. / 两次快照之间的计数器增量。
+    pub delta: T,
+}
+
+impl<T> SnapshotDelta<T> {
+    /// Converts one counter increase of this delta into a per-second rate.
+    /// Returns 0.0 when no time has elapsed.
+    ///
+    /// 将该增量中的某个计数器增量换算为每秒速率。若经过时间为 0，则返回 0.0。
+    pub fn per_second(&self, value: u64) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 { value as f64 / secs } else { 0.0 }
     }
 }
 
@@ -90,7 +113,7 @@ impl From<ParseIntError> for PipaCollectorError {
 ///
 /// 存储从 `/proc/stat` 中聚合的 CPU 时间统计信息。
 /// 所有值的单位都是 `jiffies`（通常是 1/100 秒）。
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct CpuStats {
     /// Time spent in user mode. / 在用户模式下花费的时间。
     pub user: u64,
@@ -119,6 +142,61 @@ pub struct CpuStats {
     pub guest_nice: u64,
 }
 
+impl CpuStats {
+    /// Idle time, including time spent waiting for I/O.
+    /// 空闲时间，包含等待 I/O 的时间。
+    pub fn idle_time(&self) -> u64 {
+        self.idle + self.iowait
+    }
+
+    /// Total accounted time. `guest` and `guest_nice` are excluded because the
+    /// kernel already includes them in `user` and `nice`.
+    ///
+    /// 统计到的总时间。不包含 `guest` 和 `guest_nice`，因为内核已将它们计入
+    /// `user` 和 `nice`。
+    pub fn total_time(&self) -> u64 {
+        self.idle_time()
+            + self.user
+            + self.nice
+            + self.system
+            + self.irq
+            + self.softirq
+            + self.steal
+    }
+}
+
+impl Delta for CpuStats {
+    fn delta(&self, earlier: &Self) -> Self {
+        CpuStats {
+            user: self.user.saturating_sub(earlier.user),
+            nice: self.nice.saturating_sub(earlier.nice),
+            system: self.system.saturating_sub(earlier.system),
+            idle: self.idle.saturating_sub(earlier.idle),
+            iowait: self.iowait.saturating_sub(earlier.iowait),
+            irq: self.irq.saturating_sub(earlier.irq),
+            softirq: self.softirq.saturating_sub(earlier.softirq),
+            steal: self.steal.saturating_sub(earlier.steal),
+            guest: self.guest.saturating_sub(earlier.guest),
+            guest_nice: self.guest_nice.saturating_sub(earlier.guest_nice),
+        }
+    }
+}
+
+impl AddAssign for CpuStats {
+    fn add_assign(&mut self, other: Self) {
+        self.user += other.user;
+        self.nice += other.nice;
+        self.system += other.system;
+        self.idle += other.idle;
+        self.iowait += other.iowait;
+        self.irq += other.irq;
+        self.softirq += other.softirq;
+        self.steal += other.steal;
+        self.guest += other.guest;
+        self.guest_nice += other.guest_nice;
+    }
+}
+
 /// Parses a single line from `/proc/stat` (the aggregated "cpu" line) into a
 /// `CpuStats` struct. This function is kept private and pure (no I/O) to make
 /// it easily testable.
@@ -126,6 +204,20 @@ pub struct CpuStats {
 /// 将 `/proc/stat` 的单行（聚合的 "cpu" 行）解析为 `CpuStats` 结构体。
 /// 这个函数保持私有和纯粹（无 I/O），以便于测试。
 fn parse_cpu_stats_from_line(line: &str) -> Result<CpuStats, PipaCollectorError> {
+    // `/proc/stat` might have one or two spaces after "cpu". `strip_prefix` handles
+    // one case, and `or_else` provides a fallback to try the other.
+    let trimmed =
+        line.strip_prefix("cpu  ").or_else(|| line.strip_prefix("cpu ")).ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(
+                "Line does not start with 'cpu ' or 'cpu  '".to_string(),
+            )
+        })?;
+    parse_cpu_values(trimmed)
+}
+
+/// Parses the time columns that follow the label of a `/proc/stat` CPU line.
+/// 解析 `/proc/stat` 中 CPU 行标签之后的各时间列。
+fn parse_cpu_values(values: &str) -> Result<CpuStats, PipaCollectorError> {
     // A small helper macro to reduce boilerplate when parsing iterator values.
     // 一个小的辅助宏，用于减少解析迭代器值时的模板代码。
     macro_rules! parse_next {
@@ -139,16 +231,7 @@ fn parse_cpu_stats_from_line(line: &str) -> Result<CpuStats, PipaCollectorError>
         };
     }
 
-    // `/proc/stat` might have one or two spaces after "cpu". `strip_prefix` handles
-    // one case, and `or_else` provides a fallback to try the other.
-    let trimmed =
-        line.strip_prefix("cpu  ").or_else(|| line.strip_prefix("cpu ")).ok_or_else(|| {
-            PipaCollectorError::InvalidFormat(
-                "Line does not start with 'cpu ' or 'cpu  '".to_string(),
-            )
-        })?;
-
-    let mut values = trimmed.split_whitespace();
+    let mut values = values.split_whitespace();
 
     let stats = CpuStats {
         user: parse_next!(values, "user"),
@@ -174,19 +257,42 @@ fn parse_cpu_stats_from_line(line: &str) -> Result<CpuStats, PipaCollectorError>
 fn read_cpu_stats_from_path<P: AsRef<std::path::Path>>(
     path: P,
 ) -> Result<CpuStats, PipaCollectorError> {
-    let content = std::fs::read_to_string(path)?;
-    #[cfg(not(tarpaulin_include))]
-    let first_line = content.lines().next().ok_or_else(|| {
-        PipaCollectorError::InvalidFormat("Cannot read first line from file".to_string())
-    })?;
-    parse_cpu_stats_from_line(first_line)
+    parse_file(path, |content| {
+        let first_line = content.lines().next().ok_or_else(|| {
+            PipaCollectorError::InvalidFormat("Cannot read first line from file".to_string())
+        })?;
+        parse_cpu_stats_from_line(first_line).at_line(1)
+    })
 }
 
 /// Reads and parses aggregated CPU statistics from the `/proc/stat` file.
 /// 现在这个函数只是一个简单的包装器。
-#[cfg(not(tarpaulin_include))]
-pub fn read_cpu_stats() -> Result<CpuStats, PipaCollectorError> {
-    read_cpu_stats_from_path("/proc/stat")
+pub fn read_cpu_stats(root: &SysRoot) -> Result<CpuStats, PipaCollectorError> {
+    read_cpu_stats_from_path(root.proc("stat"))
+}
+
+/// Parses the per-CPU `cpuN` lines of `/proc/stat`, in file order.
+/// 按文件顺序解析 `/proc/stat` 中按 CPU 划分的 `cpuN` 行。
+fn parse_per_cpu_stats_from_content(
+    content: &str,
+) -> Result<Vec<(u32, CpuStats)>, PipaCollectorError> {
+    let mut stats = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let Some((label, values)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(cpu) = label.strip_prefix("cpu").and_then(|n| n.parse::<u32>().ok()) else {
+            continue;
+        };
+        stats.push((cpu, parse_cpu_values(values).at_line(i + 1)?));
+    }
+    Ok(stats)
+}
+
+/// Reads the CPU time of every online CPU from `/proc/stat`.
+/// 从 `/proc/stat` 读取每个在线 CPU 的 CPU 时间。
+pub fn read_per_cpu_stats(root: &SysRoot) -> Result<Vec<(u32, CpuStats)>, PipaCollectorError> {
+    parse_file(root.proc("stat"), parse_per_cpu_stats_from_content)
 }
 
 /// Holds key memory statistics from `/proc/meminfo`.
@@ -194,7 +300,7 @@ pub fn read_cpu_stats() -> Result<CpuStats, PipaCollectorError> {
 ///
 /// 存储从 `/proc/meminfo` 中获取的关键内存统计信息。
 /// 所有值的单位都是千字节 (kB)。
-#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct MemoryStats {
     /// Total usable RAM. / 总可用 RAM。
     pub total: u64,
@@ -208,6 +314,21 @@ pub struct MemoryStats {
     pub buffers: u64,
     /// Memory used by the page cache. / 页面缓存使用的内存。
     pub cached: u64,
+    /// Recently used memory (`Active`). / 最近使用过的内存 (`Active`)。
+    pub active: u64,
+    /// Less recently used memory (`Inactive`). / 较久未使用的内存 (`Inactive`)。
+    pub inactive: u64,
+    /// Memory waiting to be written back (`Dirty`). / 等待回写的内存 (`Dirty`)。
+    pub dirty: u64,
+    /// Kernel slab caches (`Slab`). / 内核 slab 缓存 (`Slab`)。
+    pub slab: u64,
+    /// Memory committed by all processes (`Committed_AS`).
+    /// 所有进程已提交的内存 (`Committed_AS`)。
+    pub committed_as: u64,
+    /// Total swap space. / 交换空间总量。
+    pub swap_total: u64,
+    /// Unused swap space. / 未使用的交换空间。
+    pub swap_free: u64,
 }
 
 /// Parses memory statistics from the content of a `/proc/meminfo`-like string.
@@ -251,13 +372,16 @@ fn parse_memory_stats_from_content(content: &str) -> Result<MemoryStats, PipaCol
                 stats.cached = value;
                 found_count += 1;
             }
+            // Optional fields used by `sar -r`; they stay 0 when absent.
+            "Active:" => stats.active = value,
+            "Inactive:" => stats.inactive = value,
+            "Dirty:" => stats.dirty = value,
+            "Slab:" => stats.slab = value,
+            "Committed_AS:" => stats.committed_as = value,
+            "SwapTotal:" => stats.swap_total = value,
+            "SwapFree:" => stats.swap_free = value,
             _ => { /* We don't care about other keys */ }
         }
-
-        // Optimization: if we've found all our fields, we can stop parsing.
-        if found_count >= TOTAL_FIELDS {
-            break;
-        }
     }
 
     if found_count < TOTAL_FIELDS {
@@ -274,20 +398,1135 @@ fn parse_memory_stats_from_content(content: &str) -> Result<MemoryStats, PipaCol
 fn read_memory_stats_from_path<P: AsRef<std::path::Path>>(
     path: P,
 ) -> Result<MemoryStats, PipaCollectorError> {
-    let content = std::fs::read_to_string(path)?;
-    parse_memory_stats_from_content(&content)
+    parse_file(path, parse_memory_stats_from_content)
 }
 
 /// Reads and parses key memory statistics from the `/proc/meminfo` file.
 /// 现在这个函数只是一个简单的包装器。
-#[cfg(not(tarpaulin_include))]
-pub fn read_memory_stats() -> Result<MemoryStats, PipaCollectorError> {
-    read_memory_stats_from_path("/proc/meminfo")
+pub fn read_memory_stats(root: &SysRoot) -> Result<MemoryStats, PipaCollectorError> {
+    read_memory_stats_from_path(root.proc("meminfo"))
+}
+
+/// Holds the load average and run-queue statistics that make up `sar -q`.
+/// The load averages come from `/proc/loadavg`; `procs_running`,
+/// `procs_blocked` and `online_cpus` come from `/proc/stat`.
+///
+/// 存储构成 `sar -q` 的平均负载和运行队列统计信息。
+/// 平均负载来自 `/proc/loadavg`；`procs_running`、`procs_blocked` 和
+/// `online_cpus` 来自 `/proc/stat`。
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct LoadStats {
+    /// 1-minute load average. / 1 分钟平均负载。
+    pub load1: f64,
+    /// 5-minute load average. / 5 分钟平均负载。
+    pub load5: f64,
+    /// 15-minute load average. / 15 分钟平均负载。
+    pub load15: f64,
+    /// Currently runnable scheduling entities. / 当前可运行的调度实体数。
+    pub runnable: u64,
+    /// Total scheduling entities (processes and threads) on the system.
+    /// 系统中调度实体（进程和线程）的总数。
+    pub total_tasks: u64,
+    /// PID most recently assigned by the kernel. / 内核最近分配的 PID。
+    pub last_pid: u64,
+    /// Tasks running or ready to run (`procs_running`).
+    /// 正在运行或就绪的任务数（`procs_running`）。
+    pub procs_running: u64,
+    /// Tasks blocked waiting for I/O (`procs_blocked`).
+    /// 因等待 I/O 而阻塞的任务数（`procs_blocked`）。
+    pub procs_blocked: u64,
+    /// CPUs online, i.e. the per-CPU `cpuN` lines; the scale for the load
+    /// averages.
+    /// 在线 CPU 数，即每 CPU 的 `cpuN` 行数；用于衡量平均负载。
+    #[serde(default)]
+    pub online_cpus: u64,
+}
+
+/// Parses the single line of `/proc/loadavg`, e.g.
+/// `0.80 0.32 0.11 2/71 2891`, into the load fields of a `LoadStats`.
+/// `procs_running` and `procs_blocked` are left at zero.
+///
+/// 解析 `/proc/loadavg` 的单行内容（例如 `0.80 0.32 0.11 2/71 2891`），
+/// 填充 `LoadStats` 中的负载字段。`procs_running` 和 `procs_blocked` 保持为 0。
+fn parse_load_avg_from_content(content: &str) -> Result<LoadStats, PipaCollectorError> {
+    let mut parts = content.split_whitespace();
+    let mut next = |name: &str| {
+        parts.next().ok_or_else(|| {
+            PipaCollectorError::MissingData(format!("Missing value for {} in /proc/loadavg", name))
+        })
+    };
+    let parse_load = |v: &str| {
+        v.parse::<f64>()
+            .map_err(|_| PipaCollectorError::InvalidFormat(format!("Invalid load average: {}", v)))
+    };
+
+    let load1 = parse_load(next("load1")?)?;
+    let load5 = parse_load(next("load5")?)?;
+    let load15 = parse_load(next("load15")?)?;
+    let (runnable, total) = next("runnable/total")?.split_once('/').ok_or_else(|| {
+        PipaCollectorError::InvalidFormat("Expected 'runnable/total' in /proc/loadavg".to_string())
+    })?;
+    let last_pid = next("last_pid")?.parse::<u64>()?;
+
+    Ok(LoadStats {
+        load1,
+        load5,
+        load15,
+        runnable: runnable.parse::<u64>()?,
+        total_tasks: total.parse::<u64>()?,
+        last_pid,
+        ..Default::default()
+    })
+}
+
+/// Extracts `procs_running`, `procs_blocked` and the number of per-CPU lines
+/// from `/proc/stat` content.
+///
+/// 从 `/proc/stat` 的内容中提取 `procs_running`、`procs_blocked` 以及每 CPU 行的数量。
+fn parse_procs_from_stat_content(content: &str) -> Result<(u64, u64, u64), PipaCollectorError> {
+    let mut running = None;
+    let mut blocked = None;
+    let mut cpus = 0;
+
+    for line in content.lines() {
+        if line
+            .strip_prefix("cpu")
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            cpus += 1;
+        } else if let Some(v) = line.strip_prefix("procs_running ") {
+            running = Some(v.trim().parse::<u64>()?);
+        } else if let Some(v) = line.strip_prefix("procs_blocked ") {
+            blocked = Some(v.trim().parse::<u64>()?);
+        }
+        if running.is_some() && blocked.is_some() {
+            break;
+        }
+    }
+
+    match (running, blocked) {
+        (Some(r), Some(b)) => Ok((r, b, cpus)),
+        _ => Err(PipaCollectorError::MissingData(
+            "Could not find procs_running/procs_blocked in /proc/stat".to_string(),
+        )),
+    }
+}
+
+/// Reads `/proc/loadavg`-like and `/proc/stat`-like files and merges them.
+/// 读取类似 `/proc/loadavg` 和 `/proc/stat` 的文件并合并结果。
+fn read_load_stats_from_paths<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    loadavg_path: P,
+    stat_path: Q,
+) -> Result<LoadStats, PipaCollectorError> {
+    let mut stats = parse_file(loadavg_path, parse_load_avg_from_content)?;
+    let (running, blocked, cpus) = parse_file(stat_path, parse_procs_from_stat_content)?;
+    stats.procs_running = running;
+    stats.procs_blocked = blocked;
+    stats.online_cpus = cpus;
+    Ok(stats)
+}
+
+/// Reads and parses the load average and run-queue statistics.
+/// 读取并解析平均负载和运行队列统计信息。
+pub fn read_load_stats(root: &SysRoot) -> Result<LoadStats, PipaCollectorError> {
+    read_load_stats_from_paths(root.proc("loadavg"), root.proc("stat"))
+}
+
+/// Parses a kernel CPU list such as `0-3,8,10-11` (the format of
+/// `/sys/devices/system/cpu/online`) into a sorted list of CPU ids.
+///
+/// 解析内核 CPU 列表（例如 `0-3,8,10-11`，即 `/sys/devices/system/cpu/online`
+/// 的格式），返回排好序的 CPU 编号列表。
+pub fn parse_cpu_list(list: &str) -> Result<Vec<u32>, PipaCollectorError> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (start.parse::<u32>()?, end.parse::<u32>()?);
+                if start > end {
+                    return Err(PipaCollectorError::InvalidFormat(format!(
+                        "Invalid CPU range: {}",
+                        range
+                    )));
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(range.parse::<u32>()?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Formats sorted CPU ids as a kernel CPU list such as `0-3,8`; the inverse
+/// of [`parse_cpu_list`].
+///
+/// 将排好序的 CPU 编号格式化为内核 CPU 列表（例如 `0-3,8`），即 [`parse_cpu_list`] 的逆操作。
+pub fn format_cpu_list(cpus: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Reads a CPU list file and returns the number of CPUs it names.
+/// 读取 CPU 列表文件并返回其中列出的 CPU 数量。
+fn read_online_cpu_count_from_path<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<usize, PipaCollectorError> {
+    Ok(parse_file(path, parse_cpu_list)?.len())
+}
+
+/// Returns the sorted numeric suffixes of entries named `<prefix>N` in `dir`.
+/// 返回 `dir` 中名为 `<prefix>N` 的条目的数字后缀（已排序）。
+pub(crate) fn numbered_entries(
+    dir: &std::path::Path,
+    prefix: &str,
+) -> Result<Vec<u32>, PipaCollectorError> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut numbers = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name();
+        if let Some(n) = name.to_str().and_then(|n| n.strip_prefix(prefix)) {
+            if let Ok(n) = n.parse::<u32>() {
+                numbers.push(n);
+            }
+        }
+    }
+    numbers.sort_unstable();
+    Ok(numbers)
+}
+
+/// Returns the number of online CPUs from `/sys/devices/system/cpu/online`.
+/// 从 `/sys/devices/system/cpu/online` 获取在线 CPU 的数量。
+pub fn read_online_cpu_count(root: &SysRoot) -> Result<usize, PipaCollectorError> {
+    read_online_cpu_count_from_path(root.sys("devices/system/cpu/online"))
+}
+
+/// The resources for which the kernel exposes Pressure Stall Information.
+/// Each variant maps to `/proc/pressure/<name>` and `<cgroup>/<name>.pressure`.
+///
+/// 内核提供压力停滞信息 (PSI) 的资源类型。
+/// 每个变体对应 `/proc/pressure/<name>` 以及 `<cgroup>/<name>.pressure`。
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum PsiResource {
+    /// CPU pressure. / CPU 压力。
+    Cpu,
+    /// Memory pressure. / 内存压力。
+    Memory,
+    /// I/O pressure. / I/O 压力。
+    Io,
+    /// IRQ pressure (requires `CONFIG_IRQ_TIME_ACCOUNTING`, Linux 6.1+).
+    /// IRQ 压力（需要 `CONFIG_IRQ_TIME_ACCOUNTING`，Linux 6.1+）。
+    Irq,
+}
+
+impl PsiResource {
+    /// All resources, in the order they are usually reported.
+    /// 所有资源类型，按通常的报告顺序排列。
+    pub const ALL: [PsiResource; 4] =
+        [PsiResource::Cpu, PsiResource::Memory, PsiResource::Io, PsiResource::Irq];
+
+    /// The base file name used by both procfs and cgroupfs.
+    /// procfs 和 cgroupfs 共用的基础文件名。
+    pub fn name(self) -> &'static str {
+        match self {
+            PsiResource::Cpu => "cpu",
+            PsiResource::Memory => "memory",
+            PsiResource::Io => "io",
+            PsiResource::Irq => "irq",
+        }
+    }
+
+    /// The system-wide PSI file, e.g. `/proc/pressure/cpu`.
+    /// 系统级的 PSI 文件，例如 `/proc/pressure/cpu`。
+    pub fn proc_path(self, root: &SysRoot) -> std::path::PathBuf {
+        root.proc("pressure").join(self.name())
+    }
+
+    /// The per-cgroup PSI file, e.g. `<cgroup>/cpu.pressure`.
+    /// 每个 cgroup 的 PSI 文件，例如 `<cgroup>/cpu.pressure`。
+    pub fn cgroup_path<P: AsRef<std::path::Path>>(self, cgroup_dir: P) -> std::path::PathBuf {
+        cgroup_dir.as_ref().join(format!("{}.pressure", self.name()))
+    }
+}
+
+/// Distinguishes the two lines of a PSI file.
+/// `some` means at least one task was stalled; `full` means all non-idle
+/// tasks were stalled simultaneously.
+///
+/// 区分 PSI 文件中的两行。
+/// `some` 表示至少有一个任务停滞；`full` 表示所有非空闲任务同时停滞。
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum PsiKind {
+    /// At least one task stalled. / 至少一个任务停滞。
+    Some,
+    /// All non-idle tasks stalled. / 所有非空闲任务停滞。
+    Full,
+}
+
+impl PsiKind {
+    /// The line prefix used in PSI files. / PSI 文件中使用的行前缀。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PsiKind::Some => "some",
+            PsiKind::Full => "full",
+        }
+    }
+}
+
+/// One line of a PSI file: stall percentages averaged over 10s, 60s and
+/// 300s windows, plus the absolute stall time.
+///
+/// PSI 文件中的一行：10 秒、60 秒和 300 秒窗口内的平均停滞百分比，
+/// 以及累计的绝对停滞时间。
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PsiLine {
+    /// Percentage of wall time stalled over the last 10 seconds.
+    /// 最近 10 秒内停滞时间所占的百分比。
+    pub avg10: f64,
+    /// Percentage of wall time stalled over the last 60 seconds.
+    /// 最近 60 秒内停滞时间所占的百分比。
+    pub avg60: f64,
+    /// Percentage of wall time stalled over the last 300 seconds.
+    /// 最近 300 秒内停滞时间所占的百分比。
+    pub avg300: f64,
+    /// Total stall time in microseconds. / 累计停滞时间（微秒）。
+    pub total: u64,
+}
+
+/// Pressure Stall Information for a single resource.
+///
+/// 单个资源的压力停滞信息。
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PressureStats {
+    /// The `some` line, present for every resource.
+    /// `some` 行，所有资源都会提供。
+    pub some: PsiLine,
+    /// The `full` line. Absent for system-wide CPU pressure on kernels
+    /// older than 5.13.
+    /// `full` 行。在 5.13 之前的内核上，系统级 CPU 压力没有该行。
+    pub full: Option<PsiLine>,
+}
+
+/// Parses a single `some ...` or `full ...` line of a PSI file.
+///
+/// 解析 PSI 文件中的单个 `some ...` 或 `full ...` 行。
+fn parse_psi_line(line: &str) -> Result<(PsiKind, PsiLine), PipaCollectorError> {
+    let mut parts = line.split_whitespace();
+    let kind = match parts.next() {
+        Some("some") => PsiKind::Some,
+        Some("full") => PsiKind::Full,
+        other => {
+            return Err(PipaCollectorError::InvalidFormat(format!(
+                "Unknown PSI line kind: {:?}",
+                other
+            )));
+        }
+    };
+
+    let mut psi = PsiLine::default();
+    let mut found_count = 0;
+    for part in parts {
+        let (key, value) = part.split_once('=').ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(format!("Expected key=value in PSI line: {}", part))
+        })?;
+        let parse_avg = |v: &str| {
+            v.parse::<f64>().map_err(|_| {
+                PipaCollectorError::InvalidFormat(format!("Invalid PSI average: {}", v))
+            })
+        };
+        match key {
+            "avg10" => psi.avg10 = parse_avg(value)?,
+            "avg60" => psi.avg60 = parse_avg(value)?,
+            "avg300" => psi.avg300 = parse_avg(value)?,
+            "total" => psi.total = value.parse::<u64>()?,
+            _ => continue,
+        }
+        found_count += 1;
+    }
+
+    if found_count < 4 {
+        return Err(PipaCollectorError::MissingData(format!(
+            "PSI '{}' line is missing fields",
+            kind.as_str()
+        )));
+    }
+
+    Ok((kind, psi))
+}
+
+/// Parses the content of a PSI file (`/proc/pressure/*` or `*.pressure`).
+///
+/// 解析 PSI 文件（`/proc/pressure/*` 或 `*.pressure`）的内容。
+fn parse_pressure_stats_from_content(content: &str) -> Result<PressureStats, PipaCollectorError> {
+    let mut some = None;
+    let mut full = None;
+
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match parse_psi_line(line)? {
+            (PsiKind::Some, psi) => some = Some(psi),
+            (PsiKind::Full, psi) => full = Some(psi),
+        }
+    }
+
+    let some = some.ok_or_else(|| {
+        PipaCollectorError::MissingData("PSI file has no 'some' line".to_string())
+    })?;
+
+    Ok(PressureStats { some, full })
+}
+
+/// Reads and parses a PSI file at the given path.
+/// Use this for per-cgroup files together with [`PsiResource::cgroup_path`].
+///
+/// 读取并解析给定路径下的 PSI 文件。
+/// 对于 cgroup 文件，可以与 [`PsiResource::cgroup_path`] 配合使用。
+pub fn read_pressure_stats_from_path<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<PressureStats, PipaCollectorError> {
+    parse_file(path, parse_pressure_stats_from_content)
+}
+
+/// Reads and parses system-wide PSI for a resource from `/proc/pressure`.
+/// 从 `/proc/pressure` 读取并解析某个资源的系统级 PSI。
+pub fn read_pressure_stats(
+    root: &SysRoot,
+    resource: PsiResource,
+) -> Result<PressureStats, PipaCollectorError> {
+    read_pressure_stats_from_path(resource.proc_path(root))
+}
+
+/// Builds the string written to a PSI file to register a trigger.
+/// The kernel expects `<some|full> <stall_us> <window_us>`.
+///
+/// 构造用于注册 PSI 触发器的字符串。
+/// 内核期望的格式为 `<some|full> <stall_us> <window_us>`。
+fn format_psi_trigger(
+    kind: PsiKind,
+    stall: std::time::Duration,
+    window: std::time::Duration,
+) -> Result<String, PipaCollectorError> {
+    // Limits enforced by the kernel in `psi_trigger_create()`.
+    const MIN_WINDOW_US: u128 = 500_000;
+    const MAX_WINDOW_US: u128 = 10_000_000;
+
+    let stall_us = stall.as_micros();
+    let window_us = window.as_micros();
+    if !(MIN_WINDOW_US..=MAX_WINDOW_US).contains(&window_us) {
+        return Err(PipaCollectorError::InvalidFormat(format!(
+            "PSI trigger window must be between 500ms and 10s, got {:?}",
+            window
+        )));
+    }
+    if stall_us == 0 || stall_us > window_us {
+        return Err(PipaCollectorError::InvalidFormat(format!(
+            "PSI trigger threshold must be non-zero and not exceed the window, got {:?}",
+            stall
+        )));
+    }
+
+    Ok(format!("{} {} {}", kind.as_str(), stall_us, window_us))
+}
+
+/// A registered PSI trigger. The kernel signals the trigger when the stall
+/// time within any `window` exceeds the configured threshold. The trigger is
+/// unregistered when this handle is dropped.
+///
+/// 一个已注册的 PSI 触发器。当任意 `window` 时间窗口内的停滞时间超过设定阈值时，
+/// 内核会触发该事件。句柄被 drop 时触发器会自动注销。
+#[derive(Debug)]
+pub struct PsiTrigger {
+    file: std::fs::File,
+}
+
+impl PsiTrigger {
+    /// Registers a trigger on the PSI file at `path` (system-wide or cgroup).
+    /// Without `CAP_SYS_RESOURCE`, the kernel only accepts windows that are a
+    /// multiple of 2 seconds.
+    ///
+    /// 在 `path` 指定的 PSI 文件（系统级或 cgroup）上注册一个触发器。
+    /// 如果没有 `CAP_SYS_RESOURCE`，内核只接受 2 秒整数倍的时间窗口。
+    #[cfg(not(tarpaulin_include))]
+    pub fn new<P: AsRef<std::path::Path>>(
+        path: P,
+        kind: PsiKind,
+        stall: std::time::Duration,
+        window: std::time::Duration,
+    ) -> Result<Self, PipaCollectorError> {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let spec = format_psi_trigger(kind, stall, window)?;
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path.as_ref())
+            .at_path(path.as_ref())?;
+        // The trailing NUL is required: the kernel parses the buffer as a C string.
+        file.write_all(format!("{}\0", spec).as_bytes()).at_path(path)?;
+        Ok(PsiTrigger { file })
+    }
+
+    /// Registers a trigger on the system-wide PSI file of `resource`.
+    ///
+    /// 在 `resource` 对应的系统级 PSI 文件上注册一个触发器。
+    #[cfg(not(tarpaulin_include))]
+    pub fn system(
+        root: &SysRoot,
+        resource: PsiResource,
+        kind: PsiKind,
+        stall: std::time::Duration,
+        window: std::time::Duration,
+    ) -> Result<Self, PipaCollectorError> {
+        Self::new(resource.proc_path(root), kind, stall, window)
+    }
+
+    /// Blocks until the trigger fires or `timeout` elapses (`None` waits
+    /// forever). Returns `Ok(true)` if the stall threshold was exceeded.
+    ///
+    /// 阻塞直到触发器被触发或 `timeout` 超时（`None` 表示永久等待）。
+    /// 如果超过了停滞阈值，返回 `Ok(true)`。
+    #[cfg(not(tarpaulin_include))]
+    pub fn wait(&self, timeout: Option<std::time::Duration>) -> Result<bool, PipaCollectorError> {
+        use std::os::unix::io::AsRawFd;
+
+        // Signals interrupt `poll`; retry with what is left of the original
+        // timeout so that repeated signals cannot extend the wait.
+        let deadline = timeout.map(|t| std::time::Instant::now() + t);
+        let mut pfd = libc::pollfd { fd: self.file.as_raw_fd(), events: libc::POLLPRI, revents: 0 };
+
+        loop {
+            let timeout_ms = match deadline {
+                Some(deadline) => deadline
+                    .saturating_duration_since(std::time::Instant::now())
+                    .as_millis()
+                    .min(libc::c_int::MAX as u128) as libc::c_int,
+                None => -1,
+            };
+            let ret = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+            if ret < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err.into());
+            }
+            if ret == 0 {
+                return Ok(false);
+            }
+            if pfd.revents & libc::POLLERR != 0 {
+                // The monitored cgroup was removed or PSI is unavailable.
+                return Err(PipaCollectorError::Io(io::Error::other(
+                    "PSI trigger file reported POLLERR",
+                )));
+            }
+            return Ok(pfd.revents & libc::POLLPRI != 0);
+        }
+    }
+}
+
+/// A PSI trigger as given on the command line:
+/// `RESOURCE:KIND:STALL_MS:WINDOW_MS`, e.g. `memory:some:150:1000` fires when
+/// some task stalled on memory for more than 150ms within a 1s window.
+///
+/// 命令行中给出的 PSI 触发器：`RESOURCE:KIND:STALL_MS:WINDOW_MS`，
+/// 例如 `memory:some:150:1000` 表示在 1 秒窗口内有任务因内存停滞超过 150 毫秒时触发。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct PsiTriggerSpec {
+    /// The monitored resource. / 监控的资源。
+    pub resource: PsiResource,
+    /// Whether `some` or `full` stall time is measured. / 统计 `some` 还是 `full` 停滞时间。
+    pub kind: PsiKind,
+    /// Stall threshold within one window. / 单个窗口内的停滞阈值。
+    pub stall: std::time::Duration,
+    /// Length of the tracking window. / 跟踪窗口的长度。
+    pub window: std::time::Duration,
+}
+
+impl PsiTriggerSpec {
+    /// Registers this trigger on the system-wide PSI file.
+    /// 在系统级 PSI 文件上注册此触发器。
+    #[cfg(not(tarpaulin_include))]
+    pub fn arm(&self, root: &SysRoot) -> Result<PsiTrigger, PipaCollectorError> {
+        PsiTrigger::system(root, self.resource, self.kind, self.stall, self.window)
+    }
+}
+
+impl std::str::FromStr for PsiTriggerSpec {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad =
+            |why: &str| PipaCollectorError::InvalidFormat(format!("PSI trigger `{}`: {}", s, why));
+        let parts: Vec<&str> = s.split(':').collect();
+        let [resource, kind, stall, window] = parts[..] else {
+            return Err(bad("expected RESOURCE:KIND:STALL_MS:WINDOW_MS"));
+        };
+        let resource = PsiResource::ALL
+            .into_iter()
+            .find(|r| r.name() == resource)
+            .ok_or_else(|| bad("expected resource cpu, memory, io or irq"))?;
+        let kind = match kind {
+            "some" => PsiKind::Some,
+            "full" => PsiKind::Full,
+            _ => return Err(bad("expected kind some or full")),
+        };
+        let millis = |v: &str| {
+            v.parse::<u64>()
+                .map(std::time::Duration::from_millis)
+                .map_err(|_| bad("stall and window must be milliseconds"))
+        };
+        let spec =
+            PsiTriggerSpec { resource, kind, stall: millis(stall)?, window: millis(window)? };
+        // Reject limits the kernel would refuse before anything is armed.
+        format_psi_trigger(spec.kind, spec.stall, spec.window)?;
+        Ok(spec)
+    }
+}
+
+impl std::fmt::Display for PsiTriggerSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.resource.name(),
+            self.kind.as_str(),
+            self.stall.as_millis(),
+            self.window.as_millis()
+        )
+    }
+}
+
+/// Paging, swapping, reclaim, compaction and NUMA counters from
+/// `/proc/vmstat`. These are the inputs of `sar -B` and `sar -W`.
+/// All fields are monotonically increasing event counts (pages for
+/// `pgpgin`/`pgpgout` are reported in kB by the kernel). Counters missing on
+/// the running kernel (e.g. NUMA counters without `CONFIG_NUMA`) read as 0.
+///
+/// 来自 `/proc/vmstat` 的分页、交换、回收、内存规整和 NUMA 计数器，
+/// 也就是 `sar -B` 和 `sar -W` 的数据来源。所有字段都是单调递增的事件计数
+/// （内核以 kB 为单位报告 `pgpgin`/`pgpgout`）。当前内核不提供的计数器
+/// （例如未开启 `CONFIG_NUMA` 时的 NUMA 计数器）读作 0。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct VmStats {
+    /// kB paged in from disk. / 从磁盘换入的 kB 数。
+    pub pgpgin: u64,
+    /// kB paged out to disk. / 换出到磁盘的 kB 数。
+    pub pgpgout: u64,
+    /// Pages swapped in. / 换入的页数。
+    pub pswpin: u64,
+    /// Pages swapped out. / 换出的页数。
+    pub pswpout: u64,
+    /// Page faults (minor and major). / 缺页异常（次要和主要）。
+    pub pgfault: u64,
+    /// Major page faults that required disk I/O.
+    /// 需要磁盘 I/O 的主要缺页异常。
+    pub pgmajfault: u64,
+    /// Pages placed on the free list. / 放入空闲链表的页数。
+    pub pgfree: u64,
+    /// Pages scanned by kswapd. / kswapd 扫描的页数。
+    pub pgscan_kswapd: u64,
+    /// Pages scanned by direct reclaim. / 直接回收扫描的页数。
+    pub pgscan_direct: u64,
+    /// Pages scanned by khugepaged. / khugepaged 扫描的页数。
+    pub pgscan_khugepaged: u64,
+    /// Pages reclaimed by kswapd. / kswapd 回收的页数。
+    pub pgsteal_kswapd: u64,
+    /// Pages reclaimed by direct reclaim. / 直接回收回收的页数。
+    pub pgsteal_direct: u64,
+    /// Pages reclaimed by khugepaged. / khugepaged 回收的页数。
+    pub pgsteal_khugepaged: u64,
+    /// Direct compaction stalls. / 直接内存规整导致的停顿次数。
+    pub compact_stall: u64,
+    /// Failed compaction attempts. / 内存规整失败次数。
+    pub compact_fail: u64,
+    /// Successful compaction attempts. / 内存规整成功次数。
+    pub compact_success: u64,
+    /// Pages scanned by the compaction migration scanner.
+    /// 内存规整迁移扫描器扫描的页数。
+    pub compact_migrate_scanned: u64,
+    /// Pages scanned by the compaction free scanner.
+    /// 内存规整空闲扫描器扫描的页数。
+    pub compact_free_scanned: u64,
+    /// Allocations satisfied from the intended node. / 在目标节点上满足的分配。
+    pub numa_hit: u64,
+    /// Allocations satisfied from a node other than the intended one.
+    /// 在非目标节点上满足的分配。
+    pub numa_miss: u64,
+    /// Allocations intended for this node but satisfied elsewhere.
+    /// 原本针对本节点但在其他节点上满足的分配。
+    pub numa_foreign: u64,
+    /// Interleave-policy allocations. / 交错策略下的分配次数。
+    pub numa_interleave: u64,
+    /// Allocations from the local node. / 来自本地节点的分配。
+    pub numa_local: u64,
+    /// Allocations from a remote node. / 来自远端节点的分配。
+    pub numa_other: u64,
+    /// NUMA hinting faults. / NUMA 提示缺页次数。
+    pub numa_hint_faults: u64,
+    /// Pages migrated by automatic NUMA balancing. / 自动 NUMA 平衡迁移的页数。
+    pub numa_pages_migrated: u64,
+}
+
+impl VmStats {
+    /// Total pages scanned for reclaim (`pgscank` + `pgscand` in `sar -B`).
+    /// 回收时扫描的总页数（即 `sar -B` 中的 `pgscank` + `pgscand`）。
+    pub fn pgscan_total(&self) -> u64 {
+        self.pgscan_kswapd + self.pgscan_direct + self.pgscan_khugepaged
+    }
+
+    /// Total pages reclaimed (`pgsteal` in `sar -B`).
+    /// 回收的总页数（即 `sar -B` 中的 `pgsteal`）。
+    pub fn pgsteal_total(&self) -> u64 {
+        self.pgsteal_kswapd + self.pgsteal_direct + self.pgsteal_khugepaged
+    }
+
+    /// Reclaim efficiency (`%vmeff` in `sar -B`): reclaimed pages as a
+    /// percentage of scanned pages. Meaningful on a [`Delta`], not on raw
+    /// totals. Returns 0.0 when nothing was scanned.
+    ///
+    /// 回收效率（即 `sar -B` 中的 `%vmeff`）：回收页数占扫描页数的百分比。
+    /// 应在 [`Delta`] 结果上使用，而非原始累计值。若未扫描任何页，返回 0.0。
+    pub fn vmeff(&self) -> f64 {
+        let scanned = self.pgscan_total();
+        if scanned == 0 { 0.0 } else { self.pgsteal_total() as f64 / scanned as f64 * 100.0 }
+    }
+}
+
+impl Delta for VmStats {
+    fn delta(&self, earlier: &Self) -> Self {
+        macro_rules! sub {
+            ($($field:ident),* $(,)?) => {
+                VmStats { $($field: self.$field.saturating_sub(earlier.$field)),* }
+            };
+        }
+        sub!(
+            pgpgin,
+            pgpgout,
+            pswpin,
+            pswpout,
+            pgfault,
+            pgmajfault,
+            pgfree,
+            pgscan_kswapd,
+            pgscan_direct,
+            pgscan_khugepaged,
+            pgsteal_kswapd,
+            pgsteal_direct,
+            pgsteal_khugepaged,
+            compact_stall,
+            compact_fail,
+            compact_success,
+            compact_migrate_scanned,
+            compact_free_scanned,
+            numa_hit,
+            numa_miss,
+            numa_foreign,
+            numa_interleave,
+            numa_local,
+            numa_other,
+            numa_hint_faults,
+            numa_pages_migrated,
+        )
+    }
+}
+
+/// Parses the content of `/proc/vmstat`. Unknown keys are ignored. The
+/// `pgscan_*`/`pgsteal_*` families are summed per reclaimer, which also covers
+/// the per-zone names (`pgscan_kswapd_normal`, ...) of pre-4.8 kernels.
+///
+/// 解析 `/proc/vmstat` 的内容，忽略未知的键。`pgscan_*`/`pgsteal_*` 系列按回收者累加，
+/// 这样也兼容 4.8 之前内核的按 zone 命名（`pgscan_kswapd_normal` 等）。
+fn parse_vm_stats_from_content(content: &str) -> Result<VmStats, PipaCollectorError> {
+    let mut stats = VmStats::default();
+    let mut found_count = 0;
+    // The paging and fault counters exist on every kernel we support.
+    const REQUIRED_FIELDS: u8 = 6;
+
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(key), Some(value_str)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let value = match value_str.parse::<u64>() {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+
+        match key {
+            "pgpgin" => {
+                stats.pgpgin = value;
+                found_count += 1;
+            }
+            "pgpgout" => {
+                stats.pgpgout = value;
+                found_count += 1;
+            }
+            "pswpin" => {
+                stats.pswpin = value;
+                found_count += 1;
+            }
+            "pswpout" => {
+                stats.pswpout = value;
+                found_count += 1;
+            }
+            "pgfault" => {
+                stats.pgfault = value;
+                found_count += 1;
+            }
+            "pgmajfault" => {
+                stats.pgmajfault = value;
+                found_count += 1;
+            }
+            "pgfree" => stats.pgfree = value,
+            "compact_stall" => stats.compact_stall = value,
+            "compact_fail" => stats.compact_fail = value,
+            "compact_success" => stats.compact_success = value,
+            "compact_migrate_scanned" => stats.compact_migrate_scanned = value,
+            "compact_free_scanned" => stats.compact_free_scanned = value,
+            "numa_hit" => stats.numa_hit = value,
+            "numa_miss" => stats.numa_miss = value,
+            "numa_foreign" => stats.numa_foreign = value,
+            "numa_interleave" => stats.numa_interleave = value,
+            "numa_local" => stats.numa_local = value,
+            "numa_other" => stats.numa_other = value,
+            "numa_hint_faults" => stats.numa_hint_faults = value,
+            "numa_pages_migrated" => stats.numa_pages_migrated = value,
+            _ if key.starts_with("pgscan_kswapd") => stats.pgscan_kswapd += value,
+            _ if key.starts_with("pgscan_direct") && key != "pgscan_direct_throttle" => {
+                stats.pgscan_direct += value
+            }
+            _ if key.starts_with("pgscan_khugepaged") => stats.pgscan_khugepaged += value,
+            _ if key.starts_with("pgsteal_kswapd") => stats.pgsteal_kswapd += value,
+            _ if key.starts_with("pgsteal_direct") => stats.pgsteal_direct += value,
+            _ if key.starts_with("pgsteal_khugepaged") => stats.pgsteal_khugepaged += value,
+            _ => { /* gauges (nr_*) and counters we do not report */ }
+        }
+    }
+
+    if found_count < REQUIRED_FIELDS {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find all required paging fields in /proc/vmstat".to_string(),
+        ));
+    }
+
+    Ok(stats)
+}
+
+/// Reads content from a given path and calls the vmstat parser.
+/// 从给定路径读取内容并调用 vmstat 解析器。
+fn read_vm_stats_from_path<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<VmStats, PipaCollectorError> {
+    parse_file(path, parse_vm_stats_from_content)
+}
+
+/// Reads and parses paging and reclaim counters from `/proc/vmstat`.
+/// 从 `/proc/vmstat` 读取并解析分页与回收计数器。
+pub fn read_vm_stats(root: &SysRoot) -> Result<VmStats, PipaCollectorError> {
+    read_vm_stats_from_path(root.proc("vmstat"))
+}
+
+/// Cumulative I/O statistics of one block device from `/proc/diskstats`,
+/// the data behind `sar -d` and `iostat`. Sector counts are in 512-byte
+/// units and times in milliseconds. The discard fields are 0 on kernels
+/// older than 4.18.
+///
+/// 来自 `/proc/diskstats` 的单个块设备累计 I/O 统计信息，即 `sar -d` 和
+/// `iostat` 的数据来源。扇区数以 512 字节为单位，时间以毫秒为单位。
+/// 在 4.18 之前的内核上，discard 相关字段为 0。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct DiskStats {
+    /// Device major number. / 主设备号。
+    pub major: u32,
+    /// Device minor number. / 次设备号。
+    pub minor: u32,
+    /// Kernel device name, e.g. `nvme0n1`. / 内核设备名，例如 `nvme0n1`。
+    pub name: String,
+    /// Reads completed. / 完成的读操作数。
+    pub reads: u64,
+    /// Adjacent reads merged. / 合并的相邻读操作数。
+    pub reads_merged: u64,
+    /// Sectors read. / 读取的扇区数。
+    pub sectors_read: u64,
+    /// Time spent reading. / 读操作耗时。
+    pub read_ms: u64,
+    /// Writes completed. / 完成的写操作数。
+    pub writes: u64,
+    /// Adjacent writes merged. / 合并的相邻写操作数。
+    pub writes_merged: u64,
+    /// Sectors written. / 写入的扇区数。
+    pub sectors_written: u64,
+    /// Time spent writing. / 写操作耗时。
+    pub write_ms: u64,
+    /// I/Os currently in flight (a gauge, not a counter).
+    /// 当前正在进行的 I/O 数（瞬时值，而非计数器）。
+    pub in_flight: u64,
+    /// Time the device had I/O in flight. / 设备有 I/O 进行中的时间。
+    pub io_ms: u64,
+    /// In-flight time weighted by queue depth. / 按队列深度加权的 I/O 时间。
+    pub weighted_io_ms: u64,
+    /// Discards completed. / 完成的 discard 操作数。
+    pub discards: u64,
+    /// Adjacent discards merged. / 合并的相邻 discard 操作数。
+    pub discards_merged: u64,
+    /// Sectors discarded. / discard 的扇区数。
+    pub sectors_discarded: u64,
+    /// Time spent discarding. / discard 操作耗时。
+    pub discard_ms: u64,
+}
+
+impl DiskStats {
+    /// Completed reads, writes and discards. / 完成的读、写和 discard 操作总数。
+    pub fn ios(&self) -> u64 {
+        self.reads + self.writes + self.discards
+    }
+}
+
+impl Delta for DiskStats {
+    /// `in_flight` is a gauge and keeps the current value.
+    /// `in_flight` 是瞬时值，保留当前值。
+    fn delta(&self, earlier: &Self) -> Self {
+        DiskStats {
+            major: self.major,
+            minor: self.minor,
+            name: self.name.clone(),
+            reads: self.reads.saturating_sub(earlier.reads),
+            reads_merged: self.reads_merged.saturating_sub(earlier.reads_merged),
+            sectors_read: self.sectors_read.saturating_sub(earlier.sectors_read),
+            read_ms: self.read_ms.saturating_sub(earlier.read_ms),
+            writes: self.writes.saturating_sub(earlier.writes),
+            writes_merged: self.writes_merged.saturating_sub(earlier.writes_merged),
+            sectors_written: self.sectors_written.saturating_sub(earlier.sectors_written),
+            write_ms: self.write_ms.saturating_sub(earlier.write_ms),
+            in_flight: self.in_flight,
+            io_ms: self.io_ms.saturating_sub(earlier.io_ms),
+            weighted_io_ms: self.weighted_io_ms.saturating_sub(earlier.weighted_io_ms),
+            discards: self.discards.saturating_sub(earlier.discards),
+            discards_merged: self.discards_merged.saturating_sub(earlier.discards_merged),
+            sectors_discarded: self.sectors_discarded.saturating_sub(earlier.sectors_discarded),
+            discard_ms: self.discard_ms.saturating_sub(earlier.discard_ms),
+        }
+    }
+}
+
+/// Parses the content of `/proc/diskstats`. Lines need at least the 11
+/// statistics of pre-4.18 kernels; the later discard and flush columns are
+/// optional.
+///
+/// 解析 `/proc/diskstats` 的内容。每行至少需要 4.18 之前内核提供的 11 个统计值；
+/// 之后新增的 discard 和 flush 列是可选的。
+fn parse_disk_stats_from_content(content: &str) -> Result<Vec<DiskStats>, PipaCollectorError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_disk_stats_line(line).at_line(i + 1))
+        .collect()
+}
+
+/// Parses one `/proc/diskstats` line. / 解析 `/proc/diskstats` 的一行。
+fn parse_disk_stats_line(line: &str) -> Result<DiskStats, PipaCollectorError> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 14 {
+        return Err(PipaCollectorError::InvalidFormat(format!(
+            "Too few fields in /proc/diskstats line: {}",
+            line.trim()
+        )));
+    }
+    let values =
+        fields[3..].iter().take(15).map(|v| v.parse::<u64>()).collect::<Result<Vec<_>, _>>()?;
+    let value = |i: usize| values.get(i).copied().unwrap_or(0);
+    Ok(DiskStats {
+        major: fields[0].parse()?,
+        minor: fields[1].parse()?,
+        name: fields[2].to_string(),
+        reads: value(0),
+        reads_merged: value(1),
+        sectors_read: value(2),
+        read_ms: value(3),
+        writes: value(4),
+        writes_merged: value(5),
+        sectors_written: value(6),
+        write_ms: value(7),
+        in_flight: value(8),
+        io_ms: value(9),
+        weighted_io_ms: value(10),
+        discards: value(11),
+        discards_merged: value(12),
+        sectors_discarded: value(13),
+        discard_ms: value(14),
+    })
+}
+
+/// Reads block device statistics from the given path.
+/// 从指定路径读取块设备统计信息。
+pub fn read_disk_stats_from_path<P: AsRef<std::path::Path>>(
+    path: P,
+) -> Result<Vec<DiskStats>, PipaCollectorError> {
+    parse_file(path, parse_disk_stats_from_content)
+}
+
+/// Reads block device statistics from `/proc/diskstats`.
+/// 从 `/proc/diskstats` 读取块设备统计信息。
+pub fn read_disk_stats(root: &SysRoot) -> Result<Vec<DiskStats>, PipaCollectorError> {
+    read_disk_stats_from_path(root.proc("diskstats"))
+}
+
+/// Cumulative traffic of one network interface from `/proc/net/dev`, the data
+/// behind `sar -n DEV`.
+///
+/// 来自 `/proc/net/dev` 的单个网络接口累计流量，即 `sar -n DEV` 的数据来源。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct NetDevStats {
+    /// Interface name. / 接口名。
+    pub name: String,
+    /// Bytes received. / 接收的字节数。
+    pub rx_bytes: u64,
+    /// Packets received. / 接收的包数。
+    pub rx_packets: u64,
+    /// Receive errors. / 接收错误数。
+    pub rx_errs: u64,
+    /// Received packets dropped. / 丢弃的接收包数。
+    pub rx_drop: u64,
+    /// Compressed packets received. / 接收的压缩包数。
+    pub rx_compressed: u64,
+    /// Multicast packets received. / 接收的多播包数。
+    pub rx_multicast: u64,
+    /// Bytes transmitted. / 发送的字节数。
+    pub tx_bytes: u64,
+    /// Packets transmitted. / 发送的包数。
+    pub tx_packets: u64,
+    /// Transmit errors. / 发送错误数。
+    pub tx_errs: u64,
+    /// Transmitted packets dropped. / 丢弃的发送包数。
+    pub tx_drop: u64,
+    /// Compressed packets transmitted. / 发送的压缩包数。
+    pub tx_compressed: u64,
+    /// Link speed from `/sys/class/net/<name>/speed`, if the driver reports
+    /// one. Used for `%ifutil`.
+    /// 来自 `/sys/class/net/<name>/speed` 的链路速率（若驱动提供），用于 `%ifutil`。
+    pub speed_mbps: Option<u64>,
+}
+
+impl Delta for NetDevStats {
+    /// `speed_mbps` is a property of the link and keeps the current value.
+    /// `speed_mbps` 是链路属性，保留当前值。
+    fn delta(&self, earlier: &Self) -> Self {
+        NetDevStats {
+            name: self.name.clone(),
+            rx_bytes: self.rx_bytes.saturating_sub(earlier.rx_bytes),
+            rx_packets: self.rx_packets.saturating_sub(earlier.rx_packets),
+            rx_errs: self.rx_errs.saturating_sub(earlier.rx_errs),
+            rx_drop: self.rx_drop.saturating_sub(earlier.rx_drop),
+            rx_compressed: self.rx_compressed.saturating_sub(earlier.rx_compressed),
+            rx_multicast: self.rx_multicast.saturating_sub(earlier.rx_multicast),
+            tx_bytes: self.tx_bytes.saturating_sub(earlier.tx_bytes),
+            tx_packets: self.tx_packets.saturating_sub(earlier.tx_packets),
+            tx_errs: self.tx_errs.saturating_sub(earlier.tx_errs),
+            tx_drop: self.tx_drop.saturating_sub(earlier.tx_drop),
+            tx_compressed: self.tx_compressed.saturating_sub(earlier.tx_compressed),
+            speed_mbps: self.speed_mbps,
+        }
+    }
+}
+
+/// Parses the content of `/proc/net/dev`, skipping its two header lines.
+/// 解析 `/proc/net/dev` 的内容，跳过两行表头。
+fn parse_net_dev_from_content(content: &str) -> Result<Vec<NetDevStats>, PipaCollectorError> {
+    content
+        .lines()
+        .enumerate()
+        .skip(2)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_net_dev_line(line).at_line(i + 1))
+        .collect()
+}
+
+/// Parses one interface line of `/proc/net/dev`. / 解析 `/proc/net/dev` 中的一行接口数据。
+fn parse_net_dev_line(line: &str) -> Result<NetDevStats, PipaCollectorError> {
+    // Large counters can run into the colon, as in `eth0:123456`.
+    let (name, counters) = line.split_once(':').ok_or_else(|| {
+        PipaCollectorError::InvalidFormat(format!("Invalid /proc/net/dev line: {}", line))
+    })?;
+    let values =
+        counters.split_whitespace().map(|v| v.parse::<u64>()).collect::<Result<Vec<_>, _>>()?;
+    if values.len() < 16 {
+        return Err(PipaCollectorError::InvalidFormat(format!(
+            "Too few fields in /proc/net/dev line: {}",
+            line.trim()
+        )));
+    }
+    Ok(NetDevStats {
+        name: name.trim().to_string(),
+        rx_bytes: values[0],
+        rx_packets: values[1],
+        rx_errs: values[2],
+        rx_drop: values[3],
+        rx_compressed: values[6],
+        rx_multicast: values[7],
+        tx_bytes: values[8],
+        tx_packets: values[9],
+        tx_errs: values[10],
+        tx_drop: values[11],
+        tx_compressed: values[15],
+        speed_mbps: None,
+    })
+}
+
+/// Reads interface statistics from a `/proc/net/dev`-like file and link
+/// speeds from a `/sys/class/net`-like directory. Interfaces without a
+/// readable, positive speed (loopback, virtual devices, links that are down)
+/// get `None`.
+///
+/// 从类似 `/proc/net/dev` 的文件读取接口统计信息，并从类似 `/sys/class/net`
+/// 的目录读取链路速率。没有可读且为正的速率的接口（回环、虚拟设备、未连接的链路）
+/// 记为 `None`。
+pub fn read_net_dev_stats_from_paths<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(
+    net_dev: P,
+    sys_class_net: Q,
+) -> Result<Vec<NetDevStats>, PipaCollectorError> {
+    let mut devices = parse_file(net_dev, parse_net_dev_from_content)?;
+    for device in &mut devices {
+        let speed = sys_class_net.as_ref().join(&device.name).join("speed");
+        device.speed_mbps = std::fs::read_to_string(speed)
+            .ok()
+            .and_then(|s| s.trim().parse::<i64>().ok())
+            .filter(|&s| s > 0)
+            .map(|s| s as u64);
+    }
+    Ok(devices)
+}
+
+/// Reads interface statistics from `/proc/net/dev` and `/sys/class/net`.
+/// 从 `/proc/net/dev` 和 `/sys/class/net` 读取接口统计信息。
+pub fn read_net_dev_stats(root: &SysRoot) -> Result<Vec<NetDevStats>, PipaCollectorError> {
+    read_net_dev_stats_from_paths(root.proc("net/dev"), root.sys("class/net"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ErrorCode;
+    use crate::test_support::fixture_tree;
     /// Test sections for /proc/stat
     #[test]
     fn test_parse_cpu_stats_happy_path_two_spaces() {
@@ -313,12 +1552,31 @@ mod tests {
         assert_eq!(stats.user, 74608);
     }
 
+    #[test]
+    fn test_parse_per_cpu_stats() {
+        let content = "cpu  30 0 0 70 0 0 0 0 0 0\n\
+                       cpu0 10 0 0 40 0 0 0 0 0 0\n\
+                       cpu2 20 0 0 30 0 0 0 0 0 0\n\
+                       intr 12345 0 0\n";
+        let stats = parse_per_cpu_stats_from_content(content).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[1].0, 2);
+        assert_eq!(stats[1].1.user, 20);
+
+        let mut total = stats[0].1;
+        total += stats[1].1;
+        assert_eq!(total, parse_cpu_stats_from_line(content.lines().next().unwrap()).unwrap());
+
+        let err = parse_per_cpu_stats_from_content("cpu0 1 2\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 1: "), "{}", err);
+    }
+
     #[test]
     fn test_parse_cpu_stats_invalid_prefix() {
         let line = "cqu 74608 2520 24433 1117073 6176 4054 0 0 0 0";
         let result = parse_cpu_stats_from_line(line);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), PipaCollectorError::InvalidFormat(_)));
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::InvalidFormat(_)));
     }
 
     #[test]
@@ -334,7 +1592,7 @@ mod tests {
         let line = "cpu  74608 2520 not-a-number 1117073 6176 4054 0 0 0 0";
         let result = parse_cpu_stats_from_line(line);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), PipaCollectorError::Parse(_)));
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::Parse(_)));
     }
     /// Test sections for /proc/meminfo
     #[test]
@@ -353,6 +1611,34 @@ mod tests {
         assert_eq!(stats.available, 50841208);
         assert_eq!(stats.buffers, 4504);
         assert_eq!(stats.cached, 25023892);
+        assert_eq!(stats.committed_as, 0);
+    }
+
+    #[test]
+    fn test_parse_memory_stats_optional_fields() {
+        let content = "MemTotal:       1000 kB\n\
+                       MemFree:         500 kB\n\
+                       MemAvailable:    800 kB\n\
+                       Buffers:          50 kB\n\
+                       Cached:          200 kB\n\
+                       SwapCached:        0 kB\n\
+                       Active:          300 kB\n\
+                       Inactive:        100 kB\n\
+                       Active(anon):     10 kB\n\
+                       SwapTotal:      2000 kB\n\
+                       SwapFree:       1500 kB\n\
+                       Dirty:             7 kB\n\
+                       Slab:             40 kB\n\
+                       Committed_AS:   1200 kB";
+
+        let stats = parse_memory_stats_from_content(content).unwrap();
+        assert_eq!(stats.active, 300);
+        assert_eq!(stats.inactive, 100);
+        assert_eq!(stats.dirty, 7);
+        assert_eq!(stats.slab, 40);
+        assert_eq!(stats.committed_as, 1200);
+        assert_eq!(stats.swap_total, 2000);
+        assert_eq!(stats.swap_free, 1500);
     }
 
     #[test]
@@ -397,7 +1683,7 @@ mod tests {
     fn test_read_cpu_stats_io_error() {
         let result = read_cpu_stats_from_path("/a/non/existent/path");
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), PipaCollectorError::Io(_)));
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::Io(_)));
     }
 
     #[test]
@@ -418,6 +1704,467 @@ mod tests {
     fn test_read_memory_stats_io_error() {
         let result = read_memory_stats_from_path("/another/non/existent/path");
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), PipaCollectorError::Io(_)));
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::Io(_)));
+    }
+
+    /// Test sections for PSI
+    #[test]
+    fn test_parse_pressure_stats_happy_path() {
+        let content = "some avg10=2.95 avg60=5.78 avg300=2.74 total=14697951\n\
+                       full avg10=0.10 avg60=0.00 avg300=0.00 total=42\n";
+        let stats = parse_pressure_stats_from_content(content).unwrap();
+
+        assert_eq!(stats.some.avg10, 2.95);
+        assert_eq!(stats.some.avg60, 5.78);
+        assert_eq!(stats.some.avg300, 2.74);
+        assert_eq!(stats.some.total, 14697951);
+        let full = stats.full.unwrap();
+        assert_eq!(full.avg10, 0.10);
+        assert_eq!(full.total, 42);
+    }
+
+    #[test]
+    fn test_parse_pressure_stats_without_full_line() {
+        // System-wide CPU pressure on kernels older than 5.13.
+        let content = "some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n";
+        let stats = parse_pressure_stats_from_content(content).unwrap();
+        assert_eq!(stats.some.total, 0);
+        assert!(stats.full.is_none());
+    }
+
+    #[test]
+    fn test_parse_pressure_stats_missing_some_line() {
+        let content = "full avg10=0.00 avg60=0.00 avg300=0.00 total=0\n";
+        let result = parse_pressure_stats_from_content(content);
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_parse_psi_line_errors() {
+        assert!(matches!(
+            parse_psi_line("most avg10=0.00 avg60=0.00 avg300=0.00 total=0")
+                .unwrap_err()
+                .root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_psi_line("some avg10=abc avg60=0.00 avg300=0.00 total=0")
+                .unwrap_err()
+                .root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_psi_line("some avg10=0.00 avg60=0.00 avg300=0.00 total=x")
+                .unwrap_err()
+                .root_cause(),
+            PipaCollectorError::Parse(_)
+        ));
+        assert!(matches!(
+            parse_psi_line("some avg10=0.00 avg60=0.00").unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+    }
+
+    #[test]
+    fn test_psi_resource_paths() {
+        assert_eq!(
+            PsiResource::Io.proc_path(&SysRoot::default()),
+            std::path::Path::new("/proc/pressure/io")
+        );
+        assert_eq!(
+            PsiResource::Memory.cgroup_path("/sys/fs/cgroup/app.slice"),
+            std::path::Path::new("/sys/fs/cgroup/app.slice/memory.pressure")
+        );
+    }
+
+    #[test]
+    fn test_format_psi_trigger() {
+        use std::time::Duration;
+        let spec =
+            format_psi_trigger(PsiKind::Some, Duration::from_millis(150), Duration::from_secs(1))
+                .unwrap();
+        assert_eq!(spec, "some 150000 1000000");
+
+        // Window below the kernel minimum.
+        assert!(
+            format_psi_trigger(
+                PsiKind::Full,
+                Duration::from_millis(10),
+                Duration::from_millis(100)
+            )
+            .is_err()
+        );
+        // Threshold larger than the window.
+        assert!(
+            format_psi_trigger(PsiKind::Full, Duration::from_secs(2), Duration::from_secs(1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_psi_trigger_spec() {
+        use std::time::Duration;
+        let spec: PsiTriggerSpec = "memory:some:150:1000".parse().unwrap();
+        assert_eq!(
+            spec,
+            PsiTriggerSpec {
+                resource: PsiResource::Memory,
+                kind: PsiKind::Some,
+                stall: Duration::from_millis(150),
+                window: Duration::from_secs(1),
+            }
+        );
+        assert_eq!(spec.to_string(), "memory:some:150:1000");
+        assert_eq!("io:full:500:2000".parse::<PsiTriggerSpec>().unwrap().kind, PsiKind::Full);
+
+        for bad in ["memory:some:150", "disk:some:150:1000", "cpu:half:150:1000", "cpu:some:x:1000"]
+        {
+            assert!(bad.parse::<PsiTriggerSpec>().is_err(), "{}", bad);
+        }
+        // Window outside the kernel limits.
+        assert!("cpu:some:50:100".parse::<PsiTriggerSpec>().is_err());
+    }
+
+    #[test]
+    fn test_read_pressure_stats_from_path_happy_path() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "some avg10=1.00 avg60=2.00 avg300=3.00 total=100").unwrap();
+        writeln!(file, "full avg10=0.50 avg60=1.00 avg300=1.50 total=50").unwrap();
+
+        let stats = read_pressure_stats_from_path(file.path()).unwrap();
+        assert_eq!(stats.some.avg300, 3.00);
+        assert_eq!(stats.full.unwrap().total, 50);
+    }
+
+    /// Test sections for the snapshot/delta abstraction
+    #[test]
+    fn test_cpu_stats_delta() {
+        let earlier = CpuStats { user: 100, idle: 1000, iowait: 20, ..Default::default() };
+        let later = CpuStats { user: 150, idle: 1100, iowait: 10, ..Default::default() };
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.user, 50);
+        assert_eq!(delta.idle, 100);
+        // A counter that went backwards saturates instead of underflowing.
+        assert_eq!(delta.iowait, 0);
+        assert_eq!(delta.total_time(), 150);
+        assert_eq!(delta.idle_time(), 100);
+    }
+
+    #[test]
+    fn test_snapshot_delta_per_second() {
+        use std::time::Duration;
+        let earlier = Snapshot {
+            timestamp: Duration::from_secs(10),
+            stats: VmStats { pgfault: 1_000, ..Default::default() },
+        };
+        let later = Snapshot {
+            timestamp: Duration::from_secs(12),
+            stats: VmStats { pgfault: 5_000, ..Default::default() },
+        };
+        let d = later.delta_since(&earlier);
+        assert_eq!(d.elapsed, Duration::from_secs(2));
+        assert_eq!(d.delta.pgfault, 4_000);
+        assert_eq!(d.per_second(d.delta.pgfault), 2_000.0);
+
+        let zero = SnapshotDelta { elapsed: Duration::ZERO, delta: VmStats::default() };
+        assert_eq!(zero.per_second(100), 0.0);
+    }
+
+    #[test]
+    fn test_monotonic_now_is_monotonic() {
+        let a = monotonic_now();
+        let b = monotonic_now();
+        assert!(b >= a);
+    }
+
+    /// Test sections for /proc/vmstat
+    #[test]
+    fn test_parse_vm_stats_happy_path() {
+        let content = "nr_free_pages 1000\n\
+                       pgpgin 1200\n\
+                       pgpgout 3400\n\
+                       pswpin 5\n\
+                       pswpout 7\n\
+                       pgfault 99999\n\
+                       pgmajfault 12\n\
+                       pgfree 4242\n\
+                       pgsteal_kswapd 80\n\
+                       pgsteal_direct 20\n\
+                       pgsteal_khugepaged 0\n\
+                       pgsteal_anon 60\n\
+                       pgsteal_file 40\n\
+                       pgscan_kswapd 150\n\
+                       pgscan_direct 50\n\
+                       pgscan_direct_throttle 3\n\
+                       compact_stall 2\n\
+                       compact_success 1\n\
+                       numa_hit 777\n\
+                       numa_miss 3\n";
+        let stats = parse_vm_stats_from_content(content).unwrap();
+
+        assert_eq!(stats.pgpgin, 1200);
+        assert_eq!(stats.pgpgout, 3400);
+        assert_eq!(stats.pswpin, 5);
+        assert_eq!(stats.pswpout, 7);
+        assert_eq!(stats.pgfault, 99999);
+        assert_eq!(stats.pgmajfault, 12);
+        assert_eq!(stats.pgfree, 4242);
+        // The anon/file split and the throttle counter must not be double-counted.
+        assert_eq!(stats.pgsteal_total(), 100);
+        assert_eq!(stats.pgscan_total(), 200);
+        assert_eq!(stats.vmeff(), 50.0);
+        assert_eq!(stats.compact_stall, 2);
+        assert_eq!(stats.numa_hit, 777);
+        assert_eq!(stats.numa_local, 0);
+    }
+
+    #[test]
+    fn test_parse_vm_stats_per_zone_names() {
+        // Kernels before 4.8 report reclaim counters per zone.
+        let content = "pgpgin 1\npgpgout 1\npswpin 0\npswpout 0\npgfault 1\npgmajfault 0\n\
+                       pgscan_kswapd_dma32 10\npgscan_kswapd_normal 30\n\
+                       pgsteal_kswapd_dma32 5\npgsteal_kswapd_normal 15\n";
+        let stats = parse_vm_stats_from_content(content).unwrap();
+        assert_eq!(stats.pgscan_kswapd, 40);
+        assert_eq!(stats.pgsteal_kswapd, 20);
+    }
+
+    #[test]
+    fn test_parse_vm_stats_missing_fields() {
+        let content = "pgpgin 1200\npgpgout 3400\n";
+        let result = parse_vm_stats_from_content(content);
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_vm_stats_vmeff_without_scans() {
+        assert_eq!(VmStats::default().vmeff(), 0.0);
+    }
+
+    #[test]
+    fn test_read_vm_stats_from_path() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "pgpgin 1\npgpgout 2\npswpin 3\npswpout 4\npgfault 5\npgmajfault 6\n")
+            .unwrap();
+        let stats = read_vm_stats_from_path(file.path()).unwrap();
+        assert_eq!(stats.pgmajfault, 6);
+
+        let result = read_vm_stats_from_path("/a/non/existent/vmstat");
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::Io(_)));
+    }
+
+    /// Test sections for /proc/loadavg and the run queue
+    #[test]
+    fn test_parse_load_avg_happy_path() {
+        let stats = parse_load_avg_from_content("0.80 0.32 0.11 2/71 2891\n").unwrap();
+        assert_eq!(stats.load1, 0.80);
+        assert_eq!(stats.load5, 0.32);
+        assert_eq!(stats.load15, 0.11);
+        assert_eq!(stats.runnable, 2);
+        assert_eq!(stats.total_tasks, 71);
+        assert_eq!(stats.last_pid, 2891);
+    }
+
+    #[test]
+    fn test_parse_load_avg_errors() {
+        assert!(matches!(
+            parse_load_avg_from_content("0.80 0.32").unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+        assert!(matches!(
+            parse_load_avg_from_content("high 0.32 0.11 2/71 2891").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_load_avg_from_content("0.80 0.32 0.11 71 2891").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_procs_from_stat_content() {
+        let content = "cpu  1 2 3 4 5 6 7 8 9 10\n\
+                       cpu0 1 2 3 4 5 6 7 8 9 10\n\
+                       cpu2 1 2 3 4 5 6 7 8 9 10\n\
+                       ctxt 123456\n\
+                       procs_running 3\n\
+                       procs_blocked 1\n";
+        assert_eq!(parse_procs_from_stat_content(content).unwrap(), (3, 1, 2));
+
+        let result = parse_procs_from_stat_content("cpu  1 2 3 4 5 6 7 8 9 10\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_read_load_stats_from_paths() {
+        let mut loadavg = NamedTempFile::new().unwrap();
+        writeln!(loadavg, "4.00 2.00 1.00 5/300 12345").unwrap();
+        let mut stat = NamedTempFile::new().unwrap();
+        writeln!(stat, "procs_running 5\nprocs_blocked 2").unwrap();
+
+        let stats = read_load_stats_from_paths(loadavg.path(), stat.path()).unwrap();
+        assert_eq!(stats.load1, 4.0);
+        assert_eq!(stats.procs_running, 5);
+        assert_eq!(stats.procs_blocked, 2);
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("0\n").unwrap(), vec![0]);
+        assert_eq!(parse_cpu_list("0-3,8,10-11").unwrap(), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpu_list("").unwrap(), Vec::<u32>::new());
+        assert!(matches!(
+            parse_cpu_list("3-1").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_cpu_list("a-b").unwrap_err().root_cause(),
+            PipaCollectorError::Parse(_)
+        ));
+    }
+
+    #[test]
+    fn test_format_cpu_list() {
+        assert_eq!(format_cpu_list(&[0, 1, 2, 3, 8, 10, 11]), "0-3,8,10-11");
+        assert_eq!(format_cpu_list(&[5]), "5");
+        assert_eq!(format_cpu_list(&[]), "");
+        let cpus = parse_cpu_list("0-7,16-23").unwrap();
+        assert_eq!(parse_cpu_list(&format_cpu_list(&cpus)).unwrap(), cpus);
+    }
+
+    #[test]
+    fn test_read_online_cpu_count_from_path() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "0-7").unwrap();
+        assert_eq!(read_online_cpu_count_from_path(file.path()).unwrap(), 8);
+    }
+
+    /// Test sections for /proc/diskstats and /proc/net/dev
+    #[test]
+    fn test_parse_disk_stats() {
+        let content = "   7       0 loop0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0\n\
+                       259       0 nvme0n1 1000 10 80000 500 2000 20 160000 900 3 1200 1400 5 0 4096 2 100 30\n\
+                         8       0 sda 100 1 800 50 200 2 1600 90 0 120 140\n";
+        let disks = parse_disk_stats_from_content(content).unwrap();
+        assert_eq!(disks.len(), 3);
+
+        let nvme = &disks[1];
+        assert_eq!((nvme.major, nvme.minor, nvme.name.as_str()), (259, 0, "nvme0n1"));
+        assert_eq!(nvme.reads, 1000);
+        assert_eq!(nvme.sectors_written, 160000);
+        assert_eq!(nvme.in_flight, 3);
+        assert_eq!(nvme.weighted_io_ms, 1400);
+        assert_eq!(nvme.discards, 5);
+        assert_eq!(nvme.sectors_discarded, 4096);
+        assert_eq!(nvme.ios(), 3005);
+
+        // Pre-4.18 kernels have no discard columns.
+        assert_eq!(disks[2].io_ms, 120);
+        assert_eq!(disks[2].discards, 0);
+    }
+
+    #[test]
+    fn test_parse_disk_stats_errors() {
+        assert!(matches!(
+            parse_disk_stats_from_content("8 0 sda 1 2 3\n").unwrap_err().root_cause(),
+            PipaCollectorError::InvalidFormat(_)
+        ));
+        assert!(matches!(
+            parse_disk_stats_from_content("8 0 sda x 0 0 0 0 0 0 0 0 0 0\n")
+                .unwrap_err()
+                .root_cause(),
+            PipaCollectorError::Parse(_)
+        ));
+        let err = parse_disk_stats_from_content("8 0 sda 1 0 0 0 0 0 0 0 0 0 0\n\n8 1 sdb 1 2 3\n")
+            .unwrap_err();
+        assert!(err.to_string().starts_with("line 3: Invalid format"), "{}", err);
+    }
+
+    #[test]
+    fn test_disk_stats_delta() {
+        let earlier =
+            DiskStats { name: "sda".into(), reads: 10, in_flight: 4, ..Default::default() };
+        let later = DiskStats { name: "sda".into(), reads: 15, in_flight: 1, ..Default::default() };
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.reads, 5);
+        assert_eq!(delta.in_flight, 1);
+    }
+
+    const NET_DEV: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo: 29327526    3634    0    0    0     0          0         0 29327526    3634    0    0    0     0       0          0
+  eth0:12345678901 692    1    2    0     0          3         4    56899     641    5    6    0     0       0          7
+";
+
+    #[test]
+    fn test_parse_net_dev() {
+        let devices = parse_net_dev_from_content(NET_DEV).unwrap();
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].name, "lo");
+        assert_eq!(devices[0].rx_packets, 3634);
+
+        let eth0 = &devices[1];
+        assert_eq!(eth0.name, "eth0");
+        assert_eq!(eth0.rx_bytes, 12345678901);
+        assert_eq!((eth0.rx_errs, eth0.rx_drop), (1, 2));
+        assert_eq!((eth0.rx_compressed, eth0.rx_multicast), (3, 4));
+        assert_eq!((eth0.tx_bytes, eth0.tx_packets), (56899, 641));
+        assert_eq!((eth0.tx_errs, eth0.tx_drop, eth0.tx_compressed), (5, 6, 7));
+
+        let result = parse_net_dev_from_content("h1\nh2\n  eth0: 1 2 3\n");
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::InvalidFormat(_)));
+        let result = parse_net_dev_from_content("h1\nh2\n  eth0 1 2 3\n");
+        assert!(matches!(result.unwrap_err().root_cause(), PipaCollectorError::InvalidFormat(_)));
+    }
+
+    #[test]
+    fn test_read_net_dev_stats_with_speed() {
+        let dir = tempfile::tempdir().unwrap();
+        let net_dev = dir.path().join("dev");
+        std::fs::write(&net_dev, NET_DEV).unwrap();
+        let sys = dir.path().join("class_net");
+        std::fs::create_dir_all(sys.join("eth0")).unwrap();
+        std::fs::create_dir_all(sys.join("lo")).unwrap();
+        std::fs::write(sys.join("eth0/speed"), "10000\n").unwrap();
+        std::fs::write(sys.join("lo/speed"), "-1\n").unwrap();
+
+        let devices = read_net_dev_stats_from_paths(&net_dev, &sys).unwrap();
+        assert_eq!(devices[0].speed_mbps, None);
+        assert_eq!(devices[1].speed_mbps, Some(10000));
+
+        let earlier = NetDevStats { rx_bytes: 100, ..devices[1].clone() };
+        let later = NetDevStats { rx_bytes: 1100, ..devices[1].clone() };
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.rx_bytes, 1000);
+        assert_eq!(delta.tx_bytes, 0);
+        assert_eq!(delta.speed_mbps, Some(10000));
+    }
+
+    #[test]
+    fn test_readers_use_sysroot() {
+        let dir = fixture_tree(&[
+            ("proc/stat", "cpu  10 1 5 100 2 0 0 0 0 0\nprocs_running 3\nprocs_blocked 1\n"),
+            (
+                "proc/meminfo",
+                "MemTotal: 2048 kB\nMemFree: 1024 kB\nMemAvailable: 1536 kB\nBuffers: 0 kB\nCached: 0 kB\n",
+            ),
+            ("proc/loadavg", "0.50 0.40 0.30 3/200 4242\n"),
+            ("proc/vmstat", "pgpgin 0\npgpgout 0\npswpin 0\npswpout 0\npgfault 77\npgmajfault 0\n"),
+            ("proc/pressure/io", "some avg10=1.00 avg60=0.00 avg300=0.00 total=5\n"),
+            ("sys/devices/system/cpu/online", "0-3\n"),
+        ]);
+        let root = SysRoot::new(dir.path());
+
+        assert_eq!(read_cpu_stats(&root).unwrap().user, 10);
+        assert_eq!(read_memory_stats(&root).unwrap().available, 1536);
+        let load = read_load_stats(&root).unwrap();
+        assert_eq!((load.total_tasks, load.procs_blocked), (200, 1));
+        assert_eq!(read_vm_stats(&root).unwrap().pgfault, 77);
+        assert_eq!(read_pressure_stats(&root, PsiResource::Io).unwrap().some.total, 5);
+        assert_eq!(read_online_cpu_count(&root).unwrap(), 4);
+
+        // Files absent from the fixture are not read from the live system.
+        assert_eq!(read_disk_stats(&root).unwrap_err().code(), ErrorCode::NotFound);
     }
 }
@@ -0,0 +1,39 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers shared by the unit tests of this crate for building procfs and
+//! sysfs fixture trees.
+//!
+//! 本 crate 单元测试共用的辅助函数，用于构建 procfs 和 sysfs 夹具目录树。
+
+use std::path::Path;
+
+/// Writes `content` to `path`, creating its parent directories.
+/// 将 `content` 写入 `path`，并创建其父目录。
+pub(crate) fn write<P: AsRef<Path>>(path: P, content: &str) {
+    let path = path.as_ref();
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+/// Writes `files` (paths relative to the tree's root) into a new temporary
+/// directory.
+/// 将 `files`（路径相对于目录树根）写入一个新的临时目录。
+pub(crate) fn fixture_tree(files: &[(&str, &str)]) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (path, content) in files {
+        write(dir.path().join(path), content);
+    }
+    dir
+}
@@ -0,0 +1,472 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! This module discovers the CPU and NUMA topology from sysfs and parses the
+//! per-node `meminfo` and `numastat` files.
+//!
+//! 本模块从 sysfs 发现 CPU 与 NUMA 拓扑，并解析每个节点的 `meminfo` 和 `numastat` 文件。
+
+use crate::error::parse_file;
+use crate::sysroot::SysRoot;
+use crate::system_stats::{Delta, PipaCollectorError, numbered_entries, parse_cpu_list};
+use std::path::Path;
+
+/// The position of one logical CPU in the machine.
+///
+/// 单个逻辑 CPU 在机器中的位置。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct CpuTopology {
+    /// Logical CPU number. / 逻辑 CPU 编号。
+    pub cpu: u32,
+    /// `physical_package_id`, i.e. the socket. / 物理封装编号，即插槽。
+    pub package_id: u32,
+    /// `die_id` within the package; 0 on kernels without die support.
+    /// 封装内的 die 编号；在不支持 die 的内核上为 0。
+    pub die_id: u32,
+    /// `core_id`, unique only within its package and die.
+    /// 核心编号，仅在所属封装和 die 内唯一。
+    pub core_id: u32,
+    /// SMT siblings sharing the core, including this CPU.
+    /// 共享同一物理核心的 SMT 兄弟 CPU（包含本 CPU）。
+    pub thread_siblings: Vec<u32>,
+    /// NUMA node, or `None` when the kernel exposes no node information.
+    /// 所属 NUMA 节点；内核未提供节点信息时为 `None`。
+    pub node: Option<u32>,
+}
+
+/// One NUMA node. / 单个 NUMA 节点。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct NumaNode {
+    /// Node number. / 节点编号。
+    pub id: u32,
+    /// CPUs of the node (`cpulist`). Memory-only nodes have none.
+    /// 节点上的 CPU (`cpulist`)。仅含内存的节点没有 CPU。
+    pub cpus: Vec<u32>,
+    /// Relative access distance to every node, indexed by position in
+    /// [`Topology::nodes`]. / 到各节点的相对访问距离，按 [`Topology::nodes`] 中的位置索引。
+    pub distances: Vec<u32>,
+}
+
+/// The CPU and NUMA topology of the machine. Only online CPUs are listed.
+///
+/// 机器的 CPU 与 NUMA 拓扑，只列出在线 CPU。
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Topology {
+    /// Online CPUs sorted by number. / 按编号排序的在线 CPU。
+    pub cpus: Vec<CpuTopology>,
+    /// NUMA nodes sorted by number; empty on non-NUMA kernels.
+    /// 按编号排序的 NUMA 节点；在非 NUMA 内核上为空。
+    pub nodes: Vec<NumaNode>,
+}
+
+impl Topology {
+    /// Looks up a logical CPU. / 查找逻辑 CPU。
+    pub fn cpu(&self, cpu: u32) -> Option<&CpuTopology> {
+        self.cpus.iter().find(|c| c.cpu == cpu)
+    }
+
+    /// Number of distinct packages (sockets). / 不同封装（插槽）的数量。
+    pub fn package_count(&self) -> usize {
+        let mut packages: Vec<u32> = self.cpus.iter().map(|c| c.package_id).collect();
+        packages.sort_unstable();
+        packages.dedup();
+        packages.len()
+    }
+
+    /// Number of distinct physical cores. / 不同物理核心的数量。
+    pub fn core_count(&self) -> usize {
+        let mut cores: Vec<(u32, u32, u32)> =
+            self.cpus.iter().map(|c| (c.package_id, c.die_id, c.core_id)).collect();
+        cores.sort_unstable();
+        cores.dedup();
+        cores.len()
+    }
+}
+
+/// Reads a numeric sysfs attribute. / 读取数值型 sysfs 属性。
+fn read_u32(path: &Path) -> Result<u32, PipaCollectorError> {
+    parse_file(path, |content| content.trim().parse::<u32>())
+}
+
+/// Reads `/sys/devices/system/node/node*`. / 读取 `/sys/devices/system/node/node*`。
+fn read_nodes(node_root: &Path) -> Result<Vec<NumaNode>, PipaCollectorError> {
+    numbered_entries(node_root, "node")?
+        .into_iter()
+        .map(|id| {
+            let dir = node_root.join(format!("node{}", id));
+            let distances = parse_file(dir.join("distance"), |content| {
+                content.split_whitespace().map(|d| d.parse::<u32>()).collect::<Result<Vec<_>, _>>()
+            })?;
+            Ok(NumaNode { id, cpus: parse_file(dir.join("cpulist"), parse_cpu_list)?, distances })
+        })
+        .collect()
+}
+
+/// Reads the topology from a CPU sysfs root (normally
+/// `/sys/devices/system/cpu`) and a node root (normally
+/// `/sys/devices/system/node`). CPUs without a `topology` directory are
+/// offline and skipped.
+///
+/// 从 CPU sysfs 根目录（通常为 `/sys/devices/system/cpu`）和节点根目录
+///（通常为 `/sys/devices/system/node`）读取拓扑。没有 `topology` 目录的 CPU
+/// 处于离线状态，会被跳过。
+pub fn read_topology_from_paths<P: AsRef<Path>, Q: AsRef<Path>>(
+    cpu_root: P,
+    node_root: Q,
+) -> Result<Topology, PipaCollectorError> {
+    let cpu_root = cpu_root.as_ref();
+    let nodes = read_nodes(node_root.as_ref())?;
+
+    let mut cpus = Vec::new();
+    for cpu in numbered_entries(cpu_root, "cpu")? {
+        let dir = cpu_root.join(format!("cpu{}/topology", cpu));
+        if !dir.is_dir() {
+            continue;
+        }
+        let die_id = match read_u32(&dir.join("die_id")) {
+            Err(e) if e.is_not_found() => 0,
+            other => other?,
+        };
+        cpus.push(CpuTopology {
+            cpu,
+            package_id: read_u32(&dir.join("physical_package_id"))?,
+            die_id,
+            core_id: read_u32(&dir.join("core_id"))?,
+            thread_siblings: parse_file(dir.join("thread_siblings_list"), parse_cpu_list)?,
+            node: nodes.iter().find(|n| n.cpus.contains(&cpu)).map(|n| n.id),
+        });
+    }
+
+    if cpus.is_empty() {
+        return Err(PipaCollectorError::MissingData(format!(
+            "No CPU topology found under {}",
+            cpu_root.display()
+        )));
+    }
+    Ok(Topology { cpus, nodes })
+}
+
+/// Reads the topology of the running system.
+/// 读取当前系统的拓扑。
+pub fn read_topology(root: &SysRoot) -> Result<Topology, PipaCollectorError> {
+    read_topology_from_paths(root.sys("devices/system/cpu"), root.sys("devices/system/node"))
+}
+
+/// Selected fields of a node's `meminfo`, in kB (huge page fields are page
+/// counts).
+///
+/// 节点 `meminfo` 中的部分字段，单位为 kB（大页字段为页数）。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct NodeMemInfo {
+    /// Total memory of the node. / 节点总内存。
+    pub total: u64,
+    /// Free memory. / 空闲内存。
+    pub free: u64,
+    /// Used memory. / 已用内存。
+    pub used: u64,
+    /// Active pages. / 活跃页。
+    pub active: u64,
+    /// Inactive pages. / 非活跃页。
+    pub inactive: u64,
+    /// Page cache. / 页缓存。
+    pub file_pages: u64,
+    /// Anonymous pages. / 匿名页。
+    pub anon_pages: u64,
+    /// Shared memory. / 共享内存。
+    pub shmem: u64,
+    /// Dirty pages. / 脏页。
+    pub dirty: u64,
+    /// Slab. / Slab。
+    pub slab: u64,
+    /// Total huge pages. / 大页总数。
+    pub huge_pages_total: u64,
+    /// Free huge pages. / 空闲大页数。
+    pub huge_pages_free: u64,
+}
+
+/// Parses a node `meminfo` file, whose lines look like
+/// `Node 0 MemTotal:  5734136 kB`.
+///
+/// 解析节点 `meminfo` 文件，其行格式如 `Node 0 MemTotal:  5734136 kB`。
+fn parse_node_meminfo_from_content(content: &str) -> Result<NodeMemInfo, PipaCollectorError> {
+    let mut info = NodeMemInfo::default();
+    let mut found_count = 0;
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some("Node"), Some(_), Some(key), Some(value)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let field = match key.trim_end_matches(':') {
+            "MemTotal" => &mut info.total,
+            "MemFree" => &mut info.free,
+            "MemUsed" => &mut info.used,
+            "Active" => &mut info.active,
+            "Inactive" => &mut info.inactive,
+            "FilePages" => &mut info.file_pages,
+            "AnonPages" => &mut info.anon_pages,
+            "Shmem" => &mut info.shmem,
+            "Dirty" => &mut info.dirty,
+            "Slab" => &mut info.slab,
+            "HugePages_Total" => &mut info.huge_pages_total,
+            "HugePages_Free" => &mut info.huge_pages_free,
+            _ => continue,
+        };
+        *field = value.parse::<u64>()?;
+        found_count += 1;
+    }
+
+    if found_count < 3 || info.total == 0 {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find MemTotal, MemFree and MemUsed in node meminfo".to_string(),
+        ));
+    }
+    Ok(info)
+}
+
+/// Per-node allocation counters from `numastat`, in pages.
+///
+/// 来自 `numastat` 的按节点分配计数，单位为页。
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct NumaStat {
+    /// Allocations satisfied on the intended node. / 在预期节点上满足的分配。
+    pub numa_hit: u64,
+    /// Allocations that fell back to this node. / 回退到本节点的分配。
+    pub numa_miss: u64,
+    /// Allocations intended for this node that went elsewhere.
+    /// 本应在本节点但落到其他节点的分配。
+    pub numa_foreign: u64,
+    /// Interleave-policy allocations on this node. / 本节点上交错策略的分配。
+    pub interleave_hit: u64,
+    /// Allocations by processes running on this node. / 本节点上运行的进程的分配。
+    pub local_node: u64,
+    /// Allocations by processes running on other nodes. / 其他节点上运行的进程的分配。
+    pub other_node: u64,
+}
+
+impl Delta for NumaStat {
+    fn delta(&self, earlier: &Self) -> Self {
+        NumaStat {
+            numa_hit: self.numa_hit.saturating_sub(earlier.numa_hit),
+            numa_miss: self.numa_miss.saturating_sub(earlier.numa_miss),
+            numa_foreign: self.numa_foreign.saturating_sub(earlier.numa_foreign),
+            interleave_hit: self.interleave_hit.saturating_sub(earlier.interleave_hit),
+            local_node: self.local_node.saturating_sub(earlier.local_node),
+            other_node: self.other_node.saturating_sub(earlier.other_node),
+        }
+    }
+}
+
+/// Parses a node `numastat` file. / 解析节点 `numastat` 文件。
+fn parse_numastat_from_content(content: &str) -> Result<NumaStat, PipaCollectorError> {
+    let mut stat = NumaStat::default();
+    let mut found_count = 0;
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(key), Some(value)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let field = match key {
+            "numa_hit" => &mut stat.numa_hit,
+            "numa_miss" => &mut stat.numa_miss,
+            "numa_foreign" => &mut stat.numa_foreign,
+            "interleave_hit" => &mut stat.interleave_hit,
+            "local_node" => &mut stat.local_node,
+            "other_node" => &mut stat.other_node,
+            _ => continue,
+        };
+        *field = value.parse::<u64>()?;
+        found_count += 1;
+    }
+
+    if found_count < 6 {
+        return Err(PipaCollectorError::MissingData(
+            "Could not find all required fields in numastat".to_string(),
+        ));
+    }
+    Ok(stat)
+}
+
+/// Reads a node `meminfo` file from the given path.
+/// 从指定路径读取节点 `meminfo` 文件。
+pub fn read_node_meminfo_from_path<P: AsRef<Path>>(
+    path: P,
+) -> Result<NodeMemInfo, PipaCollectorError> {
+    parse_file(path, parse_node_meminfo_from_content)
+}
+
+/// Reads a node `numastat` file from the given path.
+/// 从指定路径读取节点 `numastat` 文件。
+pub fn read_numastat_from_path<P: AsRef<Path>>(path: P) -> Result<NumaStat, PipaCollectorError> {
+    parse_file(path, parse_numastat_from_content)
+}
+
+/// Reads `/sys/devices/system/node/node<node>/meminfo`.
+/// 读取 `/sys/devices/system/node/node<node>/meminfo`。
+pub fn read_node_meminfo(root: &SysRoot, node: u32) -> Result<NodeMemInfo, PipaCollectorError> {
+    read_node_meminfo_from_path(root.sys(format!("devices/system/node/node{}/meminfo", node)))
+}
+
+/// Reads `/sys/devices/system/node/node<node>/numastat`.
+/// 读取 `/sys/devices/system/node/node<node>/numastat`。
+pub fn read_numastat(root: &SysRoot, node: u32) -> Result<NumaStat, PipaCollectorError> {
+    read_numastat_from_path(root.sys(format!("devices/system/node/node{}/numastat", node)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::write;
+
+    /// Builds a 2-socket, 2-core-per-socket, 2-way SMT machine (8 CPUs) with
+    /// one node per socket. CPU7 is offline.
+    fn fixture() -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        for cpu in 0..8u32 {
+            let dir = root.path().join(format!("cpu/cpu{}", cpu));
+            std::fs::create_dir_all(&dir).unwrap();
+            if cpu == 7 {
+                continue;
+            }
+            // Siblings are N and N+4, as enumerated by most x86 firmware.
+            let (package, core) = ((cpu % 4) / 2, cpu % 2);
+            let topology = dir.join("topology");
+            write(topology.join("physical_package_id"), &format!("{}\n", package));
+            write(topology.join("die_id"), "0\n");
+            write(topology.join("core_id"), &format!("{}\n", core));
+            write(topology.join("thread_siblings_list"), &format!("{},{}\n", cpu % 4, cpu % 4 + 4));
+        }
+        write(root.path().join("node/node0/cpulist"), "0-1,4-5\n");
+        write(root.path().join("node/node0/distance"), "10 21\n");
+        write(root.path().join("node/node1/cpulist"), "2-3,6-7\n");
+        write(root.path().join("node/node1/distance"), "21 10\n");
+        root
+    }
+
+    #[test]
+    fn test_read_topology_from_paths() {
+        let root = fixture();
+        let topology =
+            read_topology_from_paths(root.path().join("cpu"), root.path().join("node")).unwrap();
+
+        assert_eq!(topology.cpus.len(), 7);
+        assert_eq!(
+            topology.cpu(6).unwrap(),
+            &CpuTopology {
+                cpu: 6,
+                package_id: 1,
+                die_id: 0,
+                core_id: 0,
+                thread_siblings: vec![2, 6],
+                node: Some(1),
+            }
+        );
+        assert!(topology.cpu(7).is_none());
+        assert_eq!(topology.package_count(), 2);
+        assert_eq!(topology.core_count(), 4);
+
+        assert_eq!(topology.nodes.len(), 2);
+        assert_eq!(topology.nodes[0].cpus, vec![0, 1, 4, 5]);
+        assert_eq!(topology.nodes[1].distances, vec![21, 10]);
+    }
+
+    #[test]
+    fn test_read_topology_without_numa_or_die() {
+        let root = fixture();
+        std::fs::remove_file(root.path().join("cpu/cpu0/topology/die_id")).unwrap();
+        let topology =
+            read_topology_from_paths(root.path().join("cpu"), root.path().join("missing")).unwrap();
+        assert!(topology.nodes.is_empty());
+        assert_eq!(topology.cpu(0).unwrap().die_id, 0);
+        assert!(topology.cpus.iter().all(|c| c.node.is_none()));
+    }
+
+    #[test]
+    fn test_read_topology_errors() {
+        let root = fixture();
+        let empty = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            read_topology_from_paths(empty.path(), root.path().join("node")).unwrap_err(),
+            PipaCollectorError::MissingData(_)
+        ));
+
+        write(root.path().join("cpu/cpu1/topology/core_id"), "x\n");
+        assert!(matches!(
+            read_topology_from_paths(root.path().join("cpu"), root.path().join("node"))
+                .unwrap_err()
+                .root_cause(),
+            PipaCollectorError::Parse(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_node_meminfo() {
+        let content = "Node 1 MemTotal:       65536000 kB\n\
+                       Node 1 MemFree:        32768000 kB\n\
+                       Node 1 MemUsed:        32768000 kB\n\
+                       Node 1 Active(anon):       1000 kB\n\
+                       Node 1 Active:          8000000 kB\n\
+                       Node 1 Inactive:        4000000 kB\n\
+                       Node 1 FilePages:      10000000 kB\n\
+                       Node 1 AnonPages:       2000000 kB\n\
+                       Node 1 Slab:             300000 kB\n\
+                       Node 1 HugePages_Total:    512\n\
+                       Node 1 HugePages_Free:     128\n";
+        let info = parse_node_meminfo_from_content(content).unwrap();
+        assert_eq!(info.total, 65536000);
+        assert_eq!(info.used, 32768000);
+        assert_eq!(info.active, 8000000);
+        assert_eq!(info.file_pages, 10000000);
+        assert_eq!(info.huge_pages_total, 512);
+        assert_eq!(info.huge_pages_free, 128);
+
+        let result = parse_node_meminfo_from_content("Node 0 MemFree: 1 kB\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_parse_numastat_and_delta() {
+        let content = "numa_hit 6244100\nnuma_miss 10\nnuma_foreign 20\n\
+                       interleave_hit 1017\nlocal_node 6244000\nother_node 100\n";
+        let earlier = parse_numastat_from_content(content).unwrap();
+        assert_eq!(earlier.numa_hit, 6244100);
+        assert_eq!(earlier.other_node, 100);
+
+        let later = NumaStat { numa_hit: 6244600, other_node: 150, ..earlier };
+        let delta = later.delta(&earlier);
+        assert_eq!(delta.numa_hit, 500);
+        assert_eq!(delta.other_node, 50);
+        assert_eq!(delta.numa_miss, 0);
+
+        let result = parse_numastat_from_content("numa_hit 1\n");
+        assert!(matches!(result.unwrap_err(), PipaCollectorError::MissingData(_)));
+    }
+
+    #[test]
+    fn test_read_node_files_from_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let meminfo = dir.path().join("meminfo");
+        std::fs::write(
+            &meminfo,
+            "Node 0 MemTotal: 4 kB\nNode 0 MemFree: 1 kB\nNode 0 MemUsed: 3 kB\n",
+        )
+        .unwrap();
+        assert_eq!(read_node_meminfo_from_path(&meminfo).unwrap().used, 3);
+        assert!(matches!(
+            read_numastat_from_path(dir.path().join("numastat")).unwrap_err().root_cause(),
+            PipaCollectorError::Io(_)
+        ));
+    }
+}
@@ -0,0 +1,614 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kernel tracepoints through tracefs: resolving `subsystem:event` names to
+//! the ids used as `PERF_TYPE_TRACEPOINT` configs, and decoding the raw
+//! sample payload with the layout described by the event's `format` file.
+//!
+//! 通过 tracefs 使用内核跟踪点：将 `subsystem:event` 名称解析为
+//! `PERF_TYPE_TRACEPOINT` 所用的 id，并按照事件 `format` 文件描述的布局
+//! 解码原始采样负载。
+
+use crate::error::{ResultExt, parse_file};
+use crate::sysroot::SysRoot;
+use crate::system_stats::PipaCollectorError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A tracepoint name such as `sched:sched_switch`.
+/// 跟踪点名称，例如 `sched:sched_switch`。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TracepointName {
+    pub subsystem: String,
+    pub event: String,
+}
+
+impl FromStr for TracepointName {
+    type Err = PipaCollectorError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let valid = |part: &str| !part.is_empty() && !part.contains(['/', ':']);
+        let (subsystem, event) =
+            name.split_once(':').filter(|(s, e)| valid(s) && valid(e)).ok_or_else(|| {
+                PipaCollectorError::InvalidFormat(format!(
+                    "Invalid tracepoint `{}` (expected subsystem:event)",
+                    name
+                ))
+            })?;
+        Ok(TracepointName { subsystem: subsystem.to_string(), event: event.to_string() })
+    }
+}
+
+impl fmt::Display for TracepointName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.subsystem, self.event)
+    }
+}
+
+/// A mounted tracefs. / 已挂载的 tracefs。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceFs {
+    root: PathBuf,
+}
+
+impl TraceFs {
+    /// Uses tracefs mounted at `root`. / 使用挂载在 `root` 的 tracefs。
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        TraceFs { root: root.into() }
+    }
+
+    /// Finds tracefs under sysfs: `kernel/tracing`, or the older
+    /// `kernel/debug/tracing` inside debugfs.
+    ///
+    /// 在 sysfs 下查找 tracefs：`kernel/tracing`，或较旧的位于 debugfs 中的
+    /// `kernel/debug/tracing`。
+    pub fn locate(root: &SysRoot) -> Result<Self, PipaCollectorError> {
+        ["kernel/tracing", "kernel/debug/tracing"]
+            .into_iter()
+            .map(|dir| root.sys(dir))
+            .find(|dir| dir.join("events").is_dir())
+            .map(TraceFs::new)
+            .ok_or_else(|| {
+                PipaCollectorError::Unsupported(format!(
+                    "tracefs is not mounted under {}; mount it with \
+                     `mount -t tracefs nodev /sys/kernel/tracing`",
+                    root.sys("kernel/tracing").display()
+                ))
+            })
+    }
+
+    /// The tracefs mount point. / tracefs 的挂载点。
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn event_dir(&self, name: &TracepointName) -> Result<PathBuf, PipaCollectorError> {
+        let dir = self.root.join("events").join(&name.subsystem).join(&name.event);
+        if !dir.is_dir() {
+            return Err(PipaCollectorError::EventNotSupported(format!(
+                "unknown tracepoint `{}` (see {})",
+                name,
+                self.root.join("available_events").display()
+            )));
+        }
+        Ok(dir)
+    }
+
+    /// Reads the id used as the `config` of a `PERF_TYPE_TRACEPOINT` event.
+    /// 读取用作 `PERF_TYPE_TRACEPOINT` 事件 `config` 的 id。
+    pub fn event_id(&self, name: &TracepointName) -> Result<u64, PipaCollectorError> {
+        parse_file(self.event_dir(name)?.join("id"), |content| content.trim().parse::<u64>())
+    }
+
+    /// Reads and parses the `format` file of a tracepoint.
+    /// 读取并解析跟踪点的 `format` 文件。
+    pub fn event_format(
+        &self,
+        name: &TracepointName,
+    ) -> Result<TracepointFormat, PipaCollectorError> {
+        parse_file(self.event_dir(name)?.join("format"), parse_format)
+    }
+}
+
+/// How a field is laid out in the payload. / 字段在负载中的布局方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldKind {
+    /// A single integer or pointer. / 单个整数或指针。
+    Scalar,
+    /// A NUL-padded `char name[N]`. / 以 NUL 填充的 `char name[N]`。
+    String,
+    /// A fixed-size array of integers. / 定长整数数组。
+    Array { len: usize },
+    /// `__data_loc type[] name`: a `u32` holding the offset (low 16 bits,
+    /// from the start of the payload) and length (high 16 bits) of the data.
+    /// `__data_loc type[] name`：一个 `u32`，低 16 位为数据偏移（相对负载起点），
+    /// 高 16 位为数据长度。
+    DataLoc,
+    /// `__rel_loc type[] name`: like `DataLoc`, but the offset counts from
+    /// the end of the field. / 与 `DataLoc` 类似，但偏移从该字段末尾起算。
+    RelLoc,
+}
+
+/// One field of a tracepoint format. / 跟踪点格式中的一个字段。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormatField {
+    pub name: String,
+    /// The C type without the name, e.g. `char[16]` or `const char *`.
+    /// 不含名称的 C 类型，例如 `char[16]` 或 `const char *`。
+    pub type_name: String,
+    pub offset: usize,
+    pub size: usize,
+    pub signed: bool,
+    pub kind: FieldKind,
+}
+
+impl FormatField {
+    /// Whether the element type is `char`, i.e. the field holds text.
+    /// 元素类型是否为 `char`，即字段是否保存文本。
+    fn is_char(&self) -> bool {
+        let element = self.type_name.split('[').next().unwrap_or_default();
+        element.trim_start_matches("const ").trim() == "char"
+    }
+
+    /// Parses a `field:<decl>;` declaration, e.g. `char prev_comm[16]`. An
+    /// array dimension that is not a number, as in
+    /// `__u8 saddr[sizeof(struct sockaddr_in6)]`, is taken to be `size` bytes.
+    /// 解析 `field:<decl>;` 声明，例如 `char prev_comm[16]`。数组维度不是数字时
+    /// （如 `__u8 saddr[sizeof(struct sockaddr_in6)]`），视为 `size` 个字节。
+    fn from_declaration(
+        declaration: &str,
+        offset: usize,
+        size: usize,
+        signed: bool,
+    ) -> Result<Self, PipaCollectorError> {
+        let invalid =
+            || PipaCollectorError::InvalidFormat(format!("Invalid field `{}`", declaration));
+        let declaration = declaration.trim();
+        // The dimension may hold spaces and brackets of its own, so it runs
+        // from the `[` that matches the last `]`.
+        // 维度本身可能包含空格和方括号，因此从与最后一个 `]` 匹配的 `[` 开始。
+        let (head, array) = match declaration.strip_suffix(']') {
+            Some(inner) => {
+                let mut depth = 0;
+                let open = inner
+                    .char_indices()
+                    .rev()
+                    .find(|&(_, c)| {
+                        match c {
+                            ']' => depth += 1,
+                            '[' if depth == 0 => return true,
+                            '[' => depth -= 1,
+                            _ => {}
+                        }
+                        false
+                    })
+                    .ok_or_else(invalid)?
+                    .0;
+                (&inner[..open], Some(&inner[open + 1..]))
+            }
+            None => (declaration, None),
+        };
+        let (prefix, name) =
+            head.trim_end().rsplit_once(char::is_whitespace).ok_or_else(invalid)?;
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(invalid());
+        }
+        let mut type_name = prefix.trim().to_string();
+        let kind = if let Some(element) = type_name.strip_prefix("__data_loc ") {
+            type_name = element.trim().to_string();
+            FieldKind::DataLoc
+        } else if let Some(element) = type_name.strip_prefix("__rel_loc ") {
+            type_name = element.trim().to_string();
+            FieldKind::RelLoc
+        } else if let Some(len) = array {
+            type_name = format!("{}[{}]", type_name, len);
+            FieldKind::Array { len: len.trim().parse().unwrap_or(size) }
+        } else {
+            FieldKind::Scalar
+        };
+        let mut field =
+            FormatField { name: name.to_string(), type_name, offset, size, signed, kind };
+        if matches!(field.kind, FieldKind::Array { .. }) && field.is_char() {
+            field.kind = FieldKind::String;
+        }
+        Ok(field)
+    }
+}
+
+/// The parsed `format` file of a tracepoint. / 解析后的跟踪点 `format` 文件。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TracepointFormat {
+    pub name: String,
+    pub id: u64,
+    /// Fields shared by every event (`common_type`, `common_pid`, ...).
+    /// 所有事件共有的字段（`common_type`、`common_pid` 等）。
+    pub common_fields: Vec<FormatField>,
+    /// The fields specific to this event. / 该事件特有的字段。
+    pub fields: Vec<FormatField>,
+}
+
+/// A decoded field value. / 解码后的字段值。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Int(i64),
+    Uint(u64),
+    /// A pointer, shown in hex. / 指针，以十六进制显示。
+    Pointer(u64),
+    Str(String),
+    Array(Vec<FieldValue>),
+    /// Dynamic data of a non-`char` type. / 非 `char` 类型的动态数据。
+    Bytes(Vec<u8>),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Int(v) => write!(f, "{}", v),
+            FieldValue::Uint(v) => write!(f, "{}", v),
+            FieldValue::Pointer(v) => write!(f, "{:#x}", v),
+            FieldValue::Str(s) => write!(f, "{}", s),
+            FieldValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(","))
+            }
+            FieldValue::Bytes(bytes) => bytes.iter().try_for_each(|b| write!(f, "{:02x}", b)),
+        }
+    }
+}
+
+/// Reads a native-endian integer of 1, 2, 4 or 8 bytes.
+/// 读取 1、2、4 或 8 字节的本机字节序整数。
+fn read_int(bytes: &[u8], signed: bool) -> Option<FieldValue> {
+    let value = match *bytes {
+        [a] if signed => FieldValue::Int(a as i8 as i64),
+        [a] => FieldValue::Uint(a as u64),
+        [a, b] if signed => FieldValue::Int(i16::from_ne_bytes([a, b]) as i64),
+        [a, b] => FieldValue::Uint(u16::from_ne_bytes([a, b]) as u64),
+        _ if bytes.len() == 4 => {
+            let v = u32::from_ne_bytes(bytes.try_into().ok()?);
+            if signed { FieldValue::Int(v as i32 as i64) } else { FieldValue::Uint(v as u64) }
+        }
+        _ if bytes.len() == 8 => {
+            let v = u64::from_ne_bytes(bytes.try_into().ok()?);
+            if signed { FieldValue::Int(v as i64) } else { FieldValue::Uint(v) }
+        }
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Text up to the first NUL. / 截至第一个 NUL 的文本。
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+impl TracepointFormat {
+    /// Looks up a field, common or event-specific. / 查找字段（通用字段或事件特有字段）。
+    pub fn field(&self, name: &str) -> Option<&FormatField> {
+        self.common_fields.iter().chain(&self.fields).find(|f| f.name == name)
+    }
+
+    /// Decodes one field from a raw payload. / 从原始负载中解码一个字段。
+    pub fn decode_field(
+        &self,
+        field: &FormatField,
+        raw: &[u8],
+    ) -> Result<FieldValue, PipaCollectorError> {
+        let truncated = || {
+            PipaCollectorError::InvalidFormat(format!(
+                "{}: payload of {} bytes is too short for field `{}`",
+                self.name,
+                raw.len(),
+                field.name
+            ))
+        };
+        let bytes = raw.get(field.offset..field.offset + field.size).ok_or_else(truncated)?;
+        let value = match field.kind {
+            // Structs and wide integers are shown as raw bytes.
+            // 结构体和宽整数以原始字节显示。
+            FieldKind::Scalar => match read_int(bytes, field.signed) {
+                Some(FieldValue::Uint(v)) if field.type_name.contains('*') => {
+                    FieldValue::Pointer(v)
+                }
+                Some(value) => value,
+                None => FieldValue::Bytes(bytes.to_vec()),
+            },
+            FieldKind::String => FieldValue::Str(c_string(bytes)),
+            FieldKind::Array { len } => {
+                let width = field.size.checked_div(len).unwrap_or(0);
+                if width == 0 {
+                    return Ok(FieldValue::Array(Vec::new()));
+                }
+                let elements: Option<Vec<FieldValue>> =
+                    bytes.chunks_exact(width).map(|chunk| read_int(chunk, field.signed)).collect();
+                match elements {
+                    Some(elements) => FieldValue::Array(elements),
+                    None => FieldValue::Bytes(bytes.to_vec()),
+                }
+            }
+            FieldKind::DataLoc | FieldKind::RelLoc => {
+                let loc = u32::from_ne_bytes(bytes.try_into().map_err(|_| truncated())?);
+                let mut start = (loc & 0xffff) as usize;
+                if field.kind == FieldKind::RelLoc {
+                    start += field.offset + field.size;
+                }
+                let data = raw.get(start..start + (loc >> 16) as usize).ok_or_else(truncated)?;
+                if field.is_char() {
+                    FieldValue::Str(c_string(data))
+                } else {
+                    FieldValue::Bytes(data.to_vec())
+                }
+            }
+        };
+        Ok(value)
+    }
+
+    /// Decodes the event-specific fields of a raw payload, in format order.
+    /// 按格式顺序解码原始负载中事件特有的字段。
+    pub fn decode(&self, raw: &[u8]) -> Result<Vec<(&str, FieldValue)>, PipaCollectorError> {
+        self.fields
+            .iter()
+            .map(|field| Ok((field.name.as_str(), self.decode_field(field, raw)?)))
+            .collect()
+    }
+}
+
+/// Parses a tracefs `format` file. / 解析 tracefs 的 `format` 文件。
+pub fn parse_format(content: &str) -> Result<TracepointFormat, PipaCollectorError> {
+    let mut name = None;
+    let mut id = None;
+    let mut common_fields = Vec::new();
+    let mut fields = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("name:") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("ID:") {
+            id = Some(value.trim().parse::<u64>().at_line(i + 1)?);
+        } else if let Some(rest) = line.strip_prefix("field:") {
+            let field = parse_field_line(rest).at_line(i + 1)?;
+            if field.name.starts_with("common_") {
+                common_fields.push(field);
+            } else {
+                fields.push(field);
+            }
+        }
+    }
+    Ok(TracepointFormat {
+        name: name.ok_or_else(|| PipaCollectorError::MissingData("format has no name".into()))?,
+        id: id.ok_or_else(|| PipaCollectorError::MissingData("format has no ID".into()))?,
+        common_fields,
+        fields,
+    })
+}
+
+/// Parses `<decl>;\toffset:N;\tsize:N;\tsigned:N;` (after `field:`).
+/// 解析 `<decl>;\toffset:N;\tsize:N;\tsigned:N;`（位于 `field:` 之后）。
+fn parse_field_line(rest: &str) -> Result<FormatField, PipaCollectorError> {
+    let mut parts = rest.split(';').map(str::trim);
+    let declaration = parts.next().unwrap_or_default();
+    let (mut offset, mut size, mut signed) = (None, None, false);
+    for part in parts {
+        match part.split_once(':') {
+            Some(("offset", v)) => offset = Some(v.parse::<usize>()?),
+            Some(("size", v)) => size = Some(v.parse::<usize>()?),
+            Some(("signed", v)) => signed = v == "1",
+            _ => {}
+        }
+    }
+    let missing = |what: &str| {
+        PipaCollectorError::MissingData(format!("field `{}` has no {}", declaration, what))
+    };
+    FormatField::from_declaration(
+        declaration,
+        offset.ok_or_else(|| missing("offset"))?,
+        size.ok_or_else(|| missing("size"))?,
+        signed,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    /// Captured from `events/sched/sched_switch/format` on Linux 6.18.
+    /// 采集自 Linux 6.18 的 `events/sched/sched_switch/format`。
+    const SCHED_SWITCH: &str = "name: sched_switch
+ID: 372
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:unsigned char common_flags;\toffset:2;\tsize:1;\tsigned:0;
+\tfield:unsigned char common_preempt_count;\toffset:3;\tsize:1;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:char prev_comm[16];\toffset:8;\tsize:16;\tsigned:0;
+\tfield:pid_t prev_pid;\toffset:24;\tsize:4;\tsigned:1;
+\tfield:int prev_prio;\toffset:28;\tsize:4;\tsigned:1;
+\tfield:long prev_state;\toffset:32;\tsize:8;\tsigned:1;
+\tfield:char next_comm[16];\toffset:40;\tsize:16;\tsigned:0;
+\tfield:pid_t next_pid;\toffset:56;\tsize:4;\tsigned:1;
+\tfield:int next_prio;\toffset:60;\tsize:4;\tsigned:1;
+
+print fmt: \"prev_comm=%s prev_pid=%d prev_prio=%d ==> next_comm=%s next_pid=%d next_prio=%d\", REC->prev_comm, REC->prev_pid, REC->prev_prio, REC->next_comm, REC->next_pid, REC->next_prio
+";
+
+    /// Captured from `events/syscalls/sys_enter_openat/format` on Linux 6.18.
+    /// 采集自 Linux 6.18 的 `events/syscalls/sys_enter_openat/format`。
+    const SYS_ENTER_OPENAT: &str = "name: sys_enter_openat
+ID: 782
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:unsigned char common_flags;\toffset:2;\tsize:1;\tsigned:0;
+\tfield:unsigned char common_preempt_count;\toffset:3;\tsize:1;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:int __syscall_nr;\toffset:8;\tsize:4;\tsigned:1;
+\tfield:int dfd;\toffset:16;\tsize:8;\tsigned:0;
+\tfield:const char * filename;\toffset:24;\tsize:8;\tsigned:0;
+\tfield:int flags;\toffset:32;\tsize:8;\tsigned:0;
+\tfield:umode_t mode;\toffset:40;\tsize:8;\tsigned:0;
+
+print fmt: \"dfd: 0x%08lx, filename: 0x%08lx, flags: 0x%08lx, mode: 0x%08lx\", ((unsigned long)(REC->dfd)), ((unsigned long)(REC->filename)), ((unsigned long)(REC->flags)), ((unsigned long)(REC->mode))
+";
+
+    /// Captured from `events/sched/sched_process_exec/format` on Linux 6.18.
+    /// 采集自 Linux 6.18 的 `events/sched/sched_process_exec/format`。
+    const SCHED_PROCESS_EXEC: &str = "name: sched_process_exec
+ID: 365
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:unsigned char common_flags;\toffset:2;\tsize:1;\tsigned:0;
+\tfield:unsigned char common_preempt_count;\toffset:3;\tsize:1;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:__data_loc char[] filename;\toffset:8;\tsize:4;\tsigned:0;
+\tfield:pid_t pid;\toffset:12;\tsize:4;\tsigned:1;
+\tfield:pid_t old_pid;\toffset:16;\tsize:4;\tsigned:1;
+
+print fmt: \"filename=%s pid=%d old_pid=%d\", __get_str(filename), REC->pid, REC->old_pid
+";
+
+    /// Decodes hex written as in `pipa record` files. / 解码 `pipa record` 文件中的十六进制。
+    fn hex(s: &str) -> Vec<u8> {
+        let s: String = s.split_whitespace().collect();
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_parse_format() {
+        let format = parse_format(SCHED_SWITCH).unwrap();
+        assert_eq!((format.name.as_str(), format.id), ("sched_switch", 372));
+        assert_eq!(format.common_fields.len(), 4);
+        assert_eq!(format.fields.len(), 7);
+        let prev_comm = format.field("prev_comm").unwrap();
+        assert_eq!(prev_comm.kind, FieldKind::String);
+        assert_eq!(prev_comm.type_name, "char[16]");
+        assert_eq!((prev_comm.offset, prev_comm.size), (8, 16));
+        assert!(format.field("prev_state").unwrap().signed);
+
+        let openat = parse_format(SYS_ENTER_OPENAT).unwrap();
+        let filename = openat.field("filename").unwrap();
+        assert_eq!(
+            (filename.type_name.as_str(), filename.kind),
+            ("const char *", FieldKind::Scalar)
+        );
+
+        let exec = parse_format(SCHED_PROCESS_EXEC).unwrap();
+        let filename = exec.field("filename").unwrap();
+        assert_eq!((filename.type_name.as_str(), filename.kind), ("char[]", FieldKind::DataLoc));
+
+        let array = FormatField::from_declaration("__u8 src[16]", 18, 16, false).unwrap();
+        assert_eq!(array.kind, FieldKind::Array { len: 16 });
+        // From `sock:inet_sock_set_state`. / 取自 `sock:inet_sock_set_state`。
+        let saddr =
+            FormatField::from_declaration("__u8 saddr[sizeof(struct sockaddr_in6)]", 28, 28, false)
+                .unwrap();
+        assert_eq!(saddr.name, "saddr");
+        assert_eq!(saddr.type_name, "__u8[sizeof(struct sockaddr_in6)]");
+        assert_eq!(saddr.kind, FieldKind::Array { len: 28 });
+        assert!(FormatField::from_declaration("int a[", 0, 4, true).is_err());
+        assert!(FormatField::from_declaration("x]", 0, 4, true).is_err());
+
+        let err =
+            parse_format("name: x\nID: 1\n\tfield:int a;\toffset:0;\tsigned:1;\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 3: Missing data"), "{}", err);
+        assert!(parse_format("ID: 1\n").is_err());
+    }
+
+    #[test]
+    fn test_decode_sched_switch() {
+        let format = parse_format(SCHED_SWITCH).unwrap();
+        // Captured by `pipa record -e sched:sched_switch -- sleep 0.01`: `sleep`
+        // blocks and switches back to `pipa_rs`.
+        // 由 `pipa record -e sched:sched_switch -- sleep 0.01` 捕获：`sleep` 阻塞后切换回 `pipa_rs`。
+        let raw = hex("74010103d5190000 736c656570000000 0000000000000000 d5190000 78000000 \
+                       0100000000000000 706970615f727300 0000000000000000 d4190000 78000000 \
+                       00000000");
+        let fields = format.decode(&raw).unwrap();
+        let get = |name: &str| fields.iter().find(|(n, _)| *n == name).unwrap().1.clone();
+        assert_eq!(get("prev_comm"), FieldValue::Str("sleep".into()));
+        assert_eq!(get("prev_pid"), FieldValue::Int(6613));
+        assert_eq!(get("prev_state"), FieldValue::Int(1));
+        assert_eq!(get("next_comm"), FieldValue::Str("pipa_rs".into()));
+        assert_eq!(get("next_pid"), FieldValue::Int(6612));
+        assert_eq!(get("next_prio"), FieldValue::Int(120));
+        assert_eq!(
+            format.decode_field(format.field("common_type").unwrap(), &raw).unwrap(),
+            FieldValue::Uint(372)
+        );
+
+        let err = format.decode(&raw[..40]).unwrap_err();
+        assert!(err.to_string().contains("too short for field `next_comm`"), "{}", err);
+    }
+
+    #[test]
+    fn test_decode_dynamic_and_pointer_fields() {
+        let openat = parse_format(SYS_ENTER_OPENAT).unwrap();
+        let raw = hex("0e03 00 00 39300000 01010000 00000000 \
+                       9cffffffffffffff 10e2ffffff7f0000 0000080000000000 0000000000000000");
+        let fields = openat.decode(&raw).unwrap();
+        assert_eq!(fields[0], ("__syscall_nr", FieldValue::Int(257)));
+        assert_eq!(fields[1], ("dfd", FieldValue::Uint(0xffff_ffff_ffff_ff9c)));
+        assert_eq!(fields[2], ("filename", FieldValue::Pointer(0x7fff_ffff_e210)));
+        assert_eq!(fields[2].1.to_string(), "0x7fffffffe210");
+        assert_eq!(fields[3], ("flags", FieldValue::Uint(0x80000)));
+
+        let exec = parse_format(SCHED_PROCESS_EXEC).unwrap();
+        // filename: 9 bytes at offset 20. / filename：位于偏移 20 处的 9 字节。
+        let raw = hex("6d01 00 00 39300000 14000900 39300000 39300000 2f62696e2f6c730000000000");
+        let fields = exec.decode(&raw).unwrap();
+        assert_eq!(fields[0], ("filename", FieldValue::Str("/bin/ls".into())));
+        assert_eq!(fields[1], ("pid", FieldValue::Int(12345)));
+
+        let rel = FormatField::from_declaration("__rel_loc char[] name", 8, 4, false).unwrap();
+        let raw = hex("0000 00 00 00000000 04000300 00000000 6869 00");
+        assert_eq!(exec.decode_field(&rel, &raw).unwrap(), FieldValue::Str("hi".into()));
+
+        // A 16-byte struct, and an array of 3-byte elements, are raw bytes.
+        // 16 字节的结构体以及 3 字节元素的数组均为原始字节。
+        let addr = FormatField::from_declaration("struct in6_addr daddr_v6", 0, 16, false).unwrap();
+        let raw: Vec<u8> = (0..16).collect();
+        let value = exec.decode_field(&addr, &raw).unwrap();
+        assert_eq!(value, FieldValue::Bytes(raw.clone()));
+        assert_eq!(value.to_string(), "000102030405060708090a0b0c0d0e0f");
+        let odd = FormatField::from_declaration("__u8 rgb[2]", 0, 6, false).unwrap();
+        assert_eq!(exec.decode_field(&odd, &raw).unwrap(), FieldValue::Bytes(raw[..6].to_vec()));
+    }
+
+    #[test]
+    fn test_tracefs_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let event = dir.path().join("sys/kernel/tracing/events/sched/sched_switch");
+        std::fs::create_dir_all(&event).unwrap();
+        std::fs::write(event.join("id"), "372\n").unwrap();
+        std::fs::write(event.join("format"), SCHED_SWITCH).unwrap();
+
+        let tracefs = TraceFs::locate(&SysRoot::new(dir.path())).unwrap();
+        let name: TracepointName = "sched:sched_switch".parse().unwrap();
+        assert_eq!(name.to_string(), "sched:sched_switch");
+        assert_eq!(tracefs.event_id(&name).unwrap(), 372);
+        assert_eq!(tracefs.event_format(&name).unwrap().fields.len(), 7);
+
+        let missing: TracepointName = "sched:nope".parse().unwrap();
+        assert_eq!(tracefs.event_id(&missing).unwrap_err().code(), ErrorCode::EventNotSupported);
+        assert!("sched".parse::<TracepointName>().is_err());
+        assert!("a/b:c".parse::<TracepointName>().is_err());
+        assert!(TraceFs::locate(&SysRoot::new("/nonexistent")).is_err());
+    }
+}
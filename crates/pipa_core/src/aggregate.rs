@@ -0,0 +1,248 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Aggregation of per-CPU counter results by physical core, socket or NUMA
+//! node, like `perf stat --per-core`, `--per-socket` and `--per-node`.
+//!
+//! 按物理核心、插槽或 NUMA 节点聚合按 CPU 统计的计数结果，
+//! 类似 `perf stat --per-core`、`--per-socket` 和 `--per-node`。
+
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::topology::Topology;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::AddAssign;
+use std::str::FromStr;
+
+/// The level at which per-CPU results are combined.
+///
+/// 合并按 CPU 统计结果的层级。
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AggregationLevel {
+    /// Keep one result per logical CPU. / 每个逻辑 CPU 保留一个结果。
+    Cpu,
+    /// Combine SMT siblings of a physical core. / 合并同一物理核心的 SMT 兄弟。
+    Core,
+    /// Combine all CPUs of a package. / 合并同一封装的所有 CPU。
+    Socket,
+    /// Combine all CPUs of a NUMA node. / 合并同一 NUMA 节点的所有 CPU。
+    Node,
+}
+
+impl FromStr for AggregationLevel {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cpu" => Ok(AggregationLevel::Cpu),
+            "core" => Ok(AggregationLevel::Core),
+            "socket" => Ok(AggregationLevel::Socket),
+            "node" => Ok(AggregationLevel::Node),
+            other => Err(PipaCollectorError::InvalidFormat(format!(
+                "unknown aggregation level `{}`, expected cpu, core, socket or node",
+                other
+            ))),
+        }
+    }
+}
+
+/// The group a CPU's result was assigned to.
+///
+/// CPU 结果被归入的分组。
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub enum CpuGroup {
+    /// A logical CPU. / 逻辑 CPU。
+    Cpu(u32),
+    /// A physical core, identified by package, die and core id.
+    /// 由封装、die 和核心编号标识的物理核心。
+    Core {
+        /// Package id. / 封装编号。
+        package: u32,
+        /// Die id. / die 编号。
+        die: u32,
+        /// Core id. / 核心编号。
+        core: u32,
+    },
+    /// A package (socket). / 封装（插槽）。
+    Socket(u32),
+    /// A NUMA node. / NUMA 节点。
+    Node(u32),
+    /// CPUs missing from the topology, or without a node at node level.
+    /// 拓扑中缺失的 CPU，或在节点层级下没有所属节点的 CPU。
+    Unknown,
+}
+
+impl fmt::Display for CpuGroup {
+    /// Uses the labels of `perf stat` (`CPU3`, `S0-D0-C1`, `S1`, `N0`).
+    /// 使用 `perf stat` 的标签格式（`CPU3`、`S0-D0-C1`、`S1`、`N0`）。
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuGroup::Cpu(cpu) => write!(f, "CPU{}", cpu),
+            CpuGroup::Core { package, die, core } => write!(f, "S{}-D{}-C{}", package, die, core),
+            CpuGroup::Socket(package) => write!(f, "S{}", package),
+            CpuGroup::Node(node) => write!(f, "N{}", node),
+            CpuGroup::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The combined result of one group.
+///
+/// 单个分组的合并结果。
+#[derive(Debug, PartialEq, Clone)]
+pub struct GroupResult<T> {
+    /// The group. / 分组。
+    pub group: CpuGroup,
+    /// CPUs whose results were combined, sorted. / 被合并结果的 CPU（已排序）。
+    pub cpus: Vec<u32>,
+    /// Sum of the per-CPU values. / 各 CPU 值之和。
+    pub value: T,
+}
+
+/// Maps a CPU to its group at `level`. / 将 CPU 映射到 `level` 层级下的分组。
+pub fn group_of(topology: &Topology, cpu: u32, level: AggregationLevel) -> CpuGroup {
+    if level == AggregationLevel::Cpu {
+        return CpuGroup::Cpu(cpu);
+    }
+    let Some(t) = topology.cpu(cpu) else {
+        return CpuGroup::Unknown;
+    };
+    match level {
+        AggregationLevel::Cpu => CpuGroup::Cpu(cpu),
+        AggregationLevel::Core => {
+            CpuGroup::Core { package: t.package_id, die: t.die_id, core: t.core_id }
+        }
+        AggregationLevel::Socket => CpuGroup::Socket(t.package_id),
+        AggregationLevel::Node => t.node.map_or(CpuGroup::Unknown, CpuGroup::Node),
+    }
+}
+
+/// Sums per-CPU `(cpu, value)` results into groups at `level`, ordered by
+/// group. Any value type that can be added works, from a plain `u64` count to
+/// a struct holding several counters.
+///
+/// 将按 CPU 统计的 `(cpu, value)` 结果按 `level` 层级求和并按分组排序。
+/// 任何可相加的值类型均可使用，从简单的 `u64` 计数到包含多个计数器的结构体。
+pub fn aggregate_per_cpu<T>(
+    results: &[(u32, T)],
+    topology: &Topology,
+    level: AggregationLevel,
+) -> Vec<GroupResult<T>>
+where
+    T: Default + AddAssign + Clone,
+{
+    let mut groups: BTreeMap<CpuGroup, GroupResult<T>> = BTreeMap::new();
+    for (cpu, value) in results {
+        let group = group_of(topology, *cpu, level);
+        let entry = groups.entry(group).or_insert_with(|| GroupResult {
+            group,
+            cpus: Vec::new(),
+            value: T::default(),
+        });
+        entry.cpus.push(*cpu);
+        entry.value += value.clone();
+    }
+    groups
+        .into_values()
+        .map(|mut result| {
+            result.cpus.sort_unstable();
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::topology::{CpuTopology, NumaNode};
+
+    /// 2 sockets x 2 cores x 2 threads; siblings are N and N+4; one node per
+    /// socket.
+    fn topology() -> Topology {
+        let cpus = (0..8)
+            .map(|cpu| CpuTopology {
+                cpu,
+                package_id: (cpu % 4) / 2,
+                die_id: 0,
+                core_id: cpu % 2,
+                thread_siblings: vec![cpu % 4, cpu % 4 + 4],
+                node: Some((cpu % 4) / 2),
+            })
+            .collect();
+        let nodes = vec![
+            NumaNode { id: 0, cpus: vec![0, 1, 4, 5], distances: vec![10, 21] },
+            NumaNode { id: 1, cpus: vec![2, 3, 6, 7], distances: vec![21, 10] },
+        ];
+        Topology { cpus, nodes }
+    }
+
+    fn results() -> Vec<(u32, u64)> {
+        (0..8).map(|cpu| (cpu, 100 * (cpu as u64 + 1))).collect()
+    }
+
+    #[test]
+    fn test_aggregate_per_core() {
+        let groups = aggregate_per_cpu(&results(), &topology(), AggregationLevel::Core);
+        assert_eq!(groups.len(), 4);
+        assert_eq!(groups[0].group, CpuGroup::Core { package: 0, die: 0, core: 0 });
+        assert_eq!(groups[0].cpus, vec![0, 4]);
+        assert_eq!(groups[0].value, 100 + 500);
+        assert_eq!(groups[3].group.to_string(), "S1-D0-C1");
+        assert_eq!(groups[3].value, 400 + 800);
+    }
+
+    #[test]
+    fn test_aggregate_per_socket_and_node() {
+        for level in [AggregationLevel::Socket, AggregationLevel::Node] {
+            let groups = aggregate_per_cpu(&results(), &topology(), level);
+            assert_eq!(groups.len(), 2);
+            assert_eq!(groups[1].cpus, vec![2, 3, 6, 7]);
+            assert_eq!(groups[1].value, 300 + 400 + 700 + 800);
+        }
+        let groups = aggregate_per_cpu(&results(), &topology(), AggregationLevel::Node);
+        assert_eq!(groups[0].group.to_string(), "N0");
+    }
+
+    #[test]
+    fn test_aggregate_per_cpu_keeps_cpus() {
+        let groups = aggregate_per_cpu(&results(), &topology(), AggregationLevel::Cpu);
+        assert_eq!(groups.len(), 8);
+        assert_eq!(groups[7].group.to_string(), "CPU7");
+        assert_eq!(groups[7].value, 800);
+    }
+
+    #[test]
+    fn test_parse_aggregation_level() {
+        assert_eq!("socket".parse::<AggregationLevel>().unwrap(), AggregationLevel::Socket);
+        assert_eq!("node".parse::<AggregationLevel>().unwrap(), AggregationLevel::Node);
+        assert!("die".parse::<AggregationLevel>().is_err());
+    }
+
+    #[test]
+    fn test_aggregate_unknown_cpus() {
+        let mut topology = topology();
+        topology.cpus.iter_mut().for_each(|c| c.node = None);
+        let results = vec![(0, 1.5), (1, 2.5), (42, 4.0)];
+
+        let groups = aggregate_per_cpu(&results, &topology, AggregationLevel::Node);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].group, CpuGroup::Unknown);
+        assert_eq!(groups[0].value, 8.0);
+
+        let groups = aggregate_per_cpu(&results, &topology, AggregationLevel::Socket);
+        assert_eq!(groups[0].group, CpuGroup::Socket(0));
+        assert_eq!(groups[1].group, CpuGroup::Unknown);
+        assert_eq!(groups[1].cpus, vec![42]);
+    }
+}
@@ -0,0 +1,499 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Call graphs: the symbolized stacks of a profile aggregated into entries
+//! by sort key, such as command, shared object and symbol, each with the
+//! weight of its own samples (self), the weight of every stack it is on
+//! (children), and the tree of calls below or above it, as `perf report`
+//! shows them.
+//!
+//! 调用图：将剖析中符号化后的调用栈按排序键（如命令、共享对象与符号）聚合为条目。
+//! 每个条目带有其自身采样的权重（self）、其所在全部调用栈的权重（children），
+//! 以及其下方或上方的调用树，与 `perf report` 的展示方式一致。
+
+use crate::profile::StackProfile;
+use pipa_collector::error::PipaCollectorError;
+use pipa_parser::symbolize::{Symbol, is_kernel_address};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// A function on a stack, named. / 已命名的调用栈上的函数。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Frame {
+    pub symbol: String,
+    /// The file the code is in, if known. / 代码所在的文件（如已知）。
+    pub dso: Option<String>,
+    /// `file:line`, from debug info. / 来自调试信息的 `file:line`。
+    pub srcline: Option<String>,
+    pub kernel: bool,
+}
+
+impl Frame {
+    /// A frame named by its address, for use without symbols.
+    /// 以地址命名的帧，在不解析符号时使用。
+    pub fn hex(address: u64) -> Self {
+        Frame {
+            symbol: format!("{:#x}", address),
+            kernel: is_kernel_address(address),
+            ..Default::default()
+        }
+    }
+
+    /// A pseudo-frame such as the reason for off-CPU time.
+    /// 伪帧，例如 off-CPU 时间的原因。
+    pub fn label(label: &str) -> Self {
+        Frame { symbol: label.to_string(), ..Default::default() }
+    }
+
+    /// The frames of a symbolized address, innermost first: one per inlined
+    /// function, each with its own source line.
+    /// 已符号化地址的调用帧（最内层在前）：每个内联函数一帧，各带其源码行。
+    pub fn from_symbol(symbol: &Symbol, address: u64) -> Vec<Self> {
+        let kernel = is_kernel_address(address);
+        let inlined: Vec<Frame> = symbol
+            .frames
+            .iter()
+            .filter_map(|frame| {
+                Some(Frame {
+                    symbol: frame.function.clone()?,
+                    dso: symbol.dso.clone(),
+                    srcline: frame
+                        .file
+                        .as_deref()
+                        .zip(frame.line)
+                        .map(|(file, line)| format!("{}:{}", file, line)),
+                    kernel,
+                })
+            })
+            .collect();
+        if !inlined.is_empty() {
+            return inlined;
+        }
+        symbol
+            .names(address)
+            .into_iter()
+            .map(|name| Frame {
+                symbol: name,
+                dso: symbol.dso.clone(),
+                srcline: symbol.srcline(),
+                kernel,
+            })
+            .collect()
+    }
+}
+
+/// What call graph entries are grouped by. / 调用图条目的分组依据。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortKey {
+    Comm,
+    Dso,
+    Symbol,
+    Srcline,
+}
+
+impl SortKey {
+    /// The column heading `perf report` gives the key.
+    /// `perf report` 为该键使用的列标题。
+    pub fn heading(self) -> &'static str {
+        match self {
+            SortKey::Comm => "Command",
+            SortKey::Dso => "Shared Object",
+            SortKey::Symbol => "Symbol",
+            SortKey::Srcline => "Source:Line",
+        }
+    }
+
+    /// The value of the key for `frame` of a stack of `comm`, as shown:
+    /// DSOs by file name and symbols marked `[k]` in the kernel or `[.]` in
+    /// user space. / 该键对 `comm` 调用栈中 `frame` 的取值（展示形式）：DSO 显示文件名，
+    /// 符号在内核中标记为 `[k]`，在用户态标记为 `[.]`。
+    pub fn value(self, comm: &str, frame: &Frame) -> String {
+        match self {
+            SortKey::Comm => comm.to_string(),
+            SortKey::Dso => match &frame.dso {
+                Some(dso) => dso.rsplit('/').next().unwrap_or(dso).to_string(),
+                None => "[unknown]".to_string(),
+            },
+            SortKey::Symbol => {
+                format!("[{}] {}", if frame.kernel { "k" } else { "." }, frame.symbol)
+            }
+            SortKey::Srcline => frame.srcline.clone().unwrap_or_else(|| "[unknown]".to_string()),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "comm" => Ok(SortKey::Comm),
+            "dso" => Ok(SortKey::Dso),
+            "sym" | "symbol" => Ok(SortKey::Symbol),
+            "srcline" => Ok(SortKey::Srcline),
+            other => Err(PipaCollectorError::InvalidFormat(format!(
+                "unknown sort key `{}`, expected comm, dso, sym or srcline",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SortKey::Comm => "comm",
+            SortKey::Dso => "dso",
+            SortKey::Symbol => "sym",
+            SortKey::Srcline => "srcline",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which way the tree under an entry runs. / 条目下调用树的方向。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallOrder {
+    /// Callers before callees: from the outermost frame inwards.
+    /// 调用者在前：从最外层帧向内。
+    Caller,
+    /// Callees before callers: from the innermost frame outwards.
+    /// 被调用者在前：从最内层帧向外。
+    Callee,
+}
+
+impl FromStr for CallOrder {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "caller" => Ok(CallOrder::Caller),
+            "callee" => Ok(CallOrder::Callee),
+            other => Err(PipaCollectorError::InvalidFormat(format!(
+                "unknown call order `{}`, expected caller or callee",
+                other
+            ))),
+        }
+    }
+}
+
+/// Weights of the call paths through a function, merged where they share
+/// a prefix. / 经过某函数的各调用路径的权重，共享前缀的路径会合并。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallTree {
+    pub name: String,
+    pub weight: u64,
+    /// Heaviest first. / 按权重从大到小排列。
+    pub children: Vec<CallTree>,
+}
+
+impl CallTree {
+    fn add(&mut self, path: &[&str], weight: u64) {
+        self.weight += weight;
+        let Some((first, rest)) = path.split_first() else { return };
+        let index = match self.children.iter().position(|child| child.name == *first) {
+            Some(index) => index,
+            None => {
+                self.children.push(CallTree { name: first.to_string(), ..Default::default() });
+                self.children.len() - 1
+            }
+        };
+        self.children[index].add(rest, weight);
+    }
+
+    fn sort(&mut self) {
+        self.children.sort_by(|a, b| b.weight.cmp(&a.weight).then_with(|| a.name.cmp(&b.name)));
+        self.children.iter_mut().for_each(CallTree::sort);
+    }
+
+    /// Whether any path has more than one frame, which is to say the
+    /// stacks had callchains. / 是否有路径包含多于一帧，即调用栈带有调用链。
+    pub fn has_calls(&self) -> bool {
+        self.children.iter().any(|child| !child.children.is_empty())
+    }
+}
+
+/// The weights of one sort key value. / 一个排序键取值的权重。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallGraphEntry {
+    /// One value per sort key. / 每个排序键一个取值。
+    pub key: Vec<String>,
+    /// The weight of stacks whose innermost frame it is.
+    /// 以其为最内层帧的调用栈的权重。
+    pub self_weight: u64,
+    /// The weight of every stack it is on, counted once per stack.
+    /// 其所在全部调用栈的权重，每个调用栈只计一次。
+    pub children_weight: u64,
+    /// The call paths through it, its root unnamed. / 经过它的调用路径，根节点无名称。
+    pub graph: CallTree,
+}
+
+/// How to build a [`CallGraph`]. / [`CallGraph`] 的构建方式。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallGraphOptions {
+    pub sort: Vec<SortKey>,
+    /// Make an entry of every function on a stack, weighted by children,
+    /// rather than only of the innermost ones.
+    /// 为调用栈上的每个函数建立条目（按 children 权重），而不仅是最内层函数。
+    pub children: bool,
+    /// With `children`, [`CallOrder::Caller`] puts what each entry calls
+    /// under it and [`CallOrder::Callee`] what calls it. Without, the trees
+    /// hold whole stacks. / 启用 `children` 时，[`CallOrder::Caller`] 在条目下列出
+    /// 其调用的函数，[`CallOrder::Callee`] 列出调用它的函数；否则调用树包含完整调用栈。
+    pub order: CallOrder,
+}
+
+impl Default for CallGraphOptions {
+    /// `perf report`'s defaults: `--sort comm,dso,sym --children -g caller`.
+    /// `perf report` 的默认值：`--sort comm,dso,sym --children -g caller`。
+    fn default() -> Self {
+        CallGraphOptions {
+            sort: vec![SortKey::Comm, SortKey::Dso, SortKey::Symbol],
+            children: true,
+            order: CallOrder::Caller,
+        }
+    }
+}
+
+/// A profile aggregated by sort key. / 按排序键聚合的剖析结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CallGraph {
+    pub total: u64,
+    /// Heaviest first: by children weight with `children`, else by self.
+    /// 按权重从大到小：启用 `children` 时按 children 权重，否则按 self 权重。
+    pub entries: Vec<CallGraphEntry>,
+}
+
+impl CallGraph {
+    /// Aggregates the stacks of `profile`, with frames named by `name`
+    /// (innermost first, as [`Frame::from_symbol`] gives them). A stack's
+    /// label becomes its outermost frame.
+    /// 聚合 `profile` 中的调用栈，帧名由 `name` 给出（最内层在前，与
+    /// [`Frame::from_symbol`] 一致）。调用栈的标签成为其最外层帧。
+    pub fn build(
+        profile: &StackProfile,
+        options: &CallGraphOptions,
+        name: &mut dyn FnMut(u32, u64) -> Vec<Frame>,
+    ) -> Self {
+        let mut names: HashMap<(u32, u64), Vec<Frame>> = HashMap::new();
+        let mut entries: HashMap<Vec<String>, CallGraphEntry> = HashMap::new();
+        for (key, &weight) in &profile.stacks {
+            // Outermost first. / 最外层在前。
+            let mut stack: Vec<Frame> =
+                key.label.as_deref().map(Frame::label).into_iter().collect();
+            for &addr in key.frames.iter().rev() {
+                let frames = names.entry((key.pid, addr)).or_insert_with(|| name(key.pid, addr));
+                stack.extend(frames.iter().rev().cloned());
+            }
+            if stack.is_empty() {
+                continue;
+            }
+            let keys: Vec<Vec<String>> = stack
+                .iter()
+                .map(|frame| options.sort.iter().map(|k| k.value(&key.comm, frame)).collect())
+                .collect();
+            let symbols: Vec<&str> = stack.iter().map(|frame| frame.symbol.as_str()).collect();
+
+            let own = keys.last().expect("non-empty stack").clone();
+            let entry = entries
+                .entry(own.clone())
+                .or_insert_with(|| CallGraphEntry { key: own.clone(), ..Default::default() });
+            entry.self_weight += weight;
+            if !options.children {
+                entry.children_weight += weight;
+                match options.order {
+                    CallOrder::Caller => entry.graph.add(&symbols, weight),
+                    CallOrder::Callee => {
+                        let reversed: Vec<&str> = symbols.iter().rev().copied().collect();
+                        entry.graph.add(&reversed, weight);
+                    }
+                }
+                continue;
+            }
+            let distinct: BTreeSet<&Vec<String>> = keys.iter().collect();
+            for value in distinct {
+                let outermost = keys.iter().position(|k| k == value).expect("on the stack");
+                let innermost = keys.iter().rposition(|k| k == value).expect("on the stack");
+                let entry = entries
+                    .entry(value.clone())
+                    .or_insert_with(|| CallGraphEntry { key: value.clone(), ..Default::default() });
+                entry.children_weight += weight;
+                match options.order {
+                    CallOrder::Caller => entry.graph.add(&symbols[outermost..], weight),
+                    CallOrder::Callee => {
+                        let callers: Vec<&str> =
+                            symbols[..=innermost].iter().rev().copied().collect();
+                        entry.graph.add(&callers, weight);
+                    }
+                }
+            }
+        }
+
+        let mut entries: Vec<CallGraphEntry> = entries
+            .into_values()
+            .filter(|entry| options.children || entry.self_weight > 0)
+            .collect();
+        entries.iter_mut().for_each(|entry| entry.graph.sort());
+        let weight = |entry: &CallGraphEntry| {
+            if options.children { entry.children_weight } else { entry.self_weight }
+        };
+        entries.sort_by(|a, b| {
+            weight(b)
+                .cmp(&weight(a))
+                .then_with(|| b.self_weight.cmp(&a.self_weight))
+                .then_with(|| a.key.cmp(&b.key))
+        });
+        CallGraph { total: profile.total, entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::StackKey;
+    use pipa_parser::symbolize::SourceFrame;
+
+    /// Names 0x1X addresses `fX` in `app` and everything else by address.
+    fn name(_pid: u32, addr: u64) -> Vec<Frame> {
+        match addr {
+            0x10..=0x1f => vec![Frame {
+                symbol: format!("f{:x}", addr & 0xf),
+                dso: Some("/usr/bin/app".to_string()),
+                srcline: Some(format!("app.c:{}", addr & 0xf)),
+                kernel: false,
+            }],
+            _ => vec![Frame::hex(addr)],
+        }
+    }
+
+    fn profile(stacks: &[(&[u64], u64)]) -> StackProfile {
+        let mut profile = StackProfile::default();
+        for (frames, weight) in stacks {
+            let key = StackKey { comm: "app".into(), pid: 1, label: None, frames: frames.to_vec() };
+            profile.add(key, *weight);
+        }
+        profile
+    }
+
+    fn symbols(entry: &CallGraphEntry) -> (&str, u64, u64) {
+        (entry.key[2].as_str(), entry.children_weight, entry.self_weight)
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let frame = name(1, 0x13).remove(0);
+        let values: Vec<String> = [SortKey::Comm, SortKey::Dso, SortKey::Symbol, SortKey::Srcline]
+            .iter()
+            .map(|key| key.value("app", &frame))
+            .collect();
+        assert_eq!(values, ["app", "app", "[.] f3", "app.c:3"]);
+        let kernel = Frame::hex(0xffff_ffff_8100_0000);
+        assert_eq!(SortKey::Symbol.value("app", &kernel), "[k] 0xffffffff81000000");
+        assert_eq!(SortKey::Dso.value("app", &kernel), "[unknown]");
+        assert_eq!("sym".parse::<SortKey>().unwrap(), SortKey::Symbol);
+        assert_eq!(SortKey::Srcline.to_string(), "srcline");
+        assert!("pid".parse::<SortKey>().is_err());
+        assert_eq!("callee".parse::<CallOrder>().unwrap(), CallOrder::Callee);
+        assert!("up".parse::<CallOrder>().is_err());
+    }
+
+    #[test]
+    fn test_frames_from_symbol() {
+        let symbol = Symbol {
+            dso: Some("/usr/bin/app".into()),
+            function: Some("outer".into()),
+            frames: vec![
+                SourceFrame {
+                    function: Some("inner".into()),
+                    file: Some("a.c".into()),
+                    line: Some(3),
+                },
+                SourceFrame { function: Some("outer".into()), file: None, line: None },
+            ],
+            ..Default::default()
+        };
+        let frames = Frame::from_symbol(&symbol, 0x10);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(
+            (frames[0].symbol.as_str(), frames[0].srcline.as_deref()),
+            ("inner", Some("a.c:3"))
+        );
+        assert_eq!((frames[1].symbol.as_str(), frames[1].srcline.as_deref()), ("outer", None));
+        let unknown = Frame::from_symbol(&Symbol::default(), 0x20);
+        assert_eq!(unknown, [Frame::hex(0x20)]);
+    }
+
+    #[test]
+    fn test_children_and_self() {
+        // main(1) -> a(2) -> b(3), main -> a, main -> c(4) -> c: a recursion.
+        let profile =
+            profile(&[(&[0x13, 0x12, 0x11], 50), (&[0x12, 0x11], 30), (&[0x14, 0x14, 0x11], 20)]);
+        let graph = CallGraph::build(&profile, &CallGraphOptions::default(), &mut name);
+        assert_eq!(graph.total, 100);
+        let entries: Vec<_> = graph.entries.iter().map(symbols).collect();
+        assert_eq!(
+            entries,
+            [("[.] f1", 100, 0), ("[.] f2", 80, 30), ("[.] f3", 50, 50), ("[.] f4", 20, 20)]
+        );
+        assert_eq!(graph.entries[0].key[..2], ["app".to_string(), "app".to_string()]);
+
+        // Under `main`, what it calls. / `main` 下方为其调用的函数。
+        let main = &graph.entries[0].graph;
+        assert!(main.has_calls());
+        assert_eq!(main.children.len(), 1);
+        let calls: Vec<_> =
+            main.children[0].children.iter().map(|c| (c.name.as_str(), c.weight)).collect();
+        assert_eq!(calls, [("f2", 80), ("f4", 20)]);
+        // A recursive function's tree starts at its outermost call.
+        // 递归函数的调用树从其最外层调用开始。
+        let f4 = &graph.entries[3].graph.children[0];
+        assert_eq!((f4.name.as_str(), f4.weight, f4.children[0].name.as_str()), ("f4", 20, "f4"));
+
+        // Callee order puts the callers of `a` under it.
+        // 被调用者顺序在 `a` 下方列出其调用者。
+        let options = CallGraphOptions { order: CallOrder::Callee, ..Default::default() };
+        let graph = CallGraph::build(&profile, &options, &mut name);
+        let f2 = &graph.entries[1].graph.children[0];
+        assert_eq!((f2.name.as_str(), f2.children[0].name.as_str()), ("f2", "f1"));
+    }
+
+    #[test]
+    fn test_self_only() {
+        let mut profile = profile(&[(&[0x13, 0x12, 0x11], 50), (&[0x12, 0x11], 30)]);
+        profile.add(
+            StackKey { comm: "app".into(), pid: 1, label: Some("[io]".into()), frames: vec![0x13] },
+            20,
+        );
+        let options = CallGraphOptions {
+            sort: vec![SortKey::Symbol],
+            children: false,
+            order: CallOrder::Callee,
+        };
+        let graph = CallGraph::build(&profile, &options, &mut name);
+        let entries: Vec<_> =
+            graph.entries.iter().map(|e| (e.key[0].as_str(), e.self_weight)).collect();
+        assert_eq!(entries, [("[.] f3", 70), ("[.] f2", 30)]);
+        // Whole stacks, innermost first, the label outermost.
+        // 完整调用栈，最内层在前，标签在最外层。
+        let f3 = &graph.entries[0].graph.children[0];
+        let callers: Vec<_> = f3.children.iter().map(|c| (c.name.as_str(), c.weight)).collect();
+        assert_eq!(callers, [("f2", 50), ("[io]", 20)]);
+
+        // Without callchains there are no calls to show.
+        // 没有调用链时无调用可显示。
+        let flat = CallGraph::build(&self::profile(&[(&[0x11], 1)]), &options, &mut name);
+        assert!(!flat.entries[0].graph.has_calls());
+    }
+}
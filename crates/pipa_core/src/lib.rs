@@ -1,3 +1,15 @@
+//! Analysis passes that turn raw collector output into reports.
+//!
+//! 将采集器的原始输出转换为报告的分析模块。
+
+pub mod aggregate;
+pub mod callgraph;
+pub mod offcpu;
+pub mod profile;
+pub mod sched;
+pub mod syscall_summary;
+pub mod syscalls;
+
 #[cfg(not(tarpaulin_include))]
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
@@ -0,0 +1,402 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Off-CPU time: how long threads were blocked, by stack and by reason.
+//!
+//! A `sched_switch` sample is taken as a task leaves the CPU, with its
+//! callchain and state. The task's next switch in, from a
+//! `PERF_RECORD_SWITCH` record or a later `sched_switch` that picks it,
+//! ends the blocked interval. The reason comes from the switch-out state and
+//! the syscall the task is in, from `raw_syscalls:sys_enter` and
+//! `raw_syscalls:sys_exit`.
+//!
+//! off-CPU 时间：线程被阻塞的时长，按调用栈和原因划分。
+//!
+//! 任务离开 CPU 时产生一个带调用链和状态的 `sched_switch` 采样。该任务下一次
+//! 被切入（来自 `PERF_RECORD_SWITCH` 记录，或之后选中它的 `sched_switch`）时，
+//! 阻塞区间结束。原因由切出时的状态以及任务最近进入的系统调用
+//! （来自 `raw_syscalls:sys_enter` 与 `raw_syscalls:sys_exit`）确定。
+
+use crate::profile::{self, SampleFilter, StackBuilder, StackKey, StackProfile};
+use crate::sched::{self, SchedEventKind};
+use crate::syscalls;
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::perf_record::{RecordEntry, RecordFile};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// The tracepoints an off-CPU recording samples, `sched_switch` first.
+/// off-CPU 记录所采样的跟踪点，`sched_switch` 在前。
+pub const EVENTS: [&str; 3] =
+    ["sched:sched_switch", "raw_syscalls:sys_enter", "raw_syscalls:sys_exit"];
+
+/// Why a task was off the CPU. / 任务离开 CPU 的原因。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OffCpuReason {
+    /// Still runnable, waiting for a CPU. / 仍可运行，正在等待 CPU。
+    Preempted,
+    /// Waiting on a disk, network or pipe. / 等待磁盘、网络或管道。
+    Io,
+    /// Waiting on a futex or file lock. / 等待 futex 或文件锁。
+    Lock,
+    /// Sleeping or waiting for an event. / 睡眠或等待事件。
+    Sleep,
+}
+
+impl fmt::Display for OffCpuReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OffCpuReason::Preempted => "preempted",
+            OffCpuReason::Io => "io",
+            OffCpuReason::Lock => "lock",
+            OffCpuReason::Sleep => "sleep",
+        };
+        f.write_str(name)
+    }
+}
+
+/// `TASK_UNINTERRUPTIBLE` in `sched_switch`'s `prev_state`, which mostly
+/// means waiting for I/O. / `sched_switch` 的 `prev_state` 中的
+/// `TASK_UNINTERRUPTIBLE`，通常表示等待 I/O。
+const TASK_UNINTERRUPTIBLE: i64 = 0x2;
+
+const LOCK_SYSCALLS: &[&str] = &["futex", "futex_waitv", "flock"];
+
+const IO_SYSCALLS: &[&str] = &[
+    "read",
+    "write",
+    "pread64",
+    "pwrite64",
+    "readv",
+    "writev",
+    "preadv",
+    "pwritev",
+    "preadv2",
+    "pwritev2",
+    "fsync",
+    "fdatasync",
+    "sync_file_range",
+    "sendfile",
+    "splice",
+    "io_uring_enter",
+    "io_getevents",
+    "io_pgetevents",
+    "accept",
+    "accept4",
+    "connect",
+    "recvfrom",
+    "recvmsg",
+    "recvmmsg",
+    "sendto",
+    "sendmsg",
+    "sendmmsg",
+];
+
+/// Classifies a switch out by the task's state and the syscall it is in.
+/// 根据任务状态及其所处的系统调用对切出进行分类。
+pub fn classify(prev_state: i64, syscall: Option<&str>) -> OffCpuReason {
+    if sched::is_preempted(prev_state) {
+        OffCpuReason::Preempted
+    } else if syscall.is_some_and(|name| LOCK_SYSCALLS.contains(&name)) {
+        OffCpuReason::Lock
+    } else if prev_state & TASK_UNINTERRUPTIBLE != 0
+        || syscall.is_some_and(|name| IO_SYSCALLS.contains(&name))
+    {
+        OffCpuReason::Io
+    } else {
+        OffCpuReason::Sleep
+    }
+}
+
+/// The result of [`OffCpuAnalyzer::finish`]. Times are in nanoseconds.
+/// [`OffCpuAnalyzer::finish`] 的结果。时间以纳秒为单位。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OffCpuProfile {
+    /// Blocked time by stack, labelled with the reason.
+    /// 按调用栈累计的阻塞时间，以原因作为标签。
+    pub stacks: StackProfile,
+    pub reasons: BTreeMap<OffCpuReason, u64>,
+}
+
+/// A task that left the CPU. / 已离开 CPU 的任务。
+#[derive(Debug)]
+struct Blocked {
+    since: u64,
+    key: StackKey,
+    reason: OffCpuReason,
+}
+
+/// Builds an [`OffCpuProfile`] from a time-ordered stream of events.
+/// 从按时间排序的事件流构建 [`OffCpuProfile`]。
+#[derive(Debug, Default)]
+pub struct OffCpuAnalyzer {
+    arch: String,
+    blocked: HashMap<u32, Blocked>,
+    syscalls: HashMap<u32, i64>,
+    profile: OffCpuProfile,
+}
+
+impl OffCpuAnalyzer {
+    /// An analyzer for the syscall numbers of `arch`.
+    /// 针对 `arch` 系统调用编号的分析器。
+    pub fn new(arch: &str) -> Self {
+        Self { arch: arch.to_string(), ..Default::default() }
+    }
+
+    /// Task `tid` entered syscall `nr`. / 任务 `tid` 进入了系统调用 `nr`。
+    pub fn syscall_enter(&mut self, tid: u32, nr: i64) {
+        self.syscalls.insert(tid, nr);
+    }
+
+    /// Task `tid` returned from its syscall. / 任务 `tid` 从其系统调用返回。
+    pub fn syscall_exit(&mut self, tid: u32) {
+        self.syscalls.remove(&tid);
+    }
+
+    /// Task `tid` of process `pid` left the CPU at `time` in `prev_state`,
+    /// in `frames`. / 进程 `pid` 的任务 `tid` 在 `time` 时以 `prev_state` 状态离开 CPU，
+    /// 调用帧为 `frames`。
+    pub fn switch_out(
+        &mut self,
+        pid: u32,
+        tid: u32,
+        comm: &str,
+        time: u64,
+        prev_state: i64,
+        frames: Vec<u64>,
+    ) {
+        let syscall =
+            self.syscalls.get(&tid).and_then(|&nr| syscalls::syscall_name(&self.arch, nr));
+        let reason = classify(prev_state, syscall);
+        let key =
+            StackKey { comm: comm.to_string(), pid, label: Some(format!("[{}]", reason)), frames };
+        self.blocked.insert(tid, Blocked { since: time, key, reason });
+    }
+
+    /// Task `tid` started running again at `time`.
+    /// 任务 `tid` 在 `time` 时重新开始运行。
+    pub fn switch_in(&mut self, tid: u32, time: u64) {
+        if let Some(blocked) = self.blocked.remove(&tid) {
+            let duration = time.saturating_sub(blocked.since);
+            *self.profile.reasons.entry(blocked.reason).or_default() += duration;
+            self.profile.stacks.add(blocked.key, duration);
+        }
+    }
+
+    /// Ends the stream. Tasks still blocked are left out, as how long they
+    /// will wait is unknown.
+    /// 结束事件流。仍处于阻塞状态的任务不计入，因为其等待时长未知。
+    pub fn finish(self) -> OffCpuProfile {
+        self.profile
+    }
+}
+
+/// Whether a record file was made with `pipa record --off-cpu`, the only
+/// mode that records context switches.
+/// 记录文件是否由 `pipa record --off-cpu` 生成（唯一记录上下文切换的模式）。
+pub fn is_off_cpu(file: &RecordFile) -> bool {
+    file.entries.iter().any(|entry| matches!(entry, RecordEntry::Switch(_)))
+}
+
+/// Analyzes an off-CPU recording of [`EVENTS`] with callchains, of any
+/// [`CallGraph`](pipa_collector::perf_record::CallGraph) mode. Blocked
+/// intervals are kept if `filter` matches the switch-out that starts them.
+/// Entries are only time-ordered within each drain of the ring buffers, so
+/// they are sorted first.
+/// 分析带调用链（任意 [`CallGraph`](pipa_collector::perf_record::CallGraph) 模式）的
+/// [`EVENTS`] off-CPU 记录。仅保留 `filter` 匹配其起始切出事件的阻塞区间。
+/// 条目仅在每次清空环形缓冲区的范围内按时间排序，因此先进行排序。
+pub fn analyze(
+    file: &RecordFile,
+    filter: &SampleFilter,
+) -> Result<OffCpuProfile, PipaCollectorError> {
+    let start = profile::start_time(file);
+    let events = &file.header.events;
+    let sys_enter = events.iter().position(|e| e.name == EVENTS[1]);
+    let sys_exit = events.iter().position(|e| e.name == EVENTS[2]);
+    let mut entries: Vec<&RecordEntry> = file.entries.iter().collect();
+    entries.sort_by_key(|entry| entry.time());
+    let mut analyzer = OffCpuAnalyzer::new(&file.header.arch);
+    let mut stacks = StackBuilder::new(file);
+    for entry in entries {
+        stacks.add_entry(entry);
+        match entry {
+            RecordEntry::Sample(sample) if Some(sample.event) == sys_enter => {
+                if let Some(format) = &events[sample.event].format {
+                    analyzer.syscall_enter(sample.tid, sched::int(format, &sample.raw, "id")?);
+                }
+            }
+            RecordEntry::Sample(sample) if Some(sample.event) == sys_exit => {
+                analyzer.syscall_exit(sample.tid)
+            }
+            RecordEntry::Sample(sample) => {
+                let Some(event) = sched::decode_sample(events, sample)? else { continue };
+                if let SchedEventKind::Switch {
+                    prev_tid, prev_comm, prev_state, next_tid, ..
+                } = event.kind
+                {
+                    // The switch is sampled in the context of the task that
+                    // leaves. / 切换事件在离开 CPU 的任务上下文中被采样。
+                    let offset = event.time.saturating_sub(start);
+                    if prev_tid != 0 && filter.matches(sample.pid, prev_tid, sample.cpu, offset) {
+                        analyzer.switch_out(
+                            sample.pid,
+                            prev_tid,
+                            &prev_comm,
+                            event.time,
+                            prev_state,
+                            stacks.frames(sample),
+                        );
+                    }
+                    analyzer.switch_in(next_tid, event.time);
+                }
+            }
+            RecordEntry::Switch(switch) if !switch.out => {
+                analyzer.switch_in(switch.tid, switch.time)
+            }
+            RecordEntry::Switch(_)
+            | RecordEntry::Comm(_)
+            | RecordEntry::Mmap(_)
+            | RecordEntry::BuildId(_)
+            | RecordEntry::Lost { .. } => {}
+        }
+    }
+    let mut profile = analyzer.finish();
+    profile.stacks.mismatched = stacks.mismatched().into_iter().map(String::from).collect();
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::{frames, hex_frame};
+    use pipa_collector::perf_record::{RecordHeader, RecordedEvent, SampleRecord, SwitchRecord};
+    use pipa_collector::sysroot::SysRoot;
+    use pipa_collector::tracepoint::parse_format;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(0, Some("futex")), OffCpuReason::Preempted);
+        assert_eq!(classify(0x100, None), OffCpuReason::Preempted);
+        assert_eq!(classify(1, Some("futex")), OffCpuReason::Lock);
+        assert_eq!(classify(2, None), OffCpuReason::Io);
+        assert_eq!(classify(1, Some("read")), OffCpuReason::Io);
+        assert_eq!(classify(1, Some("clock_nanosleep")), OffCpuReason::Sleep);
+        assert_eq!(classify(1, None), OffCpuReason::Sleep);
+    }
+
+    #[test]
+    fn test_analyze_blocked_time() {
+        let user = -512i64 as u64;
+        let mut analyzer = OffCpuAnalyzer::new("x86_64");
+        // Thread 10 sleeps twice in clock_nanosleep (230), then blocks on a
+        // futex (202); thread 11 is preempted and never switched back in.
+        // 线程 10 两次在 clock_nanosleep（230）中睡眠，随后阻塞于 futex（202）；
+        // 线程 11 被抢占且之后未再切入。
+        analyzer.syscall_enter(10, 230);
+        analyzer.switch_out(10, 10, "app", 1_000, 1, frames(&[user, 0x20, 0x30], 0));
+        analyzer.switch_in(10, 4_000);
+        analyzer.switch_in(10, 4_500);
+        analyzer.switch_out(10, 10, "app", 5_000, 1, frames(&[user, 0x20, 0x30], 0));
+        analyzer.switch_in(10, 6_000);
+        analyzer.syscall_enter(10, 202);
+        analyzer.switch_out(10, 10, "app", 7_000, 1, frames(&[user, 0x40, 0x30], 0));
+        analyzer.switch_in(10, 17_000);
+        analyzer.switch_out(11, 11, "worker", 8_000, 0, frames(&[0x50], 0));
+
+        let profile = analyzer.finish();
+        assert_eq!(
+            profile.reasons.into_iter().collect::<Vec<_>>(),
+            [(OffCpuReason::Lock, 10_000), (OffCpuReason::Sleep, 4_000)]
+        );
+        assert_eq!(profile.stacks.total, 14_000);
+        assert_eq!(
+            profile.stacks.folded(hex_frame),
+            ["app;[lock];0x30;0x40 10000", "app;[sleep];0x30;0x20 4000"]
+        );
+    }
+
+    #[test]
+    fn test_syscall_exit() {
+        let mut analyzer = OffCpuAnalyzer::new("x86_64");
+        // A futex (202) that has returned does not make a later sleep a lock
+        // wait. / 已返回的 futex（202）不会使之后的睡眠被视为锁等待。
+        analyzer.syscall_enter(10, 202);
+        analyzer.syscall_exit(10);
+        analyzer.switch_out(10, 10, "app", 1_000, 1, vec![0x30]);
+        analyzer.switch_in(10, 3_000);
+        let reasons: Vec<_> = analyzer.finish().reasons.into_iter().collect();
+        assert_eq!(reasons, [(OffCpuReason::Sleep, 2_000)]);
+    }
+
+    #[test]
+    fn test_analyze_sorts_entries() {
+        let event = |name: &str, format: &str| RecordedEvent {
+            name: name.into(),
+            format: Some(parse_format(format).unwrap()),
+        };
+        let id = "\tfield:long id;\toffset:8;\tsize:8;\tsigned:1;\n";
+        let events = vec![
+            event(
+                EVENTS[0],
+                "name: sched_switch\nID: 372\nformat:\n\
+                 \tfield:char prev_comm[16];\toffset:8;\tsize:16;\tsigned:0;\n\
+                 \tfield:pid_t prev_pid;\toffset:24;\tsize:4;\tsigned:1;\n\
+                 \tfield:long prev_state;\toffset:32;\tsize:8;\tsigned:1;\n\
+                 \tfield:char next_comm[16];\toffset:40;\tsize:16;\tsigned:0;\n\
+                 \tfield:pid_t next_pid;\toffset:56;\tsize:4;\tsigned:1;\n",
+            ),
+            event(EVENTS[1], &format!("name: sys_enter\nID: 22\nformat:\n{}", id)),
+            event(EVENTS[2], &format!("name: sys_exit\nID: 21\nformat:\n{}", id)),
+        ];
+        let mut switch = vec![0u8; 64];
+        switch[8..11].copy_from_slice(b"app");
+        switch[24..28].copy_from_slice(&10i32.to_ne_bytes());
+        switch[32..40].copy_from_slice(&1i64.to_ne_bytes());
+        let mut futex = vec![0u8; 16];
+        futex[8..].copy_from_slice(&202i64.to_ne_bytes());
+        let sample = |event, time, raw: &Vec<u8>| {
+            RecordEntry::Sample(SampleRecord {
+                event,
+                time,
+                pid: 10,
+                tid: 10,
+                callchain: vec![0x30],
+                raw: raw.clone(),
+                ..Default::default()
+            })
+        };
+        let mut header = RecordHeader::new(&SysRoot::default(), Vec::new(), events);
+        header.arch = "x86_64".into();
+        // The switch in was drained from its ring buffer before the futex
+        // and the switch out. / 切入记录先于 futex 与切出记录从环形缓冲区中被读出。
+        let file = RecordFile {
+            header,
+            entries: vec![
+                RecordEntry::Switch(SwitchRecord {
+                    pid: 10,
+                    tid: 10,
+                    time: 3_000,
+                    ..Default::default()
+                }),
+                sample(1, 500, &futex),
+                sample(2, 800, &futex),
+                sample(0, 1_000, &switch),
+            ],
+        };
+        let profile = analyze(&file, &SampleFilter::default()).unwrap();
+        let reasons: Vec<_> = profile.reasons.into_iter().collect();
+        assert_eq!(reasons, [(OffCpuReason::Sleep, 2_000)]);
+    }
+}
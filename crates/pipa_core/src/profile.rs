@@ -0,0 +1,441 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Weighted stacks, the common form of on-CPU and off-CPU profiles that
+//! flame graphs and reports are drawn from. Frames stay addresses until they
+//! are rendered, so a symbolizer can name them.
+//!
+//! 带权重的调用栈：on-CPU 与 off-CPU 剖析的通用形式，火焰图与报告均由此生成。
+//! 帧在渲染前保持为地址，以便由符号解析器命名。
+
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::perf_record::{CallGraph, RecordEntry, RecordFile, SampleRecord};
+use pipa_collector::sysroot::SysRoot;
+use pipa_parser::buildid::BuildIdCache;
+use pipa_parser::kallsyms::KernelSymbols;
+use pipa_parser::symbolize::{DEFAULT_CACHE_SIZE, Symbolizer};
+use pipa_parser::unwind::Unwinder;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// Callchain entries at or above this value are `PERF_CONTEXT_*` markers
+/// that separate kernel and user frames, not addresses.
+/// 大于等于该值的调用链条目是分隔内核帧与用户帧的 `PERF_CONTEXT_*` 标记，而非地址。
+pub const PERF_CONTEXT_MAX: u64 = -4095i64 as u64;
+/// Marks the start of kernel frames in a callchain. / 标记调用链中内核帧的开始。
+const PERF_CONTEXT_KERNEL: u64 = -128i64 as u64;
+/// Marks the start of user frames in a callchain. / 标记调用链中用户帧的开始。
+const PERF_CONTEXT_USER: u64 = -512i64 as u64;
+
+/// The frames of a sample, innermost first: its callchain without context
+/// markers, or just `ip` when no callchain was recorded.
+/// 采样的调用帧（最内层在前）：去掉上下文标记的调用链；未记录调用链时仅为 `ip`。
+pub fn frames(callchain: &[u64], ip: u64) -> Vec<u64> {
+    if callchain.is_empty() {
+        return vec![ip];
+    }
+    callchain.iter().copied().filter(|&addr| addr < PERF_CONTEXT_MAX).collect()
+}
+
+/// The kernel and the user frames of a callchain, innermost first.
+/// 调用链中的内核帧与用户帧（最内层在前）。
+fn split_callchain(callchain: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let (mut kernel, mut user) = (Vec::new(), Vec::new());
+    let mut context = PERF_CONTEXT_USER;
+    for &addr in callchain {
+        if addr >= PERF_CONTEXT_MAX {
+            context = addr;
+        } else if context == PERF_CONTEXT_KERNEL {
+            kernel.push(addr);
+        } else if context == PERF_CONTEXT_USER {
+            user.push(addr);
+        }
+    }
+    (kernel, user)
+}
+
+/// Builds the frames of samples the way their callchains were recorded:
+/// from frame pointers, the LBR call stack or by DWARF unwinding.
+/// 按调用链的记录方式构建采样的调用帧：来自帧指针、LBR 调用栈或 DWARF 回溯。
+#[derive(Debug)]
+pub struct StackBuilder {
+    call_graph: Option<CallGraph>,
+    unwinder: Option<Unwinder>,
+}
+
+impl StackBuilder {
+    /// A builder for the samples of `file`. DWARF unwinding reads files that
+    /// changed since the recording from the build-ID cache.
+    /// 为 `file` 中的采样创建构建器。DWARF 回溯从构建 ID 缓存读取记录后已变化的文件。
+    pub fn new(file: &RecordFile) -> Self {
+        let header = &file.header;
+        let mut unwinder = match header.call_graph {
+            Some(CallGraph::Dwarf) => Unwinder::new(header),
+            _ => None,
+        };
+        if let Some(unwinder) = &mut unwinder {
+            if let Some(cache) = BuildIdCache::default_root() {
+                unwinder.set_build_id_cache(BuildIdCache::new(cache));
+            }
+            // Build-IDs read at the end of the recording come after the
+            // samples. / 记录结束时读取的构建 ID 位于采样之后。
+            for entry in &file.entries {
+                if let RecordEntry::BuildId(record) = entry {
+                    unwinder.add_build_id(record);
+                }
+            }
+        }
+        StackBuilder { call_graph: header.call_graph, unwinder }
+    }
+
+    /// Follows the mappings of the recording, needed to unwind.
+    /// 跟踪记录中的内存映射，回溯时需要。
+    pub fn add_entry(&mut self, entry: &RecordEntry) {
+        if let (Some(unwinder), RecordEntry::Mmap(mmap)) = (&mut self.unwinder, entry) {
+            unwinder.add_mmap(mmap);
+        }
+    }
+
+    /// The frames of `sample`, innermost first. / `sample` 的调用帧，最内层在前。
+    pub fn frames(&mut self, sample: &SampleRecord) -> Vec<u64> {
+        match self.call_graph {
+            // The LBR holds the call sites of the user stack below the
+            // sampled instruction. / LBR 保存采样指令之下用户栈的各调用点。
+            Some(CallGraph::Lbr) if !sample.branch_stack.is_empty() => {
+                let (mut frames, user) = split_callchain(&sample.callchain);
+                frames.push(user.first().copied().unwrap_or(sample.ip));
+                frames.extend(sample.branch_stack.iter().map(|branch| branch.from));
+                frames
+            }
+            Some(CallGraph::Dwarf) => {
+                let (mut frames, _) = split_callchain(&sample.callchain);
+                if let Some(unwinder) = &mut self.unwinder {
+                    frames.extend(unwinder.unwind_sample(sample));
+                }
+                if frames.is_empty() {
+                    frames.push(sample.ip);
+                }
+                frames
+            }
+            _ => frames(&sample.callchain, sample.ip),
+        }
+    }
+
+    /// Files that DWARF unwinding needed but could not use.
+    /// DWARF 回溯需要但无法使用的文件。
+    pub fn unreadable(&self) -> Vec<&str> {
+        self.unwinder.as_ref().map(Unwinder::unreadable).unwrap_or_default()
+    }
+
+    /// Files that DWARF unwinding needed but that changed since the
+    /// recording. / DWARF 回溯需要但记录后已变化的文件。
+    pub fn mismatched(&self) -> Vec<&str> {
+        self.unwinder.as_ref().map(Unwinder::mismatched).unwrap_or_default()
+    }
+}
+
+/// Names a frame by its address, for use without symbols. Naming functions
+/// take the process and the address of a frame, and return one name per
+/// function there, innermost first: more than one where calls were inlined.
+/// 以地址命名帧，在不解析符号时使用。命名函数接受帧所属的进程与地址，并为该处的
+/// 每个函数返回一个名称（最内层在前）：存在内联调用时多于一个。
+pub fn hex_frame(_pid: u32, addr: u64) -> Vec<String> {
+    vec![format!("{:#x}", addr)]
+}
+
+/// A symbolizer for the frames of a record file: it follows the mappings
+/// the file recorded, as they were at the end of the recording, and names
+/// kernel frames from `/proc/kallsyms`. Files that changed since the
+/// recording are read from the build-ID cache.
+/// 记录文件调用帧的符号解析器：跟踪文件中记录的内存映射（以记录结束时的状态为准），
+/// 并借助 `/proc/kallsyms` 命名内核帧。记录后发生变化的文件从构建 ID 缓存中读取。
+#[cfg(not(tarpaulin_include))]
+pub fn symbolizer(file: &RecordFile) -> Symbolizer {
+    let root = SysRoot::default();
+    let mut symbolizer = Symbolizer::new(DEFAULT_CACHE_SIZE);
+    if let Ok(kernel) = KernelSymbols::load(&root) {
+        symbolizer.set_kernel_symbols(kernel);
+    }
+    if let Some(cache) = BuildIdCache::default_root() {
+        symbolizer.set_build_id_cache(BuildIdCache::new(cache));
+    }
+    for entry in &file.entries {
+        match entry {
+            RecordEntry::Mmap(mmap) => symbolizer.add_mmap(mmap),
+            RecordEntry::BuildId(record) => symbolizer.add_build_id(record),
+            _ => {}
+        }
+    }
+    symbolizer
+}
+
+/// A span of a recording in seconds since its first sample, either end
+/// open, written `start,end` as for `perf report --time`.
+/// 以距首个采样的秒数表示的记录时间段，两端均可省略，写作 `start,end`，
+/// 与 `perf report --time` 相同。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TimeRange {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+impl TimeRange {
+    /// Whether `offset`, in nanoseconds since the first sample, is inside.
+    /// 距首个采样 `offset` 纳秒的时刻是否在此范围内。
+    pub fn contains(&self, offset: u64) -> bool {
+        let seconds = offset as f64 / 1e9;
+        self.start.is_none_or(|start| seconds >= start) && self.end.is_none_or(|end| seconds < end)
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = PipaCollectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || {
+            PipaCollectorError::InvalidFormat(format!(
+                "bad time range `{}`, expected start,end in seconds",
+                s
+            ))
+        };
+        let (start, end) = s.split_once(',').ok_or_else(bad)?;
+        let seconds = |field: &str| match field.trim() {
+            "" => Ok(None),
+            field => field.parse::<f64>().ok().filter(|t| *t >= 0.0).map(Some).ok_or_else(bad),
+        };
+        Ok(TimeRange { start: seconds(start)?, end: seconds(end)? })
+    }
+}
+
+/// Which samples a profile is made of; empty lists match everything.
+/// 剖析所包含的采样；列表为空时匹配全部。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SampleFilter {
+    pub pids: Vec<u32>,
+    pub tids: Vec<u32>,
+    pub cpus: Vec<u32>,
+    pub time: TimeRange,
+}
+
+impl SampleFilter {
+    /// Whether a sample of task `tid` of process `pid` on `cpu`, taken
+    /// `offset` nanoseconds after the first sample, matches.
+    /// 进程 `pid` 的任务 `tid` 在 `cpu` 上、于首个采样后 `offset` 纳秒采得的采样是否匹配。
+    pub fn matches(&self, pid: u32, tid: u32, cpu: u32, offset: u64) -> bool {
+        (self.pids.is_empty() || self.pids.contains(&pid))
+            && (self.tids.is_empty() || self.tids.contains(&tid))
+            && (self.cpus.is_empty() || self.cpus.contains(&cpu))
+            && self.time.contains(offset)
+    }
+}
+
+/// The time of the first sample of a record file, which [`TimeRange`]s
+/// count from. / 记录文件中首个采样的时间，[`TimeRange`] 从此计时。
+pub fn start_time(file: &RecordFile) -> u64 {
+    file.samples().map(|sample| sample.time).min().unwrap_or(0)
+}
+
+/// What a weight is attributed to. / 权重归属的对象。
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StackKey {
+    pub comm: String,
+    /// The process whose address space the frames are in.
+    /// 帧地址所属地址空间的进程。
+    pub pid: u32,
+    /// A pseudo-frame between the task and its stack, such as the reason
+    /// for off-CPU time. / 任务与调用栈之间的伪帧，例如 off-CPU 时间的原因。
+    pub label: Option<String>,
+    /// Addresses, innermost first. / 地址，最内层在前。
+    pub frames: Vec<u64>,
+}
+
+/// Weights summed by stack. / 按调用栈累加的权重。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StackProfile {
+    pub stacks: HashMap<StackKey, u64>,
+    pub total: u64,
+    /// Files that changed since the recording, where DWARF unwinding
+    /// stopped. / 记录后已变化、DWARF 回溯在此停止的文件。
+    pub mismatched: Vec<String>,
+}
+
+/// Keeps `;` and line breaks, which separate frames and stacks in folded
+/// output, out of names. / 避免名称中出现在折叠格式中用于分隔帧与栈的 `;` 和换行。
+fn folded_name(name: &str) -> String {
+    name.replace([';', '\n'], "_")
+}
+
+impl StackProfile {
+    pub fn add(&mut self, key: StackKey, weight: u64) {
+        *self.stacks.entry(key).or_default() += weight;
+        self.total += weight;
+    }
+
+    /// Stacks sorted by weight, heaviest first. / 按权重从大到小排序的调用栈。
+    pub fn by_weight(&self) -> Vec<(&StackKey, u64)> {
+        let mut stacks: Vec<_> = self.stacks.iter().map(|(key, w)| (key, *w)).collect();
+        stacks.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        stacks
+    }
+
+    /// Renders the folded stacks that flame graph tools read, one
+    /// `comm;label;outer;...;inner weight` line per distinct stack, with
+    /// frames named by `name` (see [`hex_frame`]).
+    /// 渲染火焰图工具读取的折叠栈格式：每个不同的栈一行
+    /// `comm;label;outer;...;inner weight`，帧名由 `name` 给出（参见 [`hex_frame`]）。
+    pub fn folded<F: FnMut(u32, u64) -> Vec<String>>(&self, mut name: F) -> Vec<String> {
+        let mut lines: BTreeMap<String, u64> = BTreeMap::new();
+        for (key, weight) in &self.stacks {
+            let mut parts = vec![folded_name(&key.comm)];
+            parts.extend(key.label.as_deref().map(folded_name));
+            for &addr in key.frames.iter().rev() {
+                parts.extend(name(key.pid, addr).iter().rev().map(|n| folded_name(n)));
+            }
+            *lines.entry(parts.join(";")).or_default() += weight;
+        }
+        lines.into_iter().map(|(stack, weight)| format!("{} {}", stack, weight)).collect()
+    }
+}
+
+/// The on-CPU profile of a record file: every sample that `filter` matches,
+/// weighted by its period.
+/// 记录文件的 on-CPU 剖析：`filter` 匹配的每个采样，按其周期加权。
+pub fn on_cpu(file: &RecordFile, filter: &SampleFilter) -> StackProfile {
+    let start = start_time(file);
+    let mut comms: HashMap<u32, &str> = HashMap::new();
+    let mut stacks = StackBuilder::new(file);
+    let mut profile = StackProfile::default();
+    for entry in &file.entries {
+        stacks.add_entry(entry);
+        match entry {
+            RecordEntry::Comm(comm) => {
+                comms.insert(comm.tid, &comm.comm);
+            }
+            RecordEntry::Sample(sample)
+                if filter.matches(
+                    sample.pid,
+                    sample.tid,
+                    sample.cpu,
+                    sample.time.saturating_sub(start),
+                ) =>
+            {
+                let key = StackKey {
+                    comm: comms.get(&sample.tid).copied().unwrap_or(":").to_string(),
+                    pid: sample.pid,
+                    label: None,
+                    frames: stacks.frames(sample),
+                };
+                profile.add(key, sample.period.max(1));
+            }
+            RecordEntry::Sample(_)
+            | RecordEntry::Switch(_)
+            | RecordEntry::Mmap(_)
+            | RecordEntry::BuildId(_)
+            | RecordEntry::Lost { .. } => {}
+        }
+    }
+    profile.mismatched = stacks.mismatched().into_iter().map(String::from).collect();
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::perf_record::{CommRecord, RecordHeader, SampleRecord};
+    use pipa_collector::sysroot::SysRoot;
+
+    #[test]
+    fn test_on_cpu_profile_and_folded() {
+        let kernel = -128i64 as u64;
+        let user = -512i64 as u64;
+        let sample = |tid, period, callchain: Vec<u64>| {
+            RecordEntry::Sample(SampleRecord {
+                tid,
+                ip: 0x10,
+                period,
+                callchain,
+                ..Default::default()
+            })
+        };
+        let file = RecordFile {
+            header: RecordHeader::new(&SysRoot::default(), Vec::new(), Vec::new()),
+            entries: vec![
+                sample(7, 3, Vec::new()),
+                RecordEntry::Comm(CommRecord { tid: 7, comm: "a;b".into(), ..Default::default() }),
+                sample(7, 5, vec![kernel, 0xffff_1000, user, 0x20, 0x30]),
+                sample(7, 2, vec![user, 0x20, 0x30]),
+                sample(7, 0, vec![user, 0x20, 0x30]),
+            ],
+        };
+        let profile = on_cpu(&file, &SampleFilter::default());
+        assert_eq!(profile.total, 3 + 5 + 2 + 1);
+        let heaviest = profile.by_weight()[0];
+        assert_eq!((heaviest.0.frames.as_slice(), heaviest.1), (&[0xffff_1000, 0x20, 0x30][..], 5));
+        assert_eq!(
+            profile.folded(hex_frame),
+            [":;0x10 3", "a_b;0x30;0x20 3", "a_b;0x30;0x20;0xffff1000 5"]
+        );
+        // Frames that share a name are merged. / 同名的帧会被合并。
+        assert_eq!(profile.folded(|_, _| vec!["f".into()]), [":;f 3", "a_b;f;f 3", "a_b;f;f;f 5"]);
+        // Inlined functions become frames of their own, outermost first.
+        // 内联函数各自成为一帧，最外层在前。
+        let inlined = |_, addr| match addr {
+            0x10 => vec!["inner".to_string(), "outer".to_string()],
+            _ => hex_frame(0, addr),
+        };
+        assert_eq!(profile.folded(inlined)[0], ":;outer;inner 3");
+    }
+
+    #[test]
+    fn test_sample_filter() {
+        assert_eq!(
+            "1.5,3".parse::<TimeRange>().unwrap(),
+            TimeRange { start: Some(1.5), end: Some(3.0) }
+        );
+        assert_eq!(",2".parse::<TimeRange>().unwrap(), TimeRange { start: None, end: Some(2.0) });
+        assert!("2".parse::<TimeRange>().is_err());
+        assert!("-1,".parse::<TimeRange>().is_err());
+
+        let sample = |pid, tid, cpu, time| {
+            RecordEntry::Sample(SampleRecord {
+                pid,
+                tid,
+                cpu,
+                time,
+                ip: 0x10,
+                period: 1,
+                ..Default::default()
+            })
+        };
+        // Times count from the first sample, at 5 s.
+        // 时间从位于 5 秒处的首个采样起算。
+        let file = RecordFile {
+            header: RecordHeader::new(&SysRoot::default(), Vec::new(), Vec::new()),
+            entries: vec![
+                sample(1, 1, 0, 5_000_000_000),
+                sample(1, 2, 1, 6_000_000_000),
+                sample(2, 3, 1, 7_500_000_000),
+            ],
+        };
+        let total = |filter: SampleFilter| on_cpu(&file, &filter).total;
+        assert_eq!(total(SampleFilter::default()), 3);
+        assert_eq!(total(SampleFilter { pids: vec![1], ..Default::default() }), 2);
+        assert_eq!(total(SampleFilter { tids: vec![2, 3], ..Default::default() }), 2);
+        assert_eq!(total(SampleFilter { pids: vec![1], cpus: vec![1], ..Default::default() }), 1);
+        let time = "1,2.5".parse().unwrap();
+        assert_eq!(total(SampleFilter { time, ..Default::default() }), 1);
+        let time = "1,".parse().unwrap();
+        assert_eq!(total(SampleFilter { time, ..Default::default() }), 2);
+    }
+}
@@ -0,0 +1,462 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scheduler latency from `sched_switch`, `sched_wakeup` and
+//! `sched_migrate_task` samples, like `perf sched latency`: per-task
+//! runtime, wait time, scheduling delay and migrations, and which task ran
+//! on each CPU when.
+//!
+//! 基于 `sched_switch`、`sched_wakeup` 与 `sched_migrate_task` 采样的调度延迟
+//! 分析，类似 `perf sched latency`：按任务统计运行时间、等待时间、调度延迟和
+//! 迁移次数，并给出每个 CPU 上各任务的运行时间线。
+
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::perf_record::{RecordFile, RecordedEvent, SampleRecord};
+use pipa_collector::tracepoint::{FieldValue, TracepointFormat};
+use std::collections::{BTreeMap, HashMap};
+
+/// The tracepoints the analysis reads. / 分析所读取的跟踪点。
+pub const EVENTS: [&str; 4] = [
+    "sched:sched_switch",
+    "sched:sched_wakeup",
+    "sched:sched_wakeup_new",
+    "sched:sched_migrate_task",
+];
+
+/// A decoded scheduler event. / 解码后的调度器事件。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchedEventKind {
+    /// `prev` left the CPU in `prev_state` and `next` started running.
+    /// `prev` 以 `prev_state` 状态离开 CPU，`next` 开始运行。
+    Switch { prev_tid: u32, prev_comm: String, prev_state: i64, next_tid: u32, next_comm: String },
+    /// A task became runnable. / 任务变为可运行。
+    Wakeup { tid: u32, comm: String },
+    /// A task moved to another CPU. / 任务迁移到另一个 CPU。
+    Migrate { tid: u32, comm: String, orig_cpu: u32, dest_cpu: u32 },
+}
+
+/// A scheduler event with the time and CPU it was seen on.
+/// 带有发生时间与 CPU 的调度器事件。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedEvent {
+    pub time: u64,
+    pub cpu: u32,
+    pub kind: SchedEventKind,
+}
+
+fn field(
+    format: &TracepointFormat,
+    raw: &[u8],
+    name: &str,
+) -> Result<FieldValue, PipaCollectorError> {
+    let field = format.field(name).ok_or_else(|| {
+        PipaCollectorError::InvalidFormat(format!("{} has no field `{}`", format.name, name))
+    })?;
+    format.decode_field(field, raw)
+}
+
+/// Decodes an integer field of a tracepoint payload. / 解码跟踪点负载中的整数字段。
+pub(crate) fn int(
+    format: &TracepointFormat,
+    raw: &[u8],
+    name: &str,
+) -> Result<i64, PipaCollectorError> {
+    match field(format, raw, name)? {
+        FieldValue::Int(v) => Ok(v),
+        FieldValue::Uint(v) => Ok(v as i64),
+        other => Err(PipaCollectorError::InvalidFormat(format!(
+            "{}: `{}` is not an integer: {}",
+            format.name, name, other
+        ))),
+    }
+}
+
+fn string(format: &TracepointFormat, raw: &[u8], name: &str) -> Result<String, PipaCollectorError> {
+    Ok(field(format, raw, name)?.to_string())
+}
+
+/// Decodes a sample of one of [`EVENTS`]; other events give `None`.
+/// 解码 [`EVENTS`] 之一的采样；其他事件返回 `None`。
+pub fn decode_sample(
+    events: &[RecordedEvent],
+    sample: &SampleRecord,
+) -> Result<Option<SchedEvent>, PipaCollectorError> {
+    let Some(event) = events.get(sample.event) else {
+        return Ok(None);
+    };
+    let Some(format) = &event.format else {
+        return Ok(None);
+    };
+    let raw = &sample.raw;
+    let kind = match event.name.as_str() {
+        "sched:sched_switch" => SchedEventKind::Switch {
+            prev_tid: int(format, raw, "prev_pid")? as u32,
+            prev_comm: string(format, raw, "prev_comm")?,
+            prev_state: int(format, raw, "prev_state")?,
+            next_tid: int(format, raw, "next_pid")? as u32,
+            next_comm: string(format, raw, "next_comm")?,
+        },
+        "sched:sched_wakeup" | "sched:sched_wakeup_new" => SchedEventKind::Wakeup {
+            tid: int(format, raw, "pid")? as u32,
+            comm: string(format, raw, "comm")?,
+        },
+        "sched:sched_migrate_task" => SchedEventKind::Migrate {
+            tid: int(format, raw, "pid")? as u32,
+            comm: string(format, raw, "comm")?,
+            orig_cpu: int(format, raw, "orig_cpu")? as u32,
+            dest_cpu: int(format, raw, "dest_cpu")? as u32,
+        },
+        _ => return Ok(None),
+    };
+    Ok(Some(SchedEvent { time: sample.time, cpu: sample.cpu, kind }))
+}
+
+/// Whether a task switched out in `prev_state` is still runnable, that is,
+/// it was preempted rather than going to sleep. The low byte holds the
+/// sleep states; bit 8 only marks preemption.
+/// 以 `prev_state` 状态切出的任务是否仍可运行，即被抢占而非进入睡眠。
+/// 低字节为睡眠状态；第 8 位仅标记抢占。
+pub fn is_preempted(prev_state: i64) -> bool {
+    prev_state & 0xff == 0
+}
+
+/// Scheduling statistics of one task. Times are in nanoseconds.
+/// 单个任务的调度统计。时间以纳秒为单位。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskLatency {
+    pub tid: u32,
+    /// The last name seen for the task. / 任务最近一次出现时的名称。
+    pub comm: String,
+    /// Time spent running. / 运行时间。
+    pub runtime: u64,
+    /// Times the task was switched in. / 任务被切入的次数。
+    pub switches: u64,
+    /// Switch-ins whose runnable time was seen, which the delays cover.
+    /// 观察到可运行时刻的切入次数，调度延迟仅覆盖这些切入。
+    pub delays: u64,
+    /// Time spent runnable but waiting for a CPU. / 可运行但等待 CPU 的时间。
+    pub wait: u64,
+    /// The longest wait. / 最长的一次等待。
+    pub max_delay: u64,
+    /// When the longest wait ended. / 最长等待结束的时刻。
+    pub max_delay_at: u64,
+    pub migrations: u64,
+}
+
+impl TaskLatency {
+    /// The mean scheduling delay. / 平均调度延迟。
+    pub fn avg_delay(&self) -> Option<u64> {
+        self.wait.checked_div(self.delays)
+    }
+}
+
+/// A stretch of time one task ran on a CPU; tid 0 is the idle task.
+/// 某个任务在 CPU 上运行的一段时间；tid 0 为空闲任务。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuSlice {
+    pub start: u64,
+    pub end: u64,
+    pub tid: u32,
+    pub comm: String,
+}
+
+/// The result of [`SchedAnalyzer::finish`]. / [`SchedAnalyzer::finish`] 的结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchedAnalysis {
+    /// Tasks by tid, without the idle task. / 按 tid 索引的任务，不含空闲任务。
+    pub tasks: BTreeMap<u32, TaskLatency>,
+    /// What ran on each CPU, in time order. / 每个 CPU 上按时间顺序运行的任务。
+    pub timeline: BTreeMap<u32, Vec<CpuSlice>>,
+    /// The first and last event times. / 第一个与最后一个事件的时间。
+    pub start: u64,
+    pub end: u64,
+}
+
+impl SchedAnalysis {
+    /// Tasks sorted by maximum delay, then runtime, descending, as
+    /// `perf sched latency` lists the worst first.
+    /// 与 `perf sched latency` 一样将最差的排在最前：按最大延迟、再按运行时间降序排列。
+    pub fn by_max_delay(&self) -> Vec<&TaskLatency> {
+        let mut tasks: Vec<_> = self.tasks.values().collect();
+        tasks.sort_by(|a, b| (b.max_delay, b.runtime, a.tid).cmp(&(a.max_delay, a.runtime, b.tid)));
+        tasks
+    }
+}
+
+/// What a task is doing. / 任务当前的状态。
+#[derive(Debug, Clone, Copy)]
+enum TaskState {
+    Running { since: u64 },
+    Runnable { since: u64 },
+    Sleeping,
+}
+
+/// Builds a [`SchedAnalysis`] from a time-ordered stream of events.
+/// 从按时间排序的事件流构建 [`SchedAnalysis`]。
+#[derive(Debug, Default)]
+pub struct SchedAnalyzer {
+    states: HashMap<u32, TaskState>,
+    /// The slice in progress on each CPU. / 每个 CPU 上正在进行的时间片。
+    current: HashMap<u32, (u64, u32, String)>,
+    analysis: SchedAnalysis,
+    seen: bool,
+}
+
+impl SchedAnalyzer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn task(&mut self, tid: u32, comm: &str) -> &mut TaskLatency {
+        let task = self
+            .analysis
+            .tasks
+            .entry(tid)
+            .or_insert_with(|| TaskLatency { tid, ..Default::default() });
+        if task.comm != comm {
+            task.comm = comm.to_string();
+        }
+        task
+    }
+
+    /// Feeds one event. / 输入一个事件。
+    pub fn event(&mut self, event: &SchedEvent) {
+        if !self.seen {
+            self.analysis.start = event.time;
+            self.seen = true;
+        }
+        self.analysis.end = self.analysis.end.max(event.time);
+        let time = event.time;
+        match &event.kind {
+            SchedEventKind::Switch { prev_tid, prev_comm, prev_state, next_tid, next_comm } => {
+                if *prev_tid != 0 {
+                    if let Some(TaskState::Running { since }) = self.states.get(prev_tid) {
+                        let ran = time.saturating_sub(*since);
+                        self.task(*prev_tid, prev_comm).runtime += ran;
+                    } else {
+                        self.task(*prev_tid, prev_comm);
+                    }
+                    let state = if is_preempted(*prev_state) {
+                        TaskState::Runnable { since: time }
+                    } else {
+                        TaskState::Sleeping
+                    };
+                    self.states.insert(*prev_tid, state);
+                }
+                if *next_tid != 0 {
+                    let ready = self.states.insert(*next_tid, TaskState::Running { since: time });
+                    let task = self.task(*next_tid, next_comm);
+                    task.switches += 1;
+                    if let Some(TaskState::Runnable { since }) = ready {
+                        let delay = time.saturating_sub(since);
+                        task.delays += 1;
+                        task.wait += delay;
+                        if delay >= task.max_delay {
+                            task.max_delay = delay;
+                            task.max_delay_at = time;
+                        }
+                    }
+                }
+                let next = (time, *next_tid, next_comm.clone());
+                if let Some((start, tid, comm)) = self.current.insert(event.cpu, next) {
+                    let slice = CpuSlice { start, end: time, tid, comm };
+                    self.analysis.timeline.entry(event.cpu).or_default().push(slice);
+                }
+            }
+            SchedEventKind::Wakeup { tid, comm } => {
+                // Wakeups of running or already runnable tasks change nothing.
+                // 对正在运行或已可运行任务的唤醒不改变状态。
+                if !matches!(
+                    self.states.get(tid),
+                    Some(TaskState::Running { .. } | TaskState::Runnable { .. })
+                ) {
+                    self.states.insert(*tid, TaskState::Runnable { since: time });
+                }
+                self.task(*tid, comm);
+            }
+            SchedEventKind::Migrate { tid, comm, .. } => {
+                self.task(*tid, comm).migrations += 1;
+            }
+        }
+    }
+
+    /// Ends the stream, charging tasks still running up to the last event.
+    /// 结束事件流，仍在运行的任务的运行时间计至最后一个事件。
+    pub fn finish(mut self) -> SchedAnalysis {
+        let end = self.analysis.end;
+        for (tid, state) in std::mem::take(&mut self.states) {
+            if let (TaskState::Running { since }, Some(task)) =
+                (state, self.analysis.tasks.get_mut(&tid))
+            {
+                task.runtime += end.saturating_sub(since);
+            }
+        }
+        for (cpu, (start, tid, comm)) in std::mem::take(&mut self.current) {
+            let slices = self.analysis.timeline.entry(cpu).or_default();
+            slices.push(CpuSlice { start, end, tid, comm });
+        }
+        self.analysis
+    }
+}
+
+/// Analyzes the scheduler samples of a record file, in time order.
+/// 按时间顺序分析记录文件中的调度器采样。
+pub fn analyze(file: &RecordFile) -> Result<SchedAnalysis, PipaCollectorError> {
+    let mut samples: Vec<&SampleRecord> = file.samples().collect();
+    samples.sort_by_key(|s| s.time);
+    let mut analyzer = SchedAnalyzer::new();
+    for sample in samples {
+        if let Some(event) = decode_sample(&file.header.events, sample)? {
+            analyzer.event(&event);
+        }
+    }
+    Ok(analyzer.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pipa_collector::tracepoint::parse_format;
+
+    fn switch(time: u64, cpu: u32, prev: (u32, &str, i64), next: (u32, &str)) -> SchedEvent {
+        SchedEvent {
+            time,
+            cpu,
+            kind: SchedEventKind::Switch {
+                prev_tid: prev.0,
+                prev_comm: prev.1.into(),
+                prev_state: prev.2,
+                next_tid: next.0,
+                next_comm: next.1.into(),
+            },
+        }
+    }
+
+    fn wakeup(time: u64, tid: u32, comm: &str) -> SchedEvent {
+        SchedEvent { time, cpu: 0, kind: SchedEventKind::Wakeup { tid, comm: comm.into() } }
+    }
+
+    #[test]
+    fn test_analyze_latency_and_timeline() {
+        let mut analyzer = SchedAnalyzer::new();
+        // `app` runs on CPU 0, is preempted by `kworker` after it wakes up,
+        // then sleeps while `kworker` runs and is woken again later.
+        // `app` 在 CPU 0 上运行，被唤醒的 `kworker` 抢占后再次运行，
+        // 随后进入睡眠，稍后又被唤醒。
+        for event in [
+            switch(1_000, 0, (0, "swapper/0", 0), (10, "app")),
+            wakeup(2_000, 20, "kworker"),
+            switch(3_000, 0, (10, "app", 0x100), (20, "kworker")),
+            switch(4_000, 0, (20, "kworker", 1), (10, "app")),
+            switch(9_000, 0, (10, "app", 1), (0, "swapper/0")),
+            wakeup(10_000, 10, "app"),
+            SchedEvent {
+                time: 10_500,
+                cpu: 1,
+                kind: SchedEventKind::Migrate {
+                    tid: 10,
+                    comm: "app".into(),
+                    orig_cpu: 0,
+                    dest_cpu: 1,
+                },
+            },
+            switch(12_000, 1, (0, "swapper/1", 0), (10, "app")),
+            switch(15_000, 1, (10, "app", 0), (30, "other")),
+        ] {
+            analyzer.event(&event);
+        }
+        let analysis = analyzer.finish();
+        assert_eq!((analysis.start, analysis.end), (1_000, 15_000));
+
+        let app = &analysis.tasks[&10];
+        assert_eq!(
+            *app,
+            TaskLatency {
+                tid: 10,
+                comm: "app".into(),
+                runtime: 2_000 + 5_000 + 3_000,
+                switches: 3,
+                delays: 2,
+                wait: 1_000 + 2_000,
+                max_delay: 2_000,
+                max_delay_at: 12_000,
+                migrations: 1,
+            }
+        );
+        assert_eq!(app.avg_delay(), Some(1_500));
+        let kworker = &analysis.tasks[&20];
+        assert_eq!((kworker.runtime, kworker.max_delay, kworker.switches), (1_000, 1_000, 1));
+        // `other` was never seen runnable, so it has no delay.
+        // 从未观察到 `other` 可运行，因此没有延迟。
+        assert_eq!(analysis.tasks[&30].avg_delay(), None);
+        assert!(!analysis.tasks.contains_key(&0));
+
+        let order: Vec<u32> = analysis.by_max_delay().iter().map(|t| t.tid).collect();
+        assert_eq!(order, [10, 20, 30]);
+
+        let cpu0: Vec<(u64, u64, u32)> =
+            analysis.timeline[&0].iter().map(|s| (s.start, s.end, s.tid)).collect();
+        assert_eq!(
+            cpu0,
+            [(1_000, 3_000, 10), (3_000, 4_000, 20), (4_000, 9_000, 10), (9_000, 15_000, 0)]
+        );
+        assert_eq!(analysis.timeline[&1].last().unwrap().comm, "other");
+    }
+
+    #[test]
+    fn test_decode_sample() {
+        let format = parse_format(
+            "name: sched_switch\nID: 372\nformat:\n\
+             \tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;\n\n\
+             \tfield:char prev_comm[16];\toffset:8;\tsize:16;\tsigned:0;\n\
+             \tfield:pid_t prev_pid;\toffset:24;\tsize:4;\tsigned:1;\n\
+             \tfield:int prev_prio;\toffset:28;\tsize:4;\tsigned:1;\n\
+             \tfield:long prev_state;\toffset:32;\tsize:8;\tsigned:1;\n\
+             \tfield:char next_comm[16];\toffset:40;\tsize:16;\tsigned:0;\n\
+             \tfield:pid_t next_pid;\toffset:56;\tsize:4;\tsigned:1;\n\
+             \tfield:int next_prio;\toffset:60;\tsize:4;\tsigned:1;\n",
+        )
+        .unwrap();
+        let events = vec![
+            RecordedEvent { name: "cycles".into(), format: None },
+            RecordedEvent { name: "sched:sched_switch".into(), format: Some(format) },
+        ];
+        let mut raw = vec![0u8; 64];
+        raw[8..13].copy_from_slice(b"sleep");
+        raw[24..28].copy_from_slice(&6613i32.to_ne_bytes());
+        raw[32..40].copy_from_slice(&1i64.to_ne_bytes());
+        raw[40..47].copy_from_slice(b"pipa_rs");
+        raw[56..60].copy_from_slice(&6612i32.to_ne_bytes());
+        let mut sample = SampleRecord { event: 1, time: 7, cpu: 3, raw, ..Default::default() };
+        assert_eq!(
+            decode_sample(&events, &sample).unwrap(),
+            Some(SchedEvent {
+                time: 7,
+                cpu: 3,
+                kind: SchedEventKind::Switch {
+                    prev_tid: 6613,
+                    prev_comm: "sleep".into(),
+                    prev_state: 1,
+                    next_tid: 6612,
+                    next_comm: "pipa_rs".into(),
+                },
+            })
+        );
+        sample.raw.truncate(40);
+        assert!(decode_sample(&events, &sample).is_err());
+        sample.event = 0;
+        assert_eq!(decode_sample(&events, &sample).unwrap(), None);
+        assert!(is_preempted(0) && is_preempted(0x100) && !is_preempted(0x80));
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Per-syscall and per-thread statistics from `raw_syscalls:sys_enter` and
+//! `raw_syscalls:sys_exit` events, like `strace -c` and `perf trace -s`.
+//! Each exit is paired with the last enter of the same thread to measure
+//! the latency of the call.
+//!
+//! 基于 `raw_syscalls:sys_enter` 与 `raw_syscalls:sys_exit` 事件的按系统调用和
+//! 按线程统计，类似 `strace -c` 和 `perf trace -s`。每次退出都与同一线程最近的
+//! 一次进入配对，以测量调用的延迟。
+
+use crate::syscalls;
+use std::collections::{BTreeMap, HashMap};
+
+/// Whether a syscall return value is an error (`-4095..=-1`).
+/// 系统调用返回值是否表示错误（`-4095..=-1`）。
+pub fn is_error(ret: i64) -> bool {
+    (-4095..0).contains(&ret)
+}
+
+/// Statistics of one syscall. Latencies are in nanoseconds.
+/// 单个系统调用的统计。延迟以纳秒为单位。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyscallStats {
+    pub calls: u64,
+    pub errors: u64,
+    /// Calls whose enter and exit were both seen, which the latencies
+    /// cover. / 同时观察到进入与退出的调用数，延迟统计仅覆盖这些调用。
+    pub completed: u64,
+    pub total: u64,
+    pub min: u64,
+    pub max: u64,
+}
+
+impl SyscallStats {
+    fn add_latency(&mut self, latency: u64) {
+        self.min = if self.completed == 0 { latency } else { self.min.min(latency) };
+        self.max = self.max.max(latency);
+        self.total += latency;
+        self.completed += 1;
+    }
+
+    /// The mean latency of completed calls. / 已完成调用的平均延迟。
+    pub fn avg(&self) -> Option<u64> {
+        self.total.checked_div(self.completed)
+    }
+
+    fn merge(&mut self, other: &SyscallStats) {
+        if other.completed > 0 {
+            self.min = if self.completed == 0 { other.min } else { self.min.min(other.min) };
+        }
+        self.max = self.max.max(other.max);
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.completed += other.completed;
+        self.total += other.total;
+    }
+}
+
+/// The syscalls of one thread. / 单个线程的系统调用。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThreadSummary {
+    pub pid: u32,
+    pub tid: u32,
+    /// The last name reported for the thread; empty if unknown.
+    /// 线程最近一次报告的名称；未知时为空。
+    pub comm: String,
+    /// Statistics by syscall number. / 按系统调用编号划分的统计。
+    pub syscalls: BTreeMap<i64, SyscallStats>,
+}
+
+impl ThreadSummary {
+    /// Syscalls made by the thread. / 该线程发起的系统调用数。
+    pub fn calls(&self) -> u64 {
+        self.syscalls.values().map(|s| s.calls).sum()
+    }
+}
+
+/// A syscall whose exit was just seen. / 刚观察到退出的系统调用。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedSyscall {
+    pub tid: u32,
+    pub nr: i64,
+    pub ret: i64,
+    /// Latency in nanoseconds, unless the enter was not seen.
+    /// 延迟（纳秒），未观察到进入时为 `None`。
+    pub latency: Option<u64>,
+}
+
+/// The result of [`SyscallSummarizer::finish`]. / [`SyscallSummarizer::finish`] 的结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyscallSummary {
+    /// Threads by tid. / 按 tid 索引的线程。
+    pub threads: BTreeMap<u32, ThreadSummary>,
+}
+
+impl SyscallSummary {
+    /// Statistics by syscall over all threads. / 所有线程合计的按系统调用统计。
+    pub fn syscalls(&self) -> BTreeMap<i64, SyscallStats> {
+        let mut totals: BTreeMap<i64, SyscallStats> = BTreeMap::new();
+        for thread in self.threads.values() {
+            for (nr, stats) in &thread.syscalls {
+                totals.entry(*nr).or_default().merge(stats);
+            }
+        }
+        totals
+    }
+
+    /// Syscalls made by all threads. / 所有线程发起的系统调用数。
+    pub fn calls(&self) -> u64 {
+        self.threads.values().map(ThreadSummary::calls).sum()
+    }
+}
+
+/// Sorts statistics by total latency, then calls, descending, as both
+/// `strace -c` and `perf trace -s` do.
+/// 与 `strace -c` 和 `perf trace -s` 一样，按总延迟、再按调用次数降序排列统计。
+pub fn by_total_time(syscalls: &BTreeMap<i64, SyscallStats>) -> Vec<(i64, &SyscallStats)> {
+    let mut sorted: Vec<_> = syscalls.iter().map(|(nr, stats)| (*nr, stats)).collect();
+    sorted.sort_by(|a, b| (b.1.total, b.1.calls, a.0).cmp(&(a.1.total, a.1.calls, b.0)));
+    sorted
+}
+
+/// Builds a [`SyscallSummary`] from a time-ordered stream of events.
+/// 从按时间排序的事件流构建 [`SyscallSummary`]。
+#[derive(Debug, Default)]
+pub struct SyscallSummarizer {
+    /// The architecture whose syscall numbers the events carry.
+    /// 事件中系统调用编号所属的架构。
+    arch: String,
+    /// The syscall each thread is in, with its enter time.
+    /// 每个线程当前所在的系统调用及其进入时间。
+    pending: HashMap<u32, (i64, u64)>,
+    comms: HashMap<u32, String>,
+    summary: SyscallSummary,
+}
+
+impl SyscallSummarizer {
+    /// A summarizer for the syscall numbers of `arch`, as in
+    /// `std::env::consts::ARCH`. / 针对 `arch`（同 `std::env::consts::ARCH`）
+    /// 系统调用编号的汇总器。
+    pub fn new(arch: &str) -> Self {
+        Self { arch: arch.to_string(), ..Default::default() }
+    }
+
+    fn thread(&mut self, pid: u32, tid: u32) -> &mut ThreadSummary {
+        self.summary.threads.entry(tid).or_insert_with(|| ThreadSummary {
+            pid,
+            tid,
+            ..Default::default()
+        })
+    }
+
+    /// Records the name of a thread. / 记录线程名称。
+    pub fn set_comm(&mut self, tid: u32, comm: &str) {
+        self.comms.insert(tid, comm.to_string());
+    }
+
+    /// A `sys_enter` of syscall `nr` at `time` nanoseconds.
+    /// 在 `time` 纳秒时进入编号为 `nr` 的系统调用。
+    pub fn enter(&mut self, pid: u32, tid: u32, time: u64, nr: i64) {
+        // A thread still in a syscall that never returned, such as `execve`
+        // into a binary whose exit we missed, is counted without a latency.
+        // 仍处于一个从未返回的系统调用中的线程，该调用计数但不计延迟。
+        if let Some((previous, _)) = self.pending.insert(tid, (nr, time)) {
+            self.thread(pid, tid).syscalls.entry(previous).or_default().calls += 1;
+        }
+        self.thread(pid, tid);
+    }
+
+    /// A `sys_exit` of syscall `nr` with return value `ret` at `time`.
+    /// 在 `time` 时以返回值 `ret` 退出编号为 `nr` 的系统调用。
+    pub fn exit(&mut self, pid: u32, tid: u32, time: u64, nr: i64, ret: i64) -> CompletedSyscall {
+        if ret == 0 && !self.pending.contains_key(&tid) && syscalls::creates_task(&self.arch, nr) {
+            // The new task's side of a `clone`, counted once by its parent.
+            // 新任务一侧的 `clone` 返回，已由其父任务计数一次。
+            return CompletedSyscall { tid, nr, ret, latency: None };
+        }
+        // `rt_sigreturn` restores registers that report syscall -1 on exit.
+        // `rt_sigreturn` 恢复的寄存器使退出时报告的系统调用为 -1。
+        let nr = match self.pending.get(&tid) {
+            Some(&(entered, _)) if nr < 0 => entered,
+            _ => nr,
+        };
+        let latency = match self.pending.remove(&tid) {
+            Some((entered, start)) if entered == nr => Some(time.saturating_sub(start)),
+            Some((entered, _)) => {
+                self.thread(pid, tid).syscalls.entry(entered).or_default().calls += 1;
+                None
+            }
+            None => None,
+        };
+        let stats = self.thread(pid, tid).syscalls.entry(nr).or_default();
+        stats.calls += 1;
+        if is_error(ret) {
+            stats.errors += 1;
+        }
+        if let Some(latency) = latency {
+            stats.add_latency(latency);
+        }
+        CompletedSyscall { tid, nr, ret, latency }
+    }
+
+    /// Ends the stream. Syscalls still in progress, such as `exit_group`,
+    /// are counted without a latency.
+    /// 结束事件流。仍在进行中的系统调用（如 `exit_group`）计数但不计延迟。
+    pub fn finish(mut self) -> SyscallSummary {
+        for (tid, (nr, _)) in std::mem::take(&mut self.pending) {
+            if let Some(thread) = self.summary.threads.get_mut(&tid) {
+                thread.syscalls.entry(nr).or_default().calls += 1;
+            }
+        }
+        for thread in self.summary.threads.values_mut() {
+            if let Some(comm) = self.comms.remove(&thread.tid) {
+                thread.comm = comm;
+            }
+        }
+        self.summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_syscalls() {
+        let mut summarizer = SyscallSummarizer::new("x86_64");
+        summarizer.set_comm(10, "cat");
+        // Thread 10 reads twice, one failing, then opens and exits.
+        // 线程 10 读取两次（一次失败），然后打开文件并退出。
+        summarizer.enter(10, 10, 1_000, 0);
+        assert_eq!(
+            summarizer.exit(10, 10, 4_000, 0, 512),
+            CompletedSyscall { tid: 10, nr: 0, ret: 512, latency: Some(3_000) }
+        );
+        summarizer.enter(10, 10, 5_000, 0);
+        summarizer.exit(10, 10, 6_000, 0, -11);
+        // Thread 11 was already in a syscall when tracing started.
+        // 开始跟踪时线程 11 已处于系统调用中。
+        assert_eq!(summarizer.exit(10, 11, 6_500, 7, 1).latency, None);
+        summarizer.enter(10, 10, 7_000, 257);
+        summarizer.exit(10, 10, 17_000, 257, 3);
+        summarizer.enter(10, 10, 20_000, 231);
+
+        let summary = summarizer.finish();
+        assert_eq!(summary.calls(), 5);
+        let cat = &summary.threads[&10];
+        assert_eq!((cat.comm.as_str(), cat.calls()), ("cat", 4));
+        assert_eq!(
+            cat.syscalls[&0],
+            SyscallStats {
+                calls: 2,
+                errors: 1,
+                completed: 2,
+                total: 4_000,
+                min: 1_000,
+                max: 3_000
+            }
+        );
+        assert_eq!(cat.syscalls[&0].avg(), Some(2_000));
+        assert_eq!(cat.syscalls[&231].calls, 1);
+        assert_eq!(cat.syscalls[&231].avg(), None);
+        assert_eq!(summary.threads[&11].comm, "");
+
+        let totals = summary.syscalls();
+        let order: Vec<i64> = by_total_time(&totals).into_iter().map(|(nr, _)| nr).collect();
+        assert_eq!(order, [257, 0, 7, 231]);
+    }
+
+    #[test]
+    fn test_merge_and_mismatched_exit() {
+        let mut summarizer = SyscallSummarizer::new("x86_64");
+        summarizer.enter(1, 1, 0, 1);
+        summarizer.exit(1, 1, 50, 1, 0);
+        summarizer.enter(1, 2, 0, 1);
+        summarizer.exit(1, 2, 10, 1, 0);
+        // An exit that does not match the pending enter.
+        // 与挂起的进入不匹配的退出。
+        summarizer.enter(1, 2, 20, 3);
+        assert_eq!(summarizer.exit(1, 2, 30, 1, -2).latency, None);
+        // Thread 3 returns from the `clone` that created it.
+        // 线程 3 从创建它的 `clone` 中返回。
+        summarizer.enter(1, 1, 40, 56);
+        summarizer.exit(1, 3, 45, 56, 0);
+        summarizer.exit(1, 1, 50, 56, 3);
+        summarizer.enter(1, 1, 60, 15);
+        assert_eq!(summarizer.exit(1, 1, 62, -1, 0).nr, 15);
+
+        let summary = summarizer.finish();
+        assert!(!summary.threads.contains_key(&3));
+        let totals = summary.syscalls();
+        assert_eq!(
+            totals[&1],
+            SyscallStats { calls: 3, errors: 1, completed: 2, total: 60, min: 10, max: 50 }
+        );
+        assert_eq!(totals[&3].calls, 1);
+        assert_eq!((totals[&56].calls, totals[&56].completed), (1, 1));
+        assert_eq!((totals[&15].calls, totals[&15].total), (1, 2));
+        assert!(is_error(-4095) && !is_error(-4096) && !is_error(0));
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Build-IDs, which name the exact version of an ELF file, and a local store
+//! of binaries by build-ID, like perf's `~/.debug`, so that a recording can
+//! still be symbolized after the files it mapped have changed.
+//!
+//! 构建 ID（标识 ELF 文件的确切版本）以及按构建 ID 保存二进制文件的本地仓库
+//! （类似 perf 的 `~/.debug`），使得记录所映射的文件发生变化后仍能进行符号解析。
+
+use crate::elf;
+use object::Object;
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::perf_record::{BuildIdRecord, MmapRecord};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Where distributions install separate debug info by build-ID.
+/// 发行版按构建 ID 安装独立调试信息的位置。
+pub const SYSTEM_DEBUG_DIR: &str = "/usr/lib/debug/.build-id";
+
+/// The build-ID of an ELF file, from its `.note.gnu.build-id` section.
+/// ELF 文件的构建 ID，取自其 `.note.gnu.build-id` 节。
+pub fn build_id(file: &object::File<'_>) -> Option<Vec<u8>> {
+    file.build_id().ok().flatten().filter(|id| !id.is_empty()).map(<[u8]>::to_vec)
+}
+
+/// Reads the build-ID of the ELF file at `path`, if it has one.
+/// 读取 `path` 处 ELF 文件的构建 ID（如有）。
+pub fn read_build_id(path: &Path) -> Result<Option<Vec<u8>>, PipaCollectorError> {
+    let data = std::fs::read(path).map_err(|e| PipaCollectorError::from(e).at_path(path))?;
+    Ok(build_id(&elf::parse(&data).map_err(|e| e.at_path(path))?))
+}
+
+/// The build-ID of each file in `mmaps`: the one the kernel reported with
+/// the mapping, else the one the file has now. Anonymous and special
+/// mappings, and files without a build-ID, are left out.
+/// `mmaps` 中每个文件的构建 ID：优先取内核随映射报告的 ID，否则取文件当前的 ID。
+/// 匿名映射、特殊映射以及没有构建 ID 的文件不计入。
+pub fn build_id_records<'a, I>(mmaps: I) -> Vec<BuildIdRecord>
+where
+    I: IntoIterator<Item = &'a MmapRecord>,
+{
+    let mut files: BTreeMap<&str, Option<&[u8]>> = BTreeMap::new();
+    for mmap in mmaps {
+        if !mmap.filename.starts_with('/') || mmap.filename.starts_with("//") {
+            continue;
+        }
+        let reported = files.entry(&mmap.filename).or_default();
+        if !mmap.build_id.is_empty() {
+            *reported = Some(&mmap.build_id);
+        }
+    }
+    files
+        .into_iter()
+        .filter_map(|(filename, reported)| {
+            let build_id = match reported {
+                Some(id) => id.to_vec(),
+                None => read_build_id(Path::new(filename)).ok().flatten()?,
+            };
+            Some(BuildIdRecord { filename: filename.to_string(), build_id })
+        })
+        .collect()
+}
+
+/// Formats a build-ID as lowercase hex. / 将构建 ID 格式化为小写十六进制。
+pub fn hex(id: &[u8]) -> String {
+    id.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Binaries stored by build-ID under a cache directory, plus the separate
+/// debug info installed under [`SYSTEM_DEBUG_DIR`]. Both use the
+/// `xx/yyyy...` layout, split after the first byte of the ID.
+/// 在缓存目录中按构建 ID 保存的二进制文件，以及安装在 [`SYSTEM_DEBUG_DIR`]
+/// 下的独立调试信息。两者都使用在 ID 第一个字节后分割的 `xx/yyyy...` 布局。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildIdCache {
+    root: PathBuf,
+    debug_dirs: Vec<PathBuf>,
+}
+
+impl BuildIdCache {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        BuildIdCache { root: root.into(), debug_dirs: vec![PathBuf::from(SYSTEM_DEBUG_DIR)] }
+    }
+
+    /// `$XDG_CACHE_HOME/pipa/buildid`, or `~/.cache/pipa/buildid`.
+    /// `$XDG_CACHE_HOME/pipa/buildid`，或 `~/.cache/pipa/buildid`。
+    pub fn default_root() -> Option<PathBuf> {
+        let cache = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+        };
+        Some(cache.join("pipa").join("buildid"))
+    }
+
+    /// Looks for separate debug info in `dirs` instead of [`SYSTEM_DEBUG_DIR`].
+    /// 改为在 `dirs` 而不是 [`SYSTEM_DEBUG_DIR`] 中查找独立调试信息。
+    pub fn with_debug_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.debug_dirs = dirs;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn entry(dir: &Path, id: &[u8], suffix: &str) -> Option<PathBuf> {
+        let hex = hex(id);
+        (hex.len() > 2).then(|| dir.join(&hex[..2]).join(format!("{}{}", &hex[2..], suffix)))
+    }
+
+    /// Copies the ELF file at `path` into the cache, provided it still has
+    /// build-ID `id`. Returns whether it was added rather than already there.
+    /// 将 `path` 处的 ELF 文件复制到缓存中，前提是其构建 ID 仍为 `id`。
+    /// 返回是否为新加入（而非已存在）。
+    pub fn store(&self, path: &Path, id: &[u8]) -> Result<bool, PipaCollectorError> {
+        let entry = Self::entry(&self.root, id, "").ok_or_else(|| {
+            PipaCollectorError::InvalidFormat(format!(
+                "build-ID of {} is too short",
+                path.display()
+            ))
+        })?;
+        if entry.exists() {
+            return Ok(false);
+        }
+        let data = std::fs::read(path).map_err(|e| PipaCollectorError::from(e).at_path(path))?;
+        let file = elf::parse(&data).map_err(|e| e.at_path(path))?;
+        if build_id(&file).as_deref() != Some(id) {
+            return Err(PipaCollectorError::InvalidFormat(format!(
+                "{} no longer has build-ID {}",
+                path.display(),
+                hex(id)
+            )));
+        }
+        let dir = entry.parent().unwrap_or(&self.root);
+        std::fs::create_dir_all(dir).map_err(|e| PipaCollectorError::from(e).at_path(dir))?;
+        // Write under a temporary name so that readers never see half a file.
+        // 先以临时名称写入，使读取者不会看到不完整的文件。
+        let partial = entry.with_extension("tmp");
+        std::fs::write(&partial, &data)
+            .map_err(|e| PipaCollectorError::from(e).at_path(&partial))?;
+        std::fs::rename(&partial, &entry)
+            .map_err(|e| PipaCollectorError::from(e).at_path(&entry))?;
+        Ok(true)
+    }
+
+    /// The stored copy of the binary with build-ID `id`.
+    /// 构建 ID 为 `id` 的二进制文件的已保存副本。
+    pub fn binary(&self, id: &[u8]) -> Option<PathBuf> {
+        Self::entry(&self.root, id, "").filter(|path| path.is_file())
+    }
+
+    /// The separate debug info of the binary with build-ID `id`.
+    /// 构建 ID 为 `id` 的二进制文件的独立调试信息。
+    pub fn debug_file(&self, id: &[u8]) -> Option<PathBuf> {
+        self.debug_dirs
+            .iter()
+            .filter_map(|dir| Self::entry(dir, id, ".debug"))
+            .find(|path| path.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::fixture;
+
+    const INLINE_ID: &str = "4f53c5e3f5777c959bf72fac92bee1f82f898dc5";
+
+    fn mmap(filename: &str, build_id: Vec<u8>) -> MmapRecord {
+        MmapRecord {
+            pid: 1,
+            tid: 1,
+            start: 0x1000,
+            len: 0x1000,
+            pgoff: 0,
+            filename: filename.to_string(),
+            build_id,
+            time: 0,
+        }
+    }
+
+    #[test]
+    fn test_build_id_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let debug = dir.path().join("debug");
+        let cache =
+            BuildIdCache::new(dir.path().join("cache")).with_debug_dirs(vec![debug.clone()]);
+        let binary = fixture("symbolize/inline");
+        let id = read_build_id(&binary).unwrap().unwrap();
+        assert!(cache.binary(&id).is_none());
+
+        assert!(cache.store(&binary, &id).unwrap());
+        assert!(!cache.store(&binary, &id).unwrap());
+        let stored = cache.binary(&id).unwrap();
+        assert_eq!(stored, dir.path().join("cache/4f").join(&INLINE_ID[2..]));
+        assert_eq!(std::fs::read(stored).unwrap(), std::fs::read(&binary).unwrap());
+        // A file that was rebuilt since, and an ID too short to file.
+        // 此后被重新构建的文件，以及过短而无法存放的 ID。
+        assert!(cache.store(&binary, &[0xab, 0xcd]).is_err());
+        assert!(cache.store(&binary, &[0xab]).is_err());
+
+        let id = [0xab, 0xcd, 0xef, 0x01];
+        assert_eq!(hex(&id), "abcdef01");
+        assert!(cache.debug_file(&id).is_none());
+        std::fs::create_dir_all(debug.join("ab")).unwrap();
+        std::fs::write(debug.join("ab/cdef01.debug"), b"").unwrap();
+        assert_eq!(cache.debug_file(&id), Some(debug.join("ab/cdef01.debug")));
+    }
+
+    #[test]
+    fn test_read_build_id() {
+        let id = read_build_id(&fixture("symbolize/inline")).unwrap().unwrap();
+        assert_eq!(hex(&id), INLINE_ID);
+        assert!(read_build_id(Path::new("/nonexistent")).is_err());
+    }
+
+    #[test]
+    fn test_build_id_records() {
+        let inline = fixture("symbolize/inline").to_string_lossy().into_owned();
+        let mmaps = [
+            mmap(&inline, Vec::new()),
+            mmap("/app", vec![0xab, 0xcd]),
+            mmap("/app", Vec::new()),
+            mmap("//anon", Vec::new()),
+            mmap("[vdso]", Vec::new()),
+            mmap("/nonexistent", Vec::new()),
+        ];
+        let records: Vec<_> =
+            build_id_records(&mmaps).into_iter().map(|r| (r.filename, hex(&r.build_id))).collect();
+        assert_eq!(
+            records,
+            [("/app".to_string(), "abcd".to_string()), (inline, INLINE_ID.to_string())]
+        );
+    }
+}
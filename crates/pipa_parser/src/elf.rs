@@ -0,0 +1,101 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ELF files as they are mapped into a process: parsing, and the bias
+//! between runtime addresses and the addresses in the file that PIE
+//! executables, shared libraries and ASLR introduce.
+//!
+//! 映射到进程中的 ELF 文件：解析，以及 PIE 可执行文件、共享库和 ASLR
+//! 造成的运行时地址与文件内地址之间的偏差。
+
+use object::{Object, ObjectSegment};
+use pipa_collector::error::PipaCollectorError;
+
+/// Mappings start on a page boundary, and pages are at most 64 KiB.
+/// 映射起始于页边界，而页大小至多为 64 KiB。
+const MAX_PAGE_SIZE: u64 = 0x10000;
+
+/// Parses an ELF file. / 解析 ELF 文件。
+pub fn parse(data: &[u8]) -> Result<object::File<'_>, PipaCollectorError> {
+    object::File::parse(data)
+        .map_err(|e| PipaCollectorError::InvalidFormat(format!("not an ELF file: {}", e)))
+}
+
+/// The loadable segments of an ELF file, enough to relate its mappings to
+/// the addresses in the file. / ELF 文件的可加载段，足以将其映射与文件内地址关联。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadSegments {
+    /// `(vaddr, file offset, file size, page)` of each segment.
+    /// 每个段的 `(虚拟地址, 文件偏移, 文件大小, 页大小)`。
+    segments: Vec<(u64, u64, u64, u64)>,
+}
+
+impl LoadSegments {
+    pub fn new(file: &object::File<'_>) -> Self {
+        let segments = file
+            .segments()
+            .map(|segment| {
+                let (offset, size) = segment.file_range();
+                (segment.address(), offset, size, segment.align().clamp(1, MAX_PAGE_SIZE))
+            })
+            .collect();
+        LoadSegments { segments }
+    }
+
+    /// The load bias of the file mapped at `start` from file offset `pgoff`:
+    /// what to subtract from a runtime address in the mapping to get the
+    /// address in the file.
+    /// 文件从偏移 `pgoff` 处映射到 `start` 时的加载偏差：运行时地址减去该值即得
+    /// 文件内地址。
+    pub fn load_bias(&self, start: u64, pgoff: u64) -> u64 {
+        // Neighbouring segments can share a page, so prefer the one the
+        // mapping starts at. / 相邻的段可能共享一页，因此优先选择映射起始处的段。
+        let starts_at =
+            |&&(_, offset, _, page): &&(u64, u64, u64, u64)| offset - offset % page == pgoff;
+        let contains = |&&(_, offset, size, page): &&(u64, u64, u64, u64)| {
+            offset - offset % page <= pgoff && pgoff < offset + size
+        };
+        let segment =
+            self.segments.iter().find(starts_at).or_else(|| self.segments.iter().find(contains));
+        // Without a matching segment, assume addresses equal file offsets.
+        let (vaddr, offset) = segment.map(|&(vaddr, offset, ..)| (vaddr, offset)).unwrap_or((0, 0));
+        start.wrapping_sub(pgoff).wrapping_sub(vaddr.wrapping_sub(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_bias() {
+        let exe = std::env::current_exe().unwrap();
+        let data = std::fs::read(&exe).unwrap();
+        let file = parse(&data).unwrap();
+        let entry = file.entry();
+        let text = file
+            .segments()
+            .find(|s| s.address() <= entry && entry < s.address() + s.size())
+            .unwrap();
+        let (offset, _) = text.file_range();
+        let pgoff = offset & !0xfff;
+        // Map the text segment at an ASLR-like address; the entry point must
+        // come back to its address in the file.
+        // 将代码段映射到类似 ASLR 的地址；入口点应还原为其在文件中的地址。
+        let start = 0x5555_5555_4000;
+        let runtime = start + (entry - text.address() + offset - pgoff);
+        assert_eq!(runtime - LoadSegments::new(&file).load_bias(start, pgoff), entry);
+        assert!(parse(b"not elf").is_err());
+    }
+}
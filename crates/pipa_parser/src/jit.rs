@@ -0,0 +1,326 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Symbols of JIT-compiled code, which has no ELF file behind it: the
+//! `/tmp/perf-<pid>.map` files that V8 (`--perf-basic-prof`) and JVM agents
+//! write, and the jitdump files (`jit-<pid>.dump`) of V8's `--perf-prof`
+//! and JVM agents.
+//!
+//! JIT 编译代码（背后没有 ELF 文件）的符号：V8（`--perf-basic-prof`）与 JVM 代理写入的
+//! `/tmp/perf-<pid>.map` 文件，以及 V8 `--perf-prof` 与 JVM 代理写入的 jitdump 文件
+//! （`jit-<pid>.dump`）。
+
+use pipa_collector::error::PipaCollectorError;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// `JiTD`, in the byte order of the process that wrote the file.
+/// `JiTD`，采用写入文件的进程的字节序。
+const JITDUMP_MAGIC: u32 = 0x4A69_5444;
+const JITDUMP_HEADER_SIZE: usize = 40;
+const JIT_CODE_LOAD: u32 = 0;
+const JIT_CODE_MOVE: u32 = 1;
+const JIT_CODE_CLOSE: u32 = 3;
+
+/// Where a JIT symbol was read from. / JIT 符号的读取来源。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JitSource {
+    PerfMap,
+    JitDump,
+}
+
+impl JitSource {
+    /// The DSO name `perf` gives code from this source in process `pid`.
+    /// `perf` 为进程 `pid` 中来自该来源的代码所用的 DSO 名称。
+    pub fn dso(self, pid: u32) -> String {
+        match self {
+            JitSource::PerfMap => format!("perf-{}.map", pid),
+            JitSource::JitDump => format!("jit-{}.dump", pid),
+        }
+    }
+}
+
+/// A JIT-compiled function. / JIT 编译的函数。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JitSymbol {
+    pub start: u64,
+    pub size: u64,
+    pub name: String,
+    pub source: JitSource,
+}
+
+/// The JIT-compiled functions of a process, by address.
+/// 进程中按地址排列的 JIT 编译函数。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JitSymbols {
+    symbols: Vec<JitSymbol>,
+}
+
+impl JitSymbols {
+    /// Collects `symbols`, given in the order the code was compiled. Where
+    /// code was compiled again at the same address, the latest wins.
+    /// 收集按编译顺序给出的 `symbols`。同一地址被再次编译时以最新者为准。
+    pub fn new(symbols: Vec<JitSymbol>) -> Self {
+        let mut symbols: Vec<JitSymbol> =
+            symbols.into_iter().filter(|symbol| symbol.size > 0).collect();
+        // A stable sort keeps compile order among equal starts.
+        // 稳定排序会保留起始地址相同者之间的编译顺序。
+        symbols.sort_by_key(|symbol| symbol.start);
+        let mut latest: Vec<JitSymbol> = Vec::with_capacity(symbols.len());
+        for symbol in symbols {
+            match latest.last_mut() {
+                Some(last) if last.start == symbol.start => *last = symbol,
+                _ => latest.push(symbol),
+            }
+        }
+        JitSymbols { symbols: latest }
+    }
+
+    /// Parses a perf map, whose lines read `start size name` with `start`
+    /// and `size` in hex. / 解析 perf map，其各行格式为 `start size name`，
+    /// 其中 `start` 与 `size` 为十六进制。
+    pub fn parse_perf_map(text: &str) -> Result<Self, PipaCollectorError> {
+        let mut symbols = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.trim().splitn(3, char::is_whitespace);
+            let (Some(start), Some(size), Some(name)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(PipaCollectorError::InvalidFormat(format!(
+                    "bad perf map line `{}`",
+                    line
+                )));
+            };
+            let hex = |field: &str| {
+                u64::from_str_radix(field.trim_start_matches("0x"), 16).map_err(|_| {
+                    PipaCollectorError::InvalidFormat(format!("bad perf map line `{}`", line))
+                })
+            };
+            symbols.push(JitSymbol {
+                start: hex(start)?,
+                size: hex(size)?,
+                name: name.trim().to_string(),
+                source: JitSource::PerfMap,
+            });
+        }
+        Ok(Self::new(symbols))
+    }
+
+    /// Parses a jitdump file: a header, then records of compiled and moved
+    /// code. A record cut short by a JIT that is still writing ends the file.
+    /// 解析 jitdump 文件：文件头之后是代码编译与移动记录。
+    /// 仍在写入的 JIT 留下的不完整记录视为文件结尾。
+    pub fn parse_jitdump(data: &[u8]) -> Result<Self, PipaCollectorError> {
+        let magic = data.get(..4).ok_or_else(|| bad_jitdump("no header"))?;
+        let big_endian = if u32::from_le_bytes(magic.try_into().expect("4 bytes")) == JITDUMP_MAGIC
+        {
+            false
+        } else if u32::from_be_bytes(magic.try_into().expect("4 bytes")) == JITDUMP_MAGIC {
+            true
+        } else {
+            return Err(bad_jitdump("bad magic"));
+        };
+        let mut header = Reader { data, pos: 8, big_endian };
+        let header_size = header.u32().ok_or_else(|| bad_jitdump("no header"))? as usize;
+        if header_size < JITDUMP_HEADER_SIZE {
+            return Err(bad_jitdump("header too short"));
+        }
+
+        let mut symbols: Vec<JitSymbol> = Vec::new();
+        // Where each code address's latest load is in `symbols`.
+        // 每个代码地址最近一次加载在 `symbols` 中的位置。
+        let mut loaded: HashMap<u64, usize> = HashMap::new();
+        let mut pos = header_size;
+        while let Some(prefix) = data.get(pos..pos + 16) {
+            let mut record = Reader { data: prefix, pos: 0, big_endian };
+            let (id, size) = (record.u32(), record.u32().map(|size| size as usize));
+            let (Some(id), Some(size)) = (id, size) else { break };
+            let Some(body) = data.get(pos + 16..pos + size.max(16)) else { break };
+            if size < 16 || id == JIT_CODE_CLOSE {
+                break;
+            }
+            let mut record = Reader { data: body, pos: 8, big_endian };
+            match id {
+                JIT_CODE_LOAD => {
+                    let (Some(_vma), Some(start), Some(size)) =
+                        (record.u64(), record.u64(), record.u64())
+                    else {
+                        return Err(bad_jitdump("code load record too short"));
+                    };
+                    let name = body.get(40..).unwrap_or_default();
+                    let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                    loaded.insert(start, symbols.len());
+                    symbols.push(JitSymbol {
+                        start,
+                        size,
+                        name: String::from_utf8_lossy(name).into_owned(),
+                        source: JitSource::JitDump,
+                    });
+                }
+                JIT_CODE_MOVE => {
+                    let (Some(_vma), Some(old), Some(new)) =
+                        (record.u64(), record.u64(), record.u64())
+                    else {
+                        return Err(bad_jitdump("code move record too short"));
+                    };
+                    if let Some(index) = loaded.remove(&old) {
+                        let mut moved = symbols[index].clone();
+                        moved.start = new;
+                        // Nothing is left at the old address.
+                        // 旧地址处不再有代码。
+                        symbols[index].size = 0;
+                        loaded.insert(new, symbols.len());
+                        symbols.push(moved);
+                    }
+                }
+                // Debug and unwinding info. / 调试信息与回溯信息。
+                _ => {}
+            }
+            pos += size;
+        }
+        Ok(Self::new(symbols))
+    }
+
+    /// Reads the perf map and the jitdump file of process `pid` from `dir`,
+    /// or the jitdump file at `jitdump` if the recording mapped one.
+    /// Missing files give no symbols.
+    /// 从 `dir` 读取进程 `pid` 的 perf map 与 jitdump 文件；若记录中映射了 jitdump
+    /// 文件，则改为读取 `jitdump` 处的文件。文件不存在时没有符号。
+    pub fn load(dir: &Path, pid: u32, jitdump: Option<&Path>) -> Result<Self, PipaCollectorError> {
+        let mut symbols = Vec::new();
+        let map = dir.join(format!("perf-{}.map", pid));
+        if let Some(data) = read_if_exists(&map)? {
+            let text = String::from_utf8_lossy(&data);
+            symbols.extend(Self::parse_perf_map(&text).map_err(|e| e.at_path(&map))?.symbols);
+        }
+        let dump = match jitdump {
+            Some(path) => path.to_path_buf(),
+            None => dir.join(format!("jit-{}.dump", pid)),
+        };
+        if let Some(data) = read_if_exists(&dump)? {
+            symbols.extend(Self::parse_jitdump(&data).map_err(|e| e.at_path(&dump))?.symbols);
+        }
+        Ok(Self::new(symbols))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// The function that contains `address`, and the offset into it.
+    /// 包含 `address` 的函数，以及地址在其中的偏移。
+    pub fn find(&self, address: u64) -> Option<(&JitSymbol, u64)> {
+        let index = self.symbols.partition_point(|symbol| symbol.start <= address);
+        let symbol = self.symbols.get(index.checked_sub(1)?)?;
+        let offset = address - symbol.start;
+        (offset < symbol.size).then_some((symbol, offset))
+    }
+}
+
+/// Whether `path` names a jitdump file, `jit-<pid>.dump`.
+/// `path` 是否为 jitdump 文件 `jit-<pid>.dump`。
+pub fn is_jitdump(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_prefix("jit-")
+        .and_then(|rest| rest.strip_suffix(".dump"))
+        .is_some_and(|pid| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>, PipaCollectorError> {
+    match std::fs::read(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(PipaCollectorError::from(e).at_path(path)),
+    }
+}
+
+fn bad_jitdump(what: &str) -> PipaCollectorError {
+    PipaCollectorError::InvalidFormat(format!("bad jitdump file: {}", what))
+}
+
+/// Reads the integers of a jitdump file in its byte order.
+/// 按 jitdump 文件的字节序读取其中的整数。
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.data.get(self.pos..self.pos + N)?;
+        self.pos += N;
+        Some(bytes.try_into().expect("N bytes"))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let bytes = self.take()?;
+        Some(if self.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        let bytes = self.take()?;
+        Some(if self.big_endian { u64::from_be_bytes(bytes) } else { u64::from_le_bytes(bytes) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_perf_map() {
+        let symbols = JitSymbols::parse_perf_map(
+            "7f0000001000 40 LazyCompile:*fib /app/fib.js:1\n\
+             0x7f0000001100 0x20 Builtin:ArgumentsAdaptor\n\
+             7f0000001000 30 LazyCompile:*fib /app/fib.js:1 (recompiled)\n",
+        )
+        .unwrap();
+        let (symbol, offset) = symbols.find(0x7f00_0000_1010).unwrap();
+        assert_eq!(
+            (symbol.name.as_str(), offset, symbol.size),
+            ("LazyCompile:*fib /app/fib.js:1 (recompiled)", 0x10, 0x30)
+        );
+        assert_eq!(symbols.find(0x7f00_0000_1110).unwrap().0.name, "Builtin:ArgumentsAdaptor");
+        // Past the end of the recompiled function, and before any code.
+        // 越过重新编译的函数末尾，以及位于所有代码之前。
+        assert!(symbols.find(0x7f00_0000_1030).is_none());
+        assert!(symbols.find(0x1000).is_none());
+        assert!(JitSymbols::parse_perf_map("7f0000001000 40\n").is_err());
+        assert!(JitSymbols::parse_perf_map("zz 40 f\n").is_err());
+    }
+
+    #[test]
+    fn test_is_jitdump() {
+        assert!(is_jitdump("/tmp/.debug/jit/java-jit-20250101.XXX/jit-4242.dump"));
+        assert!(is_jitdump("jit-1.dump"));
+        assert!(!is_jitdump("/tmp/jit-.dump"));
+        assert!(!is_jitdump("/tmp/jit-42.map"));
+        assert!(!is_jitdump("/tmp/perf-42.map"));
+    }
+
+    #[test]
+    fn test_parse_jitdump_errors() {
+        assert!(JitSymbols::parse_jitdump(b"").is_err());
+        assert!(JitSymbols::parse_jitdump(b"ELF\x7f\0\0\0\0\0\0\0\0").is_err());
+        let mut header = b"DTiJ".to_vec();
+        header.extend_from_slice(&[1, 0, 0, 0, 16, 0, 0, 0]);
+        assert!(JitSymbols::parse_jitdump(&header).is_err());
+        // A bare header is a JIT that has compiled nothing yet.
+        // 只有文件头表示 JIT 尚未编译任何代码。
+        header[8] = JITDUMP_HEADER_SIZE as u8;
+        header.resize(JITDUMP_HEADER_SIZE, 0);
+        assert!(JitSymbols::parse_jitdump(&header).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kernel and kernel module symbols from `/proc/kallsyms`.
+//!
+//! 来自 `/proc/kallsyms` 的内核及内核模块符号。
+
+use pipa_collector::error::{PipaCollectorError, parse_file};
+use pipa_collector::sysroot::SysRoot;
+
+/// A kernel function. / 内核函数。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelSymbol {
+    pub address: u64,
+    pub name: String,
+    /// The module that defines it, or `None` for the kernel image.
+    /// 定义它的模块；内核镜像本身为 `None`。
+    pub module: Option<String>,
+}
+
+impl KernelSymbol {
+    /// The DSO name `perf` gives the symbol's code: `[kernel.kallsyms]` or
+    /// `[module]`. / `perf` 为该符号代码所用的 DSO 名称：`[kernel.kallsyms]` 或 `[module]`。
+    pub fn dso(&self) -> String {
+        match &self.module {
+            Some(module) => format!("[{}]", module),
+            None => "[kernel.kallsyms]".to_string(),
+        }
+    }
+}
+
+/// The kernel functions, by address. / 按地址排列的内核函数。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KernelSymbols {
+    symbols: Vec<KernelSymbol>,
+}
+
+impl KernelSymbols {
+    /// Parses `/proc/kallsyms`, whose lines read `address type name [module]`.
+    /// Addresses are all zero when `kptr_restrict` hides them, which leaves
+    /// no symbols. / 解析 `/proc/kallsyms`，其各行格式为 `address type name [module]`。
+    /// `kptr_restrict` 隐藏地址时地址全为零，此时没有符号。
+    pub fn parse(text: &str) -> Result<Self, PipaCollectorError> {
+        let mut symbols = Vec::new();
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            let mut fields = line.split_whitespace();
+            let (Some(address), Some(kind), Some(name)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                return Err(PipaCollectorError::InvalidFormat(format!(
+                    "bad kallsyms line `{}`",
+                    line
+                )));
+            };
+            let address = u64::from_str_radix(address, 16).map_err(|_| {
+                PipaCollectorError::InvalidFormat(format!("bad kallsyms address in `{}`", line))
+            })?;
+            // Only code symbols: text and weak ones. / 只保留代码符号：文本与弱符号。
+            if address == 0 || !matches!(kind, "t" | "T" | "w" | "W") {
+                continue;
+            }
+            let module =
+                fields.next().map(|m| m.trim_matches(|c| c == '[' || c == ']').to_string());
+            symbols.push(KernelSymbol { address, name: name.to_string(), module });
+        }
+        symbols.sort_by_key(|symbol| symbol.address);
+        Ok(KernelSymbols { symbols })
+    }
+
+    /// Reads the symbols of the running kernel. / 读取当前运行内核的符号。
+    #[cfg(not(tarpaulin_include))]
+    pub fn load(root: &SysRoot) -> Result<Self, PipaCollectorError> {
+        parse_file(root.proc("kallsyms"), Self::parse)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// The function that contains `address`, and the offset into it.
+    /// 包含 `address` 的函数，以及地址在其中的偏移。
+    pub fn find(&self, address: u64) -> Option<(&KernelSymbol, u64)> {
+        let index = self.symbols.partition_point(|symbol| symbol.address <= address);
+        let symbol = self.symbols.get(index.checked_sub(1)?)?;
+        Some((symbol, address - symbol.address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kernel_symbols() {
+        let symbols = KernelSymbols::parse(
+            "ffffffff81000000 T _stext\n\
+             ffffffff81001000 t do_one_initcall\n\
+             ffffffff82000000 D jiffies\n\
+             ffffffffc0a01000 t nf_hook\t[nf_tables]\n",
+        )
+        .unwrap();
+        let (symbol, offset) = symbols.find(0xffff_ffff_8100_1234).unwrap();
+        assert_eq!((symbol.name.as_str(), offset), ("do_one_initcall", 0x234));
+        assert_eq!(symbol.dso(), "[kernel.kallsyms]");
+        let (symbol, _) = symbols.find(0xffff_ffff_c0a0_1010).unwrap();
+        assert_eq!((symbol.name.as_str(), symbol.dso()), ("nf_hook", "[nf_tables]".to_string()));
+        assert!(symbols.find(0x1000).is_none());
+
+        // Under kptr_restrict every address reads as zero.
+        // 在 kptr_restrict 下所有地址都显示为零。
+        let hidden = KernelSymbols::parse("0000000000000000 T _stext\n").unwrap();
+        assert!(hidden.is_empty());
+        assert!(KernelSymbols::parse("zz T x\n").is_err());
+    }
+}
@@ -1,3 +1,25 @@
+//! Turns raw collector output into structured data: the memory maps of
+//! recorded processes, the ELF files behind them, stacks unwound from
+//! their call frame information, the symbols of recorded addresses, JIT
+//! included, and a build-ID cache of the binaries a recording mapped.
+//!
+//! 将采集器的原始输出转换为结构化数据：被记录进程的内存映射、其背后的 ELF
+//! 文件、借助调用帧信息回溯得到的调用栈、记录地址（包括 JIT 代码）的符号，
+//! 以及记录所映射二进制文件的构建 ID 缓存。
+
+pub mod buildid;
+pub mod elf;
+pub mod jit;
+pub mod kallsyms;
+pub mod maps;
+pub mod symbolize;
+pub mod unwind;
+
+// The fixture helpers of the integration tests, shared with the unit tests.
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
 #[cfg(not(tarpaulin_include))]
 pub fn add(left: u64, right: u64) -> u64 {
     left + right
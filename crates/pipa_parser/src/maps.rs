@@ -0,0 +1,207 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The executable mappings of recorded processes, rebuilt from the
+//! `PERF_RECORD_MMAP2` records of a recording. For processes that were
+//! already running, `pipa record` synthesizes those records from
+//! `/proc/<pid>/maps` when it starts, as `perf record` does.
+//!
+//! 被记录进程的可执行内存映射，由记录中的 `PERF_RECORD_MMAP2` 记录重建。
+//! 对于已在运行的进程，`pipa record` 会在启动时根据 `/proc/<pid>/maps`
+//! 合成这些记录，与 `perf record` 的做法相同。
+
+use pipa_collector::error::{PipaCollectorError, parse_file};
+use pipa_collector::perf_record::MmapRecord;
+use pipa_collector::sysroot::SysRoot;
+use std::collections::{BTreeMap, HashMap};
+
+/// A file or pseudo-file mapped into a process. / 映射到进程中的文件或伪文件。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub start: u64,
+    pub end: u64,
+    /// Offset of `start` in the file. / `start` 在文件中的偏移。
+    pub pgoff: u64,
+    /// The file path, or a name such as `[vdso]`. / 文件路径，或 `[vdso]` 等名称。
+    pub path: String,
+}
+
+impl Mapping {
+    /// Whether the mapping is backed by a file that can be opened, rather
+    /// than anonymous memory or a kernel-provided pseudo-file.
+    /// 映射是否由可打开的文件支持，而不是匿名内存或内核提供的伪文件。
+    pub fn is_file(&self) -> bool {
+        self.path.starts_with('/') && !self.path.starts_with("//anon")
+    }
+
+    /// The `PERF_RECORD_MMAP2` record that announces the mapping in process
+    /// `pid`. / 在进程 `pid` 中声明该映射的 `PERF_RECORD_MMAP2` 记录。
+    pub fn to_mmap(&self, pid: u32) -> MmapRecord {
+        MmapRecord {
+            pid,
+            tid: pid,
+            start: self.start,
+            len: self.end.saturating_sub(self.start),
+            pgoff: self.pgoff,
+            filename: self.path.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The mappings of every process, by pid. / 按 pid 划分的所有进程的映射。
+#[derive(Debug, Clone, Default)]
+pub struct ProcessMaps {
+    processes: HashMap<u32, BTreeMap<u64, Mapping>>,
+}
+
+impl ProcessMaps {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a mapping to process `pid`, replacing the mappings it overlaps
+    /// as `mmap` does. / 向进程 `pid` 添加映射，并像 `mmap` 一样替换与之重叠的映射。
+    pub fn insert(&mut self, pid: u32, mapping: Mapping) {
+        let maps = self.processes.entry(pid).or_default();
+        let overlapping: Vec<u64> = maps
+            .range(..mapping.end)
+            .rev()
+            .take_while(|(_, m)| m.end > mapping.start)
+            .map(|(&start, _)| start)
+            .collect();
+        for start in overlapping {
+            maps.remove(&start);
+        }
+        maps.insert(mapping.start, mapping);
+    }
+
+    /// Adds the mapping of a `PERF_RECORD_MMAP2` record.
+    /// 添加 `PERF_RECORD_MMAP2` 记录中的映射。
+    pub fn add_record(&mut self, mmap: &MmapRecord) {
+        let mapping = Mapping {
+            start: mmap.start,
+            end: mmap.start.saturating_add(mmap.len),
+            pgoff: mmap.pgoff,
+            path: mmap.filename.clone(),
+        };
+        self.insert(mmap.pid, mapping);
+    }
+
+    /// The mapping of process `pid` that contains `address`.
+    /// 进程 `pid` 中包含 `address` 的映射。
+    pub fn find(&self, pid: u32, address: u64) -> Option<&Mapping> {
+        let (_, mapping) = self.processes.get(&pid)?.range(..=address).next_back()?;
+        (address < mapping.end).then_some(mapping)
+    }
+}
+
+/// The executable mappings of running process `pid` as `PERF_RECORD_MMAP2`
+/// records, which the kernel only sends for mappings made after recording
+/// starts. / 以 `PERF_RECORD_MMAP2` 记录表示的运行中进程 `pid` 的可执行映射；
+/// 内核只会为记录开始后建立的映射发送这些记录。
+#[cfg(not(tarpaulin_include))]
+pub fn synthesize_mmaps(root: &SysRoot, pid: u32) -> Result<Vec<MmapRecord>, PipaCollectorError> {
+    let mappings = parse_file(root.proc(format!("{}/maps", pid)), parse_proc_maps)?;
+    Ok(mappings.iter().map(|mapping| mapping.to_mmap(pid)).collect())
+}
+
+/// Parses the executable mappings of a `/proc/<pid>/maps` file, whose
+/// lines read `start-end perms offset dev inode [path]`.
+/// 解析 `/proc/<pid>/maps` 文件中的可执行映射，其各行格式为
+/// `start-end perms offset dev inode [path]`。
+pub fn parse_proc_maps(text: &str) -> Result<Vec<Mapping>, PipaCollectorError> {
+    let invalid =
+        |line: &str| PipaCollectorError::InvalidFormat(format!("bad maps line `{}`", line));
+    let mut mappings = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let mut fields = line.splitn(6, ' ');
+        let mut next = || fields.next().ok_or_else(|| invalid(line));
+        let (range, perms, offset) = (next()?, next()?, next()?);
+        let (_dev, _inode) = (next()?, next()?);
+        let path = fields.next().unwrap_or("").trim();
+        if !perms.contains('x') {
+            continue;
+        }
+        let (start, end) = range.split_once('-').ok_or_else(|| invalid(line))?;
+        let hex = |field: &str| u64::from_str_radix(field, 16).map_err(|_| invalid(line));
+        mappings.push(Mapping {
+            start: hex(start)?,
+            end: hex(end)?,
+            pgoff: hex(offset)?,
+            path: if path.is_empty() { "//anon".to_string() } else { path.to_string() },
+        });
+    }
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_maps() {
+        let mut maps = ProcessMaps::new();
+        let mmap = |start, len, filename: &str| MmapRecord {
+            pid: 7,
+            start,
+            len,
+            pgoff: 0x1000,
+            filename: filename.into(),
+            ..Default::default()
+        };
+        maps.add_record(&mmap(0x1000, 0x3000, "/usr/bin/app"));
+        maps.add_record(&mmap(0x8000, 0x1000, "[vdso]"));
+        assert_eq!(maps.find(7, 0x3fff).unwrap().path, "/usr/bin/app");
+        assert!(maps.find(7, 0x4000).is_none());
+        assert!(maps.find(8, 0x2000).is_none());
+        assert!(!maps.find(7, 0x8000).unwrap().is_file());
+
+        // A new mapping over the middle of the old one replaces it.
+        // 覆盖旧映射中间部分的新映射将其替换。
+        maps.add_record(&mmap(0x2000, 0x1000, "/usr/lib/libc.so.6"));
+        assert!(maps.find(7, 0x1000).is_none());
+        assert_eq!(maps.find(7, 0x2800).unwrap().path, "/usr/lib/libc.so.6");
+        assert_eq!(maps.find(7, 0x8000).unwrap().path, "[vdso]");
+    }
+
+    #[test]
+    fn test_parse_proc_maps() {
+        let text = "\
+55d0c5a2c000-55d0c5a2e000 r--p 00000000 08:01 1234                       /usr/bin/app
+55d0c5a2e000-55d0c5a4e000 r-xp 00002000 08:01 1234                       /usr/bin/my app
+7f1c2a000000-7f1c2a021000 rwxp 00000000 00:00 0 
+7ffd5a1f2000-7ffd5a1f4000 r-xp 00000000 00:00 0                          [vdso]
+";
+        let mappings = parse_proc_maps(text).unwrap();
+        assert_eq!(mappings.len(), 3);
+        assert_eq!(
+            mappings[0],
+            Mapping {
+                start: 0x55d0_c5a2_e000,
+                end: 0x55d0_c5a4_e000,
+                pgoff: 0x2000,
+                path: "/usr/bin/my app".into(),
+            }
+        );
+        assert!(!mappings[1].is_file());
+        assert_eq!(mappings[2].path, "[vdso]");
+        let mmap = mappings[0].to_mmap(42);
+        assert_eq!((mmap.pid, mmap.tid, mmap.len, mmap.pgoff), (42, 42, 0x2_0000, 0x2000));
+        let mut maps = ProcessMaps::new();
+        maps.add_record(&mmap);
+        assert_eq!(maps.find(42, 0x55d0_c5a4_dfff), Some(&mappings[0]));
+        assert!(parse_proc_maps("zzz r-xp 0 0 0 /x").is_err());
+    }
+}
@@ -0,0 +1,577 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Turns recorded addresses into `function+offset` and `file:line`: user
+//! addresses through the process's mappings, the ELF symbol table and DWARF
+//! line info of the mapped binary, kernel addresses through `/proc/kallsyms`.
+//!
+//! 将记录的地址转换为 `function+offset` 与 `file:line`：用户态地址经由进程的
+//! 内存映射、被映射二进制文件的 ELF 符号表与 DWARF 行号信息解析，内核地址经由
+//! `/proc/kallsyms` 解析。
+
+use crate::buildid::{self, BuildIdCache};
+use crate::elf::{self, LoadSegments};
+use crate::jit::{self, JitSymbols};
+use crate::kallsyms::KernelSymbols;
+use crate::maps::{Mapping, ProcessMaps};
+use lru::LruCache;
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::perf_record::{BuildIdRecord, MmapRecord};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// How many binaries a [`Symbolizer`] keeps loaded by default.
+/// [`Symbolizer`] 默认保持加载的二进制文件数量。
+pub const DEFAULT_CACHE_SIZE: usize = 64;
+
+/// Whether `address` is in the kernel half of the address space.
+/// `address` 是否位于地址空间的内核部分。
+pub fn is_kernel_address(address: u64) -> bool {
+    address >> 63 == 1
+}
+
+/// Demangles a Rust or C++ symbol name, or returns it unchanged.
+/// 还原 Rust 或 C++ 符号名；无法还原时原样返回。
+pub fn demangle(name: &str) -> String {
+    addr2line::demangle_auto(Cow::Borrowed(name), None).into_owned()
+}
+
+/// A function at an address as the debug info describes it.
+/// 调试信息所描述的某地址处的函数。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceFrame {
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// What an address resolves to. / 地址的解析结果。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbol {
+    /// The file or pseudo-file the address is in, such as `[vdso]` or
+    /// `[kernel.kallsyms]`. / 地址所在的文件或伪文件，例如 `[vdso]` 或 `[kernel.kallsyms]`。
+    pub dso: Option<String>,
+    /// The address in `dso`, with the load bias removed.
+    /// 去除加载偏差后在 `dso` 中的地址。
+    pub dso_address: u64,
+    /// The demangled function of the symbol table that contains the address.
+    /// 符号表中包含该地址的函数（已还原名称）。
+    pub function: Option<String>,
+    /// The offset of the address into `function`. / 地址在 `function` 中的偏移。
+    pub offset: u64,
+    /// The functions inlined at the address, innermost first, then the
+    /// function they were inlined into; empty without debug info.
+    /// 内联到该地址的函数（最内层在前），最后是它们被内联进的函数；没有调试信息时为空。
+    pub frames: Vec<SourceFrame>,
+}
+
+impl Symbol {
+    /// The names of the functions at the address, innermost first: one per
+    /// inlined function, else the symbol, else `dso+0xaddress`, else the raw
+    /// address. / 该地址处的函数名（最内层在前）：每个内联函数一个，否则为符号名，
+    /// 再否则为 `dso+0xaddress`，最后为原始地址。
+    pub fn names(&self, address: u64) -> Vec<String> {
+        let inlined: Vec<String> = self.frames.iter().filter_map(|f| f.function.clone()).collect();
+        if !inlined.is_empty() {
+            return inlined;
+        }
+        if let Some(function) = &self.function {
+            return vec![function.clone()];
+        }
+        match &self.dso {
+            Some(dso) => {
+                let base = dso.rsplit('/').next().unwrap_or(dso);
+                vec![format!("{}+{:#x}", base, self.dso_address)]
+            }
+            None => vec![format!("{:#x}", address)],
+        }
+    }
+
+    /// The source line of the address, `file:line`, if debug info has it.
+    /// 地址所在的源码行 `file:line`（如调试信息中有）。
+    pub fn srcline(&self) -> Option<String> {
+        let frame = self.frames.first()?;
+        Some(format!("{}:{}", frame.file.as_deref()?, frame.line?))
+    }
+}
+
+/// Formats as `function+0xoffset (dso)` like `perf script`.
+/// 像 `perf script` 一样格式化为 `function+0xoffset (dso)`。
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.function {
+            Some(function) => write!(f, "{}+{:#x}", function, self.offset)?,
+            None => f.write_str("[unknown]")?,
+        }
+        match &self.dso {
+            Some(dso) => write!(f, " ({})", dso),
+            None => Ok(()),
+        }
+    }
+}
+
+type Reader = gimli::EndianRcSlice<gimli::RunTimeEndian>;
+
+/// The symbols and debug info of a loaded ELF file.
+/// 已加载 ELF 文件的符号与调试信息。
+pub struct Binary {
+    build_id: Option<Vec<u8>>,
+    segments: LoadSegments,
+    /// `(start, end, name)` of each function, by start address.
+    /// 每个函数的 `(起始, 结束, 名称)`，按起始地址排序。
+    functions: Vec<(u64, u64, String)>,
+    dwarf: Option<addr2line::Context<Reader>>,
+}
+
+impl fmt::Debug for Binary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Binary")
+            .field("functions", &self.functions.len())
+            .field("dwarf", &self.dwarf.is_some())
+            .finish()
+    }
+}
+
+impl Binary {
+    /// Reads the function symbols and the DWARF debug info, if any, of an ELF
+    /// file. / 读取 ELF 文件的函数符号以及（如有）DWARF 调试信息。
+    pub fn parse(data: &[u8]) -> Result<Self, PipaCollectorError> {
+        let file = elf::parse(data)?;
+        let mut functions: Vec<(u64, u64, String)> = file
+            .symbols()
+            .chain(file.dynamic_symbols())
+            .filter(|s| s.kind() == SymbolKind::Text && s.is_definition() && s.address() != 0)
+            .filter_map(|s| Some((s.address(), s.size(), s.name().ok()?)))
+            .filter(|(_, _, name)| !name.is_empty())
+            .map(|(start, size, name)| (start, start + size, demangle(name)))
+            .collect();
+        functions.sort();
+        functions.dedup_by_key(|(start, ..)| *start);
+        // Symbols without a size end where the next one starts.
+        // 没有大小的符号在下一个符号开始处结束。
+        for i in 0..functions.len() {
+            if functions[i].1 == functions[i].0 {
+                functions[i].1 = functions.get(i + 1).map_or(u64::MAX, |next| next.0);
+            }
+        }
+        Ok(Binary {
+            build_id: buildid::build_id(&file),
+            segments: LoadSegments::new(&file),
+            functions,
+            dwarf: load_dwarf(&file),
+        })
+    }
+
+    /// Loads an ELF file. / 加载 ELF 文件。
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PipaCollectorError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| PipaCollectorError::from(e).at_path(path))?;
+        Self::parse(&data).map_err(|e| e.at_path(path))
+    }
+
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.build_id.as_deref()
+    }
+
+    /// Takes the symbols and DWARF debug info of the separate debug file
+    /// `debug` where it has more than this file.
+    /// 在独立调试文件 `debug` 比本文件更完整时，改用其符号与 DWARF 调试信息。
+    pub fn with_debug_info(mut self, debug: Binary) -> Self {
+        if debug.functions.len() > self.functions.len() {
+            self.functions = debug.functions;
+        }
+        if debug.dwarf.is_some() {
+            self.dwarf = debug.dwarf;
+        }
+        self
+    }
+
+    /// Whether the file has DWARF debug info. / 文件是否带有 DWARF 调试信息。
+    pub fn has_debug_info(&self) -> bool {
+        self.dwarf.is_some()
+    }
+
+    /// The load bias of the file mapped at `start` from file offset `pgoff`.
+    /// 文件从偏移 `pgoff` 处映射到 `start` 时的加载偏差。
+    pub fn load_bias(&self, start: u64, pgoff: u64) -> u64 {
+        self.segments.load_bias(start, pgoff)
+    }
+
+    /// The function that contains `address`, an address in the file, and the
+    /// offset into it. / 包含文件内地址 `address` 的函数，以及地址在其中的偏移。
+    pub fn function(&self, address: u64) -> Option<(&str, u64)> {
+        let index = self.functions.partition_point(|(start, ..)| *start <= address);
+        let (start, end, name) = self.functions.get(index.checked_sub(1)?)?;
+        (address < *end).then(|| (name.as_str(), address - start))
+    }
+
+    /// The inlined functions and source lines at `address`, an address in the
+    /// file, innermost first. / 文件内地址 `address` 处的内联函数与源码行，最内层在前。
+    pub fn source_frames(&self, address: u64) -> Vec<SourceFrame> {
+        let Some(dwarf) = &self.dwarf else { return Vec::new() };
+        let Ok(mut iter) = dwarf.find_frames(address).skip_all_loads() else { return Vec::new() };
+        let mut frames = Vec::new();
+        while let Ok(Some(frame)) = iter.next() {
+            let function =
+                frame.function.as_ref().and_then(|f| f.demangle().ok()).map(Cow::into_owned);
+            let (file, line) = frame
+                .location
+                .map_or((None, None), |location| (location.file.map(String::from), location.line));
+            frames.push(SourceFrame { function, file, line });
+        }
+        frames
+    }
+}
+
+/// The DWARF sections of `file`, or `None` if it has no debug info.
+/// `file` 的 DWARF 节；没有调试信息时为 `None`。
+fn load_dwarf(file: &object::File<'_>) -> Option<addr2line::Context<Reader>> {
+    file.section_by_name(".debug_info")?;
+    let endian = if file.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let dwarf = gimli::Dwarf::load(|id| -> Result<Reader, gimli::Error> {
+        let data = file
+            .section_by_name(id.name())
+            .and_then(|section| section.uncompressed_data().ok())
+            .unwrap_or_default();
+        Ok(gimli::EndianRcSlice::new(Rc::from(&*data), endian))
+    })
+    .ok()?;
+    addr2line::Context::from_dwarf(dwarf).ok()
+}
+
+/// Resolves the addresses of a recording, following the mappings of its
+/// processes and keeping the most recently used binaries loaded.
+/// 解析记录中的地址：跟踪各进程的内存映射，并保持最近使用的二进制文件处于加载状态。
+#[derive(Debug)]
+pub struct Symbolizer {
+    maps: ProcessMaps,
+    kernel: KernelSymbols,
+    /// The build-IDs the recording gives for mapped files.
+    /// 记录中给出的已映射文件的构建 ID。
+    build_ids: HashMap<String, Vec<u8>>,
+    cache: Option<BuildIdCache>,
+    binaries: LruCache<String, Option<Rc<Binary>>>,
+    /// Where perf maps and jitdump files are written, and the jitdump files
+    /// the recording mapped, by pid. / perf map 与 jitdump 文件的写入目录，
+    /// 以及记录中按 pid 映射的 jitdump 文件。
+    jit_dir: PathBuf,
+    jitdumps: HashMap<u32, PathBuf>,
+    jit: HashMap<u32, Rc<JitSymbols>>,
+    unreadable: BTreeSet<String>,
+    mismatched: BTreeSet<String>,
+}
+
+impl Default for Symbolizer {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_SIZE)
+    }
+}
+
+impl Symbolizer {
+    /// A symbolizer that keeps up to `capacity` binaries loaded.
+    /// 最多保持 `capacity` 个二进制文件处于加载状态的符号解析器。
+    pub fn new(capacity: usize) -> Self {
+        Symbolizer {
+            maps: ProcessMaps::new(),
+            kernel: KernelSymbols::default(),
+            build_ids: HashMap::new(),
+            cache: None,
+            binaries: LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)),
+            jit_dir: PathBuf::from("/tmp"),
+            jitdumps: HashMap::new(),
+            jit: HashMap::new(),
+            unreadable: BTreeSet::new(),
+            mismatched: BTreeSet::new(),
+        }
+    }
+
+    /// Reads perf maps and jitdump files from `dir` instead of `/tmp`.
+    /// 改为从 `dir` 而不是 `/tmp` 读取 perf map 与 jitdump 文件。
+    pub fn set_jit_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.jit_dir = dir.into();
+    }
+
+    /// Looks up binaries that changed since the recording, and separate
+    /// debug info, in `cache`. / 在 `cache` 中查找记录后已变化的二进制文件及独立调试信息。
+    pub fn set_build_id_cache(&mut self, cache: BuildIdCache) {
+        self.cache = Some(cache);
+    }
+
+    /// Expects the file of `record` to have its build-ID.
+    /// 期望 `record` 中的文件具有其构建 ID。
+    pub fn add_build_id(&mut self, record: &BuildIdRecord) {
+        self.build_ids.insert(record.filename.clone(), record.build_id.clone());
+    }
+
+    /// Resolves kernel addresses with `symbols`. / 使用 `symbols` 解析内核地址。
+    pub fn set_kernel_symbols(&mut self, symbols: KernelSymbols) {
+        self.kernel = symbols;
+    }
+
+    /// Follows a mapping of the recording. / 跟踪记录中的一次映射。
+    pub fn add_mmap(&mut self, mmap: &MmapRecord) {
+        if !mmap.build_id.is_empty() {
+            self.build_ids.insert(mmap.filename.clone(), mmap.build_id.clone());
+        }
+        // JIT runtimes map their jitdump file to point profilers at it.
+        // JIT 运行时会映射其 jitdump 文件，以便性能分析工具找到它。
+        if jit::is_jitdump(&mmap.filename) {
+            self.jitdumps.insert(mmap.pid, PathBuf::from(&mmap.filename));
+        }
+        self.maps.add_record(mmap);
+    }
+
+    /// Resolves `address` in process `pid`. / 解析进程 `pid` 中的地址 `address`。
+    pub fn symbolize(&mut self, pid: u32, address: u64) -> Symbol {
+        if is_kernel_address(address) {
+            return match self.kernel.find(address) {
+                Some((symbol, offset)) => Symbol {
+                    dso: Some(symbol.dso()),
+                    dso_address: address,
+                    function: Some(symbol.name.clone()),
+                    offset,
+                    frames: Vec::new(),
+                },
+                None => Symbol {
+                    dso: Some("[kernel.kallsyms]".to_string()),
+                    dso_address: address,
+                    ..Default::default()
+                },
+            };
+        }
+        let mapping = self.maps.find(pid, address).cloned();
+        // JIT code lives in anonymous memory, or outside any mapping the
+        // recording saw. / JIT 代码位于匿名内存中，或在记录所见的任何映射之外。
+        if !mapping.as_ref().is_some_and(Mapping::is_file) {
+            let jit = self.jit_symbols(pid);
+            if let Some((symbol, offset)) = jit.find(address) {
+                return Symbol {
+                    dso: Some(symbol.source.dso(pid)),
+                    dso_address: address,
+                    function: Some(symbol.name.clone()),
+                    offset,
+                    frames: Vec::new(),
+                };
+            }
+        }
+        let Some(mapping) = mapping else {
+            return Symbol { dso_address: address, ..Default::default() };
+        };
+        let mut symbol = Symbol {
+            dso: Some(mapping.path.clone()),
+            dso_address: address - mapping.start + mapping.pgoff,
+            ..Default::default()
+        };
+        if let Some(binary) = self.binary(&mapping) {
+            let address = address.wrapping_sub(binary.load_bias(mapping.start, mapping.pgoff));
+            symbol.dso_address = address;
+            if let Some((function, offset)) = binary.function(address) {
+                symbol.function = Some(function.to_string());
+                symbol.offset = offset;
+            }
+            symbol.frames = binary.source_frames(address);
+        }
+        symbol
+    }
+
+    /// The JIT symbols of process `pid`, read on first use.
+    /// 进程 `pid` 的 JIT 符号，首次使用时读取。
+    fn jit_symbols(&mut self, pid: u32) -> Rc<JitSymbols> {
+        if let Some(symbols) = self.jit.get(&pid) {
+            return symbols.clone();
+        }
+        let symbols = match JitSymbols::load(
+            &self.jit_dir,
+            pid,
+            self.jitdumps.get(&pid).map(PathBuf::as_path),
+        ) {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                self.unreadable.insert(e.to_string());
+                JitSymbols::default()
+            }
+        };
+        let symbols = Rc::new(symbols);
+        self.jit.insert(pid, symbols.clone());
+        symbols
+    }
+
+    /// The binary behind a file mapping, loaded on first use.
+    /// 文件映射背后的二进制文件，首次使用时加载。
+    fn binary(&mut self, mapping: &Mapping) -> Option<Rc<Binary>> {
+        if !mapping.is_file() {
+            return None;
+        }
+        if let Some(binary) = self.binaries.get(&mapping.path) {
+            return binary.clone();
+        }
+        let binary = self.load(&mapping.path).map(Rc::new);
+        self.binaries.put(mapping.path.clone(), binary.clone());
+        binary
+    }
+
+    /// Loads the version of `path` that was recorded: the file itself if its
+    /// build-ID matches, else the copy in the build-ID cache, with separate
+    /// debug info if it has none of its own.
+    /// 加载记录时的 `path` 版本：构建 ID 匹配时为文件本身，否则为构建 ID 缓存中的副本；
+    /// 自身没有调试信息时附加独立调试信息。
+    fn load(&mut self, path: &str) -> Option<Binary> {
+        let expected = self.build_ids.get(path);
+        let binary = match Binary::load(path) {
+            Ok(binary)
+                if expected.is_none() || binary.build_id() == expected.map(Vec::as_slice) =>
+            {
+                binary
+            }
+            on_disk => {
+                let cached = expected
+                    .and_then(|id| self.cache.as_ref()?.binary(id))
+                    .and_then(|copy| Binary::load(copy).ok());
+                match (cached, on_disk) {
+                    (Some(cached), _) => cached,
+                    (None, Ok(_)) => {
+                        self.mismatched.insert(path.to_string());
+                        return None;
+                    }
+                    (None, Err(_)) => {
+                        self.unreadable.insert(path.to_string());
+                        return None;
+                    }
+                }
+            }
+        };
+        if binary.has_debug_info() {
+            return Some(binary);
+        }
+        let debug = binary
+            .build_id()
+            .and_then(|id| self.cache.as_ref()?.debug_file(id))
+            .and_then(|file| Binary::load(file).ok());
+        Some(match debug {
+            Some(debug) => binary.with_debug_info(debug),
+            None => binary,
+        })
+    }
+
+    /// Files that were mapped but could not be read as ELF files, and JIT
+    /// symbol files that could not be parsed, with the reason.
+    /// 已被映射但无法作为 ELF 文件读取的文件，以及无法解析的 JIT 符号文件（附原因）。
+    pub fn unreadable(&self) -> Vec<&str> {
+        self.unreadable.iter().map(String::as_str).collect()
+    }
+
+    /// Files whose build-ID no longer matches the recording and that the
+    /// build-ID cache has no copy of. / 构建 ID 与记录不再一致、且构建 ID 缓存中没有副本的文件。
+    pub fn mismatched(&self) -> Vec<&str> {
+        self.mismatched.iter().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use object::ObjectSegment;
+
+    #[test]
+    fn test_demangle() {
+        assert_eq!(demangle("_ZN4core3fmt5write17h0123456789abcdefE"), "core::fmt::write");
+        assert_eq!(
+            demangle("_ZNSt6vectorIiSaIiEE9push_backERKi"),
+            "std::vector<int, std::allocator<int> >::push_back(int const&)"
+        );
+        assert_eq!(demangle("main"), "main");
+    }
+
+    #[test]
+    fn test_symbol_names() {
+        let mut symbol = Symbol { dso_address: 0x1234, ..Default::default() };
+        assert_eq!(symbol.names(0x5000), ["0x5000"]);
+        assert_eq!(symbol.to_string(), "[unknown]");
+        symbol.dso = Some("/usr/lib/libc.so.6".into());
+        assert_eq!(symbol.names(0x5000), ["libc.so.6+0x1234"]);
+        symbol.function = Some("memcpy".into());
+        symbol.offset = 0x14;
+        assert_eq!(symbol.names(0x5000), ["memcpy"]);
+        assert_eq!(symbol.to_string(), "memcpy+0x14 (/usr/lib/libc.so.6)");
+        assert_eq!(symbol.srcline(), None);
+        let frame = |function: &str, line| SourceFrame {
+            function: Some(function.into()),
+            file: Some("copy.c".into()),
+            line: Some(line),
+        };
+        symbol.frames = vec![frame("copy_small", 12), frame("memcpy", 40)];
+        assert_eq!(symbol.names(0x5000), ["copy_small", "memcpy"]);
+        assert_eq!(symbol.srcline().as_deref(), Some("copy.c:12"));
+    }
+
+    #[test]
+    fn test_symbolize_own_binary() {
+        // Map this test binary into a fake process at an ASLR-like address.
+        // 将本测试二进制文件映射到伪进程中类似 ASLR 的地址。
+        let exe = std::env::current_exe().unwrap();
+        let data = std::fs::read(&exe).unwrap();
+        let file = elf::parse(&data).unwrap();
+        let function = file
+            .symbols()
+            .find(|s| {
+                s.name().is_ok_and(|n| demangle(n).ends_with("tests::test_symbolize_own_binary"))
+            })
+            .unwrap();
+        let text = file
+            .segments()
+            .find(|s| {
+                s.address() <= function.address() && function.address() < s.address() + s.size()
+            })
+            .unwrap();
+        let (offset, size) = text.file_range();
+        let pgoff = offset & !0xfff;
+        let start = 0x5555_0000_0000;
+        let mut symbolizer = Symbolizer::new(1);
+        symbolizer.add_mmap(&MmapRecord {
+            pid: 1,
+            start,
+            len: size + (offset - pgoff),
+            pgoff,
+            filename: exe.to_string_lossy().into_owned(),
+            ..Default::default()
+        });
+        let address = start + (function.address() - text.address() + offset - pgoff) + 4;
+        let symbol = symbolizer.symbolize(1, address);
+        assert_eq!(symbol.dso_address, function.address() + 4);
+        assert_eq!(symbol.offset, 4);
+        assert!(symbol.function.unwrap().ends_with("tests::test_symbolize_own_binary"));
+        assert!(
+            symbol
+                .frames
+                .iter()
+                .any(|f| f.file.as_deref().is_some_and(|f| f.ends_with("symbolize.rs")))
+        );
+
+        assert_eq!(symbolizer.symbolize(2, address).names(address), [format!("{:#x}", address)]);
+        let mut kernel = KernelSymbols::parse("ffffffff81000000 T _stext\n").unwrap();
+        symbolizer.set_kernel_symbols(std::mem::take(&mut kernel));
+        let symbol = symbolizer.symbolize(1, 0xffff_ffff_8100_0010);
+        assert_eq!(symbol.to_string(), "_stext+0x10 ([kernel.kallsyms])");
+        assert!(symbolizer.unreadable().is_empty());
+    }
+}
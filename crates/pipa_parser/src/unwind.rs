@@ -0,0 +1,684 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Offline DWARF unwinding of the user stacks that `pipa record
+//! --call-graph dwarf` copies into each sample.
+//!
+//! The call frame information in `.eh_frame` or `.debug_frame` says, for
+//! each instruction, where the caller's registers are: the canonical frame
+//! address (CFA) is a register plus an offset, and saved registers, the
+//! return address among them, are at offsets from the CFA. Starting from the
+//! sampled registers, each step reads the saved values from the stack copy
+//! until a frame has no unwind information or its return address lies
+//! beyond the copy.
+//!
+//! 对 `pipa record --call-graph dwarf` 复制到每个采样中的用户栈进行离线 DWARF 回溯。
+//!
+//! `.eh_frame` 或 `.debug_frame` 中的调用帧信息给出每条指令处调用者寄存器的
+//! 位置：规范帧地址（CFA）为某个寄存器加偏移，被保存的寄存器（包括返回地址）
+//! 位于相对 CFA 的偏移处。从采样时的寄存器开始，每一步都从栈副本中读取保存的值，
+//! 直到某一帧没有回溯信息，或其返回地址超出了栈副本的范围。
+
+use crate::buildid::{self, BuildIdCache};
+use crate::elf::{self, LoadSegments};
+use crate::maps::ProcessMaps;
+use gimli::{
+    BaseAddresses, CfaRule, CieOrFde, DebugFrame, EhFrame, EndianSlice, RunTimeEndian,
+    UnwindContext, UnwindSection,
+};
+use object::{Object, ObjectSection};
+use pipa_collector::error::PipaCollectorError;
+use pipa_collector::perf_record::{BuildIdRecord, MmapRecord, RecordHeader, SampleRecord};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Stacks are cut off after this many frames, as with perf's `max-stack`.
+/// 调用栈在该帧数后截断，与 perf 的 `max-stack` 相同。
+pub const MAX_FRAMES: usize = 127;
+
+/// An architecture PIPA can unwind. / PIPA 能够回溯的架构。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    X86_64,
+    Aarch64,
+}
+
+impl Arch {
+    /// The architecture named as in [`std::env::consts::ARCH`].
+    /// 以 [`std::env::consts::ARCH`] 的名称给出的架构。
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "x86_64" => Some(Arch::X86_64),
+            "aarch64" => Some(Arch::Aarch64),
+            _ => None,
+        }
+    }
+
+    /// DWARF number of the stack pointer. / 栈指针的 DWARF 编号。
+    pub fn sp(self) -> u16 {
+        match self {
+            Arch::X86_64 => 7,
+            Arch::Aarch64 => 31,
+        }
+    }
+
+    /// DWARF column of the return address: a pseudo-register on x86-64,
+    /// the link register `x30` on AArch64.
+    /// 返回地址的 DWARF 列：x86-64 上为伪寄存器，AArch64 上为链接寄存器 `x30`。
+    pub fn return_address(self) -> u16 {
+        match self {
+            Arch::X86_64 => 16,
+            Arch::Aarch64 => 30,
+        }
+    }
+
+    /// Where perf's register `index` goes: a DWARF register number, or
+    /// `None` for the instruction pointer.
+    /// perf 寄存器 `index` 对应的位置：DWARF 寄存器编号；指令指针为 `None`。
+    fn perf_register(self, index: u32) -> Option<Option<u16>> {
+        match (self, index) {
+            // perf orders ax, bx, cx, dx; DWARF orders rax, rdx, rcx, rbx.
+            (Arch::X86_64, 0) => Some(Some(0)),
+            (Arch::X86_64, 1) => Some(Some(3)),
+            (Arch::X86_64, 2) => Some(Some(2)),
+            (Arch::X86_64, 3) => Some(Some(1)),
+            (Arch::X86_64, 4..=7) => Some(Some(index as u16)),
+            (Arch::X86_64, 8) => Some(None),
+            (Arch::X86_64, 16..=23) => Some(Some(index as u16 - 8)),
+            (Arch::Aarch64, 0..=31) => Some(Some(index as u16)),
+            (Arch::Aarch64, 32) => Some(None),
+            _ => None,
+        }
+    }
+
+    /// Removes the pointer authentication code that AArch64 may sign
+    /// return addresses with. / 去除 AArch64 可能用于签名返回地址的指针认证码。
+    fn strip(self, address: u64) -> u64 {
+        match self {
+            Arch::X86_64 => address,
+            Arch::Aarch64 => address & 0x0000_ffff_ffff_ffff,
+        }
+    }
+}
+
+/// The registers of a frame: the instruction pointer and the values known
+/// by DWARF register number.
+/// 一帧的寄存器：指令指针，以及按 DWARF 寄存器编号记录的已知值。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u64,
+    values: BTreeMap<u16, u64>,
+}
+
+impl Registers {
+    pub fn new(pc: u64) -> Self {
+        Registers { pc, values: BTreeMap::new() }
+    }
+
+    /// The registers of a sample's `regs_user`, taken with the
+    /// `sample_regs_user` mask `mask`.
+    /// 以 `sample_regs_user` 掩码 `mask` 采集的采样 `regs_user` 中的寄存器。
+    pub fn from_perf(arch: Arch, mask: u64, values: &[u64]) -> Self {
+        let mut regs = Registers::default();
+        let indices = (0..64).filter(|bit| mask & (1 << bit) != 0);
+        for (index, &value) in indices.zip(values) {
+            match arch.perf_register(index) {
+                Some(Some(reg)) => regs.set(reg, value),
+                Some(None) => regs.pc = value,
+                None => {}
+            }
+        }
+        regs
+    }
+
+    pub fn get(&self, reg: u16) -> Option<u64> {
+        self.values.get(&reg).copied()
+    }
+
+    pub fn set(&mut self, reg: u16, value: u64) {
+        self.values.insert(reg, value);
+    }
+
+    fn remove(&mut self, reg: u16) {
+        self.values.remove(&reg);
+    }
+}
+
+/// A copy of process memory, such as the user stack from the stack pointer
+/// up. / 进程内存的副本，例如从栈指针起的用户栈。
+#[derive(Debug, Clone, Copy)]
+pub struct Memory<'a> {
+    pub start: u64,
+    pub bytes: &'a [u8],
+}
+
+impl Memory<'_> {
+    /// Reads a little-endian word, if it lies inside the copy.
+    /// 读取一个小端字（若其位于副本之内）。
+    pub fn read_u64(&self, address: u64) -> Option<u64> {
+        let offset = usize::try_from(address.checked_sub(self.start)?).ok()?;
+        let bytes = self.bytes.get(offset..offset.checked_add(8)?)?;
+        Some(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+    }
+}
+
+/// Where the caller's value of a register is. / 调用者寄存器值的位置。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterRule {
+    /// Lost, including rules given as DWARF expressions.
+    /// 已丢失（包括以 DWARF 表达式给出的规则）。
+    Undefined,
+    /// Unchanged by the callee. / 未被被调用者修改。
+    SameValue,
+    /// Saved at CFA plus the offset. / 保存在 CFA 加偏移处。
+    Offset(i64),
+    /// The CFA plus the offset itself. / 即 CFA 加偏移本身。
+    ValOffset(i64),
+    /// In another register. / 位于另一个寄存器中。
+    Register(u16),
+}
+
+/// How to find the caller's registers at one address.
+/// 在某一地址处查找调用者寄存器的方法。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRule {
+    /// The CFA as a register plus an offset. / 以寄存器加偏移表示的 CFA。
+    pub cfa: (u16, i64),
+    pub registers: Vec<(u16, RegisterRule)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CfiKind {
+    EhFrame,
+    DebugFrame,
+}
+
+#[derive(Debug)]
+struct CfiSection {
+    kind: CfiKind,
+    data: Vec<u8>,
+    address: u64,
+}
+
+/// The address range of one frame description entry.
+/// 一个帧描述条目（FDE）的地址范围。
+#[derive(Debug, Clone, Copy)]
+struct FdeRange {
+    start: u64,
+    end: u64,
+    section: usize,
+    offset: usize,
+}
+
+/// The call frame information of an ELF file, indexed by address.
+/// ELF 文件的调用帧信息，按地址建立索引。
+#[derive(Debug)]
+pub struct UnwindTable {
+    endian: RunTimeEndian,
+    text: u64,
+    sections: Vec<CfiSection>,
+    fdes: Vec<FdeRange>,
+}
+
+fn dwarf_error(e: gimli::Error) -> PipaCollectorError {
+    PipaCollectorError::InvalidFormat(format!("invalid call frame information: {}", e))
+}
+
+/// The start, end and offset of every FDE of `section` whose CIE parses.
+/// `section` 中 CIE 可解析的每个 FDE 的起止地址与偏移。
+fn fde_ranges<'a, S>(section: &S, bases: &BaseAddresses) -> gimli::Result<Vec<(u64, u64, usize)>>
+where
+    S: UnwindSection<EndianSlice<'a, RunTimeEndian>>,
+{
+    let mut ranges = Vec::new();
+    let mut entries = section.entries(bases);
+    while let Some(entry) = entries.next()? {
+        if let CieOrFde::Fde(partial) = entry {
+            if let Ok(fde) = partial.parse(S::cie_from_offset) {
+                ranges.push((fde.initial_address(), fde.end_address(), fde.offset()));
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+/// Evaluates the FDE at `offset` of `section` up to `address`.
+/// 在 `section` 中偏移 `offset` 处的 FDE 上求值至 `address`。
+fn rule_at<'a, S>(
+    section: &S,
+    bases: &BaseAddresses,
+    offset: usize,
+    address: u64,
+) -> Option<FrameRule>
+where
+    S: UnwindSection<EndianSlice<'a, RunTimeEndian>>,
+{
+    let fde = section.fde_from_offset(bases, S::Offset::from(offset), S::cie_from_offset).ok()?;
+    let mut ctx = UnwindContext::new();
+    let row = fde.unwind_info_for_address(section, bases, &mut ctx, address).ok()?;
+    let CfaRule::RegisterAndOffset { register, offset } = *row.cfa() else { return None };
+    let registers = row
+        .registers()
+        .map(|(reg, rule)| {
+            let rule = match *rule {
+                gimli::RegisterRule::SameValue => RegisterRule::SameValue,
+                gimli::RegisterRule::Offset(n) => RegisterRule::Offset(n),
+                gimli::RegisterRule::ValOffset(n) => RegisterRule::ValOffset(n),
+                gimli::RegisterRule::Register(r) => RegisterRule::Register(r.0),
+                _ => RegisterRule::Undefined,
+            };
+            (reg.0, rule)
+        })
+        .collect();
+    Some(FrameRule { cfa: (register.0, offset), registers })
+}
+
+impl UnwindTable {
+    /// Reads `.eh_frame` and `.debug_frame`, whichever the file has.
+    /// 读取文件中存在的 `.eh_frame` 与 `.debug_frame`。
+    pub fn new(file: &object::File<'_>) -> Result<Self, PipaCollectorError> {
+        let endian =
+            if file.is_little_endian() { RunTimeEndian::Little } else { RunTimeEndian::Big };
+        let mut table = UnwindTable {
+            endian,
+            text: file.section_by_name(".text").map(|s| s.address()).unwrap_or(0),
+            sections: Vec::new(),
+            fdes: Vec::new(),
+        };
+        for (name, kind) in [(".eh_frame", CfiKind::EhFrame), (".debug_frame", CfiKind::DebugFrame)]
+        {
+            let Some(section) = file.section_by_name(name) else { continue };
+            // Compressed debug sections are skipped. / 跳过压缩的调试节。
+            let Ok(data) = section.uncompressed_data() else { continue };
+            table.sections.push(CfiSection {
+                kind,
+                data: data.into_owned(),
+                address: section.address(),
+            });
+        }
+        for index in 0..table.sections.len() {
+            let section = &table.sections[index];
+            let bases = table.bases(section);
+            let data = EndianSlice::new(&section.data, endian);
+            let ranges = match section.kind {
+                CfiKind::EhFrame => fde_ranges(&EhFrame::from(data), &bases),
+                CfiKind::DebugFrame => {
+                    let mut debug_frame = DebugFrame::from(data);
+                    debug_frame.set_address_size(8);
+                    fde_ranges(&debug_frame, &bases)
+                }
+            }
+            .map_err(dwarf_error)?;
+            table.fdes.extend(ranges.into_iter().map(|(start, end, offset)| FdeRange {
+                start,
+                end,
+                section: index,
+                offset,
+            }));
+        }
+        table.fdes.sort_by_key(|fde| fde.start);
+        Ok(table)
+    }
+
+    /// Parses an ELF file and reads its unwind table.
+    /// 解析 ELF 文件并读取其回溯表。
+    pub fn parse(data: &[u8]) -> Result<Self, PipaCollectorError> {
+        Self::new(&elf::parse(data)?)
+    }
+
+    /// Whether the file has no call frame information.
+    /// 文件是否没有调用帧信息。
+    pub fn is_empty(&self) -> bool {
+        self.fdes.is_empty()
+    }
+
+    fn bases(&self, section: &CfiSection) -> BaseAddresses {
+        match section.kind {
+            CfiKind::EhFrame => {
+                BaseAddresses::default().set_eh_frame(section.address).set_text(self.text)
+            }
+            CfiKind::DebugFrame => BaseAddresses::default(),
+        }
+    }
+
+    /// The rule at `address`, an address in the file, if it is covered and
+    /// its CFA is not a DWARF expression.
+    /// `address`（文件内地址）处的规则，前提是该地址被覆盖且其 CFA 不是 DWARF 表达式。
+    pub fn rule(&self, address: u64) -> Option<FrameRule> {
+        let index = self.fdes.partition_point(|fde| fde.start <= address).checked_sub(1)?;
+        let fde = self.fdes[index];
+        if address >= fde.end {
+            return None;
+        }
+        let section = &self.sections[fde.section];
+        let bases = self.bases(section);
+        let data = EndianSlice::new(&section.data, self.endian);
+        match section.kind {
+            CfiKind::EhFrame => rule_at(&EhFrame::from(data), &bases, fde.offset, address),
+            CfiKind::DebugFrame => {
+                let mut debug_frame = DebugFrame::from(data);
+                debug_frame.set_address_size(8);
+                rule_at(&debug_frame, &bases, fde.offset, address)
+            }
+        }
+    }
+}
+
+/// The caller's registers given the rule of the current frame, or `None`
+/// when the return address cannot be recovered.
+/// 根据当前帧的规则求出调用者的寄存器；无法恢复返回地址时为 `None`。
+fn step(arch: Arch, rule: &FrameRule, regs: &Registers, stack: &Memory<'_>) -> Option<Registers> {
+    let (cfa_register, cfa_offset) = rule.cfa;
+    let cfa = regs.get(cfa_register)?.checked_add_signed(cfa_offset)?;
+    // Registers without a rule keep their value. / 没有规则的寄存器保持原值。
+    let mut caller = regs.clone();
+    for &(reg, rule) in &rule.registers {
+        let value = match rule {
+            RegisterRule::Undefined => None,
+            RegisterRule::SameValue => regs.get(reg),
+            RegisterRule::Offset(n) => stack.read_u64(cfa.wrapping_add_signed(n)),
+            RegisterRule::ValOffset(n) => cfa.checked_add_signed(n),
+            RegisterRule::Register(other) => regs.get(other),
+        };
+        match value {
+            Some(value) => caller.set(reg, value),
+            None => caller.remove(reg),
+        }
+    }
+    caller.pc = arch.strip(caller.get(arch.return_address())?);
+    if arch == Arch::X86_64 {
+        // Not a register the caller itself has. / 并非调用者自身拥有的寄存器。
+        caller.remove(arch.return_address());
+    }
+    caller.set(arch.sp(), cfa);
+    Some(caller)
+}
+
+/// Walks the stack from `regs` through the `stack` copy. `lookup` gives the
+/// unwind table of the file mapped at an address and the mapping's load
+/// bias. Returns `regs.pc` followed by the return addresses, innermost
+/// first, stopping at the first frame it cannot unwind or at `max_frames`.
+/// 从 `regs` 出发，借助 `stack` 副本遍历调用栈。`lookup` 给出映射在某地址处的文件的
+/// 回溯表及该映射的加载偏差。返回 `regs.pc` 及其后的返回地址（最内层在前），
+/// 在第一个无法回溯的帧或达到 `max_frames` 时停止。
+pub fn unwind(
+    arch: Arch,
+    regs: &Registers,
+    stack: &Memory<'_>,
+    lookup: &mut dyn FnMut(u64) -> Option<(Arc<UnwindTable>, u64)>,
+    max_frames: usize,
+) -> Vec<u64> {
+    let mut frames = vec![regs.pc];
+    let mut regs = regs.clone();
+    while frames.len() < max_frames {
+        // A return address points after its call, which may already be the
+        // next function, so look up the call instruction itself.
+        let address = if frames.len() == 1 { regs.pc } else { regs.pc.wrapping_sub(1) };
+        let Some((table, bias)) = lookup(address) else { break };
+        let Some(rule) = table.rule(address.wrapping_sub(bias)) else { break };
+        let Some(caller) = step(arch, &rule, &regs, stack) else { break };
+        // The stack grows down, so a caller's frame is never below its
+        // callee's; anything else is a corrupt or misread stack.
+        let (sp, caller_sp) = (regs.get(arch.sp()), caller.get(arch.sp()));
+        if caller.pc == 0 || caller_sp < sp || (caller_sp == sp && caller.pc == regs.pc) {
+            break;
+        }
+        frames.push(caller.pc);
+        regs = caller;
+    }
+    frames
+}
+
+/// The load segments and unwind table of a mapped file.
+/// 已映射文件的可加载段与回溯表。
+#[derive(Debug)]
+struct Module {
+    segments: LoadSegments,
+    table: Arc<UnwindTable>,
+}
+
+/// Why a mapped file cannot be used to unwind. / 已映射文件无法用于回溯的原因。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unusable {
+    /// Not readable, or without call frame information.
+    /// 不可读，或没有调用帧信息。
+    Unreadable,
+    /// Changed since the recording, with no copy in the build-ID cache.
+    /// 记录后已变化，且构建 ID 缓存中没有副本。
+    Mismatched,
+}
+
+impl Module {
+    /// The module of the file at `path`, and its build-ID.
+    /// `path` 处文件的模块及其构建 ID。
+    fn read(path: &Path) -> Option<(Module, Option<Vec<u8>>)> {
+        let data = std::fs::read(path).ok()?;
+        let file = elf::parse(&data).ok()?;
+        let table = UnwindTable::new(&file).ok()?;
+        let module = Module { segments: LoadSegments::new(&file), table: Arc::new(table) };
+        Some((module, buildid::build_id(&file)))
+    }
+
+    /// Loads the version of `path` that was recorded, as the symbolizer
+    /// does: the file itself if it has the `expected` build-ID, else its
+    /// copy in `cache`.
+    /// 与符号解析器一样加载记录时的 `path` 版本：文件具有 `expected` 构建 ID 时为文件本身，
+    /// 否则为 `cache` 中的副本。
+    fn load(
+        path: &str,
+        expected: Option<&[u8]>,
+        cache: Option<&BuildIdCache>,
+    ) -> Result<Module, Unusable> {
+        let on_disk = match Module::read(Path::new(path)) {
+            Some((module, id)) if expected.is_none() || id.as_deref() == expected => {
+                return Ok(module);
+            }
+            on_disk => on_disk,
+        };
+        let cached = expected.zip(cache).and_then(|(id, cache)| cache.binary(id));
+        match (cached.and_then(|copy| Module::read(&copy)), on_disk) {
+            (Some((module, _)), _) => Ok(module),
+            (None, Some(_)) => Err(Unusable::Mismatched),
+            (None, None) => Err(Unusable::Unreadable),
+        }
+    }
+}
+
+/// Unwinds the user stacks of a `--call-graph dwarf` recording, following
+/// the processes' mappings and loading each file once.
+/// 回溯 `--call-graph dwarf` 记录中的用户栈：跟踪各进程的内存映射，每个文件只加载一次。
+#[derive(Debug)]
+pub struct Unwinder {
+    arch: Arch,
+    regs_mask: u64,
+    maps: ProcessMaps,
+    build_ids: HashMap<String, Vec<u8>>,
+    cache: Option<BuildIdCache>,
+    modules: HashMap<String, Result<Module, Unusable>>,
+}
+
+impl Unwinder {
+    /// An unwinder for the samples of a recording, or `None` if PIPA cannot
+    /// unwind its architecture.
+    /// 为某次记录的采样创建回溯器；PIPA 无法回溯其架构时为 `None`。
+    pub fn new(header: &RecordHeader) -> Option<Self> {
+        Some(Unwinder {
+            arch: Arch::from_name(&header.arch)?,
+            regs_mask: header.sample_regs_user,
+            maps: ProcessMaps::new(),
+            build_ids: HashMap::new(),
+            cache: None,
+            modules: HashMap::new(),
+        })
+    }
+
+    /// Looks up binaries that changed since the recording in `cache`.
+    /// 在 `cache` 中查找记录后已变化的二进制文件。
+    pub fn set_build_id_cache(&mut self, cache: BuildIdCache) {
+        self.cache = Some(cache);
+    }
+
+    /// Expects the file of `record` to have its build-ID.
+    /// 期望 `record` 中的文件具有其构建 ID。
+    pub fn add_build_id(&mut self, record: &BuildIdRecord) {
+        self.build_ids.insert(record.filename.clone(), record.build_id.clone());
+    }
+
+    /// Follows a mapping of the recording. / 跟踪记录中的一次映射。
+    pub fn add_mmap(&mut self, mmap: &MmapRecord) {
+        if !mmap.build_id.is_empty() {
+            self.build_ids.insert(mmap.filename.clone(), mmap.build_id.clone());
+        }
+        self.maps.add_record(mmap);
+    }
+
+    /// The user frames of `sample`, innermost first; empty when the sample
+    /// has no user registers.
+    /// `sample` 的用户态帧（最内层在前）；采样没有用户态寄存器时为空。
+    pub fn unwind_sample(&mut self, sample: &SampleRecord) -> Vec<u64> {
+        if sample.regs_user.is_empty() {
+            return Vec::new();
+        }
+        let regs = Registers::from_perf(self.arch, self.regs_mask, &sample.regs_user);
+        let Some(sp) = regs.get(self.arch.sp()) else { return vec![regs.pc] };
+        let stack = Memory { start: sp, bytes: &sample.stack_user };
+        let (maps, modules) = (&self.maps, &mut self.modules);
+        let (build_ids, cache) = (&self.build_ids, self.cache.as_ref());
+        let mut lookup = |address: u64| {
+            let mapping = maps.find(sample.pid, address).filter(|m| m.is_file())?;
+            let module = modules.entry(mapping.path.clone()).or_insert_with(|| {
+                Module::load(&mapping.path, build_ids.get(&mapping.path).map(Vec::as_slice), cache)
+            });
+            let module = module.as_ref().ok()?;
+            Some((module.table.clone(), module.segments.load_bias(mapping.start, mapping.pgoff)))
+        };
+        unwind(self.arch, &regs, &stack, &mut lookup, MAX_FRAMES)
+    }
+
+    /// Files that were needed but could not be read or had no usable call
+    /// frame information. / 需要但无法读取或没有可用调用帧信息的文件。
+    pub fn unreadable(&self) -> Vec<&str> {
+        self.unusable(Unusable::Unreadable)
+    }
+
+    /// Files that were needed but changed since the recording, and that the
+    /// build-ID cache has no copy of.
+    /// 需要但记录后已变化、且构建 ID 缓存中没有副本的文件。
+    pub fn mismatched(&self) -> Vec<&str> {
+        self.unusable(Unusable::Mismatched)
+    }
+
+    fn unusable(&self, reason: Unusable) -> Vec<&str> {
+        let mut paths: Vec<&str> = self
+            .modules
+            .iter()
+            .filter(|(_, module)| module.as_ref().err() == Some(&reason))
+            .map(|(path, _)| path.as_str())
+            .collect();
+        paths.sort_unstable();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registers_from_perf() {
+        // ax, bx, ..., then ip at bit 8 and r8 at bit 16.
+        // ax、bx……，然后是第 8 位的 ip 与第 16 位的 r8。
+        let mask = 0x1_01ff;
+        let values: Vec<u64> = (100..110).collect();
+        let regs = Registers::from_perf(Arch::X86_64, mask, &values);
+        assert_eq!(regs.pc, 108);
+        assert_eq!((regs.get(0), regs.get(1), regs.get(3)), (Some(100), Some(103), Some(101)));
+        assert_eq!((regs.get(7), regs.get(8)), (Some(107), Some(109)));
+
+        let regs = Registers::from_perf(Arch::Aarch64, (1 << 33) - 1, &(0..33).collect::<Vec<_>>());
+        assert_eq!((regs.pc, regs.get(31), regs.get(30)), (32, Some(31), Some(30)));
+        assert_eq!(Arch::from_name("riscv64"), None);
+    }
+
+    #[test]
+    fn test_step() {
+        let stack_bytes: Vec<u8> =
+            [0x1111u64, 0x7ff0_0000_0100, 0x4321].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let stack = Memory { start: 0x7ff0_0000_0000, bytes: &stack_bytes };
+        assert_eq!(stack.read_u64(0x7ff0_0000_0010), Some(0x4321));
+        assert_eq!(stack.read_u64(0x7ff0_0000_0011), None);
+
+        // x86-64 after `push rbp`: CFA = rsp + 24, rbp at CFA - 16 and the
+        // return address at CFA - 8. rbx has no rule and keeps its value.
+        // x86-64 执行 `push rbp` 之后：CFA = rsp + 24，rbp 位于 CFA - 16，返回地址
+        // 位于 CFA - 8。rbx 没有规则，保持原值。
+        let mut regs = Registers::new(0x1000);
+        regs.set(7, 0x7ff0_0000_0000);
+        regs.set(3, 0xbb);
+        regs.set(6, 0xdead);
+        let rule = FrameRule {
+            cfa: (7, 24),
+            registers: vec![(6, RegisterRule::Offset(-16)), (16, RegisterRule::Offset(-8))],
+        };
+        let caller = step(Arch::X86_64, &rule, &regs, &stack).unwrap();
+        assert_eq!(caller.pc, 0x4321);
+        assert_eq!(caller.get(7), Some(0x7ff0_0000_0018));
+        assert_eq!(caller.get(6), Some(0x7ff0_0000_0100));
+        assert_eq!((caller.get(3), caller.get(16)), (Some(0xbb), None));
+
+        // A return address outside the copy ends the walk.
+        // 位于副本之外的返回地址终止回溯。
+        let rule = FrameRule { cfa: (7, 64), registers: vec![(16, RegisterRule::Offset(-8))] };
+        assert!(step(Arch::X86_64, &rule, &regs, &stack).is_none());
+
+        // An AArch64 leaf function leaves the return address in x30.
+        // AArch64 叶函数将返回地址留在 x30 中。
+        let mut regs = Registers::new(0x2000);
+        regs.set(31, 0x7ff0_0000_0000);
+        regs.set(30, 0x0012_0000_0000_3000);
+        let rule = FrameRule { cfa: (31, 0), registers: Vec::new() };
+        let caller = step(Arch::Aarch64, &rule, &regs, &stack).unwrap();
+        assert_eq!((caller.pc, caller.get(31)), (0x3000, Some(0x7ff0_0000_0000)));
+    }
+
+    #[test]
+    fn test_unwind_table_of_own_binary() {
+        let data = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let file = elf::parse(&data).unwrap();
+        let table = UnwindTable::new(&file).unwrap();
+        assert!(!table.is_empty());
+        // At a function's first instruction the CFA is just above the
+        // return address, except in the few entry points that mark the
+        // return address undefined.
+        // 在函数的第一条指令处，CFA 紧邻返回地址之上；少数将返回地址标记为未定义的
+        // 入口函数除外。
+        let (sp, entry_cfa, ra) = match Arch::from_name(std::env::consts::ARCH) {
+            Some(Arch::X86_64) => (7, 8, Some((16, RegisterRule::Offset(-8)))),
+            Some(Arch::Aarch64) => (31, 0, None),
+            None => return,
+        };
+        let at_entry = table
+            .fdes
+            .iter()
+            .filter_map(|fde| table.rule(fde.start))
+            .filter(|rule| {
+                rule.cfa == (sp, entry_cfa) && ra.is_none_or(|ra| rule.registers.contains(&ra))
+            })
+            .count();
+        assert!(at_entry > table.fdes.len() / 2, "{} of {}", at_entry, table.fdes.len());
+        assert!(table.rule(0).is_none());
+        assert!(UnwindTable::parse(b"not elf").is_err());
+    }
+}
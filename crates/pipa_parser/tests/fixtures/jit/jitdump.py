@@ -0,0 +1,46 @@
+#!/usr/bin/env python3
+# Writes jit-4242.dump, a little-endian jitdump file in the format of
+# tools/perf/util/jitdump.h, as a JIT compiling into anonymous memory at
+# 0x7f1000000000 would:
+#
+#   Interpreter::invoke  loaded at 0x7f1000002000, 0x100 bytes
+#   Lfib;compute(I)I     loaded at 0x7f1000003000, 0x80 bytes, then moved
+#                        to 0x7f1000004000
+#   Lfib;main([Ljava/lang/String;)V  loaded at 0x7f1000005000, 0x40 bytes
+#
+# with a debug info record in between, which readers skip, and a close
+# record at the end.
+#
+#   python3 jitdump.py
+
+import struct
+
+PID = 4242
+CODE_LOAD, CODE_MOVE, CODE_DEBUG_INFO, CODE_CLOSE = 0, 1, 2, 3
+
+
+def record(kind, timestamp, body):
+    return struct.pack("<IIQ", kind, 16 + len(body), timestamp) + body
+
+
+def load(index, timestamp, address, size, name):
+    body = struct.pack("<IIQQQQ", PID, PID, address, address, size, index)
+    return record(CODE_LOAD, timestamp, body + name.encode() + b"\0" + b"\xcc" * size)
+
+
+def move(index, timestamp, old, new, size):
+    body = struct.pack("<IIQQQQQ", PID, PID, new, old, new, size, index)
+    return record(CODE_MOVE, timestamp, body)
+
+
+header = struct.pack("<IIIIIIQQ", 0x4A695444, 1, 40, 62, 0, PID, 1000, 0)
+records = [
+    load(0, 1001, 0x7F1000002000, 0x100, "Interpreter::invoke"),
+    load(1, 1002, 0x7F1000003000, 0x80, "Lfib;compute(I)I"),
+    record(CODE_DEBUG_INFO, 1003, struct.pack("<QQ", 0x7F1000003000, 0)),
+    move(1, 1004, 0x7F1000003000, 0x7F1000004000, 0x80),
+    load(2, 1005, 0x7F1000005000, 0x40, "Lfib;main([Ljava/lang/String;)V"),
+    record(CODE_CLOSE, 1006, b""),
+]
+with open("jit-4242.dump", "wb") as f:
+    f.write(header + b"".join(records))
@@ -0,0 +1,32 @@
+// Test program for the symbolizer fixtures: `pipa::checksum` has
+// `pipa::mix` inlined into it, and both have C++ mangled names.
+//
+//   g++ -O2 -g -o inline inline.cc
+//   objcopy --compress-debug-sections=zlib inline inline_zlib
+//   strip -s inline -o inline_stripped
+
+namespace pipa {
+
+volatile unsigned sink;
+
+__attribute__((always_inline)) inline unsigned mix(unsigned x)
+{
+	return (x ^ sink) * 2654435761u;
+}
+
+__attribute__((noinline)) unsigned checksum(const unsigned *data, int n)
+{
+	unsigned sum = 0;
+	for (int i = 0; i < n; i++)
+		sum += mix(data[i]);
+	return sum;
+}
+
+} // namespace pipa
+
+int main()
+{
+	static const unsigned data[] = { 1, 2, 3, 4 };
+	pipa::sink = pipa::checksum(data, 4);
+	return 0;
+}
@@ -0,0 +1,32 @@
+/* Test program for the DWARF unwinder fixtures: a fixed call stack
+ * main -> outer -> middle -> inner that spins in `inner`.
+ *
+ *   gcc -O2 -fomit-frame-pointer -o stack_eh_frame stack.c
+ *   gcc -O2 -fomit-frame-pointer -g -fno-asynchronous-unwind-tables \
+ *       -fno-unwind-tables -o stack_debug_frame stack.c
+ */
+volatile unsigned long sink;
+
+__attribute__((noinline)) unsigned long inner(unsigned long n)
+{
+	unsigned long i, x = 0;
+	for (i = 0; i < n; i++)
+		x += i * sink;
+	return x + 1;
+}
+
+__attribute__((noinline)) unsigned long middle(unsigned long n)
+{
+	return inner(n) * 3 + sink;
+}
+
+__attribute__((noinline)) unsigned long outer(unsigned long n)
+{
+	return middle(n) * 5 + sink;
+}
+
+int main(void)
+{
+	sink = outer(400000000UL);
+	return 0;
+}
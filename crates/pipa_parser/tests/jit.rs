@@ -0,0 +1,92 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Names JIT-compiled frames from the checked-in perf map and jitdump file
+//! of `fixtures/jit` (see `fixtures/jit/jitdump.py`).
+
+use pipa_collector::perf_record::MmapRecord;
+use pipa_parser::jit::{JitSource, JitSymbols};
+use pipa_parser::symbolize::Symbolizer;
+
+mod common;
+use common::fixture;
+
+const PID: u32 = 4242;
+
+fn mmap(start: u64, len: u64, filename: &str) -> MmapRecord {
+    MmapRecord {
+        pid: PID,
+        tid: PID,
+        start,
+        len,
+        pgoff: 0,
+        filename: filename.to_string(),
+        build_id: Vec::new(),
+        time: 0,
+    }
+}
+
+#[test]
+fn test_parse_jitdump() {
+    let symbols =
+        JitSymbols::parse_jitdump(&std::fs::read(fixture("jit/jit-4242.dump")).unwrap()).unwrap();
+    let name =
+        |address| symbols.find(address).map(|(symbol, offset)| (symbol.name.as_str(), offset));
+    assert_eq!(name(0x7f10_0000_2010), Some(("Interpreter::invoke", 0x10)));
+    // `compute` moved away from 0x7f1000003000. / `compute` 已从 0x7f1000003000 移走。
+    assert_eq!(name(0x7f10_0000_3000), None);
+    assert_eq!(name(0x7f10_0000_407f), Some(("Lfib;compute(I)I", 0x7f)));
+    assert_eq!(name(0x7f10_0000_5000), Some(("Lfib;main([Ljava/lang/String;)V", 0)));
+    assert_eq!(name(0x7f10_0000_5040), None);
+}
+
+#[test]
+fn test_parse_perf_map() {
+    let text = std::fs::read_to_string(fixture("jit/perf-4242.map")).unwrap();
+    let symbols = JitSymbols::parse_perf_map(&text).unwrap();
+    let (symbol, offset) = symbols.find(0x7f10_0000_0280).unwrap();
+    assert_eq!((symbol.name.as_str(), offset), ("LazyCompile:*fib /app/fib.js:1", 0x80));
+    assert_eq!(symbol.source, JitSource::PerfMap);
+}
+
+#[test]
+fn test_symbolize_jit_frames() {
+    let mut symbolizer = Symbolizer::default();
+    symbolizer.set_jit_dir(fixture("jit"));
+    symbolizer.add_mmap(&mmap(0x7f10_0000_0000, 0x10000, "//anon"));
+    symbolizer.add_mmap(&mmap(
+        0x7f20_0000_0000,
+        0x1000,
+        fixture("jit/jit-4242.dump").to_str().unwrap(),
+    ));
+
+    // From the perf map and from the jitdump file, in place of the
+    // anonymous mapping. / 分别来自 perf map 与 jitdump 文件，取代匿名映射。
+    let symbol = symbolizer.symbolize(PID, 0x7f10_0000_0210);
+    assert_eq!(symbol.to_string(), "LazyCompile:*fib /app/fib.js:1+0x10 (perf-4242.map)");
+    let symbol = symbolizer.symbolize(PID, 0x7f10_0000_4004);
+    assert_eq!(symbol.to_string(), "Lfib;compute(I)I+0x4 (jit-4242.dump)");
+    assert_eq!(symbol.names(0x7f10_0000_4004), ["Lfib;compute(I)I"]);
+
+    // Outside any mapping the recording saw. / 位于记录所见的任何映射之外。
+    let symbol = symbolizer.symbolize(PID, 0x7f10_0001_0000 + 0x10);
+    assert_eq!(symbol.function, None);
+    // A gap in the JIT code falls back to the anonymous mapping.
+    // JIT 代码之间的空隙退回到匿名映射。
+    let symbol = symbolizer.symbolize(PID, 0x7f10_0000_8000);
+    assert_eq!((symbol.function, symbol.dso.as_deref()), (None, Some("//anon")));
+    // Other processes have no JIT files. / 其他进程没有 JIT 文件。
+    assert_eq!(symbolizer.symbolize(1, 0x7f10_0000_0210).function, None);
+    assert!(symbolizer.unreadable().is_empty());
+}
@@ -0,0 +1,155 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Symbolizes addresses in the checked-in binaries of `fixtures/`.
+
+use pipa_collector::perf_record::{BuildIdRecord, MmapRecord, RecordEntry, read_record};
+use pipa_parser::buildid::{self, BuildIdCache};
+use pipa_parser::symbolize::{Binary, Symbolizer};
+use std::path::Path;
+
+mod common;
+use common::fixture;
+
+#[test]
+fn test_inlined_frames_and_demangling() {
+    // `imul` of `pipa::mix`, inlined into `pipa::checksum` at 0x1150.
+    let address = 0x116c;
+    for binary in ["symbolize/inline", "symbolize/inline_zlib"] {
+        let binary = Binary::load(fixture(binary).to_str().unwrap()).unwrap();
+        assert!(binary.has_debug_info());
+        assert_eq!(
+            binary.function(address),
+            Some(("pipa::checksum(unsigned int const*, int)", 0x1c))
+        );
+        let frames: Vec<_> = binary
+            .source_frames(address)
+            .into_iter()
+            .map(|f| (f.function.unwrap(), f.file.unwrap().ends_with("inline.cc"), f.line.unwrap()))
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                ("pipa::mix(unsigned int)".to_string(), true, 14),
+                ("pipa::checksum(unsigned int const*, int)".to_string(), true, 21),
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_symbolize_recorded_samples() {
+    let path = fixture("unwind/stack_debug_frame");
+    let record = read_record(fixture("unwind/stack_debug_frame.data")).unwrap();
+    let mut symbolizer = Symbolizer::default();
+    let mut samples = 0;
+    for entry in &record.entries {
+        match entry {
+            RecordEntry::Mmap(mmap) => {
+                let mut mmap = mmap.clone();
+                mmap.filename = path.to_string_lossy().into_owned();
+                symbolizer.add_mmap(&mmap);
+            }
+            RecordEntry::Sample(sample) => {
+                let symbol = symbolizer.symbolize(sample.pid, sample.ip);
+                assert_eq!(symbol.function.as_deref(), Some("inner"));
+                assert_eq!(symbol.names(sample.ip), ["inner"]);
+                assert!(symbol.srcline().unwrap().contains("stack.c:"), "{:?}", symbol);
+                assert!(symbol.to_string().ends_with("stack_debug_frame)"));
+                samples += 1;
+            }
+            _ => {}
+        }
+    }
+    assert!(samples > 0);
+    assert!(symbolizer.unreadable().is_empty());
+}
+
+/// Where `inline`'s text segment is mapped in the tests below.
+const TEXT: u64 = 0x7f00_0000_0000;
+
+/// Maps the text segment of `path` into pid 1 at [`TEXT`].
+fn map_inline(symbolizer: &mut Symbolizer, path: &Path, build_id: Vec<u8>) {
+    symbolizer.add_mmap(&MmapRecord {
+        pid: 1,
+        tid: 1,
+        start: TEXT + 0x1000,
+        len: 0x1000,
+        pgoff: 0x1000,
+        filename: path.to_string_lossy().into_owned(),
+        build_id,
+        time: 0,
+    });
+}
+
+#[test]
+fn test_separate_debug_info_by_build_id() {
+    let stripped = fixture("symbolize/inline_stripped");
+    let id = buildid::read_build_id(&stripped).unwrap().unwrap();
+    assert!(!Binary::load(&stripped).unwrap().has_debug_info());
+
+    // `inline` is `inline_stripped` before stripping, so it can stand in for
+    // the debug file a distribution installs. / `inline` 即剥离前的 `inline_stripped`，
+    // 可以充当发行版安装的调试文件。
+    let dir = tempfile::tempdir().unwrap();
+    let hex = buildid::hex(&id);
+    std::fs::create_dir_all(dir.path().join(&hex[..2])).unwrap();
+    std::fs::copy(
+        fixture("symbolize/inline"),
+        dir.path().join(&hex[..2]).join(format!("{}.debug", &hex[2..])),
+    )
+    .unwrap();
+
+    let mut symbolizer = Symbolizer::default();
+    symbolizer.set_build_id_cache(
+        BuildIdCache::new(dir.path().join("cache")).with_debug_dirs(vec![dir.path().to_path_buf()]),
+    );
+    map_inline(&mut symbolizer, &stripped, Vec::new());
+    let symbol = symbolizer.symbolize(1, TEXT + 0x116c);
+    assert_eq!(
+        symbol.names(TEXT + 0x116c),
+        ["pipa::mix(unsigned int)", "pipa::checksum(unsigned int const*, int)"]
+    );
+    assert!(symbol.srcline().unwrap().ends_with("inline.cc:14"));
+}
+
+#[test]
+fn test_changed_binary_from_build_id_cache() {
+    let inline = fixture("symbolize/inline");
+    let id = buildid::read_build_id(&inline).unwrap().unwrap();
+    // The recorded file has since been replaced by a different binary.
+    // 记录的文件此后被另一个二进制文件替换。
+    let dir = tempfile::tempdir().unwrap();
+    let app = dir.path().join("app");
+    std::fs::copy(fixture("unwind/stack_debug_frame"), &app).unwrap();
+
+    let mut symbolizer = Symbolizer::default();
+    map_inline(&mut symbolizer, &app, Vec::new());
+    symbolizer.add_build_id(&BuildIdRecord {
+        filename: app.to_string_lossy().into_owned(),
+        build_id: id.clone(),
+    });
+    assert_eq!(symbolizer.symbolize(1, TEXT + 0x116c).function, None);
+    assert_eq!(symbolizer.mismatched(), [app.to_str().unwrap()]);
+    assert!(symbolizer.unreadable().is_empty());
+
+    let cache = BuildIdCache::new(dir.path().join("cache"));
+    assert!(cache.store(&inline, &id).unwrap());
+    let mut symbolizer = Symbolizer::default();
+    symbolizer.set_build_id_cache(cache);
+    map_inline(&mut symbolizer, &app, id);
+    let symbol = symbolizer.symbolize(1, TEXT + 0x116c);
+    assert_eq!(symbol.function.as_deref(), Some("pipa::checksum(unsigned int const*, int)"));
+    assert!(symbolizer.mismatched().is_empty());
+}
@@ -0,0 +1,169 @@
+// Copyright 2025 cagedbird043
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Unwinds stacks captured by `pipa record --call-graph dwarf` against the
+//! checked-in binaries they were recorded from (see `fixtures/unwind/stack.c`).
+
+use object::{Object, ObjectSymbol};
+use pipa_collector::perf_record::{BuildIdRecord, RecordEntry, read_record};
+use pipa_collector::sysroot::SysRoot;
+use pipa_parser::buildid::{self, BuildIdCache};
+use pipa_parser::elf::{self, LoadSegments};
+use pipa_parser::maps;
+use pipa_parser::unwind::Unwinder;
+
+mod common;
+use common::fixture;
+
+/// Unwinds every sample of the recording of `binary` and names each frame
+/// after the function of `binary` it falls in. Unless `announced`, the
+/// binary is mapped as in a process that was running before recording: the
+/// mapping is only in `/proc/<pid>/maps`, from which `pipa record`
+/// synthesizes its record.
+fn unwind_fixture(binary: &str, announced: bool) -> Vec<Vec<String>> {
+    let path = fixture(&format!("unwind/{}", binary));
+    let data = std::fs::read(&path).unwrap();
+    let file = elf::parse(&data).unwrap();
+    let segments = LoadSegments::new(&file);
+
+    let mut record = read_record(fixture(&format!("unwind/{}.data", binary))).unwrap();
+    for entry in &mut record.entries {
+        if let RecordEntry::Mmap(mmap) = entry {
+            // The recording names the binary relative to the fixtures.
+            mmap.filename = path.to_string_lossy().into_owned();
+        }
+    }
+    let proc = tempfile::tempdir().unwrap();
+    if !announced {
+        let (mut pid, mut text) = (0, String::new());
+        record.entries.retain(|entry| {
+            let RecordEntry::Mmap(mmap) = entry else { return true };
+            pid = mmap.pid;
+            text += &format!(
+                "{:x}-{:x} r-xp {:08x} 08:01 1234 {}\n",
+                mmap.start,
+                mmap.start + mmap.len,
+                mmap.pgoff,
+                mmap.filename
+            );
+            false
+        });
+        std::fs::create_dir(proc.path().join(pid.to_string())).unwrap();
+        std::fs::write(proc.path().join(format!("{}/maps", pid)), text).unwrap();
+        let root = SysRoot::default().with_proc(proc.path());
+        let synthesized = maps::synthesize_mmaps(&root, pid).unwrap();
+        record.entries.splice(0..0, synthesized.into_iter().map(RecordEntry::Mmap));
+    }
+
+    let mut unwinder = Unwinder::new(&record.header).unwrap();
+    let mut mapping = None;
+    let mut stacks = Vec::new();
+    for entry in &record.entries {
+        match entry {
+            RecordEntry::Mmap(mmap) => {
+                unwinder.add_mmap(mmap);
+                mapping = Some((mmap.start, mmap.start + mmap.len, mmap.pgoff));
+            }
+            RecordEntry::Sample(sample) => {
+                let (start, end, pgoff) = mapping.unwrap();
+                let bias = segments.load_bias(start, pgoff);
+                let names = unwinder
+                    .unwind_sample(sample)
+                    .into_iter()
+                    .filter(|&frame| start <= frame && frame < end)
+                    .map(|frame| symbol(&file, frame - bias))
+                    .collect();
+                stacks.push(names);
+            }
+            _ => {}
+        }
+    }
+    assert!(unwinder.unreadable().is_empty());
+    stacks
+}
+
+fn symbol(file: &object::File<'_>, address: u64) -> String {
+    file.symbols()
+        .find(|s| s.address() <= address && address < s.address() + s.size())
+        .and_then(|s| s.name().ok())
+        .unwrap_or("?")
+        .to_string()
+}
+
+fn assert_stacks(stacks: Vec<Vec<String>>) {
+    assert!(!stacks.is_empty());
+    for stack in stacks {
+        assert_eq!(stack, ["inner", "middle", "outer", "main"]);
+    }
+}
+
+#[test]
+fn test_unwind_with_eh_frame() {
+    assert_stacks(unwind_fixture("stack_eh_frame", true));
+}
+
+#[test]
+fn test_unwind_with_debug_frame() {
+    assert_stacks(unwind_fixture("stack_debug_frame", true));
+}
+
+#[test]
+fn test_unwind_with_synthesized_mapping() {
+    assert_stacks(unwind_fixture("stack_eh_frame", false));
+}
+
+#[test]
+fn test_unwind_changed_binary_from_build_id_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("stack_eh_frame");
+    std::fs::copy(fixture("unwind/stack_eh_frame"), &binary).unwrap();
+    let build_id = buildid::read_build_id(&binary).unwrap().unwrap();
+    let cache = BuildIdCache::new(dir.path().join("cache"));
+    cache.store(&binary, &build_id).unwrap();
+    // The binary is rebuilt after the recording.
+    std::fs::copy(fixture("unwind/stack_debug_frame"), &binary).unwrap();
+
+    let mut record = read_record(fixture("unwind/stack_eh_frame.data")).unwrap();
+    let filename = binary.to_string_lossy().into_owned();
+    let mut unwind = |cache: Option<BuildIdCache>| {
+        let mut unwinder = Unwinder::new(&record.header).unwrap();
+        unwinder.add_build_id(&BuildIdRecord {
+            filename: filename.clone(),
+            build_id: build_id.clone(),
+        });
+        if let Some(cache) = cache {
+            unwinder.set_build_id_cache(cache);
+        }
+        let mut depths = Vec::new();
+        for entry in &mut record.entries {
+            match entry {
+                RecordEntry::Mmap(mmap) => {
+                    mmap.filename = filename.clone();
+                    unwinder.add_mmap(mmap);
+                }
+                RecordEntry::Sample(sample) => depths.push(unwinder.unwind_sample(sample).len()),
+                _ => {}
+            }
+        }
+        let mismatched: Vec<String> = unwinder.mismatched().into_iter().map(String::from).collect();
+        (depths, mismatched)
+    };
+
+    let (depths, mismatched) = unwind(None);
+    assert!(depths.iter().all(|&depth| depth == 1), "{:?}", depths);
+    assert_eq!(mismatched, [filename.as_str()]);
+    let (depths, mismatched) = unwind(Some(cache));
+    assert!(depths.iter().all(|&depth| depth >= 4), "{:?}", depths);
+    assert!(mismatched.is_empty());
+}